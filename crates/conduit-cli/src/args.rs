@@ -0,0 +1,162 @@
+//! Command-line parsing.
+
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+usage: conduit [--root DIR] <command> [options]
+
+commands:
+  find <pattern>                  search files; exits 1 when nothing matches
+  replace <pattern> <replacement> replace matches and print the diff
+  apply-patch <file>              apply a unified diff ('-' reads stdin)
+
+options:
+  --root DIR         directory to load (default: current directory)
+  -i                 match case-insensitively
+  -w                 match whole words only
+  --glob GLOB        only files matching GLOB (repeatable)
+  --exclude GLOB     skip files matching GLOB (repeatable)
+  -C N               context lines around matches (default 2)
+  --write            write changes back to disk (replace, apply-patch)
+  --json             print results as JSON
+";
+
+/// What to run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Find {
+        pattern: String,
+    },
+    Replace {
+        pattern: String,
+        replacement: String,
+    },
+    ApplyPatch {
+        /// Patch file, or `-` for stdin.
+        file: String,
+    },
+}
+
+/// Parsed command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Args {
+    pub root: PathBuf,
+    pub command: Command,
+    pub case_insensitive: bool,
+    pub whole_word: bool,
+    pub include_globs: Vec<String>,
+    pub exclude_globs: Vec<String>,
+    pub context: usize,
+    pub write: bool,
+    pub json: bool,
+}
+
+impl Args {
+    /// Parse arguments, without the program name.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut root = PathBuf::from(".");
+        let mut case_insensitive = false;
+        let mut whole_word = false;
+        let mut include_globs = Vec::new();
+        let mut exclude_globs = Vec::new();
+        let mut context = 2;
+        let mut write = false;
+        let mut json = false;
+        let mut positional = Vec::new();
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = |flag: &str| args.next().ok_or_else(|| format!("{flag} needs a value"));
+            match arg.as_str() {
+                "--root" => root = PathBuf::from(value("--root")?),
+                "-i" | "--ignore-case" => case_insensitive = true,
+                "-w" | "--word" => whole_word = true,
+                "--glob" => include_globs.push(value("--glob")?),
+                "--exclude" => exclude_globs.push(value("--exclude")?),
+                "-C" | "--context" => {
+                    let n = value("-C")?;
+                    context = n
+                        .parse()
+                        .map_err(|_| format!("invalid context line count: {n}"))?;
+                }
+                "--write" => write = true,
+                "--json" => json = true,
+                "-" => positional.push(arg),
+                flag if flag.starts_with('-') => return Err(format!("unknown option: {flag}")),
+                _ => positional.push(arg),
+            }
+        }
+
+        let mut positional = positional.into_iter();
+        let name = positional.next().ok_or("missing command")?;
+        let mut operand = |what: &str| {
+            positional
+                .next()
+                .ok_or_else(|| format!("{name}: missing {what}"))
+        };
+        let command = match name.as_str() {
+            "find" => Command::Find {
+                pattern: operand("pattern")?,
+            },
+            "replace" => Command::Replace {
+                pattern: operand("pattern")?,
+                replacement: operand("replacement")?,
+            },
+            "apply-patch" => Command::ApplyPatch {
+                file: operand("patch file")?,
+            },
+            other => return Err(format!("unknown command: {other}")),
+        };
+        if let Some(extra) = positional.next() {
+            return Err(format!("unexpected argument: {extra}"));
+        }
+
+        Ok(Self {
+            root,
+            command,
+            case_insensitive,
+            whole_word,
+            include_globs,
+            exclude_globs,
+            context,
+            write,
+            json,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    fn test_parse() {
+        let args = parse(&[
+            "--root", "repo", "replace", "-i", "foo", "bar", "--glob", "*.rs", "--write",
+        ])
+        .unwrap();
+        assert_eq!(args.root, PathBuf::from("repo"));
+        assert_eq!(
+            args.command,
+            Command::Replace {
+                pattern: "foo".into(),
+                replacement: "bar".into()
+            }
+        );
+        assert!(args.case_insensitive && args.write && !args.json);
+        assert_eq!(args.include_globs, ["*.rs"]);
+
+        assert_eq!(
+            parse(&["apply-patch", "-"]).unwrap().command,
+            Command::ApplyPatch { file: "-".into() }
+        );
+        assert!(parse(&["replace", "foo"]).is_err());
+        assert!(parse(&["find", "x", "--bogus"]).is_err());
+        assert!(parse(&["find", "x", "-C"]).is_err());
+        assert!(parse(&[]).is_err());
+    }
+}
@@ -0,0 +1,279 @@
+//! Reading a directory into index entries and writing staged changes back.
+
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+use conduit_core::fs::{FileEntry, PathKey};
+
+/// Directories never loaded.
+const SKIPPED_DIRS: &[&str] = &[".git"];
+
+/// Files read by [`load_dir`], and those it had to leave out.
+#[derive(Debug, Default)]
+pub struct LoadedDir {
+    pub files: Vec<(PathKey, FileEntry)>,
+    /// Paths that were not loaded, with the reason.
+    pub skipped: Vec<(PathBuf, String)>,
+}
+
+/// Every regular file below `root`, keyed by its path relative to `root`.
+///
+/// Symlinks are not followed. Read-only files load as not editable. Files
+/// whose path is not valid UTF-8 or that cannot be read are skipped and
+/// reported; only an unreadable `root` fails the load.
+pub fn load_dir(root: &Path) -> io::Result<LoadedDir> {
+    let mut loaded = LoadedDir::default();
+    let mut pending = vec![root.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let items = match fs::read_dir(&dir) {
+            Ok(items) => items,
+            Err(e) if dir != root => {
+                loaded.skipped.push((dir, e.to_string()));
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        for item in items {
+            let item = match item {
+                Ok(item) => item,
+                Err(e) => {
+                    loaded.skipped.push((dir.clone(), e.to_string()));
+                    continue;
+                }
+            };
+            let path = item.path();
+            match load_item(root, &item) {
+                Ok(Loaded::Dir) => {
+                    if !SKIPPED_DIRS.iter().any(|d| item.file_name() == *d) {
+                        pending.push(path);
+                    }
+                }
+                Ok(Loaded::File(key, entry)) => loaded.files.push((key, entry)),
+                Ok(Loaded::Other) => {}
+                Err(reason) => loaded.skipped.push((path, reason)),
+            }
+        }
+    }
+    Ok(loaded)
+}
+
+enum Loaded {
+    Dir,
+    File(PathKey, FileEntry),
+    /// Symlinks, sockets and the like.
+    Other,
+}
+
+fn load_item(root: &Path, item: &fs::DirEntry) -> Result<Loaded, String> {
+    let file_type = item.file_type().map_err(|e| e.to_string())?;
+    if file_type.is_dir() {
+        return Ok(Loaded::Dir);
+    }
+    if !file_type.is_file() {
+        return Ok(Loaded::Other);
+    }
+
+    let path = item.path();
+    let relative = path.strip_prefix(root).unwrap_or(&path);
+    let relative = relative
+        .to_str()
+        .ok_or_else(|| "path is not valid UTF-8".to_string())?;
+    let key = conduit_wasm::create_path_key(relative).map_err(|e| e.to_string())?;
+    let metadata = item.metadata().map_err(|e| e.to_string())?;
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs() as i64);
+    let bytes = fs::read(&path).map_err(|e| e.to_string())?;
+    let entry = FileEntry::from_bytes(
+        FileEntry::get_extension(key.as_str()),
+        mtime,
+        Arc::from(bytes),
+        !metadata.permissions().readonly(),
+    );
+    Ok(Loaded::File(key, entry))
+}
+
+/// `path` joined to `root`, or an error if it could leave `root`: absolute
+/// paths, any `..` or `.` component, and symlinks along the way are
+/// refused, since writes would follow them.
+fn resolve(root: &Path, path: &PathKey) -> io::Result<PathBuf> {
+    let relative = Path::new(path.as_str());
+    let inside = relative.components().next().is_some()
+        && relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)));
+    if !inside {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is outside {}", path.as_str(), root.display()),
+        ));
+    }
+    let mut target = root.to_path_buf();
+    for component in relative.components() {
+        target.push(component);
+        match fs::symlink_metadata(&target) {
+            Ok(meta) if meta.file_type().is_symlink() => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "{} passes through symlink {}",
+                        path.as_str(),
+                        target.display()
+                    ),
+                ));
+            }
+            Ok(_) => {}
+            // The rest does not exist yet, so it cannot be a link.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => break,
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(root.join(relative))
+}
+
+/// Write `modifications` under `root` and remove `deletions`, returning how
+/// many paths changed on disk.
+///
+/// Every path is checked before anything is touched; one that would land
+/// outside `root` fails the whole write.
+pub fn write_changes(
+    root: &Path,
+    modifications: &[(PathKey, Vec<u8>)],
+    deletions: &[PathKey],
+) -> io::Result<usize> {
+    let deletions = deletions
+        .iter()
+        .map(|path| resolve(root, path))
+        .collect::<io::Result<Vec<_>>>()?;
+    let modifications = modifications
+        .iter()
+        .map(|(path, content)| Ok((resolve(root, path)?, content)))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    for target in &deletions {
+        match fs::remove_file(target) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    for (target, content) in &modifications {
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(target, content)?;
+    }
+    Ok(deletions.len() + modifications.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_and_write_round_trip() {
+        let root = std::env::temp_dir().join(format!("conduit-cli-{}", std::process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join("old.txt"), "gone").unwrap();
+        fs::write(root.join(".git/HEAD"), "ref").unwrap();
+
+        let mut files = load_dir(&root).unwrap().files;
+        files.sort_by(|a, b| a.0.cmp(&b.0));
+        let paths: Vec<_> = files.iter().map(|(k, _)| k.as_str()).collect();
+        assert_eq!(paths, ["old.txt", "src/main.rs"]);
+        assert_eq!(files[1].1.bytes(), Some(&b"fn main() {}\n"[..]));
+
+        let new = files[1].0.clone();
+        let changed = write_changes(
+            &root,
+            &[(new, b"fn main() { run() }\n".to_vec())],
+            &[files[0].0.clone()],
+        )
+        .unwrap();
+        assert_eq!(changed, 2);
+        assert!(!root.join("old.txt").exists());
+        assert_eq!(
+            fs::read_to_string(root.join("src/main.rs")).unwrap(),
+            "fn main() { run() }\n"
+        );
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_write_refuses_paths_outside_root() {
+        let root = std::env::temp_dir().join(format!("conduit-cli-escape-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        let key = |p: &str| PathKey::from_arc(Arc::from(p));
+
+        for path in ["/etc/passwd", "../outside.txt", "a/../../outside.txt", ""] {
+            let err =
+                write_changes(&root, &[(key("ok.txt"), b"x".to_vec())], &[key(path)]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{path}");
+            let err = write_changes(&root, &[(key(path), b"x".to_vec())], &[]).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{path}");
+        }
+        // Nothing was written before the bad path was found.
+        assert!(!root.join("ok.txt").exists());
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_refuses_symlinked_directories() {
+        let base = std::env::temp_dir().join(format!("conduit-cli-link-{}", std::process::id()));
+        let (root, outside) = (base.join("root"), base.join("outside"));
+        fs::create_dir_all(&root).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, root.join("link")).unwrap();
+        let key = PathKey::from_arc(Arc::from("link/new/escape.txt"));
+
+        let err = write_changes(&root, &[(key.clone(), b"x".to_vec())], &[]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let err = write_changes(&root, &[], &[key]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert!(!outside.join("new").exists());
+        fs::remove_dir_all(&base).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_load_skips_unreadable_and_non_utf8_files() {
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = std::env::temp_dir().join(format!("conduit-cli-skip-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("ok.txt"), "ok").unwrap();
+        fs::write(root.join(std::ffi::OsStr::from_bytes(b"bad\xff.txt")), "x").unwrap();
+        let locked = root.join("locked.txt");
+        fs::write(&locked, "secret").unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+        // Root can read anything, so only expect the lock to bite otherwise.
+        let locked_skipped = fs::read(&locked).is_err();
+
+        let loaded = load_dir(&root).unwrap();
+        let paths: Vec<_> = loaded.files.iter().map(|(k, _)| k.as_str()).collect();
+        let mut skipped: Vec<_> = loaded
+            .skipped
+            .iter()
+            .map(|(p, _)| p.file_name().unwrap().as_bytes().to_vec())
+            .collect();
+        skipped.sort();
+        let mut expected = vec![b"bad\xff.txt".to_vec()];
+        if locked_skipped {
+            expected.push(b"locked.txt".to_vec());
+            assert_eq!(paths, ["ok.txt"]);
+        }
+        assert_eq!(skipped, expected);
+
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o644)).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,176 @@
+//! `conduit`: the Conduit search and editing engine on a local directory.
+//!
+//! Loads the directory into the same index the browser build uses, runs one
+//! command through the shared orchestrator, and with `--write` writes the
+//! staged result back to disk.
+
+mod args;
+mod disk;
+
+use std::io::Read;
+use std::process::ExitCode;
+
+use args::{Args, Command, USAGE};
+use conduit_core::tools::{AbortFlag, PreviewHunk, RegexEngineOpts};
+use conduit_core::{DiffTool, EditRequest, FindRequest, IndexManager, PatchTool, SearchSpace};
+use conduit_wasm::Orchestrator;
+
+fn main() -> ExitCode {
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("conduit: {e}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    match run(&args) {
+        Ok(found) if found => ExitCode::SUCCESS,
+        Ok(_) => ExitCode::from(1),
+        Err(e) => {
+            eprintln!("conduit: {e}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Run the command; returns whether it found or changed anything.
+fn run(args: &Args) -> Result<bool, String> {
+    let loaded = disk::load_dir(&args.root)
+        .map_err(|e| format!("failed to load {}: {e}", args.root.display()))?;
+    for (path, reason) in &loaded.skipped {
+        eprintln!("conduit: skipped {}: {reason}", path.display());
+    }
+    let manager = conduit_wasm::get_index_manager();
+    manager
+        .load_files(loaded.files)
+        .map_err(|e| e.to_string())?;
+    manager.begin_staging().map_err(|e| e.to_string())?;
+
+    let mut orchestrator = Orchestrator::new();
+    let abort = AbortFlag::new();
+    let engine_opts = RegexEngineOpts {
+        case_insensitive: args.case_insensitive,
+        word: args.whole_word,
+        ..RegexEngineOpts::default()
+    };
+    let globs = |globs: &[String]| (!globs.is_empty()).then(|| globs.to_vec());
+
+    match &args.command {
+        Command::Find { pattern } => {
+            let req = FindRequest {
+                find: pattern.clone(),
+                include_globs: globs(&args.include_globs),
+                exclude_globs: globs(&args.exclude_globs),
+                delta: args.context,
+                engine_opts,
+                where_: SearchSpace::Active,
+                ..FindRequest::default()
+            };
+            let response = orchestrator
+                .handle_find(req, &abort)
+                .map_err(|e| e.to_string())?;
+            if args.json {
+                print_json(&response)?;
+            } else {
+                for hunk in &response.results {
+                    print_hunk(hunk);
+                }
+            }
+            Ok(!response.results.is_empty())
+        }
+        Command::Replace {
+            pattern,
+            replacement,
+        } => {
+            let req = EditRequest {
+                find: pattern.clone(),
+                replace: replacement.clone(),
+                include_globs: globs(&args.include_globs),
+                exclude_globs: globs(&args.exclude_globs),
+                delta: args.context,
+                engine_opts,
+                ..EditRequest::default()
+            };
+            let response = orchestrator
+                .handle_edit(req, &abort)
+                .map_err(|e| e.to_string())?;
+            if args.json {
+                print_json(&response)?;
+            } else {
+                print!("{}", all_diffs(&orchestrator)?);
+            }
+            finish(args, manager)?;
+            Ok(!response.items.is_empty())
+        }
+        Command::ApplyPatch { file } => {
+            let patch = if file == "-" {
+                let mut patch = String::new();
+                std::io::stdin()
+                    .read_to_string(&mut patch)
+                    .map_err(|e| format!("failed to read stdin: {e}"))?;
+                patch
+            } else {
+                std::fs::read_to_string(file).map_err(|e| format!("failed to read {file}: {e}"))?
+            };
+            let report = orchestrator
+                .run_apply_patch(&patch)
+                .map_err(|e| e.to_string())?;
+            if !report.applied {
+                return Err(format!(
+                    "patch does not apply:\n{}",
+                    serde_json::to_string_pretty(&report.files).map_err(|e| e.to_string())?
+                ));
+            }
+            if args.json {
+                print_json(&report)?;
+            } else {
+                print!("{}", all_diffs(&orchestrator)?);
+            }
+            finish(args, manager)?;
+            Ok(true)
+        }
+    }
+}
+
+fn all_diffs(orchestrator: &Orchestrator) -> Result<String, String> {
+    orchestrator
+        .get_all_unified_diffs()
+        .map_err(|e| e.to_string())
+}
+
+/// With `--write`, write the staged changes to disk and commit them.
+fn finish(args: &Args, manager: &IndexManager) -> Result<(), String> {
+    if !args.write {
+        return Ok(());
+    }
+    let modifications = manager
+        .get_staged_modifications()
+        .map_err(|e| e.to_string())?;
+    let deletions = manager.get_staged_deletions().map_err(|e| e.to_string())?;
+    let written = disk::write_changes(&args.root, &modifications, &deletions)
+        .map_err(|e| format!("failed to write changes: {e}"))?;
+    manager.promote_staged().map_err(|e| e.to_string())?;
+    eprintln!("conduit: wrote {written} file(s)");
+    Ok(())
+}
+
+fn print_json(value: &impl serde::Serialize) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    println!("{json}");
+    Ok(())
+}
+
+/// Print `hunk` grep-style: `path:line:` for matched lines, `path-line-`
+/// for context.
+fn print_hunk(hunk: &PreviewHunk) {
+    for (i, line) in hunk.excerpt.lines().enumerate() {
+        let number = hunk.preview_start_line + i;
+        let matched = hunk
+            .matched_line_ranges
+            .iter()
+            .any(|&(start, end)| (start..=end).contains(&number));
+        let sep = if matched { ':' } else { '-' };
+        println!("{}{sep}{number}{sep}{line}", hunk.path.as_str());
+    }
+    println!("--");
+}
@@ -0,0 +1,51 @@
+//! Full rebuild vs incremental splice of `LineIndex` after a small edit.
+
+use conduit_core::tools::{changed_span, LineIndex};
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+fn make_content(lines: usize) -> Vec<u8> {
+    (0..lines)
+        .map(|i| format!("    let value_{i} = compute(value_{i}, {i}); // some trailing comment\n"))
+        .collect::<String>()
+        .into_bytes()
+}
+
+fn edit_middle(content: &[u8]) -> Vec<u8> {
+    let mid = content.len() / 2;
+    let at = mid + content[mid..].iter().position(|&b| b == b'\n').unwrap() + 1;
+    let mut next = Vec::with_capacity(content.len() + 64);
+    next.extend_from_slice(&content[..at]);
+    next.extend_from_slice(b"    // inserted line one\n    // inserted line two\n");
+    next.extend_from_slice(&content[at..]);
+    next
+}
+
+fn bench_line_index(c: &mut Criterion) {
+    let mut group = c.benchmark_group("line_index_after_edit");
+    for lines in [10_000usize, 100_000] {
+        let old = make_content(lines);
+        let new = edit_middle(&old);
+        let cached = LineIndex::build(&old);
+
+        group.bench_with_input(BenchmarkId::new("rebuild", lines), &new, |b, new| {
+            b.iter(|| LineIndex::build(black_box(new)))
+        });
+        group.bench_with_input(BenchmarkId::new("incremental", lines), &new, |b, new| {
+            b.iter(|| cached.updated(black_box(&old), black_box(new)))
+        });
+
+        // Splice with the edited span already known (no content comparison).
+        let (start, old_end, new_end) = changed_span(&old, &new);
+        group.bench_with_input(BenchmarkId::new("known_splice", lines), &new, |b, new| {
+            b.iter(|| {
+                let mut index = cached.clone();
+                index.apply_splice(black_box(new), start, old_end, new_end);
+                index
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_line_index);
+criterion_main!(benches);
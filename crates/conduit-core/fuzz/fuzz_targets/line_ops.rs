@@ -0,0 +1,67 @@
+//! Arbitrary operation lists through `apply_line_operations`.
+//!
+//! Invariants: never panics; reported removals never exceed the lines
+//! available; the output line count matches the reported deltas (a
+//! trailing empty line may be absorbed by the final join); a trailing
+//! newline is preserved when present.
+#![no_main]
+
+use arbitrary::Arbitrary;
+use conduit_core::{apply_line_operations, LineOperation};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Debug, Arbitrary)]
+enum Op {
+    Replace { start: u16, end: u16, content: String },
+    Delete { start: u16, end: u16 },
+    InsertBefore { line: u16, content: String },
+    InsertAfter { line: u16, content: String },
+}
+
+impl From<Op> for LineOperation {
+    fn from(op: Op) -> Self {
+        match op {
+            Op::Replace {
+                start,
+                end,
+                content,
+            } => LineOperation::ReplaceRange {
+                start: start.into(),
+                end: end.into(),
+                content,
+            },
+            Op::Delete { start, end } => LineOperation::DeleteRange {
+                start: start.into(),
+                end: end.into(),
+            },
+            Op::InsertBefore { line, content } => LineOperation::InsertBefore {
+                line: line.into(),
+                content,
+            },
+            Op::InsertAfter { line, content } => LineOperation::InsertAfter {
+                line: line.into(),
+                content,
+            },
+        }
+    }
+}
+
+fuzz_target!(|input: (String, Vec<Op>)| {
+    let (content, ops) = input;
+    let original_lines = content.lines().count();
+    let ops: Vec<LineOperation> = ops.into_iter().map(Into::into).collect();
+
+    let (output, added, removed) = apply_line_operations(&content, ops);
+
+    assert!(removed <= original_lines + added);
+    let expected = original_lines + added - removed;
+    let actual = output.lines().count();
+    assert!(
+        actual == expected || actual + 1 == expected,
+        "line math: expected {expected}, got {actual}"
+    );
+
+    if content.ends_with('\n') && !output.is_empty() {
+        assert!(output.ends_with('\n'));
+    }
+});
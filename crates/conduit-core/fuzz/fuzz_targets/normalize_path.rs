@@ -0,0 +1,27 @@
+//! Arbitrary strings through `normalize_path` and `PathKey`.
+//!
+//! Invariants: never panics; accepted paths are non-empty, have no trailing
+//! slash (except root), no `..` components, and normalization is idempotent.
+#![no_main]
+
+use conduit_core::fs::{normalize_path, PathKey};
+use libfuzzer_sys::fuzz_target;
+use std::sync::Arc;
+
+fuzz_target!(|input: &str| {
+    let Ok(normalized) = normalize_path(input) else {
+        return;
+    };
+
+    assert!(!normalized.is_empty());
+    assert!(!normalized.chars().any(char::is_control));
+    assert!(normalized == "/" || !normalized.ends_with('/'));
+    assert!(!normalized.split('/').any(|c| c == ".."));
+
+    let again = normalize_path(&normalized).expect("normalized path must stay valid");
+    assert_eq!(again, normalized, "normalization is not idempotent");
+
+    let key = PathKey::from_arc(Arc::from(normalized.as_str()));
+    assert_eq!(key.as_str(), normalized);
+    assert!(key.starts_with(&key));
+});
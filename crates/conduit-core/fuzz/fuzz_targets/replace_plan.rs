@@ -0,0 +1,64 @@
+//! Arbitrary patterns, templates and haystacks through `plan_in_bytes`/`apply_plan`.
+//!
+//! Invariants: never panics; ops are in bounds, start-sorted and
+//! non-overlapping; output length equals the input length adjusted by
+//! every op's replacement delta.
+#![no_main]
+
+use arbitrary::Arbitrary;
+use conduit_core::tools::replace::{apply_plan, plan_in_bytes};
+use conduit_core::{AbortFlag, RegexEngineOpts, RegexMatcher};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Debug, Arbitrary)]
+struct Input<'a> {
+    pattern: &'a str,
+    replacement: &'a str,
+    haystack: &'a [u8],
+    case_insensitive: bool,
+    word: bool,
+    crlf: bool,
+    multiline: bool,
+    dot_all: bool,
+}
+
+fuzz_target!(|input: Input<'_>| {
+    let opts = RegexEngineOpts {
+        case_insensitive: input.case_insensitive,
+        unicode: true,
+        word: input.word,
+        crlf: input.crlf,
+        multiline: input.multiline,
+        dot_all: input.dot_all,
+    };
+    let Ok(matcher) = RegexMatcher::compile(input.pattern, &opts) else {
+        return;
+    };
+
+    let abort = AbortFlag::new();
+    let Ok(plan) = plan_in_bytes(
+        input.haystack,
+        &matcher,
+        input.replacement,
+        input.multiline,
+        &abort,
+    ) else {
+        return;
+    };
+
+    let mut expected_len = input.haystack.len() as isize;
+    let mut prev_end = 0usize;
+    for op in &plan.ops {
+        assert!(op.span.start <= op.span.end);
+        assert!(op.span.end <= input.haystack.len());
+        assert!(op.span.start >= prev_end, "overlapping or unsorted ops");
+        prev_end = op.span.end;
+        expected_len += op.replacement.len() as isize - op.span.len() as isize;
+    }
+
+    let out = apply_plan(input.haystack, &plan);
+    assert_eq!(out.len() as isize, expected_len);
+    if plan.is_empty() {
+        assert_eq!(out, input.haystack);
+    }
+});
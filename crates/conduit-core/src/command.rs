@@ -0,0 +1,539 @@
+//! One structured entry point for every tool.
+//!
+//! A [`Command`] is a request tagged with the tool it is for, e.g.
+//! `{"tool":"find","find":"todo"}`; [`execute_command`] parses one from JSON,
+//! runs it through any type implementing the tool traits, and returns the
+//! tool's response as JSON, normalizing every path first the way the index
+//! stores them. [`run_batch`] runs a list of commands as one staging step.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::{Error, Result};
+use crate::fs::{normalize_path, IndexManager};
+use crate::{
+    AbortFlag, AnchoredEditRequest, AnchoredEditTool, AppendRequest, AppendTool,
+    ApplyScaffoldRequest, BatchCopyRequest, BatchMoveRequest, CreateRequest, CreateTool,
+    DeleteDirRequest, DeleteLinesRequest, DeleteLinesTool, DeleteRequest, DeleteTool, DiffTool,
+    EditRequest, EditTool, FindRequest, FindTool, InsertLinesRequest, InsertLinesTool,
+    MergeFileRequest, MergeTool, MoveDirRequest, MoveFilesTool, MoveTreeRequest, OperationFailure,
+    PatchTool, PathKey, ReadTool, ReplaceLinesRequest, ReplaceLinesTool, ScaffoldTool, SearchSpace,
+    ValidateLinesRequest, ValidateLinesTool,
+};
+
+/// Every tool trait a [`Command`] can be dispatched to.
+pub trait CommandTools:
+    FindTool
+    + EditTool
+    + ReadTool
+    + CreateTool
+    + DeleteTool
+    + ReplaceLinesTool
+    + AnchoredEditTool
+    + ValidateLinesTool
+    + DeleteLinesTool
+    + InsertLinesTool
+    + AppendTool
+    + MoveFilesTool
+    + PatchTool
+    + ScaffoldTool
+    + DiffTool
+    + MergeTool
+{
+}
+
+impl<T> CommandTools for T where
+    T: FindTool
+        + EditTool
+        + ReadTool
+        + CreateTool
+        + DeleteTool
+        + ReplaceLinesTool
+        + AnchoredEditTool
+        + ValidateLinesTool
+        + DeleteLinesTool
+        + InsertLinesTool
+        + AppendTool
+        + MoveFilesTool
+        + PatchTool
+        + ScaffoldTool
+        + DiffTool
+        + MergeTool
+{
+}
+
+fn staged() -> SearchSpace {
+    SearchSpace::Staged
+}
+
+/// A tool request, tagged by the tool's name in `tool`.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "tool", rename_all = "snake_case")]
+pub enum Command {
+    Find(FindRequest),
+    Edit(EditRequest),
+    Read {
+        path: PathKey,
+        /// 1-based, inclusive.
+        start_line: usize,
+        end_line: usize,
+        #[serde(default = "staged", rename = "where")]
+        where_: SearchSpace,
+    },
+    Create(CreateRequest),
+    Delete(DeleteRequest),
+    DeleteDir(DeleteDirRequest),
+    ReplaceLines(ReplaceLinesRequest),
+    AnchoredEdit(AnchoredEditRequest),
+    ValidateLines(ValidateLinesRequest),
+    DeleteLines(DeleteLinesRequest),
+    InsertLines(InsertLinesRequest),
+    Append(AppendRequest),
+    CopyFiles(BatchCopyRequest),
+    MoveFiles(BatchMoveRequest),
+    MoveTree(MoveTreeRequest),
+    MoveDir(MoveDirRequest),
+    CopyDir(MoveDirRequest),
+    ApplyPatch {
+        patch: String,
+    },
+    ApplyScaffold(ApplyScaffoldRequest),
+    MergeFile(MergeFileRequest),
+    /// Summary of every staged change.
+    ModifiedFiles,
+    FileDiff {
+        path: PathKey,
+        #[serde(default)]
+        context_lines: usize,
+    },
+    UnifiedDiff {
+        path: PathKey,
+    },
+    AllUnifiedDiffs,
+}
+
+impl Command {
+    /// Parse a command from JSON.
+    pub fn parse(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| Error::InvalidCommand(e.to_string()))
+    }
+
+    /// Replace every file path in the command with `key` of it, the way
+    /// the index stores paths. Fails with the first invalid path's error.
+    pub fn normalize_paths(&mut self, key: impl Fn(&str) -> Result<PathKey>) -> Result<()> {
+        let fix = |path: &mut PathKey| -> Result<()> {
+            *path = key(path.as_str())?;
+            Ok(())
+        };
+        match self {
+            Command::Find(req) => req.paths.iter_mut().flatten().try_for_each(fix),
+            Command::Read { path, .. }
+            | Command::FileDiff { path, .. }
+            | Command::UnifiedDiff { path }
+            | Command::Create(CreateRequest { path, .. })
+            | Command::Delete(DeleteRequest { path, .. })
+            | Command::ReplaceLines(ReplaceLinesRequest { path, .. })
+            | Command::AnchoredEdit(AnchoredEditRequest { path, .. })
+            | Command::ValidateLines(ValidateLinesRequest { path, .. })
+            | Command::DeleteLines(DeleteLinesRequest { path, .. })
+            | Command::InsertLines(InsertLinesRequest { path, .. })
+            | Command::Append(AppendRequest { path, .. })
+            | Command::MergeFile(MergeFileRequest { path, .. }) => fix(path),
+            Command::CopyFiles(BatchCopyRequest { operations, .. })
+            | Command::MoveFiles(BatchMoveRequest { operations, .. }) => {
+                operations.iter_mut().try_for_each(|op| {
+                    fix(&mut op.src)?;
+                    fix(&mut op.dst)
+                })
+            }
+            Command::Edit(_)
+            | Command::DeleteDir(_)
+            | Command::MoveTree(_)
+            | Command::MoveDir(_)
+            | Command::CopyDir(_)
+            | Command::ApplyPatch { .. }
+            | Command::ApplyScaffold(_)
+            | Command::ModifiedFiles
+            | Command::AllUnifiedDiffs => Ok(()),
+        }
+    }
+
+    /// The tool name, as used in the serialized `tool` tag.
+    pub fn tool(&self) -> &'static str {
+        match self {
+            Command::Find(_) => "find",
+            Command::Edit(_) => "edit",
+            Command::Read { .. } => "read",
+            Command::Create(_) => "create",
+            Command::Delete(_) => "delete",
+            Command::DeleteDir(_) => "delete_dir",
+            Command::ReplaceLines(_) => "replace_lines",
+            Command::AnchoredEdit(_) => "anchored_edit",
+            Command::ValidateLines(_) => "validate_lines",
+            Command::DeleteLines(_) => "delete_lines",
+            Command::InsertLines(_) => "insert_lines",
+            Command::Append(_) => "append",
+            Command::CopyFiles(_) => "copy_files",
+            Command::MoveFiles(_) => "move_files",
+            Command::MoveTree(_) => "move_tree",
+            Command::MoveDir(_) => "move_dir",
+            Command::CopyDir(_) => "copy_dir",
+            Command::ApplyPatch { .. } => "apply_patch",
+            Command::ApplyScaffold(_) => "apply_scaffold",
+            Command::MergeFile(_) => "merge_file",
+            Command::ModifiedFiles => "modified_files",
+            Command::FileDiff { .. } => "file_diff",
+            Command::UnifiedDiff { .. } => "unified_diff",
+            Command::AllUnifiedDiffs => "all_unified_diffs",
+        }
+    }
+
+    /// Run the command through `tools`, returning the tool's response.
+    ///
+    /// `abort` applies to finds, edits and the modified files summary.
+    pub fn execute<T: CommandTools>(self, tools: &mut T, abort: &AbortFlag) -> Result<Value> {
+        match self {
+            Command::Find(req) => to_json(tools.run_find(req, abort)?),
+            Command::Edit(req) => to_json(tools.run_edit(req, abort)?),
+            Command::Read {
+                path,
+                start_line,
+                end_line,
+                where_,
+            } => to_json(tools.run_read(&path, start_line, end_line, where_)?),
+            Command::Create(req) => to_json(tools.run_create(req)?),
+            Command::Delete(req) => to_json(tools.run_delete(req)?),
+            Command::DeleteDir(req) => to_json(tools.run_delete_dir(req)?),
+            Command::ReplaceLines(req) => to_json(tools.run_replace_lines(req)?),
+            Command::AnchoredEdit(req) => to_json(tools.run_anchored_edit(req)?),
+            Command::ValidateLines(req) => to_json(tools.run_validate_lines(req)?),
+            Command::DeleteLines(req) => to_json(tools.run_delete_lines(req)?),
+            Command::InsertLines(req) => to_json(tools.run_insert_lines(req)?),
+            Command::Append(req) => to_json(tools.run_append(req)?),
+            Command::CopyFiles(req) => to_json(tools.run_copy_files(req)?),
+            Command::MoveFiles(req) => to_json(tools.run_move_files(req)?),
+            Command::MoveTree(req) => to_json(tools.run_move_tree(req)?),
+            Command::MoveDir(req) => to_json(tools.run_move_dir(req)?),
+            Command::CopyDir(req) => to_json(tools.run_copy_dir(req)?),
+            Command::ApplyPatch { patch } => to_json(tools.run_apply_patch(&patch)?),
+            Command::ApplyScaffold(req) => to_json(tools.run_apply_scaffold(req)?),
+            Command::MergeFile(req) => to_json(tools.run_merge_file(req)?),
+            Command::ModifiedFiles => to_json(tools.get_modified_files_summary(abort)?),
+            Command::FileDiff {
+                path,
+                context_lines,
+            } => to_json(tools.get_file_diff(&path, context_lines)?),
+            Command::UnifiedDiff { path } => to_json(tools.get_unified_diff(&path)?),
+            Command::AllUnifiedDiffs => to_json(tools.get_all_unified_diffs()?),
+        }
+    }
+}
+
+fn to_json(response: impl Serialize) -> Result<Value> {
+    serde_json::to_value(response).map_err(|e| Error::InvalidFormat(e.to_string()))
+}
+
+/// Parse `json` as a [`Command`], run it through `tools` and return the
+/// response serialized as JSON.
+pub fn execute_command<T: CommandTools>(
+    tools: &mut T,
+    json: &str,
+    abort: &AbortFlag,
+) -> Result<String> {
+    let mut command = Command::parse(json)?;
+    command.normalize_paths(normalized_key)?;
+    Ok(command.execute(tools, abort)?.to_string())
+}
+
+/// `path` normalized into a key, without interning.
+fn normalized_key(path: &str) -> Result<PathKey> {
+    normalize_path(path).map(|p| PathKey::from_arc(p.into()))
+}
+
+/// What [`run_batch`] does when an operation fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OnError {
+    /// Skip the remaining operations, keeping the ones already applied.
+    #[default]
+    Stop,
+    /// Run the remaining operations anyway.
+    Continue,
+    /// Skip the remaining operations and undo the ones already applied.
+    Rollback,
+}
+
+/// A list of commands run in order as one staging step.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BatchRequest {
+    pub operations: Vec<Command>,
+    #[serde(default)]
+    pub on_error: OnError,
+}
+
+impl BatchRequest {
+    /// [`Command::normalize_paths`] for every operation, so a bad path
+    /// fails the batch before any operation runs.
+    pub fn normalize_paths(&mut self, key: impl Fn(&str) -> Result<PathKey>) -> Result<()> {
+        self.operations
+            .iter_mut()
+            .try_for_each(|command| command.normalize_paths(&key))
+    }
+}
+
+/// Outcome of one operation in a batch.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct BatchItem {
+    pub tool: String,
+    /// The tool's response, if it succeeded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<OperationFailure>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchResponse {
+    /// One item per operation run, in request order.
+    pub results: Vec<BatchItem>,
+    /// Operations not run because an earlier one failed.
+    pub skipped: usize,
+    /// Whether the applied operations were undone by [`OnError::Rollback`].
+    pub rolled_back: bool,
+}
+
+/// Run every operation in `req` through `tools`, in order.
+///
+/// Paths are used as given; normalize them first with
+/// [`BatchRequest::normalize_paths`].
+///
+/// Failures are reported per operation and handled by `req.on_error`; an
+/// abort always stops the batch. Changes the batch leaves in staging are
+/// one undo step.
+pub fn run_batch<T: CommandTools>(
+    tools: &mut T,
+    manager: &IndexManager,
+    req: BatchRequest,
+    abort: &AbortFlag,
+) -> Result<BatchResponse> {
+    run_each(manager, req, |command| command.execute(tools, abort))
+}
+
+fn run_each(
+    manager: &IndexManager,
+    req: BatchRequest,
+    mut run: impl FnMut(Command) -> Result<Value>,
+) -> Result<BatchResponse> {
+    // Fail up front rather than reporting every operation as failed.
+    manager.staged_index()?;
+
+    let BatchRequest {
+        operations,
+        on_error,
+    } = req;
+    let total = operations.len();
+    let mut results = Vec::with_capacity(total);
+    let mut rolled_back = false;
+    let label = format!("batch of {total} operations");
+    let outcome = manager.with_undo_step(&label, || {
+        for command in operations {
+            let tool = command.tool().to_string();
+            match run(command) {
+                Ok(response) => results.push(BatchItem {
+                    tool,
+                    response: Some(response),
+                    error: None,
+                }),
+                Err(e) => {
+                    results.push(BatchItem {
+                        tool,
+                        response: None,
+                        error: Some(OperationFailure {
+                            code: e.code().to_string(),
+                            message: e.to_string(),
+                        }),
+                    });
+                    if on_error == OnError::Rollback {
+                        rolled_back = true;
+                        return Err(e);
+                    }
+                    if on_error == OnError::Stop || matches!(e, Error::Aborted) {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(())
+    });
+    match outcome {
+        Err(e) if !rolled_back => Err(e),
+        _ => Ok(BatchResponse {
+            skipped: total - results.len(),
+            results,
+            rolled_back,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_by_tool_tag() {
+        let cmd = Command::parse(r#"{"tool":"find","find":"todo","delta":0}"#).unwrap();
+        assert_eq!(cmd.tool(), "find");
+        match cmd {
+            Command::Find(req) => {
+                assert_eq!(req.find, "todo");
+                assert_eq!(req.delta, 0);
+                assert_eq!(req.where_, SearchSpace::Staged);
+            }
+            other => panic!("unexpected command: {other:?}"),
+        }
+
+        let cmd =
+            Command::parse(r#"{"tool":"read","path":"a.rs","start_line":1,"end_line":5}"#).unwrap();
+        assert!(matches!(
+            cmd,
+            Command::Read {
+                where_: SearchSpace::Staged,
+                end_line: 5,
+                ..
+            }
+        ));
+        let cmd = Command::parse(r#"{"tool":"append","path":"a.rs","content":"x"}"#).unwrap();
+        assert!(matches!(
+            cmd,
+            Command::Append(AppendRequest {
+                position: crate::AppendPosition::End,
+                must_exist: false,
+                ..
+            })
+        ));
+        let cmd = Command::parse(r#"{"tool":"all_unified_diffs"}"#).unwrap();
+        assert_eq!(cmd.tool(), "all_unified_diffs");
+
+        // The serialized tag round-trips.
+        let json = serde_json::to_string(&Command::UnifiedDiff {
+            path: PathKey::from_arc("a.rs".into()),
+        })
+        .unwrap();
+        assert_eq!(json, r#"{"tool":"unified_diff","path":"a.rs"}"#);
+    }
+
+    #[test]
+    fn test_normalize_paths() {
+        let mut cmd = Command::parse(
+            r#"{"tool":"move_files","operations":[{"src":"./a//b.rs","dst":"c/./d.rs"}]}"#,
+        )
+        .unwrap();
+        cmd.normalize_paths(normalized_key).unwrap();
+        let Command::MoveFiles(req) = cmd else {
+            panic!("unexpected command: {cmd:?}");
+        };
+        assert_eq!(req.operations[0].src.as_str(), "a/b.rs");
+        assert_eq!(req.operations[0].dst.as_str(), "c/d.rs");
+
+        let mut cmd = Command::parse(r#"{"tool":"delete","path":"a/.."}"#).unwrap();
+        let err = cmd.normalize_paths(normalized_key).unwrap_err();
+        assert_eq!(err.code(), "invalid_path");
+
+        let mut batch: BatchRequest = serde_json::from_str(
+            r#"{"operations":[{"tool":"delete","path":"./a"},{"tool":"delete","path":"b/.."}]}"#,
+        )
+        .unwrap();
+        let err = batch.normalize_paths(normalized_key).unwrap_err();
+        assert_eq!(err.code(), "invalid_path");
+    }
+
+    #[test]
+    fn test_parse_rejects_unknown_tools() {
+        for json in [r#"{"tool":"format_disk"}"#, r#"{"find":"x"}"#, "not json"] {
+            let err = Command::parse(json).unwrap_err();
+            assert_eq!(err.code(), "invalid_command");
+        }
+        // Missing required fields are reported too.
+        let err = Command::parse(r#"{"tool":"unified_diff"}"#).unwrap_err();
+        assert!(err.to_string().contains("path"), "{err}");
+    }
+
+    fn create(path: &str) -> Command {
+        Command::Create(CreateRequest {
+            path: PathKey::from_arc(path.into()),
+            content: None,
+            allow_overwrite: false,
+        })
+    }
+
+    /// Stages created files; every other command fails.
+    fn stage(manager: &IndexManager) -> impl FnMut(Command) -> Result<Value> + '_ {
+        |command| match command {
+            Command::Create(req) => {
+                let entry = crate::fs::FileEntry::from_bytes_and_path(
+                    &req.path,
+                    0,
+                    Vec::new().into(),
+                    true,
+                );
+                manager.stage_file(req.path, entry)?;
+                Ok(Value::Null)
+            }
+            other => Err(Error::FileNotFound(other.tool().to_string())),
+        }
+    }
+
+    fn batch(on_error: OnError) -> BatchRequest {
+        BatchRequest {
+            operations: vec![create("a.txt"), Command::AllUnifiedDiffs, create("b.txt")],
+            on_error,
+        }
+    }
+
+    #[test]
+    fn test_batch_error_policies() {
+        let manager = IndexManager::default();
+        manager.begin_staging().unwrap();
+        let staged = |path: &str| {
+            manager
+                .staged_index()
+                .unwrap()
+                .get_file(&PathKey::from_arc(path.into()))
+                .is_some()
+        };
+
+        let report = run_each(&manager, batch(OnError::Rollback), stage(&manager)).unwrap();
+        assert!(report.rolled_back);
+        assert_eq!((report.results.len(), report.skipped), (2, 1));
+        assert_eq!(
+            report.results[1].error.as_ref().unwrap().code,
+            "file_not_found"
+        );
+        assert!(!staged("a.txt"));
+        assert!(manager.undo_state().undo.is_empty());
+
+        let report = run_each(&manager, batch(OnError::Stop), stage(&manager)).unwrap();
+        assert!(!report.rolled_back);
+        assert_eq!((report.results.len(), report.skipped), (2, 1));
+        assert!(report.results[0].response.is_some());
+        assert!(staged("a.txt") && !staged("b.txt"));
+
+        manager.undo().unwrap();
+        let report = run_each(&manager, batch(OnError::Continue), stage(&manager)).unwrap();
+        assert_eq!((report.results.len(), report.skipped), (3, 0));
+        assert!(staged("a.txt") && staged("b.txt"));
+
+        // The whole batch undoes as one step.
+        manager.undo().unwrap();
+        assert!(!staged("a.txt") && !staged("b.txt"));
+    }
+
+    #[test]
+    fn test_batch_requires_staging() {
+        let manager = IndexManager::default();
+        let err = run_each(&manager, batch(OnError::Continue), |_| Ok(Value::Null)).unwrap_err();
+        assert_eq!(err.code(), "staging_not_active");
+    }
+}
@@ -11,18 +11,63 @@ pub enum Error {
     #[error("staging already active")]
     StagingAlreadyActive,
 
+    #[error("staging is owned by '{owner}', not '{requested}'")]
+    StagingOwnedBy { owner: String, requested: String },
+
     #[error("file not found: {0}")]
     FileNotFound(String),
 
     #[error("invalid path provided: {0}")]
     InvalidPath(String),
 
+    #[error("duplicate path during load: {0}")]
+    DuplicatePath(String),
+
     #[error("file has no content: {0}")]
     MissingContent(String),
 
+    #[error("file content is not loaded; hydrate it first: {0}")]
+    NotHydrated(String),
+
     #[error("file already exists: {0}")]
     FileAlreadyExists(String),
 
+    #[error("{path} changed: expected hash {expected}, found {actual}")]
+    ContentChanged {
+        path: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error(
+        "memory ceiling of {ceiling} bytes reached: {usage} in use, {requested} more requested"
+    )]
+    MemoryPressure {
+        usage: u64,
+        requested: u64,
+        ceiling: u64,
+    },
+
+    #[error("{path} changed since it was read: now hash {hash}, mtime {mtime}")]
+    EditConflict {
+        path: String,
+        hash: String,
+        mtime: i64,
+    },
+
+    #[error("anchor text not found in {0}")]
+    AnchorNotFound(String),
+
+    #[error("anchor text matches {count} places in {path}; pick an occurrence")]
+    AmbiguousAnchor { path: String, count: usize },
+
+    #[error("overlapping line ranges in {path}: {}", format_conflicts(conflicts))]
+    OverlappingEdits {
+        path: String,
+        /// Request positions of each pair of overlapping operations.
+        conflicts: Vec<(usize, usize)>,
+    },
+
     // -------- Search / Replace / Preview --------
     #[error("invalid range: [{0}, {1})")]
     InvalidRange(usize, usize),
@@ -33,6 +78,12 @@ pub enum Error {
     #[error("encoding conversion failed")]
     Encoding,
 
+    #[error("file is not valid UTF-8: {0}")]
+    InvalidUtf8(String),
+
+    #[error("unsupported format: {0}")]
+    InvalidFormat(String),
+
     // -------- Wrapped sources --------
     #[error(transparent)]
     Regex(#[from] regex::Error),
@@ -52,14 +103,106 @@ pub enum Error {
     #[error("pattern error: {0}")]
     Pattern(String),
 
+    #[error("unknown pattern: {0}")]
+    UnknownPattern(String),
+
+    #[error("missing template parameter: {0}")]
+    MissingTemplateParam(String),
+
+    #[error("unknown saved search: {0}")]
+    UnknownSavedSearch(String),
+
+    #[error("unknown template: {0}")]
+    UnknownTemplate(String),
+
+    #[error("unknown checkpoint: {0}")]
+    UnknownCheckpoint(String),
+
+    #[error("unknown hunk: {0}")]
+    UnknownHunk(usize),
+
     #[error("no replacement found at ({0}, {1})")]
     NoReplacementFound(usize, usize),
 
     #[error("file is not editable: {0}")]
     ReadOnlyFile(String),
 
+    #[error("invalid or revoked elevation token: {0}")]
+    InvalidElevationToken(String),
+
     #[error("file needs to be read before editing: {0}")]
     FileNeedsRead(String),
+
+    #[error("invalid patch at line {line}: {reason}")]
+    InvalidPatch { line: usize, reason: String },
+
+    #[error("invalid command: {0}")]
+    InvalidCommand(String),
+
+    #[error("archive error: {0}")]
+    Archive(String),
+
+    #[error("transaction operation {index} ({op}) failed: {source}")]
+    TransactionFailed {
+        index: usize,
+        op: &'static str,
+        source: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Stable machine-readable name of the variant, for structured reports.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::StagingNotActive => "staging_not_active",
+            Error::StagingAlreadyActive => "staging_already_active",
+            Error::StagingOwnedBy { .. } => "staging_owned_by",
+            Error::FileNotFound(_) => "file_not_found",
+            Error::InvalidPath(_) => "invalid_path",
+            Error::DuplicatePath(_) => "duplicate_path",
+            Error::MissingContent(_) => "missing_content",
+            Error::NotHydrated(_) => "not_hydrated",
+            Error::FileAlreadyExists(_) => "file_already_exists",
+            Error::ContentChanged { .. } => "content_changed",
+            Error::MemoryPressure { .. } => "memory_pressure",
+            Error::EditConflict { .. } => "edit_conflict",
+            Error::AnchorNotFound(_) => "anchor_not_found",
+            Error::AmbiguousAnchor { .. } => "ambiguous_anchor",
+            Error::OverlappingEdits { .. } => "overlapping_edits",
+            Error::InvalidRange(..) => "invalid_range",
+            Error::Aborted => "aborted",
+            Error::Encoding => "encoding",
+            Error::InvalidUtf8(_) => "invalid_utf8",
+            Error::InvalidFormat(_) => "invalid_format",
+            Error::Regex(_) | Error::Grep(_) | Error::GrepMatcher(_) | Error::Pattern(_) => {
+                "pattern"
+            }
+            Error::UnknownPattern(_) => "unknown_pattern",
+            Error::Io(_) => "io",
+            Error::Glob(_) => "glob",
+            Error::MissingTemplateParam(_) => "missing_template_param",
+            Error::UnknownSavedSearch(_) => "unknown_saved_search",
+            Error::UnknownTemplate(_) => "unknown_template",
+            Error::UnknownCheckpoint(_) => "unknown_checkpoint",
+            Error::UnknownHunk(_) => "unknown_hunk",
+            Error::NoReplacementFound(..) => "no_replacement_found",
+            Error::ReadOnlyFile(_) => "read_only_file",
+            Error::InvalidElevationToken(_) => "invalid_elevation_token",
+            Error::FileNeedsRead(_) => "file_needs_read",
+            Error::InvalidPatch { .. } => "invalid_patch",
+            Error::InvalidCommand(_) => "invalid_command",
+            Error::Archive(_) => "archive",
+            Error::TransactionFailed { .. } => "transaction_failed",
+        }
+    }
+}
+
+fn format_conflicts(conflicts: &[(usize, usize)]) -> String {
+    conflicts
+        .iter()
+        .map(|(a, b)| format!("#{a} and #{b}"))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 impl SinkError for Error {
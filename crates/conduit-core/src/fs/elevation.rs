@@ -0,0 +1,202 @@
+//! Scoped override of the read-only guard for host-driven fixups.
+//!
+//! Hosts mint named tokens; running an operation under a token lets
+//! [`Index::upsert_file`](super::Index::upsert_file) overwrite read-only
+//! entries for the duration of that operation only. Every elevated run is
+//! recorded with the read-only paths it actually wrote.
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::marker::PhantomData;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::fs::PathKey;
+
+/// Audit entries kept before the oldest are dropped.
+const MAX_AUDIT_ENTRIES: usize = 256;
+
+thread_local! {
+    static DEPTH: Cell<usize> = const { Cell::new(0) };
+    static BYPASSED: RefCell<Vec<PathKey>> = const { RefCell::new(Vec::new()) };
+}
+
+/// While alive, read-only files on this thread may be overwritten.
+///
+/// Scopes nest; the guard is lifted when the outermost scope ends. Paths a
+/// nested scope reports from [`ElevatedScope::finish`] are not reported
+/// again by the scopes around it.
+pub struct ElevatedScope {
+    /// Length of the bypass log when this scope was entered.
+    start: usize,
+    _not_send: PhantomData<*const ()>,
+}
+
+impl ElevatedScope {
+    pub fn enter() -> Self {
+        DEPTH.with(|d| d.set(d.get() + 1));
+        Self {
+            start: BYPASSED.with(|b| b.borrow().len()),
+            _not_send: PhantomData,
+        }
+    }
+
+    /// End the scope, returning the read-only paths written while it was open.
+    pub fn finish(self) -> Vec<PathKey> {
+        let mut paths = BYPASSED.with(|b| {
+            let mut b = b.borrow_mut();
+            let start = self.start.min(b.len());
+            b.drain(start..).collect::<Vec<_>>()
+        });
+        paths.sort();
+        paths.dedup();
+        paths
+    }
+}
+
+impl Drop for ElevatedScope {
+    fn drop(&mut self) {
+        let depth = DEPTH.with(|d| {
+            let depth = d.get().saturating_sub(1);
+            d.set(depth);
+            depth
+        });
+        if depth == 0 {
+            BYPASSED.with(|b| b.borrow_mut().clear());
+        }
+    }
+}
+
+/// Whether read-only files on this thread may currently be overwritten.
+pub(crate) fn is_elevated() -> bool {
+    DEPTH.with(|d| d.get() > 0)
+}
+
+/// Record a completed write to read-only `key` for the open scopes.
+pub(crate) fn record_readonly_write(key: &PathKey) {
+    BYPASSED.with(|b| b.borrow_mut().push(key.clone()));
+}
+
+/// One elevated operation, as recorded in the audit log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ElevationRecord {
+    pub reason: String,
+    /// Host-supplied timestamp of the run.
+    pub at: i64,
+    /// Read-only files written under elevation.
+    pub paths: Vec<PathKey>,
+    pub succeeded: bool,
+}
+
+/// Host-minted elevation tokens and the audit log of their use.
+#[derive(Debug, Default)]
+pub struct ElevationTokens {
+    tokens: HashMap<String, String>,
+    audit: VecDeque<ElevationRecord>,
+    minted: u64,
+}
+
+impl ElevationTokens {
+    /// Mint a token for `reason`. `nonce` makes tokens hard to guess.
+    pub fn mint(&mut self, reason: impl Into<String>, nonce: u64) -> String {
+        self.minted += 1;
+        let token = format!("elev-{:x}-{:016x}", self.minted, nonce);
+        self.tokens.insert(token.clone(), reason.into());
+        token
+    }
+
+    /// Revoke a token. Returns whether it existed.
+    pub fn revoke(&mut self, token: &str) -> bool {
+        self.tokens.remove(token).is_some()
+    }
+
+    /// Reason a live token was minted for.
+    pub fn reason(&self, token: &str) -> Result<&str> {
+        self.tokens
+            .get(token)
+            .map(String::as_str)
+            .ok_or_else(|| Error::InvalidElevationToken(token.to_string()))
+    }
+
+    pub fn record(&mut self, record: ElevationRecord) {
+        if self.audit.len() == MAX_AUDIT_ENTRIES {
+            self.audit.pop_front();
+        }
+        self.audit.push_back(record);
+    }
+
+    /// Audit log, oldest first.
+    pub fn audit(&self) -> impl Iterator<Item = &ElevationRecord> {
+        self.audit.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::{FileEntry, Index};
+    use std::sync::Arc;
+
+    fn readonly_index(path: &PathKey) -> Index {
+        let mut index = Index::default();
+        let entry = FileEntry::from_bytes("", 0, Arc::from(&b"v1"[..]), false);
+        index.upsert_file(path.clone(), entry).unwrap();
+        index
+    }
+
+    #[test]
+    fn test_scope_allows_readonly_writes_and_reports_them() {
+        let path = PathKey::from_arc(Arc::from("Cargo.lock"));
+        let mut index = readonly_index(&path);
+        let update = || FileEntry::from_bytes("", 1, Arc::from(&b"v2"[..]), false);
+
+        assert!(matches!(
+            index.upsert_file(path.clone(), update()),
+            Err(Error::ReadOnlyFile(_))
+        ));
+
+        let scope = ElevatedScope::enter();
+        index.upsert_file(path.clone(), update()).unwrap();
+        assert_eq!(scope.finish(), vec![path.clone()]);
+
+        assert!(index.upsert_file(path, update()).is_err());
+    }
+
+    #[test]
+    fn test_nested_scopes_report_their_own_writes() {
+        let outer_path = PathKey::from_arc(Arc::from("Cargo.lock"));
+        let inner_path = PathKey::from_arc(Arc::from("yarn.lock"));
+        let mut outer_index = readonly_index(&outer_path);
+        let mut inner_index = readonly_index(&inner_path);
+        let update = || FileEntry::from_bytes("", 1, Arc::from(&b"v2"[..]), false);
+
+        let outer = ElevatedScope::enter();
+        outer_index
+            .upsert_file(outer_path.clone(), update())
+            .unwrap();
+
+        let inner = ElevatedScope::enter();
+        inner_index
+            .upsert_file(inner_path.clone(), update())
+            .unwrap();
+        assert_eq!(inner.finish(), vec![inner_path]);
+
+        assert_eq!(outer.finish(), vec![outer_path]);
+    }
+
+    #[test]
+    fn test_tokens_mint_and_revoke() {
+        let mut tokens = ElevationTokens::default();
+        let token = tokens.mint("lockfile regen", 7);
+
+        assert_eq!(tokens.reason(&token).unwrap(), "lockfile regen");
+        assert!(tokens.revoke(&token));
+        assert!(matches!(
+            tokens.reason(&token),
+            Err(Error::InvalidElevationToken(_))
+        ));
+    }
+}
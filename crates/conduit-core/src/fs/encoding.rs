@@ -0,0 +1,139 @@
+//! Text encodings of loaded files.
+//!
+//! Search, reads and diffs work on UTF-8. Files saved by Windows tools often
+//! arrive as UTF-16 or a legacy Latin-1 code page instead; [`detect`] picks
+//! the encoding when a file is loaded, the entry keeps UTF-8 text next to
+//! the original bytes, and edits are written back in the file's encoding
+//! with [`encode`].
+//!
+//! UTF-16 is recognised by its byte order mark only. Other text that is not
+//! valid UTF-8 is read as Windows-1252, the superset of ISO-8859-1 that
+//! browsers use for "Latin-1"; content with NUL bytes is left as binary.
+
+use std::borrow::Cow;
+
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, WINDOWS_1252};
+use serde::{Deserialize, Serialize};
+
+use crate::tools::looks_binary;
+
+/// Encoding of a file's bytes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TextEncoding {
+    /// UTF-8, or binary content searched as raw bytes.
+    #[default]
+    Utf8,
+    /// UTF-16 little-endian with a byte order mark.
+    Utf16Le,
+    /// UTF-16 big-endian with a byte order mark.
+    Utf16Be,
+    /// Windows-1252.
+    Latin1,
+}
+
+impl TextEncoding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TextEncoding::Utf8 => "utf8",
+            TextEncoding::Utf16Le => "utf16le",
+            TextEncoding::Utf16Be => "utf16be",
+            TextEncoding::Latin1 => "latin1",
+        }
+    }
+
+    fn encoding(self) -> &'static Encoding {
+        match self {
+            TextEncoding::Utf8 => encoding_rs::UTF_8,
+            TextEncoding::Utf16Le => UTF_16LE,
+            TextEncoding::Utf16Be => UTF_16BE,
+            TextEncoding::Latin1 => WINDOWS_1252,
+        }
+    }
+}
+
+/// Guess the encoding of `bytes`.
+pub fn detect(bytes: &[u8]) -> TextEncoding {
+    match Encoding::for_bom(bytes) {
+        Some((enc, _)) if enc == UTF_16LE => return TextEncoding::Utf16Le,
+        Some((enc, _)) if enc == UTF_16BE => return TextEncoding::Utf16Be,
+        _ => {}
+    }
+    if looks_binary(bytes) || std::str::from_utf8(bytes).is_ok() {
+        TextEncoding::Utf8
+    } else {
+        TextEncoding::Latin1
+    }
+}
+
+/// `bytes` in `encoding` as UTF-8, without the byte order mark.
+pub fn decode(bytes: &[u8], encoding: TextEncoding) -> Cow<'_, str> {
+    if encoding == TextEncoding::Utf8 {
+        return String::from_utf8_lossy(bytes);
+    }
+    encoding.encoding().decode_with_bom_removal(bytes).0
+}
+
+/// `text` in `encoding`, with a byte order mark for UTF-16.
+///
+/// Returns `None` if `text` has characters `encoding` cannot represent.
+pub fn encode(text: &str, encoding: TextEncoding) -> Option<Vec<u8>> {
+    let utf16 = |bom: [u8; 2], to_bytes: fn(u16) -> [u8; 2]| {
+        let mut out = Vec::with_capacity(2 + text.len() * 2);
+        out.extend_from_slice(&bom);
+        for unit in text.encode_utf16() {
+            out.extend_from_slice(&to_bytes(unit));
+        }
+        out
+    };
+    match encoding {
+        TextEncoding::Utf8 => Some(text.as_bytes().to_vec()),
+        // encoding_rs only encodes to UTF-8 and legacy encodings.
+        TextEncoding::Utf16Le => Some(utf16([0xFF, 0xFE], u16::to_le_bytes)),
+        TextEncoding::Utf16Be => Some(utf16([0xFE, 0xFF], u16::to_be_bytes)),
+        TextEncoding::Latin1 => {
+            let (bytes, _, unmappable) = WINDOWS_1252.encode(text);
+            (!unmappable).then(|| bytes.into_owned())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_and_round_trip() {
+        let text = "naïve café\r\nline 2\n";
+        for encoding in [
+            TextEncoding::Utf8,
+            TextEncoding::Utf16Le,
+            TextEncoding::Utf16Be,
+            TextEncoding::Latin1,
+        ] {
+            let bytes = encode(text, encoding).unwrap();
+            assert_eq!(detect(&bytes), encoding);
+            assert_eq!(decode(&bytes, encoding), text);
+        }
+
+        // NUL bytes mark binary content, which stays as it is.
+        assert_eq!(detect(b"\x00\xff\xfe\x01"), TextEncoding::Utf8);
+        assert_eq!(encode("日本", TextEncoding::Latin1), None);
+    }
+
+    #[test]
+    fn test_entry_keeps_decoded_text() {
+        use crate::fs::FileEntry;
+        use std::sync::Arc;
+
+        let bytes = encode("héllo\n", TextEncoding::Utf16Le).unwrap();
+        let mut entry = FileEntry::from_bytes("txt", 0, Arc::from(bytes.clone()), true);
+        assert_eq!(entry.encoding(), TextEncoding::Utf16Le);
+        assert_eq!(entry.bytes(), Some(&bytes[..]));
+        assert_eq!(entry.search_content(), Some("héllo\n".as_bytes()));
+
+        entry.update_bytes(Arc::from(&b"plain"[..]), None);
+        assert_eq!(entry.encoding(), TextEncoding::Utf8);
+        assert_eq!(entry.search_content(), Some(&b"plain"[..]));
+    }
+}
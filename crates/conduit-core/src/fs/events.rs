@@ -0,0 +1,58 @@
+//! Change notifications from an [`IndexManager`](crate::fs::IndexManager).
+//!
+//! Hosts that mirror staging in a UI register a listener instead of polling
+//! for modified files. Events are queued while the manager holds its locks
+//! and sent once the outermost operation returns, so a listener may call
+//! back into the manager. Events of an operation that failed and was rolled
+//! back are never sent.
+
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::fs::PathKey;
+
+/// Something that changed in the staged or active index.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum IndexEvent {
+    StagingBegun,
+    /// Content written to `path`, creating or updating it.
+    FileStaged {
+        path: PathKey,
+    },
+    FileDeleted {
+        path: PathKey,
+    },
+    FileMoved {
+        from: PathKey,
+        to: PathKey,
+    },
+    /// Staged changes became active; `paths` are the ones changed.
+    StagingPromoted {
+        paths: Vec<PathKey>,
+    },
+    /// Staged changes were discarded.
+    StagingReverted,
+    /// Staging was replaced as a whole by an undo, redo, checkpoint
+    /// restore or session import.
+    StagingRestored,
+    /// Files were loaded in bulk; no per-file events are sent for them.
+    FilesLoaded {
+        count: usize,
+    },
+}
+
+/// Receives every [`IndexEvent`] in the order the changes happened.
+pub type EventListener = Arc<dyn Fn(&IndexEvent) + Send + Sync>;
+
+/// Events waiting for the operation that produced them to finish.
+#[derive(Default)]
+pub(crate) struct EventQueue {
+    pub(crate) listener: Option<EventListener>,
+    pub(crate) pending: Vec<IndexEvent>,
+    /// Open scopes holding events back; they are sent when this drops to 0.
+    pub(crate) held: usize,
+    /// Open scopes whose events are not recorded at all.
+    pub(crate) muted: usize,
+}
@@ -0,0 +1,93 @@
+//! Compiling the include and exclude globs of a request.
+//!
+//! Every binding that filters by glob goes through [`compile_globs`], so a
+//! pattern like `src/{app,lib}/**/*.ts` means the same thing everywhere.
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+
+/// How glob patterns are interpreted. Brace alternation (`{a,b}`) and `**`
+/// are always available.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+pub struct GlobOptions {
+    /// `*` and `?` stop at `/`, so only `**` crosses directories.
+    pub literal_separator: bool,
+    /// Match paths regardless of case.
+    pub case_insensitive: bool,
+    /// A backslash escapes the character after it, e.g. `\*` for a
+    /// literal `*`.
+    pub backslash_escape: bool,
+}
+
+impl Default for GlobOptions {
+    fn default() -> Self {
+        Self {
+            literal_separator: false,
+            case_insensitive: false,
+            backslash_escape: true,
+        }
+    }
+}
+
+/// Compile `patterns` into one set, or `None` when there are none.
+pub fn compile_globs(patterns: Option<&[String]>, opts: &GlobOptions) -> Result<Option<GlobSet>> {
+    patterns
+        .filter(|p| !p.is_empty())
+        .map(|patterns| {
+            let mut builder = GlobSetBuilder::new();
+            for pattern in patterns {
+                builder.add(
+                    GlobBuilder::new(pattern)
+                        .literal_separator(opts.literal_separator)
+                        .case_insensitive(opts.case_insensitive)
+                        .backslash_escape(opts.backslash_escape)
+                        .build()?,
+                );
+            }
+            builder.build().map_err(Into::into)
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(pattern: &str, opts: GlobOptions, path: &str) -> bool {
+        compile_globs(Some(&[pattern.to_string()]), &opts)
+            .unwrap()
+            .unwrap()
+            .is_match(path)
+    }
+
+    #[test]
+    fn test_options_change_matching() {
+        let default = GlobOptions::default();
+        assert!(matches("src/{app,lib}/**/*.ts", default, "src/lib/a/b.ts"));
+        assert!(!matches("src/{app,lib}/**/*.ts", default, "src/bin/b.ts"));
+        assert!(matches("src/*.ts", default, "src/a/b.ts"));
+        assert!(!matches("src/*.TS", default, "src/a.ts"));
+        assert!(matches(r"a\*.ts", default, "a*.ts"));
+
+        let strict = GlobOptions {
+            literal_separator: true,
+            case_insensitive: true,
+            backslash_escape: true,
+        };
+        assert!(!matches("src/*.ts", strict, "src/a/b.ts"));
+        assert!(matches("src/**/*.ts", strict, "src/a/b.ts"));
+        assert!(matches("src/*.TS", strict, "src/a.ts"));
+    }
+
+    #[test]
+    fn test_no_patterns_compile_to_none() {
+        let opts = GlobOptions::default();
+        assert!(compile_globs(None, &opts).unwrap().is_none());
+        assert!(compile_globs(Some(&[]), &opts).unwrap().is_none());
+        assert!(compile_globs(Some(&["a[".to_string()]), &opts).is_err());
+    }
+}
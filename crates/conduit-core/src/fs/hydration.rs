@@ -0,0 +1,203 @@
+//! Byte budget for content fetched on demand.
+//!
+//! Large repositories can be loaded as metadata only, with the host
+//! supplying a file's bytes when an operation needs them. Hydrated content
+//! is tracked here in least-recently-used order; once it exceeds the
+//! budget, the oldest files go back to metadata only and are fetched again
+//! on next use. Content loaded up front or written by edits is never
+//! tracked, so it is never evicted.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::fs::PathKey;
+
+/// Hydrated bytes kept before the least recently used are evicted.
+pub const DEFAULT_HYDRATION_BUDGET: u64 = 256 * 1024 * 1024;
+
+/// Hydrated content held, and the budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HydrationUsage {
+    pub files: usize,
+    pub bytes: u64,
+    pub budget: u64,
+}
+
+/// Hydrated files in least-recently-used order.
+#[derive(Debug)]
+pub struct HydrationLru {
+    budget: u64,
+    bytes: u64,
+    clock: u64,
+    /// Last use and size of each file.
+    entries: HashMap<PathKey, (u64, u64)>,
+    /// Files by last use, oldest first.
+    order: BTreeMap<u64, PathKey>,
+    /// Open [`Self::pin`] scopes; files hydrated inside one are not evicted
+    /// until the last closes.
+    pins: usize,
+    pinned: HashSet<PathKey>,
+}
+
+impl Default for HydrationLru {
+    fn default() -> Self {
+        Self {
+            budget: DEFAULT_HYDRATION_BUDGET,
+            bytes: 0,
+            clock: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+            pins: 0,
+            pinned: HashSet::new(),
+        }
+    }
+}
+
+impl HydrationLru {
+    pub fn usage(&self) -> HydrationUsage {
+        HydrationUsage {
+            files: self.entries.len(),
+            bytes: self.bytes,
+            budget: self.budget,
+        }
+    }
+
+    pub fn set_budget(&mut self, budget: u64) {
+        self.budget = budget;
+    }
+
+    pub fn contains(&self, key: &PathKey) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    /// Start a scope in which newly hydrated files are kept from eviction.
+    pub fn pin(&mut self) {
+        self.pins += 1;
+    }
+
+    /// End a [`Self::pin`] scope; after the last, pinned files can be
+    /// evicted again by the next [`Self::evict_over_budget`].
+    pub fn unpin(&mut self) {
+        self.pins = self.pins.saturating_sub(1);
+        if self.pins == 0 {
+            self.pinned.clear();
+        }
+    }
+
+    /// Track `key` as hydrated with `size` bytes, as the most recent use.
+    pub fn insert(&mut self, key: PathKey, size: u64) {
+        self.remove(&key);
+        if self.pins > 0 {
+            self.pinned.insert(key.clone());
+        }
+        self.clock += 1;
+        self.bytes += size;
+        self.order.insert(self.clock, key.clone());
+        self.entries.insert(key, (self.clock, size));
+    }
+
+    /// Mark `key` as just used. Returns whether it is tracked.
+    pub fn touch(&mut self, key: &PathKey) -> bool {
+        let Some((used, _)) = self.entries.get_mut(key) else {
+            return false;
+        };
+        self.order.remove(used);
+        self.clock += 1;
+        *used = self.clock;
+        self.order.insert(self.clock, key.clone());
+        true
+    }
+
+    /// Stop tracking `key`, as when an edit replaces its content. Returns
+    /// its size.
+    pub fn remove(&mut self, key: &PathKey) -> Option<u64> {
+        let (used, size) = self.entries.remove(key)?;
+        self.order.remove(&used);
+        self.bytes -= size;
+        Some(size)
+    }
+
+    /// Stop tracking files for which `keep` returns `false`.
+    pub fn retain(&mut self, mut keep: impl FnMut(&PathKey) -> bool) {
+        let dropped: Vec<PathKey> = self.entries.keys().filter(|k| !keep(k)).cloned().collect();
+        for key in dropped {
+            self.remove(&key);
+        }
+    }
+
+    /// Untrack the least recently used files until the rest fit the
+    /// budget, returning them oldest first. The most recent file and pinned
+    /// files are kept even if they alone exceed the budget.
+    pub fn evict_over_budget(&mut self) -> Vec<PathKey> {
+        let mut evicted = Vec::new();
+        while self.bytes > self.budget && self.entries.len() > 1 {
+            let oldest = self
+                .order
+                .iter()
+                .find(|(_, key)| !self.pinned.contains(*key))
+                .map(|(&used, _)| used);
+            let Some(key) = oldest.and_then(|used| self.order.remove(&used)) else {
+                break;
+            };
+            if let Some((_, size)) = self.entries.remove(&key) {
+                self.bytes -= size;
+            }
+            evicted.push(key);
+        }
+        evicted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn key(p: &str) -> PathKey {
+        PathKey::from_arc(Arc::from(p))
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut lru = HydrationLru::default();
+        lru.set_budget(10);
+        lru.insert(key("a"), 4);
+        lru.insert(key("b"), 4);
+        assert!(lru.evict_over_budget().is_empty());
+
+        assert!(lru.touch(&key("a")));
+        lru.insert(key("c"), 4);
+        assert_eq!(lru.evict_over_budget(), [key("b")]);
+        assert_eq!(lru.usage().bytes, 8);
+
+        lru.insert(key("d"), 20);
+        assert_eq!(lru.evict_over_budget(), [key("a"), key("c")]);
+        assert_eq!(
+            lru.usage(),
+            HydrationUsage {
+                files: 1,
+                bytes: 20,
+                budget: 10
+            }
+        );
+        assert_eq!(lru.remove(&key("d")), Some(20));
+        assert!(!lru.touch(&key("d")));
+    }
+
+    #[test]
+    fn test_pinned_files_outlive_budget() {
+        let mut lru = HydrationLru::default();
+        lru.set_budget(4);
+        lru.pin();
+        lru.insert(key("a"), 4);
+        lru.insert(key("b"), 4);
+        assert!(lru.evict_over_budget().is_empty());
+        lru.unpin();
+
+        lru.insert(key("c"), 4);
+        assert_eq!(lru.evict_over_budget(), [key("a"), key("b")]);
+    }
+}
@@ -0,0 +1,227 @@
+//! `.gitignore`-style filtering of indexed paths.
+//!
+//! Hosts usually load a whole working tree, including dependency folders and
+//! build output. An [`IgnoreSet`] collects the ignore files present in the
+//! index, plus any rules supplied by the host, so searches and listings skip
+//! those paths by default.
+//!
+//! Supported syntax: comments, `!` negation, trailing `/` for directories,
+//! patterns anchored by a `/`, and `*`, `?`, `**` and character classes.
+//! The last matching rule wins, and rules from deeper ignore files come after
+//! shallower ones. Unlike git, a negation can re-include a file inside an
+//! ignored directory.
+
+use globset::{GlobBuilder, GlobMatcher};
+
+use crate::error::Result;
+use crate::fs::{FileEntry, Index, PathKey};
+
+/// File name of the ignore files read from the index.
+pub const IGNORE_FILE_NAME: &str = ".gitignore";
+
+/// Every `.gitignore` in `index`, shallowest first.
+pub fn ignore_files(index: &Index) -> Vec<(&PathKey, &FileEntry)> {
+    let mut files: Vec<_> = index
+        .iter_sorted()
+        .filter(|(path, _)| path.as_str().rsplit('/').next() == Some(IGNORE_FILE_NAME))
+        .collect();
+    files.sort_by_key(|(path, _)| path.as_str().matches('/').count());
+    files
+}
+
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    /// Directory of the ignore file, with a trailing `/`; empty for the root.
+    base: String,
+    matcher: GlobMatcher,
+    negated: bool,
+    dir_only: bool,
+}
+
+/// Ordered ignore rules from one or more ignore files.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreSet {
+    rules: Vec<IgnoreRule>,
+}
+
+impl IgnoreSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collect the rules of every `.gitignore` in `index`, shallowest first.
+    ///
+    /// Ignore files that are not valid UTF-8 are skipped, as are lines that
+    /// do not form a valid pattern.
+    pub fn from_index(index: &Index) -> Self {
+        Self::from_files(ignore_files(index))
+    }
+
+    /// Collect the rules of `files`, as listed by [`ignore_files`].
+    pub fn from_files<'a>(files: impl IntoIterator<Item = (&'a PathKey, &'a FileEntry)>) -> Self {
+        let mut set = Self::new();
+        for (path, entry) in files {
+            let path = path.as_str();
+            let dir = &path[..path.len() - IGNORE_FILE_NAME.len()];
+            if let Some(text) = entry
+                .search_content()
+                .and_then(|b| std::str::from_utf8(b).ok())
+            {
+                set.add_lenient(dir, text);
+            }
+        }
+        set
+    }
+
+    /// Append the rules of an ignore file located in `dir` (`""` for the root).
+    ///
+    /// Fails on the first line that is not a valid pattern.
+    pub fn add_file(&mut self, dir: &str, content: &str) -> Result<()> {
+        for line in content.lines() {
+            if let Some(rule) = parse_rule(dir, line)? {
+                self.rules.push(rule);
+            }
+        }
+        Ok(())
+    }
+
+    /// Like [`IgnoreSet::add_file`], but skips invalid lines.
+    fn add_lenient(&mut self, dir: &str, content: &str) {
+        for line in content.lines() {
+            if let Ok(Some(rule)) = parse_rule(dir, line) {
+                self.rules.push(rule);
+            }
+        }
+    }
+
+    /// Append the rules of `other` after this set's rules.
+    pub fn extend(&mut self, other: IgnoreSet) {
+        self.rules.extend(other.rules);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Whether `path` (a file) is ignored, directly or through a directory.
+    pub fn is_ignored(&self, path: &str) -> bool {
+        let mut ignored = false;
+        for rule in &self.rules {
+            let Some(rel) = path.strip_prefix(rule.base.as_str()) else {
+                continue;
+            };
+            let hit = ancestors(rel).any(|dir| rule.matcher.is_match(dir))
+                || (!rule.dir_only && rule.matcher.is_match(rel));
+            if hit {
+                ignored = !rule.negated;
+            }
+        }
+        ignored
+    }
+}
+
+/// Proper ancestor directories of `rel`, shortest first: `a`, `a/b` for `a/b/c`.
+fn ancestors(rel: &str) -> impl Iterator<Item = &str> {
+    rel.match_indices('/').map(move |(i, _)| &rel[..i])
+}
+
+fn parse_rule(dir: &str, line: &str) -> Result<Option<IgnoreRule>> {
+    let line = line.trim_end_matches('\r');
+    // Trailing spaces are ignored unless escaped.
+    let line = if line.ends_with("\\ ") {
+        line
+    } else {
+        line.trim_end_matches(' ')
+    };
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+
+    let (negated, pattern) = match line.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, line.strip_prefix('\\').unwrap_or(line)),
+    };
+    let (dir_only, pattern) = match pattern.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+    if pattern.is_empty() {
+        return Ok(None);
+    }
+
+    // A slash anywhere but the end anchors the pattern to the ignore file's
+    // directory; otherwise it matches at any depth.
+    let glob = match pattern.strip_prefix('/') {
+        Some(anchored) => anchored.to_string(),
+        None if pattern.contains('/') => pattern.to_string(),
+        None => format!("**/{pattern}"),
+    };
+    let matcher = GlobBuilder::new(&glob)
+        .literal_separator(true)
+        .build()?
+        .compile_matcher();
+
+    let base = match dir.trim_end_matches('/') {
+        "" => String::new(),
+        d => format!("{d}/"),
+    };
+    Ok(Some(IgnoreRule {
+        base,
+        matcher,
+        negated,
+        dir_only,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::PathKey;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_gitignore_rules() {
+        let mut set = IgnoreSet::new();
+        set.add_file("", "# deps\nnode_modules/\n*.log\n!keep.log\n/build\n")
+            .unwrap();
+        set.add_file("web", "dist/\n*.map\n").unwrap();
+
+        assert!(set.is_ignored("node_modules/a/index.js"));
+        assert!(set.is_ignored("pkg/node_modules/x.js"));
+        assert!(set.is_ignored("logs/out.log"));
+        assert!(!set.is_ignored("logs/keep.log"));
+        assert!(set.is_ignored("build/main.o"));
+        assert!(!set.is_ignored("src/build/main.rs"));
+        assert!(set.is_ignored("web/dist/app.js"));
+        assert!(set.is_ignored("web/app.js.map"));
+        assert!(!set.is_ignored("app.js.map"));
+        assert!(!set.is_ignored("src/main.rs"));
+        // Directory-only rules do not match files of that name.
+        set.add_file("", "notes/\n").unwrap();
+        assert!(!set.is_ignored("notes"));
+    }
+
+    #[test]
+    fn test_from_index_reads_nested_ignore_files() {
+        let mut index = Index::default();
+        let key = |p: &str| PathKey::from_arc(Arc::from(p));
+        for (path, content) in [
+            (".gitignore", "*.tmp\n"),
+            ("app/.gitignore", "!keep.tmp\nout/\n"),
+            ("app/keep.tmp", ""),
+            ("app/x.tmp", ""),
+            ("app/out/a.js", ""),
+            ("out/b.js", ""),
+        ] {
+            let path = key(path);
+            let entry = FileEntry::from_bytes_and_path(&path, 0, content.as_bytes().into(), true);
+            index.upsert_file(path, entry).unwrap();
+        }
+
+        let set = IgnoreSet::from_index(&index);
+        assert!(set.is_ignored("app/x.tmp"));
+        assert!(!set.is_ignored("app/keep.tmp"));
+        assert!(set.is_ignored("app/out/a.js"));
+        assert!(!set.is_ignored("out/b.js"));
+    }
+}
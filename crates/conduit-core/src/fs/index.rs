@@ -6,8 +6,21 @@ use std::{
     sync::Arc,
 };
 
+use xxhash_rust::xxh3::xxh3_64;
+
 use crate::error::{Error, Result};
-use crate::fs::PathKey;
+use crate::fs::encoding::{self, TextEncoding};
+use crate::fs::{elevation, PathKey};
+
+/// Which files default listings and searches include.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct Visibility {
+    /// Include dotfiles and files the host flagged hidden.
+    pub include_hidden: bool,
+    /// Include OS junk such as `.DS_Store`; see [`PathKey::is_junk`].
+    pub include_junk: bool,
+}
 
 /// File metadata with optional content.
 #[derive(Debug, Clone)]
@@ -18,7 +31,11 @@ pub struct FileEntry {
     mtime: i64, // unix epoch
     bytes: Option<Arc<[u8]>>,
     text_content: Option<Arc<[u8]>>,
+    /// Encoding of `bytes`; `text_content` holds them decoded when not UTF-8.
+    encoding: TextEncoding,
     editable: bool,
+    /// Flagged hidden by the host; see [`FileEntry::is_hidden`].
+    hidden: bool,
 }
 
 /// Path-indexed file collection with efficient prefix queries.
@@ -30,6 +47,8 @@ pub struct Index {
     files: IHashMap<PathKey, FileEntry>,
     // sorted paths for prefix/range queries
     prefixes: IOrdSet<PathKey>,
+    // sum of `resident_bytes` over `files`
+    content_bytes: u64,
 }
 
 impl FileEntry {
@@ -55,7 +74,9 @@ impl FileEntry {
             mtime,
             bytes: None,
             text_content: None,
+            encoding: TextEncoding::Utf8,
             editable,
+            hidden: false,
         }
     }
 
@@ -79,7 +100,9 @@ impl FileEntry {
             mtime,
             bytes: None,
             text_content: None,
+            encoding: TextEncoding::Utf8,
             editable,
+            hidden: false,
         }
     }
 
@@ -98,8 +121,11 @@ impl FileEntry {
             mtime,
             bytes: Some(bytes),
             text_content: None,
+            encoding: TextEncoding::Utf8,
             editable,
+            hidden: false,
         }
+        .with_detected_encoding()
     }
 
     /// Create entry with content from a path.
@@ -128,17 +154,50 @@ impl FileEntry {
             mtime,
             bytes: Some(bytes),
             text_content: None,
+            encoding: TextEncoding::Utf8,
             editable,
+            hidden: false,
         }
+        .with_detected_encoding()
     }
 
     /// Replace content, optionally updating mtime.
+    ///
+    /// Text decoded from a previous non-UTF-8 encoding is decoded afresh;
+    /// text supplied by the host is kept.
     pub fn update_bytes(&mut self, bytes: Arc<[u8]>, new_mtime: Option<i64>) {
         self.size = bytes.len() as u64;
         self.bytes = Some(bytes);
         if let Some(t) = new_mtime {
             self.mtime = t;
         }
+        if self.text_content.is_none() || self.encoding != TextEncoding::Utf8 {
+            self.text_content = None;
+            self.encoding = TextEncoding::Utf8;
+            self.detect_encoding();
+        }
+    }
+
+    fn with_detected_encoding(mut self) -> Self {
+        self.detect_encoding();
+        self
+    }
+
+    /// Detect the encoding of the loaded bytes, keeping them decoded as
+    /// UTF-8 text when they are not UTF-8 already.
+    fn detect_encoding(&mut self) {
+        if let Some(bytes) = &self.bytes {
+            self.encoding = encoding::detect(bytes);
+            if self.encoding != TextEncoding::Utf8 {
+                let text = encoding::decode(bytes, self.encoding).into_owned();
+                self.text_content = Some(Arc::from(text.into_bytes()));
+            }
+        }
+    }
+
+    /// Encoding of the loaded bytes.
+    pub fn encoding(&self) -> TextEncoding {
+        self.encoding
     }
 
     /// Drop content, keep metadata.
@@ -162,7 +221,42 @@ impl FileEntry {
             mtime,
             bytes: Some(original_bytes),
             text_content: Some(text_content),
+            encoding: TextEncoding::Utf8,
             editable,
+            hidden: false,
+        }
+    }
+
+    /// Bytes of content held in memory: the loaded bytes plus separate
+    /// search text, if any.
+    pub fn resident_bytes(&self) -> u64 {
+        let len = |b: &Option<Arc<[u8]>>| b.as_ref().map_or(0, |b| b.len() as u64);
+        match (&self.bytes, &self.text_content) {
+            (Some(a), Some(b)) if Arc::ptr_eq(a, b) => a.len() as u64,
+            _ => len(&self.bytes) + len(&self.text_content),
+        }
+    }
+
+    /// Bytes of search text held in addition to the loaded bytes.
+    pub fn text_bytes(&self) -> u64 {
+        self.resident_bytes() - self.bytes.as_ref().map_or(0, |b| b.len() as u64)
+    }
+
+    /// Whether the search text is a separate copy of the loaded bytes, which
+    /// [`Index::compact`] can drop without changing what searches see.
+    pub fn has_redundant_text(&self) -> bool {
+        match (&self.bytes, &self.text_content) {
+            (Some(a), Some(b)) => !Arc::ptr_eq(a, b) && a[..] == b[..],
+            _ => false,
+        }
+    }
+
+    /// Whether both entries hold the same content allocation, as after a
+    /// clone or a move, so that keeping both costs no extra memory.
+    pub fn shares_content(&self, other: &FileEntry) -> bool {
+        match (&self.bytes, &other.bytes) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            _ => false,
         }
     }
 
@@ -175,6 +269,16 @@ impl FileEntry {
         self.bytes.as_deref()
     }
 
+    /// Fast non-cryptographic hash (XXH3-64) of the loaded bytes.
+    ///
+    /// Meant for cache keys and change detection, not integrity checks.
+    pub fn content_hash(&self) -> Option<u64> {
+        self.bytes
+            .as_deref()
+            .or(self.text_content.as_deref())
+            .map(xxh3_64)
+    }
+
     /// File extension.
     pub fn ext(&self) -> &str {
         &self.ext
@@ -195,9 +299,39 @@ impl FileEntry {
         self.mtime
     }
 
+    /// Whether mtime falls within the given inclusive bounds (unix epoch).
+    ///
+    /// Missing bounds are treated as open.
+    pub fn modified_within(&self, after: Option<i64>, before: Option<i64>) -> bool {
+        after.is_none_or(|t| self.mtime >= t) && before.is_none_or(|t| self.mtime <= t)
+    }
+
     pub fn is_editable(&self) -> bool {
         self.editable
     }
+
+    /// Mark or unmark the entry as hidden by the host.
+    pub fn set_hidden(&mut self, hidden: bool) {
+        self.hidden = hidden;
+    }
+
+    /// Whether the host flagged the entry hidden, ignoring its path.
+    pub fn is_host_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Whether the entry at `path` is left out of default listings and
+    /// searches: flagged by the host, or under a dot-prefixed component.
+    pub fn is_hidden(&self, path: &PathKey) -> bool {
+        self.hidden || path.is_dotfile()
+    }
+
+    /// Whether the entry at `path` is listed under `visibility`. Junk stays
+    /// out unless included on its own, even when hidden files are shown.
+    pub fn is_visible(&self, path: &PathKey, visibility: Visibility) -> bool {
+        (visibility.include_hidden || !self.is_hidden(path))
+            && (visibility.include_junk || !path.is_junk())
+    }
 }
 
 impl Index {
@@ -206,18 +340,89 @@ impl Index {
         self.files.get(key)
     }
 
+    /// Mutable access for metadata changes. Replace content through
+    /// [`Index::upsert_file`] so [`Index::content_bytes`] stays accurate.
+    pub fn get_file_mut(&mut self, key: &PathKey) -> Option<&mut FileEntry> {
+        self.files.get_mut(key)
+    }
+
     pub fn take_file(&mut self, key: &PathKey) -> Option<FileEntry> {
-        self.files.remove(key)
+        let entry = self.files.remove(key)?;
+        let _ = self.prefixes.remove(key);
+        self.content_bytes -= entry.resident_bytes();
+        Some(entry)
+    }
+
+    /// Bytes of file content held by this index.
+    pub fn content_bytes(&self) -> u64 {
+        self.content_bytes
+    }
+
+    /// Give a metadata-only entry its content. Returns `false` if the path
+    /// is not indexed or already has content.
+    pub fn fill_content(&mut self, key: &PathKey, bytes: Arc<[u8]>) -> bool {
+        let Some(entry) = self.files.get_mut(key) else {
+            return false;
+        };
+        if entry.bytes.is_some() {
+            return false;
+        }
+        entry.update_bytes(bytes, None);
+        self.content_bytes += entry.resident_bytes();
+        true
+    }
+
+    /// Drop an entry's content, keeping its metadata. Returns the bytes
+    /// freed.
+    pub fn release_content(&mut self, key: &PathKey) -> u64 {
+        let Some(entry) = self.files.get_mut(key) else {
+            return 0;
+        };
+        let freed = entry.resident_bytes();
+        entry.clear_bytes();
+        self.content_bytes -= freed;
+        freed
+    }
+
+    /// Drop search text that duplicates the loaded bytes. Returns the bytes
+    /// freed.
+    pub fn compact(&mut self) -> u64 {
+        let redundant: Vec<PathKey> = self
+            .files
+            .iter()
+            .filter(|(_, entry)| entry.has_redundant_text())
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        let mut freed = 0;
+        for key in redundant {
+            if let Some(entry) = self.files.get_mut(&key) {
+                freed += entry.text_bytes();
+                entry.text_content = None;
+            }
+        }
+        self.content_bytes -= freed;
+        freed
     }
 
     /// Insert or update file.
     pub fn upsert_file(&mut self, key: PathKey, entry: FileEntry) -> Result<()> {
-        if let Some(existing) = self.files.get(&key) {
-            if !existing.is_editable() {
-                return Err(Error::ReadOnlyFile(key.into()));
+        let elevated = match self.files.get(&key) {
+            Some(existing) if !existing.is_editable() => {
+                if !elevation::is_elevated() {
+                    return Err(Error::ReadOnlyFile(key.into()));
+                }
+                true
             }
+            _ => false,
+        };
+        self.content_bytes += entry.resident_bytes();
+        if let Some(old) = self.files.insert(key.clone(), entry) {
+            self.content_bytes -= old.resident_bytes();
+        }
+        if elevated {
+            elevation::record_readonly_write(&key);
         }
-        let _old = self.files.insert(key.clone(), entry);
         let _ = self.prefixes.insert(key);
         Ok(())
     }
@@ -230,11 +435,7 @@ impl Index {
         //     return Err(Error::ReadOnlyFile(key.clone().into()));
         // }
 
-        let existed = self.files.remove(key).is_some();
-        if existed {
-            let _ = self.prefixes.remove(key);
-        }
-        Ok(existed)
+        Ok(self.take_file(key).is_some())
     }
 
     /// All paths with given prefix.
@@ -246,6 +447,16 @@ impl Index {
             .collect()
     }
 
+    /// All paths inside directory `dir`, at any depth. Unlike
+    /// [`Index::paths_by_prefix`], `src/a` does not match `src/ab/x`.
+    pub fn paths_under(&self, dir: &str) -> Vec<PathKey> {
+        let dir = dir.trim_end_matches('/');
+        if dir.is_empty() {
+            return self.prefixes.iter().cloned().collect();
+        }
+        self.paths_by_prefix(&PathKey::from_arc(Arc::from(format!("{dir}/"))))
+    }
+
     /// Filtered paths matching prefix and glob patterns.
     ///
     /// Applies includes first, then excludes. All filters optional.
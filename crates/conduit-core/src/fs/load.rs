@@ -0,0 +1,231 @@
+//! Load reports: what each staging batch accepted and why files were skipped.
+
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::fs::PathKey;
+
+/// What to do when a path is offered more than once during a load.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum DuplicatePolicy {
+    /// The later entry replaces the earlier one.
+    #[default]
+    LastWriteWins,
+    /// The earlier entry is kept; later ones are skipped.
+    KeepFirst,
+    /// The entry with the newer mtime is kept; ties keep the earlier one.
+    KeepNewestMtime,
+    /// Fail the batch containing the duplicate.
+    Error,
+}
+
+impl DuplicatePolicy {
+    /// Whether an incoming entry should replace the one already loaded for `path`.
+    pub fn should_replace(
+        self,
+        path: &PathKey,
+        existing_mtime: i64,
+        incoming_mtime: i64,
+    ) -> Result<bool> {
+        match self {
+            Self::LastWriteWins => Ok(true),
+            Self::KeepFirst => Ok(false),
+            Self::KeepNewestMtime => Ok(incoming_mtime > existing_mtime),
+            Self::Error => Err(Error::DuplicatePath(path.as_str().to_string())),
+        }
+    }
+}
+
+/// Why a file offered during load was not staged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum SkipReason {
+    /// The path was empty or failed normalization.
+    InvalidPath,
+    /// The modification time was negative or not finite.
+    InvalidTimestamp,
+    /// The path was already loaded and the duplicate policy kept the earlier entry.
+    Duplicate,
+    /// The content exceeded the configured size limit.
+    TooLarge,
+}
+
+/// A file that was skipped during load.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedFile {
+    /// Path as provided by the host (not normalized).
+    pub path: String,
+    pub reason: SkipReason,
+    /// Human-readable detail.
+    pub message: String,
+}
+
+/// Outcome of staging a single batch.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchReport {
+    pub accepted: usize,
+    pub skipped: Vec<SkippedFile>,
+    /// Paths in this batch that were already loaded, whatever the outcome.
+    pub duplicates: Vec<String>,
+    /// Bytes staged by this batch.
+    pub bytes_staged: u64,
+    /// Bytes staged since the load began, including this batch.
+    pub total_bytes_staged: u64,
+}
+
+impl BatchReport {
+    pub fn skip(
+        &mut self,
+        path: impl Into<String>,
+        reason: SkipReason,
+        message: impl Into<String>,
+    ) {
+        self.skipped.push(SkippedFile {
+            path: path.into(),
+            reason,
+            message: message.into(),
+        });
+    }
+}
+
+/// Running totals across the batches of one load.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LoadProgress {
+    /// Whether a load has begun and not yet been committed or aborted.
+    pub in_progress: bool,
+    pub batches: usize,
+    pub files_accepted: usize,
+    pub bytes_staged: u64,
+    /// Every file skipped so far, in the order encountered.
+    pub skipped: Vec<SkippedFile>,
+    /// Every duplicate path seen so far, in the order encountered.
+    pub duplicates: Vec<String>,
+    /// mtime and size of each path staged during this load.
+    #[serde(skip)]
+    loaded: HashMap<PathKey, (i64, u64)>,
+}
+
+impl LoadProgress {
+    /// Start a fresh load.
+    pub fn begin() -> Self {
+        Self {
+            in_progress: true,
+            ..Self::default()
+        }
+    }
+
+    /// mtime of `path` if it was already staged during this load.
+    pub fn loaded_mtime(&self, path: &PathKey) -> Option<i64> {
+        self.loaded.get(path).map(|&(mtime, _)| mtime)
+    }
+
+    /// Fold a batch into the totals, filling in its `total_bytes_staged`.
+    ///
+    /// `staged` lists `(path, mtime, size)` for each entry the batch staged;
+    /// entries replacing an earlier one are not counted twice.
+    pub fn record(&mut self, report: &mut BatchReport, staged: &[(PathKey, i64, u64)]) {
+        self.batches += 1;
+        for (path, mtime, size) in staged {
+            match self.loaded.insert(path.clone(), (*mtime, *size)) {
+                Some((_, old_size)) => self.bytes_staged -= old_size,
+                None => self.files_accepted += 1,
+            }
+            self.bytes_staged += size;
+        }
+        self.skipped.extend(report.skipped.iter().cloned());
+        self.duplicates.extend(report.duplicates.iter().cloned());
+        report.total_bytes_staged = self.bytes_staged;
+    }
+
+    /// Mark the load as finished, keeping the totals for inspection.
+    pub fn finish(&mut self) {
+        self.in_progress = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn key(p: &str) -> PathKey {
+        PathKey::from_arc(Arc::from(p))
+    }
+
+    #[test]
+    fn test_progress_accumulates_batches() {
+        let mut progress = LoadProgress::begin();
+
+        let mut first = BatchReport {
+            accepted: 2,
+            bytes_staged: 100,
+            ..BatchReport::default()
+        };
+        first.skip("../x", SkipReason::InvalidPath, "escapes root");
+        progress.record(&mut first, &[(key("a"), 1, 60), (key("b"), 1, 40)]);
+        assert_eq!(first.total_bytes_staged, 100);
+
+        let mut second = BatchReport {
+            accepted: 1,
+            bytes_staged: 50,
+            ..BatchReport::default()
+        };
+        progress.record(&mut second, &[(key("c"), 1, 50)]);
+        assert_eq!(second.total_bytes_staged, 150);
+
+        assert!(progress.in_progress);
+        assert_eq!(progress.batches, 2);
+        assert_eq!(progress.files_accepted, 3);
+        assert_eq!(progress.skipped.len(), 1);
+        assert_eq!(progress.skipped[0].reason, SkipReason::InvalidPath);
+
+        progress.finish();
+        assert!(!progress.in_progress);
+        assert_eq!(progress.bytes_staged, 150);
+    }
+
+    #[test]
+    fn test_replacement_not_double_counted() {
+        let mut progress = LoadProgress::begin();
+        progress.record(&mut BatchReport::default(), &[(key("a"), 1, 60)]);
+
+        let mut second = BatchReport {
+            duplicates: vec!["a".to_string()],
+            ..BatchReport::default()
+        };
+        progress.record(&mut second, &[(key("a"), 5, 20)]);
+
+        assert_eq!(progress.files_accepted, 1);
+        assert_eq!(progress.bytes_staged, 20);
+        assert_eq!(progress.loaded_mtime(&key("a")), Some(5));
+        assert_eq!(progress.duplicates, vec!["a"]);
+    }
+
+    #[test]
+    fn test_duplicate_policies() {
+        let path = key("src/a.rs");
+        assert!(DuplicatePolicy::LastWriteWins
+            .should_replace(&path, 10, 5)
+            .unwrap());
+        assert!(!DuplicatePolicy::KeepFirst
+            .should_replace(&path, 5, 10)
+            .unwrap());
+        assert!(DuplicatePolicy::KeepNewestMtime
+            .should_replace(&path, 5, 10)
+            .unwrap());
+        assert!(!DuplicatePolicy::KeepNewestMtime
+            .should_replace(&path, 10, 10)
+            .unwrap());
+        assert!(matches!(
+            DuplicatePolicy::Error.should_replace(&path, 5, 10),
+            Err(Error::DuplicatePath(p)) if p == "src/a.rs"
+        ));
+    }
+}
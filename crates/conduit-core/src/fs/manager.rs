@@ -1,13 +1,22 @@
 use arc_swap::ArcSwap;
 use im::{HashSet as IHashSet, OrdSet as IOrdSet};
-use parking_lot::{Mutex, RwLock};
+use parking_lot::{Mutex, MutexGuard, RwLock};
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use xxhash_rust::xxh3::xxh3_64;
 
 use crate::error::{Error, Result};
+use crate::fs::events::EventQueue;
 use crate::fs::PathKey;
-use crate::fs::{FileEntry, Index};
-use crate::tools::LineIndex;
+use crate::fs::{
+    ActiveRef, CompactReport, EventListener, FileEntry, HintIntent, HydrationLru, HydrationUsage,
+    Index, IndexEvent, MemoryReport, MemoryUsage, Session, SessionContent, SessionFile,
+    StagingRecord, Visibility,
+};
+use crate::schema::SCHEMA_VERSION;
+use crate::tools::{compute_diff, FileDiff, LineIndex, Splice};
+use crate::SearchSpace;
 
 #[derive(Default, Clone)]
 pub struct StagingState {
@@ -19,10 +28,111 @@ pub struct StagingState {
     moves: im::HashMap<PathKey, PathKey>,
     /// Track files that need to be read before line-based edits
     needs_read: im::HashSet<PathKey>,
+    /// Label of the flow that began this session, if it gave one
+    owner: Option<String>,
+    /// Operations applied to each path this session, repeats collapsed
+    history: im::HashMap<PathKey, im::Vector<StagedOp>>,
+    /// Staged content bytes per path that the active index does not share
+    overlay: im::HashMap<PathKey, u64>,
+    /// Sum of `overlay`
+    overlay_bytes: u64,
+}
+
+impl StagingState {
+    fn record(&mut self, key: &PathKey, op: StagedOp) {
+        let ops = self.history.entry(key.clone()).or_default();
+        if ops.last() != Some(&op) {
+            ops.push_back(op);
+        }
+    }
+
+    /// Fail unless this session was begun by `owner`; `None` matches only
+    /// an unlabeled session.
+    fn check_owner(&self, owner: Option<&str>) -> Result<()> {
+        if self.owner.as_deref() == owner {
+            return Ok(());
+        }
+        let label = |name: Option<&str>| name.unwrap_or("<unlabeled>").to_string();
+        Err(Error::StagingOwnedBy {
+            owner: label(self.owner.as_deref()),
+            requested: label(owner),
+        })
+    }
+}
+
+/// An operation recorded in a path's staging history.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum StagedOp {
+    /// Content written (created or updated).
+    Write,
+    Delete,
+    /// Moved away to another path.
+    MoveOut,
+    /// Moved here from another path.
+    MoveIn,
+}
+
+/// Undo steps kept per staging session; older ones are dropped.
+pub const UNDO_LIMIT: usize = 100;
+
+/// Staging as it was before (for undo) or after (for redo) an operation.
+#[derive(Clone)]
+struct UndoStep {
+    label: String,
+    state: StagingState,
+}
+
+#[derive(Default)]
+struct Journal {
+    undo: Vec<UndoStep>,
+    redo: Vec<UndoStep>,
+    /// Nesting of `with_undo_step`; only the outermost call records.
+    depth: usize,
+    /// Named staging states, oldest first; see `create_checkpoint`.
+    checkpoints: Vec<(String, StagingState)>,
+}
+
+impl Journal {
+    fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.checkpoints.clear();
+    }
+
+    fn checkpoint(&self, name: &str) -> Result<&StagingState> {
+        self.checkpoints
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, state)| state)
+            .ok_or_else(|| Error::UnknownCheckpoint(name.to_string()))
+    }
+}
+
+/// A named checkpoint, as listed by [`IndexManager::list_checkpoints`].
+#[derive(
+    Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckpointInfo {
+    pub name: String,
+    /// Files staging had changed when the checkpoint was taken.
+    pub modified_files: usize,
+}
+
+/// Labels of the operations that can be undone and redone, most recent last.
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema,
+)]
+pub struct UndoState {
+    pub undo: Vec<String>,
+    pub redo: Vec<String>,
 }
 
 /// Statistics about changes to a file
-#[derive(Default, Clone, Debug)]
+#[derive(Default, Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct FileChangeStats {
     /// Total lines added across all operations
     pub lines_added: isize,
@@ -33,6 +143,144 @@ pub struct FileChangeStats {
     /// Current line count
     pub current_line_count: usize,
 }
+/// A file written or removed by a promote.
+#[derive(Clone, Debug)]
+pub struct DeltaEntry {
+    pub path: PathKey,
+    /// The committed entry, or `None` if the file was deleted.
+    pub entry: Option<FileEntry>,
+}
+
+/// A staged index together with the staging state it was read under.
+///
+/// Taken under one lock, so counts and path sets derived from a view agree
+/// with each other even if staging changes before the caller is done.
+#[derive(Clone, Debug)]
+pub struct StagedView {
+    pub index: Arc<Index>,
+    /// Paths written, deleted or moved this session.
+    pub modified: IOrdSet<PathKey>,
+    /// [`IndexManager::generation`] when the view was taken.
+    pub generation: u64,
+}
+
+impl StagedView {
+    /// Modified paths that still exist, with their staged bytes.
+    pub fn modifications(&self) -> impl Iterator<Item = (&PathKey, &[u8])> {
+        self.modified.iter().filter_map(|path| {
+            self.index
+                .get_file(path)
+                .and_then(|entry| entry.bytes())
+                .map(|bytes| (path, bytes))
+        })
+    }
+
+    /// Modified paths absent from the staged index.
+    pub fn deletions(&self) -> impl Iterator<Item = &PathKey> {
+        self.modified
+            .iter()
+            .filter(|path| self.index.get_file(path).is_none())
+    }
+}
+
+/// Files changed by one promote, for incremental persistence.
+#[derive(Clone, Debug, Default)]
+pub struct CommitDelta {
+    /// Increments on every promote; a gap means a delta was missed.
+    pub sequence: u64,
+    /// Changed paths in sorted order.
+    pub entries: Vec<DeltaEntry>,
+}
+
+/// What changes do when staging has not begun.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum StagingMode {
+    /// Fail with [`Error::StagingNotActive`] until staging is begun.
+    #[default]
+    Strict,
+    /// Begin staging on the first change.
+    Auto,
+}
+
+/// Settings of an [`IndexManager`]; see [`IndexManager::configure`].
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
+)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ManagerConfig {
+    pub staging: StagingMode,
+    /// Default for listings and searches that do not say whether to
+    /// include dotfiles and host-hidden files.
+    pub include_hidden: bool,
+    /// Default for whether OS junk files are included.
+    pub include_junk: bool,
+}
+
+impl ManagerConfig {
+    /// The visibility defaults, with `include_hidden` and `include_junk`
+    /// overriding them where set.
+    pub fn visibility(
+        &self,
+        include_hidden: Option<bool>,
+        include_junk: Option<bool>,
+    ) -> Visibility {
+        Visibility {
+            include_hidden: include_hidden.unwrap_or(self.include_hidden),
+            include_junk: include_junk.unwrap_or(self.include_junk),
+        }
+    }
+}
+
+/// Events held back by an operation in progress; see
+/// [`IndexManager::set_event_listener`].
+struct HeldEvents<'a> {
+    manager: &'a IndexManager,
+    /// Events pending when the hold began.
+    mark: usize,
+}
+
+impl HeldEvents<'_> {
+    /// Drop the events recorded since the hold began.
+    fn discard(&self) {
+        self.manager.events.lock().pending.truncate(self.mark);
+    }
+}
+
+impl Drop for HeldEvents<'_> {
+    fn drop(&mut self) {
+        self.manager.release_events();
+    }
+}
+
+struct MutedEvents<'a> {
+    manager: &'a IndexManager,
+}
+
+impl Drop for MutedEvents<'_> {
+    fn drop(&mut self) {
+        self.manager.events.lock().muted -= 1;
+    }
+}
+
 /// Manages staged index updates with copy-on-write semantics.
 ///
 /// Architecture:
@@ -44,9 +292,27 @@ pub struct IndexManager {
     active: ArcSwap<Index>,
     // Only writers touch this; protects the optional staged snapshot.
     staged: Mutex<Option<StagingState>>,
-    // Cache of line indices for files, keyed by (PathKey, mtime)
+    // Cache of line indices for files, keyed by path and XXH3-64 of the
+    // search content, so edits within one mtime tick never hit stale ones.
     // Using RwLock for concurrent reads
-    line_index_cache: RwLock<HashMap<(PathKey, i64), Arc<LineIndex>>>,
+    line_index_cache: RwLock<HashMap<(PathKey, u64), Arc<LineIndex>>>,
+    // Files changed by the most recent promote.
+    last_commit: Mutex<Option<Arc<CommitDelta>>>,
+    // Per-path edit epochs; see `edit_epoch`.
+    epochs: RwLock<HashMap<PathKey, u64>>,
+    epoch_clock: AtomicU64,
+    // Bumped on every change to the staged or active index; see `generation`.
+    generation: AtomicU64,
+    // Limit on content bytes held; see `set_memory_ceiling`.
+    memory_ceiling: Mutex<Option<u64>>,
+    // Staging states to return to; see `with_undo_step`. Lock `staged`
+    // before this when holding both.
+    journal: Mutex<Journal>,
+    // Content fetched on demand, for eviction; see `hydrate`.
+    hydrated: Mutex<HydrationLru>,
+    config: Mutex<ManagerConfig>,
+    // Listener and events not yet sent to it; see `set_event_listener`.
+    events: Mutex<EventQueue>,
 }
 
 impl Default for IndexManager {
@@ -55,6 +321,15 @@ impl Default for IndexManager {
             active: ArcSwap::from_pointee(Index::default()),
             staged: Mutex::new(None),
             line_index_cache: RwLock::new(HashMap::new()),
+            last_commit: Mutex::new(None),
+            epochs: RwLock::new(HashMap::new()),
+            epoch_clock: AtomicU64::new(0),
+            generation: AtomicU64::new(0),
+            memory_ceiling: Mutex::new(None),
+            journal: Mutex::new(Journal::default()),
+            hydrated: Mutex::new(HydrationLru::default()),
+            config: Mutex::new(ManagerConfig::default()),
+            events: Mutex::new(EventQueue::default()),
         }
     }
 }
@@ -69,12 +344,21 @@ impl IndexManager {
     ///
     /// Creates O(1) clone of current index for modifications.
     pub fn begin_staging(&self) -> Result<()> {
+        let _events = self.hold_events();
         let mut g = self.staged.lock();
 
         if g.is_some() {
             return Ok(());
         }
+        self.start_staging(&mut g, None);
+        drop(g);
+        self.advance();
+        self.emit(IndexEvent::StagingBegun);
+        Ok(())
+    }
 
+    /// Fill an empty staging slot from the active index.
+    fn start_staging(&self, g: &mut Option<StagingState>, owner: Option<String>) {
         let snapshot = self.active.load_full();
         let mut needs_read = IHashSet::new();
 
@@ -89,24 +373,201 @@ impl IndexManager {
             change_stats: im::HashMap::new(),
             moves: im::HashMap::new(),
             needs_read,
+            owner,
+            history: im::HashMap::new(),
+            overlay: im::HashMap::new(),
+            overlay_bytes: 0,
         });
+        self.journal.lock().clear();
+    }
+
+    /// Start staging on behalf of `owner`.
+    ///
+    /// Re-entrant for the same owner. Fails if another owner, or an
+    /// unlabeled `begin_staging`, already holds the session, so that
+    /// interleaved flows surface instead of silently sharing it.
+    pub fn begin_staging_as(&self, owner: &str) -> Result<()> {
+        let _events = self.hold_events();
+        let mut g = self.staged.lock();
+
+        if let Some(staged) = g.as_ref() {
+            return staged.check_owner(Some(owner));
+        }
+        self.start_staging(&mut g, Some(owner.to_string()));
+        drop(g);
+        self.advance();
+        self.emit(IndexEvent::StagingBegun);
+        Ok(())
+    }
+
+    /// Replace the manager's settings.
+    pub fn configure(&self, config: ManagerConfig) {
+        *self.config.lock() = config;
+    }
+
+    pub fn config(&self) -> ManagerConfig {
+        *self.config.lock()
+    }
+
+    /// Begin staging ahead of a change if it has not begun and
+    /// [`StagingMode::Auto`] is set. Under [`StagingMode::Strict`] this does
+    /// nothing and the change fails as before.
+    pub fn ensure_staging(&self) -> Result<()> {
+        if self.config().staging == StagingMode::Auto {
+            self.begin_staging()?;
+        }
         Ok(())
     }
 
+    /// Owner label of the active session; `None` if unlabeled.
+    pub fn staging_owner(&self) -> Result<Option<String>> {
+        let g = self.staged.lock();
+        let staged = g.as_ref().ok_or(Error::StagingNotActive)?;
+        Ok(staged.owner.clone())
+    }
+
+    /// Fail unless the active session was begun by `owner`; `None` passes
+    /// only for an unlabeled session.
+    pub fn check_staging_owner(&self, owner: Option<&str>) -> Result<()> {
+        let g = self.staged.lock();
+        g.as_ref()
+            .ok_or(Error::StagingNotActive)?
+            .check_owner(owner)
+    }
+
     /// Add/update file in staging area.
     ///
     /// First write triggers COW split via `Arc::make_mut`.
     pub fn stage_file(&self, key: PathKey, entry: FileEntry) -> Result<()> {
+        let _events = self.hold_events();
+        self.ensure_staging()?;
+        let mut g = self.staged.lock();
+        let staged = g.as_mut().ok_or(Error::StagingNotActive)?;
+        self.stage_locked(staged, key, entry, None)
+    }
+
+    /// [`Self::stage_file`] for an edit known to replace only `splice` of
+    /// the staged content, so a cached line index is carried over without
+    /// comparing the old and new text.
+    pub fn stage_file_spliced(&self, key: PathKey, entry: FileEntry, splice: Splice) -> Result<()> {
+        let _events = self.hold_events();
+        self.ensure_staging()?;
+        let mut g = self.staged.lock();
+        let staged = g.as_mut().ok_or(Error::StagingNotActive)?;
+        self.stage_locked(staged, key, entry, Some(splice))
+    }
+
+    /// Stage `entry` only if the active content of `key` still hashes to
+    /// `expected`; `None` expects the file to be absent from the active index.
+    ///
+    /// The check runs under the staging lock, so no promote can land between
+    /// it and the write.
+    pub fn stage_file_if_active(
+        &self,
+        key: PathKey,
+        entry: FileEntry,
+        expected: Option<u64>,
+    ) -> Result<()> {
+        let _events = self.hold_events();
+        self.ensure_staging()?;
         let mut g = self.staged.lock();
         let staged = g.as_mut().ok_or(Error::StagingNotActive)?;
+
+        let actual = match self.active.load().get_file(&key) {
+            Some(current) => Some(
+                current
+                    .content_hash()
+                    .ok_or_else(|| Error::MissingContent(key.as_str().to_string()))?,
+            ),
+            None => None,
+        };
+        if actual != expected {
+            let show = |h: Option<u64>| h.map_or("none".to_string(), |h| format!("{h:016x}"));
+            return Err(Error::ContentChanged {
+                path: key.as_str().to_string(),
+                expected: show(expected),
+                actual: show(actual),
+            });
+        }
+        self.stage_locked(staged, key, entry, None)
+    }
+
+    fn stage_locked(
+        &self,
+        staged: &mut StagingState,
+        key: PathKey,
+        entry: FileEntry,
+        splice: Option<Splice>,
+    ) -> Result<()> {
+        let size = match self.active.load().get_file(&key) {
+            Some(active) if active.shares_content(&entry) => 0,
+            _ => entry.resident_bytes(),
+        };
+        let previous_size = staged.overlay.get(&key).copied().unwrap_or(0);
+        if size > previous_size {
+            self.usage_locked(Some(staged))
+                .check(size - previous_size)?;
+        }
+
         let idx = Arc::make_mut(&mut staged.snapshot); // split on first write
 
         staged.modified.insert(key.clone());
         staged.needs_read.insert(key.clone());
-        idx.upsert_file(key, entry)?;
+        let previous = idx.get_file(&key).cloned();
+        if let Some(previous) = previous {
+            self.carry_line_index(&key, &previous, &entry, splice);
+        }
+        idx.upsert_file(key.clone(), entry)?;
+        staged.overlay_bytes = staged.overlay_bytes - previous_size + size;
+        staged.overlay.insert(key.clone(), size);
+        staged.record(&key, StagedOp::Write);
+        self.bump_epochs([&key]);
+        self.advance();
+        self.emit(IndexEvent::FileStaged { path: key });
         Ok(())
     }
 
+    /// Carry a cached line index across a content change, rescanning only
+    /// the changed region instead of the whole file. A `splice` from the
+    /// caller saves finding that region by comparing the contents.
+    fn carry_line_index(
+        &self,
+        key: &PathKey,
+        old: &FileEntry,
+        new: &FileEntry,
+        splice: Option<Splice>,
+    ) {
+        let (Some(old_bytes), Some(new_bytes)) = (old.search_content(), new.search_content())
+        else {
+            return;
+        };
+
+        let cached = self
+            .line_index_cache
+            .read()
+            .get(&line_index_key(key, old_bytes))
+            .cloned();
+        let Some(cached) = cached else {
+            return;
+        };
+        if cached.total_bytes() != old_bytes.len() {
+            return;
+        }
+
+        let updated = match splice {
+            Some(splice) if splice.fits(old_bytes.len(), new_bytes.len()) => {
+                let mut index = (*cached).clone();
+                index.apply_splice(new_bytes, splice.start, splice.old_end, splice.new_end);
+                index
+            }
+            _ => cached.updated(old_bytes, new_bytes),
+        };
+        let updated = Arc::new(updated);
+        self.line_index_cache
+            .write()
+            .insert(line_index_key(key, new_bytes), updated);
+    }
+
     /// Update line change statistics for a file
     pub fn update_line_stats(
         &self,
@@ -145,17 +606,46 @@ impl IndexManager {
 
     /// Remove file from staging area.
     pub fn remove_staged_file(&self, key: &PathKey) -> Result<()> {
+        let _events = self.hold_events();
+        self.ensure_staging()?;
         let mut g = self.staged.lock();
         let staged = g.as_mut().ok_or(Error::StagingNotActive)?;
         let idx = Arc::make_mut(&mut staged.snapshot);
         staged.modified.insert(key.clone());
         staged.needs_read.remove(key);
-        let _ = idx.remove_file(key)?;
+        if idx.remove_file(key)? {
+            staged.overlay_bytes -= staged.overlay.remove(key).unwrap_or(0);
+            staged.record(key, StagedOp::Delete);
+            self.bump_epochs([key]);
+            self.advance();
+            self.emit(IndexEvent::FileDeleted { path: key.clone() });
+        }
         Ok(())
     }
 
+    /// Set the host hidden flag on staged files. Returns how many exist.
+    ///
+    /// Metadata only: the files are not marked modified.
+    pub fn set_hidden(&self, keys: &[PathKey], hidden: bool) -> Result<usize> {
+        let mut g = self.staged.lock();
+        let staged = g.as_mut().ok_or(Error::StagingNotActive)?;
+        let idx = Arc::make_mut(&mut staged.snapshot);
+
+        let mut found = 0;
+        for key in keys {
+            if let Some(entry) = idx.get_file_mut(key) {
+                entry.set_hidden(hidden);
+                found += 1;
+            }
+        }
+        self.advance();
+        Ok(found)
+    }
+
     /// Move a file within the staging area without copying content.
     pub fn move_staged_file(&self, src: &PathKey, dst: &PathKey, update_mtime: i64) -> Result<()> {
+        let _events = self.hold_events();
+        self.ensure_staging()?;
         let mut g = self.staged.lock();
         let staged = g.as_mut().ok_or(Error::StagingNotActive)?;
         let idx = Arc::make_mut(&mut staged.snapshot);
@@ -175,33 +665,468 @@ impl IndexManager {
         }
 
         idx.upsert_file(dst.clone(), entry)?;
+        // The content moves with the entry; whatever `dst` held is gone.
+        let moved_size = staged.overlay.remove(src).unwrap_or(0);
+        staged.overlay_bytes -= staged.overlay.remove(dst).unwrap_or(0);
+        staged.overlay.insert(dst.clone(), moved_size);
+        staged.record(src, StagedOp::MoveOut);
+        staged.record(dst, StagedOp::MoveIn);
+        self.bump_epochs([src, dst]);
+        self.advance();
+        self.emit(IndexEvent::FileMoved {
+            from: src.clone(),
+            to: dst.clone(),
+        });
 
         Ok(())
     }
 
+    /// Stage a copy of `src` at `dst`, replacing whatever `dst` held.
+    ///
+    /// The copy shares `src`'s bytes and keeps its MIME type, encoding,
+    /// decoded text and flags, so binary and non-UTF-8 files copy exactly.
+    pub fn copy_staged_file(&self, src: &PathKey, dst: &PathKey, update_mtime: i64) -> Result<()> {
+        let _events = self.hold_events();
+        self.ensure_staging()?;
+        let mut g = self.staged.lock();
+        let staged = g.as_mut().ok_or(Error::StagingNotActive)?;
+
+        let mut entry = staged
+            .snapshot
+            .get_file(src)
+            .cloned()
+            .ok_or_else(|| Error::FileNotFound(src.clone().into()))?;
+        if entry.bytes().is_none() {
+            return Err(Error::MissingContent(format!(
+                "No original bytes for: {}",
+                src.as_str()
+            )));
+        }
+        entry.set_modified(update_mtime);
+        self.stage_locked(staged, dst.clone(), entry, None)
+    }
+
+    /// Whether `key` was written, deleted or moved in the current session.
+    ///
+    /// `false` when staging is not active.
+    pub fn is_staged_modified(&self, key: &PathKey) -> bool {
+        self.staged
+            .lock()
+            .as_ref()
+            .is_some_and(|staged| staged.modified.contains(key))
+    }
+
+    /// Operations applied to `key` this session, oldest first.
+    pub fn staged_history(&self, key: &PathKey) -> Result<Vec<StagedOp>> {
+        let g = self.staged.lock();
+        let staged = g.as_ref().ok_or(Error::StagingNotActive)?;
+        Ok(staged
+            .history
+            .get(key)
+            .map(|ops| ops.iter().copied().collect())
+            .unwrap_or_default())
+    }
+
+    /// Whether `key` was deleted or moved away and then written again this session.
+    pub fn was_recreated(&self, key: &PathKey) -> Result<bool> {
+        let history = self.staged_history(key)?;
+        let removed_at = history
+            .iter()
+            .position(|op| matches!(op, StagedOp::Delete | StagedOp::MoveOut));
+        Ok(removed_at.is_some_and(|i| {
+            history[i..]
+                .iter()
+                .any(|op| matches!(op, StagedOp::Write | StagedOp::MoveIn))
+        }))
+    }
+
     /// Atomically replace active index with staged.
     ///
-    /// Existing readers keep their snapshots until dropped.
-    pub fn promote_staged(&self) -> Result<()> {
+    /// Existing readers keep their snapshots until dropped. Returns the view
+    /// that was promoted, so callers can report on exactly what became active.
+    pub fn promote_staged(&self) -> Result<StagedView> {
+        let _events = self.hold_events();
         let mut g = self.staged.lock();
         let staged = g.take().ok_or(Error::StagingNotActive)?;
+        let promoted = StagedView {
+            index: staged.snapshot.clone(),
+            modified: staged.modified.clone(),
+            generation: self.advance(),
+        };
+
+        let entries = staged
+            .modified
+            .iter()
+            .map(|path| DeltaEntry {
+                path: path.clone(),
+                entry: staged.snapshot.get_file(path).cloned(),
+            })
+            .collect();
+        {
+            let mut last = self.last_commit.lock();
+            let sequence = last.as_ref().map_or(0, |d| d.sequence) + 1;
+            *last = Some(Arc::new(CommitDelta { sequence, entries }));
+        }
+
+        self.journal.lock().clear();
+        // Promoted edits are no longer the host's copy; never evict them.
+        self.hydrated.lock().retain(|path| {
+            !staged.modified.contains(path) && staged.snapshot.get_file(path).is_some()
+        });
+        // Line indexes of unchanged files still hold; only drop those for
+        // content a promoted path no longer has.
+        self.line_index_cache.write().retain(|(key, hash), _| {
+            !staged.modified.contains(key)
+                || staged
+                    .snapshot
+                    .get_file(key)
+                    .and_then(FileEntry::search_content)
+                    .is_some_and(|c| xxh3_64(c) == *hash)
+        });
         // O(1) atomic swap; existing readers keep their old Arc<Index> until they drop it.
         self.active.store(staged.snapshot);
-        // Clear line index cache since files have changed
-        self.clear_line_index_cache();
-        Ok(())
+        self.emit(IndexEvent::StagingPromoted {
+            paths: promoted.modified.iter().cloned().collect(),
+        });
+        Ok(promoted)
+    }
+
+    /// Files changed by the most recent promote, if any.
+    pub fn last_commit_delta(&self) -> Option<Arc<CommitDelta>> {
+        self.last_commit.lock().clone()
     }
 
     /// Discard staged changes.
     pub fn revert_staged(&self) -> Result<()> {
+        let _events = self.hold_events();
         let mut g = self.staged.lock();
-        if g.is_none() {
-            return Err(Error::StagingNotActive);
-        }
-        *g = None;
+        let staged = g.take().ok_or(Error::StagingNotActive)?;
+        self.journal.lock().clear();
+        // Reverted paths go back to their active content.
+        self.bump_epochs(staged.modified.iter());
+        self.advance();
+        self.emit(IndexEvent::StagingReverted);
         Ok(())
     }
 
+    /// Limit total content bytes held by the active and staged indexes.
+    ///
+    /// Writes that would grow usage past the ceiling fail with
+    /// [`Error::MemoryPressure`]; shrinking writes and deletions always
+    /// succeed. `None` removes the limit. Lowering the ceiling below current
+    /// usage evicts nothing.
+    pub fn set_memory_ceiling(&self, ceiling: Option<u64>) {
+        *self.memory_ceiling.lock() = ceiling;
+    }
+
+    /// Content bytes currently held, and the ceiling.
+    pub fn memory_usage(&self) -> MemoryUsage {
+        self.usage_locked(self.staged.lock().as_ref())
+    }
+
+    /// Fail with [`Error::MemoryPressure`] if `requested` more bytes would
+    /// exceed the ceiling. Lets callers refuse work before copying content.
+    pub fn reserve_memory(&self, requested: u64) -> Result<()> {
+        self.memory_usage().check(requested)
+    }
+
+    fn usage_locked(&self, staged: Option<&StagingState>) -> MemoryUsage {
+        MemoryUsage {
+            active_bytes: self.active.load().content_bytes(),
+            staged_bytes: staged.map_or(0, |s| s.overlay_bytes),
+            ceiling: *self.memory_ceiling.lock(),
+        }
+    }
+
+    /// Estimated memory held, broken down by kind.
+    ///
+    /// Content counts follow [`IndexManager::memory_usage`]: staged entries
+    /// sharing active content are counted once. Line index and path sizes
+    /// count their payload only, not allocator or map overhead.
+    pub fn memory_report(&self) -> MemoryReport {
+        let staged = self.staged.lock();
+        let active = self.active.load_full();
+        let usage = self.usage_locked(staged.as_ref());
+        let mut report = MemoryReport {
+            content_bytes: usage.total(),
+            ..MemoryReport::default()
+        };
+
+        let mut count_text = |entry: &FileEntry| {
+            report.text_bytes += entry.text_bytes();
+            if entry.has_redundant_text() {
+                report.duplicate_text_bytes += entry.text_bytes();
+            }
+        };
+        let mut paths: IHashSet<&PathKey> = IHashSet::new();
+        for (key, entry) in active.iter() {
+            count_text(entry);
+            paths.insert(key);
+        }
+        if let Some(staged) = staged.as_ref() {
+            for (key, size) in staged.overlay.iter() {
+                match staged.snapshot.get_file(key) {
+                    Some(entry) if *size > 0 => count_text(entry),
+                    _ => {}
+                }
+            }
+            paths.extend(staged.snapshot.iter().map(|(key, _)| key));
+        }
+        report.paths = paths.len();
+        report.path_bytes = paths.iter().map(|key| key.as_str().len() as u64).sum();
+
+        let staged_index = staged.as_ref().map(|s| &*s.snapshot);
+        for ((key, hash), line_index) in self.line_index_cache.read().iter() {
+            report.line_index_entries += 1;
+            report.line_index_bytes += std::mem::size_of_val(line_index.line_starts()) as u64;
+            if !line_index_is_live(key, *hash, line_index, &active, staged_index) {
+                report.stale_line_index_entries += 1;
+            }
+        }
+        report
+    }
+
+    /// Drop data that can be derived again: search text identical to the
+    /// loaded bytes, and line indexes for content no longer in either index.
+    ///
+    /// Searches and reads see the same content afterwards. Staging states
+    /// kept for undo hold on to their copies until the undo history is
+    /// cleared.
+    pub fn compact(&self) -> CompactReport {
+        let mut staged = self.staged.lock();
+        let mut report = CompactReport::default();
+
+        let mut active = Index::clone(&self.active.load());
+        let freed = active.compact();
+        if freed > 0 {
+            report.text_bytes_freed += freed;
+            self.active.store(Arc::new(active));
+        }
+
+        if let Some(staged) = staged.as_mut() {
+            if staged.snapshot.iter().any(|(_, e)| e.has_redundant_text()) {
+                let idx = Arc::make_mut(&mut staged.snapshot);
+                idx.compact();
+
+                // Recount what staging holds beyond the active index.
+                let active = self.active.load();
+                let mut overlay_bytes = 0;
+                for (key, size) in staged.overlay.iter_mut() {
+                    if *size == 0 {
+                        continue;
+                    }
+                    let Some(entry) = staged.snapshot.get_file(key) else {
+                        continue;
+                    };
+                    let resized = match active.get_file(key) {
+                        Some(active) if active.shares_content(entry) => 0,
+                        _ => entry.resident_bytes(),
+                    };
+                    report.text_bytes_freed += *size - resized;
+                    *size = resized;
+                    overlay_bytes += resized;
+                }
+                staged.overlay_bytes = overlay_bytes;
+            }
+        }
+
+        let active = self.active.load();
+        let staged_index = staged.as_ref().map(|s| &*s.snapshot);
+        let mut cache = self.line_index_cache.write();
+        let before = cache.len();
+        cache.retain(|(key, hash), line_index| {
+            line_index_is_live(key, *hash, line_index, &active, staged_index)
+        });
+        report.line_indexes_dropped = before - cache.len();
+        report
+    }
+
+    /// Whether `key` is indexed without content, in the staged index while
+    /// staging and the active one otherwise. A hydrated path is marked as
+    /// just used.
+    pub fn needs_content(&self, key: &PathKey) -> bool {
+        let current = match self.staged.lock().as_ref() {
+            Some(staged) => staged.snapshot.clone(),
+            None => self.active_index(),
+        };
+        match current.get_file(key) {
+            Some(entry) if entry.bytes().is_none() => true,
+            Some(_) => {
+                self.hydrated.lock().touch(key);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Give the metadata-only entry at `key` the content fetched for it,
+    /// then evict the least recently hydrated files over the budget.
+    ///
+    /// Fills the active index, and the staged one unless staging has
+    /// written `key`. Returns `false`, changing nothing, if `key` is not
+    /// indexed or already has content. Fails with
+    /// [`Error::MemoryPressure`] past the memory ceiling.
+    pub fn hydrate(&self, key: &PathKey, bytes: Arc<[u8]>) -> Result<bool> {
+        let mut staged = self.staged.lock();
+        let mut active = Index::clone(&self.active.load());
+        let staged_fill = staged.as_ref().is_some_and(|s| {
+            !s.modified.contains(key)
+                && s.snapshot
+                    .get_file(key)
+                    .is_some_and(|e| e.bytes().is_none())
+        });
+        let active_fill = active.get_file(key).is_some_and(|e| e.bytes().is_none());
+        if !active_fill && !staged_fill {
+            return Ok(false);
+        }
+        self.usage_locked(staged.as_ref())
+            .check(bytes.len() as u64)?;
+
+        if active_fill {
+            active.fill_content(key, bytes.clone());
+        }
+        if let Some(staged) = staged.as_mut().filter(|_| staged_fill) {
+            Arc::make_mut(&mut staged.snapshot).fill_content(key, bytes.clone());
+        }
+
+        let evicted = {
+            let mut hydrated = self.hydrated.lock();
+            hydrated.insert(key.clone(), bytes.len() as u64);
+            hydrated.evict_over_budget()
+        };
+        release_evicted(&evicted, &mut active, staged.as_mut());
+        self.active.store(Arc::new(active));
+        self.advance();
+        Ok(true)
+    }
+
+    /// Run `f` with every file hydrated during it kept from eviction, so a
+    /// batch larger than the budget is all in memory when `f` returns.
+    /// Eviction resumes with the next hydration or budget change.
+    pub fn with_hydration_pinned<T>(&self, f: impl FnOnce() -> T) -> T {
+        self.hydrated.lock().pin();
+        let out = f();
+        self.hydrated.lock().unpin();
+        out
+    }
+
+    /// Limit the bytes of hydrated content kept, evicting the least
+    /// recently used files now if they exceed it.
+    pub fn set_hydration_budget(&self, budget: u64) {
+        let mut staged = self.staged.lock();
+        let evicted = {
+            let mut hydrated = self.hydrated.lock();
+            hydrated.set_budget(budget);
+            hydrated.evict_over_budget()
+        };
+        if evicted.is_empty() {
+            return;
+        }
+        let mut active = Index::clone(&self.active.load());
+        release_evicted(&evicted, &mut active, staged.as_mut());
+        self.active.store(Arc::new(active));
+        self.advance();
+    }
+
+    /// Hydrated content held, and the budget.
+    pub fn hydration_usage(&self) -> HydrationUsage {
+        self.hydrated.lock().usage()
+    }
+
+    /// Counter that moves whenever the staged or active index changes.
+    ///
+    /// Two reads reporting the same generation saw the same index state.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    fn advance(&self) -> u64 {
+        self.generation.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Edit epoch of `key`: changes whenever its content in the staged or
+    /// active index may have changed, and never repeats. `0` if the path
+    /// has not been written since startup.
+    ///
+    /// Hosts caching data derived from a file can key it by epoch and
+    /// recompute only when the epoch moves.
+    pub fn edit_epoch(&self, key: &PathKey) -> u64 {
+        self.epochs.read().get(key).copied().unwrap_or(0)
+    }
+
+    /// Send every [`IndexEvent`] to `listener` from now on; `None` stops.
+    pub fn set_event_listener(&self, listener: Option<EventListener>) {
+        let mut events = self.events.lock();
+        events.listener = listener;
+        events.pending.clear();
+    }
+
+    fn emit(&self, event: IndexEvent) {
+        let mut events = self.events.lock();
+        if events.listener.is_some() && events.muted == 0 {
+            events.pending.push(event);
+        }
+    }
+
+    /// Hold events back until the returned guard, and any taken before it,
+    /// is dropped. Take it before any lock, so it is dropped after them.
+    fn hold_events(&self) -> HeldEvents<'_> {
+        let mut events = self.events.lock();
+        events.held += 1;
+        HeldEvents {
+            manager: self,
+            mark: events.pending.len(),
+        }
+    }
+
+    /// Stop recording events until the returned guard is dropped.
+    fn mute_events(&self) -> MutedEvents<'_> {
+        self.events.lock().muted += 1;
+        MutedEvents { manager: self }
+    }
+
+    /// Send pending events unless something still holds them, with no lock
+    /// held so the listener may call back into the manager.
+    fn release_events(&self) {
+        let ready = {
+            let mut events = self.events.lock();
+            events.held -= 1;
+            match &events.listener {
+                Some(listener) if events.held == 0 && !events.pending.is_empty() => {
+                    Some((listener.clone(), std::mem::take(&mut events.pending)))
+                }
+                _ => None,
+            }
+        };
+        if let Some((listener, pending)) = ready {
+            for event in &pending {
+                listener(event);
+            }
+        }
+    }
+
+    fn bump_epochs<'a>(&self, keys: impl IntoIterator<Item = &'a PathKey>) {
+        let mut epochs = self.epochs.write();
+        for key in keys {
+            let epoch = self.epoch_clock.fetch_add(1, Ordering::Relaxed) + 1;
+            epochs.insert(key.clone(), epoch);
+        }
+    }
+
+    /// Staged index and modified paths, read together (fails if not staging).
+    ///
+    /// Prefer this over separate `staged_index` and `get_staged_*` calls
+    /// when several figures must describe the same state.
+    pub fn staged_view(&self) -> Result<StagedView> {
+        let g = self.staged.lock();
+        let staged = g.as_ref().ok_or(Error::StagingNotActive)?;
+        Ok(StagedView {
+            index: staged.snapshot.clone(),
+            modified: staged.modified.clone(),
+            generation: self.generation(),
+        })
+    }
+
     /// Get staged index snapshot (fails if not staging).
     ///
     /// This is a cheap Arc clone, safe to hold across operations.
@@ -214,6 +1139,56 @@ impl IndexManager {
             .map(|s| s.snapshot)
     }
 
+    /// Index to search or read for `space`.
+    ///
+    /// `Active` and `Staged` are cheap snapshots; the virtual spaces build a
+    /// view from the set of paths touched in staging.
+    pub fn index_for(&self, space: SearchSpace) -> Result<Arc<Index>> {
+        if space == SearchSpace::Active {
+            return Ok(self.active_index());
+        }
+
+        let g = self.staged.lock();
+        let staged = g.as_ref().ok_or(Error::StagingNotActive)?;
+        let active = self.active.load();
+
+        let view = match space {
+            SearchSpace::Active => unreachable!(),
+            SearchSpace::Staged => return Ok(staged.snapshot.clone()),
+            SearchSpace::StagedOnly => {
+                let mut view = Index::default();
+                for path in &staged.modified {
+                    if let Some(entry) = staged.snapshot.get_file(path) {
+                        view.upsert_file(path.clone(), entry.clone())?;
+                    }
+                }
+                view
+            }
+            SearchSpace::ActiveMinusStaged => {
+                let mut view = Index::default();
+                for path in &staged.modified {
+                    if let Some(entry) = active.get_file(path) {
+                        view.upsert_file(path.clone(), entry.clone())?;
+                    }
+                }
+                view
+            }
+            SearchSpace::Union => {
+                let mut view = (*staged.snapshot).clone();
+                for path in &staged.modified {
+                    if staged.snapshot.get_file(path).is_none() {
+                        if let Some(entry) = active.get_file(path) {
+                            view.upsert_file(path.clone(), entry.clone())?;
+                        }
+                    }
+                }
+                view
+            }
+        };
+
+        Ok(Arc::new(view))
+    }
+
     /// Bulk load files into the index.
     ///
     /// This method:
@@ -224,6 +1199,10 @@ impl IndexManager {
     /// This is designed for initial file loading. It replaces the entire
     /// index with the provided files.
     pub fn load_files(&self, files: Vec<(PathKey, FileEntry)>) -> Result<()> {
+        let _events = self.hold_events();
+        let muted = self.mute_events();
+        let count = files.len();
+
         // Clear any existing staging and start fresh
         {
             let mut g = self.staged.lock();
@@ -237,6 +1216,8 @@ impl IndexManager {
 
         self.promote_staged()?;
 
+        drop(muted);
+        self.emit(IndexEvent::FilesLoaded { count });
         Ok(())
     }
 
@@ -250,42 +1231,30 @@ impl IndexManager {
             return Err(Error::StagingNotActive);
         }
 
+        let _events = self.hold_events();
+        let muted = self.mute_events();
+        let count = files.len();
         for (key, entry) in files {
             self.stage_file(key, entry)?;
         }
 
+        drop(muted);
+        self.emit(IndexEvent::FilesLoaded { count });
         Ok(())
     }
 
     /// Get modified files from staging with their content.
     pub fn get_staged_modifications(&self) -> Result<Vec<(PathKey, Vec<u8>)>> {
-        let g = self.staged.lock();
-        let staged = g.as_ref().ok_or(Error::StagingNotActive)?;
-
-        Ok(staged
-            .modified
-            .iter()
-            .filter_map(|path| {
-                staged
-                    .snapshot
-                    .get_file(path)
-                    .and_then(|entry| entry.bytes())
-                    .map(|bytes| (path.clone(), bytes.to_vec()))
-            })
+        Ok(self
+            .staged_view()?
+            .modifications()
+            .map(|(path, bytes)| (path.clone(), bytes.to_vec()))
             .collect())
     }
 
     /// Get paths that were removed in staging.
     pub fn get_staged_deletions(&self) -> Result<Vec<PathKey>> {
-        let g = self.staged.lock();
-        let staged = g.as_ref().ok_or(Error::StagingNotActive)?;
-
-        Ok(staged
-            .modified
-            .iter()
-            .filter(|path| staged.snapshot.get_file(path).is_none())
-            .cloned()
-            .collect())
+        Ok(self.staged_view()?.deletions().cloned().collect())
     }
 
     /// Get change statistics for all modified files
@@ -313,10 +1282,9 @@ impl IndexManager {
         let entry = index.get_file(path)?;
         // Use search_content() to match what handle_read uses
         let content = entry.search_content()?;
-        let mtime = entry.mtime();
 
         // Check cache first
-        let cache_key = (path.clone(), mtime);
+        let cache_key = line_index_key(path, content);
         {
             let cache = self.line_index_cache.read();
             if let Some(line_index) = cache.get(&cache_key) {
@@ -335,6 +1303,40 @@ impl IndexManager {
         Some(line_index)
     }
 
+    /// Cached line index for `entry`, without building one on a miss.
+    ///
+    /// For scans over many files, where caching every index would cost more
+    /// memory than it saves; hinted files are the ones worth keeping.
+    pub fn cached_line_index(&self, path: &PathKey, entry: &FileEntry) -> Option<Arc<LineIndex>> {
+        let content = entry.search_content()?;
+        let cache = self.line_index_cache.read();
+        // Skip hashing the content when there is nothing to find.
+        if cache.is_empty() {
+            return None;
+        }
+        let cached = cache.get(&line_index_key(path, content)).cloned()?;
+        (cached.total_bytes() == content.len()).then_some(cached)
+    }
+
+    /// Build the caches the operation `intent` will need on `key`.
+    ///
+    /// Uses the staged index while staging, else the active one. Returns
+    /// `false` if the path is not indexed.
+    pub fn warm(&self, key: &PathKey, intent: HintIntent) -> Result<bool> {
+        let active = self.active_index();
+        let current = match self.staged_index() {
+            Ok(staged) => staged,
+            Err(Error::StagingNotActive) => active.clone(),
+            Err(e) => return Err(e),
+        };
+
+        let found = self.get_line_index(key, &current).is_some();
+        if intent == HintIntent::WillEdit {
+            self.get_line_index(key, &active);
+        }
+        Ok(found)
+    }
+
     /// Get move operations from staging
     pub fn get_staged_moves(&self) -> Result<im::HashMap<PathKey, PathKey>> {
         let g = self.staged.lock();
@@ -342,7 +1344,7 @@ impl IndexManager {
         Ok(staged.moves.clone())
     }
 
-    /// Clear line index cache (e.g., when promoting staged changes)
+    /// Clear line index cache (e.g., before loading a new file set)
     pub fn clear_line_index_cache(&self) {
         let mut cache = self.line_index_cache.write();
         cache.clear();
@@ -354,21 +1356,431 @@ impl IndexManager {
 
     pub fn restore_staging(&self, snapshot: Option<StagingState>) -> Result<()> {
         *self.staged.lock() = snapshot;
+        self.advance();
         Ok(())
     }
 
     /// Execute a function with automatic snapshot rollback on error.
+    ///
+    /// Events are sent once the outermost call returns; those of a failed
+    /// `f` are dropped with its changes.
     pub fn with_snapshot<T>(&self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        let events = self.hold_events();
         let snapshot = self.snapshot_staging()?;
+        let mark = self.epoch_clock.load(Ordering::Relaxed);
         match f() {
             Ok(result) => Ok(result),
             Err(e) => {
+                events.discard();
                 self.restore_staging(snapshot)?;
+                // Paths touched by the failed attempt are back to older content.
+                let touched: Vec<PathKey> = self
+                    .epochs
+                    .read()
+                    .iter()
+                    .filter(|(_, &epoch)| epoch > mark)
+                    .map(|(key, _)| key.clone())
+                    .collect();
+                self.bump_epochs(&touched);
                 Err(e)
             }
         }
     }
 
+    /// Run `f` like [`IndexManager::with_snapshot`] and, if it succeeds and
+    /// changes staging, record it as one undo step named `label`.
+    ///
+    /// Nested calls fold into the outermost one, so an operation built from
+    /// smaller ones is undone as a whole. Recording a step clears redo.
+    /// Under [`StagingMode::Auto`] staging is begun first.
+    pub fn with_undo_step<T>(&self, label: &str, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        let _events = self.hold_events();
+        self.ensure_staging()?;
+        let outermost = {
+            let mut journal = self.journal.lock();
+            journal.depth += 1;
+            journal.depth == 1
+        };
+        let before = if outermost {
+            self.snapshot_staging().ok().flatten()
+        } else {
+            None
+        };
+        let generation = self.generation();
+
+        let result = self.with_snapshot(f);
+
+        let mut journal = self.journal.lock();
+        journal.depth -= 1;
+        if let (Ok(_), Some(state)) = (&result, before) {
+            if self.generation() != generation {
+                journal.undo.push(UndoStep {
+                    label: label.to_string(),
+                    state,
+                });
+                if journal.undo.len() > UNDO_LIMIT {
+                    journal.undo.remove(0);
+                }
+                journal.redo.clear();
+            }
+        }
+        result
+    }
+
+    /// Return staging to how it was before the last recorded operation.
+    /// Returns that operation's label, or `None` if there is nothing to undo.
+    pub fn undo(&self) -> Result<Option<String>> {
+        let _events = self.hold_events();
+        let g = self.staged.lock();
+        let mut journal = self.journal.lock();
+        let Journal { undo, redo, .. } = &mut *journal;
+        self.step_journal(g, undo, redo)
+    }
+
+    /// Reapply the last undone operation; returns its label, or `None`.
+    pub fn redo(&self) -> Result<Option<String>> {
+        let _events = self.hold_events();
+        let g = self.staged.lock();
+        let mut journal = self.journal.lock();
+        let Journal { undo, redo, .. } = &mut *journal;
+        self.step_journal(g, redo, undo)
+    }
+
+    /// Operations that can currently be undone and redone.
+    pub fn undo_state(&self) -> UndoState {
+        let journal = self.journal.lock();
+        let labels = |steps: &[UndoStep]| steps.iter().map(|s| s.label.clone()).collect();
+        UndoState {
+            undo: labels(&journal.undo),
+            redo: labels(&journal.redo),
+        }
+    }
+
+    /// Save the current staging state as `name`, replacing any checkpoint of
+    /// that name. Returns whether one was replaced.
+    ///
+    /// Checkpoints share structure with live staging, so they are cheap;
+    /// they last until staging is committed, reverted or restarted.
+    pub fn create_checkpoint(&self, name: &str) -> Result<bool> {
+        let state = self.staged.lock().clone().ok_or(Error::StagingNotActive)?;
+        let mut journal = self.journal.lock();
+        let before = journal.checkpoints.len();
+        journal.checkpoints.retain(|(n, _)| n != name);
+        let replaced = journal.checkpoints.len() != before;
+        journal.checkpoints.push((name.to_string(), state));
+        Ok(replaced)
+    }
+
+    /// Return staging to the checkpoint `name`, as one undo step.
+    pub fn restore_checkpoint(&self, name: &str) -> Result<()> {
+        let _events = self.hold_events();
+        let mut g = self.staged.lock();
+        let mut journal = self.journal.lock();
+        let state = journal.checkpoint(name)?.clone();
+        let current = g.as_ref().ok_or(Error::StagingNotActive)?.clone();
+
+        let touched: Vec<PathKey> = current
+            .modified
+            .clone()
+            .union(state.modified.clone())
+            .into_iter()
+            .collect();
+        *g = Some(state);
+        drop(g);
+        self.bump_epochs(&touched);
+        self.advance();
+        self.emit(IndexEvent::StagingRestored);
+
+        journal.undo.push(UndoStep {
+            label: format!("restore checkpoint {name}"),
+            state: current,
+        });
+        if journal.undo.len() > UNDO_LIMIT {
+            journal.undo.remove(0);
+        }
+        journal.redo.clear();
+        Ok(())
+    }
+
+    /// Remove the checkpoint `name`; returns whether it existed.
+    pub fn remove_checkpoint(&self, name: &str) -> bool {
+        let mut journal = self.journal.lock();
+        let before = journal.checkpoints.len();
+        journal.checkpoints.retain(|(n, _)| n != name);
+        journal.checkpoints.len() != before
+    }
+
+    /// Checkpoints of this staging session, oldest first.
+    pub fn list_checkpoints(&self) -> Vec<CheckpointInfo> {
+        self.journal
+            .lock()
+            .checkpoints
+            .iter()
+            .map(|(name, state)| CheckpointInfo {
+                name: name.clone(),
+                modified_files: state.modified.len(),
+            })
+            .collect()
+    }
+
+    /// Line diffs of every file that differs between checkpoint `from` and
+    /// checkpoint `to`, or current staging when `to` is `None`, in path
+    /// order. A file missing on one side diffs against empty content.
+    pub fn diff_checkpoints(
+        &self,
+        from: &str,
+        to: Option<&str>,
+        context_lines: usize,
+    ) -> Result<Vec<FileDiff>> {
+        let (old, new) = {
+            let staged = self.staged.lock();
+            let journal = self.journal.lock();
+            let old = journal.checkpoint(from)?.clone();
+            let new = match to {
+                Some(name) => journal.checkpoint(name)?.clone(),
+                None => staged.clone().ok_or(Error::StagingNotActive)?,
+            };
+            (old, new)
+        };
+
+        // Files neither side modified are as staging began in both.
+        let text = |entry: Option<&FileEntry>| {
+            entry
+                .and_then(FileEntry::search_content)
+                .map(|bytes| String::from_utf8_lossy(bytes).into_owned())
+                .unwrap_or_default()
+        };
+        let mut diffs = Vec::new();
+        for path in old.modified.clone().union(new.modified.clone()) {
+            let (a, b) = (old.snapshot.get_file(&path), new.snapshot.get_file(&path));
+            let same = match (a, b) {
+                (Some(a), Some(b)) => a.search_content() == b.search_content(),
+                (None, None) => true,
+                _ => false,
+            };
+            if !same {
+                diffs.push(compute_diff(path, &text(a), &text(b), context_lines));
+            }
+        }
+        Ok(diffs)
+    }
+
+    /// Record the active index by reference and staging in full, so the
+    /// session can be resumed with [`IndexManager::import_session`] after
+    /// the host reloads the same active files.
+    ///
+    /// Undo history and checkpoints are not included.
+    pub fn export_session(&self) -> Session {
+        let g = self.staged.lock();
+        let active = self.active.load_full();
+        let active_refs = active
+            .iter_sorted()
+            .map(|(path, entry)| ActiveRef {
+                path: path.clone(),
+                size: entry.size(),
+                mtime: entry.mtime(),
+                hash: entry.content_hash(),
+            })
+            .collect();
+
+        let staging = g.as_ref().map(|staged| {
+            let mut record = StagingRecord {
+                owner: staged.owner.clone(),
+                ..StagingRecord::default()
+            };
+            for path in &staged.modified {
+                match staged.snapshot.get_file(path) {
+                    Some(entry) => record.files.push(SessionFile {
+                        path: path.clone(),
+                        mtime: entry.mtime(),
+                        editable: entry.is_editable(),
+                        hidden: entry.is_host_hidden(),
+                        mime_type: entry.mime_type().map(str::to_string),
+                        content: entry.bytes().map(SessionContent::from_bytes),
+                        size: entry.size(),
+                    }),
+                    None => record.deleted.push(path.clone()),
+                }
+            }
+            record.moves = sorted_by_path(staged.moves.clone().into_iter().collect());
+            record.change_stats = sorted_by_path(staged.change_stats.clone().into_iter().collect());
+            record.history = sorted_by_path(
+                staged
+                    .history
+                    .iter()
+                    .map(|(path, ops)| (path.clone(), ops.iter().copied().collect()))
+                    .collect(),
+            );
+            record.needs_read = staged.needs_read.iter().cloned().collect();
+            record.needs_read.sort();
+            record
+        });
+
+        Session {
+            schema_version: SCHEMA_VERSION,
+            active: active_refs,
+            staging,
+        }
+    }
+
+    /// Replace staging with the one recorded in `session`.
+    ///
+    /// Fails without changing anything if a path in the session is not
+    /// normalized, or a file it references is missing from the active index
+    /// or has changed. Files added to the active index since the export are
+    /// kept. Clears undo history and checkpoints.
+    pub fn import_session(&self, session: Session) -> Result<()> {
+        session.check_paths()?;
+        let _events = self.hold_events();
+        let active = self.active.load_full();
+        for file in &session.active {
+            let entry = active
+                .get_file(&file.path)
+                .ok_or_else(|| Error::FileNotFound(file.path.as_str().to_string()))?;
+            let unchanged = match (file.hash, entry.content_hash()) {
+                (Some(expected), Some(actual)) => expected == actual,
+                _ => file.size == entry.size() && file.mtime == entry.mtime(),
+            };
+            if !unchanged {
+                let show = |h: Option<u64>| h.map_or("none".to_string(), |h| format!("{h:016x}"));
+                return Err(Error::ContentChanged {
+                    path: file.path.as_str().to_string(),
+                    expected: show(file.hash),
+                    actual: show(entry.content_hash()),
+                });
+            }
+        }
+
+        let state = match session.staging {
+            Some(record) => Some(self.rebuild_staging(&active, record)?),
+            None => None,
+        };
+
+        let mut g = self.staged.lock();
+        let mut touched: IOrdSet<PathKey> =
+            g.as_ref().map(|s| s.modified.clone()).unwrap_or_default();
+        if let Some(state) = &state {
+            touched = touched.union(state.modified.clone());
+        }
+        *g = state;
+        drop(g);
+        self.journal.lock().clear();
+        self.bump_epochs(&touched);
+        self.advance();
+        self.emit(IndexEvent::StagingRestored);
+        Ok(())
+    }
+
+    /// Staging state from `record`, on top of `active`.
+    fn rebuild_staging(&self, active: &Arc<Index>, record: StagingRecord) -> Result<StagingState> {
+        let mut snapshot = Index::clone(active);
+        let mut modified = IOrdSet::new();
+        let mut overlay = im::HashMap::new();
+        let mut overlay_bytes = 0;
+        let moved_from: HashMap<&PathKey, &PathKey> =
+            record.moves.iter().map(|(src, dst)| (dst, src)).collect();
+
+        for file in record.files {
+            let ext = FileEntry::get_extension(file.path.as_str());
+            // Share content with the active file it came from, as staging
+            // did before the export.
+            let origin = active.get_file(moved_from.get(&file.path).copied().unwrap_or(&file.path));
+            let mut entry = match file.content {
+                Some(content) => {
+                    let bytes = content.into_bytes();
+                    match origin {
+                        Some(original)
+                            if original.bytes() == Some(&bytes[..])
+                                && original.is_editable() == file.editable =>
+                        {
+                            original.clone()
+                        }
+                        _ => FileEntry::from_bytes_with_mime(
+                            ext,
+                            file.mime_type,
+                            file.mtime,
+                            Arc::from(bytes),
+                            file.editable,
+                        ),
+                    }
+                }
+                None => match file.mime_type {
+                    Some(mime) => {
+                        FileEntry::new_with_mime(ext, mime, file.size, file.mtime, file.editable)
+                    }
+                    None => FileEntry::new(ext, file.size, file.mtime, file.editable),
+                },
+            };
+            entry.set_modified(file.mtime);
+            entry.set_hidden(file.hidden);
+
+            let size = match origin {
+                Some(original) if original.shares_content(&entry) => 0,
+                _ => entry.resident_bytes(),
+            };
+            overlay_bytes += size;
+            overlay.insert(file.path.clone(), size);
+            modified.insert(file.path.clone());
+            snapshot.upsert_file(file.path, entry)?;
+        }
+        for path in record.deleted {
+            snapshot.remove_file(&path)?;
+            modified.insert(path);
+        }
+        self.usage_locked(None).check(overlay_bytes)?;
+
+        Ok(StagingState {
+            snapshot: Arc::new(snapshot),
+            modified,
+            change_stats: record.change_stats.into_iter().collect(),
+            moves: record.moves.into_iter().collect(),
+            needs_read: record.needs_read.into_iter().collect(),
+            owner: record.owner,
+            history: record
+                .history
+                .into_iter()
+                .map(|(path, ops)| (path, ops.into_iter().collect()))
+                .collect(),
+            overlay,
+            overlay_bytes,
+        })
+    }
+
+    /// Restore the newest state in `from`, saving the current one to `to`.
+    ///
+    /// Takes the staging guard from the caller, which locks staging before
+    /// the journal as every path that holds both does.
+    fn step_journal(
+        &self,
+        mut g: MutexGuard<'_, Option<StagingState>>,
+        from: &mut Vec<UndoStep>,
+        to: &mut Vec<UndoStep>,
+    ) -> Result<Option<String>> {
+        let current = g.as_ref().ok_or(Error::StagingNotActive)?.clone();
+        let Some(step) = from.pop() else {
+            return Ok(None);
+        };
+
+        // Paths modified in either state may change content.
+        let touched: Vec<PathKey> = current
+            .modified
+            .clone()
+            .union(step.state.modified.clone())
+            .into_iter()
+            .collect();
+        *g = Some(step.state);
+        drop(g);
+        self.bump_epochs(&touched);
+        self.advance();
+        self.emit(IndexEvent::StagingRestored);
+
+        to.push(UndoStep {
+            label: step.label.clone(),
+            state: current,
+        });
+        Ok(Some(step.label))
+    }
+
     /// Mark a file as needing to be read before line-based edits.
     pub fn mark_needs_read(&self, key: &PathKey) -> Result<()> {
         let mut g = self.staged.lock();
@@ -404,3 +1816,960 @@ impl IndexManager {
         Ok(())
     }
 }
+
+/// Return evicted files to metadata only, leaving staged edits alone.
+fn release_evicted(evicted: &[PathKey], active: &mut Index, staged: Option<&mut StagingState>) {
+    for key in evicted {
+        active.release_content(key);
+    }
+    if let Some(staged) = staged {
+        let keep: Vec<&PathKey> = evicted
+            .iter()
+            .filter(|key| !staged.modified.contains(*key))
+            .collect();
+        if !keep.is_empty() {
+            let idx = Arc::make_mut(&mut staged.snapshot);
+            for key in keep {
+                idx.release_content(key);
+            }
+        }
+    }
+}
+
+/// Whether a cached line index still describes content at `key` in the
+/// active or staged index.
+fn line_index_is_live(
+    key: &PathKey,
+    hash: u64,
+    line_index: &LineIndex,
+    active: &Index,
+    staged: Option<&Index>,
+) -> bool {
+    [Some(active), staged].into_iter().flatten().any(|index| {
+        index
+            .get_file(key)
+            .and_then(FileEntry::search_content)
+            .is_some_and(|c| c.len() == line_index.total_bytes() && xxh3_64(c) == hash)
+    })
+}
+
+/// Cache key for the line index of `content` at `key`.
+fn line_index_key(key: &PathKey, content: &[u8]) -> (PathKey, u64) {
+    (key.clone(), xxh3_64(content))
+}
+
+/// `items` in path order, for exports that should not depend on hashing.
+fn sorted_by_path<T>(mut items: Vec<(PathKey, T)>) -> Vec<(PathKey, T)> {
+    items.sort_by(|a, b| a.0.cmp(&b.0));
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(p: &str) -> PathKey {
+        PathKey::from_arc(Arc::from(p))
+    }
+
+    fn entry(content: &str) -> FileEntry {
+        FileEntry::from_bytes("txt", 0, Arc::from(content.as_bytes()), true)
+    }
+
+    fn paths(index: &Index) -> Vec<&str> {
+        index.iter_sorted().map(|(p, _)| p.as_str()).collect()
+    }
+
+    #[test]
+    fn test_paths_under_respects_directory_boundaries() {
+        let mut index = Index::default();
+        for p in ["src/a/x.rs", "src/a/b/y.rs", "src/ab/z.rs", "src/a.rs"] {
+            index.upsert_file(key(p), entry("")).unwrap();
+        }
+        let under = |index: &Index, dir: &str| -> Vec<String> {
+            index
+                .paths_under(dir)
+                .into_iter()
+                .map(String::from)
+                .collect()
+        };
+        assert_eq!(under(&index, "src/a/"), vec!["src/a/b/y.rs", "src/a/x.rs"]);
+        assert_eq!(under(&index, "src/a"), under(&index, "src/a/"));
+        assert_eq!(under(&index, "").len(), 4);
+
+        index.remove_file(&key("src/a/x.rs")).unwrap();
+        assert_eq!(under(&index, "src/a"), vec!["src/a/b/y.rs"]);
+    }
+
+    #[test]
+    fn test_checkpoints_restore_and_diff() {
+        let manager = IndexManager::default();
+        manager
+            .load_files(vec![(key("a.txt"), entry("one\n"))])
+            .unwrap();
+        assert!(matches!(
+            manager.create_checkpoint("start"),
+            Err(Error::StagingNotActive)
+        ));
+        manager.begin_staging().unwrap();
+        let content = |path: &str| {
+            manager
+                .staged_index()
+                .unwrap()
+                .get_file(&key(path))
+                .and_then(|f| f.bytes().map(|b| b.to_vec()))
+        };
+
+        assert!(!manager.create_checkpoint("start").unwrap());
+        manager.stage_file(key("a.txt"), entry("two\n")).unwrap();
+        manager.stage_file(key("b.txt"), entry("b\n")).unwrap();
+        assert!(!manager.create_checkpoint("refactor").unwrap());
+        manager.remove_staged_file(&key("b.txt")).unwrap();
+
+        assert_eq!(
+            manager.list_checkpoints(),
+            vec![
+                CheckpointInfo {
+                    name: "start".into(),
+                    modified_files: 0,
+                },
+                CheckpointInfo {
+                    name: "refactor".into(),
+                    modified_files: 2,
+                },
+            ]
+        );
+
+        let diffs = manager
+            .diff_checkpoints("start", Some("refactor"), 0)
+            .unwrap();
+        let changed: Vec<_> = diffs.iter().map(|d| d.path.as_str()).collect();
+        assert_eq!(changed, ["a.txt", "b.txt"]);
+        assert_eq!(diffs[0].regions[0].removed_lines, ["one"]);
+        assert_eq!(diffs[0].regions[0].added_lines, ["two"]);
+        let diffs = manager.diff_checkpoints("refactor", None, 0).unwrap();
+        assert_eq!(diffs.len(), 1);
+        assert_eq!(diffs[0].regions[0].removed_lines, ["b"]);
+        assert!(matches!(
+            manager.diff_checkpoints("nope", None, 0),
+            Err(Error::UnknownCheckpoint(_))
+        ));
+
+        manager.restore_checkpoint("refactor").unwrap();
+        assert_eq!(content("b.txt").unwrap(), b"b\n");
+        manager.restore_checkpoint("start").unwrap();
+        assert_eq!(content("a.txt").unwrap(), b"one\n");
+        assert!(content("b.txt").is_none());
+        assert!(!manager.is_staged_modified(&key("a.txt")));
+
+        // Restores undo like any other operation.
+        assert_eq!(
+            manager.undo().unwrap().as_deref(),
+            Some("restore checkpoint start")
+        );
+        assert_eq!(content("a.txt").unwrap(), b"two\n");
+
+        assert!(manager.remove_checkpoint("start"));
+        assert!(!manager.remove_checkpoint("start"));
+        manager.promote_staged().unwrap();
+        assert!(manager.list_checkpoints().is_empty());
+    }
+
+    #[test]
+    fn test_session_export_import_round_trip() {
+        let files = || {
+            vec![
+                (key("a.txt"), entry("one\n")),
+                (key("b.txt"), entry("bee\n")),
+                (key("c.txt"), entry("sea\n")),
+            ]
+        };
+        let manager = IndexManager::default();
+        manager.load_files(files()).unwrap();
+        manager.begin_staging_as("agent").unwrap();
+        manager.stage_file(key("a.txt"), entry("two\n")).unwrap();
+        manager.update_line_stats(&key("a.txt"), 1, 1, 1).unwrap();
+        manager
+            .move_staged_file(&key("b.txt"), &key("d.txt"), 7)
+            .unwrap();
+        manager.remove_staged_file(&key("c.txt")).unwrap();
+        manager.clear_needs_read(&key("a.txt")).unwrap();
+        manager.create_checkpoint("x").unwrap();
+
+        let bytes = manager.export_session().to_bytes().unwrap();
+        let session = Session::from_bytes(&bytes).unwrap();
+
+        let resumed = IndexManager::default();
+        resumed.load_files(files()).unwrap();
+        resumed.import_session(session.clone()).unwrap();
+
+        let staged = resumed.staged_index().unwrap();
+        let content = |path: &str| staged.get_file(&key(path)).and_then(|f| f.bytes());
+        assert_eq!(content("a.txt"), Some(&b"two\n"[..]));
+        assert_eq!(content("d.txt"), Some(&b"bee\n"[..]));
+        assert!(content("b.txt").is_none() && content("c.txt").is_none());
+        assert_eq!(staged.get_file(&key("d.txt")).unwrap().mtime(), 7);
+        assert_eq!(resumed.staging_owner().unwrap().as_deref(), Some("agent"));
+        assert_eq!(
+            resumed.get_staged_moves().unwrap(),
+            manager.get_staged_moves().unwrap()
+        );
+        assert_eq!(
+            resumed.get_file_change_stats(&key("a.txt")).unwrap(),
+            manager.get_file_change_stats(&key("a.txt")).unwrap()
+        );
+        assert_eq!(
+            resumed.staged_history(&key("d.txt")).unwrap(),
+            vec![StagedOp::MoveIn]
+        );
+        assert!(!resumed.check_needs_read(&key("a.txt")).unwrap());
+        assert!(resumed.check_needs_read(&key("d.txt")).unwrap());
+        // The moved file still shares its active content.
+        assert_eq!(resumed.memory_usage(), manager.memory_usage());
+        assert_eq!(
+            serde_json::to_value(resumed.export_session()).unwrap(),
+            serde_json::to_value(&session).unwrap()
+        );
+        assert!(resumed.list_checkpoints().is_empty());
+
+        // Active files must be reloaded unchanged.
+        let changed = IndexManager::default();
+        changed
+            .load_files(vec![
+                (key("a.txt"), entry("one\n")),
+                (key("b.txt"), entry("changed\n")),
+                (key("c.txt"), entry("sea\n")),
+            ])
+            .unwrap();
+        let err = changed.import_session(session.clone()).unwrap_err();
+        assert_eq!(err.code(), "content_changed");
+        assert!(changed.staged_index().is_err());
+
+        assert_eq!(
+            Session::from_bytes(b"nope").unwrap_err().code(),
+            "invalid_format"
+        );
+
+        // Paths the index could never hold are refused before anything
+        // changes.
+        for bad in ["./a.txt", "x/../a.txt", "a//b.txt", ".."] {
+            let mut tampered = session.clone();
+            tampered.staging.as_mut().unwrap().deleted = vec![key(bad)];
+            let err = resumed.import_session(tampered).unwrap_err();
+            assert_eq!(err.code(), "invalid_path", "{bad}");
+        }
+        assert_eq!(
+            resumed
+                .staged_index()
+                .unwrap()
+                .get_file(&key("a.txt"))
+                .unwrap()
+                .bytes(),
+            Some(&b"two\n"[..])
+        );
+    }
+
+    #[test]
+    fn test_undo_redo_steps_through_operations() {
+        let manager = IndexManager::default();
+        manager
+            .load_files(vec![(key("a.txt"), entry("one"))])
+            .unwrap();
+        manager.begin_staging().unwrap();
+        let content = |path: &str| {
+            manager
+                .staged_index()
+                .unwrap()
+                .get_file(&key(path))
+                .and_then(|f| f.bytes().map(|b| b.to_vec()))
+        };
+
+        manager
+            .with_undo_step("edit a", || manager.stage_file(key("a.txt"), entry("two")))
+            .unwrap();
+        // Nested steps fold into the outer one.
+        manager
+            .with_undo_step("batch", || {
+                manager
+                    .with_undo_step("create b", || manager.stage_file(key("b.txt"), entry("b")))?;
+                manager.with_undo_step("delete a", || manager.remove_staged_file(&key("a.txt")))
+            })
+            .unwrap();
+        // Failed and no-op steps are not recorded.
+        let _ = manager.with_undo_step("fail", || -> Result<()> {
+            manager.stage_file(key("c.txt"), entry("c"))?;
+            Err(Error::Aborted)
+        });
+        manager.with_undo_step("noop", || Ok(())).unwrap();
+        assert_eq!(manager.undo_state().undo, vec!["edit a", "batch"]);
+
+        let epoch = manager.edit_epoch(&key("a.txt"));
+        assert_eq!(manager.undo().unwrap().as_deref(), Some("batch"));
+        assert_eq!(content("a.txt").unwrap(), b"two");
+        assert!(content("b.txt").is_none());
+        assert_ne!(manager.edit_epoch(&key("a.txt")), epoch);
+
+        assert_eq!(manager.undo().unwrap().as_deref(), Some("edit a"));
+        assert_eq!(content("a.txt").unwrap(), b"one");
+        assert!(!manager.is_staged_modified(&key("a.txt")));
+        assert_eq!(manager.undo().unwrap(), None);
+
+        assert_eq!(manager.redo().unwrap().as_deref(), Some("edit a"));
+        assert_eq!(content("a.txt").unwrap(), b"two");
+
+        // A new step drops what could have been redone.
+        manager
+            .with_undo_step("create d", || manager.stage_file(key("d.txt"), entry("d")))
+            .unwrap();
+        assert_eq!(
+            manager.undo_state(),
+            UndoState {
+                undo: vec!["edit a".into(), "create d".into()],
+                redo: vec![],
+            }
+        );
+
+        manager.promote_staged().unwrap();
+        assert_eq!(manager.undo_state(), UndoState::default());
+        assert!(matches!(manager.undo(), Err(Error::StagingNotActive)));
+    }
+
+    #[test]
+    fn test_memory_ceiling_counts_only_unshared_content() {
+        let manager = IndexManager::default();
+        manager
+            .load_files(vec![(key("a.txt"), entry("0123456789"))])
+            .unwrap();
+        manager.set_memory_ceiling(Some(25));
+        manager.begin_staging().unwrap();
+
+        // Moving shares the active content, so it costs nothing.
+        manager
+            .move_staged_file(&key("a.txt"), &key("b.txt"), 0)
+            .unwrap();
+        assert_eq!(manager.memory_usage().total(), 10);
+
+        manager
+            .stage_file(key("c.txt"), entry("0123456789"))
+            .unwrap();
+        let err = manager
+            .stage_file(key("d.txt"), entry("0123456"))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::MemoryPressure {
+                usage: 20,
+                requested: 7,
+                ceiling: 25
+            }
+        ));
+        assert!(manager
+            .staged_index()
+            .unwrap()
+            .get_file(&key("d.txt"))
+            .is_none());
+
+        // Shrinking a file and deleting one free room again.
+        manager.stage_file(key("c.txt"), entry("01")).unwrap();
+        manager.remove_staged_file(&key("b.txt")).unwrap();
+        assert_eq!(manager.memory_usage().staged_bytes, 2);
+        manager.stage_file(key("d.txt"), entry("0123456")).unwrap();
+
+        manager.promote_staged().unwrap();
+        let usage = manager.memory_usage();
+        assert_eq!((usage.active_bytes, usage.staged_bytes), (9, 0));
+    }
+
+    #[test]
+    fn test_compact_drops_duplicate_text_and_stale_line_indexes() {
+        let with_copy = |content: &str, mtime| {
+            FileEntry::from_bytes_with_text(
+                "txt",
+                mtime,
+                Arc::from(content.as_bytes()),
+                Arc::from(content.as_bytes()),
+                true,
+            )
+        };
+        let manager = IndexManager::default();
+        manager
+            .load_files(vec![
+                (key("a.txt"), with_copy("one\ntwo\n", 1)),
+                (key("b.txt"), entry("three\n")),
+            ])
+            .unwrap();
+        manager.get_line_index(&key("a.txt"), &manager.active_index());
+        manager.begin_staging().unwrap();
+        manager
+            .stage_file(key("a.txt"), with_copy("one\n", 2))
+            .unwrap();
+        manager
+            .stage_file(key("c.txt"), with_copy("four\n", 1))
+            .unwrap();
+        // An index for content that is gone from both indexes.
+        manager
+            .line_index_cache
+            .write()
+            .insert((key("gone.txt"), 1), Arc::new(LineIndex::build(b"x\n")));
+
+        let report = manager.memory_report();
+        assert_eq!(report.content_bytes, 16 + 6 + 8 + 10);
+        assert_eq!((report.text_bytes, report.duplicate_text_bytes), (17, 17));
+        assert_eq!(
+            (report.line_index_entries, report.stale_line_index_entries),
+            (3, 1)
+        );
+        assert_eq!((report.paths, report.path_bytes), (3, 15));
+
+        let compacted = manager.compact();
+        assert_eq!(compacted.text_bytes_freed, 17);
+        assert_eq!(compacted.line_indexes_dropped, 1);
+        let report = manager.memory_report();
+        assert_eq!(report.content_bytes, 8 + 6 + 4 + 5);
+        assert_eq!(report.text_bytes, 0);
+        assert_eq!(report.line_index_entries, 2);
+
+        let staged = manager.staged_index().unwrap();
+        let a = staged.get_file(&key("a.txt")).unwrap();
+        assert_eq!(a.search_content(), Some(&b"one\n"[..]));
+        assert_eq!(manager.compact(), CompactReport::default());
+    }
+
+    #[test]
+    fn test_copy_keeps_binary_content_and_metadata() {
+        let png: Arc<[u8]> = Arc::from(&b"\x89PNG\r\n\x1a\n\0\xff\xfe\x00"[..]);
+        let latin1: Arc<[u8]> = Arc::from(&b"caf\xe9\n"[..]);
+        let manager = IndexManager::default();
+        manager
+            .load_files(vec![
+                (
+                    key("logo.png"),
+                    FileEntry::from_bytes_with_mime(
+                        "png",
+                        Some("image/png".into()),
+                        0,
+                        png.clone(),
+                        false,
+                    ),
+                ),
+                (
+                    key("menu.txt"),
+                    FileEntry::from_bytes_with_text(
+                        "txt",
+                        0,
+                        latin1.clone(),
+                        Arc::from("café\n".as_bytes()),
+                        true,
+                    ),
+                ),
+            ])
+            .unwrap();
+        manager.begin_staging().unwrap();
+
+        manager
+            .copy_staged_file(&key("logo.png"), &key("img/logo.png"), 7)
+            .unwrap();
+        manager
+            .copy_staged_file(&key("menu.txt"), &key("menu-copy.txt"), 7)
+            .unwrap();
+
+        let staged = manager.staged_index().unwrap();
+        let original = staged.get_file(&key("logo.png")).unwrap();
+        let copy = staged.get_file(&key("img/logo.png")).unwrap();
+        assert_eq!(copy.bytes(), Some(&png[..]));
+        assert!(copy.shares_content(original));
+        assert_eq!(copy.mime_type(), Some("image/png"));
+        assert!(!copy.is_editable());
+        assert_eq!(copy.mtime(), 7);
+
+        let copy = staged.get_file(&key("menu-copy.txt")).unwrap();
+        assert_eq!(copy.bytes(), Some(&latin1[..]));
+        assert_eq!(copy.search_content(), Some("café\n".as_bytes()));
+
+        assert!(matches!(
+            manager.copy_staged_file(&key("missing"), &key("x"), 0),
+            Err(Error::FileNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_events_are_sent_after_each_operation() {
+        let manager = Arc::new(IndexManager::default());
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let listener: EventListener = {
+            let manager = Arc::downgrade(&manager);
+            let seen = seen.clone();
+            Arc::new(move |event: &IndexEvent| {
+                // No lock is held while the listener runs.
+                let files = manager.upgrade().unwrap().staged_index().map(|i| i.len());
+                seen.lock().push((event.clone(), files.ok()));
+            })
+        };
+        manager.set_event_listener(Some(listener));
+        let take = || std::mem::take(&mut *seen.lock());
+
+        manager
+            .load_files(vec![(key("a.txt"), entry("a")), (key("b.txt"), entry("b"))])
+            .unwrap();
+        assert_eq!(take(), [(IndexEvent::FilesLoaded { count: 2 }, None)]);
+
+        manager.begin_staging().unwrap();
+        manager
+            .move_staged_file(&key("a.txt"), &key("c.txt"), 0)
+            .unwrap();
+        assert_eq!(
+            take(),
+            [
+                (IndexEvent::StagingBegun, Some(2)),
+                (
+                    IndexEvent::FileMoved {
+                        from: key("a.txt"),
+                        to: key("c.txt"),
+                    },
+                    Some(2)
+                ),
+            ]
+        );
+
+        // A step's events arrive together once it has finished; a failed
+        // step rolls back and sends none.
+        manager
+            .with_undo_step("edit", || {
+                manager.stage_file(key("d.txt"), entry("d"))?;
+                manager.remove_staged_file(&key("b.txt"))?;
+                assert!(take().is_empty());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(
+            take(),
+            [
+                (IndexEvent::FileStaged { path: key("d.txt") }, Some(2)),
+                (IndexEvent::FileDeleted { path: key("b.txt") }, Some(2)),
+            ]
+        );
+        let failed: Result<()> = manager.with_undo_step("fail", || {
+            manager.stage_file(key("e.txt"), entry("e"))?;
+            Err(Error::StagingNotActive)
+        });
+        assert!(failed.is_err());
+        assert!(take().is_empty());
+
+        manager.undo().unwrap();
+        manager.promote_staged().unwrap();
+        assert_eq!(
+            take(),
+            [
+                (IndexEvent::StagingRestored, Some(2)),
+                (
+                    IndexEvent::StagingPromoted {
+                        paths: vec![key("a.txt"), key("c.txt")],
+                    },
+                    None
+                ),
+            ]
+        );
+
+        manager.set_event_listener(None);
+        manager.begin_staging().unwrap();
+        manager.revert_staged().unwrap();
+        assert!(take().is_empty());
+    }
+
+    #[test]
+    fn test_auto_staging_begins_on_first_change() {
+        let manager = IndexManager::default();
+        manager
+            .load_files(vec![(key("a.txt"), entry("one\n"))])
+            .unwrap();
+        assert!(matches!(
+            manager.stage_file(key("b.txt"), entry("two\n")),
+            Err(Error::StagingNotActive)
+        ));
+
+        manager.configure(ManagerConfig {
+            staging: StagingMode::Auto,
+            ..ManagerConfig::default()
+        });
+        // Reads still see no staging until something changes.
+        assert!(manager.staged_index().is_err());
+        manager
+            .with_undo_step("write b", || {
+                manager.stage_file(key("b.txt"), entry("two\n"))
+            })
+            .unwrap();
+        assert_eq!(paths(&manager.staged_index().unwrap()), ["a.txt", "b.txt"]);
+
+        // Undoing the first change returns to the freshly begun session.
+        manager.undo().unwrap();
+        assert_eq!(paths(&manager.staged_index().unwrap()), ["a.txt"]);
+
+        manager.promote_staged().unwrap();
+        manager.remove_staged_file(&key("a.txt")).unwrap();
+        assert!(manager.staged_index().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_line_index_cache_follows_content_not_mtime() {
+        let manager = IndexManager::default();
+        manager
+            .load_files(vec![
+                (key("a.txt"), entry("one\n")),
+                (key("b.txt"), entry("two\nthree\n")),
+            ])
+            .unwrap();
+        let active = manager.active_index();
+        let b_index = manager.get_line_index(&key("b.txt"), &active).unwrap();
+        manager.get_line_index(&key("a.txt"), &active);
+
+        // Same mtime, different content: the cached index does not apply.
+        manager.begin_staging().unwrap();
+        manager
+            .stage_file(key("a.txt"), entry("one\nuno\n"))
+            .unwrap();
+        let staged = manager.staged_index().unwrap();
+        let a_entry = staged.get_file(&key("a.txt")).unwrap();
+        let a_index = manager.get_line_index(&key("a.txt"), &staged).unwrap();
+        assert_eq!(a_index.total_bytes(), 8);
+        assert!(manager.cached_line_index(&key("a.txt"), a_entry).is_some());
+
+        // Promoting evicts only the old content of the changed path.
+        manager.promote_staged().unwrap();
+        let cache = manager.line_index_cache.read();
+        assert_eq!(cache.len(), 2);
+        let b_cached = &cache[&line_index_key(&key("b.txt"), b"two\nthree\n")];
+        assert!(Arc::ptr_eq(b_cached, &b_index));
+        assert!(cache.contains_key(&line_index_key(&key("a.txt"), b"one\nuno\n")));
+    }
+
+    #[test]
+    fn test_spliced_stage_carries_line_index() {
+        let manager = IndexManager::default();
+        manager
+            .load_files(vec![(key("a.txt"), entry("one\ntwo\n"))])
+            .unwrap();
+        manager.get_line_index(&key("a.txt"), &manager.active_index());
+
+        manager.begin_staging().unwrap();
+        let splice = Splice {
+            start: 4,
+            old_end: 4,
+            new_end: 8,
+        };
+        manager
+            .stage_file_spliced(key("a.txt"), entry("one\nnew\ntwo\n"), splice)
+            .unwrap();
+        let staged = manager.staged_index().unwrap();
+        let a_entry = staged.get_file(&key("a.txt")).unwrap();
+        let carried = manager.cached_line_index(&key("a.txt"), a_entry).unwrap();
+        assert_eq!(carried.line_starts(), &[0, 4, 8]);
+    }
+
+    #[test]
+    fn test_hydrate_fills_metadata_only_entries_within_budget() {
+        let manager = IndexManager::default();
+        let lazy = |size| FileEntry::new("txt", size, 1, true);
+        manager
+            .load_files(vec![
+                (key("a.txt"), lazy(4)),
+                (key("b.txt"), lazy(4)),
+                (key("c.txt"), entry("eager")),
+            ])
+            .unwrap();
+        manager.set_hydration_budget(8);
+        let content = |path: &str| {
+            let index = manager.active_index();
+            index
+                .get_file(&key(path))
+                .unwrap()
+                .bytes()
+                .map(<[u8]>::to_vec)
+        };
+
+        assert!(manager.needs_content(&key("a.txt")));
+        assert!(manager
+            .hydrate(&key("a.txt"), Arc::from(&b"aaaa"[..]))
+            .unwrap());
+        assert!(!manager
+            .hydrate(&key("a.txt"), Arc::from(&b"AAAA"[..]))
+            .unwrap());
+        assert!(!manager
+            .hydrate(&key("zzz.txt"), Arc::from(&b"z"[..]))
+            .unwrap());
+        assert_eq!(content("a.txt").as_deref(), Some(&b"aaaa"[..]));
+
+        // Staging sees hydration of files it has not written.
+        manager.begin_staging().unwrap();
+        manager
+            .hydrate(&key("b.txt"), Arc::from(&b"bbbb"[..]))
+            .unwrap();
+        let staged = manager.staged_index().unwrap();
+        assert!(staged.get_file(&key("b.txt")).unwrap().bytes().is_some());
+        manager.stage_file(key("a.txt"), entry("edited")).unwrap();
+
+        // Lowering the budget evicts the oldest hydration, but not the
+        // staged edit; eager content is never tracked.
+        manager.set_hydration_budget(4);
+        assert_eq!(content("a.txt"), None);
+        assert!(content("b.txt").is_some() && content("c.txt").is_some());
+        let staged = manager.staged_index().unwrap();
+        assert_eq!(
+            staged.get_file(&key("a.txt")).unwrap().bytes(),
+            Some(&b"edited"[..])
+        );
+
+        manager.promote_staged().unwrap();
+        let usage = manager.hydration_usage();
+        assert_eq!((usage.files, usage.bytes), (1, 4));
+        assert_eq!(manager.memory_usage().active_bytes, 6 + 4 + 5);
+    }
+
+    #[test]
+    fn test_pinned_hydration_survives_until_call_ends() {
+        let manager = IndexManager::default();
+        let lazy = |size| FileEntry::new("txt", size, 1, true);
+        manager
+            .load_files(vec![(key("a.txt"), lazy(4)), (key("b.txt"), lazy(4))])
+            .unwrap();
+        manager.set_hydration_budget(4);
+
+        manager.with_hydration_pinned(|| {
+            for (path, bytes) in [("a.txt", &b"aaaa"[..]), ("b.txt", &b"bbbb"[..])] {
+                manager.hydrate(&key(path), Arc::from(bytes)).unwrap();
+            }
+            assert!(!manager.needs_content(&key("a.txt")));
+            assert!(!manager.needs_content(&key("b.txt")));
+        });
+        assert_eq!(manager.hydration_usage().bytes, 8);
+
+        manager.set_hydration_budget(4);
+        assert!(manager.needs_content(&key("a.txt")));
+        assert_eq!(manager.hydration_usage().bytes, 4);
+    }
+
+    #[test]
+    fn test_virtual_search_spaces() {
+        let manager = IndexManager::default();
+        manager
+            .load_files(vec![
+                (key("kept.txt"), entry("kept")),
+                (key("edited.txt"), entry("old")),
+                (key("deleted.txt"), entry("gone")),
+            ])
+            .unwrap();
+
+        manager.begin_staging().unwrap();
+        manager.stage_file(key("edited.txt"), entry("new")).unwrap();
+        manager
+            .stage_file(key("created.txt"), entry("fresh"))
+            .unwrap();
+        manager.remove_staged_file(&key("deleted.txt")).unwrap();
+
+        let staged_only = manager.index_for(SearchSpace::StagedOnly).unwrap();
+        assert_eq!(paths(&staged_only), vec!["created.txt", "edited.txt"]);
+
+        let removed = manager.index_for(SearchSpace::ActiveMinusStaged).unwrap();
+        assert_eq!(paths(&removed), vec!["deleted.txt", "edited.txt"]);
+        assert_eq!(
+            removed.get_file(&key("edited.txt")).unwrap().bytes(),
+            Some(&b"old"[..])
+        );
+
+        let union = manager.index_for(SearchSpace::Union).unwrap();
+        assert_eq!(
+            paths(&union),
+            vec!["created.txt", "deleted.txt", "edited.txt", "kept.txt"]
+        );
+        assert_eq!(
+            union.get_file(&key("edited.txt")).unwrap().bytes(),
+            Some(&b"new"[..])
+        );
+
+        manager.revert_staged().unwrap();
+        assert!(matches!(
+            manager.index_for(SearchSpace::Union),
+            Err(Error::StagingNotActive)
+        ));
+    }
+
+    #[test]
+    fn test_commit_delta_lists_only_changed_files() {
+        let manager = IndexManager::default();
+        assert!(manager.last_commit_delta().is_none());
+
+        manager
+            .load_files(vec![(key("a.txt"), entry("a")), (key("b.txt"), entry("b"))])
+            .unwrap();
+        assert_eq!(manager.last_commit_delta().unwrap().entries.len(), 2);
+
+        manager.begin_staging().unwrap();
+        manager.stage_file(key("a.txt"), entry("a2")).unwrap();
+        manager.remove_staged_file(&key("b.txt")).unwrap();
+        manager.promote_staged().unwrap();
+
+        let delta = manager.last_commit_delta().unwrap();
+        assert_eq!(delta.sequence, 2);
+        let changes: Vec<_> = delta
+            .entries
+            .iter()
+            .map(|d| (d.path.as_str(), d.entry.as_ref().and_then(|e| e.bytes())))
+            .collect();
+        assert_eq!(changes, vec![("a.txt", Some(&b"a2"[..])), ("b.txt", None)]);
+    }
+
+    #[test]
+    fn test_staged_view_is_consistent_and_generation_moves() {
+        let manager = IndexManager::default();
+        manager
+            .load_files(vec![(key("a.txt"), entry("a")), (key("b.txt"), entry("b"))])
+            .unwrap();
+
+        manager.begin_staging().unwrap();
+        manager.stage_file(key("a.txt"), entry("a2")).unwrap();
+        let view = manager.staged_view().unwrap();
+        assert_eq!(view.generation, manager.generation());
+
+        manager.remove_staged_file(&key("b.txt")).unwrap();
+        assert!(manager.generation() > view.generation);
+        // The earlier view still describes the state it was taken in.
+        assert_eq!(view.index.len(), 2);
+        assert_eq!(view.deletions().count(), 0);
+
+        let promoted = manager.promote_staged().unwrap();
+        assert_eq!(promoted.index.len(), 1);
+        let deleted: Vec<_> = promoted.deletions().map(PathKey::as_str).collect();
+        assert_eq!(deleted, vec!["b.txt"]);
+        assert_eq!(promoted.generation, manager.generation());
+    }
+
+    #[test]
+    fn test_stage_if_active_rejects_stale_hash() {
+        let manager = IndexManager::default();
+        manager
+            .load_files(vec![(key("a.txt"), entry("one\n"))])
+            .unwrap();
+        let hash = manager
+            .active_index()
+            .get_file(&key("a.txt"))
+            .and_then(FileEntry::content_hash)
+            .unwrap();
+        manager.begin_staging().unwrap();
+
+        let err = manager
+            .stage_file_if_active(key("a.txt"), entry("two\n"), Some(hash ^ 1))
+            .unwrap_err();
+        assert_eq!(err.code(), "content_changed");
+        assert!(manager
+            .stage_file_if_active(key("new.txt"), entry("x\n"), Some(hash))
+            .is_err());
+
+        manager
+            .stage_file_if_active(key("a.txt"), entry("two\n"), Some(hash))
+            .unwrap();
+        manager
+            .stage_file_if_active(key("new.txt"), entry("x\n"), None)
+            .unwrap();
+        let staged = manager.staged_index().unwrap();
+        assert_eq!(
+            staged.get_file(&key("a.txt")).unwrap().bytes(),
+            Some(&b"two\n"[..])
+        );
+        assert!(staged.get_file(&key("new.txt")).is_some());
+    }
+
+    #[test]
+    fn test_stage_if_active_without_staging() {
+        let manager = IndexManager::default();
+        manager
+            .load_files(vec![(key("a.txt"), entry("one\n"))])
+            .unwrap();
+        assert!(matches!(
+            manager.stage_file_if_active(key("b.txt"), entry("two\n"), None),
+            Err(Error::StagingNotActive)
+        ));
+
+        manager.configure(ManagerConfig {
+            staging: StagingMode::Auto,
+            ..ManagerConfig::default()
+        });
+        manager
+            .stage_file_if_active(key("b.txt"), entry("two\n"), None)
+            .unwrap();
+        assert_eq!(paths(&manager.staged_index().unwrap()), ["a.txt", "b.txt"]);
+    }
+
+    #[test]
+    fn test_staging_owner_guards_interleaved_flows() {
+        let manager = IndexManager::default();
+        manager.begin_staging_as("lint-fix").unwrap();
+        manager.begin_staging_as("lint-fix").unwrap();
+        assert_eq!(
+            manager.staging_owner().unwrap().as_deref(),
+            Some("lint-fix")
+        );
+
+        let err = manager.begin_staging_as("agent").unwrap_err();
+        assert!(matches!(
+            err,
+            Error::StagingOwnedBy { ref owner, ref requested }
+                if owner == "lint-fix" && requested == "agent"
+        ));
+        assert!(manager.check_staging_owner(Some("agent")).is_err());
+        assert!(manager.check_staging_owner(None).is_err());
+        manager.check_staging_owner(Some("lint-fix")).unwrap();
+
+        manager.promote_staged().unwrap();
+        manager.begin_staging().unwrap();
+        assert_eq!(manager.staging_owner().unwrap(), None);
+        manager.check_staging_owner(None).unwrap();
+        assert!(manager.begin_staging_as("agent").is_err());
+    }
+
+    #[test]
+    fn test_history_detects_recreated_paths() {
+        let manager = IndexManager::default();
+        manager
+            .load_files(vec![(key("a.txt"), entry("a")), (key("b.txt"), entry("b"))])
+            .unwrap();
+
+        manager.begin_staging().unwrap();
+        manager.remove_staged_file(&key("a.txt")).unwrap();
+        manager.stage_file(key("a.txt"), entry("a2")).unwrap();
+        manager.stage_file(key("a.txt"), entry("a3")).unwrap();
+        manager.stage_file(key("b.txt"), entry("b2")).unwrap();
+
+        assert_eq!(
+            manager.staged_history(&key("a.txt")).unwrap(),
+            vec![StagedOp::Delete, StagedOp::Write]
+        );
+        assert!(manager.was_recreated(&key("a.txt")).unwrap());
+        assert!(!manager.was_recreated(&key("b.txt")).unwrap());
+    }
+
+    #[test]
+    fn test_edit_epochs_move_on_change_and_rollback() {
+        let manager = IndexManager::default();
+        manager
+            .load_files(vec![(key("a.txt"), entry("a")), (key("b.txt"), entry("b"))])
+            .unwrap();
+        let loaded = manager.edit_epoch(&key("a.txt"));
+        assert!(loaded > 0);
+        assert_eq!(manager.edit_epoch(&key("missing.txt")), 0);
+
+        manager.begin_staging().unwrap();
+        manager.stage_file(key("a.txt"), entry("a2")).unwrap();
+        let edited = manager.edit_epoch(&key("a.txt"));
+        assert!(edited > loaded);
+
+        let b_before = manager.edit_epoch(&key("b.txt"));
+        let _ = manager.with_snapshot(|| -> Result<()> {
+            manager.stage_file(key("b.txt"), entry("b2"))?;
+            Err(Error::Aborted)
+        });
+        assert!(manager.edit_epoch(&key("b.txt")) > b_before);
+        assert_eq!(manager.edit_epoch(&key("a.txt")), edited);
+
+        manager.revert_staged().unwrap();
+        assert!(manager.edit_epoch(&key("a.txt")) > edited);
+    }
+}
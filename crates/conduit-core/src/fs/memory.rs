@@ -0,0 +1,110 @@
+//! Memory accounting for file content held by the index manager.
+//!
+//! Content dominates the module's heap, so a ceiling on content bytes lets
+//! writes fail with [`Error::MemoryPressure`] while the host can still react,
+//! instead of trapping on a failed allocation once the heap is exhausted.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// Content bytes held in memory, and the configured ceiling.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryUsage {
+    /// Content of the active index.
+    pub active_bytes: u64,
+    /// Staged content not shared with the active index.
+    pub staged_bytes: u64,
+    pub ceiling: Option<u64>,
+}
+
+impl MemoryUsage {
+    pub fn total(&self) -> u64 {
+        self.active_bytes + self.staged_bytes
+    }
+
+    /// Bytes that can still be added before reaching the ceiling.
+    pub fn headroom(&self) -> Option<u64> {
+        self.ceiling.map(|c| c.saturating_sub(self.total()))
+    }
+
+    /// Fail if adding `requested` bytes would exceed the ceiling.
+    pub fn check(&self, requested: u64) -> Result<()> {
+        match self.ceiling {
+            Some(ceiling) if self.total().saturating_add(requested) > ceiling => {
+                Err(Error::MemoryPressure {
+                    usage: self.total(),
+                    requested,
+                    ceiling,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Estimated memory held by the index manager, by kind. See
+/// [`IndexManager::memory_report`](crate::fs::IndexManager::memory_report).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryReport {
+    /// Content bytes, as in [`MemoryUsage::total`].
+    pub content_bytes: u64,
+    /// Part of `content_bytes` held as search text next to the loaded bytes.
+    pub text_bytes: u64,
+    /// Part of `text_bytes` that repeats the loaded bytes exactly.
+    pub duplicate_text_bytes: u64,
+    /// Cached line indexes.
+    pub line_index_entries: usize,
+    pub line_index_bytes: u64,
+    /// Cached line indexes for content no longer in either index.
+    pub stale_line_index_entries: usize,
+    /// Distinct paths in the active and staged indexes, and their length.
+    pub paths: usize,
+    pub path_bytes: u64,
+}
+
+/// What [`IndexManager::compact`](crate::fs::IndexManager::compact) freed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactReport {
+    /// Duplicate search text dropped.
+    pub text_bytes_freed: u64,
+    /// Stale line indexes evicted.
+    pub line_indexes_dropped: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_against_ceiling() {
+        let usage = MemoryUsage {
+            active_bytes: 60,
+            staged_bytes: 30,
+            ceiling: Some(100),
+        };
+        assert_eq!(usage.headroom(), Some(10));
+        assert!(usage.check(10).is_ok());
+
+        let err = usage.check(11).unwrap_err();
+        assert_eq!(err.code(), "memory_pressure");
+        assert!(matches!(
+            err,
+            Error::MemoryPressure {
+                usage: 90,
+                requested: 11,
+                ceiling: 100
+            }
+        ));
+
+        let unlimited = MemoryUsage {
+            ceiling: None,
+            ..usage
+        };
+        assert!(unlimited.check(u64::MAX).is_ok());
+    }
+}
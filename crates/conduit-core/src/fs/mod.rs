@@ -2,13 +2,40 @@
 //! used by search/replace tools. Keep IO-free; all bytes are
 //! already resident in memory.
 
+pub mod elevation;
+pub mod encoding;
+pub mod events;
+pub mod glob;
+pub mod hydration;
+pub mod ignore;
 pub mod index;
+pub mod load;
 pub mod manager;
+pub mod memory;
 pub mod path;
+pub mod session;
+pub mod stats;
+pub mod tree;
+pub mod warm;
 
-pub use index::{FileEntry, Index};
-pub use manager::{FileChangeStats, IndexManager};
-pub use path::{normalize_path, PathKey};
+pub use elevation::{ElevatedScope, ElevationRecord, ElevationTokens};
+pub use encoding::TextEncoding;
+pub use events::{EventListener, IndexEvent};
+pub use glob::{compile_globs, GlobOptions};
+pub use hydration::{HydrationLru, HydrationUsage, DEFAULT_HYDRATION_BUDGET};
+pub use ignore::{ignore_files, IgnoreSet, IGNORE_FILE_NAME};
+pub use index::{FileEntry, Index, Visibility};
+pub use load::{BatchReport, DuplicatePolicy, LoadProgress, SkipReason, SkippedFile};
+pub use manager::{
+    CheckpointInfo, CommitDelta, DeltaEntry, FileChangeStats, IndexManager, ManagerConfig,
+    StagedOp, StagedView, StagingMode, UndoState, UNDO_LIMIT,
+};
+pub use memory::{CompactReport, MemoryReport, MemoryUsage};
+pub use path::{normalize_path, parse_path_list, PathKey, JUNK_FILE_NAMES};
+pub use session::{ActiveRef, Session, SessionContent, SessionFile, StagingRecord};
+pub use stats::{ExtensionStats, IndexStats, SizedPath};
+pub use tree::{build_tree, DirNode, FileNode};
+pub use warm::{HintIntent, HintQueue};
 
 pub mod prelude {
     pub use super::{Index, IndexManager, PathKey};
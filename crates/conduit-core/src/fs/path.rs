@@ -6,11 +6,30 @@ use crate::error::{Error, Result};
 
 use globset::GlobSet;
 
+/// File names operating systems leave behind in folders, compared
+/// case-insensitively.
+pub const JUNK_FILE_NAMES: &[&str] = &[
+    ".DS_Store",
+    ".localized",
+    "Thumbs.db",
+    "ehthumbs.db",
+    "desktop.ini",
+];
+
 /// Represents a normalized path in the virtual file system.
 ///
 /// Serialized transparently as a plain JSON string.
 #[derive(
-    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+    Debug,
+    Clone,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
 )]
 #[serde(transparent)]
 pub struct PathKey(Arc<str>);
@@ -22,6 +41,7 @@ pub struct PathKey(Arc<str>);
 /// - Normalize OS separators, collapse `.`/`..`
 /// - Convert to POSIX slashes
 /// - Strip trailing slashes (except root)
+/// - Must still name something once `.`/`..` are collapsed
 pub fn normalize_path(s: &str) -> Result<String> {
     if s.is_empty() {
         return Err(Error::InvalidPath("empty path".to_string()));
@@ -38,10 +58,34 @@ pub fn normalize_path(s: &str) -> Result<String> {
             out.pop();
         }
     }
+    if out.is_empty() {
+        return Err(Error::InvalidPath(format!("names no file: {s}")));
+    }
 
     Ok(out)
 }
 
+/// Parse a newline-delimited list of paths and normalize each one.
+///
+/// Accepts `\n` or `\r\n` separators and skips blank lines. This is the
+/// compact form for very long path lists, which are much cheaper to pass
+/// as one byte buffer than as an array of strings.
+pub fn parse_path_list(bytes: &[u8]) -> Result<Vec<String>> {
+    bytes
+        .split(|&b| b == b'\n')
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.strip_suffix(b"\r").unwrap_or(line)))
+        .filter(|(_, line)| !line.iter().all(u8::is_ascii_whitespace))
+        .map(|(line_no, line)| {
+            let s = std::str::from_utf8(line).map_err(|_| {
+                Error::InvalidPath(format!("path list line {line_no} is not valid UTF-8"))
+            })?;
+            normalize_path(s)
+                .map_err(|e| Error::InvalidPath(format!("path list line {line_no}: {e}")))
+        })
+        .collect()
+}
+
 impl PathKey {
     /// Construct from a **pre-normalized** string with a given Arc.
     ///
@@ -63,6 +107,38 @@ impl PathKey {
         self.as_str().starts_with(prefix.as_str())
     }
 
+    /// Whether any component starts with `.` (`.env`, `.github/ci.yml`).
+    pub fn is_dotfile(&self) -> bool {
+        self.as_str()
+            .split('/')
+            .any(|part| part.starts_with('.') && part != "." && part != "..")
+    }
+
+    /// Whether the file name is one of [`JUNK_FILE_NAMES`] or an AppleDouble
+    /// `._` resource fork.
+    pub fn is_junk(&self) -> bool {
+        let name = self.as_str().rsplit('/').next().unwrap_or_default();
+        name.starts_with("._") || JUNK_FILE_NAMES.iter().any(|j| j.eq_ignore_ascii_case(name))
+    }
+
+    /// The path with `-n` added to the file name before its extension:
+    /// `src/a.test.ts` becomes `src/a-2.test.ts`, `.env` becomes `.env-2`.
+    pub fn with_suffix(&self, n: usize) -> PathKey {
+        let path = self.as_str();
+        let name_start = path.rfind('/').map_or(0, |i| i + 1);
+        let name = &path[name_start..];
+        // A leading dot starts a dotfile's name, not its extension.
+        let stem_len = name[1.min(name.len())..]
+            .find('.')
+            .map_or(name.len(), |i| i + 1);
+        let split = name_start + stem_len;
+        PathKey(Arc::from(format!(
+            "{}-{n}{}",
+            &path[..split],
+            &path[split..]
+        )))
+    }
+
     /// Matches a compiled globset.
     #[inline]
     pub fn matches(&self, glob: &GlobSet) -> bool {
@@ -81,3 +157,57 @@ impl AsRef<str> for PathKey {
         self.as_str()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_path_list() {
+        let list = b"src/a.rs\r\n\n./src/b.rs\nsrc/dir/../c.rs\n  \n";
+        assert_eq!(
+            parse_path_list(list).unwrap(),
+            vec!["src/a.rs", "src/b.rs", "src/c.rs"]
+        );
+        assert!(parse_path_list(b"").unwrap().is_empty());
+        assert!(parse_path_list(b"src/..\n").is_err());
+    }
+
+    #[test]
+    fn test_is_dotfile() {
+        let key = |p: &str| PathKey::from_arc(Arc::from(p));
+        assert!(key(".env").is_dotfile());
+        assert!(key(".github/workflows/ci.yml").is_dotfile());
+        assert!(key("src/.hidden/mod.rs").is_dotfile());
+        assert!(!key("src/lib.rs").is_dotfile());
+        assert!(!key("src/file.test.ts").is_dotfile());
+    }
+
+    #[test]
+    fn test_with_suffix() {
+        let key = |p: &str| PathKey::from_arc(Arc::from(p));
+        assert_eq!(
+            key("src/a.test.ts").with_suffix(2).as_str(),
+            "src/a-2.test.ts"
+        );
+        assert_eq!(key("Makefile").with_suffix(1).as_str(), "Makefile-1");
+        assert_eq!(key("cfg/.env").with_suffix(3).as_str(), "cfg/.env-3");
+        assert_eq!(key("v1.2/notes").with_suffix(1).as_str(), "v1.2/notes-1");
+    }
+
+    #[test]
+    fn test_is_junk() {
+        let key = |p: &str| PathKey::from_arc(Arc::from(p));
+        assert!(key(".DS_Store").is_junk());
+        assert!(key("assets/thumbs.db").is_junk());
+        assert!(key("docs/._notes.md").is_junk());
+        assert!(!key("src/.DS_Store/lib.rs").is_junk());
+        assert!(!key(".env").is_junk());
+    }
+
+    #[test]
+    fn test_parse_path_list_reports_line() {
+        let err = parse_path_list(b"ok.txt\n\xff\xfe").unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{err}");
+    }
+}
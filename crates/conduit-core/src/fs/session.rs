@@ -0,0 +1,150 @@
+//! Saving a session so it can be resumed after a reload.
+//!
+//! The active index is recorded by reference only (path, size, mtime and
+//! hash): the host reloads those files from where they came from, and the
+//! import checks they are unchanged. Staging is recorded in full, with the
+//! content of every staged file, since it exists nowhere else.
+
+use std::io::{Read, Write};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::fs::{normalize_path, FileChangeStats, PathKey, StagedOp};
+use crate::schema::SCHEMA_VERSION;
+
+/// Everything needed to resume a session; see
+/// [`IndexManager::export_session`](crate::fs::IndexManager::export_session).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Session {
+    pub schema_version: u32,
+    /// Every file of the active index, in path order.
+    pub active: Vec<ActiveRef>,
+    /// `None` if staging was not active.
+    pub staging: Option<StagingRecord>,
+}
+
+/// An active file as it was when the session was exported.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveRef {
+    pub path: PathKey,
+    pub size: u64,
+    pub mtime: i64,
+    /// XXH3-64 of the content; `None` for metadata-only entries.
+    pub hash: Option<u64>,
+}
+
+/// Staging state, relative to the active index.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct StagingRecord {
+    pub owner: Option<String>,
+    /// Modified paths that exist in staging, with their staged entries.
+    pub files: Vec<SessionFile>,
+    /// Modified paths absent from staging.
+    pub deleted: Vec<PathKey>,
+    /// Staged moves, source to destination.
+    pub moves: Vec<(PathKey, PathKey)>,
+    pub change_stats: Vec<(PathKey, FileChangeStats)>,
+    pub needs_read: Vec<PathKey>,
+    pub history: Vec<(PathKey, Vec<StagedOp>)>,
+}
+
+/// A staged file entry with its content.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionFile {
+    pub path: PathKey,
+    pub mtime: i64,
+    pub editable: bool,
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    pub mime_type: Option<String>,
+    /// `None` for metadata-only entries, which keep `size`.
+    pub content: Option<SessionContent>,
+    pub size: u64,
+}
+
+/// File content, as text when it is valid UTF-8 to keep the export compact.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SessionContent {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl SessionContent {
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => Self::Text(text.to_string()),
+            Err(_) => Self::Bytes(bytes.to_vec()),
+        }
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        match self {
+            Self::Text(text) => text.into_bytes(),
+            Self::Bytes(bytes) => bytes,
+        }
+    }
+}
+
+impl Session {
+    /// The session as gzip-compressed JSON.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let json = serde_json::to_vec(self).map_err(|e| Error::InvalidFormat(e.to_string()))?;
+        let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(&json)?;
+        Ok(encoder.finish()?)
+    }
+
+    /// Read a session written by [`Session::to_bytes`] under the current
+    /// schema version.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut json = Vec::new();
+        GzDecoder::new(bytes)
+            .read_to_end(&mut json)
+            .map_err(|e| Error::InvalidFormat(format!("session is not gzip data: {e}")))?;
+        let session: Session =
+            serde_json::from_slice(&json).map_err(|e| Error::InvalidFormat(e.to_string()))?;
+        if session.schema_version != SCHEMA_VERSION {
+            return Err(Error::InvalidFormat(format!(
+                "session schema version {} (expected {SCHEMA_VERSION})",
+                session.schema_version
+            )));
+        }
+        Ok(session)
+    }
+
+    /// Fail with [`Error::InvalidPath`] on the first path not normalized
+    /// the way the index stores paths, as a hand-edited or foreign session
+    /// may hold.
+    pub fn check_paths(&self) -> Result<()> {
+        let active = self.active.iter().map(|file| &file.path);
+        let staged = self.staging.iter().flat_map(|record| {
+            record
+                .files
+                .iter()
+                .map(|file| &file.path)
+                .chain(&record.deleted)
+                .chain(record.moves.iter().flat_map(|(src, dst)| [src, dst]))
+                .chain(record.change_stats.iter().map(|(path, _)| path))
+                .chain(&record.needs_read)
+                .chain(record.history.iter().map(|(path, _)| path))
+        });
+        for path in active.chain(staged) {
+            if normalize_path(path.as_str()).ok().as_deref() != Some(path.as_str()) {
+                return Err(Error::InvalidPath(format!(
+                    "session path is not normalized: {}",
+                    path.as_str()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,134 @@
+//! Aggregate statistics over an index, for project overviews.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::fs::{Index, PathKey};
+
+/// Totals for one file extension.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtensionStats {
+    /// Extension without the dot, as recorded at load; empty for none.
+    pub extension: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// A file in [`IndexStats::largest`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct SizedPath {
+    pub path: PathKey,
+    pub size: u64,
+}
+
+/// Summary of an index computed by [`Index::stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexStats {
+    pub file_count: usize,
+    /// Sum of file sizes.
+    pub total_bytes: u64,
+    pub editable_files: usize,
+    pub read_only_files: usize,
+    /// Per-extension totals, most bytes first.
+    pub extensions: Vec<ExtensionStats>,
+    /// The largest files, largest first; ties go to the earlier path.
+    pub largest: Vec<SizedPath>,
+}
+
+impl Index {
+    /// Totals, per-extension breakdown and the `top_n` largest files, in
+    /// one pass over the index.
+    pub fn stats(&self, top_n: usize) -> IndexStats {
+        let mut stats = IndexStats::default();
+        let mut extensions: HashMap<&str, (usize, u64)> = HashMap::new();
+        // Min-heap of the largest files seen so far.
+        let mut largest: BinaryHeap<Reverse<(u64, Reverse<&PathKey>)>> =
+            BinaryHeap::with_capacity(top_n + 1);
+
+        for (path, entry) in self.iter() {
+            let size = entry.size();
+            stats.file_count += 1;
+            stats.total_bytes += size;
+            if entry.is_editable() {
+                stats.editable_files += 1;
+            } else {
+                stats.read_only_files += 1;
+            }
+            let ext = extensions.entry(entry.ext()).or_default();
+            ext.0 += 1;
+            ext.1 += size;
+
+            if top_n > 0 {
+                largest.push(Reverse((size, Reverse(path))));
+                if largest.len() > top_n {
+                    largest.pop();
+                }
+            }
+        }
+
+        stats.extensions = extensions
+            .into_iter()
+            .map(|(extension, (files, bytes))| ExtensionStats {
+                extension: extension.to_string(),
+                files,
+                bytes,
+            })
+            .collect();
+        stats.extensions.sort_by(|a, b| {
+            b.bytes
+                .cmp(&a.bytes)
+                .then_with(|| a.extension.cmp(&b.extension))
+        });
+        stats.largest = largest
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((size, Reverse(path)))| SizedPath {
+                path: path.clone(),
+                size,
+            })
+            .collect();
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::FileEntry;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_index_stats() {
+        let mut index = Index::default();
+        for (path, size, editable) in [
+            ("a.rs", 10, true),
+            ("b.rs", 30, true),
+            ("c.md", 30, false),
+            ("Makefile", 5, true),
+        ] {
+            let entry = FileEntry::new(FileEntry::get_extension(path), size, 0, editable);
+            index
+                .upsert_file(PathKey::from_arc(Arc::from(path)), entry)
+                .unwrap();
+        }
+
+        let stats = index.stats(2);
+        assert_eq!((stats.file_count, stats.total_bytes), (4, 75));
+        assert_eq!((stats.editable_files, stats.read_only_files), (3, 1));
+        let exts: Vec<_> = stats
+            .extensions
+            .iter()
+            .map(|e| (e.extension.as_str(), e.files, e.bytes))
+            .collect();
+        assert_eq!(exts, [("rs", 2, 40), ("md", 1, 30), ("", 1, 5)]);
+        let largest: Vec<_> = stats.largest.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(largest, ["b.rs", "c.md"]);
+
+        assert!(index.stats(0).largest.is_empty());
+    }
+}
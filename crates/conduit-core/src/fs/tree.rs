@@ -0,0 +1,185 @@
+//! Nested directory view of a flat, sorted path set, for file explorers.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::fs::{FileEntry, PathKey};
+
+/// A directory in a [`build_tree`] result.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DirNode {
+    /// Last path component; empty for the index root.
+    pub name: String,
+    /// Full directory path without a trailing `/`; empty for the index root.
+    pub path: String,
+    /// Files anywhere below this directory.
+    pub file_count: usize,
+    /// Sum of the sizes of those files, in bytes.
+    pub total_size: u64,
+    /// Subdirectories, sorted by name.
+    // Tool schemas are inlined, which a recursive schema cannot be.
+    #[schemars(with = "Vec<serde_json::Value>")]
+    pub dirs: Vec<DirNode>,
+    /// Files directly in this directory, sorted by name.
+    pub files: Vec<FileNode>,
+    /// Whether the depth limit cut off this directory's contents; its
+    /// counts still cover everything below it.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+/// A file in a [`DirNode`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FileNode {
+    pub name: String,
+    pub size: u64,
+    pub editable: bool,
+}
+
+impl DirNode {
+    fn child_dir(&mut self, name: &str) -> &mut DirNode {
+        // Input is sorted, so every path under a directory arrives in one
+        // run and an existing child is always the last one added.
+        if self.dirs.last().is_none_or(|d| d.name != name) {
+            let path = if self.path.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}/{name}", self.path)
+            };
+            self.dirs.push(DirNode {
+                name: name.to_string(),
+                path,
+                ..DirNode::default()
+            });
+        }
+        let last = self.dirs.len() - 1;
+        &mut self.dirs[last]
+    }
+
+    fn sort(&mut self) {
+        self.dirs.sort_by(|a, b| a.name.cmp(&b.name));
+        self.dirs.iter_mut().for_each(DirNode::sort);
+    }
+}
+
+/// Fold `files`, sorted by path, into the tree below directory `root`
+/// (`""` for the whole index). Files outside `root` are skipped.
+///
+/// `depth` limits how many directory levels below `root` are expanded:
+/// `Some(0)` gives only the root's totals, `Some(1)` its direct files and
+/// subdirectories, and so on. Deeper directories keep their totals and are
+/// marked [`DirNode::truncated`].
+pub fn build_tree<'a>(
+    files: impl IntoIterator<Item = (&'a PathKey, &'a FileEntry)>,
+    root: &str,
+    depth: Option<usize>,
+) -> DirNode {
+    let root = root.trim_matches('/');
+    let mut tree = DirNode {
+        name: root.rsplit('/').next().unwrap_or_default().to_string(),
+        path: root.to_string(),
+        ..DirNode::default()
+    };
+
+    for (path, entry) in files {
+        let relative = if root.is_empty() {
+            path.as_str()
+        } else {
+            match path
+                .as_str()
+                .strip_prefix(root)
+                .and_then(|rest| rest.strip_prefix('/'))
+            {
+                Some(rest) => rest,
+                None => continue,
+            }
+        };
+        let size = entry.size();
+        let mut components: Vec<&str> = relative.split('/').collect();
+        let file_name = components.pop().unwrap_or_default();
+
+        let mut node = &mut tree;
+        let mut level = 0;
+        let mut cut = false;
+        for dir in components {
+            node.file_count += 1;
+            node.total_size += size;
+            if depth.is_some_and(|limit| level >= limit) {
+                node.truncated = true;
+                cut = true;
+                break;
+            }
+            node = node.child_dir(dir);
+            level += 1;
+        }
+        if cut {
+            continue;
+        }
+        node.file_count += 1;
+        node.total_size += size;
+        if depth.is_some_and(|limit| level >= limit) {
+            node.truncated = true;
+        } else {
+            node.files.push(FileNode {
+                name: file_name.to_string(),
+                size,
+                editable: entry.is_editable(),
+            });
+        }
+    }
+
+    tree.sort();
+    tree
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::Index;
+    use std::sync::Arc;
+
+    fn index(paths: &[(&str, usize)]) -> Index {
+        let mut index = Index::default();
+        for (path, size) in paths {
+            let entry = FileEntry::from_bytes("txt", 0, Arc::from(vec![b'x'; *size]), true);
+            index
+                .upsert_file(PathKey::from_arc(Arc::from(*path)), entry)
+                .unwrap();
+        }
+        index
+    }
+
+    #[test]
+    fn test_build_tree() {
+        let index = index(&[
+            ("README.md", 1),
+            ("src/b-x/y.rs", 2),
+            ("src/b.rs", 3),
+            ("src/b/c.rs", 4),
+            ("src/b/deep/d.rs", 5),
+        ]);
+
+        let tree = build_tree(index.iter_sorted(), "", None);
+        assert_eq!((tree.file_count, tree.total_size), (5, 15));
+        assert_eq!(tree.files[0].name, "README.md");
+        let src = &tree.dirs[0];
+        assert_eq!((src.path.as_str(), src.file_count), ("src", 4));
+        let names: Vec<_> = src.dirs.iter().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, ["b", "b-x"]);
+        assert_eq!(src.dirs[0].dirs[0].path, "src/b/deep");
+
+        let tree = build_tree(index.iter_sorted(), "src/b/", Some(0));
+        assert_eq!((tree.name.as_str(), tree.path.as_str()), ("b", "src/b"));
+        assert_eq!((tree.file_count, tree.total_size), (2, 9));
+        assert!(tree.truncated && tree.files.is_empty() && tree.dirs.is_empty());
+
+        let tree = build_tree(index.iter_sorted(), "src", Some(1));
+        assert!(!tree.truncated);
+        assert_eq!(tree.files.len(), 1);
+        let b = &tree.dirs[0];
+        assert!(b.truncated && b.dirs.is_empty());
+        assert_eq!((b.file_count, b.total_size), (2, 9));
+    }
+}
@@ -0,0 +1,141 @@
+//! Cache warming driven by host hints about upcoming operations.
+//!
+//! Hosts know what the user is about to do (a file was clicked, an editor
+//! tab opened) before conduit does. Hinted paths are queued and warmed in
+//! small slices between other work, so the first real read, edit or search
+//! on them finds its line index already built.
+
+use std::collections::{HashMap, VecDeque};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::fs::{IndexManager, PathKey};
+
+/// What the host expects to do with a path next.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum HintIntent {
+    WillSearch,
+    WillRead,
+    /// Also warms the committed version, which edits diff against.
+    WillEdit,
+}
+
+/// Paths waiting to be warmed, oldest hint first.
+#[derive(Debug, Default)]
+pub struct HintQueue {
+    order: VecDeque<PathKey>,
+    intents: HashMap<PathKey, HintIntent>,
+}
+
+impl HintQueue {
+    /// Queue `keys`. A path hinted again keeps its place and the strongest
+    /// intent seen so far.
+    pub fn push(&mut self, keys: impl IntoIterator<Item = PathKey>, intent: HintIntent) {
+        for key in keys {
+            match self.intents.get_mut(&key) {
+                Some(existing) => *existing = (*existing).max(intent),
+                None => {
+                    self.intents.insert(key.clone(), intent);
+                    self.order.push_back(key);
+                }
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.order.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.order.clear();
+        self.intents.clear();
+    }
+
+    /// Warm queued paths while `keep_going` returns true, checked before each
+    /// path. Returns how many were warmed; paths no longer indexed are dropped.
+    pub fn drain(
+        &mut self,
+        manager: &IndexManager,
+        mut keep_going: impl FnMut() -> bool,
+    ) -> Result<usize> {
+        let mut warmed = 0;
+        while keep_going() {
+            let Some(key) = self.order.pop_front() else {
+                break;
+            };
+            let intent = self.intents.remove(&key).unwrap_or(HintIntent::WillRead);
+            if manager.warm(&key, intent)? {
+                warmed += 1;
+            }
+        }
+        Ok(warmed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::FileEntry;
+    use std::sync::Arc;
+
+    fn key(p: &str) -> PathKey {
+        PathKey::from_arc(Arc::from(p))
+    }
+
+    #[test]
+    fn test_push_dedupes_and_keeps_strongest_intent() {
+        let mut queue = HintQueue::default();
+        queue.push([key("a"), key("b")], HintIntent::WillRead);
+        queue.push([key("a")], HintIntent::WillEdit);
+        queue.push([key("a")], HintIntent::WillSearch);
+
+        assert_eq!(queue.len(), 2);
+        assert_eq!(queue.intents[&key("a")], HintIntent::WillEdit);
+    }
+
+    #[test]
+    fn test_drain_builds_line_indexes_within_budget() {
+        let manager = IndexManager::default();
+        let entry = |s: &str| FileEntry::from_bytes("rs", 0, Arc::from(s.as_bytes()), true);
+        manager
+            .load_files(vec![
+                (key("a.rs"), entry("a\nb\n")),
+                (key("b.rs"), entry("c\n")),
+            ])
+            .unwrap();
+
+        let mut queue = HintQueue::default();
+        queue.push(
+            [key("a.rs"), key("gone.rs"), key("b.rs")],
+            HintIntent::WillRead,
+        );
+
+        let mut budget = 2;
+        let warmed = queue
+            .drain(&manager, || {
+                budget -= 1;
+                budget >= 0
+            })
+            .unwrap();
+        assert_eq!(warmed, 1);
+        assert_eq!(queue.len(), 1);
+
+        let active = manager.active_index();
+        let a = active.get_file(&key("a.rs")).unwrap();
+        assert!(manager.cached_line_index(&key("a.rs"), a).is_some());
+        let b = active.get_file(&key("b.rs")).unwrap();
+        assert!(manager.cached_line_index(&key("b.rs"), b).is_none());
+
+        assert_eq!(queue.drain(&manager, || true).unwrap(), 1);
+        assert!(queue.is_empty());
+    }
+}
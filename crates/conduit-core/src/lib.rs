@@ -1,27 +1,68 @@
+pub mod command;
 pub mod error;
 pub mod fs;
+pub mod schema;
 pub mod tools;
+pub mod transaction;
 
+pub use command::{
+    execute_command, run_batch, BatchItem, BatchRequest, BatchResponse, Command, CommandTools,
+    OnError,
+};
 pub use error::{Error, Result};
 pub use fs::prelude::*;
+pub use schema::{Versioned, SCHEMA_VERSION};
 pub use tools::{
     apply_line_operations, compute_diff, compute_diffs, search_regions, AbortFlag, ByteSpan,
-    DiffRegion, DiffStats, FileDiff, LineIndex, LineOperation, LineSpan, Match, MatchRegion,
-    PreviewBuilder, PreviewHunk, ReadRequest, ReadResponse, RegexEngineOpts, RegexMatcher,
+    CaseFolding, DiffRegion, DiffStats, FileDiff, LineIndex, LineOperation, LineSpan, Match,
+    MatchRegion, MergeConflict, MergeOutcome, PatchReport, PreviewBuilder, PreviewHunk,
+    ReadRequest, ReadResponse, RegexEngineOpts, RegexMatcher,
 };
+pub use transaction::{Transaction, TransactionOp, TransactionOutcome};
 
 /// Selects which buffer set to operate on.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema,
+)]
 #[serde(rename_all = "PascalCase")]
 pub enum SearchSpace {
     /// The primary/committed buffer.
     Active,
     /// The working/uncommitted buffer.
     Staged,
+    /// Staged versions of files created or modified in staging.
+    StagedOnly,
+    /// Active versions of files that staging modifies, moves or deletes.
+    ActiveMinusStaged,
+    /// Every staged file, plus active versions of files deleted in staging.
+    Union,
+}
+
+/// Order in which candidate files are scanned during a search.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum SearchOrder {
+    /// Sorted by path (deterministic, the default).
+    #[default]
+    Path,
+    /// Smallest files first, so most hits surface before large files are scanned.
+    SmallestFirst,
 }
 
 /// Parameters for searching files.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(
+    Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema,
+)]
 #[serde(default, rename_all = "camelCase")]
 pub struct FindRequest {
     /// Glob patterns to include (if any).
@@ -32,12 +73,47 @@ pub struct FindRequest {
     pub prefix: Option<String>,
     /// Regex pattern to search for.
     pub find: String,
+    /// Name of a registered pattern; replaces `find` and `engine_opts` when set.
+    pub pattern_ref: Option<String>,
+    /// Search for any of these patterns in one pass instead of `find`.
+    /// Results name the pattern that matched. Ignored with `pattern_ref`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<String>,
     /// Number of context lines around matches.
     pub delta: usize,
     /// Regex compilation options.
     pub engine_opts: RegexEngineOpts,
     /// Which buffer set to search.
     pub where_: SearchSpace,
+    /// Only include files modified at or after this time (unix epoch seconds).
+    pub modified_after: Option<i64>,
+    /// Only include files modified at or before this time (unix epoch seconds).
+    pub modified_before: Option<i64>,
+    /// Scan order for candidate files.
+    pub order: SearchOrder,
+    /// Return only `hits` (path, line, match length) without building excerpts.
+    pub minimal: bool,
+    /// Also search dotfiles and host-flagged hidden files.
+    pub include_hidden: bool,
+    /// Also search OS junk files such as `.DS_Store`.
+    pub include_junk: bool,
+    /// Also search paths matched by ignore rules.
+    pub include_ignored: bool,
+    /// Stop reporting matches in a file after this many.
+    pub max_matches_per_file: Option<usize>,
+    /// Stop the whole search after this many matches.
+    pub max_total_matches: Option<usize>,
+    /// Merge hunks of nearby matches whose excerpts overlap or touch.
+    pub merge_adjacent: bool,
+    /// How `include_globs` and `exclude_globs` are matched.
+    pub glob_options: fs::GlobOptions,
+    /// Give up after this many milliseconds, keeping the results found so
+    /// far and marking the response [`FindStats::aborted`].
+    pub timeout_ms: Option<u64>,
+    /// Search exactly these files. `prefix` and the globs are not evaluated,
+    /// and hidden or ignored files are searched; other filters still apply.
+    /// Paths that are not indexed are skipped.
+    pub paths: Option<Vec<PathKey>>,
 }
 
 impl Default for FindRequest {
@@ -47,21 +123,133 @@ impl Default for FindRequest {
             exclude_globs: None,
             prefix: None,
             find: String::new(),
+            pattern_ref: None,
+            patterns: Vec::new(),
             delta: 2,
             engine_opts: RegexEngineOpts::default(),
             where_: SearchSpace::Staged,
+            modified_after: None,
+            modified_before: None,
+            order: SearchOrder::Path,
+            minimal: false,
+            include_hidden: false,
+            include_junk: false,
+            include_ignored: false,
+            max_matches_per_file: None,
+            max_total_matches: None,
+            merge_adjacent: false,
+            glob_options: fs::GlobOptions::default(),
+            timeout_ms: None,
+            paths: None,
         }
     }
 }
 
-/// Search results as preview excerpts.
+/// A single match location returned by minimal finds.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct FindHit {
+    pub path: PathKey,
+    /// 1-based line of the match start
+    pub line: usize,
+    /// Match length in bytes
+    pub length: usize,
+    /// Index into [`FindRequest::patterns`] of the pattern that matched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern_index: Option<usize>,
+}
+
+/// Search results as preview excerpts, or bare hits for minimal finds.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct FindResponse {
     pub results: Vec<PreviewHunk>,
+    /// Populated instead of `results` when the request sets `minimal`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hits: Vec<FindHit>,
+    #[serde(default)]
+    pub stats: FindStats,
+    /// Files with more matches than [`FindRequest::max_matches_per_file`];
+    /// only their first matches are reported.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub truncated_paths: Vec<PathKey>,
+}
+
+/// Aggregate counts for a search, taken before any result limit.
+///
+/// Matches beyond the request's `max_matches_per_file` and
+/// `max_total_matches` are never scanned, so they are not counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct FindStats {
+    /// Matches found, one per hit or matched range of a hunk.
+    pub total_matches: usize,
+    /// Distinct files with at least one match.
+    pub files_with_matches: usize,
+    /// Files with matches dropped by [`FindResponse::truncate`] or by a
+    /// result limit applied during the search.
+    pub files_truncated: usize,
+    /// The search stopped at [`FindRequest::max_total_matches`] with more
+    /// matches left.
+    #[serde(default)]
+    pub match_limit_reached: bool,
+    /// The search was aborted or timed out before scanning every file; the
+    /// results are what it found until then.
+    #[serde(default)]
+    pub aborted: bool,
+    /// Candidate files not searched because they are loaded as metadata
+    /// only; see [`IndexManager::hydrate`](crate::fs::IndexManager::hydrate).
+    #[serde(default)]
+    pub skipped_unhydrated: usize,
+}
+
+impl FindResponse {
+    /// Build a response, counting its matches and files.
+    pub fn new(results: Vec<PreviewHunk>, hits: Vec<FindHit>) -> Self {
+        let mut files: Vec<&PathKey> = results
+            .iter()
+            .map(|h| &h.path)
+            .chain(hits.iter().map(|h| &h.path))
+            .collect();
+        files.sort();
+        files.dedup();
+        let stats = FindStats {
+            total_matches: results
+                .iter()
+                .map(|h| h.matched_line_ranges.len())
+                .sum::<usize>()
+                + hits.len(),
+            files_with_matches: files.len(),
+            files_truncated: 0,
+            match_limit_reached: false,
+            aborted: false,
+            skipped_unhydrated: 0,
+        };
+        Self {
+            results,
+            hits,
+            stats,
+            truncated_paths: Vec::new(),
+        }
+    }
+
+    /// Keep only the first `limit` results (or hits), recording how many
+    /// files lost matches. Totals still describe the whole search.
+    pub fn truncate(&mut self, limit: usize) {
+        let mut dropped: Vec<&PathKey> = self
+            .results
+            .iter()
+            .skip(limit)
+            .map(|h| &h.path)
+            .chain(self.hits.iter().skip(limit).map(|h| &h.path))
+            .collect();
+        dropped.sort();
+        dropped.dedup();
+        self.stats.files_truncated = dropped.len();
+        self.results.truncate(limit);
+        self.hits.truncate(limit);
+    }
 }
 
 /// Parameters for find-and-replace operations.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 #[serde(default, rename_all = "camelCase")]
 pub struct EditRequest {
     /// Glob patterns to include (if any).
@@ -72,12 +260,19 @@ pub struct EditRequest {
     pub prefix: Option<String>,
     /// Regex pattern to search for.
     pub find: String,
+    /// Name of a registered pattern; replaces `find` and `engine_opts` when set.
+    pub pattern_ref: Option<String>,
     /// Replacement template supporting `$1`, `${name}`, `$$`.
     pub replace: String,
+    /// Recase each replacement to follow the text it replaces, e.g.
+    /// `Foo` -> `Bar` and `FOO` -> `BAR` when replacing `foo` with `bar`.
+    pub preserve_case: bool,
     /// Number of context lines in previews.
     pub delta: usize,
     /// Regex compilation options.
     pub engine_opts: RegexEngineOpts,
+    /// How `include_globs` and `exclude_globs` are matched.
+    pub glob_options: fs::GlobOptions,
 }
 
 impl Default for EditRequest {
@@ -87,9 +282,12 @@ impl Default for EditRequest {
             exclude_globs: None,
             prefix: None,
             find: String::new(),
+            pattern_ref: None,
             replace: String::new(),
+            preserve_case: false,
             delta: 2,
             engine_opts: RegexEngineOpts::default(),
+            glob_options: fs::GlobOptions::default(),
         }
     }
 }
@@ -115,7 +313,7 @@ pub struct EditResponse {
 }
 
 /// Request to create a file in the staged index.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 pub struct CreateRequest {
     /// Path where the file should be created
     pub path: PathKey,
@@ -126,7 +324,7 @@ pub struct CreateRequest {
 }
 
 /// Response after creating a file.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 pub struct CreateResponse {
     /// Path of the created file
     pub path: PathKey,
@@ -137,7 +335,7 @@ pub struct CreateResponse {
 }
 
 /// Request to delete a file from the staged index.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 pub struct DeleteRequest {
     /// Path of the file to delete
     pub path: PathKey,
@@ -157,7 +355,7 @@ impl DeleteRequest {
 }
 
 /// Response after deleting a file.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 pub struct DeleteResponse {
     /// Path of the deleted file
     pub path: PathKey,
@@ -165,14 +363,53 @@ pub struct DeleteResponse {
     pub existed: bool,
 }
 
+/// Request to delete every file under a directory.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct DeleteDirRequest {
+    /// Directory to delete; a trailing `/` is optional.
+    pub path: String,
+}
+
+/// Files removed by a directory delete.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct DeleteDirResponse {
+    /// Deleted paths, in path order.
+    pub deleted: Vec<PathKey>,
+}
+
+/// Request to move or copy every file under a directory.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct MoveDirRequest {
+    /// Source directory; a trailing `/` is optional.
+    pub from: String,
+    /// Directory the files end up under.
+    pub to: String,
+    /// What to do with files whose destination exists.
+    #[serde(default)]
+    pub on_conflict: ConflictPolicy,
+}
+
+/// Files moved or copied by a directory operation.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct DirOperationResponse {
+    /// One operation per file, in source path order, to where it went.
+    pub operations: Vec<FileOperation>,
+    /// Files whose destination already existed, in source path order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<DestinationConflict>,
+}
+
 /// Request to replace specific lines in a file.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 pub struct ReplaceLinesRequest {
     /// Path of the file to modify
     pub path: PathKey,
     /// List of (start_line, end_line, new_content) replacements
     /// Lines are 1-based and inclusive
     pub replacements: Vec<(usize, usize, String)>,
+    /// Refuse the edit if the file changed since this version
+    #[serde(default)]
+    pub precondition: Option<tools::EditPrecondition>,
 }
 
 /// Response after replacing lines in a file.
@@ -188,27 +425,287 @@ pub struct ReplaceLinesResponse {
     pub total_lines: usize,
     /// Original line count before replacement
     pub original_lines: usize,
+    /// Whether the file was not valid UTF-8 and replacement characters
+    /// were written back as part of this edit
+    #[serde(default)]
+    pub lossy: bool,
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+/// Request to replace a piece of text located by its content.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct AnchoredEditRequest {
+    /// Path of the file to modify
+    pub path: PathKey,
+    /// Text the edit replaces, as the caller last saw it
+    pub expected_text: String,
+    pub replacement: String,
+    /// 1-based occurrence of `expected_text` to replace; when `None` the
+    /// text must appear exactly once
+    #[serde(default)]
+    pub occurrence: Option<usize>,
+    /// Let any run of whitespace in `expected_text` match any run of
+    /// whitespace in the file
+    #[serde(default)]
+    pub whitespace_insensitive: bool,
+    /// Refuse the edit if the file changed since this version
+    #[serde(default)]
+    pub precondition: Option<tools::EditPrecondition>,
+}
+
+/// Response after an anchored edit.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AnchoredEditResponse {
+    pub path: PathKey,
+    /// First and last original line of the replaced text, 1-based
+    pub start_line: usize,
+    pub end_line: usize,
+    /// How many places `expected_text` matched
+    pub occurrences: usize,
+    /// Net change in line count
+    pub lines_added: isize,
+    /// Total lines in the file after the edit
+    pub total_lines: usize,
+    /// Line count before the edit
+    pub original_lines: usize,
+    /// Whether the file was not valid UTF-8 and replacement characters
+    /// were written back as part of this edit
+    #[serde(default)]
+    pub lossy: bool,
+}
+
+/// Request to check line operations against a file without applying them.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ValidateLinesRequest {
+    /// Path of the staged file the operations target
+    pub path: PathKey,
+    pub operations: Vec<LineOperation>,
+}
+
+/// Problems found by a [`ValidateLinesRequest`]; nothing is modified.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidateLinesResponse {
+    pub path: PathKey,
+    /// Lines in the staged file
+    pub line_count: usize,
+    /// `true` if `issues` is empty
+    pub valid: bool,
+    /// Problems in request order
+    pub issues: Vec<tools::LineOpIssue>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 pub struct FileOperation {
     pub src: PathKey,
     pub dst: PathKey,
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+/// What a copy or move does when its destination already exists.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictPolicy {
+    /// Fail with [`Error::FileAlreadyExists`].
+    #[default]
+    Error,
+    /// Replace the existing file.
+    Overwrite,
+    /// Write to the first free `name-N.ext` instead; see [`PathKey::with_suffix`].
+    Rename,
+}
+
+impl ConflictPolicy {
+    /// Where copying or moving `src` to `dst` in `index` writes, and the
+    /// conflict if `dst` is taken. Moving a file onto itself is no conflict.
+    pub fn resolve(
+        self,
+        index: &Index,
+        src: &PathKey,
+        dst: &PathKey,
+    ) -> Result<(PathKey, Option<DestinationConflict>)> {
+        self.resolve_with(|path| index.get_file(path).is_some(), src, dst)
+    }
+
+    /// [`Self::resolve`] with `taken` deciding which paths are occupied, for
+    /// callers placing several files before any is written.
+    pub fn resolve_with(
+        self,
+        taken: impl Fn(&PathKey) -> bool,
+        src: &PathKey,
+        dst: &PathKey,
+    ) -> Result<(PathKey, Option<DestinationConflict>)> {
+        if src == dst || !taken(dst) {
+            return Ok((dst.clone(), None));
+        }
+        let written = match self {
+            ConflictPolicy::Error => return Err(Error::FileAlreadyExists(dst.clone().into())),
+            ConflictPolicy::Overwrite => dst.clone(),
+            ConflictPolicy::Rename => (1..usize::MAX)
+                .map(|n| dst.with_suffix(n))
+                .find(|candidate| !taken(candidate))
+                .ok_or_else(|| Error::FileAlreadyExists(dst.clone().into()))?,
+        };
+        let conflict = DestinationConflict {
+            src: src.clone(),
+            dst: dst.clone(),
+            renamed_to: (written != *dst).then(|| written.clone()),
+        };
+        Ok((written, Some(conflict)))
+    }
+}
+
+/// A copy or move whose destination already existed.
+#[derive(
+    Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema,
+)]
+pub struct DestinationConflict {
+    pub src: PathKey,
+    /// The destination asked for.
+    pub dst: PathKey,
+    /// Where the file went instead; `None` if `dst` was overwritten.
+    pub renamed_to: Option<PathKey>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 pub struct BatchCopyRequest {
     pub operations: Vec<FileOperation>,
+    /// Apply each operation independently and report failures instead of
+    /// rolling back the whole batch on the first error.
+    #[serde(default)]
+    pub continue_on_error: bool,
+    /// What to do with destinations that already exist.
+    #[serde(default)]
+    pub on_conflict: ConflictPolicy,
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 pub struct BatchMoveRequest {
     pub operations: Vec<FileOperation>,
+    /// See [`BatchCopyRequest::continue_on_error`].
+    #[serde(default)]
+    pub continue_on_error: bool,
+    /// See [`BatchCopyRequest::on_conflict`].
+    #[serde(default)]
+    pub on_conflict: ConflictPolicy,
+}
+
+/// Why one operation in a `continue_on_error` batch failed.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct OperationFailure {
+    /// Stable error name, see [`Error::code`].
+    pub code: String,
+    pub message: String,
+}
+
+/// Outcome of one operation in a `continue_on_error` batch.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct OperationResult {
+    pub src: PathKey,
+    pub dst: PathKey,
+    /// `None` if the operation was applied.
+    pub error: Option<OperationFailure>,
 }
 
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct BatchOperationResponse {
+    /// Operations applied.
     pub count: usize,
+    /// Per-operation outcomes, in request order; only in `continue_on_error` mode.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub results: Vec<OperationResult>,
+    /// Applied operations whose destination already existed, in request order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<DestinationConflict>,
+}
+
+/// Request to move every file under a directory to another directory.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct MoveTreeRequest {
+    /// Directory prefix to move; a trailing `/` is optional.
+    pub from: String,
+    /// Directory the files end up under.
+    pub to: String,
+    /// Also rewrite import paths that point into or out of the moved tree.
+    #[serde(default)]
+    pub rewrite_references: bool,
+    /// Compute the moves and rewrites without staging them.
+    #[serde(default)]
+    pub dry_run: bool,
+    /// What to do with files whose destination exists.
+    #[serde(default)]
+    pub on_conflict: ConflictPolicy,
+}
+
+/// Import lines rewritten in one file.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct FileRewrite {
+    /// The file's path after the move.
+    pub path: PathKey,
+    pub edits: Vec<tools::ReferenceEdit>,
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct MoveTreeResponse {
+    /// Files moved, in source path order.
+    pub moved: Vec<FileOperation>,
+    /// Files whose import paths were rewritten, in path order.
+    pub rewrites: Vec<FileRewrite>,
+    /// Files whose destination already existed, in source path order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<DestinationConflict>,
+}
+
+/// Request to instantiate a registered scaffold template.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ApplyScaffoldRequest {
+    /// Name of the registered template
+    pub template: String,
+    /// Placeholder values
+    pub params: std::collections::HashMap<String, String>,
+    /// Directory prefix the template paths are created under
+    pub dst_prefix: String,
+    /// Whether to overwrite files that already exist
+    pub allow_overwrite: bool,
+}
+
+/// Response after applying a scaffold.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct ApplyScaffoldResponse {
+    /// Paths written, in template order
+    pub created: Vec<PathKey>,
+}
+
+/// Request to merge a file changed outside staging into its staged copy.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct MergeFileRequest {
+    pub path: PathKey,
+    /// Content both the staged copy and `theirs` started from
+    pub base: String,
+    /// The other changed copy, e.g. the file as now on disk
+    pub theirs: String,
+    /// Stage the merged content, conflict markers included
+    #[serde(default)]
+    pub stage: bool,
+}
+
+/// Response after merging a file.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct MergeFileResponse {
+    pub path: PathKey,
+    #[serde(flatten)]
+    pub outcome: MergeOutcome,
+    /// Whether staging changed
+    pub staged: bool,
 }
 
 /// Search files and return preview excerpts.
@@ -240,6 +737,7 @@ pub trait CreateTool {
 /// Delete files from the staged index.
 pub trait DeleteTool {
     fn run_delete(&mut self, req: DeleteRequest) -> Result<DeleteResponse>;
+    fn run_delete_dir(&mut self, req: DeleteDirRequest) -> Result<DeleteDirResponse>;
 }
 
 /// Replace specific lines in files.
@@ -247,6 +745,16 @@ pub trait ReplaceLinesTool {
     fn run_replace_lines(&mut self, req: ReplaceLinesRequest) -> Result<ReplaceLinesResponse>;
 }
 
+/// Replace text located by content rather than line numbers.
+pub trait AnchoredEditTool {
+    fn run_anchored_edit(&mut self, req: AnchoredEditRequest) -> Result<AnchoredEditResponse>;
+}
+
+/// Check line operations without applying them.
+pub trait ValidateLinesTool {
+    fn run_validate_lines(&mut self, req: ValidateLinesRequest) -> Result<ValidateLinesResponse>;
+}
+
 /// Delete specific lines from files.
 pub trait DeleteLinesTool {
     fn run_delete_lines(&mut self, req: DeleteLinesRequest) -> Result<ReplaceLinesResponse>;
@@ -257,18 +765,63 @@ pub trait InsertLinesTool {
     fn run_insert_lines(&mut self, req: InsertLinesRequest) -> Result<ReplaceLinesResponse>;
 }
 
+/// Add lines at either end of a file without knowing its length.
+pub trait AppendTool {
+    fn run_append(&mut self, req: AppendRequest) -> Result<ReplaceLinesResponse>;
+}
+
 /// Compute diffs between active and staged versions of files.
 pub trait DiffTool {
-    /// Get summary of all modified files with line change statistics
-    fn get_modified_files_summary(&self) -> Result<Vec<ModifiedFileSummary>>;
+    /// Get summary of all modified files with line change statistics.
+    ///
+    /// `abort` is checked between files; an aborted call returns the
+    /// summaries made so far, marked incomplete.
+    fn get_modified_files_summary(&self, abort: &AbortFlag)
+        -> Result<Partial<ModifiedFileSummary>>;
+
+    /// Get detailed diff for a specific file, with up to `context_lines`
+    /// unchanged lines around each region.
+    fn get_file_diff(&self, path: &PathKey, context_lines: usize) -> Result<FileDiff>;
+
+    /// Staged-vs-active change of one file as a git-style unified patch.
+    ///
+    /// A path that is either end of a staged move renders as a rename.
+    /// Returns an empty string if the file is unchanged.
+    fn get_unified_diff(&self, path: &PathKey) -> Result<String>;
 
-    /// Get detailed diff for a specific file
-    fn get_file_diff(&self, path: &PathKey) -> Result<FileDiff>;
+    /// Every staged change as one git-style patch, ordered by path.
+    fn get_all_unified_diffs(&self) -> Result<String>;
+}
+
+/// Apply a unified diff to staging, all files or none.
+pub trait PatchTool {
+    fn run_apply_patch(&mut self, patch: &str) -> Result<PatchReport>;
+}
+
+/// Create a set of files from a registered template in one transaction.
+pub trait ScaffoldTool {
+    fn run_apply_scaffold(&mut self, req: ApplyScaffoldRequest) -> Result<ApplyScaffoldResponse>;
+}
+
+/// Three-way merge of a staged file with another copy of it.
+pub trait MergeTool {
+    fn run_merge_file(&mut self, req: MergeFileRequest) -> Result<MergeFileResponse>;
 }
 
 pub trait MoveFilesTool {
     fn run_copy_files(&mut self, req: BatchCopyRequest) -> Result<BatchOperationResponse>;
     fn run_move_files(&mut self, req: BatchMoveRequest) -> Result<BatchOperationResponse>;
+    fn run_move_tree(&mut self, req: MoveTreeRequest) -> Result<MoveTreeResponse>;
+    fn run_move_dir(&mut self, req: MoveDirRequest) -> Result<DirOperationResponse>;
+    fn run_copy_dir(&mut self, req: MoveDirRequest) -> Result<DirOperationResponse>;
+}
+
+/// Results of an operation that stops early when aborted.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Partial<T> {
+    pub items: Vec<T>,
+    /// `false` if the operation was aborted before producing every item.
+    pub complete: bool,
 }
 
 /// Summary of changes for a modified file
@@ -280,33 +833,46 @@ pub struct ModifiedFileSummary {
     pub lines_added: usize,
     /// Number of lines removed
     pub lines_removed: usize,
-    /// File status (created, modified, deleted, moved)
+    /// File status (created, modified, deleted, moved, renamed)
     pub status: FileChangeStatus,
-    /// Destination path for moved files
+    /// Destination path for moved and renamed files
     #[serde(skip_serializing_if = "Option::is_none")]
     pub moved_to: Option<PathKey>,
+    /// Percentage of lines a renamed file kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<u8>,
 }
 
-#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema,
+)]
 #[serde(rename_all = "lowercase")]
 pub enum FileChangeStatus {
     Created,
     Modified,
     Deleted,
     Moved,
+    /// Existed before staging, was deleted or moved away, then written again.
+    Recreated,
+    /// Deleted, with a created file of similar content; see
+    /// [`tools::detect_renames`].
+    Renamed,
 }
 
 /// Request to delete specific lines from a file.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 pub struct DeleteLinesRequest {
     /// Path of the file to modify
     pub path: PathKey,
     /// Line numbers to delete (1-based)
     pub line_numbers: Vec<usize>,
+    /// Refuse the edit if the file changed since this version
+    #[serde(default)]
+    pub precondition: Option<tools::EditPrecondition>,
 }
 
 /// Single insertion operation.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 pub struct InsertOperation {
     /// Line number where to insert (1-based)
     pub line_number: usize,
@@ -317,30 +883,122 @@ pub struct InsertOperation {
 }
 
 /// Request to insert lines into a file.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 pub struct InsertLinesRequest {
     /// Path of the file to modify
     pub path: PathKey,
     /// List of insertions to perform
     pub insertions: Vec<InsertOperation>,
+    /// Refuse the edit if the file changed since this version
+    #[serde(default)]
+    pub precondition: Option<tools::EditPrecondition>,
 }
 
-#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
 pub enum InsertPosition {
     Before,
     After,
 }
 
+/// Request to add lines at the start or end of a file.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, schemars::JsonSchema)]
+pub struct AppendRequest {
+    /// Path of the file to modify
+    pub path: PathKey,
+    /// Lines to add
+    pub content: String,
+    /// Which end of the file to add them at
+    #[serde(default)]
+    pub position: AppendPosition,
+    /// Fail instead of creating the file when it does not exist
+    #[serde(default)]
+    pub must_exist: bool,
+    /// Refuse the edit if the file changed since this version
+    #[serde(default)]
+    pub precondition: Option<tools::EditPrecondition>,
+}
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
+)]
+pub enum AppendPosition {
+    /// Before the first line, or after a leading shebang line
+    Start,
+    #[default]
+    End,
+}
+
 pub mod prelude {
     //! Common imports for consumers of this crate.
     pub use super::{
-        AbortFlag, BatchCopyRequest, BatchMoveRequest, BatchOperationResponse, CreateRequest,
-        CreateResponse, CreateTool, DeleteLinesRequest, DeleteLinesTool, DeleteRequest,
-        DeleteResponse, DeleteTool, DiffTool, EditItem, EditRequest, EditResponse, EditTool, Error,
-        FileChangeStatus, FileDiff, FileOperation, FindRequest, FindResponse, FindTool, Index,
-        IndexManager, InsertLinesRequest, InsertLinesTool, InsertOperation, InsertPosition, Match,
-        ModifiedFileSummary, MoveFilesTool, PathKey, PreviewBuilder, PreviewHunk, ReadRequest,
-        ReadResponse, ReadTool, RegexEngineOpts, ReplaceLinesRequest, ReplaceLinesResponse,
-        ReplaceLinesTool, Result, SearchSpace,
+        AbortFlag, AnchoredEditRequest, AnchoredEditResponse, AnchoredEditTool, AppendPosition,
+        AppendRequest, AppendTool, ApplyScaffoldRequest, ApplyScaffoldResponse, BatchCopyRequest,
+        BatchMoveRequest, BatchOperationResponse, ConflictPolicy, CreateRequest, CreateResponse,
+        CreateTool, DeleteDirRequest, DeleteDirResponse, DeleteLinesRequest, DeleteLinesTool,
+        DeleteRequest, DeleteResponse, DeleteTool, DestinationConflict, DiffTool,
+        DirOperationResponse, EditItem, EditRequest, EditResponse, EditTool, Error,
+        FileChangeStatus, FileDiff, FileOperation, FindHit, FindRequest, FindResponse, FindStats,
+        FindTool, Index, IndexManager, InsertLinesRequest, InsertLinesTool, InsertOperation,
+        InsertPosition, Match, MergeFileRequest, MergeFileResponse, MergeTool, ModifiedFileSummary,
+        MoveDirRequest, MoveFilesTool, MoveTreeRequest, MoveTreeResponse, OperationFailure,
+        OperationResult, Partial, PatchReport, PatchTool, PathKey, PreviewBuilder, PreviewHunk,
+        ReadRequest, ReadResponse, ReadTool, RegexEngineOpts, ReplaceLinesRequest,
+        ReplaceLinesResponse, ReplaceLinesTool, Result, ScaffoldTool, SearchOrder, SearchSpace,
+        Transaction, TransactionOp, TransactionOutcome, ValidateLinesRequest,
+        ValidateLinesResponse, ValidateLinesTool,
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::FileEntry;
+    use std::sync::Arc;
+
+    fn key(p: &str) -> PathKey {
+        PathKey::from_arc(Arc::from(p))
+    }
+
+    #[test]
+    fn test_conflict_policy_resolves_taken_destinations() {
+        let mut index = Index::default();
+        for path in ["a.txt", "b.txt", "b-1.txt"] {
+            let entry = FileEntry::from_bytes("txt", 0, Arc::from(&b"x"[..]), true);
+            index.upsert_file(key(path), entry).unwrap();
+        }
+        let (a, b) = (key("a.txt"), key("b.txt"));
+
+        let (dst, conflict) = ConflictPolicy::Error
+            .resolve(&index, &a, &key("c.txt"))
+            .unwrap();
+        assert_eq!((dst.as_str(), conflict), ("c.txt", None));
+        assert!(matches!(
+            ConflictPolicy::Error.resolve(&index, &a, &b),
+            Err(Error::FileAlreadyExists(_))
+        ));
+        assert!(ConflictPolicy::Error.resolve(&index, &a, &a).is_ok());
+
+        let (dst, conflict) = ConflictPolicy::Overwrite.resolve(&index, &a, &b).unwrap();
+        assert_eq!(dst, b);
+        assert_eq!(conflict.unwrap().renamed_to, None);
+
+        let (dst, conflict) = ConflictPolicy::Rename.resolve(&index, &a, &b).unwrap();
+        assert_eq!(dst.as_str(), "b-2.txt");
+        assert_eq!(conflict.unwrap().renamed_to, Some(dst));
+
+        // Names placed earlier in the same batch count as taken.
+        let (dst, _) = ConflictPolicy::Rename
+            .resolve_with(|p| p == &b || p.as_str() == "b-1.txt", &a, &b)
+            .unwrap();
+        assert_eq!(dst.as_str(), "b-2.txt");
+        assert_eq!(ConflictPolicy::default(), ConflictPolicy::Error);
+    }
+}
@@ -0,0 +1,355 @@
+//! Wire-format versioning for serialized request/response types.
+//!
+//! Bump [`SCHEMA_VERSION`] whenever the JSON shape of any public
+//! request/response type changes incompatibly (renamed or removed fields,
+//! changed enum tags). The snapshot tests below pin the current shapes so
+//! such changes cannot land unnoticed.
+
+use serde::{Deserialize, Serialize};
+
+/// Current wire schema version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A payload tagged with the schema version it was produced under.
+///
+/// Serializes as the payload's own fields plus `schemaVersion`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Versioned<T> {
+    pub schema_version: u32,
+    #[serde(flatten)]
+    pub body: T,
+}
+
+impl<T> Versioned<T> {
+    /// Wrap `body` with the current [`SCHEMA_VERSION`].
+    pub fn new(body: T) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            body,
+        }
+    }
+
+    /// Whether this payload was produced under the current schema.
+    pub fn is_current(&self) -> bool {
+        self.schema_version == SCHEMA_VERSION
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::IndexEvent;
+    use crate::tools::MatchColumns;
+    use crate::{
+        compute_diff, EditRequest, FileChangeStatus, FindHit, FindRequest, FindResponse,
+        ModifiedFileSummary, PathKey, PreviewHunk, ReadResponse,
+    };
+    use serde::de::DeserializeOwned;
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    fn path(p: &str) -> PathKey {
+        PathKey::from_arc(Arc::from(p))
+    }
+
+    fn assert_shape<T: Serialize + DeserializeOwned>(value: &T, expected: Value) {
+        let actual = serde_json::to_value(value).unwrap();
+        assert_eq!(actual, expected);
+        // Round-trip must be lossless.
+        let back: T = serde_json::from_value(actual.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&back).unwrap(), actual);
+    }
+
+    #[test]
+    fn test_find_request_shape() {
+        assert_shape(
+            &FindRequest::default(),
+            json!({
+                "includeGlobs": null,
+                "excludeGlobs": null,
+                "prefix": null,
+                "find": "",
+                "patternRef": null,
+                "delta": 2,
+                "engineOpts": {
+                    "caseInsensitive": false,
+                    "caseFolding": "unicode",
+                    "unicode": true,
+                    "word": false,
+                    "crlf": false,
+                    "multiline": false,
+                    "dotAll": false
+                },
+                "where": "Staged",
+                "modifiedAfter": null,
+                "modifiedBefore": null,
+                "order": "path",
+                "minimal": false,
+                "includeHidden": false,
+                "includeJunk": false,
+                "includeIgnored": false,
+                "maxMatchesPerFile": null,
+                "maxTotalMatches": null,
+                "mergeAdjacent": false,
+                "globOptions": {
+                    "literalSeparator": false,
+                    "caseInsensitive": false,
+                    "backslashEscape": true
+                },
+                "timeoutMs": null,
+                "paths": null
+            }),
+        );
+    }
+
+    #[test]
+    fn test_edit_request_shape() {
+        assert_shape(
+            &EditRequest::default(),
+            json!({
+                "includeGlobs": null,
+                "excludeGlobs": null,
+                "prefix": null,
+                "find": "",
+                "patternRef": null,
+                "replace": "",
+                "preserveCase": false,
+                "delta": 2,
+                "engineOpts": {
+                    "caseInsensitive": false,
+                    "caseFolding": "unicode",
+                    "unicode": true,
+                    "word": false,
+                    "crlf": false,
+                    "multiline": false,
+                    "dotAll": false
+                },
+                "globOptions": {
+                    "literalSeparator": false,
+                    "caseInsensitive": false,
+                    "backslashEscape": true
+                }
+            }),
+        );
+    }
+
+    #[test]
+    fn test_find_response_shape() {
+        let response = FindResponse::new(
+            vec![PreviewHunk {
+                path: path("src/lib.rs"),
+                preview_start_line: 1,
+                preview_end_line: 3,
+                matched_line_ranges: vec![(2, 2)],
+                excerpt: "a\nb\nc\n".to_string(),
+                lossy: false,
+                omitted_prefix: 0,
+                omitted_suffix: 0,
+                pattern_index: None,
+                match_columns: vec![MatchColumns {
+                    line: 2,
+                    start_byte: 0,
+                    end_byte: 1,
+                    start_char: 0,
+                    end_char: 1,
+                }],
+            }],
+            vec![],
+        );
+        assert_shape(
+            &response,
+            json!({
+                "results": [{
+                    "path": "src/lib.rs",
+                    "preview_start_line": 1,
+                    "preview_end_line": 3,
+                    "matched_line_ranges": [[2, 2]],
+                    "excerpt": "a\nb\nc\n",
+                    "lossy": false,
+                    "omitted_prefix": 0,
+                    "omitted_suffix": 0,
+                    "match_columns": [{
+                        "line": 2,
+                        "start_byte": 0,
+                        "end_byte": 1,
+                        "start_char": 0,
+                        "end_char": 1
+                    }]
+                }],
+                "stats": {
+                    "total_matches": 1,
+                    "files_with_matches": 1,
+                    "files_truncated": 0,
+                    "match_limit_reached": false,
+                    "aborted": false,
+                    "skipped_unhydrated": 0
+                }
+            }),
+        );
+    }
+
+    #[test]
+    fn test_minimal_find_response_shape() {
+        let mut response = FindResponse::new(
+            vec![],
+            vec![
+                FindHit {
+                    path: path("src/lib.rs"),
+                    line: 12,
+                    length: 5,
+                    pattern_index: Some(1),
+                },
+                FindHit {
+                    path: path("src/main.rs"),
+                    line: 3,
+                    length: 5,
+                    pattern_index: None,
+                },
+            ],
+        );
+        response.truncate(1);
+        response.truncated_paths = vec![path("src/main.rs")];
+        response.stats.match_limit_reached = true;
+        assert_shape(
+            &response,
+            json!({
+                "results": [],
+                "hits": [{ "path": "src/lib.rs", "line": 12, "length": 5, "pattern_index": 1 }],
+                "stats": {
+                    "total_matches": 2,
+                    "files_with_matches": 2,
+                    "files_truncated": 1,
+                    "match_limit_reached": true,
+                    "aborted": false,
+                    "skipped_unhydrated": 0
+                },
+                "truncated_paths": ["src/main.rs"]
+            }),
+        );
+    }
+
+    #[test]
+    fn test_file_diff_shape() {
+        let diff = compute_diff(path("a.txt"), "one\ntwo\n", "one\nthree\n", 0);
+        assert_shape(
+            &diff,
+            json!({
+                "path": "a.txt",
+                "stats": {
+                    "lines_added": 1,
+                    "lines_removed": 1,
+                    "regions_changed": 1
+                },
+                "regions": [{
+                    "original_start": 2,
+                    "lines_removed": 1,
+                    "modified_start": 2,
+                    "lines_added": 1,
+                    "removed_lines": ["two"],
+                    "added_lines": ["three"]
+                }]
+            }),
+        );
+    }
+
+    #[test]
+    fn test_read_response_shape() {
+        let response = ReadResponse {
+            path: path("a.txt"),
+            start_line: 1,
+            end_line: 2,
+            content: "one\ntwo\n".to_string(),
+            total_lines: 2,
+            lossy: false,
+        };
+        assert_shape(
+            &response,
+            json!({
+                "path": "a.txt",
+                "start_line": 1,
+                "end_line": 2,
+                "content": "one\ntwo\n",
+                "total_lines": 2,
+                "lossy": false
+            }),
+        );
+    }
+
+    #[test]
+    fn test_modified_file_summary_shape() {
+        let moved = ModifiedFileSummary {
+            path: path("old.rs"),
+            lines_added: 0,
+            lines_removed: 0,
+            status: FileChangeStatus::Moved,
+            moved_to: Some(path("new.rs")),
+            similarity: None,
+        };
+        assert_shape(
+            &moved,
+            json!({
+                "path": "old.rs",
+                "lines_added": 0,
+                "lines_removed": 0,
+                "status": "moved",
+                "moved_to": "new.rs"
+            }),
+        );
+
+        let created = ModifiedFileSummary {
+            path: path("new.rs"),
+            lines_added: 3,
+            lines_removed: 0,
+            status: FileChangeStatus::Created,
+            moved_to: None,
+            similarity: None,
+        };
+        assert_shape(
+            &created,
+            json!({
+                "path": "new.rs",
+                "lines_added": 3,
+                "lines_removed": 0,
+                "status": "created"
+            }),
+        );
+    }
+
+    #[test]
+    fn test_index_event_shape() {
+        let moved = IndexEvent::FileMoved {
+            from: path("a.rs"),
+            to: path("b.rs"),
+        };
+        assert_shape(
+            &moved,
+            json!({ "type": "fileMoved", "from": "a.rs", "to": "b.rs" }),
+        );
+        assert_shape(&IndexEvent::StagingBegun, json!({ "type": "stagingBegun" }));
+    }
+
+    #[test]
+    fn test_versioned_envelope() {
+        let versioned = Versioned::new(FindResponse::new(vec![], vec![]));
+        let value = serde_json::to_value(&versioned).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "schemaVersion": SCHEMA_VERSION,
+                "results": [],
+                "stats": {
+                    "total_matches": 0,
+                    "files_with_matches": 0,
+                    "files_truncated": 0,
+                    "match_limit_reached": false,
+                    "aborted": false,
+                    "skipped_unhydrated": 0
+                }
+            })
+        );
+
+        let back: Versioned<FindResponse> = serde_json::from_value(value).unwrap();
+        assert!(back.is_current());
+    }
+}
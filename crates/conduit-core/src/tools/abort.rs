@@ -11,23 +11,40 @@
 //! Usage:
 //! - Call `abort()` from a controller; poll `is_aborted()` in hot loops or long-running tasks.
 //! - Use `reset()` only when intentionally reusing the same flag instance.
+//! - Use `with_timeout()` for a flag that also trips on a deadline. The
+//!   engine is IO-free, so the caller supplies the clock.
 
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     Arc,
 };
 
+/// Milliseconds since an arbitrary, fixed origin.
+pub type Clock = fn() -> f64;
+
 /// Cooperative cancellation flag shared across threads.
 ///
 /// Cloning an `AbortFlag` is cheap and preserves shared state: aborting via any
 /// clone is visible to all others.
 #[derive(Debug, Clone)]
-pub struct AbortFlag(Arc<AtomicBool>);
+pub struct AbortFlag {
+    flag: Arc<AtomicBool>,
+    deadline: Option<Deadline>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Deadline {
+    at_ms: f64,
+    clock: Clock,
+}
 
 impl Default for AbortFlag {
     /// Create a new, non-aborted flag.
     fn default() -> Self {
-        AbortFlag(Arc::new(AtomicBool::new(false)))
+        AbortFlag {
+            flag: Arc::new(AtomicBool::new(false)),
+            deadline: None,
+        }
     }
 }
 
@@ -43,7 +60,7 @@ impl AbortFlag {
     ///
     /// This operation is idempotent and visible to all clones.
     pub fn abort(&self) {
-        self.0.store(true, Ordering::SeqCst);
+        self.flag.store(true, Ordering::SeqCst);
     }
 
     #[inline]
@@ -51,14 +68,66 @@ impl AbortFlag {
     ///
     /// Intended for controlled reuse of the same flag instance.
     pub fn reset(&self) {
-        self.0.store(false, Ordering::SeqCst);
+        self.flag.store(false, Ordering::SeqCst);
     }
 
     #[inline]
     /// Return whether the flag has been aborted.
     ///
     /// Safe to call from hot paths; uses `SeqCst` for simple, strong ordering.
+    /// A flag with a deadline also reads the clock.
     pub fn is_aborted(&self) -> bool {
-        self.0.load(Ordering::SeqCst)
+        self.flag.load(Ordering::SeqCst) || self.timed_out()
+    }
+
+    /// A flag sharing this one's state that also reads as aborted once
+    /// `timeout_ms` have passed on `clock`.
+    ///
+    /// The deadline belongs to the returned flag only: this one and its other
+    /// clones are unaffected, and `reset()` does not clear it.
+    pub fn with_timeout(&self, timeout_ms: u64, clock: Clock) -> Self {
+        AbortFlag {
+            flag: Arc::clone(&self.flag),
+            deadline: Some(Deadline {
+                at_ms: clock() + timeout_ms as f64,
+                clock,
+            }),
+        }
+    }
+
+    /// Whether this flag's deadline, if any, has passed.
+    pub fn timed_out(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| (deadline.clock)() >= deadline.at_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicU64;
+
+    static NOW: AtomicU64 = AtomicU64::new(0);
+
+    fn clock() -> f64 {
+        NOW.load(Ordering::SeqCst) as f64
+    }
+
+    #[test]
+    fn test_timeout_trips_only_the_derived_flag() {
+        let flag = AbortFlag::new();
+        let timed = flag.with_timeout(50, clock);
+        assert!(!timed.is_aborted());
+
+        NOW.store(50, Ordering::SeqCst);
+        assert!(timed.is_aborted() && timed.timed_out());
+        assert!(!flag.is_aborted());
+        timed.reset();
+        assert!(timed.is_aborted());
+
+        // Aborting the original still reaches the derived flag.
+        let timed = flag.with_timeout(50, clock);
+        flag.abort();
+        assert!(timed.is_aborted() && !timed.timed_out());
     }
 }
@@ -0,0 +1,180 @@
+//! Locating an edit by the text it replaces instead of by line number.
+//!
+//! Line numbers go stale as soon as anything above them changes; the text
+//! being replaced does not. An anchor must match exactly one place in the
+//! file unless the caller picks an occurrence.
+
+use std::ops::Range;
+
+use regex::Regex;
+
+use crate::error::{Error, Result};
+use crate::AnchoredEditRequest;
+
+/// `content` with an anchored edit applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnchoredEdit {
+    pub content: String,
+    /// Inclusive 1-based lines of the replaced text in the original.
+    pub original_lines: (usize, usize),
+    /// Inclusive 1-based lines of the replacement in the new content.
+    pub new_lines: (usize, usize),
+    /// How many places the anchor matched.
+    pub occurrences: usize,
+}
+
+/// Apply `req` to `content`, the current text of `req.path`.
+pub fn apply_anchored_edit(content: &str, req: &AnchoredEditRequest) -> Result<AnchoredEdit> {
+    let (range, occurrences) = resolve_anchor(
+        req.path.as_str(),
+        content,
+        &req.expected_text,
+        req.occurrence,
+        req.whitespace_insensitive,
+    )?;
+    let start_line = content[..range.start].matches('\n').count() + 1;
+    let mut new_content = String::with_capacity(content.len() + req.replacement.len());
+    new_content.push_str(&content[..range.start]);
+    new_content.push_str(&req.replacement);
+    new_content.push_str(&content[range.end..]);
+
+    Ok(AnchoredEdit {
+        content: new_content,
+        original_lines: lines_spanned(start_line, &content[range]),
+        new_lines: lines_spanned(start_line, &req.replacement),
+        occurrences,
+    })
+}
+
+/// Lines covered by `text` starting on `first`; a trailing newline does not
+/// start another line.
+fn lines_spanned(first: usize, text: &str) -> (usize, usize) {
+    let body = text.strip_suffix('\n').unwrap_or(text);
+    (first, first + body.matches('\n').count())
+}
+
+/// Non-overlapping byte ranges of `content` that match `anchor`, in order.
+///
+/// With `whitespace_insensitive`, any run of whitespace in the anchor
+/// matches any run of whitespace in the content (including line breaks),
+/// and whitespace around the anchor is ignored.
+pub fn find_anchor(content: &str, anchor: &str, whitespace_insensitive: bool) -> Vec<Range<usize>> {
+    if !whitespace_insensitive {
+        if anchor.is_empty() {
+            return Vec::new();
+        }
+        return content
+            .match_indices(anchor)
+            .map(|(start, text)| start..start + text.len())
+            .collect();
+    }
+
+    let words: Vec<String> = anchor.split_whitespace().map(regex::escape).collect();
+    if words.is_empty() {
+        return Vec::new();
+    }
+    // Escaped literals joined by `\s+` always form a valid pattern.
+    let Ok(re) = Regex::new(&words.join(r"\s+")) else {
+        return Vec::new();
+    };
+    re.find_iter(content).map(|m| m.range()).collect()
+}
+
+/// The range of `content` an anchored edit replaces.
+///
+/// `occurrence` is 1-based; without it the anchor must be unique.
+pub fn resolve_anchor(
+    path: &str,
+    content: &str,
+    anchor: &str,
+    occurrence: Option<usize>,
+    whitespace_insensitive: bool,
+) -> Result<(Range<usize>, usize)> {
+    let matches = find_anchor(content, anchor, whitespace_insensitive);
+    let count = matches.len();
+    let range = match occurrence {
+        None if count > 1 => {
+            return Err(Error::AmbiguousAnchor {
+                path: path.to_string(),
+                count,
+            })
+        }
+        None => matches.into_iter().next(),
+        Some(n) => matches.into_iter().nth(n.wrapping_sub(1)),
+    };
+    match (range, occurrence) {
+        (Some(range), _) => Ok((range, count)),
+        (None, Some(n)) => Err(Error::AnchorNotFound(format!(
+            "{path} (occurrence {n} requested, {count} found)"
+        ))),
+        (None, None) => Err(Error::AnchorNotFound(path.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_anchor() {
+        let content = "fn a() {\n    x(1,  2);\n}\nfn b() {\n  x(1, 2);\n}\n";
+
+        assert_eq!(find_anchor(content, "x(1, 2);", false), vec![36..44]);
+        let loose = find_anchor(content, "  x(1, 2);\n", true);
+        assert_eq!(loose.len(), 2);
+        assert_eq!(&content[loose[0].clone()], "x(1,  2);");
+        assert!(find_anchor(content, " \n", true).is_empty());
+        assert!(find_anchor(content, "", false).is_empty());
+    }
+
+    #[test]
+    fn test_apply_anchored_edit() {
+        let req = |expected: &str, replacement: &str| AnchoredEditRequest {
+            path: crate::PathKey::from_arc(std::sync::Arc::from("a.rs")),
+            expected_text: expected.to_string(),
+            replacement: replacement.to_string(),
+            occurrence: None,
+            whitespace_insensitive: false,
+            precondition: None,
+        };
+        let content = "a\nb\nc\nd\n";
+
+        let edit = apply_anchored_edit(content, &req("b\nc\n", "x\n")).unwrap();
+        assert_eq!(edit.content, "a\nx\nd\n");
+        assert_eq!(edit.original_lines, (2, 3));
+        assert_eq!(edit.new_lines, (2, 2));
+        assert_eq!(edit.occurrences, 1);
+
+        let edit = apply_anchored_edit(content, &req("c", "c1\nc2")).unwrap();
+        assert_eq!(edit.content, "a\nb\nc1\nc2\nd\n");
+        assert_eq!((edit.original_lines, edit.new_lines), ((3, 3), (3, 4)));
+    }
+
+    #[test]
+    fn test_resolve_anchor() {
+        let content = "one two one";
+
+        assert!(matches!(
+            resolve_anchor("f", content, "one", None, false),
+            Err(Error::AmbiguousAnchor { count: 2, .. })
+        ));
+        assert_eq!(
+            resolve_anchor("f", content, "one", Some(2), false).unwrap(),
+            (8..11, 2)
+        );
+        assert_eq!(
+            resolve_anchor("f", content, "two", None, false).unwrap(),
+            (4..7, 1)
+        );
+        for occurrence in [Some(0), Some(3)] {
+            assert!(matches!(
+                resolve_anchor("f", content, "one", occurrence, false),
+                Err(Error::AnchorNotFound(_))
+            ));
+        }
+        assert!(matches!(
+            resolve_anchor("f", content, "three", None, false),
+            Err(Error::AnchorNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,863 @@
+//! Zip and tar archives of staged changes and of projects to load.
+//!
+//! Hosts that hand a whole change set to a download or an upload would
+//! otherwise copy every file across the module boundary and bundle them in
+//! JavaScript. [`ArchiveWriter`] builds the archive here instead; a
+//! [`ChangeManifest`] stored alongside the files records what cannot be
+//! expressed as file content, such as deletions and moves.
+//! [`read_archive`] goes the other way, unpacking an uploaded project.
+//!
+//! Only what the exports need is written: regular files, UTF-8 names,
+//! deflated zip entries without zip64, and ustar tar entries with PAX
+//! headers for long paths. Reading also accepts stored zip entries, GNU
+//! long names and gzip-compressed tar; directories, links and other special
+//! entries are skipped.
+
+use std::io::{Read, Write};
+
+use flate2::read::{DeflateDecoder, GzDecoder};
+use flate2::write::DeflateEncoder;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::schema::SCHEMA_VERSION;
+use crate::{FileOperation, PathKey};
+
+/// Archive path of the [`ChangeManifest`].
+pub const CHANGE_MANIFEST_PATH: &str = ".conduit/changes.json";
+
+/// Archive container formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveFormat {
+    Zip,
+    Tar,
+}
+
+impl std::str::FromStr for ArchiveFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "zip" => Ok(Self::Zip),
+            "tar" => Ok(Self::Tar),
+            other => Err(Error::InvalidFormat(other.to_string())),
+        }
+    }
+}
+
+/// Changes in an archive that are not file contents.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ChangeManifest {
+    pub schema_version: u32,
+    /// Files deleted from the active index.
+    pub deleted: Vec<PathKey>,
+    /// Files moved; their new content is stored under `dst`.
+    pub moved: Vec<FileOperation>,
+}
+
+impl ChangeManifest {
+    pub fn new(deleted: Vec<PathKey>, moved: Vec<FileOperation>) -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            deleted,
+            moved,
+        }
+    }
+}
+
+/// Zip entry written so far, kept for the central directory.
+struct ZipEntry {
+    name: String,
+    crc: u32,
+    compressed: u32,
+    size: u32,
+    dos_time: u16,
+    dos_date: u16,
+    offset: u32,
+}
+
+/// Builds a zip or tar archive in memory.
+pub struct ArchiveWriter {
+    format: ArchiveFormat,
+    out: Vec<u8>,
+    zip_entries: Vec<ZipEntry>,
+}
+
+impl ArchiveWriter {
+    pub fn new(format: ArchiveFormat) -> Self {
+        Self {
+            format,
+            out: Vec::new(),
+            zip_entries: Vec::new(),
+        }
+    }
+
+    /// Append a regular file; `mtime` is in unix seconds.
+    pub fn add_file(&mut self, path: &str, content: &[u8], mtime: i64) -> Result<()> {
+        match self.format {
+            ArchiveFormat::Zip => self.add_zip_file(path, content, mtime),
+            ArchiveFormat::Tar => self.add_tar_file(path, content, mtime),
+        }
+    }
+
+    /// Append `manifest` as JSON at [`CHANGE_MANIFEST_PATH`].
+    pub fn add_manifest(&mut self, manifest: &ChangeManifest, mtime: i64) -> Result<()> {
+        let json = serde_json::to_vec_pretty(manifest)
+            .map_err(|e| Error::Archive(format!("cannot encode manifest: {e}")))?;
+        self.add_file(CHANGE_MANIFEST_PATH, &json, mtime)
+    }
+
+    /// Write the trailing records and return the archive bytes.
+    pub fn finish(mut self) -> Result<Vec<u8>> {
+        match self.format {
+            ArchiveFormat::Zip => self.finish_zip()?,
+            // Two zero blocks mark the end of a tar archive.
+            ArchiveFormat::Tar => self.out.extend_from_slice(&[0; 2 * TAR_BLOCK]),
+        }
+        Ok(self.out)
+    }
+
+    fn add_zip_file(&mut self, path: &str, content: &[u8], mtime: i64) -> Result<()> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(content)?;
+        let deflated = encoder.finish()?;
+        let mut crc = flate2::Crc::new();
+        crc.update(content);
+        let (dos_time, dos_date) = dos_datetime(mtime);
+
+        let entry = ZipEntry {
+            name: path.to_string(),
+            crc: crc.sum(),
+            compressed: zip_u32(deflated.len())?,
+            size: zip_u32(content.len())?,
+            dos_time,
+            dos_date,
+            offset: zip_u32(self.out.len())?,
+        };
+        let name_len = zip_u16(entry.name.len())?;
+
+        let out = &mut self.out;
+        out.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        out.extend_from_slice(&ZIP_VERSION.to_le_bytes());
+        out.extend_from_slice(&ZIP_UTF8_FLAG.to_le_bytes());
+        out.extend_from_slice(&ZIP_DEFLATE.to_le_bytes());
+        out.extend_from_slice(&entry.dos_time.to_le_bytes());
+        out.extend_from_slice(&entry.dos_date.to_le_bytes());
+        out.extend_from_slice(&entry.crc.to_le_bytes());
+        out.extend_from_slice(&entry.compressed.to_le_bytes());
+        out.extend_from_slice(&entry.size.to_le_bytes());
+        out.extend_from_slice(&name_len.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes()); // extra field length
+        out.extend_from_slice(entry.name.as_bytes());
+        out.extend_from_slice(&deflated);
+
+        self.zip_entries.push(entry);
+        Ok(())
+    }
+
+    fn finish_zip(&mut self) -> Result<()> {
+        let directory_offset = zip_u32(self.out.len())?;
+        for entry in &self.zip_entries {
+            let out = &mut self.out;
+            out.extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            out.extend_from_slice(&ZIP_VERSION.to_le_bytes()); // made by
+            out.extend_from_slice(&ZIP_VERSION.to_le_bytes()); // needed
+            out.extend_from_slice(&ZIP_UTF8_FLAG.to_le_bytes());
+            out.extend_from_slice(&ZIP_DEFLATE.to_le_bytes());
+            out.extend_from_slice(&entry.dos_time.to_le_bytes());
+            out.extend_from_slice(&entry.dos_date.to_le_bytes());
+            out.extend_from_slice(&entry.crc.to_le_bytes());
+            out.extend_from_slice(&entry.compressed.to_le_bytes());
+            out.extend_from_slice(&entry.size.to_le_bytes());
+            out.extend_from_slice(&(entry.name.len() as u16).to_le_bytes());
+            out.extend_from_slice(&[0; 8]); // extra, comment, disk, internal attrs
+            out.extend_from_slice(&0u32.to_le_bytes()); // external attrs
+            out.extend_from_slice(&entry.offset.to_le_bytes());
+            out.extend_from_slice(entry.name.as_bytes());
+        }
+        let directory_size = zip_u32(self.out.len())? - directory_offset;
+        let count = zip_u16(self.zip_entries.len())?;
+
+        let out = &mut self.out;
+        out.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        out.extend_from_slice(&[0; 4]); // disk numbers
+        out.extend_from_slice(&count.to_le_bytes());
+        out.extend_from_slice(&count.to_le_bytes());
+        out.extend_from_slice(&directory_size.to_le_bytes());
+        out.extend_from_slice(&directory_offset.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        Ok(())
+    }
+
+    fn add_tar_file(&mut self, path: &str, content: &[u8], mtime: i64) -> Result<()> {
+        let split = split_ustar_name(path);
+        if split.is_none() {
+            // Record the full path in a PAX extended header; the ustar name
+            // that follows is only a fallback for old readers.
+            let record = pax_record("path", path);
+            self.push_tar_header("././@PaxHeader", "", record.len() as u64, mtime, b'x');
+            self.push_tar_data(record.as_bytes());
+        }
+        let (prefix, name) = split.unwrap_or(("", truncate_utf8(path, 100)));
+        self.push_tar_header(name, prefix, content.len() as u64, mtime, b'0');
+        self.push_tar_data(content);
+        Ok(())
+    }
+
+    fn push_tar_header(&mut self, name: &str, prefix: &str, size: u64, mtime: i64, kind: u8) {
+        let mut header = [0u8; TAR_BLOCK];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        write_octal(&mut header[100..108], 0o644);
+        write_octal(&mut header[108..116], 0);
+        write_octal(&mut header[116..124], 0);
+        write_octal(&mut header[124..136], size);
+        write_octal(&mut header[136..148], mtime.max(0) as u64);
+        header[156] = kind;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header[263..265].copy_from_slice(b"00");
+        header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+
+        // The checksum is computed with its own field read as spaces.
+        header[148..156].fill(b' ');
+        let checksum: u32 = header.iter().map(|&b| b as u32).sum();
+        write_octal(&mut header[148..155], checksum as u64);
+        header[155] = b' ';
+
+        self.out.extend_from_slice(&header);
+    }
+
+    fn push_tar_data(&mut self, data: &[u8]) {
+        self.out.extend_from_slice(data);
+        let padding = (TAR_BLOCK - data.len() % TAR_BLOCK) % TAR_BLOCK;
+        self.out.resize(self.out.len() + padding, 0);
+    }
+}
+
+/// A regular file unpacked by [`read_archive`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+    /// Path inside the archive, without a leading `./`.
+    pub path: String,
+    pub content: Vec<u8>,
+    /// Unix seconds; `0` when the archive does not record one.
+    pub mtime: i64,
+}
+
+/// Most bytes [`read_archive`] unpacks in total, so a small archive cannot
+/// claim or inflate to more memory than a page can hold.
+pub const MAX_UNPACKED_BYTES: u64 = 1 << 30;
+
+/// Deflate cannot expand data by more than this factor.
+const MAX_DEFLATE_RATIO: usize = 1032;
+
+/// Unpack the regular files of a zip, tar or gzip-compressed tar archive,
+/// detected from its leading bytes.
+pub fn read_archive(bytes: &[u8]) -> Result<Vec<ArchiveEntry>> {
+    read_archive_with(bytes, |_, _| Ok(true))
+}
+
+/// [`read_archive`], asking `admit` about each file before unpacking it.
+///
+/// `admit` gets the cleaned path and the size the archive declares, and
+/// returns whether to keep the file; skipped files are never inflated. An
+/// error from `admit` ends the read, which lets callers enforce a memory
+/// budget. Files larger than declared fail their checksum.
+pub fn read_archive_with(
+    bytes: &[u8],
+    mut admit: impl FnMut(&str, u64) -> Result<bool>,
+) -> Result<Vec<ArchiveEntry>> {
+    let mut budget = Budget {
+        admit: &mut admit,
+        unpacked: 0,
+    };
+    if bytes.starts_with(b"PK\x03\x04") || bytes.starts_with(b"PK\x05\x06") {
+        return read_zip(bytes, &mut budget);
+    }
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut tar = Vec::new();
+        GzDecoder::new(bytes)
+            .take(MAX_UNPACKED_BYTES + 1)
+            .read_to_end(&mut tar)
+            .map_err(|e| Error::Archive(format!("invalid gzip data: {e}")))?;
+        if tar.len() as u64 > MAX_UNPACKED_BYTES {
+            return Err(too_large());
+        }
+        return read_tar(&tar, &mut budget);
+    }
+    if bytes.is_empty() || (bytes.len() >= TAR_BLOCK && &bytes[257..262] == b"ustar") {
+        return read_tar(bytes, &mut budget);
+    }
+    Err(Error::Archive("not a zip or tar archive".into()))
+}
+
+/// Files admitted so far and the bytes they unpack to.
+struct Budget<'a> {
+    admit: &'a mut dyn FnMut(&str, u64) -> Result<bool>,
+    unpacked: u64,
+}
+
+impl Budget<'_> {
+    fn admit(&mut self, path: &str, size: u64) -> Result<bool> {
+        let unpacked = self
+            .unpacked
+            .checked_add(size)
+            .filter(|&n| n <= MAX_UNPACKED_BYTES)
+            .ok_or_else(too_large)?;
+        if !(self.admit)(path, size)? {
+            return Ok(false);
+        }
+        self.unpacked = unpacked;
+        Ok(true)
+    }
+}
+
+fn too_large() -> Error {
+    Error::Archive(format!(
+        "archive unpacks to more than {MAX_UNPACKED_BYTES} bytes"
+    ))
+}
+
+/// `base` plus each of `offsets`, or the truncation error on overflow.
+fn offset(base: usize, offsets: &[usize]) -> Result<usize> {
+    offsets
+        .iter()
+        .try_fold(base, |at, &n| at.checked_add(n))
+        .ok_or_else(truncated)
+}
+
+/// `len` bytes of `b` from `at`.
+fn span(b: &[u8], at: usize, len: usize) -> Result<&[u8]> {
+    b.get(at..offset(at, &[len])?).ok_or_else(truncated)
+}
+
+fn le16(b: &[u8], at: usize) -> Result<u16> {
+    span(b, at, 2).map(|s| u16::from_le_bytes([s[0], s[1]]))
+}
+
+fn le32(b: &[u8], at: usize) -> Result<u32> {
+    span(b, at, 4).map(|s| u32::from_le_bytes([s[0], s[1], s[2], s[3]]))
+}
+
+fn truncated() -> Error {
+    Error::Archive("archive is truncated".into())
+}
+
+fn read_zip(bytes: &[u8], budget: &mut Budget) -> Result<Vec<ArchiveEntry>> {
+    // The end-of-directory record is last, followed only by a comment.
+    let eocd = (0..=bytes.len().saturating_sub(22))
+        .rev()
+        .take(22 + u16::MAX as usize)
+        .find(|&i| bytes[i..].starts_with(b"PK\x05\x06"))
+        .ok_or_else(|| Error::Archive("zip end of central directory not found".into()))?;
+    let count = le16(bytes, eocd + 10)? as usize;
+    let mut pos = le32(bytes, eocd + 16)? as usize;
+    if count == u16::MAX as usize || pos == u32::MAX as usize {
+        return Err(Error::Archive("zip64 archives are not supported".into()));
+    }
+
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0..count {
+        // With the fixed-size header in bounds, offsets into it cannot overflow.
+        if le32(span(bytes, pos, 46)?, 0)? != 0x0201_4b50 {
+            return Err(Error::Archive("corrupt zip central directory".into()));
+        }
+        let flags = le16(bytes, pos + 8)?;
+        let method = le16(bytes, pos + 10)?;
+        let (dos_time, dos_date) = (le16(bytes, pos + 12)?, le16(bytes, pos + 14)?);
+        let crc = le32(bytes, pos + 16)?;
+        let compressed = le32(bytes, pos + 20)? as usize;
+        let size = le32(bytes, pos + 24)?;
+        let name_len = le16(bytes, pos + 28)? as usize;
+        let extra_len = le16(bytes, pos + 30)? as usize;
+        let comment_len = le16(bytes, pos + 32)? as usize;
+        let local = le32(bytes, pos + 42)? as usize;
+        let name = span(bytes, offset(pos, &[46])?, name_len)?;
+        let extra = span(bytes, offset(pos, &[46, name_len])?, extra_len)?;
+        pos = offset(pos, &[46, name_len, extra_len, comment_len])?;
+
+        let name = String::from_utf8_lossy(name);
+        if name.ends_with('/') {
+            continue;
+        }
+        let path = clean_archive_path(&name);
+        if !budget.admit(&path, u64::from(size))? {
+            continue;
+        }
+        if flags & 1 != 0 {
+            return Err(Error::Archive(format!("{name} is encrypted")));
+        }
+
+        let local_name_len = le16(bytes, offset(local, &[26])?)? as usize;
+        let local_extra_len = le16(bytes, offset(local, &[28])?)? as usize;
+        let start = offset(local, &[30, local_name_len, local_extra_len])?;
+        let data = span(bytes, start, compressed)?;
+        let content = match method {
+            0 => data.to_vec(),
+            ZIP_DEFLATE => {
+                // The declared size is untrusted: preallocate no more than
+                // deflate can produce, and read one byte past it so a
+                // longer entry fails the size check below.
+                let capacity = (size as usize).min(data.len().saturating_mul(MAX_DEFLATE_RATIO));
+                let mut out = Vec::with_capacity(capacity);
+                DeflateDecoder::new(data)
+                    .take(u64::from(size) + 1)
+                    .read_to_end(&mut out)
+                    .map_err(|e| Error::Archive(format!("{name}: {e}")))?;
+                out
+            }
+            other => {
+                return Err(Error::Archive(format!(
+                    "{name} uses unsupported compression method {other}"
+                )))
+            }
+        };
+        let mut check = flate2::Crc::new();
+        check.update(&content);
+        if check.sum() != crc || content.len() as u64 != u64::from(size) {
+            return Err(Error::Archive(format!("{name} failed its checksum")));
+        }
+
+        entries.push(ArchiveEntry {
+            path,
+            content,
+            mtime: extended_mtime(extra).unwrap_or_else(|| unix_from_dos(dos_time, dos_date)),
+        });
+    }
+    Ok(entries)
+}
+
+/// Modification time from a zip "extended timestamp" extra field, which
+/// unlike the DOS fields is exact and in UTC.
+fn extended_mtime(mut extra: &[u8]) -> Option<i64> {
+    while extra.len() >= 4 {
+        let id = u16::from_le_bytes([extra[0], extra[1]]);
+        let len = u16::from_le_bytes([extra[2], extra[3]]) as usize;
+        let data = extra.get(4..4 + len)?;
+        if id == 0x5455 && data.len() >= 5 && data[0] & 1 != 0 {
+            return Some(i32::from_le_bytes([data[1], data[2], data[3], data[4]]) as i64);
+        }
+        extra = &extra[4 + len..];
+    }
+    None
+}
+
+fn read_tar(bytes: &[u8], budget: &mut Budget) -> Result<Vec<ArchiveEntry>> {
+    let mut entries = Vec::new();
+    // Overrides from a PAX or GNU header, for the entry that follows.
+    let mut next_path: Option<String> = None;
+    let mut next_mtime: Option<i64> = None;
+    let mut pos = 0;
+    while let Ok(header) = span(bytes, pos, TAR_BLOCK) {
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        let stored: u32 = octal(&header[148..156])? as u32;
+        let sum: u32 = header
+            .iter()
+            .enumerate()
+            .map(|(i, &b)| {
+                if (148..156).contains(&i) {
+                    b' ' as u32
+                } else {
+                    b as u32
+                }
+            })
+            .sum();
+        if stored != sum {
+            return Err(Error::Archive("corrupt tar header".into()));
+        }
+
+        let size = usize::try_from(tar_number(&header[124..136])?).map_err(|_| truncated())?;
+        pos += TAR_BLOCK;
+        let data = span(bytes, pos, size)?;
+        // `data` lies within `bytes`, so rounding up to a block cannot overflow.
+        pos += size.div_ceil(TAR_BLOCK) * TAR_BLOCK;
+
+        match header[156] {
+            b'x' => {
+                for (key, value) in pax_records(data) {
+                    match key {
+                        "path" => next_path = Some(value.to_string()),
+                        "mtime" => {
+                            next_mtime = value.split('.').next().and_then(|s| s.parse().ok())
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            b'L' => {
+                let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+                next_path = Some(String::from_utf8_lossy(&data[..end]).into_owned());
+            }
+            b'0' | 0 | b'7' => {
+                let path = next_path.take().unwrap_or_else(|| {
+                    let name = c_string(&header[0..100]);
+                    match c_string(&header[345..500]) {
+                        prefix if !prefix.is_empty() && &header[257..262] == b"ustar" => {
+                            format!("{prefix}/{name}")
+                        }
+                        _ => name,
+                    }
+                });
+                let mtime = match next_mtime.take() {
+                    Some(mtime) => mtime,
+                    None => tar_number(&header[136..148])? as i64,
+                };
+                let path = clean_archive_path(&path);
+                if !path.ends_with('/') && budget.admit(&path, data.len() as u64)? {
+                    entries.push(ArchiveEntry {
+                        path,
+                        content: data.to_vec(),
+                        mtime,
+                    });
+                }
+            }
+            // Directories, links, devices and global headers.
+            _ => {
+                next_path = None;
+                next_mtime = None;
+            }
+        }
+    }
+    Ok(entries)
+}
+
+fn c_string(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+fn octal(field: &[u8]) -> Result<u64> {
+    let text = std::str::from_utf8(field).map_err(|_| bad_number())?;
+    let text = text.trim_matches(|c: char| c == '\0' || c == ' ');
+    if text.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(text, 8).map_err(|_| bad_number())
+}
+
+/// An octal field, or a big-endian base-256 one when the high bit is set.
+fn tar_number(field: &[u8]) -> Result<u64> {
+    if field[0] & 0x80 == 0 {
+        return octal(field);
+    }
+    let mut value = u64::from(field[0] & 0x7f);
+    for &b in &field[1..] {
+        value = value.checked_mul(256).ok_or_else(bad_number)? | u64::from(b);
+    }
+    Ok(value)
+}
+
+fn bad_number() -> Error {
+    Error::Archive("corrupt tar header".into())
+}
+
+/// Key/value pairs of PAX records; malformed records end the list.
+fn pax_records(data: &[u8]) -> Vec<(&str, &str)> {
+    let mut records = Vec::new();
+    let mut rest = data;
+    while let Some(space) = rest.iter().position(|&b| b == b' ') {
+        let Some(len) = std::str::from_utf8(&rest[..space])
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .filter(|&len| len > space + 1 && len <= rest.len())
+        else {
+            break;
+        };
+        let record = &rest[space + 1..len - 1];
+        if let Some((key, value)) = std::str::from_utf8(record)
+            .ok()
+            .and_then(|r| r.split_once('='))
+        {
+            records.push((key, value));
+        }
+        rest = &rest[len..];
+    }
+    records
+}
+
+fn clean_archive_path(path: &str) -> String {
+    let mut path = path.trim_start_matches('/');
+    while let Some(rest) = path.strip_prefix("./") {
+        path = rest;
+    }
+    path.to_string()
+}
+
+const TAR_BLOCK: usize = 512;
+const ZIP_VERSION: u16 = 20;
+/// General purpose flag bit 11: names are UTF-8.
+const ZIP_UTF8_FLAG: u16 = 1 << 11;
+const ZIP_DEFLATE: u16 = 8;
+
+fn zip_u32(n: usize) -> Result<u32> {
+    u32::try_from(n).map_err(|_| Error::Archive("zip archives are limited to 4 GiB".into()))
+}
+
+fn zip_u16(n: usize) -> Result<u16> {
+    u16::try_from(n).map_err(|_| Error::Archive("zip archives are limited to 65535 files".into()))
+}
+
+/// Split `path` into ustar `prefix` and `name` fields, if it fits.
+fn split_ustar_name(path: &str) -> Option<(&str, &str)> {
+    if path.len() <= 100 {
+        return Some(("", path));
+    }
+    path.match_indices('/')
+        .map(|(i, _)| (&path[..i], &path[i + 1..]))
+        .find(|(prefix, name)| prefix.len() <= 155 && name.len() <= 100 && !name.is_empty())
+}
+
+fn truncate_utf8(s: &str, max: usize) -> &str {
+    let mut end = s.len().min(max);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// A PAX record, `"<len> <key>=<value>\n"`, where `len` counts itself.
+fn pax_record(key: &str, value: &str) -> String {
+    let body = format!(" {key}={value}\n");
+    let mut len = body.len() + 1;
+    while len.to_string().len() + body.len() != len {
+        len += 1;
+    }
+    format!("{len}{body}")
+}
+
+/// Zero-padded octal, NUL-terminated, filling `field`.
+fn write_octal(field: &mut [u8], value: u64) {
+    let digits = format!("{:0width$o}", value, width = field.len() - 1);
+    let digits = &digits.as_bytes()[digits.len() - (field.len() - 1)..];
+    field[..digits.len()].copy_from_slice(digits);
+    field[field.len() - 1] = 0;
+}
+
+/// MS-DOS time and date for unix seconds `mtime` in UTC, clamped to the
+/// 1980–2107 range DOS dates can express.
+fn dos_datetime(mtime: i64) -> (u16, u16) {
+    const MIN: i64 = 315_532_800; // 1980-01-01
+    const MAX: i64 = 4_354_819_198; // 2107-12-31 23:59:58
+    let secs = mtime.clamp(MIN, MAX);
+    let (days, rem) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+
+    // Civil date from days since the unix epoch (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    let time = ((rem / 3600) << 11) | ((rem % 3600 / 60) << 5) | (rem % 60 / 2);
+    let date = ((year - 1980) << 9) | (month << 5) | day;
+    (time as u16, date as u16)
+}
+
+/// Unix seconds for MS-DOS time and date fields, read as UTC.
+fn unix_from_dos(time: u16, date: u16) -> i64 {
+    let year = i64::from(date >> 9) + 1980;
+    let month = i64::from((date >> 5) & 0xF).clamp(1, 12);
+    let day = i64::from(date & 0x1F).max(1);
+
+    // Days since the unix epoch from a civil date (Howard Hinnant's algorithm).
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    let days = era * 146_097 + doe - 719_468;
+
+    let secs = i64::from(time >> 11) * 3600
+        + i64::from((time >> 5) & 0x3F) * 60
+        + i64::from(time & 0x1F) * 2;
+    days * 86_400 + secs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tar_entries(archive: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let mut entries = Vec::new();
+        let mut pos = 0;
+        while archive[pos..pos + TAR_BLOCK].iter().any(|&b| b != 0) {
+            let header = &archive[pos..pos + TAR_BLOCK];
+            let field = |r: std::ops::Range<usize>| {
+                let raw = &header[r];
+                let end = raw.iter().position(|&b| b == 0).unwrap_or(raw.len());
+                String::from_utf8(raw[..end].to_vec()).unwrap()
+            };
+            let size = usize::from_str_radix(&field(124..135), 8).unwrap();
+            let name = match field(345..500).as_str() {
+                "" => field(0..100),
+                prefix => format!("{prefix}/{}", field(0..100)),
+            };
+            pos += TAR_BLOCK;
+            entries.push((name, archive[pos..pos + size].to_vec()));
+            pos += size.div_ceil(TAR_BLOCK) * TAR_BLOCK;
+        }
+        entries
+    }
+
+    #[test]
+    fn test_tar_layout_and_long_paths() {
+        let long = format!("{}/{}.rs", "d".repeat(120), "f".repeat(101));
+        let mut writer = ArchiveWriter::new(ArchiveFormat::Tar);
+        writer.add_file("src/lib.rs", b"fn main() {}\n", 0).unwrap();
+        writer.add_file(&long, b"x", 0).unwrap();
+        let archive = writer.finish().unwrap();
+        assert_eq!(archive.len() % TAR_BLOCK, 0);
+
+        let entries = tar_entries(&archive);
+        assert_eq!(
+            entries[0],
+            ("src/lib.rs".into(), b"fn main() {}\n".to_vec())
+        );
+        // The long path does not fit ustar fields and gets a PAX header.
+        assert_eq!(entries[1].0, "././@PaxHeader");
+        assert_eq!(
+            String::from_utf8(entries[1].1.clone()).unwrap(),
+            pax_record("path", &long)
+        );
+        assert_eq!(entries[2].1, b"x");
+    }
+
+    #[test]
+    fn test_zip_records() {
+        let mut writer = ArchiveWriter::new(ArchiveFormat::Zip);
+        writer.add_file("a.txt", b"hello hello hello", 0).unwrap();
+        writer
+            .add_manifest(&ChangeManifest::new(Vec::new(), Vec::new()), 0)
+            .unwrap();
+        let archive = writer.finish().unwrap();
+
+        assert_eq!(&archive[..4], b"PK\x03\x04");
+        let eocd = &archive[archive.len() - 22..];
+        assert_eq!(&eocd[..4], b"PK\x05\x06");
+        assert_eq!(u16::from_le_bytes([eocd[10], eocd[11]]), 2);
+        let directory = u32::from_le_bytes(eocd[16..20].try_into().unwrap()) as usize;
+        assert_eq!(&archive[directory..directory + 4], b"PK\x01\x02");
+    }
+
+    #[test]
+    fn test_archives_round_trip() {
+        let long = format!("{}/{}.rs", "d".repeat(120), "f".repeat(101));
+        for format in [ArchiveFormat::Zip, ArchiveFormat::Tar] {
+            let mut writer = ArchiveWriter::new(format);
+            writer
+                .add_file("src/lib.rs", b"fn lib() {}\n", 1_709_214_330)
+                .unwrap();
+            writer.add_file(&long, b"", 1_709_214_330).unwrap();
+            let entries = read_archive(&writer.finish().unwrap()).unwrap();
+
+            let paths: Vec<_> = entries.iter().map(|e| e.path.as_str()).collect();
+            assert_eq!(paths, ["src/lib.rs", long.as_str()], "{format:?}");
+            assert_eq!(entries[0].content, b"fn lib() {}\n");
+            assert_eq!(entries[0].mtime, 1_709_214_330);
+        }
+    }
+
+    #[test]
+    fn test_read_archive_rejects_garbage() {
+        assert_eq!(read_archive(b"hello").unwrap_err().code(), "archive");
+        let mut writer = ArchiveWriter::new(ArchiveFormat::Zip);
+        writer.add_file("a.txt", b"abc", 0).unwrap();
+        let mut zip = writer.finish().unwrap();
+        // Corrupt the first byte of the deflated data.
+        zip[30 + "a.txt".len()] ^= 0xff;
+        assert!(read_archive(&zip).is_err());
+        assert!(read_archive(&[]).unwrap().is_empty());
+    }
+
+    /// A one-file zip and the offset of its central directory record.
+    fn one_file_zip(content: &[u8]) -> (Vec<u8>, usize) {
+        let mut writer = ArchiveWriter::new(ArchiveFormat::Zip);
+        writer.add_file("a.txt", content, 0).unwrap();
+        let zip = writer.finish().unwrap();
+        let eocd = zip.len() - 22;
+        let directory = u32::from_le_bytes(zip[eocd + 16..eocd + 20].try_into().unwrap());
+        (zip, directory as usize)
+    }
+
+    #[test]
+    fn test_read_archive_rejects_lying_headers() {
+        let (zip, directory) = one_file_zip(&[b'a'; 4096]);
+
+        // A declared size past the limit is refused before inflating.
+        let mut huge = zip.clone();
+        huge[directory + 24..directory + 28].copy_from_slice(&u32::MAX.to_le_bytes());
+        let err = read_archive(&huge).unwrap_err();
+        assert!(err.to_string().contains("more than"), "{err}");
+
+        // Inflating past a small declared size stops and fails the check.
+        let mut small = zip.clone();
+        small[directory + 24..directory + 28].copy_from_slice(&16u32.to_le_bytes());
+        let err = read_archive(&small).unwrap_err();
+        assert!(err.to_string().contains("checksum"), "{err}");
+
+        // Offsets that overflow are truncation errors, not panics.
+        let mut far = zip.clone();
+        far[directory + 42..directory + 46].copy_from_slice(&u32::MAX.to_le_bytes());
+        far[directory + 20..directory + 24].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(read_archive(&far).unwrap_err().code(), "archive");
+
+        let mut writer = ArchiveWriter::new(ArchiveFormat::Tar);
+        writer.add_file("a.txt", b"abc", 0).unwrap();
+        let mut tar = writer.finish().unwrap();
+        // A base-256 size of 2^56 - 1, with the checksum fixed up.
+        tar[124..129].copy_from_slice(&[0x80, 0, 0, 0, 0]);
+        tar[129..136].fill(0xff);
+        tar[148..156].fill(b' ');
+        let sum: u32 = tar[..TAR_BLOCK].iter().map(|&b| b as u32).sum();
+        write_octal(&mut tar[148..155], sum as u64);
+        assert_eq!(read_archive(&tar).unwrap_err().code(), "archive");
+    }
+
+    #[test]
+    fn test_read_archive_with_skips_before_inflating() {
+        let (mut zip, _) = one_file_zip(b"abc");
+        // A skipped entry is never inflated, so corrupt data goes unnoticed.
+        zip[30 + "a.txt".len()] ^= 0xff;
+        let mut seen = Vec::new();
+        let entries = read_archive_with(&zip, |path, size| {
+            seen.push((path.to_string(), size));
+            Ok(false)
+        })
+        .unwrap();
+        assert!(entries.is_empty());
+        assert_eq!(seen, [("a.txt".to_string(), 3)]);
+
+        let err = read_archive_with(&zip, |_, _| Err(Error::Archive("over budget".into())));
+        assert_eq!(err.unwrap_err().to_string(), "archive error: over budget");
+    }
+
+    #[test]
+    fn test_dos_datetime() {
+        // 2024-02-29 13:45:30 UTC
+        let (time, date) = dos_datetime(1_709_214_330);
+        assert_eq!((date >> 9) + 1980, 2024);
+        assert_eq!((date >> 5) & 0xF, 2);
+        assert_eq!(date & 0x1F, 29);
+        assert_eq!(
+            (time >> 11, (time >> 5) & 0x3F, (time & 0x1F) * 2),
+            (13, 45, 30)
+        );
+        assert_eq!(dos_datetime(0), (0, (1 << 5) | 1));
+    }
+
+    #[test]
+    fn test_pax_record_length_counts_itself() {
+        let record = pax_record("path", "abc");
+        assert_eq!(record, "12 path=abc\n");
+        assert_eq!(record.len(), 12);
+    }
+}
@@ -0,0 +1,155 @@
+//! Markdown rendering of the staged change summary.
+//!
+//! Output depends only on the summaries, never on their order, so hosts
+//! can paste it into PR descriptions or diff it across runs.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use crate::{FileChangeStatus, ModifiedFileSummary};
+
+/// Render `summaries` as a markdown report grouped by directory.
+///
+/// Directories and the files within them are sorted by path. Moved files
+/// are listed under their original directory with an arrow to the full
+/// destination path.
+pub fn render_change_summary(summaries: &[ModifiedFileSummary]) -> String {
+    if summaries.is_empty() {
+        return "No changes.\n".to_string();
+    }
+
+    let added: usize = summaries.iter().map(|s| s.lines_added).sum();
+    let removed: usize = summaries.iter().map(|s| s.lines_removed).sum();
+    let files = if summaries.len() == 1 {
+        "file"
+    } else {
+        "files"
+    };
+
+    let mut groups: BTreeMap<&str, Vec<&ModifiedFileSummary>> = BTreeMap::new();
+    for summary in summaries {
+        let (dir, _) = split_dir(summary.path.as_str());
+        groups.entry(dir).or_default().push(summary);
+    }
+
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "**{} {files} changed** (+{added} -{removed})",
+        summaries.len()
+    );
+
+    for (dir, mut entries) in groups {
+        entries.sort_by(|a, b| a.path.as_str().cmp(b.path.as_str()));
+        let heading = if dir.is_empty() { "./" } else { dir };
+        let _ = write!(out, "\n### {}\n\n", code_span(heading));
+
+        for entry in entries {
+            let (_, name) = split_dir(entry.path.as_str());
+            let _ = write!(
+                out,
+                "- **{}** {}",
+                status_label(entry.status),
+                code_span(name)
+            );
+            if let Some(dest) = &entry.moved_to {
+                let _ = write!(out, " → {}", code_span(dest.as_str()));
+            }
+            let _ = writeln!(out, " (+{} -{})", entry.lines_added, entry.lines_removed);
+        }
+    }
+
+    out
+}
+
+fn status_label(status: FileChangeStatus) -> &'static str {
+    match status {
+        FileChangeStatus::Created => "created",
+        FileChangeStatus::Modified => "modified",
+        FileChangeStatus::Deleted => "deleted",
+        FileChangeStatus::Moved => "moved",
+        FileChangeStatus::Recreated => "recreated",
+        FileChangeStatus::Renamed => "renamed",
+    }
+}
+
+/// Split a path into its directory (with trailing slash, empty at the
+/// root) and file name.
+fn split_dir(path: &str) -> (&str, &str) {
+    match path.rfind('/') {
+        Some(i) => (&path[..=i], &path[i + 1..]),
+        None => ("", path),
+    }
+}
+
+/// Inline code span that survives backticks in `text`.
+fn code_span(text: &str) -> String {
+    let longest_run = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest_run + 1);
+    if longest_run == 0 {
+        format!("{fence}{text}{fence}")
+    } else {
+        format!("{fence} {text} {fence}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::PathKey;
+    use std::sync::Arc;
+
+    fn summary(
+        path: &str,
+        status: FileChangeStatus,
+        added: usize,
+        removed: usize,
+    ) -> ModifiedFileSummary {
+        ModifiedFileSummary {
+            path: PathKey::from_arc(Arc::from(path)),
+            lines_added: added,
+            lines_removed: removed,
+            status,
+            moved_to: None,
+            similarity: None,
+        }
+    }
+
+    #[test]
+    fn test_groups_by_directory_independent_of_order() {
+        let mut moved = summary("src/old.rs", FileChangeStatus::Moved, 1, 0);
+        moved.moved_to = Some(PathKey::from_arc(Arc::from("src/fs/new.rs")));
+        let mut summaries = vec![
+            summary("src/main.rs", FileChangeStatus::Modified, 3, 1),
+            summary("README.md", FileChangeStatus::Created, 5, 0),
+            moved,
+            summary("src/fs/gone.rs", FileChangeStatus::Deleted, 0, 7),
+        ];
+
+        let expected = "\
+**4 files changed** (+9 -8)
+
+### `./`
+
+- **created** `README.md` (+5 -0)
+
+### `src/`
+
+- **modified** `main.rs` (+3 -1)
+- **moved** `old.rs` → `src/fs/new.rs` (+1 -0)
+
+### `src/fs/`
+
+- **deleted** `gone.rs` (+0 -7)
+";
+        assert_eq!(render_change_summary(&summaries), expected);
+        summaries.reverse();
+        assert_eq!(render_change_summary(&summaries), expected);
+    }
+
+    #[test]
+    fn test_empty_summary_and_backticks() {
+        assert_eq!(render_change_summary(&[]), "No changes.\n");
+        assert_eq!(code_span("a`b"), "`` a`b ``");
+    }
+}
@@ -0,0 +1,218 @@
+//! Line-aligned chunking of file content for retrieval pipelines.
+//!
+//! Chunks cover whole lines only, so they can be mapped back to line
+//! ranges for display, and carry a hash of their bytes for caching.
+//!
+//! Chunk ids derive from the path and chunk text, never line numbers, and
+//! cuts prefer blank lines so boundaries resynchronize after an edit.
+//! Chunks whose text an edit does not touch usually keep their id.
+
+use std::collections::HashMap;
+
+use xxhash_rust::xxh3::{xxh3_64, Xxh3};
+
+use crate::error::{Error, Result};
+use crate::fs::PathKey;
+use crate::tools::ByteSpan;
+
+/// Bytes inspected by [`looks_binary`].
+const BINARY_SNIFF_LEN: usize = 8192;
+
+/// How to split files into chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkOptions {
+    /// Lines per chunk.
+    pub max_lines: usize,
+    /// Lines shared between consecutive chunks; must be below `max_lines`.
+    pub overlap: usize,
+}
+
+impl Default for ChunkOptions {
+    fn default() -> Self {
+        Self {
+            max_lines: 40,
+            overlap: 5,
+        }
+    }
+}
+
+/// A run of whole lines from one file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    pub path: PathKey,
+    /// 1-based first line.
+    pub start_line: usize,
+    /// 1-based last line, inclusive.
+    pub end_line: usize,
+    /// Byte range of the chunk within the file content.
+    pub span: ByteSpan,
+    /// XXH3-64 of the chunk bytes.
+    pub hash: u64,
+    /// Identity of the chunk: path, text, and which repeat of that text in
+    /// the file it is. Independent of where the chunk sits in the file.
+    pub id: u64,
+}
+
+impl Chunk {
+    /// The chunk's bytes within `content`, the file it was cut from.
+    pub fn text<'a>(&self, content: &'a [u8]) -> &'a [u8] {
+        &content[self.span.start..self.span.end]
+    }
+}
+
+/// Heuristic: a NUL byte near the start means the content is not text.
+pub fn looks_binary(content: &[u8]) -> bool {
+    memchr::memchr(0, &content[..content.len().min(BINARY_SNIFF_LEN)]).is_some()
+}
+
+/// Split `content` into overlapping chunks of whole lines.
+pub fn chunk_text(path: &PathKey, content: &[u8], opts: ChunkOptions) -> Result<Vec<Chunk>> {
+    if opts.max_lines == 0 || opts.overlap >= opts.max_lines {
+        return Err(Error::InvalidRange(opts.overlap, opts.max_lines));
+    }
+
+    // Byte offset where each line starts, plus the end of content.
+    let mut starts: Vec<usize> = std::iter::once(0)
+        .chain(memchr::memchr_iter(b'\n', content).map(|i| i + 1))
+        .filter(|&i| i < content.len())
+        .collect();
+    starts.push(content.len());
+    let lines = starts.len() - 1;
+
+    let is_blank = |line: usize| {
+        content[starts[line]..starts[line + 1]]
+            .iter()
+            .all(u8::is_ascii_whitespace)
+    };
+
+    let mut chunks = Vec::new();
+    let mut repeats: HashMap<u64, u64> = HashMap::new();
+    let mut first = 0;
+    while first < lines {
+        let mut last = (first + opts.max_lines).min(lines);
+        if last < lines {
+            // End after the last blank line in the back half, if any.
+            let earliest = (first + opts.max_lines / 2).max(first + opts.overlap + 1);
+            if let Some(blank) = (earliest..last).rev().find(|&l| is_blank(l - 1)) {
+                last = blank;
+            }
+        }
+
+        let span = ByteSpan {
+            start: starts[first],
+            end: starts[last],
+        };
+        let hash = xxh3_64(&content[span.start..span.end]);
+        let repeat = repeats.entry(hash).or_insert(0);
+        chunks.push(Chunk {
+            path: path.clone(),
+            start_line: first + 1,
+            end_line: last,
+            span,
+            hash,
+            id: chunk_id(path, hash, *repeat),
+        });
+        *repeat += 1;
+
+        if last == lines {
+            break;
+        }
+        first = last - opts.overlap;
+    }
+
+    Ok(chunks)
+}
+
+fn chunk_id(path: &PathKey, hash: u64, repeat: u64) -> u64 {
+    let mut hasher = Xxh3::new();
+    hasher.update(path.as_str().as_bytes());
+    hasher.update(&[0]);
+    hasher.update(&hash.to_le_bytes());
+    hasher.update(&repeat.to_le_bytes());
+    hasher.digest()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn key() -> PathKey {
+        PathKey::from_arc(Arc::from("a.txt"))
+    }
+
+    fn ranges(chunks: &[Chunk]) -> Vec<(usize, usize)> {
+        chunks.iter().map(|c| (c.start_line, c.end_line)).collect()
+    }
+
+    #[test]
+    fn test_chunks_overlap_and_cover_all_lines() {
+        let content = b"1\n2\n3\n4\n5\n6\n7";
+        let opts = ChunkOptions {
+            max_lines: 3,
+            overlap: 1,
+        };
+        let chunks = chunk_text(&key(), content, opts).unwrap();
+
+        assert_eq!(ranges(&chunks), vec![(1, 3), (3, 5), (5, 7)]);
+        assert_eq!(chunks[0].text(content), b"1\n2\n3\n");
+        assert_eq!(chunks[2].text(content), b"5\n6\n7");
+    }
+
+    #[test]
+    fn test_same_text_same_hash() {
+        let opts = ChunkOptions {
+            max_lines: 2,
+            overlap: 0,
+        };
+        let chunks = chunk_text(&key(), b"a\nb\na\nb\n", opts).unwrap();
+
+        assert_eq!(ranges(&chunks), vec![(1, 2), (3, 4)]);
+        assert_eq!(chunks[0].hash, chunks[1].hash);
+    }
+
+    #[test]
+    fn test_ids_survive_edits_in_other_chunks() {
+        let opts = ChunkOptions {
+            max_lines: 4,
+            overlap: 0,
+        };
+        let before = b"fn a() {\n}\n\nfn b() {\n}\n\nfn c() {\n}\n";
+        let after = b"fn a() {\n  x();\n}\n\nfn b() {\n}\n\nfn c() {\n}\n";
+
+        let ids = |content: &[u8]| -> Vec<u64> {
+            chunk_text(&key(), content, opts)
+                .unwrap()
+                .iter()
+                .map(|c| c.id)
+                .collect()
+        };
+        let (before, after) = (ids(before), ids(after));
+
+        assert_ne!(before[0], after[0]);
+        assert_eq!(before[1..], after[1..]);
+    }
+
+    #[test]
+    fn test_repeated_text_gets_distinct_ids() {
+        let opts = ChunkOptions {
+            max_lines: 1,
+            overlap: 0,
+        };
+        let chunks = chunk_text(&key(), b"x\nx\n", opts).unwrap();
+        assert_eq!(chunks[0].hash, chunks[1].hash);
+        assert_ne!(chunks[0].id, chunks[1].id);
+    }
+
+    #[test]
+    fn test_rejects_overlap_not_below_max_lines() {
+        let opts = ChunkOptions {
+            max_lines: 2,
+            overlap: 2,
+        };
+        assert!(chunk_text(&key(), b"a\n", opts).is_err());
+        assert!(chunk_text(&key(), b"", ChunkOptions::default())
+            .unwrap()
+            .is_empty());
+    }
+}
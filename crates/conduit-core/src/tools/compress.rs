@@ -0,0 +1,159 @@
+//! Optional compression of large payloads handed to the host.
+//!
+//! Copying bytes out of the module costs time proportional to their size.
+//! Hosts that pull large diffs or file contents can opt into compressing
+//! anything above a threshold; small payloads are always left as they are.
+
+use std::borrow::Cow;
+use std::io::{Read, Write};
+
+use flate2::read::{DeflateDecoder, GzDecoder};
+use flate2::write::{DeflateEncoder, GzEncoder};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+
+/// Payloads smaller than this are never compressed by default.
+pub const DEFAULT_COMPRESSION_THRESHOLD: usize = 64 * 1024;
+
+/// Compressed payload formats.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    /// RFC 1952; readable by `DecompressionStream("gzip")`.
+    Gzip,
+    /// Raw RFC 1951; readable by `DecompressionStream("deflate-raw")`.
+    Deflate,
+}
+
+impl Compression {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Deflate => "deflate",
+        }
+    }
+}
+
+pub fn compress(bytes: &[u8], format: Compression) -> Result<Vec<u8>> {
+    let level = flate2::Compression::fast();
+    Ok(match format {
+        Compression::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), level);
+            encoder.write_all(bytes)?;
+            encoder.finish()?
+        }
+        Compression::Deflate => {
+            let mut encoder = DeflateEncoder::new(Vec::new(), level);
+            encoder.write_all(bytes)?;
+            encoder.finish()?
+        }
+    })
+}
+
+pub fn decompress(bytes: &[u8], format: Compression) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    match format {
+        Compression::Gzip => GzDecoder::new(bytes).read_to_end(&mut out)?,
+        Compression::Deflate => DeflateDecoder::new(bytes).read_to_end(&mut out)?,
+    };
+    Ok(out)
+}
+
+/// Bytes as handed to the host, with the format they are in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Packed<'a> {
+    /// `None` when the bytes are uncompressed.
+    pub encoding: Option<Compression>,
+    pub bytes: Cow<'a, [u8]>,
+}
+
+/// When and how to compress outgoing payloads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CompressionPolicy {
+    /// `None` disables compression.
+    pub format: Option<Compression>,
+    /// Minimum payload size, in bytes, worth compressing.
+    pub threshold: usize,
+}
+
+impl Default for CompressionPolicy {
+    fn default() -> Self {
+        Self {
+            format: None,
+            threshold: DEFAULT_COMPRESSION_THRESHOLD,
+        }
+    }
+}
+
+impl CompressionPolicy {
+    /// Compress `bytes` if the policy asks for it and it pays off.
+    ///
+    /// Output that would not be smaller than the input is discarded, so
+    /// already-compressed content is passed through unchanged.
+    pub fn pack<'a>(&self, bytes: &'a [u8]) -> Result<Packed<'a>> {
+        let plain = Packed {
+            encoding: None,
+            bytes: Cow::Borrowed(bytes),
+        };
+        let Some(format) = self.format else {
+            return Ok(plain);
+        };
+        if bytes.len() < self.threshold {
+            return Ok(plain);
+        }
+
+        let compressed = compress(bytes, format)?;
+        if compressed.len() >= bytes.len() {
+            return Ok(plain);
+        }
+        Ok(Packed {
+            encoding: Some(format),
+            bytes: Cow::Owned(compressed),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_compresses_only_large_payloads() {
+        let policy = CompressionPolicy {
+            format: Some(Compression::Gzip),
+            threshold: 100,
+        };
+        let small = b"tiny".to_vec();
+        assert_eq!(policy.pack(&small).unwrap().encoding, None);
+
+        let large = "fn main() {}\n".repeat(200).into_bytes();
+        let packed = policy.pack(&large).unwrap();
+        assert_eq!(packed.encoding, Some(Compression::Gzip));
+        assert!(packed.bytes.len() < large.len());
+        assert_eq!(decompress(&packed.bytes, Compression::Gzip).unwrap(), large);
+    }
+
+    #[test]
+    fn test_pack_keeps_incompressible_bytes_plain() {
+        let policy = CompressionPolicy {
+            format: Some(Compression::Deflate),
+            threshold: 0,
+        };
+        // A short pseudo-random sequence that deflate cannot shrink.
+        let noise: Vec<u8> = (0u32..64)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 24) as u8)
+            .collect();
+        let packed = policy.pack(&noise).unwrap();
+        assert_eq!(packed.encoding, None);
+        assert_eq!(&packed.bytes[..], &noise[..]);
+    }
+
+    #[test]
+    fn test_deflate_round_trip() {
+        let text = b"abcabcabcabcabcabc".repeat(10);
+        let compressed = compress(&text, Compression::Deflate).unwrap();
+        assert_eq!(decompress(&compressed, Compression::Deflate).unwrap(), text);
+    }
+}
@@ -1,6 +1,7 @@
 //! Line-based diffing utilities using the `similar` crate.
 
 use crate::fs::PathKey;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use similar::{ChangeTag, TextDiff};
 
@@ -19,6 +20,42 @@ pub struct DiffRegion {
     pub removed_lines: Vec<String>,
     /// The actual lines added to the modified content.
     pub added_lines: Vec<String>,
+    /// Unchanged lines just before the region, when context was requested.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub context_before: Vec<String>,
+    /// Unchanged lines just after the region, when context was requested.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub context_after: Vec<String>,
+    /// Changed runs within `removed_lines`, set by [`refine_diff`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed_spans: Vec<InlineSpan>,
+    /// Changed runs within `added_lines`, set by [`refine_diff`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub added_spans: Vec<InlineSpan>,
+}
+
+/// A changed run of text within one line of a [`DiffRegion`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InlineSpan {
+    /// Index into the region's `removed_lines` or `added_lines`.
+    pub line: usize,
+    /// Start byte offset within the line.
+    pub start: usize,
+    /// End byte offset (exclusive) within the line.
+    pub end: usize,
+}
+
+/// How finely [`refine_diff`] marks changes within lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffGranularity {
+    /// Whole lines only; regions carry no spans.
+    #[default]
+    Line,
+    /// Runs of words and whitespace.
+    Word,
+    /// Single characters.
+    Char,
 }
 
 /// Summary statistics for a file diff.
@@ -44,7 +81,16 @@ pub struct FileDiff {
 }
 
 /// Compute line-based diff between two text contents using the `similar` crate
-pub fn compute_diff(path: PathKey, original: &str, modified: &str) -> FileDiff {
+///
+/// Each region gets up to `context_lines` unchanged lines on either side.
+/// Context is taken per region, so regions closer together than twice that
+/// share lines.
+pub fn compute_diff(
+    path: PathKey,
+    original: &str,
+    modified: &str,
+    context_lines: usize,
+) -> FileDiff {
     let diff = TextDiff::from_lines(original, modified);
 
     let mut regions = Vec::new();
@@ -66,6 +112,10 @@ pub fn compute_diff(path: PathKey, original: &str, modified: &str) -> FileDiff {
                             lines_added: added.len(),
                             removed_lines: removed,
                             added_lines: added,
+                            context_before: Vec::new(),
+                            context_after: Vec::new(),
+                            removed_spans: Vec::new(),
+                            added_spans: Vec::new(),
                         });
                     }
                 }
@@ -129,10 +179,28 @@ pub fn compute_diff(path: PathKey, original: &str, modified: &str) -> FileDiff {
                 lines_added: added.len(),
                 removed_lines: removed,
                 added_lines: added,
+                context_before: Vec::new(),
+                context_after: Vec::new(),
+                removed_spans: Vec::new(),
+                added_spans: Vec::new(),
             });
         }
     }
 
+    if context_lines > 0 {
+        let lines: Vec<&str> = original
+            .split_inclusive('\n')
+            .map(|line| line.strip_suffix('\n').unwrap_or(line))
+            .collect();
+        let owned = |range: &[&str]| range.iter().map(|line| line.to_string()).collect();
+        for region in &mut regions {
+            let start = (region.original_start - 1).min(lines.len());
+            let end = (start + region.lines_removed).min(lines.len());
+            region.context_before = owned(&lines[start.saturating_sub(context_lines)..start]);
+            region.context_after = owned(&lines[end..(end + context_lines).min(lines.len())]);
+        }
+    }
+
     // Calculate stats
     let stats = DiffStats {
         lines_added: regions.iter().map(|r| r.lines_added).sum(),
@@ -148,13 +216,108 @@ pub fn compute_diff(path: PathKey, original: &str, modified: &str) -> FileDiff {
 }
 
 /// Compute diffs for multiple files
-pub fn compute_diffs(files: Vec<(PathKey, String, String)>) -> Vec<FileDiff> {
+pub fn compute_diffs(files: Vec<(PathKey, String, String)>, context_lines: usize) -> Vec<FileDiff> {
     files
         .into_iter()
-        .map(|(path, original, modified)| compute_diff(path, &original, &modified))
+        .map(|(path, original, modified)| compute_diff(path, &original, &modified, context_lines))
         .collect()
 }
 
+/// Mark the changed runs within each region's lines at `granularity`.
+///
+/// Only regions that both remove and add lines are refined: a pure
+/// addition or removal changes its lines entirely. The removed lines are
+/// compared with the added lines as one block, so text moving between
+/// lines of a region is matched too. Spans from an earlier refinement are
+/// replaced; [`DiffGranularity::Line`] clears them.
+pub fn refine_diff(diff: &mut FileDiff, granularity: DiffGranularity) {
+    for region in &mut diff.regions {
+        region.removed_spans.clear();
+        region.added_spans.clear();
+        if region.removed_lines.is_empty() || region.added_lines.is_empty() {
+            continue;
+        }
+        let old = region.removed_lines.join("\n");
+        let new = region.added_lines.join("\n");
+        let text_diff = match granularity {
+            DiffGranularity::Line => continue,
+            DiffGranularity::Word => TextDiff::from_words(old.as_str(), new.as_str()),
+            DiffGranularity::Char => TextDiff::from_chars(old.as_str(), new.as_str()),
+        };
+
+        let (mut removed, mut added) = (SpanCollector::new(&old), SpanCollector::new(&new));
+        for change in text_diff.iter_all_changes() {
+            let len = change.value().len();
+            match change.tag() {
+                ChangeTag::Equal => {
+                    removed.skip(len);
+                    added.skip(len);
+                }
+                ChangeTag::Delete => removed.mark(len),
+                ChangeTag::Insert => added.mark(len),
+            }
+        }
+        region.removed_spans = removed.spans;
+        region.added_spans = added.spans;
+    }
+}
+
+/// Turns byte runs of a `\n`-joined block into per-line spans.
+struct SpanCollector<'a> {
+    text: &'a str,
+    pos: usize,
+    line: usize,
+    line_start: usize,
+    spans: Vec<InlineSpan>,
+}
+
+impl<'a> SpanCollector<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            pos: 0,
+            line: 0,
+            line_start: 0,
+            spans: Vec::new(),
+        }
+    }
+
+    fn skip(&mut self, len: usize) {
+        self.advance(len, false);
+    }
+
+    fn mark(&mut self, len: usize) {
+        self.advance(len, true);
+    }
+
+    fn advance(&mut self, len: usize, changed: bool) {
+        let end = self.pos + len;
+        while self.pos < end {
+            let line_end = self.text[self.pos..end]
+                .find('\n')
+                .map_or(end, |i| self.pos + i);
+            if changed && line_end > self.pos {
+                let (start, end) = (self.pos - self.line_start, line_end - self.line_start);
+                match self.spans.last_mut() {
+                    Some(last) if last.line == self.line && last.end == start => last.end = end,
+                    _ => self.spans.push(InlineSpan {
+                        line: self.line,
+                        start,
+                        end,
+                    }),
+                }
+            }
+            self.pos = line_end;
+            if line_end < end {
+                // Step over the newline joining two lines.
+                self.pos += 1;
+                self.line += 1;
+                self.line_start = self.pos;
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -169,7 +332,7 @@ mod tests {
         let original = "line 1\nline 2\nline 3";
         let modified = "line 1\nline 2 modified\nline 3";
 
-        let diff = compute_diff(create_test_path("test.txt"), original, modified);
+        let diff = compute_diff(create_test_path("test.txt"), original, modified, 0);
 
         assert_eq!(diff.stats.lines_removed, 1);
         assert_eq!(diff.stats.lines_added, 1);
@@ -192,7 +355,7 @@ Collaborators: & [list all the people you worked with]"#;
 Name: & [Please fill in your first and last name] \\
 Collaborators: & [Please list all the people you worked with, or write "None"]"#;
 
-        let diff = compute_diff(create_test_path("test.tex"), original, modified);
+        let diff = compute_diff(create_test_path("test.tex"), original, modified, 0);
 
         assert_eq!(diff.stats.lines_removed, 3);
         assert_eq!(diff.stats.lines_added, 3);
@@ -204,7 +367,7 @@ Collaborators: & [Please list all the people you worked with, or write "None"]"#
         let original = "line 1\nline 2\nline 3";
         let modified = "";
 
-        let diff = compute_diff(create_test_path("test.txt"), original, modified);
+        let diff = compute_diff(create_test_path("test.txt"), original, modified, 0);
 
         assert_eq!(diff.stats.lines_removed, 3);
         assert_eq!(diff.stats.lines_added, 0);
@@ -216,7 +379,7 @@ Collaborators: & [Please list all the people you worked with, or write "None"]"#
         let original = "";
         let modified = "line 1\nline 2\nline 3";
 
-        let diff = compute_diff(create_test_path("test.txt"), original, modified);
+        let diff = compute_diff(create_test_path("test.txt"), original, modified, 0);
 
         assert_eq!(diff.stats.lines_removed, 0);
         assert_eq!(diff.stats.lines_added, 3);
@@ -228,7 +391,7 @@ Collaborators: & [Please list all the people you worked with, or write "None"]"#
         let original = "line 1\nline 2\nline 3";
         let modified = "line 1\nline 2\nline 3";
 
-        let diff = compute_diff(create_test_path("test.txt"), original, modified);
+        let diff = compute_diff(create_test_path("test.txt"), original, modified, 0);
 
         assert_eq!(diff.stats.lines_removed, 0);
         assert_eq!(diff.stats.lines_added, 0);
@@ -242,7 +405,7 @@ Collaborators: & [Please list all the people you worked with, or write "None"]"#
         let original = "line 1\nline 2\nline 3";
         let modified = "line 1\nadded line A\nadded line B\nline 2\nline 3";
 
-        let diff = compute_diff(path, original, modified);
+        let diff = compute_diff(path, original, modified, 0);
 
         assert_eq!(diff.stats.lines_added, 2);
         assert_eq!(diff.stats.lines_removed, 0);
@@ -264,7 +427,7 @@ Collaborators: & [Please list all the people you worked with, or write "None"]"#
             "    # TODO: Implement\n\n\ndef split_last_dim_pattern() -> str:\n    \"\"\"";
         let modified = "    # Compute x * W using einsum: (batch, d_in) * (d_in, d_out) -> (batch, d_out)\n    y = einsum(x, W, 'batch d_in, d_in d_out -> batch d_out')\n    # Add bias using broadcasting\n    y = y + b\n    return y";
 
-        let diff = compute_diff(path, original, modified);
+        let diff = compute_diff(path, original, modified, 0);
 
         println!("Original:\n{original}");
         println!("\nModified:\n{modified}");
@@ -298,7 +461,7 @@ def split_last_dim_pattern() -> str:"#;
 
 def split_last_dim_pattern() -> str:"#;
 
-        let diff = compute_diff(path, original, modified);
+        let diff = compute_diff(path, original, modified, 0);
 
         println!("\nSubmission.py exact case:");
         let original_lines = original.lines().count();
@@ -321,4 +484,75 @@ def split_last_dim_pattern() -> str:"#;
             println!("  Added lines: {:?}", region.added_lines);
         }
     }
+
+    #[test]
+    fn test_refine_diff_marks_changed_words_and_chars() {
+        let original = "keep\nlet total = count + 1;\nfn a() {}\n";
+        let modified = "keep\nlet sum = count + 2;\nfn a() {}\n";
+
+        let mut diff = compute_diff(create_test_path("a.rs"), original, modified, 0);
+        refine_diff(&mut diff, DiffGranularity::Line);
+        assert!(diff.regions[0].removed_spans.is_empty());
+
+        refine_diff(&mut diff, DiffGranularity::Word);
+        let region = &diff.regions[0];
+        let text = |lines: &[String], span: &InlineSpan| {
+            lines[span.line][span.start..span.end].to_string()
+        };
+        let removed: Vec<_> = region
+            .removed_spans
+            .iter()
+            .map(|s| text(&region.removed_lines, s))
+            .collect();
+        let added: Vec<_> = region
+            .added_spans
+            .iter()
+            .map(|s| text(&region.added_lines, s))
+            .collect();
+        assert_eq!(removed, vec!["total", "1;"]);
+        assert_eq!(added, vec!["sum", "2;"]);
+
+        // Spans never cross the newline joining a region's lines.
+        let mut diff = compute_diff(create_test_path("b.txt"), "ab\ncd", "aX\nYd", 0);
+        refine_diff(&mut diff, DiffGranularity::Char);
+        let region = &diff.regions[0];
+        assert_eq!(
+            region.removed_spans,
+            vec![
+                InlineSpan {
+                    line: 0,
+                    start: 1,
+                    end: 2
+                },
+                InlineSpan {
+                    line: 1,
+                    start: 0,
+                    end: 1
+                },
+            ]
+        );
+        assert_eq!(region.added_spans.len(), 2);
+
+        // Refining at line granularity drops the spans again.
+        refine_diff(&mut diff, DiffGranularity::Line);
+        assert!(diff.regions[0].removed_spans.is_empty());
+        assert!(diff.regions[0].added_spans.is_empty());
+    }
+
+    #[test]
+    fn test_context_lines_around_regions() {
+        let original = "1\n2\n3\n4\n5\n6\n7\n";
+        let modified = "1\n2\nthree\n4\n5\n6\n7\nnew\n";
+
+        let diff = compute_diff(create_test_path("a.txt"), original, modified, 2);
+        assert_eq!(diff.regions.len(), 2);
+        assert_eq!(diff.regions[0].context_before, vec!["1", "2"]);
+        assert_eq!(diff.regions[0].context_after, vec!["4", "5"]);
+        // An insertion at the end has context only before it.
+        assert_eq!(diff.regions[1].context_before, vec!["6", "7"]);
+        assert!(diff.regions[1].context_after.is_empty());
+
+        let diff = compute_diff(create_test_path("a.txt"), original, modified, 0);
+        assert!(diff.regions[0].context_before.is_empty());
+    }
 }
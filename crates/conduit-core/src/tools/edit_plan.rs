@@ -0,0 +1,161 @@
+//! Bulk replacements held for review before any of them is staged.
+//!
+//! An [`EditPlan`] keeps every replacement a find-and-replace would make,
+//! numbered across the whole plan, so a host can show them as hunks with
+//! checkboxes and stage only the ones the user keeps.
+
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::error::{Error, Result};
+use crate::fs::PathKey;
+use crate::tools::replace::ReplacePlan;
+use crate::{EditItem, EditRequest};
+
+/// Replacements planned in one file.
+#[derive(Debug, Clone)]
+pub struct PlannedFile {
+    pub path: PathKey,
+    /// XXH3-64 of the content the plan was made against.
+    pub content_hash: u64,
+    pub plan: ReplacePlan,
+    /// One preview per op of `plan`, in the same order.
+    pub items: Vec<EditItem>,
+    /// Id of the first op; the rest follow consecutively.
+    first_hunk: usize,
+}
+
+impl PlannedFile {
+    /// Plan `plan` against `content`, with `items` previewing its ops.
+    pub fn new(path: PathKey, content: &[u8], plan: ReplacePlan, items: Vec<EditItem>) -> Self {
+        Self {
+            path,
+            content_hash: xxh3_64(content),
+            plan,
+            items,
+            first_hunk: 0,
+        }
+    }
+
+    /// Fail with [`Error::ContentChanged`] unless `content` is what the
+    /// plan was made against.
+    pub fn check_unchanged(&self, content: &[u8]) -> Result<()> {
+        let actual = xxh3_64(content);
+        if actual != self.content_hash {
+            return Err(Error::ContentChanged {
+                path: self.path.as_str().to_string(),
+                expected: format!("{:016x}", self.content_hash),
+                actual: format!("{actual:016x}"),
+            });
+        }
+        Ok(())
+    }
+
+    /// Ids of this file's hunks.
+    pub fn hunk_ids(&self) -> std::ops::Range<usize> {
+        self.first_hunk..self.first_hunk + self.plan.ops.len()
+    }
+}
+
+/// Replacements a find-and-replace would make, not yet staged.
+#[derive(Debug, Clone)]
+pub struct EditPlan {
+    pub request: EditRequest,
+    files: Vec<PlannedFile>,
+}
+
+impl EditPlan {
+    /// A plan of `files`, in order; hunk ids are assigned across them.
+    pub fn new(request: EditRequest, mut files: Vec<PlannedFile>) -> Self {
+        let mut next = 0;
+        for file in &mut files {
+            file.first_hunk = next;
+            next += file.plan.ops.len();
+        }
+        Self { request, files }
+    }
+
+    pub fn files(&self) -> &[PlannedFile] {
+        &self.files
+    }
+
+    pub fn hunk_count(&self) -> usize {
+        self.files.iter().map(|f| f.plan.ops.len()).sum()
+    }
+
+    /// The ops `ids` select, as one plan per file that has any, in file
+    /// order. Repeated ids count once.
+    pub fn select(&self, ids: &[usize]) -> Result<Vec<(&PlannedFile, ReplacePlan)>> {
+        let mut ids = ids.to_vec();
+        ids.sort_unstable();
+        ids.dedup();
+        if let Some(&unknown) = ids.iter().find(|&&id| id >= self.hunk_count()) {
+            return Err(Error::UnknownHunk(unknown));
+        }
+
+        let mut selected = Vec::new();
+        let mut ids = ids.into_iter().peekable();
+        for file in &self.files {
+            let range = file.hunk_ids();
+            let mut ops = Vec::new();
+            while let Some(id) = ids.next_if(|id| range.contains(id)) {
+                ops.push(file.plan.ops[id - range.start].clone());
+            }
+            if !ops.is_empty() {
+                selected.push((file, ReplacePlan { ops }));
+            }
+        }
+        Ok(selected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::replace::EditOp;
+    use crate::tools::ByteSpan;
+    use std::sync::Arc;
+
+    fn file(path: &str, ops: usize) -> PlannedFile {
+        let key = PathKey::from_arc(Arc::from(path));
+        let ops = (0..ops)
+            .map(|i| EditOp {
+                span: ByteSpan {
+                    start: i * 4,
+                    end: i * 4 + 3,
+                },
+                replacement: b"new".to_vec(),
+            })
+            .collect();
+        PlannedFile::new(key, b"old old old", ReplacePlan { ops }, Vec::new())
+    }
+
+    #[test]
+    fn test_select_groups_hunks_by_file() {
+        let plan = EditPlan::new(EditRequest::default(), vec![file("a", 2), file("b", 3)]);
+        assert_eq!(plan.hunk_count(), 5);
+        assert_eq!(plan.files()[1].hunk_ids(), 2..5);
+
+        let selected = plan.select(&[4, 0, 2, 4]).unwrap();
+        let spans: Vec<_> = selected
+            .iter()
+            .map(|(f, p)| {
+                let starts: Vec<_> = p.ops.iter().map(|op| op.span.start).collect();
+                (f.path.as_str(), starts)
+            })
+            .collect();
+        assert_eq!(spans, [("a", vec![0]), ("b", vec![0, 8])]);
+
+        assert!(matches!(plan.select(&[5]), Err(Error::UnknownHunk(5))));
+        assert!(plan.select(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_check_unchanged() {
+        let file = file("a", 1);
+        assert!(file.check_unchanged(b"old old old").is_ok());
+        assert!(matches!(
+            file.check_unchanged(b"old"),
+            Err(Error::ContentChanged { .. })
+        ));
+    }
+}
@@ -0,0 +1,146 @@
+//! Cache and ranking for host-computed text embeddings.
+//!
+//! Conduit never computes vectors itself. Hosts supply them per chunk;
+//! they are cached by chunk content hash, so unchanged text is embedded
+//! once no matter which file or line range it moves to.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+use crate::tools::Chunk;
+
+/// Embedding vectors keyed by chunk content hash.
+#[derive(Debug, Default)]
+pub struct EmbeddingCache {
+    vectors: HashMap<u64, Arc<[f32]>>,
+    dim: Option<usize>,
+}
+
+impl EmbeddingCache {
+    /// Store the vector for `hash`. Every vector must have the same length.
+    pub fn insert(&mut self, hash: u64, vector: Vec<f32>) -> Result<()> {
+        match self.dim {
+            Some(dim) if dim != vector.len() => {
+                return Err(Error::InvalidFormat(format!(
+                    "embedding has {} dimensions, expected {}",
+                    vector.len(),
+                    dim
+                )))
+            }
+            _ => self.dim = Some(vector.len()),
+        }
+        self.vectors.insert(hash, vector.into());
+        Ok(())
+    }
+
+    pub fn get(&self, hash: u64) -> Option<&[f32]> {
+        self.vectors.get(&hash).map(|v| &v[..])
+    }
+
+    /// Chunks without a cached vector, one per distinct hash.
+    pub fn missing<'a>(&self, chunks: &'a [Chunk]) -> Vec<&'a Chunk> {
+        let mut seen = HashSet::new();
+        chunks
+            .iter()
+            .filter(|c| !self.vectors.contains_key(&c.hash) && seen.insert(c.hash))
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.vectors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vectors.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.vectors.clear();
+        self.dim = None;
+    }
+}
+
+/// A chunk and its similarity to the query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoredChunk {
+    pub chunk: Chunk,
+    /// Cosine similarity in `[-1, 1]`.
+    pub score: f32,
+}
+
+/// The `top_k` chunks most similar to `query`, best first.
+///
+/// Chunks without a cached vector are skipped.
+pub fn rank_chunks(
+    query: &[f32],
+    chunks: &[Chunk],
+    cache: &EmbeddingCache,
+    top_k: usize,
+) -> Vec<ScoredChunk> {
+    let mut scored: Vec<ScoredChunk> = chunks
+        .iter()
+        .filter_map(|chunk| {
+            let vector = cache.get(chunk.hash)?;
+            Some(ScoredChunk {
+                chunk: chunk.clone(),
+                score: cosine(query, vector)?,
+            })
+        })
+        .collect();
+
+    scored.sort_by(|a, b| b.score.total_cmp(&a.score));
+    scored.truncate(top_k);
+    scored
+}
+
+/// Cosine similarity, or `None` for mismatched lengths or zero vectors.
+fn cosine(a: &[f32], b: &[f32]) -> Option<f32> {
+    if a.len() != b.len() {
+        return None;
+    }
+    let (mut dot, mut na, mut nb) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        na += x * x;
+        nb += y * y;
+    }
+    let norm = (na * nb).sqrt();
+    (norm > 0.0).then(|| dot / norm)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::PathKey;
+    use crate::tools::{chunk_text, ChunkOptions};
+
+    #[test]
+    fn test_rank_orders_by_similarity_and_skips_uncached() {
+        let path = PathKey::from_arc(Arc::from("a.txt"));
+        let opts = ChunkOptions {
+            max_lines: 1,
+            overlap: 0,
+        };
+        let chunks = chunk_text(&path, b"north\neast\nsouth\n", opts).unwrap();
+
+        let mut cache = EmbeddingCache::default();
+        cache.insert(chunks[0].hash, vec![0.0, 1.0]).unwrap();
+        cache.insert(chunks[1].hash, vec![1.0, 0.0]).unwrap();
+        assert_eq!(cache.missing(&chunks), vec![&chunks[2]]);
+
+        let ranked = rank_chunks(&[0.9, 0.1], &chunks, &cache, 5);
+        let lines: Vec<_> = ranked.iter().map(|s| s.chunk.start_line).collect();
+        assert_eq!(lines, vec![2, 1]);
+    }
+
+    #[test]
+    fn test_rejects_mismatched_dimensions() {
+        let mut cache = EmbeddingCache::default();
+        cache.insert(1, vec![1.0, 2.0]).unwrap();
+        assert!(matches!(
+            cache.insert(2, vec![1.0]),
+            Err(Error::InvalidFormat(_))
+        ));
+    }
+}
@@ -0,0 +1,151 @@
+//! Capture groups of matches as structured data.
+//!
+//! Scraping values out of a repository (TODO owners, version strings) needs
+//! each group's text and position, not an excerpt to run the regex on again.
+
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::fs::PathKey;
+use crate::tools::{ByteSpan, LineIndex, RegexMatcher};
+
+/// A capture group that took part in a match.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureGroup {
+    /// Name from `(?P<name>...)`, if any.
+    pub name: Option<String>,
+    /// Decoded lossily.
+    pub text: String,
+    /// Byte offset of the group's start in the file.
+    pub start: usize,
+    /// Byte offset just past the group's end.
+    pub end: usize,
+}
+
+/// One match with its capture groups.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractedMatch {
+    pub path: PathKey,
+    /// 1-based line of the match start
+    pub line: usize,
+    /// 1-based byte column of the match start within its line
+    pub column: usize,
+    /// The matched text, decoded lossily.
+    pub text: String,
+    pub start: usize,
+    pub end: usize,
+    /// Groups `$1..$N` in order; `None` for groups that did not participate.
+    pub groups: Vec<Option<CaptureGroup>>,
+    /// Text of each named group that participated.
+    pub named: BTreeMap<String, String>,
+}
+
+/// Extract the match of `matcher` at `span` in `content`.
+///
+/// `names` are the matcher's [`RegexMatcher::capture_names`], passed in so
+/// they are worked out once per search rather than once per match.
+pub fn extract_match(
+    path: &PathKey,
+    content: &[u8],
+    line_index: &LineIndex,
+    matcher: &RegexMatcher,
+    names: &[Option<String>],
+    span: ByteSpan,
+) -> Result<ExtractedMatch> {
+    let text = |span: ByteSpan| String::from_utf8_lossy(&content[span.to_range()]).into_owned();
+    let line = line_index.line_of_byte(span.start).unwrap_or(1);
+    let line_start = line_index.byte_of_line_start(line).unwrap_or(0);
+
+    let mut named = BTreeMap::new();
+    let groups = matcher
+        .captures_at(content, span.start)?
+        .into_iter()
+        .enumerate()
+        .map(|(i, group)| {
+            group.map(|group| {
+                let name = names.get(i).cloned().flatten();
+                if let Some(name) = &name {
+                    named.insert(name.clone(), text(group));
+                }
+                CaptureGroup {
+                    name,
+                    text: text(group),
+                    start: group.start,
+                    end: group.end,
+                }
+            })
+        })
+        .collect();
+
+    Ok(ExtractedMatch {
+        path: path.clone(),
+        line,
+        column: span.start - line_start + 1,
+        text: text(span),
+        start: span.start,
+        end: span.end,
+        groups,
+        named,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_extracts_numbered_and_named_groups() {
+        let content = b"fn main() {}\n// TODO(ana): fix\n// TODO: later\n";
+        let matcher = RegexMatcher::new(r"TODO(?:\((?P<owner>\w+)\))?: (\w+)").unwrap();
+        let names = matcher.capture_names();
+        let line_index = LineIndex::build(content);
+        let path = PathKey::from_arc(Arc::from("src/main.rs"));
+
+        let mut found = Vec::new();
+        matcher
+            .find_matches(content, |span| {
+                found.push(span);
+                true
+            })
+            .unwrap();
+        let extracted: Vec<_> = found
+            .into_iter()
+            .map(|span| extract_match(&path, content, &line_index, &matcher, &names, span))
+            .collect::<Result<_>>()
+            .unwrap();
+
+        let first = &extracted[0];
+        assert_eq!((first.line, first.column), (2, 4));
+        assert_eq!(first.text, "TODO(ana): fix");
+        assert_eq!(
+            first.groups,
+            [
+                Some(CaptureGroup {
+                    name: Some("owner".into()),
+                    text: "ana".into(),
+                    start: 21,
+                    end: 24,
+                }),
+                Some(CaptureGroup {
+                    name: None,
+                    text: "fix".into(),
+                    start: 27,
+                    end: 30,
+                }),
+            ]
+        );
+        assert_eq!(first.named["owner"], "ana");
+
+        let second = &extracted[1];
+        assert_eq!(second.line, 3);
+        assert_eq!(second.groups[0], None);
+        assert_eq!(second.groups[1].as_ref().unwrap().text, "later");
+        assert!(second.named.is_empty());
+    }
+}
@@ -0,0 +1,124 @@
+//! Recently run searches and named saved searches.
+//!
+//! Hosts show recent searches and let users rerun favourites. Keeping both
+//! here, serializable as one value, lets a host persist them across reloads
+//! by storing the serialized history and restoring it on startup.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::FindRequest;
+
+/// Recent searches kept by a [`SearchHistory`].
+pub const SEARCH_HISTORY_LIMIT: usize = 50;
+
+/// Recent searches, newest first, and saved searches by name.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SearchHistory {
+    recent: VecDeque<FindRequest>,
+    saved: BTreeMap<String, FindRequest>,
+}
+
+impl SearchHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a search that was run. Repeating a search moves it to the
+    /// front instead of adding a copy; beyond [`SEARCH_HISTORY_LIMIT`] the
+    /// oldest entries are dropped.
+    pub fn record(&mut self, req: &FindRequest) {
+        self.recent.retain(|r| r != req);
+        self.recent.push_front(req.clone());
+        self.recent.truncate(SEARCH_HISTORY_LIMIT);
+    }
+
+    /// Recent searches, newest first.
+    pub fn recent(&self) -> impl Iterator<Item = &FindRequest> {
+        self.recent.iter()
+    }
+
+    pub fn clear_recent(&mut self) {
+        self.recent.clear();
+    }
+
+    /// Save `req` as `name`; returns `true` if it replaced a saved search.
+    pub fn save(&mut self, name: &str, req: FindRequest) -> bool {
+        self.saved.insert(name.to_string(), req).is_some()
+    }
+
+    /// Remove the saved search `name`; returns whether it existed.
+    pub fn remove_saved(&mut self, name: &str) -> bool {
+        self.saved.remove(name).is_some()
+    }
+
+    pub fn saved(&self, name: &str) -> Result<&FindRequest> {
+        self.saved
+            .get(name)
+            .ok_or_else(|| Error::UnknownSavedSearch(name.to_string()))
+    }
+
+    /// Saved searches, sorted by name.
+    pub fn iter_saved(&self) -> impl Iterator<Item = (&str, &FindRequest)> {
+        self.saved.iter().map(|(name, req)| (name.as_str(), req))
+    }
+
+    /// Replace this history with `other`, such as one restored from storage,
+    /// keeping at most [`SEARCH_HISTORY_LIMIT`] recent searches.
+    pub fn restore(&mut self, mut other: SearchHistory) {
+        other.recent.truncate(SEARCH_HISTORY_LIMIT);
+        *self = other;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find(pattern: &str) -> FindRequest {
+        FindRequest {
+            find: pattern.to_string(),
+            ..FindRequest::default()
+        }
+    }
+
+    #[test]
+    fn test_recent_searches_dedupe_and_cap() {
+        let mut history = SearchHistory::new();
+        history.record(&find("a"));
+        history.record(&find("b"));
+        history.record(&find("a"));
+        let recent: Vec<_> = history.recent().map(|r| r.find.as_str()).collect();
+        assert_eq!(recent, ["a", "b"]);
+
+        for i in 0..SEARCH_HISTORY_LIMIT + 5 {
+            history.record(&find(&i.to_string()));
+        }
+        assert_eq!(history.recent().count(), SEARCH_HISTORY_LIMIT);
+        assert_eq!(history.recent().next().unwrap().find, "54");
+    }
+
+    #[test]
+    fn test_saved_searches_round_trip_through_json() {
+        let mut history = SearchHistory::new();
+        assert!(!history.save("todos", find("TODO")));
+        assert!(history.save("todos", find("TODO|FIXME")));
+        history.record(&find("x"));
+
+        let json = serde_json::to_string(&history).unwrap();
+        let mut restored = SearchHistory::new();
+        restored.restore(serde_json::from_str(&json).unwrap());
+        assert_eq!(restored.saved("todos").unwrap().find, "TODO|FIXME");
+        assert_eq!(restored.recent().count(), 1);
+
+        assert!(restored.remove_saved("todos"));
+        assert_eq!(
+            restored.saved("todos").unwrap_err().code(),
+            "unknown_saved_search"
+        );
+    }
+}
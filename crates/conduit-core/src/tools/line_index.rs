@@ -25,6 +25,61 @@ impl LineIndex {
         }
     }
 
+    /// Incrementally derive the index for `new_bytes` from this index of `old_bytes`.
+    ///
+    /// Only the region between the common prefix and suffix is rescanned, so
+    /// small edits to large files avoid a full newline scan.
+    pub fn updated(&self, old_bytes: &[u8], new_bytes: &[u8]) -> Self {
+        debug_assert_eq!(old_bytes.len(), self.total_bytes);
+        let (start, old_end, new_end) = changed_span(old_bytes, new_bytes);
+        let mut next = self.clone();
+        next.apply_splice(new_bytes, start, old_end, new_end);
+        next
+    }
+
+    /// Update in place after `old[start..old_end]` was replaced by
+    /// `new_bytes[start..new_end]`.
+    ///
+    /// `new_bytes` is the full content after the edit; only the replaced
+    /// region is scanned for newlines.
+    pub fn apply_splice(&mut self, new_bytes: &[u8], start: usize, old_end: usize, new_end: usize) {
+        debug_assert!(start <= old_end && old_end <= self.total_bytes);
+        debug_assert!(start <= new_end && new_end <= new_bytes.len());
+
+        let new_total = new_bytes.len();
+        let delta = new_end as isize - old_end as isize;
+
+        // Starts at or before `start` are unaffected; starts whose preceding
+        // newline fell inside the replaced range are dropped.
+        let keep = self.line_starts.partition_point(|&s| s <= start);
+        let tail_from = self.line_starts.partition_point(|&s| s <= old_end);
+
+        let mut inserted = Vec::new();
+        // `build` omits a start at end-of-file, so a trailing newline right
+        // before an append point has no recorded start yet.
+        if start > 0
+            && start == self.total_bytes
+            && new_bytes[start - 1] == b'\n'
+            && self.line_starts[keep - 1] != start
+        {
+            inserted.push(start);
+        }
+        for nl in memchr_iter(b'\n', &new_bytes[start..new_end]) {
+            inserted.push(start + nl + 1);
+        }
+
+        for s in &mut self.line_starts[tail_from..] {
+            *s = (*s as isize + delta) as usize;
+        }
+        self.line_starts.splice(keep..tail_from, inserted);
+
+        // Match `build`: no line starts at end-of-file.
+        while self.line_starts.len() > 1 && *self.line_starts.last().unwrap() >= new_total {
+            self.line_starts.pop();
+        }
+        self.total_bytes = new_total;
+    }
+
     /// Build from pre-computed line starts and total bytes.
     #[inline]
     pub fn from_parts(line_starts: Vec<usize>, total_bytes: usize) -> Self {
@@ -145,3 +200,139 @@ impl LineIndex {
         Some((start, end))
     }
 }
+
+/// A replaced byte range: `old[start..old_end]` became `new[start..new_end]`,
+/// with everything before `start` and after the ends unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Splice {
+    pub start: usize,
+    pub old_end: usize,
+    pub new_end: usize,
+}
+
+impl Splice {
+    /// The whole of `old` replaced by the whole of `new`.
+    pub fn whole(old_len: usize, new_len: usize) -> Self {
+        Self {
+            start: 0,
+            old_end: old_len,
+            new_end: new_len,
+        }
+    }
+
+    /// Whether this splice can describe an `old_len` to `new_len` change.
+    pub fn fits(&self, old_len: usize, new_len: usize) -> bool {
+        self.start <= self.old_end
+            && self.start <= self.new_end
+            && self.old_end <= old_len
+            && self.new_end <= new_len
+            && old_len - self.old_end == new_len - self.new_end
+    }
+}
+
+/// Smallest region that differs between `old` and `new`.
+///
+/// Returns `(start, old_end, new_end)` such that `old[start..old_end]` was
+/// replaced by `new[start..new_end]`.
+pub fn changed_span(old: &[u8], new: &[u8]) -> (usize, usize, usize) {
+    let prefix = common_prefix_len(old, new);
+    let suffix = common_suffix_len(&old[prefix..], &new[prefix..]);
+    (prefix, old.len() - suffix, new.len() - suffix)
+}
+
+// Compare in fixed-size chunks so the bulk of the work is a memcmp.
+const CMP_CHUNK: usize = 4096;
+
+fn common_prefix_len(a: &[u8], b: &[u8]) -> usize {
+    let chunked = a
+        .chunks_exact(CMP_CHUNK)
+        .zip(b.chunks_exact(CMP_CHUNK))
+        .take_while(|(x, y)| x == y)
+        .count()
+        * CMP_CHUNK;
+    chunked
+        + a[chunked..]
+            .iter()
+            .zip(&b[chunked..])
+            .take_while(|(x, y)| x == y)
+            .count()
+}
+
+fn common_suffix_len(a: &[u8], b: &[u8]) -> usize {
+    let chunked = a
+        .rchunks_exact(CMP_CHUNK)
+        .zip(b.rchunks_exact(CMP_CHUNK))
+        .take_while(|(x, y)| x == y)
+        .count()
+        * CMP_CHUNK;
+    chunked
+        + a[..a.len() - chunked]
+            .iter()
+            .rev()
+            .zip(b[..b.len() - chunked].iter().rev())
+            .take_while(|(x, y)| x == y)
+            .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_incremental(old: &str, new: &str) {
+        let updated = LineIndex::build(old.as_bytes()).updated(old.as_bytes(), new.as_bytes());
+        let rebuilt = LineIndex::build(new.as_bytes());
+        assert_eq!(
+            updated.line_starts(),
+            rebuilt.line_starts(),
+            "{old:?} -> {new:?}"
+        );
+        assert_eq!(updated.total_bytes(), rebuilt.total_bytes());
+    }
+
+    #[test]
+    fn test_changed_span() {
+        assert_eq!(changed_span(b"abcdef", b"abXYef"), (2, 4, 4));
+        assert_eq!(changed_span(b"abc", b"abc"), (3, 3, 3));
+        assert_eq!(changed_span(b"aaa", b"aaaa"), (3, 3, 4));
+        assert_eq!(changed_span(b"", b"xyz"), (0, 0, 3));
+    }
+
+    #[test]
+    fn test_splice_replace_middle_line() {
+        assert_incremental("one\ntwo\nthree\n", "one\n2\n2b\nthree\n");
+        assert_incremental("one\ntwo\nthree", "one\nthree");
+    }
+
+    #[test]
+    fn test_splice_at_boundaries() {
+        assert_incremental("a\n", "a\nb");
+        assert_incremental("a\nb", "a\n");
+        assert_incremental("a\nb\n", "");
+        assert_incremental("", "x\ny\n");
+        assert_incremental("x\ny", "new\nx\ny");
+        assert_incremental("a\n\n\n", "a\n\n");
+        assert_incremental("a\r\nb\r\n", "a\nb\n");
+    }
+
+    #[test]
+    fn test_splice_many_edits() {
+        let base: String = (0..200).map(|i| format!("line {i}\n")).collect();
+        let mut current = base.clone();
+        for i in 0..50 {
+            let at = (i * 37) % current.len();
+            let at = (0..=at)
+                .rev()
+                .find(|&p| current.is_char_boundary(p))
+                .unwrap();
+            let mut next = current.clone();
+            if i % 3 == 0 {
+                next.insert_str(at, "inserted\nlines\n");
+            } else {
+                let end = (at + 13).min(next.len());
+                next.replace_range(at..end, "");
+            }
+            assert_incremental(&current, &next);
+            current = next;
+        }
+    }
+}
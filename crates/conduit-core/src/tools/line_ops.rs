@@ -1,7 +1,15 @@
 //! Line-based text operations with range support
 
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::fs::{FileEntry, PathKey};
+use crate::tools::line_index::Splice;
+
 /// Operations that can be performed on line ranges
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "op", rename_all = "camelCase")]
 pub enum LineOperation {
     /// Replace lines from start to end (inclusive) with new content
     ReplaceRange {
@@ -26,17 +34,265 @@ pub enum LineOperation {
     },
 }
 
+/// Whitespace handling for line edits. Everything is off by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EditPolicy {
+    /// Strip trailing whitespace from inserted and replacement lines.
+    pub trim_trailing_whitespace: bool,
+    /// End the edited file with a newline, even if the original did not.
+    pub ensure_final_newline: bool,
+    /// Shift replacement content to the indentation of the lines it replaces.
+    pub preserve_indentation: bool,
+}
+
+impl EditPolicy {
+    fn clean_line<'a>(&self, line: &'a str) -> &'a str {
+        if self.trim_trailing_whitespace {
+            line.trim_end()
+        } else {
+            line
+        }
+    }
+
+    fn new_lines(&self, content: &str) -> Vec<String> {
+        content
+            .lines()
+            .map(|line| self.clean_line(line).to_string())
+            .collect()
+    }
+}
+
+impl LineOperation {
+    /// Inclusive line range removed by a replace or delete.
+    fn removed_range(&self) -> Option<(usize, usize)> {
+        match self {
+            LineOperation::ReplaceRange { start, end, .. }
+            | LineOperation::DeleteRange { start, end } => Some((*start, *end)),
+            LineOperation::InsertBefore { .. } | LineOperation::InsertAfter { .. } => None,
+        }
+    }
+}
+
+/// What is wrong with a line operation; see [`validate_line_operations`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum LineOpIssueKind {
+    /// A line number is zero or past the end of the file.
+    OutOfRange,
+    /// A range starts after it ends.
+    InvertedRange,
+    /// Two replace/delete ranges share lines.
+    Overlap,
+}
+
+/// A problem with one operation of a request.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LineOpIssue {
+    /// Position of the operation in the request.
+    pub index: usize,
+    pub kind: LineOpIssueKind,
+    /// The operation this one overlaps, for [`LineOpIssueKind::Overlap`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub other: Option<usize>,
+    pub message: String,
+}
+
+/// Pairs of operations, by request position, whose replace/delete ranges
+/// share at least one line. The lower index comes first.
+pub fn overlapping_operations(operations: &[LineOperation]) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize, usize)> = operations
+        .iter()
+        .enumerate()
+        .filter_map(|(i, op)| op.removed_range().map(|(start, end)| (start, end, i)))
+        .filter(|(start, end, _)| start <= end)
+        .collect();
+    ranges.sort_unstable();
+
+    let mut pairs = Vec::new();
+    for (n, &(_, end, i)) in ranges.iter().enumerate() {
+        for &(other_start, _, j) in &ranges[n + 1..] {
+            if other_start > end {
+                break;
+            }
+            pairs.push((i.min(j), i.max(j)));
+        }
+    }
+    pairs.sort_unstable();
+    pairs
+}
+
+/// Check `operations` against `content` without applying them.
+///
+/// [`apply_line_operations`] skips operations whose lines do not exist and
+/// applies overlapping ranges one after the other; this reports both, in
+/// request order, so a caller can reject the edit instead.
+pub fn validate_line_operations(content: &str, operations: &[LineOperation]) -> Vec<LineOpIssue> {
+    let line_count = content.strip_prefix(BOM).unwrap_or(content).lines().count();
+    let out_of_range = |index: usize, line: usize, last: usize| LineOpIssue {
+        index,
+        kind: LineOpIssueKind::OutOfRange,
+        other: None,
+        message: format!(
+            "line {line} is out of range; the file has {line_count} lines, \
+             so lines 1-{last} are allowed"
+        ),
+    };
+
+    let mut issues = Vec::new();
+    for (index, op) in operations.iter().enumerate() {
+        match *op {
+            LineOperation::ReplaceRange { start, end, .. }
+            | LineOperation::DeleteRange { start, end } => {
+                if start > end {
+                    issues.push(LineOpIssue {
+                        index,
+                        kind: LineOpIssueKind::InvertedRange,
+                        other: None,
+                        message: format!("range {start}-{end} starts after it ends"),
+                    });
+                } else if let Some(line) =
+                    [start, end].into_iter().find(|&l| l == 0 || l > line_count)
+                {
+                    issues.push(out_of_range(index, line, line_count));
+                }
+            }
+            LineOperation::InsertBefore { line, .. } => {
+                if line == 0 || line > line_count + 1 {
+                    issues.push(out_of_range(index, line, line_count + 1));
+                }
+            }
+            LineOperation::InsertAfter { line, .. } => {
+                if line == 0 || line > line_count {
+                    issues.push(out_of_range(index, line, line_count));
+                }
+            }
+        }
+    }
+    for (i, j) in overlapping_operations(operations) {
+        let (Some((a_start, a_end)), Some((b_start, b_end))) =
+            (operations[i].removed_range(), operations[j].removed_range())
+        else {
+            continue;
+        };
+        issues.push(LineOpIssue {
+            index: j,
+            kind: LineOpIssueKind::Overlap,
+            other: Some(i),
+            message: format!(
+                "lines {b_start}-{b_end} overlap lines {a_start}-{a_end} of operation {i}"
+            ),
+        });
+    }
+    issues.sort_by_key(|issue| issue.index);
+    issues
+}
+
+/// The version of a file an edit was written against.
+///
+/// Edits carrying one fail with [`Error::EditConflict`] instead of
+/// overwriting a change made since the caller last read the file.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase", default)]
+pub struct EditPrecondition {
+    /// XXH3-64 of the staged content as 16 hex digits, see
+    /// [`FileEntry::content_hash`].
+    pub expected_hash: Option<String>,
+    /// Staged modification time in unix seconds. Two writes within the same
+    /// second share an mtime; prefer `expected_hash` when that matters.
+    pub expected_mtime: Option<i64>,
+}
+
+impl EditPrecondition {
+    /// Fail unless `entry`, the staged version of `path`, matches.
+    pub fn check(&self, path: &PathKey, entry: &FileEntry) -> Result<()> {
+        let hash = entry
+            .content_hash()
+            .map_or_else(|| "none".to_string(), |h| format!("{h:016x}"));
+        let hash_ok = self
+            .expected_hash
+            .as_ref()
+            .is_none_or(|expected| expected.eq_ignore_ascii_case(&hash));
+        let mtime_ok = self.expected_mtime.is_none_or(|m| m == entry.mtime());
+        if hash_ok && mtime_ok {
+            return Ok(());
+        }
+        Err(Error::EditConflict {
+            path: path.as_str().to_string(),
+            hash,
+            mtime: entry.mtime(),
+        })
+    }
+}
+
+const BOM: char = '\u{FEFF}';
+
 /// Apply line operations to text content
+///
+/// A leading UTF-8 byte-order mark is kept at the start of the output, and
+/// insertions before line 1 of a file starting with a `#!` shebang land after
+/// the shebang so the file stays executable.
 pub fn apply_line_operations(
     content: &str,
     operations: Vec<LineOperation>,
 ) -> (String, usize, usize) {
+    apply_line_operations_with_policy(content, operations, &EditPolicy::default())
+}
+
+/// [`apply_line_operations`], with the whitespace handling of `policy`.
+pub fn apply_line_operations_with_policy(
+    content: &str,
+    operations: Vec<LineOperation>,
+    policy: &EditPolicy,
+) -> (String, usize, usize) {
+    let edit = apply_line_edit(content, operations, policy);
+    (edit.content, edit.lines_added, edit.lines_removed)
+}
+
+/// Result of [`apply_line_edit`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineEdit {
+    pub content: String,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    /// Byte range of the input that changed, so a cached
+    /// [`LineIndex`](crate::tools::LineIndex) can be spliced without
+    /// comparing the two texts.
+    pub splice: Splice,
+}
+
+/// [`apply_line_operations_with_policy`], also reporting where the text changed.
+pub fn apply_line_edit(
+    original: &str,
+    operations: Vec<LineOperation>,
+    policy: &EditPolicy,
+) -> LineEdit {
+    let (has_bom, content) = match original.strip_prefix(BOM) {
+        Some(rest) => (true, rest),
+        None => (false, original),
+    };
+    let has_shebang = content.starts_with("#!");
     let ends_with_newline = content.ends_with('\n');
 
     let mut lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
+    // Lines no operation touched at the start and end of the file.
+    let (mut head, mut tail) = (lines.len(), lines.len());
+    let mut touch = |lines: &[String], from: usize, to: usize| {
+        head = head.min(from);
+        tail = tail.min(lines.len() - to);
+    };
 
     // Sort operations by starting line (descending) to avoid index shifting issues
-    let mut sorted_ops = operations;
+    let mut sorted_ops: Vec<LineOperation> = operations
+        .into_iter()
+        .map(|op| match op {
+            LineOperation::InsertBefore { line: 1, content } if has_shebang => {
+                LineOperation::InsertBefore { line: 2, content }
+            }
+            op => op,
+        })
+        .collect();
     sorted_ops.sort_by(|a, b| {
         let a_start = match a {
             LineOperation::ReplaceRange { start, .. }
@@ -69,6 +325,13 @@ pub fn apply_line_operations(
                     // Calculate how many lines to remove (inclusive range)
                     let lines_to_remove = (end - start + 1).min(lines.len() - (start - 1));
                     total_lines_removed += lines_to_remove;
+                    touch(&lines, start - 1, start - 1 + lines_to_remove);
+
+                    let content = if policy.preserve_indentation {
+                        reindent(&content, &lines[start - 1..start - 1 + lines_to_remove])
+                    } else {
+                        content
+                    };
 
                     // Remove the lines in the range
                     for _ in 0..lines_to_remove {
@@ -79,8 +342,7 @@ pub fn apply_line_operations(
 
                     // Insert new content at the same position
                     if !content.is_empty() {
-                        let new_lines: Vec<String> =
-                            content.lines().map(|s| s.to_string()).collect();
+                        let new_lines = policy.new_lines(&content);
                         total_lines_added += new_lines.len();
                         for (i, line) in new_lines.iter().enumerate() {
                             lines.insert(start - 1 + i, line.clone());
@@ -92,6 +354,7 @@ pub fn apply_line_operations(
                 if start > 0 && start <= lines.len() && start <= end {
                     let lines_to_remove = (end - start + 1).min(lines.len() - (start - 1));
                     total_lines_removed += lines_to_remove;
+                    touch(&lines, start - 1, start - 1 + lines_to_remove);
                     for _ in 0..lines_to_remove {
                         if start - 1 < lines.len() {
                             lines.remove(start - 1);
@@ -101,7 +364,8 @@ pub fn apply_line_operations(
             }
             LineOperation::InsertBefore { line, content } => {
                 if line > 0 && line <= lines.len() + 1 {
-                    let new_lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
+                    touch(&lines, line - 1, line - 1);
+                    let new_lines = policy.new_lines(&content);
                     total_lines_added += new_lines.len();
                     for (i, new_line) in new_lines.iter().enumerate() {
                         lines.insert(line - 1 + i, new_line.clone());
@@ -110,7 +374,8 @@ pub fn apply_line_operations(
             }
             LineOperation::InsertAfter { line, content } => {
                 if line > 0 && line <= lines.len() {
-                    let new_lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
+                    touch(&lines, line, line);
+                    let new_lines = policy.new_lines(&content);
                     total_lines_added += new_lines.len();
                     for (i, new_line) in new_lines.iter().enumerate() {
                         lines.insert(line + i, new_line.clone());
@@ -122,11 +387,103 @@ pub fn apply_line_operations(
 
     let mut modified_content = lines.join("\n");
 
-    if ends_with_newline && !modified_content.is_empty() {
+    if (ends_with_newline || policy.ensure_final_newline) && !modified_content.is_empty() {
         modified_content.push('\n');
     }
 
-    (modified_content, total_lines_added, total_lines_removed)
+    // Replacing line 1 with content that carries its own BOM must not double it.
+    if let Some(rest) = modified_content.strip_prefix(BOM) {
+        modified_content = rest.to_string();
+    }
+    if has_bom && !modified_content.is_empty() {
+        modified_content.insert(0, BOM);
+    }
+
+    let splice = changed_lines(original, &modified_content, head, tail);
+    LineEdit {
+        content: modified_content,
+        lines_added: total_lines_added,
+        lines_removed: total_lines_removed,
+        splice,
+    }
+}
+
+/// Bytes of `old` between its first `head` and last `tail` lines, and where
+/// they ended up in `new`.
+fn changed_lines(old: &str, new: &str, head: usize, tail: usize) -> Splice {
+    let whole = Splice::whole(old.len(), new.len());
+    // Joining the lines drops `\r` from CRLF endings outside the edit too,
+    // and emptying a file drops its BOM.
+    if old.contains("\r\n") || new.is_empty() {
+        return whole;
+    }
+    let bom = if old.starts_with(BOM) {
+        BOM.len_utf8()
+    } else {
+        0
+    };
+    let starts: Vec<usize> = old[bom..]
+        .lines()
+        .map(|line| line.as_ptr() as usize - old.as_ptr() as usize)
+        .collect();
+    let line_start = |line: usize| starts.get(line).copied().unwrap_or(old.len());
+
+    let start = line_start(head);
+    // A newline added or dropped at the end changes the last line too.
+    let old_end = if tail == 0 || old.ends_with('\n') != new.ends_with('\n') {
+        old.len()
+    } else {
+        line_start(starts.len() - tail)
+    };
+    // Untouched head and tail lines meet when nothing was edited between them.
+    let old_end = old_end.max(start);
+    let splice = Splice {
+        start,
+        old_end,
+        new_end: (new.len() + old_end).wrapping_sub(old.len()),
+    };
+    if splice.fits(old.len(), new.len()) {
+        splice
+    } else {
+        whole
+    }
+}
+
+fn indentation(line: &str) -> &str {
+    &line[..line.len() - line.trim_start().len()]
+}
+
+/// Shift `content` so its least indented line starts at the indentation of
+/// the least indented line in `replaced`. Blank lines are left alone.
+fn reindent(content: &str, replaced: &[String]) -> String {
+    fn base<'a>(lines: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+        lines
+            .filter(|line| !line.trim().is_empty())
+            .map(indentation)
+            .min_by_key(|indent| indent.len())
+    }
+    let target = base(replaced.iter().map(String::as_str));
+    let current = base(content.lines());
+    let (Some(target), Some(current)) = (target, current) else {
+        return content.to_string();
+    };
+    if target == current {
+        return content.to_string();
+    }
+    content
+        .lines()
+        .map(|line| {
+            if line.trim().is_empty() {
+                line.to_string()
+            } else {
+                let rest = line
+                    .strip_prefix(current)
+                    .unwrap_or_else(|| line.trim_start());
+                format!("{target}{rest}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 #[cfg(test)]
@@ -258,4 +615,265 @@ mod tests {
         assert!(result.ends_with('\n'));
         assert_eq!(result, "line 1\nmodified line 2\n");
     }
+
+    #[test]
+    fn test_bom_preserved() {
+        let content = "\u{FEFF}first\nsecond\n";
+
+        let ops = vec![LineOperation::ReplaceRange {
+            start: 1,
+            end: 1,
+            content: "replaced".to_string(),
+        }];
+        let (result, _, _) = apply_line_operations(content, ops);
+        assert_eq!(result, "\u{FEFF}replaced\nsecond\n");
+
+        let ops = vec![LineOperation::InsertBefore {
+            line: 1,
+            content: "top".to_string(),
+        }];
+        let (result, _, _) = apply_line_operations(content, ops);
+        assert_eq!(result, "\u{FEFF}top\nfirst\nsecond\n");
+
+        // A replacement that includes the BOM itself does not duplicate it.
+        let ops = vec![LineOperation::ReplaceRange {
+            start: 1,
+            end: 2,
+            content: "\u{FEFF}whole file".to_string(),
+        }];
+        let (result, _, _) = apply_line_operations(content, ops);
+        assert_eq!(result, "\u{FEFF}whole file\n");
+    }
+
+    #[test]
+    fn test_insert_at_top_goes_after_shebang() {
+        let content = "#!/usr/bin/env python3\nprint('hi')\n";
+        let ops = vec![LineOperation::InsertBefore {
+            line: 1,
+            content: "import sys".to_string(),
+        }];
+
+        let (result, added, _) = apply_line_operations(content, ops);
+
+        assert_eq!(result, "#!/usr/bin/env python3\nimport sys\nprint('hi')\n");
+        assert_eq!(added, 1);
+
+        // Explicitly replacing the shebang line is still allowed.
+        let ops = vec![LineOperation::ReplaceRange {
+            start: 1,
+            end: 1,
+            content: "#!/bin/sh".to_string(),
+        }];
+        let (result, _, _) = apply_line_operations(content, ops);
+        assert_eq!(result, "#!/bin/sh\nprint('hi')\n");
+    }
+
+    #[test]
+    fn test_line_edit_splice_covers_change() {
+        use crate::tools::LineIndex;
+
+        let insert = |line| LineOperation::InsertAfter {
+            line,
+            content: "new".to_string(),
+        };
+        let cases: Vec<(&str, Vec<LineOperation>)> = vec![
+            ("a\nb\nc\n", vec![insert(1)]),
+            ("a\nb\nc", vec![insert(3)]),
+            (
+                "a\nb\nc\n",
+                vec![LineOperation::DeleteRange { start: 3, end: 3 }],
+            ),
+            (
+                "a\nb\nc",
+                vec![LineOperation::DeleteRange { start: 2, end: 3 }],
+            ),
+            (
+                "a\nb\nc\n",
+                vec![LineOperation::DeleteRange { start: 1, end: 3 }],
+            ),
+            (
+                "\u{FEFF}a\nb\n",
+                vec![LineOperation::ReplaceRange {
+                    start: 1,
+                    end: 1,
+                    content: "x\ny".to_string(),
+                }],
+            ),
+            (
+                "a\nb\nc\nd\n",
+                vec![insert(3), LineOperation::DeleteRange { start: 1, end: 1 }],
+            ),
+            ("a\r\nb\r\n", vec![insert(1)]),
+            ("a\nb\n", vec![]),
+        ];
+        for (content, ops) in cases {
+            let edit = apply_line_edit(content, ops, &EditPolicy::default());
+            let (old, new) = (content.as_bytes(), edit.content.as_bytes());
+            let Splice {
+                start,
+                old_end,
+                new_end,
+            } = edit.splice;
+            assert!(edit.splice.fits(old.len(), new.len()), "{content:?}");
+            assert_eq!(old[..start], new[..start], "{content:?}");
+            assert_eq!(old[old_end..], new[new_end..], "{content:?}");
+
+            let mut index = LineIndex::build(old);
+            index.apply_splice(new, start, old_end, new_end);
+            assert_eq!(index.line_starts(), LineIndex::build(new).line_starts());
+        }
+
+        // Only the edited line is reported, not the whole file.
+        let edit = apply_line_edit("a\nb\nc\n", vec![insert(1)], &EditPolicy::default());
+        assert_eq!(
+            edit.splice,
+            Splice {
+                start: 2,
+                old_end: 2,
+                new_end: 6
+            }
+        );
+    }
+
+    #[test]
+    fn test_edit_policy() {
+        let content = "def f():\n    if x:\n        pass\n    return 1";
+        let replace = |policy: &EditPolicy| {
+            let ops = vec![LineOperation::ReplaceRange {
+                start: 2,
+                end: 3,
+                content: "if y:  \n    \n    run()\t".to_string(),
+            }];
+            apply_line_operations_with_policy(content, ops, policy).0
+        };
+
+        assert_eq!(
+            replace(&EditPolicy::default()),
+            "def f():\nif y:  \n    \n    run()\t\n    return 1"
+        );
+        let policy = EditPolicy {
+            trim_trailing_whitespace: true,
+            ensure_final_newline: true,
+            preserve_indentation: true,
+        };
+        assert_eq!(
+            replace(&policy),
+            "def f():\n    if y:\n\n        run()\n    return 1\n"
+        );
+
+        // Insertions are trimmed but keep their own indentation.
+        let ops = vec![LineOperation::InsertAfter {
+            line: 1,
+            content: "  x = 1 ".to_string(),
+        }];
+        let (result, _, _) = apply_line_operations_with_policy(content, ops, &policy);
+        assert_eq!(
+            result,
+            "def f():\n  x = 1\n    if x:\n        pass\n    return 1\n"
+        );
+    }
+
+    #[test]
+    fn test_validate_line_operations() {
+        let content = "a\nb\nc\nd\n";
+        let ops = vec![
+            LineOperation::ReplaceRange {
+                start: 1,
+                end: 2,
+                content: "x".to_string(),
+            },
+            LineOperation::DeleteRange { start: 2, end: 3 },
+            LineOperation::InsertBefore {
+                line: 5,
+                content: "end".to_string(),
+            },
+            LineOperation::InsertAfter {
+                line: 5,
+                content: "past".to_string(),
+            },
+            LineOperation::DeleteRange { start: 4, end: 3 },
+            LineOperation::DeleteRange { start: 0, end: 1 },
+        ];
+
+        let issues = validate_line_operations(content, &ops);
+        let summary: Vec<_> = issues.iter().map(|i| (i.index, i.kind, i.other)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (1, LineOpIssueKind::Overlap, Some(0)),
+                (3, LineOpIssueKind::OutOfRange, None),
+                (4, LineOpIssueKind::InvertedRange, None),
+                (5, LineOpIssueKind::OutOfRange, None),
+                (5, LineOpIssueKind::Overlap, Some(0)),
+            ]
+        );
+        assert_eq!(
+            issues[1].message,
+            "line 5 is out of range; the file has 4 lines, so lines 1-4 are allowed"
+        );
+        assert!(validate_line_operations(content, &ops[2..3]).is_empty());
+    }
+
+    #[test]
+    fn test_overlapping_operations() {
+        let range = |start, end| LineOperation::ReplaceRange {
+            start,
+            end,
+            content: String::new(),
+        };
+        let ops = vec![
+            range(5, 8),
+            range(1, 2),
+            LineOperation::InsertAfter {
+                line: 6,
+                content: "x".to_string(),
+            },
+            range(3, 4),
+            LineOperation::DeleteRange { start: 8, end: 9 },
+            range(6, 6),
+        ];
+
+        // Adjacent ranges and insertions never conflict.
+        assert_eq!(overlapping_operations(&ops), vec![(0, 4), (0, 5)]);
+        assert!(overlapping_operations(&ops[1..4]).is_empty());
+    }
+
+    #[test]
+    fn test_edit_precondition() {
+        use std::sync::Arc;
+
+        let path = PathKey::from_arc(Arc::from("a.txt"));
+        let entry = FileEntry::from_bytes("txt", 42, Arc::from(&b"abc"[..]), true);
+        let hash = format!("{:016x}", entry.content_hash().unwrap());
+
+        let pre = |expected_hash: Option<&str>, expected_mtime| EditPrecondition {
+            expected_hash: expected_hash.map(str::to_string),
+            expected_mtime,
+        };
+        assert!(pre(None, None).check(&path, &entry).is_ok());
+        assert!(pre(Some(&hash.to_uppercase()), Some(42))
+            .check(&path, &entry)
+            .is_ok());
+        assert!(matches!(
+            pre(None, Some(41)).check(&path, &entry),
+            Err(Error::EditConflict { mtime: 42, .. })
+        ));
+        match pre(Some("0000000000000000"), None).check(&path, &entry) {
+            Err(Error::EditConflict { hash: current, .. }) => assert_eq!(current, hash),
+            other => panic!("expected a conflict, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_bom_and_shebang_together() {
+        let content = "\u{FEFF}#!/bin/sh\necho hi";
+        let ops = vec![LineOperation::InsertBefore {
+            line: 1,
+            content: "set -e".to_string(),
+        }];
+
+        let (result, _, _) = apply_line_operations(content, ops);
+
+        assert_eq!(result, "\u{FEFF}#!/bin/sh\nset -e\necho hi");
+    }
 }
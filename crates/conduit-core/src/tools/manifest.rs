@@ -0,0 +1,179 @@
+//! Machine-readable tool descriptions for agent frameworks.
+//!
+//! Schemas are derived through [`JsonSchema`] from the same types the
+//! bindings parse and serialize, so they follow the serde attributes that
+//! shape the wire form: a renamed field or a changed signature cannot keep
+//! advertising the old one.
+
+use schemars::gen::SchemaSettings;
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+/// A Rust type with a JSON Schema for its wire form.
+pub trait Describe {
+    fn schema() -> Value;
+
+    /// Whether a parameter of this type may be omitted.
+    fn optional() -> bool;
+}
+
+impl<T: JsonSchema + ?Sized> Describe for T {
+    fn schema() -> Value {
+        let mut settings = SchemaSettings::draft07();
+        // Each tool schema has to stand alone, without shared definitions.
+        settings.inline_subschemas = true;
+        settings.meta_schema = None;
+        let root = settings.into_generator().into_root_schema_for::<T>();
+        let mut schema = serde_json::to_value(root.schema).unwrap_or(Value::Null);
+        if let Some(schema) = schema.as_object_mut() {
+            schema.remove("title");
+        }
+        schema
+    }
+
+    /// `Option`s, which derive a schema that accepts `null`.
+    fn optional() -> bool {
+        accepts_null(&T::schema())
+    }
+}
+
+fn accepts_null(schema: &Value) -> bool {
+    let null = Value::from("null");
+    let nullable = match &schema["type"] {
+        Value::Array(types) => types.contains(&null),
+        ty => *ty == null,
+    };
+    nullable
+        || schema["anyOf"]
+            .as_array()
+            .is_some_and(|variants| variants.iter().any(accepts_null))
+}
+
+/// Description of one exposed operation.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolSpec {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Whether the operation can change indexed files or staging state.
+    pub mutating: bool,
+    /// Parameter names in call order.
+    pub args: Vec<String>,
+    /// Object schema of the parameters.
+    pub params: Value,
+    /// Schema of the return value; `null` for operations returning nothing.
+    pub result: Value,
+}
+
+impl ToolSpec {
+    pub fn new(name: &'static str, description: &'static str) -> Self {
+        Self {
+            name,
+            description,
+            mutating: false,
+            args: Vec::new(),
+            params: json!({ "type": "object", "properties": {}, "required": [] }),
+            result: Value::Null,
+        }
+    }
+
+    pub fn mutating(mut self) -> Self {
+        self.mutating = true;
+        self
+    }
+
+    /// Add a parameter described by its Rust type.
+    pub fn param<T: Describe + ?Sized>(self, name: &str, description: &str) -> Self {
+        self.param_schema(name, description, T::schema(), !T::optional())
+    }
+
+    /// Add a parameter with an explicit schema, for structured options.
+    pub fn param_schema(
+        mut self,
+        name: &str,
+        description: &str,
+        mut schema: Value,
+        required: bool,
+    ) -> Self {
+        schema["description"] = Value::from(description);
+        self.args.push(name.to_string());
+        self.params["properties"][name] = schema;
+        if required {
+            if let Some(list) = self.params["required"].as_array_mut() {
+                list.push(Value::from(name));
+            }
+        }
+        self
+    }
+
+    /// Set the result schema from a Rust type.
+    pub fn returns<T: Describe + ?Sized>(self) -> Self {
+        self.returns_schema(T::schema())
+    }
+
+    pub fn returns_schema(mut self, schema: Value) -> Self {
+        self.result = schema;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::GlobOptions;
+    use crate::tools::CaseFolding;
+    use crate::{ConflictPolicy, PathKey, SearchSpace};
+
+    #[test]
+    fn test_params_follow_rust_types() {
+        let spec = ToolSpec::new("read", "Read lines")
+            .param::<String>("path", "File path")
+            .param::<Option<usize>>("limit", "Max lines")
+            .param::<Vec<String>>("globs", "Filters")
+            .returns::<Vec<u32>>();
+
+        assert!(!spec.mutating);
+        assert_eq!(spec.args, ["path", "limit", "globs"]);
+        assert_eq!(spec.params["required"], json!(["path", "globs"]));
+        let limit = &spec.params["properties"]["limit"];
+        assert_eq!(limit["type"], json!(["integer", "null"]));
+        assert_eq!(limit["description"], "Max lines");
+        assert_eq!(
+            spec.params["properties"]["globs"]["items"],
+            json!({ "type": "string" })
+        );
+        assert_eq!(spec.result["type"], "array");
+    }
+
+    /// Names a string enum schema allows; documented variants are listed
+    /// one per `oneOf` entry.
+    fn names(schema: &Value) -> Vec<Value> {
+        match schema["oneOf"].as_array() {
+            Some(variants) => variants.iter().flat_map(names).collect(),
+            None => schema["enum"].as_array().cloned().unwrap_or_default(),
+        }
+    }
+
+    #[test]
+    fn test_enum_schema_uses_serialized_names() {
+        assert_eq!(names(&CaseFolding::schema()), ["unicode", "ascii"]);
+        assert_eq!(names(&SearchSpace::schema())[0], "Active");
+        assert_eq!(
+            names(&ConflictPolicy::schema()),
+            ["error", "overwrite", "rename"]
+        );
+        assert_eq!(PathKey::schema(), json!({ "type": "string" }));
+    }
+
+    #[test]
+    fn test_struct_schema_follows_serde_attributes() {
+        let schema = GlobOptions::schema();
+        assert_eq!(schema["type"], "object");
+        assert!(schema["properties"]["literalSeparator"].is_object());
+        // `#[serde(default)]` fields may be omitted.
+        assert_eq!(schema.get("required"), None);
+        assert!(!GlobOptions::optional());
+        assert!(Option::<GlobOptions>::optional());
+    }
+}
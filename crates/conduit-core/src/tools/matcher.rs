@@ -6,12 +6,41 @@ use crate::tools::model::ByteSpan;
 use grep_matcher::{Captures as _, Matcher};
 use grep_regex::{RegexMatcher as GrepMatcher, RegexMatcherBuilder};
 
+/// How letters are folded when matching case-insensitively.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    serde::Serialize,
+    serde::Deserialize,
+    schemars::JsonSchema,
+)]
+#[serde(rename_all = "camelCase")]
+pub enum CaseFolding {
+    /// Unicode simple case folding, e.g. `k` also matches the Kelvin sign `K`.
+    #[default]
+    Unicode,
+    /// Fold ASCII letters only.
+    ///
+    /// Cheaper to compile and scan on large ASCII corpora. The regex engine
+    /// ties folding to Unicode mode, so classes like `\w`, `\d` and `\b` also
+    /// become ASCII-only for the pattern.
+    Ascii,
+}
+
 /// Regex compilation options.
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(
+    Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize, schemars::JsonSchema,
+)]
 #[serde(default, rename_all = "camelCase")]
 pub struct RegexEngineOpts {
     /// Whether to match case insensitively.
     pub case_insensitive: bool,
+    /// Folding used when `case_insensitive` is set.
+    pub case_folding: CaseFolding,
     /// Whether to match unicode characters.
     pub unicode: bool,
     /// Whether to match whole words only.
@@ -28,6 +57,7 @@ impl Default for RegexEngineOpts {
     fn default() -> Self {
         Self {
             case_insensitive: false,
+            case_folding: CaseFolding::Unicode,
             unicode: true,
             word: false,
             crlf: false,
@@ -40,6 +70,7 @@ impl Default for RegexEngineOpts {
 /// Compiled regex matcher.
 pub struct RegexMatcher {
     inner: GrepMatcher,
+    pattern: Box<str>,
 }
 
 impl RegexMatcher {
@@ -50,16 +81,20 @@ impl RegexMatcher {
 
     /// Compile a pattern with the given options.
     pub fn compile(pattern: &str, opts: &RegexEngineOpts) -> Result<Self> {
+        let ascii_folding = opts.case_insensitive && opts.case_folding == CaseFolding::Ascii;
         let matcher = RegexMatcherBuilder::new()
             .case_insensitive(opts.case_insensitive)
-            .unicode(opts.unicode)
+            .unicode(opts.unicode && !ascii_folding)
             .word(opts.word)
             .crlf(opts.crlf)
             .multi_line(opts.multiline)
             .dot_matches_new_line(opts.dot_all)
             .build(pattern)?;
 
-        Ok(Self { inner: matcher })
+        Ok(Self {
+            inner: matcher,
+            pattern: pattern.into(),
+        })
     }
 
     /// Find all matches in a region, calling the callback for each.
@@ -97,6 +132,33 @@ impl RegexMatcher {
             .collect()
     }
 
+    /// Names of capture groups `$1..$N`, `None` for unnamed groups.
+    pub fn capture_names(&self) -> Vec<Option<String>> {
+        let mut names = vec![None; self.inner.capture_count().saturating_sub(1)];
+        // Every named group is written `(?P<name>` or `(?<name>`; the engine
+        // confirms which candidates are groups and where they are.
+        let mut rest = &*self.pattern;
+        while let Some(at) = rest.find("(?") {
+            rest = &rest[at + 2..];
+            let body = rest.strip_prefix('P').unwrap_or(rest);
+            let Some(name) = body
+                .strip_prefix('<')
+                .and_then(|b| b.split_once('>'))
+                .map(|(name, _)| name)
+            else {
+                continue;
+            };
+            if let Some(slot) = self
+                .inner
+                .capture_index(name)
+                .and_then(|i| names.get_mut(i.wrapping_sub(1)))
+            {
+                *slot = Some(name.to_string());
+            }
+        }
+        names
+    }
+
     /// Replace all matches in a region, writing to dst.
     pub fn replace_all(&self, region: &[u8], replacement: &str, dst: &mut Vec<u8>) -> Result<()> {
         let mut caps = self.inner.new_captures()?;
@@ -139,3 +201,138 @@ impl RegexMatcher {
         &self.inner
     }
 }
+
+/// Several patterns searched in one pass.
+///
+/// The patterns are compiled into a single alternation, so a file is
+/// scanned once however many there are. Each pattern is also kept on its
+/// own to tell which one produced a match.
+pub struct MultiMatcher {
+    combined: RegexMatcher,
+    members: Vec<RegexMatcher>,
+}
+
+impl MultiMatcher {
+    /// Compile `patterns` with the same options; fails if any is invalid.
+    pub fn compile(patterns: &[String], opts: &RegexEngineOpts) -> Result<Self> {
+        let members = patterns
+            .iter()
+            .map(|p| RegexMatcher::compile(p, opts))
+            .collect::<Result<Vec<_>>>()?;
+        let alternation = patterns
+            .iter()
+            .map(|p| format!("(?:{p})"))
+            .collect::<Vec<_>>()
+            .join("|");
+        Ok(Self {
+            combined: RegexMatcher::compile(&alternation, opts)?,
+            members,
+        })
+    }
+
+    /// Matcher for any of the patterns.
+    pub fn matcher(&self) -> &RegexMatcher {
+        &self.combined
+    }
+
+    /// Index of the pattern that produced `span`, a match of
+    /// [`MultiMatcher::matcher`] in `haystack`.
+    ///
+    /// The alternation prefers earlier patterns, so this is the first one
+    /// that matches at the same position.
+    pub fn which(&self, haystack: &[u8], span: &ByteSpan) -> Result<Option<usize>> {
+        for (i, member) in self.members.iter().enumerate() {
+            if let Some(m) = member.inner.find_at(haystack, span.start)? {
+                if m.start() == span.start {
+                    return Ok(Some(i));
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_matches(pattern: &str, opts: &RegexEngineOpts, haystack: &str) -> usize {
+        let matcher = RegexMatcher::compile(pattern, opts).unwrap();
+        let mut count = 0;
+        matcher
+            .find_matches(haystack.as_bytes(), |_| {
+                count += 1;
+                true
+            })
+            .unwrap();
+        count
+    }
+
+    #[test]
+    fn test_multi_matcher_reports_which_pattern_matched() {
+        let patterns = ["foo".to_string(), "ba[rz]".to_string(), "fo".to_string()];
+        let multi = MultiMatcher::compile(&patterns, &RegexEngineOpts::default()).unwrap();
+        let haystack = b"baz foo fo bar";
+
+        let mut found = Vec::new();
+        multi
+            .matcher()
+            .find_matches(haystack, |span| {
+                found.push(multi.which(haystack, &span).unwrap());
+                true
+            })
+            .unwrap();
+        assert_eq!(found, [Some(1), Some(0), Some(2), Some(1)]);
+
+        let bad = ["ok".to_string(), "(".to_string()];
+        assert!(MultiMatcher::compile(&bad, &RegexEngineOpts::default()).is_err());
+    }
+
+    #[test]
+    fn test_capture_names() {
+        let matcher =
+            RegexMatcher::new(r"TODO\((?P<owner>\w+)\): (\d+)?(?<rest>.*)|\(?<x>").unwrap();
+        assert_eq!(
+            matcher.capture_names(),
+            [Some("owner".to_string()), None, Some("rest".to_string())]
+        );
+        assert!(RegexMatcher::new("a").unwrap().capture_names().is_empty());
+    }
+
+    #[test]
+    fn test_case_folding_modes() {
+        // U+212A KELVIN SIGN folds to 'k' only under Unicode folding.
+        let haystack = "kelvin KELVIN \u{212A}elvin";
+        let unicode = RegexEngineOpts {
+            case_insensitive: true,
+            ..RegexEngineOpts::default()
+        };
+        let ascii = RegexEngineOpts {
+            case_folding: CaseFolding::Ascii,
+            ..unicode.clone()
+        };
+
+        assert_eq!(count_matches("kelvin", &unicode, haystack), 3);
+        assert_eq!(count_matches("kelvin", &ascii, haystack), 2);
+    }
+
+    #[test]
+    fn test_ascii_folding_ignored_when_case_sensitive() {
+        let opts = RegexEngineOpts {
+            case_folding: CaseFolding::Ascii,
+            ..RegexEngineOpts::default()
+        };
+        // Unicode classes stay available for case-sensitive searches.
+        assert_eq!(count_matches(r"\w+", &opts, "héllo"), 1);
+    }
+
+    #[test]
+    fn test_ascii_folding_accepts_non_ascii_literals() {
+        let opts = RegexEngineOpts {
+            case_insensitive: true,
+            case_folding: CaseFolding::Ascii,
+            ..RegexEngineOpts::default()
+        };
+        assert_eq!(count_matches("café", &opts, "CAFé café CAFÉ"), 2);
+    }
+}
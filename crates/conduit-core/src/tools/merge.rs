@@ -0,0 +1,252 @@
+//! Line-based three-way merge.
+//!
+//! Used to reconcile a file changed outside the app with the staged copy
+//! of it: both are diffed against the content they started from, changes
+//! to different lines are combined, and changes to the same lines are
+//! written as a conflict between git-style markers.
+
+use std::ops::Range;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use similar::{capture_diff_slices, Algorithm, DiffTag};
+
+/// Opens a conflict; followed by the staged side.
+pub const CONFLICT_OURS_MARKER: &str = "<<<<<<< staged";
+/// Separates the staged side from the other.
+pub const CONFLICT_SEPARATOR: &str = "=======";
+/// Closes a conflict.
+pub const CONFLICT_THEIRS_MARKER: &str = ">>>>>>> theirs";
+
+/// Lines changed on both sides of a merge, differently.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeConflict {
+    /// 1-based line of the opening marker in the merged content.
+    pub start_line: usize,
+    /// 1-based line of the closing marker.
+    pub end_line: usize,
+    pub base: String,
+    pub ours: String,
+    pub theirs: String,
+}
+
+/// Result of [`merge_three_way`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MergeOutcome {
+    /// Merged content, with conflict markers around each conflict.
+    pub content: String,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+impl MergeOutcome {
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
+/// Lines `base[base]` replaced by `other[new]`.
+#[derive(Debug)]
+struct Change {
+    base: Range<usize>,
+    new: Range<usize>,
+}
+
+/// Merge `ours` and `theirs`, both derived from `base`.
+///
+/// Changes are compared line by line, line endings included. A change on
+/// one side only is taken; the same change on both sides is taken once.
+/// Different changes to overlapping lines, or insertions at the same
+/// place, conflict. Changes to adjacent lines do not.
+pub fn merge_three_way(base: &str, ours: &str, theirs: &str) -> MergeOutcome {
+    let base: Vec<&str> = base.split_inclusive('\n').collect();
+    let ours: Vec<&str> = ours.split_inclusive('\n').collect();
+    let theirs: Vec<&str> = theirs.split_inclusive('\n').collect();
+    let our_changes = changes(&base, &ours);
+    let their_changes = changes(&base, &theirs);
+
+    let mut out = Output::default();
+    let (mut i, mut j) = (0, 0);
+    let mut base_pos = 0;
+    while i < our_changes.len() || j < their_changes.len() {
+        let first = match (our_changes.get(i), their_changes.get(j)) {
+            (Some(a), Some(b)) if b.base.start < a.base.start => b,
+            (Some(a), _) => a,
+            (None, Some(b)) => b,
+            (None, None) => unreachable!("loop runs while either side has changes"),
+        };
+        let start = first.base.start;
+        let mut end = first.base.end;
+        let (ours_from, theirs_from) = (i, j);
+        loop {
+            if let Some(change) = our_changes.get(i).filter(|c| overlaps(&c.base, start, end)) {
+                end = end.max(change.base.end);
+                i += 1;
+            } else if let Some(change) = their_changes
+                .get(j)
+                .filter(|c| overlaps(&c.base, start, end))
+            {
+                end = end.max(change.base.end);
+                j += 1;
+            } else {
+                break;
+            }
+        }
+
+        out.push_lines(&base[base_pos..start]);
+        let our_side = side(&our_changes[ours_from..i], &ours, &base, start, end);
+        let their_side = side(&their_changes[theirs_from..j], &theirs, &base, start, end);
+        if ours_from == i {
+            out.push_lines(their_side);
+        } else if theirs_from == j || our_side == their_side {
+            out.push_lines(our_side);
+        } else {
+            out.push_conflict(&base[start..end], our_side, their_side);
+        }
+        base_pos = end;
+    }
+    out.push_lines(&base[base_pos..]);
+
+    MergeOutcome {
+        content: out.content,
+        conflicts: out.conflicts,
+    }
+}
+
+/// Runs of changed lines between `base` and `other`, in order.
+fn changes(base: &[&str], other: &[&str]) -> Vec<Change> {
+    let mut changes: Vec<Change> = Vec::new();
+    for op in capture_diff_slices(Algorithm::Myers, base, other) {
+        let (tag, old, new) = op.as_tag_tuple();
+        if tag == DiffTag::Equal {
+            continue;
+        }
+        match changes.last_mut() {
+            Some(last) if last.base.end == old.start && last.new.end == new.start => {
+                last.base.end = old.end;
+                last.new.end = new.end;
+            }
+            _ => changes.push(Change { base: old, new }),
+        }
+    }
+    changes
+}
+
+/// Whether a change to `range` of the base touches the region
+/// `start..end`. Insertions conflict with anything at the same place.
+fn overlaps(range: &Range<usize>, start: usize, end: usize) -> bool {
+    range.start < end || (range.start == end && (range.is_empty() || start == end))
+}
+
+/// What one side made of `base[start..end]`, given its `changes` there.
+fn side<'a>(
+    changes: &[Change],
+    lines: &'a [&'a str],
+    base: &'a [&'a str],
+    start: usize,
+    end: usize,
+) -> &'a [&'a str] {
+    match (changes.first(), changes.last()) {
+        (Some(first), Some(last)) => {
+            let from = first.new.start - (first.base.start - start);
+            let to = last.new.end + (end - last.base.end);
+            &lines[from..to]
+        }
+        _ => &base[start..end],
+    }
+}
+
+#[derive(Default)]
+struct Output {
+    content: String,
+    lines: usize,
+    conflicts: Vec<MergeConflict>,
+}
+
+impl Output {
+    fn push_lines(&mut self, lines: &[&str]) {
+        for line in lines {
+            self.content.push_str(line);
+        }
+        self.lines += lines.len();
+    }
+
+    /// Push `marker` on a line of its own, ending the previous line if a side
+    /// without a final newline left it open.
+    fn push_marker(&mut self, marker: &str) {
+        if !self.content.is_empty() && !self.content.ends_with('\n') {
+            self.content.push('\n');
+        }
+        self.content.push_str(marker);
+        self.content.push('\n');
+        self.lines += 1;
+    }
+
+    fn push_conflict(&mut self, base: &[&str], ours: &[&str], theirs: &[&str]) {
+        let start_line = self.lines + 1;
+        self.push_marker(CONFLICT_OURS_MARKER);
+        self.push_lines(ours);
+        self.push_marker(CONFLICT_SEPARATOR);
+        self.push_lines(theirs);
+        self.push_marker(CONFLICT_THEIRS_MARKER);
+        self.conflicts.push(MergeConflict {
+            start_line,
+            end_line: self.lines,
+            base: base.concat(),
+            ours: ours.concat(),
+            theirs: theirs.concat(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "one\ntwo\nthree\nfour\nfive\n";
+
+    #[test]
+    fn test_combines_separate_changes() {
+        let ours = "ONE\ntwo\nthree\nfour\nfive\n";
+        let theirs = "one\ntwo\nthree\nfour\nFIVE\nsix\n";
+        let merged = merge_three_way(BASE, ours, theirs);
+        assert!(merged.is_clean());
+        assert_eq!(merged.content, "ONE\ntwo\nthree\nfour\nFIVE\nsix\n");
+
+        // The same change on both sides is taken once.
+        let merged = merge_three_way(BASE, ours, ours);
+        assert_eq!(merged.content, ours);
+        assert_eq!(merge_three_way(BASE, BASE, BASE).content, BASE);
+    }
+
+    #[test]
+    fn test_marks_conflicting_changes() {
+        let ours = "one\nTWO\nthree\nfour\nfive\n";
+        let theirs = "one\n2\nthree\nfour\nfive\nsix";
+        let merged = merge_three_way(BASE, ours, theirs);
+        assert_eq!(
+            merged.content,
+            "one\n<<<<<<< staged\nTWO\n=======\n2\n>>>>>>> theirs\nthree\nfour\nfive\nsix"
+        );
+        assert_eq!(
+            merged.conflicts,
+            vec![MergeConflict {
+                start_line: 2,
+                end_line: 6,
+                base: "two\n".into(),
+                ours: "TWO\n".into(),
+                theirs: "2\n".into(),
+            }]
+        );
+
+        // Insertions at the same place conflict; a side without a final
+        // newline still gets markers on lines of their own.
+        let merged = merge_three_way("a\n", "a\nb", "a\nc\n");
+        assert_eq!(
+            merged.content,
+            "a\n<<<<<<< staged\nb\n=======\nc\n>>>>>>> theirs\n"
+        );
+        assert_eq!(merged.conflicts[0].ours, "b");
+    }
+}
@@ -1,24 +1,90 @@
 pub mod abort;
+pub mod anchor;
+pub mod archive;
+pub mod changes;
+pub mod chunk;
+pub mod compress;
 pub mod diff;
+pub mod edit_plan;
+pub mod embedding;
+pub mod extract;
+pub mod history;
 pub mod line_index;
 pub mod line_ops;
+pub mod manifest;
 pub mod matcher;
+pub mod merge;
 pub mod model;
+pub mod parallel;
+pub mod patch;
+pub mod patterns;
 pub mod preview;
 pub mod read;
+pub mod references;
+pub mod related;
+pub mod renames;
 pub mod replace;
+pub mod report;
+pub mod result_set;
+pub mod scaffold;
 pub mod search;
+pub mod tokens;
+pub mod utf8;
 
-pub use abort::AbortFlag;
-pub use diff::{compute_diff, compute_diffs, DiffRegion, DiffStats, FileDiff};
-pub use line_index::LineIndex;
-pub use line_ops::{apply_line_operations, LineOperation};
-pub use matcher::{RegexEngineOpts, RegexMatcher};
+pub use abort::{AbortFlag, Clock};
+pub use anchor::{apply_anchored_edit, find_anchor, resolve_anchor, AnchoredEdit};
+pub use archive::{
+    read_archive, read_archive_with, ArchiveEntry, ArchiveFormat, ArchiveWriter, ChangeManifest,
+    CHANGE_MANIFEST_PATH,
+};
+pub use changes::render_change_summary;
+pub use chunk::{chunk_text, looks_binary, Chunk, ChunkOptions};
+pub use compress::{
+    compress, decompress, Compression, CompressionPolicy, Packed, DEFAULT_COMPRESSION_THRESHOLD,
+};
+pub use diff::{
+    compute_diff, compute_diffs, refine_diff, DiffGranularity, DiffRegion, DiffStats, FileDiff,
+    InlineSpan,
+};
+pub use edit_plan::{EditPlan, PlannedFile};
+pub use embedding::{rank_chunks, EmbeddingCache, ScoredChunk};
+pub use extract::{extract_match, CaptureGroup, ExtractedMatch};
+pub use history::{SearchHistory, SEARCH_HISTORY_LIMIT};
+pub use line_index::{changed_span, LineIndex, Splice};
+pub use line_ops::{
+    apply_line_edit, apply_line_operations, apply_line_operations_with_policy,
+    overlapping_operations, validate_line_operations, EditPolicy, EditPrecondition, LineEdit,
+    LineOpIssue, LineOpIssueKind, LineOperation,
+};
+pub use manifest::{Describe, ToolSpec};
+pub use matcher::{CaseFolding, MultiMatcher, RegexEngineOpts, RegexMatcher};
+pub use merge::{
+    merge_three_way, MergeConflict, MergeOutcome, CONFLICT_OURS_MARKER, CONFLICT_SEPARATOR,
+    CONFLICT_THEIRS_MARKER,
+};
 pub use model::{ByteSpan, LineSpan, Match};
-pub use preview::{PreviewBuilder, PreviewHunk};
-pub use read::{extract_lines, extract_lines_with_index, ReadRequest, ReadResponse};
-pub use replace::{EditOp, ReplacePlan};
+pub use parallel::{available_threads, map_ordered};
+pub use patch::{
+    apply_hunks, parse_patch, FilePatch, FilePatchOutcome, Hunk, HunkLine, HunkOutcome,
+    PatchReport, PatchSection, DEFAULT_CONTEXT_LINES,
+};
+pub use patterns::{PatternRegistry, SavedPattern};
+pub use preview::{MatchColumns, PreviewBuilder, PreviewHunk};
+pub use read::{
+    extract_lines, extract_lines_checked, extract_lines_with_index, ReadRequest, ReadResponse,
+};
+pub use references::{ReferenceEdit, ReferenceRewriter};
+pub use related::{suggest_related, RelatedFile, RelatedFiles, RelatedKind};
+pub use renames::{detect_renames, DetectedRename, DEFAULT_RENAME_THRESHOLD, RENAME_PAIR_LIMIT};
+pub use replace::{
+    apply_plan, changed_lines, plan_in_bytes, preserve_case, preview_plan, EditOp, ReplacePlan,
+};
+pub use report::{render_report, MatchRecord, ReportFormat};
+pub use result_set::{ResultLine, ResultSet, ResultSetPage, DEFAULT_RESULT_PAGE_SIZE};
+pub use scaffold::{ScaffoldRegistry, ScaffoldTemplate, TemplateFile};
 pub use search::{for_each_match, search_regions, MatchRegion};
+pub use tokens::{TokenCount, TokenCounter, TokenKind};
+pub use utf8::{decode_checked, decode_lossy};
 pub mod prelude {
     pub use super::{
         extract_lines, AbortFlag, ByteSpan, LineIndex, LineSpan, Match, PreviewBuilder,
@@ -0,0 +1,70 @@
+//! Spreading per-file work across threads.
+//!
+//! With the `parallel` feature, work runs on rayon's global pool: native
+//! threads on native targets, and on `wasm32` built with the `atomics`
+//! target feature, whatever pool the host started (for example with
+//! `wasm-bindgen-rayon`). Without the feature, on `wasm32` without atomics,
+//! or when the pool has a single thread (as when `SharedArrayBuffer` is
+//! unavailable and no workers could start), work runs in order on the
+//! calling thread.
+
+#[cfg(all(
+    feature = "parallel",
+    any(not(target_arch = "wasm32"), target_feature = "atomics")
+))]
+mod imp {
+    use rayon::prelude::*;
+
+    pub fn threads() -> usize {
+        rayon::current_num_threads()
+    }
+
+    pub fn map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+        items.par_iter().map(&f).collect()
+    }
+}
+
+#[cfg(not(all(
+    feature = "parallel",
+    any(not(target_arch = "wasm32"), target_feature = "atomics")
+)))]
+mod imp {
+    pub fn threads() -> usize {
+        1
+    }
+
+    pub fn map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+        items.iter().map(f).collect()
+    }
+}
+
+/// Threads [`map_ordered`] can use; `1` means it runs serially.
+pub fn available_threads() -> usize {
+    imp::threads()
+}
+
+/// `f` applied to every item, with results in input order however the
+/// work was split.
+pub fn map_ordered<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    if items.len() < 2 || available_threads() < 2 {
+        return items.iter().map(f).collect();
+    }
+    imp::map(items, f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_map_ordered_keeps_input_order() {
+        let items: Vec<u64> = (0..1000).collect();
+        let squares = map_ordered(&items, |n| n * n);
+        assert_eq!(squares.len(), 1000);
+        assert!(squares
+            .iter()
+            .enumerate()
+            .all(|(i, &sq)| sq == (i * i) as u64));
+        assert!(available_threads() >= 1);
+    }
+}
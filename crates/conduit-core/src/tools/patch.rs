@@ -0,0 +1,642 @@
+//! Git-style unified patches: rendering staged changes and parsing patches
+//! to apply.
+//!
+//! [`FilePatch`] describes one file's change between two snapshots; its
+//! [`FilePatch::render`] output is what `git diff` prints for the same
+//! change, so the result can be reviewed with standard tools or fed to
+//! `git apply`. In the other direction, [`parse_patch`] reads such a patch
+//! into [`PatchSection`]s and [`apply_hunks`] applies one section's hunks to
+//! a file's text, checking their context lines.
+
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+
+use crate::error::{Error, Result};
+use crate::fs::PathKey;
+use crate::tools::chunk::looks_binary;
+
+/// Lines of unchanged context around each hunk, as in `git diff`.
+pub const DEFAULT_CONTEXT_LINES: usize = 3;
+
+const NULL_PATH: &str = "/dev/null";
+
+/// One file's change: absent `old_path` means the file was created, absent
+/// `new_path` that it was deleted, and two different paths a rename.
+#[derive(Debug, Clone, Copy)]
+pub struct FilePatch<'a> {
+    pub old_path: Option<&'a str>,
+    pub new_path: Option<&'a str>,
+    pub old: &'a [u8],
+    pub new: &'a [u8],
+}
+
+impl<'a> FilePatch<'a> {
+    pub fn modified(path: &'a str, old: &'a [u8], new: &'a [u8]) -> Self {
+        Self {
+            old_path: Some(path),
+            new_path: Some(path),
+            old,
+            new,
+        }
+    }
+
+    pub fn created(path: &'a str, new: &'a [u8]) -> Self {
+        Self {
+            old_path: None,
+            new_path: Some(path),
+            old: &[],
+            new,
+        }
+    }
+
+    pub fn deleted(path: &'a str, old: &'a [u8]) -> Self {
+        Self {
+            old_path: Some(path),
+            new_path: None,
+            old,
+            new: &[],
+        }
+    }
+
+    pub fn renamed(from: &'a str, to: &'a str, old: &'a [u8], new: &'a [u8]) -> Self {
+        Self {
+            old_path: Some(from),
+            new_path: Some(to),
+            old,
+            new,
+        }
+    }
+
+    /// Render the patch with `context` lines around each hunk.
+    ///
+    /// Returns an empty string for an unchanged file kept at the same path.
+    /// Binary content is reported as differing without hunks.
+    pub fn render(&self, context: usize) -> String {
+        let (old_name, new_name) = match (self.old_path, self.new_path) {
+            (Some(old), Some(new)) => (old, new),
+            (Some(path), None) | (None, Some(path)) => (path, path),
+            (None, None) => return String::new(),
+        };
+        let renamed = old_name != new_name;
+        let same_content = self.old == self.new;
+        if same_content && !renamed && self.old_path.is_some() && self.new_path.is_some() {
+            return String::new();
+        }
+
+        let mut out = format!("diff --git a/{old_name} b/{new_name}\n");
+        if self.old_path.is_none() {
+            out.push_str("new file mode 100644\n");
+        } else if self.new_path.is_none() {
+            out.push_str("deleted file mode 100644\n");
+        } else if renamed {
+            out.push_str(&format!(
+                "similarity index {}%\nrename from {old_name}\nrename to {new_name}\n",
+                similarity(self.old, self.new)
+            ));
+        }
+        if same_content {
+            return out;
+        }
+
+        let from = self
+            .old_path
+            .map_or_else(|| NULL_PATH.to_string(), |p| format!("a/{p}"));
+        let to = self
+            .new_path
+            .map_or_else(|| NULL_PATH.to_string(), |p| format!("b/{p}"));
+        if looks_binary(self.old) || looks_binary(self.new) {
+            out.push_str(&format!("Binary files {from} and {to} differ\n"));
+            return out;
+        }
+
+        out.push_str(&format!("--- {from}\n+++ {to}\n"));
+        let old = String::from_utf8_lossy(self.old);
+        let new = String::from_utf8_lossy(self.new);
+        let diff = TextDiff::from_lines(old.as_ref(), new.as_ref());
+        for hunk in diff.unified_diff().context_radius(context).iter_hunks() {
+            out.push_str(&hunk.to_string());
+        }
+        out
+    }
+}
+
+/// Percentage of lines shared by `old` and `new`, as shown for renames.
+fn similarity(old: &[u8], new: &[u8]) -> u32 {
+    if old == new {
+        return 100;
+    }
+    let old = String::from_utf8_lossy(old);
+    let new = String::from_utf8_lossy(new);
+    let ratio = TextDiff::from_lines(old.as_ref(), new.as_ref()).ratio();
+    (ratio * 100.0).floor() as u32
+}
+
+/// One line of a hunk body. The text keeps its line terminator unless the
+/// patch marks the line as having no newline at end of file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+/// One `@@` hunk; starts are 1-based, as written in the header.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_len: usize,
+    pub new_start: usize,
+    pub new_len: usize,
+    pub lines: Vec<HunkLine>,
+}
+
+impl Hunk {
+    /// Number of lines the hunk adds and removes.
+    pub fn line_counts(&self) -> (usize, usize) {
+        self.lines
+            .iter()
+            .fold((0, 0), |(added, removed), line| match line {
+                HunkLine::Add(_) => (added + 1, removed),
+                HunkLine::Remove(_) => (added, removed + 1),
+                HunkLine::Context(_) => (added, removed),
+            })
+    }
+
+    fn old_lines(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().filter_map(|line| match line {
+            HunkLine::Context(text) | HunkLine::Remove(text) => Some(text.as_str()),
+            HunkLine::Add(_) => None,
+        })
+    }
+}
+
+/// The part of a patch concerning one file. Paths follow the same
+/// conventions as [`FilePatch`]: no `old_path` for a created file, no
+/// `new_path` for a deleted one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PatchSection {
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    pub hunks: Vec<Hunk>,
+}
+
+/// Result of applying one hunk.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HunkOutcome {
+    pub old_start: usize,
+    pub new_start: usize,
+    pub applied: bool,
+    /// Lines between where the header placed the hunk and where its
+    /// context was found.
+    pub offset: isize,
+    pub error: Option<String>,
+}
+
+/// Result of applying one [`PatchSection`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilePatchOutcome {
+    pub old_path: Option<PathKey>,
+    pub new_path: Option<PathKey>,
+    /// Why the file could not be patched, apart from failed hunks.
+    pub error: Option<String>,
+    pub hunks: Vec<HunkOutcome>,
+}
+
+impl FilePatchOutcome {
+    pub fn is_applied(&self) -> bool {
+        self.error.is_none() && self.hunks.iter().all(|hunk| hunk.applied)
+    }
+}
+
+/// Result of applying a whole patch. Staging only changes if every file
+/// and hunk applied.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatchReport {
+    pub applied: bool,
+    pub files: Vec<FilePatchOutcome>,
+}
+
+/// Parse a unified diff, with or without git's extended headers.
+///
+/// Lines outside of file sections and hunks, such as commit messages or
+/// `index` lines, are ignored. A body line that is empty is read as an empty
+/// context line, since editors and models often strip the leading space.
+pub fn parse_patch(text: &str) -> Result<Vec<PatchSection>> {
+    let mut sections = Vec::new();
+    let mut current: Option<SectionBuilder> = None;
+    let mut lines = text.split_inclusive('\n').enumerate().peekable();
+
+    while let Some((index, raw)) = lines.next() {
+        let line_no = index + 1;
+        let line = raw.trim_end_matches(['\n', '\r']);
+
+        if let Some(rest) = line.strip_prefix("diff --git ") {
+            sections.extend(current.take().map(SectionBuilder::finish).transpose()?);
+            let (old, new) = split_git_header(rest)
+                .ok_or_else(|| invalid(line_no, "malformed 'diff --git' header"))?;
+            current = Some(SectionBuilder::new(line_no, Some(old), Some(new)));
+        } else if let Some(rest) = line.strip_prefix("--- ") {
+            // A plain diff starts a new file at each `---` following hunks.
+            let fresh = current.as_ref().is_none_or(|c| !c.section.hunks.is_empty());
+            if fresh {
+                sections.extend(current.take().map(SectionBuilder::finish).transpose()?);
+                current = Some(SectionBuilder::new(line_no, None, None));
+            }
+            let section = current.as_mut().expect("section was just started");
+            match parse_path(rest) {
+                Some(path) => section.section.old_path = Some(path),
+                None => section.created = true,
+            }
+        } else if let Some(rest) = line.strip_prefix("+++ ") {
+            let section = current
+                .as_mut()
+                .ok_or_else(|| invalid(line_no, "'+++' without a file header"))?;
+            match parse_path(rest) {
+                Some(path) => section.section.new_path = Some(path),
+                None => section.deleted = true,
+            }
+        } else if line.starts_with("@@ ") {
+            let section = current
+                .as_mut()
+                .ok_or_else(|| invalid(line_no, "hunk without a file header"))?;
+            let mut hunk =
+                parse_hunk_header(line).ok_or_else(|| invalid(line_no, "malformed hunk header"))?;
+            let (mut old_left, mut new_left) = (hunk.old_len, hunk.new_len);
+            while old_left > 0 || new_left > 0 {
+                let (index, raw) = lines
+                    .next()
+                    .ok_or_else(|| invalid(line_no, "hunk ends before its line counts"))?;
+                let body_no = index + 1;
+                // Only the last line of the text can lack a terminator.
+                let text = |body: &str| {
+                    if body.ends_with('\n') {
+                        body.to_string()
+                    } else {
+                        format!("{body}\n")
+                    }
+                };
+                let (line, old, new) = match raw.as_bytes()[0] {
+                    b' ' => (HunkLine::Context(text(&raw[1..])), 1, 1),
+                    b'\n' | b'\r' => (HunkLine::Context(text(raw)), 1, 1),
+                    b'-' => (HunkLine::Remove(text(&raw[1..])), 1, 0),
+                    b'+' => (HunkLine::Add(text(&raw[1..])), 0, 1),
+                    b'\\' => {
+                        strip_newline(&mut hunk);
+                        continue;
+                    }
+                    _ => return Err(invalid(body_no, "unexpected line inside hunk")),
+                };
+                if old > old_left || new > new_left {
+                    return Err(invalid(body_no, "hunk is longer than its header says"));
+                }
+                old_left -= old;
+                new_left -= new;
+                hunk.lines.push(line);
+            }
+            if lines.peek().is_some_and(|(_, next)| next.starts_with('\\')) {
+                lines.next();
+                strip_newline(&mut hunk);
+            }
+            section.section.hunks.push(hunk);
+        } else if let Some(section) = current.as_mut() {
+            if line.starts_with("new file mode") {
+                section.created = true;
+            } else if line.starts_with("deleted file mode") {
+                section.deleted = true;
+            } else if let Some(path) = line.strip_prefix("rename from ") {
+                section.section.old_path = Some(unquote(path).to_string());
+            } else if let Some(path) = line.strip_prefix("rename to ") {
+                section.section.new_path = Some(unquote(path).to_string());
+            } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+                return Err(invalid(line_no, "binary patches are not supported"));
+            }
+        }
+    }
+    sections.extend(current.take().map(SectionBuilder::finish).transpose()?);
+    Ok(sections)
+}
+
+/// Apply `hunks`, in order, to `content`.
+///
+/// Each hunk's removed and context lines must appear in `content`, at the
+/// line its header names or, failing that, at the nearest position after
+/// the previous hunk; the offset found carries over to later hunks. Line
+/// endings are not compared, and context lines keep the file's endings.
+/// Hunks that do not match are skipped and reported as failed.
+pub fn apply_hunks(content: &str, hunks: &[Hunk]) -> (String, Vec<HunkOutcome>) {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let mut out = String::with_capacity(content.len());
+    let mut outcomes = Vec::with_capacity(hunks.len());
+    let mut cursor = 0;
+    let mut drift = 0isize;
+
+    for hunk in hunks {
+        let old: Vec<&str> = hunk.old_lines().collect();
+        // A header `-N,0` names the line after which to insert.
+        let declared = match hunk.old_len {
+            0 => hunk.old_start,
+            _ => hunk.old_start.saturating_sub(1),
+        } as isize;
+        let found = find_block(&lines, &old, cursor, declared + drift);
+        let Some(at) = found else {
+            outcomes.push(HunkOutcome {
+                old_start: hunk.old_start,
+                new_start: hunk.new_start,
+                applied: false,
+                offset: 0,
+                error: Some(format!(
+                    "context does not match near line {}",
+                    hunk.old_start
+                )),
+            });
+            continue;
+        };
+
+        for line in &lines[cursor..at] {
+            push_line(&mut out, line);
+        }
+        let mut pos = at;
+        for line in &hunk.lines {
+            match line {
+                HunkLine::Context(_) => {
+                    push_line(&mut out, lines[pos]);
+                    pos += 1;
+                }
+                HunkLine::Remove(_) => pos += 1,
+                HunkLine::Add(text) => push_line(&mut out, text),
+            }
+        }
+        cursor = pos;
+        drift = at as isize - declared;
+        outcomes.push(HunkOutcome {
+            old_start: hunk.old_start,
+            new_start: hunk.new_start,
+            applied: true,
+            offset: drift,
+            error: None,
+        });
+    }
+    for line in &lines[cursor..] {
+        push_line(&mut out, line);
+    }
+    (out, outcomes)
+}
+
+/// Start of the first run of `block` in `lines[from..]` closest to `near`.
+fn find_block(lines: &[&str], block: &[&str], from: usize, near: isize) -> Option<usize> {
+    let last = lines.len().checked_sub(block.len())?;
+    if from > last {
+        return None;
+    }
+    let near = near.clamp(from as isize, last as isize) as usize;
+    let matches = |at: usize| {
+        lines[at..at + block.len()]
+            .iter()
+            .zip(block)
+            .all(|(a, b)| same_line(a, b))
+    };
+    (0..=last - from).find_map(|delta| {
+        [near.checked_add(delta), near.checked_sub(delta)]
+            .into_iter()
+            .flatten()
+            .find(|&at| at >= from && at <= last && matches(at))
+    })
+}
+
+fn same_line(a: &str, b: &str) -> bool {
+    a.trim_end_matches(['\n', '\r']) == b.trim_end_matches(['\n', '\r'])
+}
+
+/// Append `line`, first ending the previous line if it had no newline.
+fn push_line(out: &mut String, line: &str) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+    out.push_str(line);
+}
+
+struct SectionBuilder {
+    line_no: usize,
+    section: PatchSection,
+    created: bool,
+    deleted: bool,
+}
+
+impl SectionBuilder {
+    fn new(line_no: usize, old_path: Option<String>, new_path: Option<String>) -> Self {
+        Self {
+            line_no,
+            section: PatchSection {
+                old_path,
+                new_path,
+                hunks: Vec::new(),
+            },
+            created: false,
+            deleted: false,
+        }
+    }
+
+    fn finish(mut self) -> Result<PatchSection> {
+        if self.created && self.deleted {
+            return Err(invalid(self.line_no, "file is both created and deleted"));
+        }
+        if self.created {
+            self.section.old_path = None;
+        }
+        if self.deleted {
+            self.section.new_path = None;
+        }
+        if self.section.old_path.is_none() && self.section.new_path.is_none() {
+            return Err(invalid(self.line_no, "file section names no path"));
+        }
+        Ok(self.section)
+    }
+}
+
+fn invalid(line: usize, reason: &str) -> Error {
+    Error::InvalidPatch {
+        line,
+        reason: reason.to_string(),
+    }
+}
+
+/// Drop the newline of the hunk's last line, for `\ No newline at end of file`.
+fn strip_newline(hunk: &mut Hunk) {
+    if let Some(HunkLine::Context(text) | HunkLine::Remove(text) | HunkLine::Add(text)) =
+        hunk.lines.last_mut()
+    {
+        let len = text.trim_end_matches(['\n', '\r']).len();
+        text.truncate(len);
+    }
+}
+
+/// Old and new path of `a/x b/y`, the part after `diff --git `.
+fn split_git_header(rest: &str) -> Option<(String, String)> {
+    let rest = rest.trim_end();
+    // Equal paths split in the middle, which also copes with ` b/` in names.
+    let half = rest.len() / 2;
+    let symmetric = rest.len() % 2 == 1
+        && rest.is_char_boundary(half)
+        && rest.as_bytes()[half] == b' '
+        && rest[..half].get(2..) == rest[half + 1..].get(2..);
+    let (old, new) = if symmetric {
+        (&rest[..half], &rest[half + 1..])
+    } else {
+        let at = rest.find(" b/")?;
+        (&rest[..at], &rest[at + 1..])
+    };
+    let old = unquote(old).strip_prefix("a/")?;
+    let new = unquote(new).strip_prefix("b/")?;
+    Some((old.to_string(), new.to_string()))
+}
+
+/// Path of a `---`/`+++` line, without its `a/`/`b/` prefix or timestamp;
+/// `None` for `/dev/null`.
+fn parse_path(rest: &str) -> Option<String> {
+    let path = unquote(rest.split('\t').next().unwrap_or(rest).trim_end());
+    if path == NULL_PATH {
+        return None;
+    }
+    let path = path
+        .strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path);
+    Some(path.to_string())
+}
+
+fn unquote(path: &str) -> &str {
+    path.strip_prefix('"')
+        .and_then(|p| p.strip_suffix('"'))
+        .unwrap_or(path)
+}
+
+/// `@@ -a,b +c,d @@`, with lengths defaulting to 1.
+fn parse_hunk_header(line: &str) -> Option<Hunk> {
+    let rest = line.strip_prefix("@@ -")?;
+    let (ranges, _) = rest.split_once(" @@")?;
+    let (old, new) = ranges.split_once(" +")?;
+    let range = |r: &str| -> Option<(usize, usize)> {
+        match r.split_once(',') {
+            Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+            None => Some((r.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_len) = range(old)?;
+    let (new_start, new_len) = range(new)?;
+    Some(Hunk {
+        old_start,
+        old_len,
+        new_start,
+        new_len,
+        lines: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_modification_and_lifecycle_headers() {
+        let patch = FilePatch::modified("src/a.rs", b"one\ntwo\nthree\n", b"one\n2\nthree\n");
+        assert_eq!(
+            patch.render(DEFAULT_CONTEXT_LINES),
+            "diff --git a/src/a.rs b/src/a.rs\n\
+             --- a/src/a.rs\n\
+             +++ b/src/a.rs\n\
+             @@ -1,3 +1,3 @@\n \
+             one\n\
+             -two\n\
+             +2\n \
+             three\n"
+        );
+        assert_eq!(FilePatch::modified("x", b"same", b"same").render(3), "");
+
+        let created = FilePatch::created("new.txt", b"hi").render(3);
+        assert_eq!(
+            created,
+            "diff --git a/new.txt b/new.txt\n\
+             new file mode 100644\n\
+             --- /dev/null\n\
+             +++ b/new.txt\n\
+             @@ -0,0 +1 @@\n\
+             +hi\n\
+             \\ No newline at end of file\n"
+        );
+
+        let deleted = FilePatch::deleted("old.txt", b"bye\n").render(3);
+        assert!(deleted.contains("deleted file mode 100644\n--- a/old.txt\n+++ /dev/null\n"));
+        assert!(deleted.ends_with("@@ -1 +0,0 @@\n-bye\n"));
+
+        let binary = FilePatch::modified("img.png", b"\0a", b"\0b").render(3);
+        assert!(binary.ends_with("Binary files a/img.png and b/img.png differ\n"));
+    }
+
+    #[test]
+    fn test_render_rename() {
+        let pure = FilePatch::renamed("a.txt", "b.txt", b"x\n", b"x\n").render(3);
+        assert_eq!(
+            pure,
+            "diff --git a/a.txt b/b.txt\n\
+             similarity index 100%\n\
+             rename from a.txt\n\
+             rename to b.txt\n"
+        );
+
+        let edited =
+            FilePatch::renamed("a.txt", "b.txt", b"1\n2\n3\n4\n", b"1\n2\n3\n5\n").render(3);
+        assert!(edited.contains("similarity index 75%\n"));
+        assert!(edited.contains("--- a/a.txt\n+++ b/b.txt\n@@ -1,4 +1,4 @@\n"));
+    }
+
+    #[test]
+    fn test_rendered_patches_parse_and_apply() {
+        let cases: [(&[u8], &[u8]); 3] = [
+            (b"a\nb\nc\nd\ne\nf\ng\nh\n", b"a\nB\nc\nd\ne\nf\ng\nh\ni\n"),
+            (b"x\ny", b"x\ny\nz"),
+            (b"", b"new\n"),
+        ];
+        for (old, new) in cases {
+            let text = FilePatch::renamed("a.txt", "b.txt", old, new).render(1);
+            let sections = parse_patch(&text).unwrap();
+            assert_eq!(sections.len(), 1);
+            assert_eq!(sections[0].old_path.as_deref(), Some("a.txt"));
+            assert_eq!(sections[0].new_path.as_deref(), Some("b.txt"));
+
+            let old = std::str::from_utf8(old).unwrap();
+            let (patched, outcomes) = apply_hunks(old, &sections[0].hunks);
+            assert!(outcomes.iter().all(|o| o.applied && o.offset == 0));
+            assert_eq!(patched.as_bytes(), new);
+        }
+
+        let sections = parse_patch(&FilePatch::deleted("gone.rs", b"1\n").render(3)).unwrap();
+        assert_eq!(sections[0].old_path.as_deref(), Some("gone.rs"));
+        assert_eq!(sections[0].new_path, None);
+    }
+
+    #[test]
+    fn test_apply_hunks_with_offset_and_mismatch() {
+        // A plain diff whose line numbers are two lines off, one of whose
+        // hunks does not match.
+        let patch = "--- src/lib.rs\n+++ src/lib.rs\n\
+                     @@ -1,2 +1,2 @@\n fn a() {}\n-fn b() {}\n+fn b2() {}\n\
+                     @@ -5,1 +5,1 @@\n-fn missing() {}\n+fn z() {}\n";
+        let sections = parse_patch(patch).unwrap();
+        assert_eq!(sections[0].old_path.as_deref(), Some("src/lib.rs"));
+
+        let content = "// header\r\n\r\nfn a() {}\r\nfn b() {}\r\nfn c() {}\r\n";
+        let (patched, outcomes) = apply_hunks(content, &sections[0].hunks);
+        assert!(outcomes[0].applied);
+        assert_eq!(outcomes[0].offset, 2);
+        assert!(!outcomes[1].applied);
+        assert!(outcomes[1].error.is_some());
+        // Context keeps the file's line endings.
+        assert_eq!(
+            patched,
+            "// header\r\n\r\nfn a() {}\r\nfn b2() {}\nfn c() {}\r\n"
+        );
+
+        let err = parse_patch("--- a/x\n+++ b/x\n@@ -1,2 +1,1 @@\n-a\n").unwrap_err();
+        assert_eq!(err.code(), "invalid_patch");
+    }
+}
@@ -0,0 +1,92 @@
+//! Named, precompiled regex patterns.
+//!
+//! Hosts that run the same heavy patterns repeatedly register them once and
+//! refer to them by name through `pattern_ref`, skipping recompilation.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+use crate::tools::{RegexEngineOpts, RegexMatcher};
+
+/// A registered pattern and its compiled matcher.
+pub struct SavedPattern {
+    pub pattern: String,
+    pub opts: RegexEngineOpts,
+    pub matcher: Arc<RegexMatcher>,
+}
+
+/// Patterns by name.
+#[derive(Default)]
+pub struct PatternRegistry {
+    patterns: BTreeMap<String, SavedPattern>,
+}
+
+impl PatternRegistry {
+    /// Compile and store `pattern` under `name`, replacing any pattern
+    /// already registered there. Nothing changes if compilation fails.
+    /// Returns `true` if an existing pattern was replaced.
+    pub fn register(&mut self, name: &str, pattern: &str, opts: RegexEngineOpts) -> Result<bool> {
+        let matcher = Arc::new(RegexMatcher::compile(pattern, &opts)?);
+        let saved = SavedPattern {
+            pattern: pattern.to_string(),
+            opts,
+            matcher,
+        };
+        Ok(self.patterns.insert(name.to_string(), saved).is_some())
+    }
+
+    /// Remove `name`; returns whether it was registered.
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.patterns.remove(name).is_some()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&SavedPattern> {
+        self.patterns.get(name)
+    }
+
+    /// The compiled matcher for `name`.
+    pub fn matcher(&self, name: &str) -> Result<Arc<RegexMatcher>> {
+        self.get(name)
+            .map(|saved| saved.matcher.clone())
+            .ok_or_else(|| Error::UnknownPattern(name.to_string()))
+    }
+
+    /// Registered patterns, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &SavedPattern)> {
+        self.patterns
+            .iter()
+            .map(|(name, saved)| (name.as_str(), saved))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_replaces_and_rejects_bad_patterns() {
+        let mut registry = PatternRegistry::default();
+        assert!(!registry
+            .register("todo", "TODO", RegexEngineOpts::default())
+            .unwrap());
+        let first = registry.matcher("todo").unwrap();
+        assert!(Arc::ptr_eq(&first, &registry.matcher("todo").unwrap()));
+
+        assert!(registry
+            .register("todo", "(", RegexEngineOpts::default())
+            .is_err());
+        assert_eq!(registry.get("todo").unwrap().pattern, "TODO");
+
+        assert!(registry
+            .register("todo", "FIXME", RegexEngineOpts::default())
+            .unwrap());
+        assert!(!Arc::ptr_eq(&first, &registry.matcher("todo").unwrap()));
+
+        assert!(registry.remove("todo"));
+        assert_eq!(
+            registry.matcher("todo").err().map(|e| e.code()),
+            Some("unknown_pattern")
+        );
+    }
+}
@@ -3,6 +3,11 @@
 use crate::error::{Error, Result};
 use crate::fs::PathKey;
 use crate::tools::line_index::LineIndex;
+use crate::tools::model::ByteSpan;
+use crate::tools::utf8::decode_lossy;
+
+/// Marker placed where an excerpt cuts into a line.
+pub const ELLIPSIS: &str = "…";
 
 /// A preview excerpt showing a match with surrounding context lines.
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
@@ -11,11 +16,43 @@ pub struct PreviewHunk {
     /// Inclusive 1-based line range for the preview.
     pub preview_start_line: usize,
     pub preview_end_line: usize,
-    /// Line ranges of actual matches within the preview (for highlighting).
-    /// Each tuple is (start_line, end_line) inclusive, 1-based.
+    /// Line ranges of actual matches within the preview (for highlighting),
+    /// one per match. Each tuple is (start_line, end_line) inclusive, 1-based.
     pub matched_line_ranges: Vec<(usize, usize)>,
     /// UTF-8 text excerpt, with invalid sequences replaced by �.
     pub excerpt: String,
+    /// Whether `excerpt` contains replacement characters from invalid UTF-8.
+    #[serde(default)]
+    pub lossy: bool,
+    /// Characters cut from the start of the first line, which then begins
+    /// with [`ELLIPSIS`].
+    #[serde(default)]
+    pub omitted_prefix: usize,
+    /// Characters cut from the end of the last line, which then ends with
+    /// [`ELLIPSIS`].
+    #[serde(default)]
+    pub omitted_suffix: usize,
+    /// Index of the pattern that matched, for multi-pattern searches.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern_index: Option<usize>,
+    /// Where the match falls in the excerpt, one entry per line it covers.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub match_columns: Vec<MatchColumns>,
+}
+
+/// Part of a match on one line of a [`PreviewHunk`] excerpt.
+///
+/// Columns are 0-based and half-open, counted in the excerpt's line as
+/// shown: after a leading [`ELLIPSIS`] and with invalid UTF-8 replaced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct MatchColumns {
+    /// 1-based line in the file.
+    pub line: usize,
+    pub start_byte: usize,
+    pub end_byte: usize,
+    /// Columns in Unicode scalar values.
+    pub start_char: usize,
+    pub end_char: usize,
 }
 
 /// Builds preview windows around matches with configurable context.
@@ -23,7 +60,8 @@ pub struct PreviewHunk {
 pub struct PreviewBuilder {
     /// Number of context lines before/after the match.
     pub delta: usize,
-    /// Maximum characters to show before/after match in same line (None = unlimited)
+    /// Maximum characters to show before/after the match (None = unlimited).
+    /// Budget one side cannot use goes to the other.
     pub char_limit: Option<usize>,
 }
 
@@ -47,13 +85,15 @@ impl PreviewBuilder {
     /// Build a preview excerpt for a match.
     ///
     /// Creates a window of `match ± delta` lines, clamped to valid line bounds.
+    /// With a `char_limit`, long lines are cut on character boundaries
+    /// around the match and the cuts are marked with [`ELLIPSIS`].
     /// Non-UTF-8 bytes are replaced with � rather than failing.
     pub fn build_hunk(
         &self,
         path: PathKey,
         line_index: &LineIndex,
         bytes: &[u8],
-        match_span: &crate::tools::model::ByteSpan,
+        match_span: &ByteSpan,
         match_start_line: usize,
         match_end_line: usize,
     ) -> Result<PreviewHunk> {
@@ -64,13 +104,9 @@ impl PreviewBuilder {
             .span_of_lines(p_start, p_end)
             .ok_or(Error::InvalidRange(p_start, p_end))?;
 
-        let final_range = if let Some(limit) = self.char_limit {
-            let start = match_span.start.saturating_sub(limit).max(byte_range.start);
-            let end = (match_span.end + limit).min(byte_range.end);
-
-            crate::tools::model::ByteSpan { start, end }
-        } else {
-            byte_range
+        let final_range = match self.char_limit {
+            Some(limit) => window_around(bytes, byte_range, match_span, limit),
+            None => byte_range,
         };
 
         // Adjust line numbers to match the truncated byte range
@@ -81,9 +117,46 @@ impl PreviewBuilder {
             .line_of_byte(final_range.end.saturating_sub(1))
             .unwrap_or(p_end);
 
+        let omitted_prefix = line_index
+            .byte_of_line_start(actual_start_line)
+            .map_or(0, |line_start| {
+                count_chars(&bytes[line_start..final_range.start])
+            });
+        let omitted_suffix = match line_index.line_of_byte(final_range.end) {
+            Some(line) if line == actual_end_line => line_index
+                .content_range_of_line(bytes, line)
+                .filter(|&(_, content_end)| content_end > final_range.end)
+                .map_or(0, |(_, content_end)| {
+                    count_chars(&bytes[final_range.end..content_end])
+                }),
+            _ => 0,
+        };
+
         // Extract and convert to UTF-8 (lossy for non-UTF-8 files)
         let excerpt_bytes = &bytes[final_range.to_range()];
-        let excerpt = String::from_utf8_lossy(excerpt_bytes).into_owned();
+        let (decoded, lossy) = decode_lossy(excerpt_bytes);
+        let excerpt = if omitted_prefix == 0 && omitted_suffix == 0 {
+            decoded.into_owned()
+        } else {
+            let mut excerpt = String::with_capacity(decoded.len() + 2 * ELLIPSIS.len());
+            if omitted_prefix > 0 {
+                excerpt.push_str(ELLIPSIS);
+            }
+            excerpt.push_str(&decoded);
+            if omitted_suffix > 0 {
+                excerpt.push_str(ELLIPSIS);
+            }
+            excerpt
+        };
+
+        let match_columns = match_columns(
+            bytes,
+            line_index,
+            final_range,
+            match_span,
+            match_start_line,
+            (omitted_prefix > 0).then_some(actual_start_line),
+        );
 
         Ok(PreviewHunk {
             path,
@@ -91,6 +164,278 @@ impl PreviewBuilder {
             preview_end_line: actual_end_line,
             matched_line_ranges: vec![(match_start_line, match_end_line)],
             excerpt,
+            lossy,
+            omitted_prefix,
+            omitted_suffix,
+            pattern_index: None,
+            match_columns,
         })
     }
+
+    /// Fold `next` into `hunk` when their windows overlap or touch, so
+    /// close matches share one excerpt instead of repeating lines. Returns
+    /// `next` back when it cannot be merged.
+    ///
+    /// Only hunks of the same file and pattern merge, and only where the
+    /// lines they share are shown whole: an excerpt cut mid-line by the
+    /// character limit no longer lines up with its neighbour's.
+    pub fn merge(hunk: &mut PreviewHunk, next: PreviewHunk) -> Option<PreviewHunk> {
+        let mergeable = hunk.path == next.path
+            && hunk.pattern_index == next.pattern_index
+            && next.preview_start_line >= hunk.preview_start_line
+            && next.preview_start_line <= hunk.preview_end_line + 1
+            && hunk.omitted_suffix == 0
+            && next.omitted_prefix == 0
+            && (hunk.omitted_prefix == 0 || next.preview_start_line > hunk.preview_start_line);
+        if !mergeable {
+            return Some(next);
+        }
+
+        if next.preview_end_line > hunk.preview_end_line {
+            let shared = hunk.preview_end_line + 1 - next.preview_start_line;
+            let tail: String = next.excerpt.split_inclusive('\n').skip(shared).collect();
+            if !hunk.excerpt.is_empty() && !hunk.excerpt.ends_with('\n') {
+                hunk.excerpt.push('\n');
+            }
+            hunk.excerpt.push_str(&tail);
+            hunk.preview_end_line = next.preview_end_line;
+            hunk.omitted_suffix = next.omitted_suffix;
+        }
+        hunk.lossy |= next.lossy;
+        hunk.matched_line_ranges.extend(next.matched_line_ranges);
+        hunk.match_columns.extend(next.match_columns);
+        None
+    }
+}
+
+/// Columns of `span` on each excerpt line it covers, where the excerpt is
+/// `range` of `bytes` and `marked_line`, if any, begins with [`ELLIPSIS`].
+/// Lines the match only ends on, such as a trailing newline, are left out.
+fn match_columns(
+    bytes: &[u8],
+    line_index: &LineIndex,
+    range: ByteSpan,
+    span: &ByteSpan,
+    first_line: usize,
+    marked_line: Option<usize>,
+) -> Vec<MatchColumns> {
+    let start = span.start.clamp(range.start, range.end);
+    let end = span.end.clamp(start, range.end);
+    let last_line = if end > start {
+        line_index.line_of_byte(end - 1).unwrap_or(first_line)
+    } else {
+        first_line
+    };
+
+    let mut columns = Vec::new();
+    for line in first_line..=last_line {
+        let Some((content_start, content_end)) = line_index.content_range_of_line(bytes, line)
+        else {
+            continue;
+        };
+        let line_start = content_start.max(range.start);
+        let seg_start = start.clamp(line_start, content_end.max(line_start));
+        let seg_end = end.min(content_end).max(seg_start);
+        if seg_start == seg_end && line != first_line {
+            continue;
+        }
+        let marker = if marked_line == Some(line) {
+            ELLIPSIS
+        } else {
+            ""
+        };
+        let (before, _) = decode_lossy(&bytes[line_start..seg_start]);
+        let (matched, _) = decode_lossy(&bytes[seg_start..seg_end]);
+        let start_byte = marker.len() + before.len();
+        let start_char = marker.chars().count() + before.chars().count();
+        columns.push(MatchColumns {
+            line,
+            start_byte,
+            end_byte: start_byte + matched.len(),
+            start_char,
+            end_char: start_char + matched.chars().count(),
+        });
+    }
+    columns
+}
+
+fn is_continuation(byte: u8) -> bool {
+    byte & 0xC0 == 0x80
+}
+
+/// Characters in `bytes`, counting each invalid byte as one.
+fn count_chars(bytes: &[u8]) -> usize {
+    bytes.iter().filter(|&&b| !is_continuation(b)).count()
+}
+
+/// Move back from `pos` by up to `n` characters, not past `floor`.
+/// Returns the new position and the characters actually moved.
+fn back_chars(bytes: &[u8], mut pos: usize, floor: usize, n: usize) -> (usize, usize) {
+    let mut moved = 0;
+    while moved < n && pos > floor {
+        pos -= 1;
+        while pos > floor && is_continuation(bytes[pos]) {
+            pos -= 1;
+        }
+        moved += 1;
+    }
+    (pos, moved)
+}
+
+/// Move forward from `pos` by up to `n` characters, not past `ceil`.
+fn forward_chars(bytes: &[u8], mut pos: usize, ceil: usize, n: usize) -> (usize, usize) {
+    let mut moved = 0;
+    while moved < n && pos < ceil {
+        pos += 1;
+        while pos < ceil && is_continuation(bytes[pos]) {
+            pos += 1;
+        }
+        moved += 1;
+    }
+    (pos, moved)
+}
+
+/// Up to `limit` characters either side of `span` within `bounds`, with
+/// budget one side cannot use given to the other so the excerpt stays as
+/// long as the content allows.
+fn window_around(bytes: &[u8], bounds: ByteSpan, span: &ByteSpan, limit: usize) -> ByteSpan {
+    let span_start = span.start.clamp(bounds.start, bounds.end);
+    let span_end = span.end.clamp(span_start, bounds.end);
+
+    let (start, before) = back_chars(bytes, span_start, bounds.start, limit);
+    let (end, after) = forward_chars(bytes, span_end, bounds.end, 2 * limit - before);
+    let (start, _) = back_chars(bytes, start, bounds.start, limit.saturating_sub(after));
+    ByteSpan { start, end }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn hunk(content: &str, needle: &str, limit: usize) -> PreviewHunk {
+        let bytes = content.as_bytes();
+        let start = content.find(needle).unwrap();
+        let span = ByteSpan {
+            start,
+            end: start + needle.len(),
+        };
+        let line_index = LineIndex::build(bytes);
+        let line = line_index.line_of_byte(start).unwrap();
+        let builder = PreviewBuilder {
+            delta: 1,
+            char_limit: Some(limit),
+        };
+        builder
+            .build_hunk(
+                PathKey::from_arc(Arc::from("a.min.js")),
+                &line_index,
+                bytes,
+                &span,
+                line,
+                line,
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn test_long_line_is_centered_with_omitted_counts() {
+        let line = format!("{}MATCH{}", "a".repeat(100), "b".repeat(200));
+        let h = hunk(
+            &format!(
+                "first
+{line}
+last
+"
+            ),
+            "MATCH",
+            10,
+        );
+
+        assert_eq!(
+            h.excerpt,
+            format!("…{}MATCH{}…", "a".repeat(10), "b".repeat(10))
+        );
+        assert_eq!((h.omitted_prefix, h.omitted_suffix), (90, 190));
+        assert_eq!((h.preview_start_line, h.preview_end_line), (2, 2));
+        // Columns count the ellipsis that starts the line.
+        let col = h.match_columns[0];
+        assert_eq!((col.start_char, col.end_char), (11, 16));
+        assert_eq!((col.start_byte, col.end_byte), (13, 18));
+        assert_eq!(&h.excerpt[col.start_byte..col.end_byte], "MATCH");
+    }
+
+    #[test]
+    fn test_match_columns_per_line() {
+        let content = "héllo wörld\nnext line\n";
+        let h = hunk(content, "wörld\nnext", 100);
+        assert_eq!(
+            h.match_columns,
+            vec![
+                MatchColumns {
+                    line: 1,
+                    start_byte: 7,
+                    end_byte: 13,
+                    start_char: 6,
+                    end_char: 11,
+                },
+                MatchColumns {
+                    line: 2,
+                    start_byte: 0,
+                    end_byte: 4,
+                    start_char: 0,
+                    end_char: 4,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unused_budget_moves_to_other_side_on_char_boundaries() {
+        let line = format!("MATCH{}", "é".repeat(50));
+        let h = hunk(&line, "MATCH", 10);
+
+        assert_eq!(h.excerpt, format!("MATCH{}…", "é".repeat(20)));
+        assert_eq!((h.omitted_prefix, h.omitted_suffix), (0, 30));
+        assert!(!h.lossy);
+    }
+
+    #[test]
+    fn test_short_lines_are_not_marked() {
+        let h = hunk(
+            "one
+two MATCH
+three
+",
+            "MATCH",
+            100,
+        );
+        assert_eq!(h.excerpt, "one\ntwo MATCH\nthree\n");
+        assert_eq!((h.omitted_prefix, h.omitted_suffix), (0, 0));
+    }
+
+    #[test]
+    fn test_merge_overlapping_and_touching_windows() {
+        let content = "1\n2 a\n3\n4 b\n5\n6\n7 c\n8\n";
+        let mut merged = hunk(content, "a", 100);
+        assert!(PreviewBuilder::merge(&mut merged, hunk(content, "b", 100)).is_none());
+        assert_eq!(merged.excerpt, "1\n2 a\n3\n4 b\n5\n");
+        assert_eq!(merged.matched_line_ranges, [(2, 2), (4, 4)]);
+        assert_eq!(merged.match_columns.len(), 2);
+
+        // Lines 3-5 and 6-8 touch, so they merge too.
+        assert!(PreviewBuilder::merge(&mut merged, hunk(content, "c", 100)).is_none());
+        assert_eq!(merged.excerpt, content);
+        assert_eq!((merged.preview_start_line, merged.preview_end_line), (1, 8));
+
+        // A gap, or another file, keeps hunks apart.
+        let mut first = hunk(content, "a", 100);
+        assert!(PreviewBuilder::merge(&mut first, hunk(content, "c", 100)).is_some());
+        let other = PreviewHunk {
+            path: PathKey::from_arc(Arc::from("b.js")),
+            ..hunk(content, "b", 100)
+        };
+        assert!(PreviewBuilder::merge(&mut first, other).is_some());
+        assert_eq!(first.excerpt, "1\n2 a\n3\n");
+    }
 }
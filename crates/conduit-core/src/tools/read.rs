@@ -3,6 +3,7 @@
 use crate::error::{Error, Result};
 use crate::fs::PathKey;
 use crate::tools::line_index::LineIndex;
+use crate::tools::utf8::decode_checked;
 use serde::{Deserialize, Serialize};
 
 /// Request to read specific lines from a file.
@@ -29,6 +30,9 @@ pub struct ReadResponse {
     pub content: String,
     /// Total number of lines in the file
     pub total_lines: usize,
+    /// Whether invalid UTF-8 was replaced with U+FFFD in `content`
+    #[serde(default)]
+    pub lossy: bool,
 }
 
 impl ReadRequest {
@@ -55,6 +59,8 @@ impl ReadRequest {
 }
 
 /// Extract exact line range from file content.
+///
+/// Invalid UTF-8 is replaced with U+FFFD and reported via `lossy`.
 pub fn extract_lines(
     path: PathKey,
     content: &[u8],
@@ -71,6 +77,19 @@ pub fn extract_lines_with_index(
     start_line: usize,
     end_line: usize,
     line_index: &LineIndex,
+) -> Result<ReadResponse> {
+    extract_lines_checked(path, content, start_line, end_line, line_index, false)
+}
+
+/// Like [`extract_lines_with_index`], but fails with `InvalidUtf8` when
+/// `strict` is set and the extracted range is not valid UTF-8.
+pub fn extract_lines_checked(
+    path: PathKey,
+    content: &[u8],
+    start_line: usize,
+    end_line: usize,
+    line_index: &LineIndex,
+    strict: bool,
 ) -> Result<ReadResponse> {
     let total_lines = line_index.line_count();
 
@@ -85,13 +104,14 @@ pub fn extract_lines_with_index(
         .ok_or(Error::InvalidRange(start_line, actual_end))?;
 
     let content_bytes: &[u8] = &content[byte_range.to_range()];
-    let content = String::from_utf8_lossy(content_bytes).into_owned();
+    let (content, lossy) = decode_checked(path.as_str(), content_bytes, strict)?;
 
     Ok(ReadResponse {
         path,
         start_line,
         end_line: actual_end,
-        content,
+        content: content.into_owned(),
         total_lines,
+        lossy,
     })
 }
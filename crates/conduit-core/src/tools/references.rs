@@ -0,0 +1,259 @@
+//! Import path rewriting after a directory moves.
+//!
+//! Only import-like lines are touched (`import`, `export ... from`,
+//! `require(...)`, `import(...)`, `#include`, Python `from`/`import`).
+//! Relative specifiers are resolved against the importing file and
+//! recomputed from its new location, so files inside the moved tree that
+//! import files outside it are fixed too. Other quoted specifiers are
+//! rewritten textually when they start with the moved path. Nothing is
+//! parsed, so aliases and computed paths are left alone.
+
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// One rewritten line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ReferenceEdit {
+    /// 1-based line number.
+    pub line: usize,
+    pub before: String,
+    pub after: String,
+}
+
+/// Rewrites references for one move of `from` to `to`.
+pub struct ReferenceRewriter {
+    from: String,
+    to: String,
+    import_line: Regex,
+    quoted: Regex,
+    python_module: Regex,
+}
+
+impl ReferenceRewriter {
+    /// `from` and `to` are directory paths; trailing slashes are ignored.
+    pub fn new(from: &str, to: &str) -> Result<Self> {
+        let from = from.trim_end_matches('/');
+        let to = to.trim_end_matches('/');
+        if from.is_empty() || to.is_empty() {
+            return Err(Error::InvalidPath(format!("{from} -> {to}")));
+        }
+        Ok(Self {
+            from: from.to_string(),
+            to: to.to_string(),
+            import_line: Regex::new(
+                r"^\s*(?:import|export|from|#\s*include)\b|\brequire\s*\(|\bimport\s*\(",
+            )?,
+            quoted: Regex::new(r#"'([^'\n]*)'|"([^"\n]*)""#)?,
+            python_module: Regex::new(r"^(\s*(?:from|import)\s+)([\w.]+)")?,
+        })
+    }
+
+    /// Where `path` lives after the move, or `None` if it is not under `from`.
+    pub fn moved_path(&self, path: &str) -> Option<String> {
+        let rest = path.strip_prefix(&self.from)?;
+        (rest.is_empty() || rest.starts_with('/')).then(|| format!("{}{}", self.to, rest))
+    }
+
+    /// Rewrite the references in `content`, a file that was at `old_path`
+    /// and is now at `new_path`. Returns `None` if nothing changed.
+    pub fn rewrite(
+        &self,
+        content: &str,
+        old_path: &str,
+        new_path: &str,
+    ) -> Option<(String, Vec<ReferenceEdit>)> {
+        let python = old_path.ends_with(".py");
+        let mut out = String::with_capacity(content.len());
+        let mut edits = Vec::new();
+
+        for (i, line) in content.split_inclusive('\n').enumerate() {
+            let rewritten = if python {
+                self.rewrite_python_line(line)
+            } else if self.import_line.is_match(line) {
+                self.rewrite_specifiers(line, old_path, new_path)
+            } else {
+                None
+            };
+
+            match rewritten {
+                Some(after) => {
+                    edits.push(ReferenceEdit {
+                        line: i + 1,
+                        before: line.trim_end_matches(['\n', '\r']).to_string(),
+                        after: after.trim_end_matches(['\n', '\r']).to_string(),
+                    });
+                    out.push_str(&after);
+                }
+                None => out.push_str(line),
+            }
+        }
+
+        (!edits.is_empty()).then_some((out, edits))
+    }
+
+    fn rewrite_specifiers(&self, line: &str, old_path: &str, new_path: &str) -> Option<String> {
+        let mut out = String::with_capacity(line.len());
+        let mut last = 0;
+        let mut changed = false;
+
+        for caps in self.quoted.captures_iter(line) {
+            let Some(spec) = caps.get(1).or_else(|| caps.get(2)) else {
+                continue;
+            };
+            if let Some(new_spec) = self.rewrite_specifier(spec.as_str(), old_path, new_path) {
+                out.push_str(&line[last..spec.start()]);
+                out.push_str(&new_spec);
+                last = spec.end();
+                changed = true;
+            }
+        }
+
+        changed.then(|| {
+            out.push_str(&line[last..]);
+            out
+        })
+    }
+
+    fn rewrite_specifier(&self, spec: &str, old_path: &str, new_path: &str) -> Option<String> {
+        if spec.starts_with("./") || spec.starts_with("../") {
+            let target = resolve(parent(old_path), spec)?;
+            let moved_target = self.moved_path(&target);
+            if moved_target.is_none() && old_path == new_path {
+                return None;
+            }
+            let new_spec = relative(parent(new_path), moved_target.as_deref().unwrap_or(&target));
+            return (new_spec != spec).then_some(new_spec);
+        }
+
+        // Root-anchored specifiers that name the moved path directly.
+        let (lead, rest) = match spec.strip_prefix('/') {
+            Some(rest) => ("/", rest),
+            None => ("", spec),
+        };
+        self.moved_path(rest).map(|moved| format!("{lead}{moved}"))
+    }
+
+    fn rewrite_python_line(&self, line: &str) -> Option<String> {
+        let caps = self.python_module.captures(line)?;
+        let module = caps.get(2)?;
+        let from = self.from.replace('/', ".");
+        let rest = module.as_str().strip_prefix(&from)?;
+        if !(rest.is_empty() || rest.starts_with('.')) {
+            return None;
+        }
+        Some(format!(
+            "{}{}{}{}",
+            &line[..module.start()],
+            self.to.replace('/', "."),
+            rest,
+            &line[module.end()..]
+        ))
+    }
+}
+
+fn parent(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(dir, _)| dir)
+}
+
+/// Resolve a `./` or `../` specifier against `dir`; `None` if it climbs
+/// above the root.
+fn resolve(dir: &str, spec: &str) -> Option<String> {
+    let mut parts: Vec<&str> = dir.split('/').filter(|p| !p.is_empty()).collect();
+    for part in spec.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop()?;
+            }
+            _ => parts.push(part),
+        }
+    }
+    Some(parts.join("/"))
+}
+
+/// Relative specifier from `dir` to `target`: `./x`, `../x`, or just `.`
+/// or `..` when the target is a directory on the way.
+fn relative(dir: &str, target: &str) -> String {
+    let from: Vec<&str> = dir.split('/').filter(|p| !p.is_empty()).collect();
+    let to: Vec<&str> = target.split('/').filter(|p| !p.is_empty()).collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let ups = from.len() - common;
+    let mut parts: Vec<&str> = if ups == 0 { vec!["."] } else { vec![".."; ups] };
+    parts.extend(&to[common..]);
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewriter() -> ReferenceRewriter {
+        ReferenceRewriter::new("src/utils/", "src/lib/utils").unwrap()
+    }
+
+    #[test]
+    fn test_relative_imports_follow_moved_targets() {
+        let content = "import { a } from './utils/a';\nconst b = require(\"./utils\");\nimport x from './other';\n";
+        let (out, edits) = rewriter()
+            .rewrite(content, "src/app.ts", "src/app.ts")
+            .unwrap();
+
+        assert_eq!(
+            out,
+            "import { a } from './lib/utils/a';\nconst b = require(\"./lib/utils\");\nimport x from './other';\n"
+        );
+        assert_eq!(edits.len(), 2);
+        assert_eq!(edits[1].line, 2);
+    }
+
+    #[test]
+    fn test_moved_files_fix_imports_leaving_the_tree() {
+        let content = "import cfg from '../config';\nimport b from './b';\n";
+        let (out, edits) = rewriter()
+            .rewrite(content, "src/utils/a.ts", "src/lib/utils/a.ts")
+            .unwrap();
+
+        assert_eq!(
+            out,
+            "import cfg from '../../config';\nimport b from './b';\n"
+        );
+        assert_eq!(edits.len(), 1);
+    }
+
+    #[test]
+    fn test_root_anchored_and_python_modules() {
+        let r = rewriter();
+        let (out, _) = r
+            .rewrite("#include \"src/utils/str.h\"\n", "main.c", "main.c")
+            .unwrap();
+        assert_eq!(out, "#include \"src/lib/utils/str.h\"\n");
+
+        let (out, _) = r
+            .rewrite(
+                "from src.utils.io import read\nimport src.utilsx\n",
+                "app.py",
+                "app.py",
+            )
+            .unwrap();
+        assert_eq!(
+            out,
+            "from src.lib.utils.io import read\nimport src.utilsx\n"
+        );
+
+        assert!(r
+            .rewrite("let s = \"src/utils/a\";\n", "x.ts", "x.ts")
+            .is_none());
+    }
+
+    #[test]
+    fn test_relative_paths() {
+        assert_eq!(relative("src", "src/lib/a"), "./lib/a");
+        assert_eq!(relative("src/lib/utils", "src/config"), "../../config");
+        assert_eq!(relative("src/a", "src"), "..");
+        assert_eq!(resolve("src", "../../x"), None);
+    }
+}
@@ -0,0 +1,270 @@
+//! Heuristic discovery of files related to a change set.
+//!
+//! Three signals: test/source naming conventions, files whose import-like
+//! lines mention the changed module, and files in the same directory. None
+//! of these parse the language; they suggest what to read or run next and
+//! are not exhaustive.
+
+use std::collections::HashMap;
+
+use regex::bytes::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::fs::{Index, PathKey};
+
+/// Why a file was suggested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub enum RelatedKind {
+    /// A test for the changed file, by naming convention.
+    Test,
+    /// The file under test, when the changed file is a test.
+    Source,
+    /// A file with an import-like line naming the changed module.
+    Importer,
+    /// Another file in the same directory.
+    Sibling,
+}
+
+/// A suggested file and why it was suggested.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct RelatedFile {
+    pub path: PathKey,
+    pub kind: RelatedKind,
+}
+
+/// Suggestions for one changed path, ordered test, source, importer, sibling.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct RelatedFiles {
+    pub path: PathKey,
+    pub related: Vec<RelatedFile>,
+}
+
+/// Suggest related files for each of `changed`, at most `per_kind` of each kind.
+pub fn suggest_related(
+    index: &Index,
+    changed: &[PathKey],
+    per_kind: usize,
+) -> Result<Vec<RelatedFiles>> {
+    // subject stem -> (tests, non-tests), built once for all changed paths.
+    let mut by_stem: HashMap<&str, (Vec<&PathKey>, Vec<&PathKey>)> = HashMap::new();
+    for (path, _) in index.iter_sorted() {
+        match test_subject(path.as_str()) {
+            Some(subject) => by_stem.entry(subject).or_default().0.push(path),
+            None => by_stem.entry(stem(path.as_str())).or_default().1.push(path),
+        }
+    }
+
+    let mut out = Vec::with_capacity(changed.len());
+    for path in changed {
+        let mut related = Vec::new();
+        let push = |p: &PathKey, kind: RelatedKind, related: &mut Vec<RelatedFile>| {
+            if p != path && !related.iter().any(|r: &RelatedFile| &r.path == p) {
+                related.push(RelatedFile {
+                    path: p.clone(),
+                    kind,
+                });
+            }
+        };
+
+        match test_subject(path.as_str()) {
+            Some(subject) => {
+                if let Some((_, sources)) = by_stem.get(subject) {
+                    for source in sources.iter().take(per_kind) {
+                        push(source, RelatedKind::Source, &mut related);
+                    }
+                }
+            }
+            None => {
+                if let Some((tests, _)) = by_stem.get(stem(path.as_str())) {
+                    for test in tests.iter().take(per_kind) {
+                        push(test, RelatedKind::Test, &mut related);
+                    }
+                }
+                for importer in importers(index, path, per_kind)? {
+                    push(importer, RelatedKind::Importer, &mut related);
+                }
+            }
+        }
+
+        let dir = parent(path.as_str());
+        let siblings = index
+            .iter_sorted()
+            .map(|(p, _)| p)
+            .filter(|p| *p != path && parent(p.as_str()) == dir)
+            .take(per_kind);
+        for sibling in siblings {
+            push(sibling, RelatedKind::Sibling, &mut related);
+        }
+
+        out.push(RelatedFiles {
+            path: path.clone(),
+            related,
+        });
+    }
+
+    Ok(out)
+}
+
+/// Files (other than `path`) whose import-like lines mention its module name.
+fn importers<'a>(index: &'a Index, path: &PathKey, limit: usize) -> Result<Vec<&'a PathKey>> {
+    let module = module_name(path.as_str());
+    if module.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let pattern = format!(
+        r#"(?m)^\s*(?:import|from|export|use|mod|require|#\s*include)\b.*\b{}\b|require\(\s*['"][^'"]*\b{}\b"#,
+        regex::escape(module),
+        regex::escape(module)
+    );
+    let re = Regex::new(&pattern)?;
+
+    Ok(index
+        .iter_sorted()
+        .filter(|(p, _)| *p != path)
+        .filter(|(_, entry)| entry.search_content().is_some_and(|c| re.is_match(c)))
+        .map(|(p, _)| p)
+        .take(limit)
+        .collect())
+}
+
+fn file_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+fn parent(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(dir, _)| dir)
+}
+
+/// File name up to the first dot (`button.test.tsx` -> `button`).
+fn stem(path: &str) -> &str {
+    let name = file_name(path);
+    match name.find('.') {
+        Some(0) | None => name,
+        Some(i) => &name[..i],
+    }
+}
+
+/// Name other files use to import `path`; index-like files use their directory.
+fn module_name(path: &str) -> &str {
+    match stem(path) {
+        "index" | "mod" | "lib" | "main" | "__init__" => file_name(parent(path)),
+        s => s,
+    }
+}
+
+/// The stem a test file is testing, or `None` if `path` is not a test.
+fn test_subject(path: &str) -> Option<&str> {
+    let name = file_name(path);
+    for marker in [".test.", ".spec.", "_test.", "_spec."] {
+        if let Some(i) = name.find(marker) {
+            return Some(&name[..i]).filter(|s| !s.is_empty());
+        }
+    }
+
+    let s = stem(path);
+    if let Some(subject) = s.strip_prefix("test_") {
+        return Some(subject).filter(|s| !s.is_empty());
+    }
+    for suffix in ["Tests", "Test"] {
+        if let Some(subject) = s.strip_suffix(suffix) {
+            if !subject.is_empty() {
+                return Some(subject);
+            }
+        }
+    }
+
+    let in_test_dir = path
+        .split('/')
+        .rev()
+        .skip(1)
+        .any(|dir| matches!(dir, "tests" | "test" | "__tests__" | "spec"));
+    in_test_dir.then_some(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::FileEntry;
+    use std::sync::Arc;
+
+    fn key(p: &str) -> PathKey {
+        PathKey::from_arc(Arc::from(p))
+    }
+
+    fn index(files: &[(&str, &str)]) -> Index {
+        let mut index = Index::default();
+        for (path, content) in files {
+            let entry = FileEntry::from_bytes("", 0, Arc::from(content.as_bytes()), true);
+            index.upsert_file(key(path), entry).unwrap();
+        }
+        index
+    }
+
+    #[test]
+    fn test_subject_conventions() {
+        assert_eq!(test_subject("src/button.test.tsx"), Some("button"));
+        assert_eq!(test_subject("src/button.spec.ts"), Some("button"));
+        assert_eq!(test_subject("pkg/server_test.go"), Some("server"));
+        assert_eq!(test_subject("tests/test_parser.py"), Some("parser"));
+        assert_eq!(test_subject("src/ParserTest.java"), Some("Parser"));
+        assert_eq!(test_subject("src/__tests__/button.tsx"), Some("button"));
+        assert_eq!(test_subject("src/button.tsx"), None);
+        assert_eq!(test_subject("src/Test.java"), None);
+    }
+
+    #[test]
+    fn test_suggests_tests_importers_and_siblings() {
+        let index = index(&[
+            ("src/button.tsx", "export const Button = 1;"),
+            ("src/button.test.tsx", "import { Button } from './button';"),
+            ("src/card.tsx", "import { Button } from './button';\n"),
+            ("src/theme.ts", "const buttonColor = 'red';"),
+            ("app/page.tsx", "import { Card } from '../src/card';"),
+        ]);
+
+        let result = suggest_related(&index, &[key("src/button.tsx")], 10).unwrap();
+        let related: Vec<_> = result[0]
+            .related
+            .iter()
+            .map(|r| (r.path.as_str(), r.kind))
+            .collect();
+
+        assert_eq!(
+            related,
+            vec![
+                ("src/button.test.tsx", RelatedKind::Test),
+                ("src/card.tsx", RelatedKind::Importer),
+                ("src/theme.ts", RelatedKind::Sibling),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_test_file_maps_back_to_source() {
+        let index = index(&[
+            ("pkg/server.go", "package pkg"),
+            ("pkg/server_test.go", "package pkg"),
+        ]);
+
+        let result = suggest_related(&index, &[key("pkg/server_test.go")], 10).unwrap();
+
+        assert_eq!(
+            result[0].related,
+            vec![RelatedFile {
+                path: key("pkg/server.go"),
+                kind: RelatedKind::Source,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_index_files_are_imported_by_directory_name() {
+        assert_eq!(module_name("src/widgets/index.ts"), "widgets");
+        assert_eq!(module_name("crate/src/lib.rs"), "src");
+        assert_eq!(module_name("src/util.rs"), "util");
+    }
+}
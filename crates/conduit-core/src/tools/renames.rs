@@ -0,0 +1,151 @@
+//! Pairing deleted and created files by content, like `git diff -M`.
+//!
+//! A file deleted in staging and another created with mostly the same
+//! content read better as one rename than as an unrelated delete and
+//! create. Moves made through the tools are recorded as moves already;
+//! this finds the ones made by deleting and writing.
+
+use similar::TextDiff;
+
+use crate::fs::PathKey;
+
+/// Similarity percentage `git diff -M` uses by default.
+pub const DEFAULT_RENAME_THRESHOLD: u8 = 50;
+
+/// Most deleted/created pairs compared by content. Beyond it only
+/// identical files are paired, so a mass delete and create stays fast.
+pub const RENAME_PAIR_LIMIT: usize = 10_000;
+
+/// A deleted file paired with a created one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedRename {
+    pub src: PathKey,
+    pub dst: PathKey,
+    /// Percentage of lines the two share, 0 to 100.
+    pub similarity: u8,
+}
+
+/// Pair each of `deleted` with at most one of `created` whose content is at
+/// least `threshold` percent similar, most similar pairs first.
+///
+/// Ties are broken by source then destination path, so the result does
+/// not depend on input order.
+pub fn detect_renames(
+    deleted: &[(PathKey, &[u8])],
+    created: &[(PathKey, &[u8])],
+    threshold: u8,
+) -> Vec<DetectedRename> {
+    let compare_content = deleted.len().saturating_mul(created.len()) <= RENAME_PAIR_LIMIT;
+    let mut candidates = Vec::new();
+    for (i, (_, old)) in deleted.iter().enumerate() {
+        for (j, (_, new)) in created.iter().enumerate() {
+            let score = if old == new {
+                100
+            } else if compare_content {
+                similarity(old, new, threshold)
+            } else {
+                0
+            };
+            if score >= threshold && score > 0 {
+                candidates.push((score, i, j));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| {
+        b.0.cmp(&a.0)
+            .then_with(|| deleted[a.1].0.cmp(&deleted[b.1].0))
+            .then_with(|| created[a.2].0.cmp(&created[b.2].0))
+    });
+
+    let mut src_used = vec![false; deleted.len()];
+    let mut dst_used = vec![false; created.len()];
+    let mut renames = Vec::new();
+    for (score, i, j) in candidates {
+        if src_used[i] || dst_used[j] {
+            continue;
+        }
+        src_used[i] = true;
+        dst_used[j] = true;
+        renames.push(DetectedRename {
+            src: deleted[i].0.clone(),
+            dst: created[j].0.clone(),
+            similarity: score,
+        });
+    }
+    renames.sort_by(|a, b| a.src.cmp(&b.src));
+    renames
+}
+
+/// Line similarity of `old` and `new` as a percentage, or 0 when their
+/// sizes alone rule out reaching `threshold`.
+fn similarity(old: &[u8], new: &[u8], threshold: u8) -> u8 {
+    let (small, large) = if old.len() < new.len() {
+        (old.len(), new.len())
+    } else {
+        (new.len(), old.len())
+    };
+    if large == 0 || small * 100 < large * threshold as usize {
+        return 0;
+    }
+    let (old, new) = (String::from_utf8_lossy(old), String::from_utf8_lossy(new));
+    let ratio = TextDiff::from_lines(old.as_ref(), new.as_ref()).ratio();
+    // Identical files scored 100 already; never round a changed one up to it.
+    ((ratio * 100.0).floor() as u8).min(99)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn key(p: &str) -> PathKey {
+        PathKey::from_arc(Arc::from(p))
+    }
+
+    #[test]
+    fn test_pairs_most_similar_files() {
+        let body = "fn a() {}\nfn b() {}\nfn c() {}\nfn d() {}\n";
+        let edited = "fn a() {}\nfn b() {}\nfn c() {}\nfn e() {}\n";
+        let deleted = [
+            (key("src/old.rs"), body.as_bytes()),
+            (key("src/copy.rs"), b"fn a() {}\nfn b() {}\n".as_slice()),
+            (key("notes.txt"), b"unrelated\n".as_slice()),
+        ];
+        let created = [
+            (key("src/new.rs"), body.as_bytes()),
+            (key("src/edited.rs"), edited.as_bytes()),
+            (key("other.txt"), b"something else entirely\n".as_slice()),
+        ];
+
+        let renames = detect_renames(&deleted, &created, DEFAULT_RENAME_THRESHOLD);
+        assert_eq!(
+            renames,
+            vec![
+                DetectedRename {
+                    src: key("src/copy.rs"),
+                    dst: key("src/edited.rs"),
+                    similarity: 66,
+                },
+                DetectedRename {
+                    src: key("src/old.rs"),
+                    dst: key("src/new.rs"),
+                    similarity: 100,
+                },
+            ]
+        );
+
+        // Nothing reaches a strict threshold except the exact copy.
+        let renames = detect_renames(&deleted, &created, 90);
+        assert_eq!(renames.len(), 1);
+        assert_eq!(renames[0].dst, key("src/new.rs"));
+    }
+
+    #[test]
+    fn test_empty_files_pair_only_with_empty_files() {
+        let deleted = [(key("a"), b"".as_slice())];
+        let created = [(key("b"), b"x\n".as_slice()), (key("c"), b"".as_slice())];
+        let renames = detect_renames(&deleted, &created, 50);
+        assert_eq!(renames.len(), 1);
+        assert_eq!(renames[0].dst, key("c"));
+    }
+}
@@ -1,11 +1,14 @@
 //! replace.rs — build and apply a staged replace plan over an in-memory buffer.
 
 use crate::error::Result;
+use crate::fs::PathKey;
 use crate::tools::abort::AbortFlag;
+use crate::tools::line_index::LineIndex;
 use crate::tools::matcher::RegexMatcher;
 use crate::tools::model::ByteSpan;
+use crate::tools::preview::PreviewBuilder;
 use crate::tools::search::{search_regions, MatchRegion};
-use crate::Error;
+use crate::{EditItem, Error};
 
 /// One concrete edit to apply to the haystack.
 #[derive(Debug, Clone)]
@@ -103,6 +106,60 @@ pub fn plan_in_bytes(
     Ok(ReplacePlan { ops })
 }
 
+/// Recase each replacement of `plan` to follow the text it replaces, so
+/// replacing `foo` with `bar` writes `Bar` over `Foo` and `BAR` over `FOO`.
+///
+/// Matches in all capitals (two or more letters) make the replacement all
+/// capitals, and matches in lower case make it lower case. Otherwise the
+/// replacement's first letter takes the case of the match's first letter.
+/// Matches without letters, and text that is not UTF-8, are left alone.
+pub fn preserve_case(haystack: &[u8], plan: &mut ReplacePlan) {
+    for op in &mut plan.ops {
+        let (Ok(matched), Ok(replacement)) = (
+            std::str::from_utf8(&haystack[op.span.to_range()]),
+            std::str::from_utf8(&op.replacement),
+        ) else {
+            continue;
+        };
+        if let Some(recased) = match_case(matched, replacement) {
+            op.replacement = recased.into_bytes();
+        }
+    }
+}
+
+/// `replacement` recased to follow `matched`; `None` to keep it as it is.
+fn match_case(matched: &str, replacement: &str) -> Option<String> {
+    let mut cased = matched
+        .chars()
+        .filter(|c| c.is_uppercase() || c.is_lowercase());
+    let first = cased.next()?;
+    let mut upper = usize::from(first.is_uppercase());
+    let mut lower = usize::from(first.is_lowercase());
+    for c in cased {
+        upper += usize::from(c.is_uppercase());
+        lower += usize::from(c.is_lowercase());
+    }
+
+    if lower == 0 && upper > 1 {
+        return Some(replacement.to_uppercase());
+    }
+    if upper == 0 {
+        return Some(replacement.to_lowercase());
+    }
+
+    let start = replacement.find(|c: char| c.is_uppercase() || c.is_lowercase())?;
+    let letter = replacement[start..].chars().next()?;
+    let mut out = String::with_capacity(replacement.len());
+    out.push_str(&replacement[..start]);
+    if first.is_uppercase() {
+        out.extend(letter.to_uppercase());
+    } else {
+        out.extend(letter.to_lowercase());
+    }
+    out.push_str(&replacement[start + letter.len_utf8()..]);
+    Some(out)
+}
+
 /// Apply a previously built plan to `haystack` in a single pass.
 ///
 /// If the plan is empty, returns a clone of the input.
@@ -127,3 +184,152 @@ pub fn apply_plan(haystack: &[u8], plan: &ReplacePlan) -> Vec<u8> {
     }
     out
 }
+
+/// Before/after previews for each op of `plan`, which turned `before`
+/// into `after`.
+pub fn preview_plan(
+    path: &PathKey,
+    before: &[u8],
+    after: &[u8],
+    plan: &ReplacePlan,
+    builder: &PreviewBuilder,
+) -> Result<Vec<EditItem>> {
+    let before_index = LineIndex::build(before);
+    let after_index = LineIndex::build(after);
+
+    let mut items = Vec::with_capacity(plan.ops.len());
+    // Bytes added so far, minus bytes removed, by earlier ops.
+    let mut shift = 0isize;
+    for op in &plan.ops {
+        let start = op.span.start.checked_add_signed(shift).unwrap_or(0);
+        let staged_span = ByteSpan {
+            start,
+            end: start + op.replacement.len(),
+        };
+        shift += op.replacement.len() as isize - op.span.len() as isize;
+
+        let original_range = line_range(&before_index, op.span);
+        let staged_range = line_range(&after_index, staged_span);
+        items.push(EditItem {
+            path: path.clone(),
+            original_preview: builder.build_hunk(
+                path.clone(),
+                &before_index,
+                before,
+                &op.span,
+                original_range.0,
+                original_range.1,
+            )?,
+            staged_preview: builder.build_hunk(
+                path.clone(),
+                &after_index,
+                after,
+                &staged_span,
+                staged_range.0,
+                staged_range.1,
+            )?,
+            original_range,
+            staged_range,
+        });
+    }
+    Ok(items)
+}
+
+/// Lines removed and added by `items`, counting each line once even when
+/// several edits touch it.
+pub fn changed_lines(items: &[EditItem]) -> (usize, usize) {
+    let count = |ranges: &mut dyn Iterator<Item = (usize, usize)>| {
+        let mut total = 0;
+        let mut last = 0;
+        for (start, end) in ranges {
+            let start = start.max(last + 1);
+            if end >= start {
+                total += end - start + 1;
+                last = end;
+            }
+        }
+        total
+    };
+    (
+        count(&mut items.iter().map(|i| i.original_range)),
+        count(&mut items.iter().map(|i| i.staged_range)),
+    )
+}
+
+/// Inclusive 1-based lines of `span`; empty spans get the line they sit on.
+fn line_range(index: &LineIndex, span: ByteSpan) -> (usize, usize) {
+    index.lines_of_span(span).unwrap_or_else(|| {
+        let line = index
+            .line_of_byte(span.start)
+            .unwrap_or_else(|| index.line_count().max(1));
+        (line, line)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn plan(haystack: &str, find: &str, replace: &str) -> ReplacePlan {
+        let re = RegexMatcher::compile(find, &Default::default()).unwrap();
+        plan_in_bytes(haystack.as_bytes(), &re, replace, false, &AbortFlag::new()).unwrap()
+    }
+
+    #[test]
+    fn test_capture_templates_rewrite_every_match() {
+        let before = "let a = f(1);\nlet b = f(22);\n";
+        let plan = plan(before, r"f\((?P<arg>\d+)\)", "g(${arg}, $1)");
+        let after = apply_plan(before.as_bytes(), &plan);
+        assert_eq!(
+            String::from_utf8(after).unwrap(),
+            "let a = g(1, 1);\nlet b = g(22, 22);\n"
+        );
+    }
+
+    #[test]
+    fn test_preserve_case_follows_each_match() {
+        let before = "foo Foo FOO fOO fooBar F 42";
+        let mut plan = plan(before, "(?i)foo|f|42", "bar");
+        preserve_case(before.as_bytes(), &mut plan);
+        let after = apply_plan(before.as_bytes(), &plan);
+        assert_eq!(
+            String::from_utf8(after).unwrap(),
+            "bar Bar BAR bar barBar Bar bar"
+        );
+
+        assert_eq!(match_case("Foo", "_bar").as_deref(), Some("_Bar"));
+        assert_eq!(match_case("foo", "BarBaz").as_deref(), Some("barbaz"));
+        assert_eq!(match_case("Foo", "über").as_deref(), Some("Über"));
+        assert_eq!(match_case("42", "bar"), None);
+    }
+
+    #[test]
+    fn test_previews_track_shifted_lines() {
+        let before = "one\ntwo\nthree\n";
+        let plan = plan(before, "one|three", "x\ny");
+        let after = apply_plan(before.as_bytes(), &plan);
+        let path = PathKey::from_arc(Arc::from("a.txt"));
+        let items = preview_plan(
+            &path,
+            before.as_bytes(),
+            &after,
+            &plan,
+            &PreviewBuilder::new(0),
+        )
+        .unwrap();
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(
+            (items[0].original_range, items[0].staged_range),
+            ((1, 1), (1, 2))
+        );
+        assert_eq!(
+            (items[1].original_range, items[1].staged_range),
+            ((3, 3), (4, 5))
+        );
+        assert_eq!(items[1].original_preview.excerpt, "three\n");
+        assert_eq!(items[1].staged_preview.excerpt, "x\ny\n");
+        assert_eq!(changed_lines(&items), (2, 4));
+    }
+}
@@ -0,0 +1,139 @@
+//! Flat match reports for export to spreadsheets and scripts.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// Output format for [`render_report`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    Csv,
+    Json,
+}
+
+impl std::str::FromStr for ReportFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Self::Csv),
+            "json" => Ok(Self::Json),
+            other => Err(Error::InvalidFormat(other.to_string())),
+        }
+    }
+}
+
+/// One match, flattened for export.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchRecord {
+    pub path: String,
+    /// 1-based line of the match start
+    pub line: usize,
+    /// 1-based byte column of the match start within its line
+    pub column: usize,
+    /// The matched text
+    #[serde(rename = "match")]
+    pub matched: String,
+    /// Capture groups `$1..$N`; `None` for groups that did not participate
+    pub captures: Vec<Option<String>>,
+}
+
+/// Serialize records as CSV (RFC 4180) or a JSON array.
+///
+/// CSV columns are `path,line,column,match,capture_1..capture_N`, where `N`
+/// is the largest capture count among the records.
+pub fn render_report(records: &[MatchRecord], format: ReportFormat) -> Result<String> {
+    match format {
+        ReportFormat::Json => {
+            serde_json::to_string(records).map_err(|e| Error::InvalidFormat(e.to_string()))
+        }
+        ReportFormat::Csv => Ok(render_csv(records)),
+    }
+}
+
+fn render_csv(records: &[MatchRecord]) -> String {
+    let capture_cols = records.iter().map(|r| r.captures.len()).max().unwrap_or(0);
+
+    let mut out = String::from("path,line,column,match");
+    for i in 1..=capture_cols {
+        out.push_str(&format!(",capture_{i}"));
+    }
+    out.push_str("\r\n");
+
+    for record in records {
+        push_csv_field(&mut out, &record.path);
+        out.push_str(&format!(",{},{},", record.line, record.column));
+        push_csv_field(&mut out, &record.matched);
+        for i in 0..capture_cols {
+            out.push(',');
+            if let Some(Some(cap)) = record.captures.get(i) {
+                push_csv_field(&mut out, cap);
+            }
+        }
+        out.push_str("\r\n");
+    }
+
+    out
+}
+
+fn push_csv_field(out: &mut String, field: &str) {
+    if field.contains([',', '"', '\r', '\n']) {
+        out.push('"');
+        out.push_str(&field.replace('"', "\"\""));
+        out.push('"');
+    } else {
+        out.push_str(field);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(path: &str, matched: &str, captures: Vec<Option<&str>>) -> MatchRecord {
+        MatchRecord {
+            path: path.to_string(),
+            line: 3,
+            column: 5,
+            matched: matched.to_string(),
+            captures: captures
+                .into_iter()
+                .map(|c| c.map(str::to_string))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_csv_quotes_and_pads_captures() {
+        let records = vec![
+            record("src/a.rs", "foo(1, 2)", vec![Some("1"), None]),
+            record("src/b.rs", "say \"hi\"", vec![]),
+        ];
+        let csv = render_report(&records, ReportFormat::Csv).unwrap();
+        assert_eq!(
+            csv,
+            "path,line,column,match,capture_1,capture_2\r\n\
+             src/a.rs,3,5,\"foo(1, 2)\",1,\r\n\
+             src/b.rs,3,5,\"say \"\"hi\"\"\",,\r\n"
+        );
+    }
+
+    #[test]
+    fn test_json_shape() {
+        let records = vec![record("a.txt", "x", vec![Some("x"), None])];
+        let json = render_report(&records, ReportFormat::Json).unwrap();
+        assert_eq!(
+            json,
+            r#"[{"path":"a.txt","line":3,"column":5,"match":"x","captures":["x",null]}]"#
+        );
+    }
+
+    #[test]
+    fn test_format_parse() {
+        assert_eq!("CSV".parse::<ReportFormat>().unwrap(), ReportFormat::Csv);
+        assert_eq!("json".parse::<ReportFormat>().unwrap(), ReportFormat::Json);
+        assert!("xml".parse::<ReportFormat>().is_err());
+    }
+}
@@ -0,0 +1,244 @@
+//! Search results kept for narrowing, like "search in results" in editors.
+//!
+//! A [`ResultSet`] holds the lines a search matched along with the index
+//! snapshot it ran on. Refining it with another pattern only reads those
+//! lines, so narrowing a large result stays cheap and never picks up lines
+//! the first search did not match.
+
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::fs::{Index, PathKey};
+use crate::tools::{AbortFlag, LineIndex, RegexMatcher};
+use crate::FindRequest;
+
+/// Lines per page when the caller does not choose.
+pub const DEFAULT_RESULT_PAGE_SIZE: usize = 100;
+
+/// A matched line.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultLine {
+    pub path: PathKey,
+    /// 1-based
+    pub line: usize,
+    /// The line without its line ending, decoded lossily.
+    pub text: String,
+}
+
+/// One page of a [`ResultSet`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultSetPage {
+    pub lines: Vec<ResultLine>,
+    /// 0-based
+    pub page: usize,
+    pub page_count: usize,
+    pub total_lines: usize,
+    pub total_files: usize,
+    /// Patterns applied with [`ResultSet::refine`], in order.
+    pub refinements: Vec<String>,
+}
+
+/// Lines matched by a search, narrowed by later patterns.
+///
+/// The set pins the index snapshot it was created on, so its lines stay
+/// valid while staging changes; it does not see those changes.
+pub struct ResultSet {
+    request: FindRequest,
+    index: Arc<Index>,
+    /// Sorted by path then line, without duplicates.
+    lines: Vec<(PathKey, usize)>,
+    refinements: Vec<String>,
+}
+
+impl ResultSet {
+    /// A set of the `lines` a search for `request` matched in `index`.
+    pub fn new(
+        request: FindRequest,
+        index: Arc<Index>,
+        lines: impl IntoIterator<Item = (PathKey, usize)>,
+    ) -> Self {
+        let mut lines: Vec<_> = lines.into_iter().collect();
+        lines.sort();
+        lines.dedup();
+        Self {
+            request,
+            index,
+            lines,
+            refinements: Vec::new(),
+        }
+    }
+
+    pub fn request(&self) -> &FindRequest {
+        &self.request
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.lines.chunk_by(|(a, _), (b, _)| a == b).count()
+    }
+
+    /// Keep only lines that also match `pattern`, compiled with the
+    /// original request's options. Returns the number of lines left.
+    ///
+    /// An invalid pattern or an abort leaves the set unchanged.
+    pub fn refine(&mut self, pattern: &str, abort: &AbortFlag) -> Result<usize> {
+        let matcher = RegexMatcher::compile(pattern, &self.request.engine_opts)?;
+        let mut kept = Vec::with_capacity(self.lines.len());
+        for file in self.lines.chunk_by(|(a, _), (b, _)| a == b) {
+            if abort.is_aborted() {
+                return Err(Error::Aborted);
+            }
+            let Some(content) = self.content(&file[0].0) else {
+                continue;
+            };
+            let line_index = LineIndex::build(content);
+            for (path, line) in file {
+                let Some((start, end)) = line_index.content_range_of_line(content, *line) else {
+                    continue;
+                };
+                let mut found = false;
+                matcher.find_matches(&content[start..end], |_| {
+                    found = true;
+                    false
+                })?;
+                if found {
+                    kept.push((path.clone(), *line));
+                }
+            }
+        }
+        self.lines = kept;
+        self.refinements.push(pattern.to_string());
+        Ok(self.lines.len())
+    }
+
+    /// Page `page` (0-based) of `page_size` lines; past the end it is empty.
+    pub fn page(&self, page: usize, page_size: usize) -> ResultSetPage {
+        let page_size = page_size.max(1);
+        let start = page.saturating_mul(page_size).min(self.lines.len());
+        let end = start.saturating_add(page_size).min(self.lines.len());
+
+        let mut lines = Vec::with_capacity(end - start);
+        for file in self.lines[start..end].chunk_by(|(a, _), (b, _)| a == b) {
+            let content = self.content(&file[0].0).unwrap_or_default();
+            let line_index = LineIndex::build(content);
+            for (path, line) in file {
+                let text = line_index
+                    .content_range_of_line(content, *line)
+                    .map(|(s, e)| String::from_utf8_lossy(&content[s..e]).into_owned())
+                    .unwrap_or_default();
+                lines.push(ResultLine {
+                    path: path.clone(),
+                    line: *line,
+                    text,
+                });
+            }
+        }
+
+        ResultSetPage {
+            lines,
+            page,
+            page_count: self.lines.len().div_ceil(page_size),
+            total_lines: self.lines.len(),
+            total_files: self.file_count(),
+            refinements: self.refinements.clone(),
+        }
+    }
+
+    fn content(&self, path: &PathKey) -> Option<&[u8]> {
+        self.index.get_file(path)?.search_content()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::FileEntry;
+
+    fn key(p: &str) -> PathKey {
+        PathKey::from_arc(Arc::from(p))
+    }
+
+    fn set() -> ResultSet {
+        let mut index = Index::default();
+        for (path, content) in [
+            ("a.rs", "fn foo() {}\nlet foo_bar = 1;\nfoo();\n"),
+            ("b.rs", "// foo\r\nfoo_bar();\n"),
+        ] {
+            let entry = FileEntry::from_bytes("rs", 0, Arc::from(content.as_bytes()), true);
+            index.upsert_file(key(path), entry).unwrap();
+        }
+        let hits = [
+            ("b.rs", 1),
+            ("a.rs", 3),
+            ("a.rs", 1),
+            ("a.rs", 2),
+            ("b.rs", 2),
+        ];
+        ResultSet::new(
+            FindRequest {
+                find: "foo".into(),
+                ..FindRequest::default()
+            },
+            Arc::new(index),
+            hits.into_iter().map(|(p, l)| (key(p), l)),
+        )
+    }
+
+    #[test]
+    fn test_refine_narrows_matched_lines() {
+        let mut set = set();
+        assert_eq!((set.len(), set.file_count()), (5, 2));
+
+        assert_eq!(set.refine("_bar", &AbortFlag::new()).unwrap(), 2);
+        let page = set.page(0, 10);
+        assert_eq!(
+            page.lines,
+            vec![
+                ResultLine {
+                    path: key("a.rs"),
+                    line: 2,
+                    text: "let foo_bar = 1;".into(),
+                },
+                ResultLine {
+                    path: key("b.rs"),
+                    line: 2,
+                    text: "foo_bar();".into(),
+                },
+            ]
+        );
+        assert_eq!(page.refinements, ["_bar"]);
+
+        assert!(set.refine("(", &AbortFlag::new()).is_err());
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn test_pages() {
+        let set = set();
+        let page = set.page(1, 2);
+        assert_eq!(
+            (page.page_count, page.total_lines, page.total_files),
+            (3, 5, 2)
+        );
+        let lines: Vec<_> = page
+            .lines
+            .iter()
+            .map(|l| (l.path.as_str(), l.line))
+            .collect();
+        assert_eq!(lines, [("a.rs", 3), ("b.rs", 1)]);
+        assert_eq!(page.lines[1].text, "// foo");
+        assert!(set.page(3, 2).lines.is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+//! Named multi-file templates with `{{placeholder}}` substitution.
+
+use std::collections::{BTreeMap, HashMap};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// One file in a scaffold; both `path` and `content` may contain placeholders.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TemplateFile {
+    /// Path relative to the destination prefix.
+    pub path: String,
+    /// File content.
+    pub content: String,
+}
+
+/// A named set of template files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScaffoldTemplate {
+    pub name: String,
+    pub files: Vec<TemplateFile>,
+}
+
+impl ScaffoldTemplate {
+    /// Render every file with `params`, returning `(relative_path, content)` pairs.
+    ///
+    /// Fails if any placeholder has no matching parameter.
+    pub fn render(&self, params: &HashMap<String, String>) -> Result<Vec<(String, String)>> {
+        self.files
+            .iter()
+            .map(|file| Ok((render(&file.path, params)?, render(&file.content, params)?)))
+            .collect()
+    }
+
+    /// Placeholder names referenced anywhere in the template, sorted.
+    pub fn placeholders(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .files
+            .iter()
+            .flat_map(|f| placeholder_names(&f.path).chain(placeholder_names(&f.content)))
+            .map(str::to_string)
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+}
+
+/// Registry of scaffold templates keyed by name.
+#[derive(Debug, Clone, Default)]
+pub struct ScaffoldRegistry {
+    templates: BTreeMap<String, ScaffoldTemplate>,
+}
+
+impl ScaffoldRegistry {
+    /// Register or replace a template. Returns whether one was replaced.
+    pub fn register(&mut self, template: ScaffoldTemplate) -> bool {
+        self.templates
+            .insert(template.name.clone(), template)
+            .is_some()
+    }
+
+    /// Remove a template. Returns whether it existed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.templates.remove(name).is_some()
+    }
+
+    /// Lookup by name.
+    pub fn get(&self, name: &str) -> Option<&ScaffoldTemplate> {
+        self.templates.get(name)
+    }
+
+    /// Registered templates in name order.
+    pub fn iter(&self) -> impl Iterator<Item = &ScaffoldTemplate> {
+        self.templates.values()
+    }
+}
+
+/// Substitute `{{name}}` placeholders (surrounding whitespace allowed).
+pub fn render(template: &str, params: &HashMap<String, String>) -> Result<String> {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(open) = rest.find("{{") {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 2..];
+        let close = after
+            .find("}}")
+            .ok_or_else(|| Error::Pattern(format!("unterminated placeholder in '{template}'")))?;
+        let name = after[..close].trim();
+        let value = params
+            .get(name)
+            .ok_or_else(|| Error::MissingTemplateParam(name.to_string()))?;
+        out.push_str(value);
+        rest = &after[close + 2..];
+    }
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+fn placeholder_names(template: &str) -> impl Iterator<Item = &str> {
+    template
+        .split("{{")
+        .skip(1)
+        .filter_map(|chunk| chunk.find("}}").map(|close| chunk[..close].trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_render_substitutes_placeholders() {
+        let p = params(&[("name", "Button"), ("kind", "component")]);
+        assert_eq!(
+            render("export const {{name}} = /* {{ kind }} */ null;", &p).unwrap(),
+            "export const Button = /* component */ null;"
+        );
+        assert_eq!(render("no placeholders", &p).unwrap(), "no placeholders");
+    }
+
+    #[test]
+    fn test_render_errors() {
+        let p = params(&[]);
+        assert!(matches!(
+            render("{{missing}}", &p),
+            Err(Error::MissingTemplateParam(name)) if name == "missing"
+        ));
+        assert!(render("{{open", &p).is_err());
+    }
+
+    #[test]
+    fn test_template_render_and_placeholders() {
+        let template = ScaffoldTemplate {
+            name: "component".to_string(),
+            files: vec![
+                TemplateFile {
+                    path: "{{name}}/index.ts".to_string(),
+                    content: "export * from './{{name}}';\n".to_string(),
+                },
+                TemplateFile {
+                    path: "{{name}}/{{name}}.test.ts".to_string(),
+                    content: "describe('{{name}}', () => {});\n// {{author}}\n".to_string(),
+                },
+            ],
+        };
+
+        assert_eq!(template.placeholders(), vec!["author", "name"]);
+
+        let rendered = template
+            .render(&params(&[("name", "Card"), ("author", "me")]))
+            .unwrap();
+        assert_eq!(rendered[0].0, "Card/index.ts");
+        assert_eq!(rendered[1].0, "Card/Card.test.ts");
+        assert_eq!(rendered[1].1, "describe('Card', () => {});\n// me\n");
+    }
+}
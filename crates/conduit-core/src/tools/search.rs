@@ -25,20 +25,20 @@ pub struct MatchRegion<'a> {
 /// - `line_start`: 1-based line number where the match starts
 ///
 /// The callback should return Ok(true) to continue searching, Ok(false) to stop.
+/// Once `abort` is set the search stops quietly, between matches.
 pub fn for_each_match(
     haystack: &[u8],
     matcher: &RegexMatcher,
+    abort: &AbortFlag,
     mut on_match: impl FnMut(ByteSpan, usize) -> Result<bool>,
 ) -> Result<()> {
-    let abort = AbortFlag::new();
-
-    search_regions(haystack, matcher, false, &abort, |region| {
+    search_regions(haystack, matcher, false, abort, |region| {
         let mut continue_search = true;
         let mut error: Result<()> = Ok(());
 
         matcher.find_matches(region.bytes, |span| {
             // Short-circuit if we've already hit an error or stop
-            if !continue_search {
+            if !continue_search || abort.is_aborted() {
                 return false;
             }
 
@@ -112,9 +112,12 @@ pub fn search_regions(
         }
     }
 
+    // Spans must index into `haystack` itself, so never let the searcher
+    // transcode UTF-16 input it detects via a byte-order mark.
     let mut searcher = SearcherBuilder::new()
         .line_number(true)
         .multi_line(multiline)
+        .bom_sniffing(false)
         .build();
 
     let mut sink = RegionSink { abort, on_region };
@@ -123,3 +126,43 @@ pub fn search_regions(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spans_stay_within_utf16_bom_haystack() {
+        // 0xFE 0xFF is a UTF-16BE byte-order mark; spans must still refer to
+        // the raw bytes rather than a transcoded copy.
+        let haystack = [0xFE, 0xFF, 0xFF, 0x94, 0x94, 0x94];
+        let matcher = RegexMatcher::new("").unwrap();
+
+        let mut spans = Vec::new();
+        for_each_match(&haystack, &matcher, &AbortFlag::new(), |span, _| {
+            spans.push(span);
+            Ok(true)
+        })
+        .unwrap();
+
+        assert!(!spans.is_empty());
+        assert!(spans.iter().all(|s| s.end <= haystack.len()));
+    }
+
+    #[test]
+    fn test_abort_stops_between_matches() {
+        let matcher = RegexMatcher::new("a").unwrap();
+        let abort = AbortFlag::new();
+
+        let mut seen = 0;
+        for_each_match(b"a a a\na\n", &matcher, &abort, |_, _| {
+            seen += 1;
+            if seen == 2 {
+                abort.abort();
+            }
+            Ok(true)
+        })
+        .unwrap();
+        assert_eq!(seen, 2);
+    }
+}
@@ -0,0 +1,179 @@
+//! Token frequency counts across indexed files.
+//!
+//! Counting inside the module avoids exporting every file's text to the
+//! host just to tally identifiers.
+
+use std::collections::{HashMap, HashSet};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+/// What counts as one token.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenKind {
+    /// Identifiers as written: letters, digits and `_`, not starting with a
+    /// digit. `parseHttpRequest` and `MAX_LEN` are one token each.
+    #[default]
+    Identifier,
+    /// Lowercased words within identifiers, split at `_`, digits and
+    /// lower-to-upper case changes. `parseHTTPRequest` gives `parse`,
+    /// `http` and `request`.
+    Word,
+}
+
+impl std::str::FromStr for TokenKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "identifier" => Ok(Self::Identifier),
+            "word" => Ok(Self::Word),
+            other => Err(Error::InvalidFormat(other.to_string())),
+        }
+    }
+}
+
+/// How often a token occurs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct TokenCount {
+    pub token: String,
+    /// Occurrences across all files.
+    pub count: u64,
+    /// Files containing the token at least once.
+    pub files: u64,
+}
+
+/// Running token tallies, fed one file at a time.
+#[derive(Debug, Default)]
+pub struct TokenCounter {
+    kind: TokenKind,
+    counts: HashMap<String, (u64, u64)>,
+}
+
+impl TokenCounter {
+    pub fn new(kind: TokenKind) -> Self {
+        Self {
+            kind,
+            counts: HashMap::new(),
+        }
+    }
+
+    /// Tally the tokens of one file.
+    pub fn add_file(&mut self, text: &str) {
+        let tokens: Vec<String> = match self.kind {
+            TokenKind::Identifier => identifiers(text).map(str::to_string).collect(),
+            TokenKind::Word => identifiers(text).flat_map(split_words).collect(),
+        };
+
+        let mut seen = HashSet::new();
+        for token in tokens {
+            let first_in_file = seen.insert(token.clone());
+            self.bump(token, first_in_file);
+        }
+    }
+
+    fn bump(&mut self, token: String, first_in_file: bool) {
+        let (count, files) = self.counts.entry(token).or_default();
+        *count += 1;
+        if first_in_file {
+            *files += 1;
+        }
+    }
+
+    /// The `n` most frequent tokens, by count and then alphabetically.
+    pub fn top(&self, n: usize) -> Vec<TokenCount> {
+        let mut all: Vec<TokenCount> = self
+            .counts
+            .iter()
+            .map(|(token, &(count, files))| TokenCount {
+                token: token.clone(),
+                count,
+                files,
+            })
+            .collect();
+        all.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.token.cmp(&b.token)));
+        all.truncate(n);
+        all
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c == '_' || c.is_alphanumeric()
+}
+
+/// Identifier-like runs in `text`, skipping runs that start with a digit
+/// (numbers and hex literals).
+fn identifiers(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|c: char| !is_ident_char(c))
+        .filter(|run| run.chars().next().is_some_and(|c| !c.is_ascii_digit()))
+}
+
+/// Lowercased words of one identifier.
+fn split_words(ident: &str) -> Vec<String> {
+    let chars: Vec<char> = ident.chars().collect();
+    let mut words = Vec::new();
+    let mut current = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphabetic() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+        let prev = i.checked_sub(1).map(|p| chars[p]);
+        let next = chars.get(i + 1);
+        // `fooBar` breaks before `B`; `HTTPRequest` breaks before `R`.
+        let boundary = c.is_uppercase()
+            && prev.is_some_and(|p| {
+                p.is_lowercase() || (p.is_uppercase() && next.is_some_and(|n| n.is_lowercase()))
+            });
+        if boundary && !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(counts: &[TokenCount]) -> Vec<(&str, u64, u64)> {
+        counts
+            .iter()
+            .map(|c| (c.token.as_str(), c.count, c.files))
+            .collect()
+    }
+
+    #[test]
+    fn test_identifiers_counted_with_file_frequency() {
+        let mut counter = TokenCounter::new(TokenKind::Identifier);
+        counter.add_file("let x = parse(x); 0x1f 42");
+        counter.add_file("fn parse() {}");
+
+        assert_eq!(
+            tokens(&counter.top(3)),
+            vec![("parse", 2, 2), ("x", 2, 1), ("fn", 1, 1)]
+        );
+    }
+
+    #[test]
+    fn test_words_split_case_and_underscores() {
+        assert_eq!(
+            split_words("parseHTTPRequest_v2Value"),
+            vec!["parse", "http", "request", "v", "value"]
+        );
+
+        let mut counter = TokenCounter::new(TokenKind::Word);
+        counter.add_file("getUser get_user MAX_USERS");
+        assert_eq!(tokens(&counter.top(2)), vec![("get", 2, 1), ("user", 2, 1)]);
+    }
+}
@@ -0,0 +1,56 @@
+//! UTF-8 decoding helpers that report whether replacement occurred.
+
+use std::borrow::Cow;
+
+use crate::error::{Error, Result};
+
+/// Decode bytes as UTF-8, replacing invalid sequences with `U+FFFD`.
+///
+/// Returns the text and whether any replacement characters were introduced.
+#[inline]
+pub fn decode_lossy(bytes: &[u8]) -> (Cow<'_, str>, bool) {
+    let text = String::from_utf8_lossy(bytes);
+    let lossy = matches!(text, Cow::Owned(_));
+    (text, lossy)
+}
+
+/// Decode bytes as UTF-8, failing with `InvalidUtf8` when `strict` is set
+/// and the content is not valid UTF-8.
+#[inline]
+pub fn decode_checked<'a>(
+    path: &str,
+    bytes: &'a [u8],
+    strict: bool,
+) -> Result<(Cow<'a, str>, bool)> {
+    let (text, lossy) = decode_lossy(bytes);
+    if strict && lossy {
+        return Err(Error::InvalidUtf8(path.to_string()));
+    }
+    Ok((text, lossy))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_utf8_is_not_lossy() {
+        let (text, lossy) = decode_lossy("héllo".as_bytes());
+        assert_eq!(text, "héllo");
+        assert!(!lossy);
+    }
+
+    #[test]
+    fn test_invalid_utf8_is_reported() {
+        let (text, lossy) = decode_lossy(b"ab\xffcd");
+        assert_eq!(text, "ab\u{FFFD}cd");
+        assert!(lossy);
+    }
+
+    #[test]
+    fn test_strict_mode_rejects_invalid_utf8() {
+        assert!(decode_checked("a.bin", b"\xfe\xff", true).is_err());
+        assert!(decode_checked("a.bin", b"\xfe\xff", false).unwrap().1);
+        assert!(!decode_checked("a.txt", b"ok", true).unwrap().1);
+    }
+}
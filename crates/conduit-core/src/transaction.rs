@@ -0,0 +1,340 @@
+//! Batches of tool requests applied to staging all or nothing.
+//!
+//! Orchestrator handlers are each atomic on their own; a [`Transaction`]
+//! extends that to a sequence of different requests, so a caller can create,
+//! edit, move and delete files and have either every step land or none.
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+use crate::fs::{IndexManager, PathKey};
+use crate::{
+    BatchMoveRequest, ConflictPolicy, CreateRequest, CreateResponse, CreateTool, DeleteRequest,
+    DeleteResponse, DeleteTool, FileOperation, MoveFilesTool, ReplaceLinesRequest,
+    ReplaceLinesResponse, ReplaceLinesTool,
+};
+
+/// One queued request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum TransactionOp {
+    Create(CreateRequest),
+    Delete(DeleteRequest),
+    ReplaceLines(ReplaceLinesRequest),
+    Move {
+        src: PathKey,
+        dst: PathKey,
+        /// What to do if `dst` exists.
+        #[serde(default)]
+        on_conflict: ConflictPolicy,
+    },
+}
+
+impl TransactionOp {
+    /// Short name of the request kind, as used in the serialized `op` tag.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TransactionOp::Create(_) => "create",
+            TransactionOp::Delete(_) => "delete",
+            TransactionOp::ReplaceLines(_) => "replace_lines",
+            TransactionOp::Move { .. } => "move",
+        }
+    }
+}
+
+/// Response to one queued request, in queue order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum TransactionOutcome {
+    Create(CreateResponse),
+    Delete(DeleteResponse),
+    ReplaceLines(ReplaceLinesResponse),
+    Move {
+        src: PathKey,
+        /// Where the file went, which differs from the requested destination
+        /// when [`ConflictPolicy::Rename`] picked a free name.
+        dst: PathKey,
+        /// Whether an existing file at `dst` was replaced.
+        overwritten: bool,
+    },
+}
+
+/// Requests queued to be applied to staging together.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Transaction {
+    ops: Vec<TransactionOp>,
+}
+
+impl Transaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append `op`; returns the number of queued requests.
+    pub fn queue(&mut self, op: TransactionOp) -> usize {
+        self.ops.push(op);
+        self.ops.len()
+    }
+
+    pub fn operations(&self) -> &[TransactionOp] {
+        &self.ops
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Apply every queued request in order through `tools`.
+    ///
+    /// If any request fails, staging is rolled back to how it was before the
+    /// first one and the error is returned as [`Error::TransactionFailed`]
+    /// naming the failed request. A successful commit is one undo step.
+    pub fn commit<T>(self, tools: &mut T, manager: &IndexManager) -> Result<Vec<TransactionOutcome>>
+    where
+        T: CreateTool + DeleteTool + ReplaceLinesTool + MoveFilesTool,
+    {
+        // Fail up front rather than reporting the first request as the culprit.
+        manager.staged_index()?;
+
+        let label = format!("transaction of {} operations", self.ops.len());
+        manager.with_undo_step(&label, || {
+            let mut outcomes = Vec::with_capacity(self.ops.len());
+            for (index, op) in self.ops.into_iter().enumerate() {
+                let kind = op.kind();
+                let outcome = apply(tools, op).map_err(|source| Error::TransactionFailed {
+                    index,
+                    op: kind,
+                    source: Box::new(source),
+                })?;
+                outcomes.push(outcome);
+            }
+            Ok(outcomes)
+        })
+    }
+}
+
+fn apply<T>(tools: &mut T, op: TransactionOp) -> Result<TransactionOutcome>
+where
+    T: CreateTool + DeleteTool + ReplaceLinesTool + MoveFilesTool,
+{
+    Ok(match op {
+        TransactionOp::Create(req) => TransactionOutcome::Create(tools.run_create(req)?),
+        TransactionOp::Delete(req) => TransactionOutcome::Delete(tools.run_delete(req)?),
+        TransactionOp::ReplaceLines(req) => {
+            TransactionOutcome::ReplaceLines(tools.run_replace_lines(req)?)
+        }
+        TransactionOp::Move {
+            src,
+            dst,
+            on_conflict,
+        } => {
+            let response = tools.run_move_files(BatchMoveRequest {
+                operations: vec![FileOperation {
+                    src: src.clone(),
+                    dst: dst.clone(),
+                }],
+                continue_on_error: false,
+                on_conflict,
+            })?;
+            let conflict = response.conflicts.into_iter().next();
+            TransactionOutcome::Move {
+                src,
+                overwritten: conflict.as_ref().is_some_and(|c| c.renamed_to.is_none()),
+                dst: conflict.and_then(|c| c.renamed_to).unwrap_or(dst),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fs::FileEntry;
+    use crate::{
+        BatchCopyRequest, BatchOperationResponse, DeleteDirRequest, DeleteDirResponse,
+        DirOperationResponse, MoveDirRequest, MoveTreeRequest, MoveTreeResponse,
+    };
+
+    /// Applies requests straight to the manager; line edits always fail and
+    /// requests a transaction never makes are refused.
+    struct Tools<'a>(&'a IndexManager);
+
+    fn unsupported(tool: &str) -> Error {
+        Error::InvalidCommand(format!("{tool} is not supported by the test tools"))
+    }
+
+    impl CreateTool for Tools<'_> {
+        fn run_create(&mut self, req: CreateRequest) -> Result<CreateResponse> {
+            let content = req.content.unwrap_or_default();
+            let size = content.len() as u64;
+            let entry = FileEntry::from_bytes_and_path(&req.path, 0, content.into(), true);
+            self.0.stage_file(req.path.clone(), entry)?;
+            Ok(CreateResponse {
+                path: req.path,
+                size,
+                created: true,
+            })
+        }
+    }
+
+    impl DeleteTool for Tools<'_> {
+        fn run_delete(&mut self, req: DeleteRequest) -> Result<DeleteResponse> {
+            self.0.remove_staged_file(&req.path)?;
+            Ok(DeleteResponse {
+                path: req.path,
+                existed: true,
+            })
+        }
+
+        fn run_delete_dir(&mut self, _: DeleteDirRequest) -> Result<DeleteDirResponse> {
+            Err(unsupported("delete_dir"))
+        }
+    }
+
+    impl ReplaceLinesTool for Tools<'_> {
+        fn run_replace_lines(&mut self, req: ReplaceLinesRequest) -> Result<ReplaceLinesResponse> {
+            Err(Error::FileNotFound(req.path.as_str().to_string()))
+        }
+    }
+
+    impl MoveFilesTool for Tools<'_> {
+        fn run_copy_files(&mut self, _: BatchCopyRequest) -> Result<BatchOperationResponse> {
+            Err(unsupported("copy_files"))
+        }
+
+        fn run_move_files(&mut self, req: BatchMoveRequest) -> Result<BatchOperationResponse> {
+            let mut conflicts = Vec::new();
+            for op in &req.operations {
+                let staged = self.0.staged_index()?;
+                let (dst, conflict) = req.on_conflict.resolve(&staged, &op.src, &op.dst)?;
+                self.0.move_staged_file(&op.src, &dst, 0)?;
+                conflicts.extend(conflict);
+            }
+            Ok(BatchOperationResponse {
+                count: req.operations.len(),
+                results: Vec::new(),
+                conflicts,
+            })
+        }
+
+        fn run_move_tree(&mut self, _: MoveTreeRequest) -> Result<MoveTreeResponse> {
+            Err(unsupported("move_tree"))
+        }
+
+        fn run_move_dir(&mut self, _: MoveDirRequest) -> Result<DirOperationResponse> {
+            Err(unsupported("move_dir"))
+        }
+
+        fn run_copy_dir(&mut self, _: MoveDirRequest) -> Result<DirOperationResponse> {
+            Err(unsupported("copy_dir"))
+        }
+    }
+
+    fn key(path: &str) -> PathKey {
+        PathKey::from_arc(path.into())
+    }
+
+    fn create(path: &str) -> TransactionOp {
+        TransactionOp::Create(CreateRequest {
+            path: key(path),
+            content: Some(b"x".to_vec()),
+            allow_overwrite: false,
+        })
+    }
+
+    #[test]
+    fn test_commit_is_all_or_nothing() {
+        let manager = IndexManager::default();
+        manager.begin_staging().unwrap();
+        let staged = |path: &str| {
+            manager
+                .staged_index()
+                .unwrap()
+                .get_file(&key(path))
+                .is_some()
+        };
+
+        let mut tx = Transaction::new();
+        tx.queue(create("a.txt"));
+        tx.queue(TransactionOp::Move {
+            src: key("a.txt"),
+            dst: key("b.txt"),
+            on_conflict: ConflictPolicy::Error,
+        });
+        tx.queue(TransactionOp::ReplaceLines(ReplaceLinesRequest {
+            path: key("b.txt"),
+            replacements: vec![(1, 1, "y".into())],
+            precondition: None,
+        }));
+        let err = tx.commit(&mut Tools(&manager), &manager).unwrap_err();
+        assert_eq!(err.code(), "transaction_failed");
+        assert!(matches!(
+            err,
+            Error::TransactionFailed {
+                index: 2,
+                op: "replace_lines",
+                ..
+            }
+        ));
+        assert!(!staged("a.txt") && !staged("b.txt"));
+        assert!(manager.undo_state().undo.is_empty());
+
+        let mut tx = Transaction::new();
+        tx.queue(create("a.txt"));
+        tx.queue(create("c.txt"));
+        tx.queue(TransactionOp::Delete(DeleteRequest::new(key("a.txt"))));
+        let outcomes = tx.commit(&mut Tools(&manager), &manager).unwrap();
+        assert_eq!(outcomes.len(), 3);
+        assert!(!staged("a.txt") && staged("c.txt"));
+
+        // The whole commit undoes as one step.
+        manager.undo().unwrap();
+        assert!(!staged("c.txt"));
+    }
+
+    #[test]
+    fn test_move_follows_conflict_policy() {
+        let manager = IndexManager::default();
+        manager.begin_staging().unwrap();
+        let move_op = |on_conflict| TransactionOp::Move {
+            src: key("a.txt"),
+            dst: key("b.txt"),
+            on_conflict,
+        };
+
+        let mut tx = Transaction::new();
+        tx.queue(create("a.txt"));
+        tx.queue(create("b.txt"));
+        tx.queue(move_op(ConflictPolicy::Error));
+        let err = tx.commit(&mut Tools(&manager), &manager).unwrap_err();
+        assert!(matches!(err, Error::TransactionFailed { index: 2, .. }));
+
+        let mut tx = Transaction::new();
+        tx.queue(create("a.txt"));
+        tx.queue(create("b.txt"));
+        tx.queue(move_op(ConflictPolicy::Rename));
+        let outcomes = tx.commit(&mut Tools(&manager), &manager).unwrap();
+        assert!(matches!(
+            &outcomes[2],
+            TransactionOutcome::Move { dst, overwritten: false, .. } if dst.as_str() == "b-1.txt"
+        ));
+    }
+
+    #[test]
+    fn test_ops_deserialize_by_tag() {
+        let op: TransactionOp =
+            serde_json::from_str(r#"{"op":"move","src":"a.txt","dst":"b.txt"}"#).unwrap();
+        assert_eq!(op.kind(), "move");
+        assert!(matches!(
+            op,
+            TransactionOp::Move {
+                on_conflict: ConflictPolicy::Error,
+                ..
+            }
+        ));
+    }
+}
@@ -0,0 +1,71 @@
+use crate::globals::with_search_handles;
+use crate::js_err;
+use conduit_core::tools::AbortFlag;
+use wasm_bindgen::prelude::*;
+
+/// Cancels a search or edit from JavaScript.
+///
+/// Pass the handle as the last argument of a search or replace binding and
+/// call `abort()` from a callback, or before a queued call starts, to stop
+/// it; the call then fails with `code: "aborted"`. One handle may serve
+/// several calls. Call `free()` when done with it.
+#[wasm_bindgen]
+pub struct SearchHandle {
+    id: u32,
+    flag: AbortFlag,
+}
+
+#[wasm_bindgen]
+impl SearchHandle {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> SearchHandle {
+        let flag = AbortFlag::new();
+        let id = with_search_handles(|h| h.register(flag.clone()));
+        SearchHandle { id, flag }
+    }
+
+    /// Request cancellation of every call using this handle.
+    pub fn abort(&self) {
+        self.flag.abort();
+    }
+
+    /// Clear a previous `abort()` so the handle can be reused.
+    pub fn reset(&self) {
+        self.flag.reset();
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn aborted(&self) -> bool {
+        self.flag.is_aborted()
+    }
+
+    /// Identifier the bindings use to find this handle.
+    #[wasm_bindgen(getter)]
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl Default for SearchHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for SearchHandle {
+    fn drop(&mut self) {
+        with_search_handles(|h| h.remove(self.id));
+    }
+}
+
+/// The abort flag of the `SearchHandle` passed as `handle`, or a fresh flag
+/// when it is `undefined` or `null`.
+pub(crate) fn abort_flag_from_js(handle: &JsValue) -> Result<AbortFlag, JsValue> {
+    if handle.is_undefined() || handle.is_null() {
+        return Ok(AbortFlag::new());
+    }
+    let id = js_sys::Reflect::get(handle, &JsValue::from_str("id"))?
+        .as_f64()
+        .ok_or_else(|| js_err!("Expected a SearchHandle"))?;
+    with_search_handles(|h| h.get(id as u32)).ok_or_else(|| js_err!("SearchHandle has been freed"))
+}
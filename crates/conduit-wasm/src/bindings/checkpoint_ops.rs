@@ -0,0 +1,51 @@
+use crate::bindings::staging_ops::file_diff_to_js;
+use crate::globals::get_index_manager;
+use crate::js_err;
+use crate::utils::core_err;
+use js_sys::Array;
+use wasm_bindgen::prelude::*;
+
+/// Save the current staging state as `name`, replacing any checkpoint of
+/// that name; returns whether one was replaced.
+#[wasm_bindgen]
+pub fn create_checkpoint(name: String) -> Result<bool, JsValue> {
+    get_index_manager()
+        .create_checkpoint(&name)
+        .map_err(|e| core_err("Failed to create checkpoint", &e))
+}
+
+/// Return staging to the checkpoint `name`. The restore is one undo step.
+#[wasm_bindgen]
+pub fn restore_checkpoint(name: String) -> Result<(), JsValue> {
+    get_index_manager()
+        .restore_checkpoint(&name)
+        .map_err(|e| core_err(format_args!("Failed to restore checkpoint '{name}'"), &e))
+}
+
+/// Remove the checkpoint `name`; returns whether it existed.
+#[wasm_bindgen]
+pub fn remove_checkpoint(name: String) -> bool {
+    get_index_manager().remove_checkpoint(&name)
+}
+
+/// Checkpoints of the current staging session, oldest first.
+#[wasm_bindgen]
+pub fn list_checkpoints() -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(&get_index_manager().list_checkpoints())
+        .map_err(|e| js_err!("Failed to serialize checkpoints: {}", e))
+}
+
+/// Diffs of every file that differs between checkpoint `from` and
+/// checkpoint `to`, or current staging when `to` is omitted, shaped like
+/// `get_file_diff` results.
+#[wasm_bindgen]
+pub fn diff_checkpoints(
+    from: String,
+    to: Option<String>,
+    context_lines: Option<usize>,
+) -> Result<Array, JsValue> {
+    let diffs = get_index_manager()
+        .diff_checkpoints(&from, to.as_deref(), context_lines.unwrap_or(0))
+        .map_err(|e| core_err("Failed to diff checkpoints", &e))?;
+    diffs.iter().map(file_diff_to_js).collect()
+}
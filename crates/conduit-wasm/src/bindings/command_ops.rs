@@ -0,0 +1,79 @@
+use crate::bindings::abort_ops::abort_flag_from_js;
+use crate::globals::{create_path_key, get_index_manager};
+use crate::js_err;
+use crate::orchestrator::Orchestrator;
+use crate::utils::core_err;
+use conduit_core::{BatchRequest, Command, OnError};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+/// Run one JSON-encoded tool request and return the response as JSON.
+///
+/// The request names its tool in `tool`, e.g.
+/// `{"tool":"find","find":"todo"}`, with the tool's request fields beside
+/// it; see `conduit_core::Command` for the tools and their fields.
+/// Paths are normalized first; an invalid one fails with
+/// `code: "invalid_path"`. Failures throw with the usual `code`; malformed
+/// requests use `code: "invalid_command"`.
+#[wasm_bindgen]
+pub fn execute_command(json: &str, handle: JsValue) -> Result<String, JsValue> {
+    let abort_flag = abort_flag_from_js(&handle)?;
+    let run = || {
+        let mut command = Command::parse(json)?;
+        command.normalize_paths(create_path_key)?;
+        command.execute(&mut Orchestrator::new(), &abort_flag)
+    };
+    run()
+        .map(|response| response.to_string())
+        .map_err(|e| core_err("Command failed", &e))
+}
+
+/// Options for `run_batch`.
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BatchOptions {
+    on_error: OnError,
+}
+
+/// Run a list of tool requests, each shaped like an `execute_command`
+/// request, in order as one undo step.
+///
+/// `options.onError` picks what a failure does: `"stop"` (the default)
+/// skips the rest, `"continue"` runs them anyway and `"rollback"` skips the
+/// rest and undoes the ones applied. Returns one result per operation run.
+/// Every path is normalized first, so an invalid one fails the batch with
+/// `code: "invalid_path"` before anything runs.
+#[wasm_bindgen]
+pub fn run_batch(
+    operations: JsValue,
+    options: JsValue,
+    handle: JsValue,
+) -> Result<JsValue, JsValue> {
+    let operations: Vec<Command> = serde_wasm_bindgen::from_value(operations)
+        .map_err(|e| js_err!("Invalid batch operations: {}", e))?;
+    let options: BatchOptions = if options.is_undefined() || options.is_null() {
+        BatchOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)
+            .map_err(|e| js_err!("Invalid batch options: {}", e))?
+    };
+    let abort_flag = abort_flag_from_js(&handle)?;
+    let mut request = BatchRequest {
+        operations,
+        on_error: options.on_error,
+    };
+    request
+        .normalize_paths(create_path_key)
+        .map_err(|e| core_err("Batch failed", &e))?;
+    let report = conduit_core::run_batch(
+        &mut Orchestrator::new(),
+        get_index_manager(),
+        request,
+        &abort_flag,
+    )
+    .map_err(|e| core_err("Batch failed", &e))?;
+    report
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| js_err!("Failed to serialize batch report: {}", e))
+}
@@ -0,0 +1,85 @@
+use crate::globals::{
+    get_index_manager, host_defaults, reset_host_defaults, update_host_defaults, HostDefaults,
+};
+use crate::js_err;
+use conduit_core::fs::StagingMode;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use wasm_bindgen::prelude::*;
+
+/// Fields of `configure_index_manager`; absent ones keep their value.
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+pub struct IndexManagerOptions {
+    staging: Option<StagingMode>,
+    include_hidden: Option<bool>,
+    include_junk: Option<bool>,
+}
+
+/// Set request defaults applied by subsequent calls unless overridden.
+///
+/// Only the fields present in `options` are updated; others keep their
+/// current value. Returns the full configuration after the update.
+#[wasm_bindgen]
+pub fn configure(options: JsValue) -> Result<JsValue, JsValue> {
+    let update: HostDefaults = serde_wasm_bindgen::from_value(options)
+        .map_err(|e| js_err!("Invalid configuration: {}", e))?;
+    update_host_defaults(update);
+    get_configuration()
+}
+
+/// Current request defaults.
+#[wasm_bindgen]
+pub fn get_configuration() -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(&host_defaults())
+        .map_err(|e| js_err!("Failed to serialize configuration: {}", e))
+}
+
+/// Restore the built-in request defaults.
+#[wasm_bindgen]
+pub fn reset_configuration() {
+    reset_host_defaults();
+}
+
+/// Configure the index manager.
+///
+/// `staging: "auto"` begins staging on the first change, so tools no longer
+/// fail with `StagingNotActive` when the host has not called
+/// `begin_index_staging`; `"strict"` (the default) keeps that failure.
+/// `includeHidden` and `includeJunk` set whether listings, trees and
+/// searches include dotfiles and OS junk files when the call leaves it
+/// unset.
+/// Only the fields present in `options` are updated. Returns the full
+/// manager configuration after the update.
+#[wasm_bindgen]
+pub fn configure_index_manager(options: JsValue) -> Result<JsValue, JsValue> {
+    let update: IndexManagerOptions = if options.is_undefined() || options.is_null() {
+        IndexManagerOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)
+            .map_err(|e| js_err!("Invalid index manager configuration: {}", e))?
+    };
+
+    let manager = get_index_manager();
+    let mut config = manager.config();
+    if let Some(staging) = update.staging {
+        config.staging = staging;
+    }
+    if let Some(include_hidden) = update.include_hidden {
+        config.include_hidden = include_hidden;
+    }
+    if let Some(include_junk) = update.include_junk {
+        config.include_junk = include_junk;
+    }
+    manager.configure(config);
+    serde_wasm_bindgen::to_value(&config)
+        .map_err(|e| js_err!("Failed to serialize configuration: {}", e))
+}
+
+/// Threads searches can match files on; `1` when this build has no
+/// `parallel` feature or no thread pool is running, in which case searches
+/// run serially.
+#[wasm_bindgen]
+pub fn search_threads() -> usize {
+    conduit_core::tools::available_threads()
+}
@@ -1,8 +1,58 @@
 use crate::globals::{create_path_key, get_index_manager};
 use crate::js_err;
-use crate::utils::JsObjectBuilder;
+use crate::utils::to_js;
+use conduit_core::PathKey;
+use schemars::JsonSchema;
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
 
+/// Result of `debug_file_info`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugFileInfo {
+    pub original_path: String,
+    pub normalized_path: PathKey,
+    pub path_key: PathKey,
+    /// `"staged"` or `"active"`.
+    pub index_type: &'static str,
+    pub total_files_in_index: usize,
+    pub file_found: bool,
+    #[serde(flatten)]
+    pub entry: Option<DebugEntryInfo>,
+    /// Up to 10 indexed paths, when the file was not found.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sample_files_in_index: Option<Vec<PathKey>>,
+}
+
+/// Entry details in a [`DebugFileInfo`] for a file that was found.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugEntryInfo {
+    pub has_bytes: bool,
+    pub bytes_len: usize,
+    pub encoding: &'static str,
+    pub has_search_content: bool,
+    pub search_content_len: usize,
+    pub is_editable: bool,
+    /// Unix seconds.
+    pub mtime: i64,
+    pub line_index_built: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_lines: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line_index_total_bytes: Option<usize>,
+}
+
+/// A file listed by `debug_list_all_files`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DebugFileSummary {
+    pub path: PathKey,
+    pub has_bytes: bool,
+    pub has_search_content: bool,
+    pub is_editable: bool,
+}
+
 #[wasm_bindgen]
 pub fn debug_file_info(path: String, use_staged: bool) -> Result<JsValue, JsValue> {
     let manager = get_index_manager();
@@ -18,58 +68,40 @@ pub fn debug_file_info(path: String, use_staged: bool) -> Result<JsValue, JsValu
     let path_key = create_path_key(&path)
         .map_err(|e| js_err!("Failed to create path key '{}': {}", path, e))?;
 
-    let mut obj = JsObjectBuilder::new();
-    obj = obj.set("originalPath", JsValue::from_str(&path))?;
-    obj = obj.set("normalizedPath", JsValue::from_str(path_key.as_str()))?;
-    obj = obj.set("pathKey", JsValue::from_str(path_key.as_str()))?;
-    obj = obj.set(
-        "indexType",
-        JsValue::from_str(if use_staged { "staged" } else { "active" }),
-    )?;
-    obj = obj.set("totalFilesInIndex", JsValue::from(index.len() as u32))?;
-
-    if let Some(entry) = index.get_file(&path_key) {
-        obj = obj.set("fileFound", JsValue::from(true))?;
-        obj = obj.set("hasBytes", JsValue::from(entry.bytes().is_some()))?;
-        obj = obj.set(
-            "bytesLen",
-            JsValue::from(entry.bytes().map(|b| b.len()).unwrap_or(0) as u32),
-        )?;
-        obj = obj.set(
-            "hasSearchContent",
-            JsValue::from(entry.search_content().is_some()),
-        )?;
-        obj = obj.set(
-            "searchContentLen",
-            JsValue::from(entry.search_content().map(|c| c.len()).unwrap_or(0) as u32),
-        )?;
-        obj = obj.set("isEditable", JsValue::from(entry.is_editable()))?;
-        obj = obj.set("mtime", JsValue::from(entry.mtime() as f64))?;
-
-        // Check if line index can be built
-        if let Some(line_index) = manager.get_line_index(&path_key, &index) {
-            obj = obj.set("lineIndexBuilt", JsValue::from(true))?;
-            obj = obj.set("totalLines", JsValue::from(line_index.line_count() as u32))?;
-            obj = obj.set(
-                "lineIndexTotalBytes",
-                JsValue::from(line_index.total_bytes() as u32),
-            )?;
-        } else {
-            obj = obj.set("lineIndexBuilt", JsValue::from(false))?;
-        }
-    } else {
-        obj = obj.set("fileFound", JsValue::from(false))?;
-
-        // List some files that ARE in the index for debugging
-        let mut sample_files = Vec::new();
-        for (p, _) in index.iter_sorted().take(10) {
-            sample_files.push(JsValue::from_str(p.as_str()));
+    let entry = index.get_file(&path_key).map(|entry| {
+        let line_index = manager.get_line_index(&path_key, &index);
+        DebugEntryInfo {
+            has_bytes: entry.bytes().is_some(),
+            bytes_len: entry.bytes().map_or(0, |b| b.len()),
+            encoding: entry.encoding().as_str(),
+            has_search_content: entry.search_content().is_some(),
+            search_content_len: entry.search_content().map_or(0, |c| c.len()),
+            is_editable: entry.is_editable(),
+            mtime: entry.mtime(),
+            line_index_built: line_index.is_some(),
+            total_lines: line_index.as_ref().map(|l| l.line_count()),
+            line_index_total_bytes: line_index.as_ref().map(|l| l.total_bytes()),
         }
-        let sample_array = js_sys::Array::from_iter(sample_files);
-        obj = obj.set("sampleFilesInIndex", JsValue::from(sample_array))?;
-    }
+    });
+    // List some files that ARE in the index for debugging
+    let sample_files_in_index = entry.is_none().then(|| {
+        index
+            .iter_sorted()
+            .take(10)
+            .map(|(p, _)| p.clone())
+            .collect()
+    });
 
-    Ok(obj.build())
+    to_js(&DebugFileInfo {
+        original_path: path,
+        normalized_path: path_key.clone(),
+        path_key,
+        index_type: if use_staged { "staged" } else { "active" },
+        total_files_in_index: index.len(),
+        file_found: entry.is_some(),
+        entry,
+        sample_files_in_index,
+    })
 }
 
 #[wasm_bindgen]
@@ -84,19 +116,15 @@ pub fn debug_list_all_files(use_staged: bool, limit: usize) -> Result<JsValue, J
         manager.active_index()
     };
 
-    let files = js_sys::Array::new();
-    for (path, entry) in index.iter_sorted().take(limit) {
-        let file_obj = JsObjectBuilder::new()
-            .set("path", JsValue::from_str(path.as_str()))?
-            .set("hasBytes", JsValue::from(entry.bytes().is_some()))?
-            .set(
-                "hasSearchContent",
-                JsValue::from(entry.search_content().is_some()),
-            )?
-            .set("isEditable", JsValue::from(entry.is_editable()))?
-            .build();
-        files.push(&file_obj);
-    }
-
-    Ok(JsValue::from(files))
+    let files: Vec<DebugFileSummary> = index
+        .iter_sorted()
+        .take(limit)
+        .map(|(path, entry)| DebugFileSummary {
+            path: path.clone(),
+            has_bytes: entry.bytes().is_some(),
+            has_search_content: entry.search_content().is_some(),
+            is_editable: entry.is_editable(),
+        })
+        .collect();
+    to_js(&files)
 }
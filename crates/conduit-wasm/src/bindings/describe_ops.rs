@@ -0,0 +1,1086 @@
+use crate::bindings::command_ops::BatchOptions;
+use crate::bindings::config_ops::IndexManagerOptions;
+use crate::bindings::debug_ops::{DebugFileInfo, DebugFileSummary};
+use crate::bindings::edit_plan_ops::EditPlanView;
+use crate::bindings::file_ops::{
+    BatchResult, DeleteListResult, DirResult, MoveTreeResult, OverwriteResult, PlacedFile,
+};
+use crate::bindings::hint_ops::HintProgress;
+use crate::bindings::lazy_ops::HydrateReport;
+use crate::bindings::line_ops::{
+    AnchoredEditOptions, AppendOptions, Insertion, JsPrecondition, LineReplacement,
+};
+use crate::bindings::pattern_ops::PatternInfo;
+use crate::bindings::read_ops::{FileStat, FileText, FilesRead, LinesRead, PathStat};
+use crate::bindings::scaffold_ops::ScaffoldInfo;
+use crate::bindings::search_ops::{
+    FileListing, ReplaceOptions, ReplaceResult, SearchHit, SearchHunk, SearchOptions, SearchPage,
+};
+use crate::bindings::semantic_ops::{PendingChunk, ScoredChunk, SemanticOptions, WorkspaceChunk};
+use crate::bindings::staging_ops::{
+    ArchiveLoadOptions, ChangeSummary, CommitDeltaView, CommitInfo, DiffOptions, DiffView,
+    MemoryUsageInfo, PackedDiff, PatchResult, StagedContents, StagingInfo,
+};
+use crate::bindings::transaction_ops::{OpResult, QueuedOp};
+use crate::globals::HostDefaults;
+use crate::js_err;
+use crate::utils::LineEditResult;
+use crate::IndexStatsView;
+use conduit_core::fs::{
+    BatchReport, CheckpointInfo, CompactReport, DirNode, ElevationRecord, GlobOptions, HintIntent,
+    HydrationUsage, LoadProgress, ManagerConfig, MemoryReport, StagedOp, UndoState,
+};
+use conduit_core::tools::{
+    ArchiveFormat, Describe, EditPolicy, ExtractedMatch, RelatedFiles, ReportFormat, ResultSetPage,
+    SearchHistory, TemplateFile, TokenCount, TokenKind, ToolSpec,
+};
+use conduit_core::{
+    AnchoredEditResponse, ApplyScaffoldResponse, BatchResponse, Command, ConflictPolicy,
+    CreateResponse, DeleteDirResponse, DeleteResponse, FileOperation, LineOperation,
+    MergeFileResponse, PathKey, ValidateLinesResponse, SCHEMA_VERSION,
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+/// Description of the `onConflict` parameter of copies and moves.
+const ON_CONFLICT: &str =
+    "When the destination exists: fail (default), overwrite it, or rename to name-N.ext";
+
+const USE_STAGED: &str = "Use the staged view (default true)";
+const OWNER: &str = "Label of the flow that owns the staging session";
+const PRECONDITION: &str =
+    "Fail instead of editing if the file no longer has this hash or mtime (ms)";
+
+/// Results of the search bindings: excerpts, or bare hits for `minimal`
+/// searches.
+fn search_results() -> serde_json::Value {
+    json!({ "anyOf": [Vec::<SearchHunk>::schema(), Vec::<SearchHit>::schema()] })
+}
+
+fn search_tools() -> Vec<ToolSpec> {
+    vec![
+        ToolSpec::new(
+            "search_files",
+            "Regex search with positional filters; prefer search_files_with_options.",
+        )
+        .param::<String>("searchTerm", "Regular expression")
+        .param::<Option<String>>("pathPrefix", "Only paths under this prefix")
+        .param::<Option<String>>("includePattern", "Only paths matching this glob")
+        .param::<Option<String>>("excludePattern", "Skip paths matching this glob")
+        .param::<Option<bool>>("caseSensitive", "Match case")
+        .param::<Option<bool>>("wholeWord", "Match whole words only")
+        .param::<Option<bool>>("useStaged", USE_STAGED)
+        .param::<Option<usize>>("contextLines", "Lines around each match")
+        .param::<Option<usize>>("limit", "Maximum results")
+        .param::<Option<f64>>("modifiedAfter", "JavaScript millisecond timestamp")
+        .param::<Option<f64>>("modifiedBefore", "JavaScript millisecond timestamp")
+        .returns_schema(search_results()),
+        ToolSpec::new(
+            "search_files_with_options",
+            "Regex search across indexed files, returning excerpts around each match.",
+        )
+        .param::<String>("searchTerm", "Regular expression")
+        .param::<Option<SearchOptions>>("options", "Search filters")
+        .returns_schema(search_results()),
+        ToolSpec::new(
+            "search_files_multi",
+            "Search for any of several regexes in one pass; each excerpt names the pattern that matched.",
+        )
+        .param::<Vec<String>>("patterns", "Regular expressions")
+        .param::<Option<SearchOptions>>("options", "Search filters")
+        .returns_schema(search_results()),
+        ToolSpec::new(
+            "search_files_in_list",
+            "Regex search over the files of a newline-delimited path list.",
+        )
+        .param::<String>("searchTerm", "Regular expression")
+        .param::<Vec<u8>>("paths", "Newline-delimited UTF-8 paths (a Uint8Array)")
+        .param::<Option<SearchOptions>>("options", "Search filters")
+        .returns_schema(search_results()),
+        ToolSpec::new("search_files_page", "Fetch one page of search results.")
+            .param::<String>("pattern", "Regular expression")
+            .param::<Option<String>>("cursor", "Cursor from the previous page; null to start")
+            .param::<usize>("pageSize", "Results per page")
+            .param::<Option<SearchOptions>>("options", "Search filters, for the first page")
+            .returns::<SearchPage>(),
+        ToolSpec::new("close_search_cursor", "Drop a search cursor before it is exhausted.")
+            .param::<String>("cursor", "Cursor to drop")
+            .returns::<bool>(),
+        ToolSpec::new(
+            "replace_in_files",
+            "Replace every regex match in the selected files, or preview with dryRun.",
+        )
+        .mutating()
+        .param::<String>("pattern", "Regular expression")
+        .param::<String>("replacement", "Replacement text; $1 and ${name} expand groups")
+        .param::<Option<ReplaceOptions>>("options", "Search filters and dryRun")
+        .returns::<ReplaceResult>(),
+        ToolSpec::new(
+            "export_search_report",
+            "Run a search and render every match as a CSV or JSON document.",
+        )
+        .param::<String>("searchTerm", "Regular expression")
+        .param::<Option<SearchOptions>>("options", "Search filters")
+        .param::<ReportFormat>("format", "Report format")
+        .returns::<String>(),
+        ToolSpec::new(
+            "extract_matches",
+            "Every regex match with its numbered and named capture groups, as structured data.",
+        )
+        .param::<String>("pattern", "Regular expression")
+        .param::<Option<SearchOptions>>("options", "Search filters")
+        .returns::<Vec<ExtractedMatch>>(),
+        ToolSpec::new(
+            "get_token_frequencies",
+            "Most frequent identifiers or words across the selected files.",
+        )
+        .param::<Option<Vec<String>>>("globs", "Only count files matching these globs")
+        .param::<Option<usize>>("topN", "Tokens to return (default 100)")
+        .param::<Option<TokenKind>>("tokenKind", "What counts as a token (default identifier)")
+        .param::<Option<bool>>("useStaged", USE_STAGED)
+        .returns::<Vec<TokenCount>>(),
+        ToolSpec::new(
+            "suggest_related_files",
+            "Tests, importers and siblings likely affected by changes to the given paths.",
+        )
+        .param::<Vec<String>>("paths", "Changed paths")
+        .param::<Option<bool>>("useStaged", USE_STAGED)
+        .param::<Option<usize>>("perKind", "Suggestions per kind per path (default 10)")
+        .returns::<Vec<RelatedFiles>>(),
+        ToolSpec::new(
+            "list_files_from_wasm",
+            "List indexed files with size, mtime and editability, paginated.",
+        )
+        .param::<Option<String>>("pathPrefix", "Only paths under this prefix")
+        .param::<Option<String>>("globPattern", "Only paths matching this glob")
+        .param::<Option<bool>>("useStaged", USE_STAGED)
+        .param::<Option<usize>>("limit", "Page size")
+        .param::<Option<usize>>("offset", "Files to skip")
+        .param::<Option<f64>>("modifiedAfter", "JavaScript millisecond timestamp")
+        .param::<Option<f64>>("modifiedBefore", "JavaScript millisecond timestamp")
+        .param::<Option<bool>>("withChecksums", "Include content checksums")
+        .param::<Option<bool>>("includeHidden", "Include dotfiles and hidden files")
+        .param::<Option<bool>>("includeIgnored", "Include paths matched by ignore rules")
+        .param::<Option<GlobOptions>>("globOptions", "How globPattern is matched")
+        .param::<Option<bool>>("includeJunk", "Include OS junk files such as .DS_Store")
+        .returns::<FileListing>(),
+        ToolSpec::new("get_edit_epochs", "Edit epoch per path; 0 if never written.")
+            .param::<Vec<String>>("paths", "Paths to look up")
+            .returns::<Vec<f64>>(),
+        ToolSpec::new(
+            "find_in_text",
+            "Search a caller-provided string with the file search engine.",
+        )
+        .param::<String>("content", "Text to search")
+        .param::<String>("pattern", "Regular expression")
+        .param::<Option<SearchOptions>>("options", "Matching options")
+        .returns_schema(search_results()),
+        ToolSpec::new("diff_texts", "Line diff of two caller-provided strings.")
+            .param::<String>("original", "Original text")
+            .param::<String>("modified", "Modified text")
+            .returns::<DiffView>(),
+        ToolSpec::new(
+            "create_result_set",
+            "Run a search and keep the matched lines for refining; returns the set id.",
+        )
+        .param::<String>("pattern", "Regular expression")
+        .param::<Option<SearchOptions>>("options", "Search filters")
+        .returns::<String>(),
+        ToolSpec::new(
+            "refine_result_set",
+            "Keep only the lines of a result set that also match a pattern.",
+        )
+        .param::<String>("id", "Result set id")
+        .param::<String>("pattern", "Regular expression")
+        .returns::<usize>(),
+        ToolSpec::new("get_result_set", "One page of a result set.")
+            .param::<String>("id", "Result set id")
+            .param::<usize>("page", "0-based page")
+            .param::<Option<usize>>("pageSize", "Lines per page (default 100)")
+            .returns::<ResultSetPage>(),
+        ToolSpec::new("close_result_set", "Drop a result set.")
+            .param::<String>("id", "Result set id")
+            .returns::<bool>(),
+        ToolSpec::new(
+            "register_pattern",
+            "Save a regex with its matching options under a name for patternRef.",
+        )
+        .param::<String>("name", "Pattern name")
+        .param::<String>("pattern", "Regular expression")
+        .param::<Option<SearchOptions>>("options", "Matching options")
+        .returns::<bool>(),
+        ToolSpec::new("remove_pattern", "Remove a registered pattern.")
+            .param::<String>("name", "Pattern name")
+            .returns::<bool>(),
+        ToolSpec::new("list_patterns", "Registered patterns.").returns::<Vec<PatternInfo>>(),
+        ToolSpec::new("get_search_history", "Recent and saved searches.")
+            .returns::<SearchHistory>(),
+        ToolSpec::new(
+            "restore_search_history",
+            "Replace the search history with one from get_search_history.",
+        )
+        .param::<SearchHistory>("history", "Search history"),
+        ToolSpec::new("clear_recent_searches", "Forget recent searches, keeping saved ones."),
+        ToolSpec::new("save_search", "Save a search under a name.")
+            .param::<String>("name", "Search name")
+            .param::<String>("pattern", "Regular expression")
+            .param::<Option<SearchOptions>>("options", "Search filters")
+            .returns::<bool>(),
+        ToolSpec::new("remove_saved_search", "Remove a saved search.")
+            .param::<String>("name", "Search name")
+            .returns::<bool>(),
+        ToolSpec::new("run_saved_search", "Run a saved search.")
+            .param::<String>("name", "Search name")
+            .param::<Option<usize>>("limit", "Maximum results")
+            .returns_schema(search_results()),
+        ToolSpec::new(
+            "create_edit_plan",
+            "Preview a regex replacement as hunks that can be applied selectively; returns the plan id.",
+        )
+        .param::<String>("pattern", "Regular expression")
+        .param::<String>("replacement", "Replacement text")
+        .param::<Option<SearchOptions>>("options", "Search filters")
+        .returns::<String>(),
+        ToolSpec::new("get_edit_plan", "Hunks of an edit plan.")
+            .param::<String>("planId", "Plan id")
+            .returns::<EditPlanView>(),
+        ToolSpec::new("apply_edit_hunks", "Apply the chosen hunks of an edit plan.")
+            .mutating()
+            .param::<String>("planId", "Plan id")
+            .param::<Vec<usize>>("hunkIds", "Hunks to apply")
+            .returns::<ReplaceResult>(),
+        ToolSpec::new("discard_edit_plan", "Drop an edit plan.")
+            .param::<String>("planId", "Plan id")
+            .returns::<bool>(),
+        ToolSpec::new(
+            "semantic_search",
+            "Rank chunks of the selected files by embedding similarity to a query.",
+        )
+        .param::<String>("query", "Natural-language query")
+        .param::<Option<usize>>("topK", "Number of results (default 10)")
+        .param::<Option<SemanticOptions>>("options", "Search filters and chunking")
+        .returns::<Vec<ScoredChunk>>(),
+        ToolSpec::new(
+            "semantic_search_by_vector",
+            "Rank chunks by similarity to a caller-computed query embedding.",
+        )
+        .param::<Vec<f32>>("query", "Query embedding")
+        .param::<Option<usize>>("topK", "Number of results (default 10)")
+        .param::<Option<SemanticOptions>>("options", "Search filters and chunking")
+        .returns::<Vec<ScoredChunk>>(),
+        ToolSpec::new("pending_embeddings", "Chunks whose embeddings are not cached yet.")
+            .param::<Option<SemanticOptions>>("options", "Search filters and chunking")
+            .returns::<Vec<PendingChunk>>(),
+        ToolSpec::new("put_embeddings", "Cache embeddings for chunk hashes.")
+            .param::<Vec<String>>("hashes", "Chunk hashes from pending_embeddings")
+            .param::<Vec<Vec<f32>>>("vectors", "One embedding per hash")
+            .returns::<u32>(),
+        ToolSpec::new("clear_embedding_provider", "Unregister the embedding provider."),
+        ToolSpec::new("clear_embedding_cache", "Drop every cached embedding."),
+        ToolSpec::new(
+            "chunk_workspace",
+            "Split the workspace into line-aligned chunks with stable ids.",
+        )
+        .param::<Option<usize>>("maxChunkLines", "Lines per chunk (default 40)")
+        .param::<Option<usize>>("overlap", "Lines shared by consecutive chunks (default 5)")
+        .param::<Option<Vec<String>>>("globs", "Only chunk files matching these globs")
+        .param::<Option<bool>>("useStaged", USE_STAGED)
+        .returns::<Vec<WorkspaceChunk>>(),
+    ]
+}
+
+fn read_tools() -> Vec<ToolSpec> {
+    vec![
+        ToolSpec::new(
+            "read_file_lines",
+            "Read an inclusive, 1-based line range of a file.",
+        )
+        .param::<String>("path", "File path")
+        .param::<usize>("startLine", "First line, 1-based")
+        .param::<usize>("endLine", "Last line, inclusive")
+        .param::<bool>("useStaged", "Read the staged view")
+        .returns::<LinesRead>(),
+        ToolSpec::new("read_file", "Read the bytes of a file.")
+            .param::<String>("path", "File path")
+            .param::<Option<bool>>("useStaged", USE_STAGED)
+            .returns::<Vec<u8>>(),
+        ToolSpec::new("read_file_text", "Read the whole text of a file.")
+            .param::<String>("path", "File path")
+            .param::<Option<bool>>("useStaged", USE_STAGED)
+            .returns::<FileText>(),
+        ToolSpec::new(
+            "read_files_in_list",
+            "Read the text of every file in a newline-delimited path list.",
+        )
+        .param::<Vec<u8>>("paths", "Newline-delimited UTF-8 paths (a Uint8Array)")
+        .param::<Option<bool>>("useStaged", USE_STAGED)
+        .returns::<FilesRead>(),
+        ToolSpec::new(
+            "stat_paths",
+            "Existence, size, mtime and flags for each path, in order.",
+        )
+        .param::<Vec<String>>("paths", "Paths to look up")
+        .param::<Option<bool>>("useStaged", USE_STAGED)
+        .returns::<Vec<PathStat>>(),
+        ToolSpec::new(
+            "stat_file",
+            "Size, mtime, extension, line count and hash of one file, without its content.",
+        )
+        .param::<String>("path", "File path")
+        .param::<Option<bool>>("useStaged", USE_STAGED)
+        .returns::<FileStat>(),
+        ToolSpec::new(
+            "file_exists",
+            "Whether a file exists, in the staged view if staging is active.",
+        )
+        .param::<String>("path", "File path")
+        .returns::<bool>(),
+        ToolSpec::new(
+            "get_file_tree",
+            "Nested directory tree with file counts and total sizes per directory.",
+        )
+        .param::<Option<String>>("prefix", "Directory to root the tree at")
+        .param::<Option<usize>>("depth", "Directory levels to expand (default unlimited)")
+        .param::<Option<bool>>("useStaged", USE_STAGED)
+        .param::<Option<bool>>("includeHidden", "Include dotfiles and hidden files")
+        .param::<Option<bool>>("includeIgnored", "Include paths matched by ignore rules")
+        .param::<Option<bool>>("includeJunk", "Include OS junk files such as .DS_Store")
+        .returns::<DirNode>(),
+        ToolSpec::new(
+            "get_index_stats",
+            "File counts, sizes, per-extension totals and the largest files.",
+        )
+        .param::<Option<usize>>("topN", "Largest files to list (default 10)")
+        .returns::<IndexStatsView>(),
+        ToolSpec::new("file_count", "Number of files in the active index.").returns::<u32>(),
+        ToolSpec::new(
+            "validate_line_operations",
+            "Check line edits against the staged file without applying them.",
+        )
+        .param::<String>("path", "File path")
+        .param::<Vec<LineOperation>>("operations", "Line operations, 1-based and inclusive")
+        .returns::<ValidateLinesResponse>(),
+        ToolSpec::new(
+            "validate_can_edit_lines",
+            "Whether line edits on a file are allowed; throws if it must be read first.",
+        )
+        .param::<String>("path", "File path")
+        .returns::<bool>(),
+        ToolSpec::new(
+            "record_file_read",
+            "Mark a file as read so line edits on it are allowed.",
+        )
+        .param::<String>("path", "File path"),
+        ToolSpec::new(
+            "mark_file_needs_read",
+            "Require a file to be read again before line edits.",
+        )
+        .param::<String>("path", "File path"),
+        ToolSpec::new(
+            "check_file_needs_read",
+            "Whether a file must be re-read before line edits.",
+        )
+        .param::<String>("path", "File path")
+        .returns::<bool>(),
+        ToolSpec::new(
+            "hint_paths",
+            "Warm caches for paths about to be read, edited or searched.",
+        )
+        .param::<Vec<String>>("paths", "Paths to warm")
+        .param::<HintIntent>("intent", "What the paths will be used for")
+        .param::<Option<f64>>("budgetMs", "Milliseconds to spend warming now (default 4)")
+        .returns::<HintProgress>(),
+        ToolSpec::new(
+            "drain_hints",
+            "Keep warming hinted paths for a time budget.",
+        )
+        .param::<f64>("budgetMs", "Milliseconds to spend")
+        .returns::<HintProgress>(),
+        ToolSpec::new("clear_hints", "Drop hinted paths not warmed yet."),
+        ToolSpec::new("debug_file_info", "Index internals for one path.")
+            .param::<String>("path", "File path")
+            .param::<bool>("useStaged", "Use the staged view")
+            .returns::<DebugFileInfo>(),
+        ToolSpec::new("debug_list_all_files", "Indexed paths with their sizes.")
+            .param::<bool>("useStaged", "Use the staged view")
+            .param::<usize>("limit", "Maximum files")
+            .returns::<Vec<DebugFileSummary>>(),
+    ]
+}
+
+fn edit_tools() -> Vec<ToolSpec> {
+    let line_edit = |name, description| {
+        ToolSpec::new(name, description)
+            .mutating()
+            .param::<String>("path", "File path")
+    };
+    let edit_options = |spec: ToolSpec| {
+        spec.param::<bool>(
+            "useStaged",
+            "Ignored; edits always apply to the staged view",
+        )
+        .param::<Option<EditPolicy>>(
+            "policy",
+            "Whitespace handling; every option defaults to off",
+        )
+        .param::<Option<JsPrecondition>>("precondition", PRECONDITION)
+        .returns::<LineEditResult>()
+    };
+
+    vec![
+        edit_options(
+            line_edit(
+                "replace_lines",
+                "Replace single lines or inclusive line ranges.",
+            )
+            .param::<Vec<LineReplacement>>("replacements", "Lines or ranges and their new content"),
+        ),
+        edit_options(
+            line_edit("delete_lines", "Delete lines by 1-based number.")
+                .param::<Vec<usize>>("lineNumbers", "Lines to delete"),
+        ),
+        edit_options(
+            line_edit("insert_before_line", "Insert content before a line.")
+                .param::<usize>("lineNumber", "1-based line")
+                .param::<String>("content", "Text to insert"),
+        ),
+        edit_options(
+            line_edit("insert_after_line", "Insert content after a line.")
+                .param::<usize>("lineNumber", "1-based line")
+                .param::<String>("content", "Text to insert"),
+        ),
+        edit_options(
+            line_edit("insert_lines", "Apply several insertions in one edit.")
+                .param::<Vec<Insertion>>("insertions", "Insertions"),
+        ),
+        line_edit(
+            "append_to_file",
+            "Add lines at the end of a file, creating it if missing.",
+        )
+        .param::<String>("content", "Lines to add")
+        .param::<Option<AppendOptions>>("options", "Creation and whitespace handling")
+        .returns::<LineEditResult>(),
+        line_edit(
+            "prepend_to_file",
+            "Add lines at the start of a file (after any shebang), creating it if missing.",
+        )
+        .param::<String>("content", "Lines to add")
+        .param::<Option<AppendOptions>>("options", "Creation and whitespace handling")
+        .returns::<LineEditResult>(),
+        line_edit(
+            "anchored_edit",
+            "Replace text located by its content; it must be unique unless an occurrence is given.",
+        )
+        .param::<String>("expectedText", "Text to replace, as last read")
+        .param::<String>("replacement", "New text")
+        .param::<Option<AnchoredEditOptions>>(
+            "options",
+            "Which match to replace and how to compare whitespace",
+        )
+        .returns::<AnchoredEditResponse>(),
+        ToolSpec::new(
+            "merge_file",
+            "Three-way merge of the staged file with another version, without staging it.",
+        )
+        .param::<String>("path", "File path")
+        .param::<String>("base", "Common ancestor text")
+        .param::<String>("theirs", "Other version's text")
+        .returns::<MergeFileResponse>(),
+        line_edit(
+            "stage_merge",
+            "Three-way merge of the staged file with another version, staging the result.",
+        )
+        .param::<String>("base", "Common ancestor text")
+        .param::<String>("theirs", "Other version's text")
+        .returns::<MergeFileResponse>(),
+    ]
+}
+
+fn file_tools() -> Vec<ToolSpec> {
+    vec![
+        ToolSpec::new(
+            "create_index_file",
+            "Create a file, or overwrite one if allowed.",
+        )
+        .mutating()
+        .param::<String>("path", "File path")
+        .param::<Option<Vec<u8>>>("content", "File bytes (a Uint8Array)")
+        .param::<bool>("allowOverwrite", "Replace an existing file")
+        .returns::<CreateResponse>(),
+        ToolSpec::new(
+            "overwrite_file",
+            "Replace a file's content, optionally only if the active copy is unchanged.",
+        )
+        .mutating()
+        .param::<String>("path", "File path")
+        .param::<Vec<u8>>("content", "File bytes (a Uint8Array)")
+        .param::<Option<String>>("expectedActiveHash", "Hash the active copy must still have")
+        .returns::<OverwriteResult>(),
+        ToolSpec::new("delete_file", "Delete a file.")
+            .mutating()
+            .param::<String>("path", "File path")
+            .returns::<DeleteResponse>(),
+        ToolSpec::new(
+            "delete_files_in_list",
+            "Delete every file in a newline-delimited path list.",
+        )
+        .mutating()
+        .param::<Vec<u8>>("paths", "Newline-delimited UTF-8 paths (a Uint8Array)")
+        .returns::<DeleteListResult>(),
+        ToolSpec::new("copy_file", "Copy a file.")
+            .mutating()
+            .param::<String>("src", "Source path")
+            .param::<String>("dst", "Destination path")
+            .param::<Option<ConflictPolicy>>("onConflict", ON_CONFLICT)
+            .returns::<PlacedFile>(),
+        ToolSpec::new("move_file", "Move or rename a file.")
+            .mutating()
+            .param::<String>("src", "Source path")
+            .param::<String>("dst", "Destination path")
+            .param::<Option<ConflictPolicy>>("onConflict", ON_CONFLICT)
+            .returns::<PlacedFile>(),
+        ToolSpec::new("copy_files", "Copy several files.")
+            .mutating()
+            .param::<Vec<FileOperation>>("operations", "Copies")
+            .param::<Option<bool>>(
+                "continueOnError",
+                "Report failures per file instead of aborting",
+            )
+            .param::<Option<ConflictPolicy>>("onConflict", ON_CONFLICT)
+            .returns::<BatchResult>(),
+        ToolSpec::new("move_files", "Move several files.")
+            .mutating()
+            .param::<Vec<FileOperation>>("operations", "Moves")
+            .param::<Option<bool>>(
+                "continueOnError",
+                "Report failures per file instead of aborting",
+            )
+            .param::<Option<ConflictPolicy>>("onConflict", ON_CONFLICT)
+            .returns::<BatchResult>(),
+        ToolSpec::new(
+            "move_tree",
+            "Move a file or directory and rewrite the imports that name it.",
+        )
+        .mutating()
+        .param::<String>("from", "Source path")
+        .param::<String>("to", "Destination path")
+        .param::<Option<bool>>("rewriteReferences", "Rewrite imports (default true)")
+        .param::<Option<bool>>(
+            "dryRun",
+            "Report the moves and rewrites without staging them",
+        )
+        .param::<Option<ConflictPolicy>>("onConflict", ON_CONFLICT)
+        .returns::<MoveTreeResult>(),
+        ToolSpec::new("delete_directory", "Delete every file under a directory.")
+            .mutating()
+            .param::<String>("path", "Directory path")
+            .returns::<DeleteDirResponse>(),
+        ToolSpec::new("move_directory", "Move every file under a directory.")
+            .mutating()
+            .param::<String>("from", "Source directory")
+            .param::<String>("to", "Destination directory")
+            .param::<Option<ConflictPolicy>>("onConflict", ON_CONFLICT)
+            .returns::<DirResult>(),
+        ToolSpec::new("copy_directory", "Copy every file under a directory.")
+            .mutating()
+            .param::<String>("from", "Source directory")
+            .param::<String>("to", "Destination directory")
+            .param::<Option<ConflictPolicy>>("onConflict", ON_CONFLICT)
+            .returns::<DirResult>(),
+        ToolSpec::new(
+            "set_files_hidden",
+            "Flag or unflag files as hidden from listings and search.",
+        )
+        .mutating()
+        .param::<Vec<String>>("paths", "Paths to flag")
+        .param::<bool>("hidden", "New flag value")
+        .returns::<u32>(),
+        ToolSpec::new("register_scaffold", "Register a named file template.")
+            .param::<String>("name", "Template name")
+            .param::<Vec<TemplateFile>>("files", "Files, with {{param}} placeholders")
+            .returns::<bool>(),
+        ToolSpec::new("remove_scaffold", "Remove a file template.")
+            .param::<String>("name", "Template name")
+            .returns::<bool>(),
+        ToolSpec::new("list_scaffolds", "Registered file templates.")
+            .returns::<Vec<ScaffoldInfo>>(),
+        ToolSpec::new(
+            "apply_scaffold",
+            "Render a registered template and stage its files.",
+        )
+        .mutating()
+        .param::<String>("name", "Template name")
+        .param::<Option<HashMap<String, String>>>("params", "Placeholder values")
+        .param::<String>("dstPrefix", "Directory to render into")
+        .param::<Option<bool>>("allowOverwrite", "Replace existing files")
+        .returns::<ApplyScaffoldResponse>(),
+    ]
+}
+
+fn staging_tools() -> Vec<ToolSpec> {
+    vec![
+        ToolSpec::new("begin_index_staging", "Start a staging session.")
+            .mutating()
+            .param::<Option<String>>("owner", OWNER),
+        ToolSpec::new("get_staging_owner", "Owner of the staging session, if any.")
+            .returns::<Option<String>>(),
+        ToolSpec::new(
+            "commit_index_staging",
+            "Promote staged changes to the active index.",
+        )
+        .mutating()
+        .param::<Option<String>>("owner", OWNER)
+        .returns::<CommitInfo>(),
+        ToolSpec::new("revert_index_staging", "Discard staged changes.")
+            .mutating()
+            .param::<Option<String>>("owner", OWNER),
+        ToolSpec::new(
+            "promote_staged_index",
+            "Make staging the active index; returns its file count.",
+        )
+        .mutating()
+        .returns::<usize>(),
+        ToolSpec::new(
+            "get_staging_info",
+            "File, modification and deletion counts for staging.",
+        )
+        .returns::<StagingInfo>(),
+        ToolSpec::new(
+            "export_commit_delta",
+            "Files changed by the last commit, with their content; null before any commit.",
+        )
+        .returns::<Option<CommitDeltaView>>(),
+        ToolSpec::new(
+            "get_staged_modifications",
+            "Paths created or changed in staging.",
+        )
+        .returns::<Vec<PathKey>>(),
+        ToolSpec::new("get_staged_deletions", "Paths deleted in staging.")
+            .returns::<Vec<PathKey>>(),
+        ToolSpec::new(
+            "get_staged_modifications_with_active",
+            "Staged and active content of each modified file.",
+        )
+        .returns::<Vec<StagedContents>>(),
+        ToolSpec::new(
+            "get_modified_files_summary",
+            "Per-file line counts and status of staged changes.",
+        )
+        .returns::<Vec<ChangeSummary>>(),
+        ToolSpec::new(
+            "export_change_summary_markdown",
+            "Staged changes as a Markdown table.",
+        )
+        .returns::<String>(),
+        ToolSpec::new(
+            "get_staged_history",
+            "Operations staged against a path, oldest first.",
+        )
+        .param::<String>("path", "File path")
+        .returns::<Vec<StagedOp>>(),
+        ToolSpec::new(
+            "get_file_diff",
+            "Line diff of a file between active and staged.",
+        )
+        .param::<String>("path", "File path")
+        .param::<Option<DiffOptions>>("options", "Granularity and context")
+        .returns::<DiffView>(),
+        ToolSpec::new(
+            "get_file_diff_packed",
+            "get_file_diff as JSON bytes, compressed under the configured policy.",
+        )
+        .param::<String>("path", "File path")
+        .returns::<PackedDiff>(),
+        ToolSpec::new(
+            "get_unified_diff",
+            "Git-style unified patch of one file's staged change.",
+        )
+        .param::<String>("path", "File path")
+        .returns::<String>(),
+        ToolSpec::new(
+            "export_patch",
+            "All staged changes as one git-style unified patch.",
+        )
+        .returns::<String>(),
+        ToolSpec::new(
+            "export_changes_archive",
+            "All staged changes as a zip or tar archive.",
+        )
+        .param::<ArchiveFormat>("format", "Archive format")
+        .returns::<Vec<u8>>(),
+        ToolSpec::new(
+            "apply_patch",
+            "Apply a unified diff to staging; all files and hunks or nothing.",
+        )
+        .mutating()
+        .param::<String>("patch", "Unified diff text, git-style headers optional")
+        .returns::<PatchResult>(),
+    ]
+}
+
+fn history_tools() -> Vec<ToolSpec> {
+    vec![
+        ToolSpec::new(
+            "undo_last_operation",
+            "Undo the last staged operation; returns its label, or null if none.",
+        )
+        .mutating()
+        .returns::<Option<String>>(),
+        ToolSpec::new(
+            "redo_operation",
+            "Redo the last undone operation; returns its label, or null if none.",
+        )
+        .mutating()
+        .returns::<Option<String>>(),
+        ToolSpec::new("get_undo_history", "Labels of the undo and redo stacks.")
+            .returns::<UndoState>(),
+        ToolSpec::new(
+            "create_checkpoint",
+            "Save the staging state under a name; returns whether one was replaced.",
+        )
+        .param::<String>("name", "Checkpoint name")
+        .returns::<bool>(),
+        ToolSpec::new("restore_checkpoint", "Return staging to a checkpoint.")
+            .mutating()
+            .param::<String>("name", "Checkpoint name"),
+        ToolSpec::new("remove_checkpoint", "Remove a checkpoint.")
+            .param::<String>("name", "Checkpoint name")
+            .returns::<bool>(),
+        ToolSpec::new(
+            "list_checkpoints",
+            "Checkpoints of the staging session, oldest first.",
+        )
+        .returns::<Vec<CheckpointInfo>>(),
+        ToolSpec::new(
+            "diff_checkpoints",
+            "Diffs of the files that differ between two checkpoints.",
+        )
+        .param::<String>("from", "Older checkpoint")
+        .param::<Option<String>>("to", "Newer checkpoint (default current staging)")
+        .param::<Option<usize>>("contextLines", "Unchanged lines around each region")
+        .returns::<Vec<DiffView>>(),
+        ToolSpec::new(
+            "begin_transaction",
+            "Start queueing operations to commit together.",
+        ),
+        ToolSpec::new(
+            "queue_operation",
+            "Queue an operation in the open transaction; returns its position.",
+        )
+        .param::<QueuedOp>("operation", "Operation to queue")
+        .returns::<u32>(),
+        ToolSpec::new(
+            "commit_transaction",
+            "Apply the queued operations as one undo step; all or nothing.",
+        )
+        .mutating()
+        .returns::<Vec<OpResult>>(),
+        ToolSpec::new("abort_transaction", "Drop the open transaction.").returns::<bool>(),
+        ToolSpec::new(
+            "export_session",
+            "Snapshot of indexes, staging and history as bytes.",
+        )
+        .returns::<Vec<u8>>(),
+        ToolSpec::new("import_session", "Restore a snapshot from export_session.")
+            .mutating()
+            .param::<Vec<u8>>("bytes", "Snapshot bytes (a Uint8Array)"),
+        ToolSpec::new(
+            "execute_command",
+            "Run one JSON-encoded tool request and return the response as JSON.",
+        )
+        .mutating()
+        .param_schema(
+            "json",
+            "A request naming its tool in `tool`",
+            json!({
+                "type": "string",
+                "contentMediaType": "application/json",
+                "contentSchema": Command::schema(),
+            }),
+            true,
+        )
+        .returns::<String>(),
+        ToolSpec::new(
+            "run_batch",
+            "Run several tool requests in order as one undo step.",
+        )
+        .mutating()
+        .param::<Vec<Command>>("operations", "Requests as for execute_command")
+        .param::<Option<BatchOptions>>("options", "What a failure does")
+        .returns::<BatchResponse>(),
+    ]
+}
+
+fn host_tools() -> Vec<ToolSpec> {
+    let load_batch = |name, description| {
+        ToolSpec::new(name, description)
+            .mutating()
+            .param::<Vec<String>>("paths", "File paths")
+            .param::<Vec<Vec<u8>>>("contents", "File bytes (Uint8Arrays)")
+            .param::<Vec<f64>>("mtimes", "JavaScript millisecond timestamps")
+            .param::<Vec<bool>>("permissions", "Whether each file is editable")
+    };
+    vec![
+        ToolSpec::new("ping", "Liveness check; returns \"pong\".").returns::<String>(),
+        ToolSpec::new(
+            "schema_version",
+            "Wire schema version of structured responses.",
+        )
+        .returns::<u32>(),
+        ToolSpec::new(
+            "describe_tools",
+            "This manifest: every operation with its parameter and result schemas.",
+        )
+        .returns::<ToolManifest>(),
+        ToolSpec::new(
+            "configure",
+            "Update request defaults; returns the full configuration.",
+        )
+        .param::<HostDefaults>("options", "Defaults to change")
+        .returns::<HostDefaults>(),
+        ToolSpec::new("get_configuration", "Current request defaults.").returns::<HostDefaults>(),
+        ToolSpec::new(
+            "reset_configuration",
+            "Restore the built-in request defaults.",
+        ),
+        ToolSpec::new(
+            "configure_index_manager",
+            "Set the staging mode and listing visibility; returns the manager configuration.",
+        )
+        .param::<Option<IndexManagerOptions>>("options", "Settings to change")
+        .returns::<ManagerConfig>(),
+        ToolSpec::new("search_threads", "Threads searches can match files on.").returns::<usize>(),
+        ToolSpec::new("get_memory_usage", "Content bytes held by the indexes.")
+            .returns::<MemoryUsageInfo>(),
+        ToolSpec::new(
+            "get_memory_report",
+            "Content bytes by kind, including bytes shared between indexes.",
+        )
+        .returns::<MemoryReport>(),
+        ToolSpec::new(
+            "compact_index",
+            "Release unused capacity held by the indexes.",
+        )
+        .returns::<CompactReport>(),
+        ToolSpec::new("clear_index", "Drop cached line indexes."),
+        ToolSpec::new(
+            "reset_all_indices",
+            "Discard staging and empty the active index.",
+        )
+        .mutating(),
+        ToolSpec::new("clear_wasm_index", "Empty the active index.").mutating(),
+        ToolSpec::new(
+            "mint_elevated_token",
+            "Mint a token that lets with_elevated overwrite read-only files.",
+        )
+        .param::<String>("reason", "Recorded in every audit entry")
+        .returns::<String>(),
+        ToolSpec::new("revoke_elevated_token", "Revoke an elevation token.")
+            .param::<String>("token", "Token to revoke")
+            .returns::<bool>(),
+        ToolSpec::new(
+            "get_elevation_audit",
+            "Audit log of elevated runs, oldest first.",
+        )
+        .returns::<Vec<ElevationRecord>>(),
+        ToolSpec::new(
+            "begin_file_load",
+            "Start a load; batches are staged until committed.",
+        )
+        .mutating(),
+        load_batch(
+            "load_file_batch",
+            "Stage a batch of files for the current load.",
+        )
+        .returns::<usize>(),
+        load_batch(
+            "load_file_batch_with_text",
+            "Stage a batch of files, with their decoded text where known.",
+        )
+        .param::<Option<Vec<String>>>("textContents", "Text of each file")
+        .returns::<usize>(),
+        ToolSpec::new("commit_file_load", "Finish a load; returns the file count.")
+            .mutating()
+            .returns::<usize>(),
+        ToolSpec::new(
+            "abort_file_load",
+            "Discard the files staged by the current load.",
+        )
+        .mutating(),
+        load_batch(
+            "add_files_to_staging",
+            "Stage files, reporting those skipped.",
+        )
+        .param::<Option<Vec<String>>>("textContents", "Text of each file")
+        .returns::<BatchReport>(),
+        ToolSpec::new("load_archive", "Stage the files of a zip or tar archive.")
+            .mutating()
+            .param::<Vec<u8>>("bytes", "Archive bytes (a Uint8Array)")
+            .param::<Option<ArchiveLoadOptions>>("options", "Which entries to load")
+            .returns::<BatchReport>(),
+        ToolSpec::new(
+            "get_load_progress",
+            "Totals for the current or most recent load.",
+        )
+        .returns::<LoadProgress>(),
+        ToolSpec::new(
+            "load_file_metadata_batch",
+            "Stage files by metadata only; content is fetched on demand.",
+        )
+        .mutating()
+        .param::<Vec<String>>("paths", "File paths")
+        .param::<Vec<f64>>("sizes", "Sizes in bytes")
+        .param::<Vec<f64>>("mtimes", "JavaScript millisecond timestamps")
+        .param::<Vec<bool>>("permissions", "Whether each file is editable")
+        .returns::<BatchReport>(),
+        ToolSpec::new(
+            "hydrate_files",
+            "Fetch content for metadata-only files from the content provider.",
+        )
+        .param::<Vec<String>>("paths", "Paths to fetch")
+        .returns::<HydrateReport>(),
+        ToolSpec::new(
+            "get_hydration_usage",
+            "Bytes of fetched content held, and the budget.",
+        )
+        .returns::<HydrationUsage>(),
+    ]
+}
+
+/// Every described operation, grouped by area.
+fn tools() -> Vec<ToolSpec> {
+    let mut tools = search_tools();
+    tools.extend(read_tools());
+    tools.extend(edit_tools());
+    tools.extend(file_tools());
+    tools.extend(staging_tools());
+    tools.extend(history_tools());
+    tools.extend(host_tools());
+    tools
+}
+
+/// Result of `describe_tools`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolManifest {
+    pub schema_version: u32,
+    pub tools: Vec<ToolSpec>,
+}
+
+/// Describe the operations agents can call, with JSON Schemas for their
+/// parameters and results.
+///
+/// Returns `{ schemaVersion, tools: [{ name, description, mutating, args,
+/// params, result }] }`, where `args` lists parameter names in call order.
+/// Every export is listed except those taking JS callbacks; trailing
+/// `SearchHandle` arguments are left out of `args`.
+#[wasm_bindgen]
+pub fn describe_tools() -> Result<JsValue, JsValue> {
+    let manifest = ToolManifest {
+        schema_version: SCHEMA_VERSION,
+        tools: tools(),
+    };
+    manifest
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| js_err!("Failed to serialize tool descriptions: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+    use std::path::Path;
+
+    /// Exports that take JS callbacks or set up the module, which a tool
+    /// manifest cannot describe.
+    const UNLISTED: &[&str] = &[
+        "init",
+        "on_index_event",
+        "register_content_provider",
+        "register_embedding_provider",
+        "with_elevated",
+        "search_files_streaming",
+        "get_modified_files_summary_with_progress",
+    ];
+
+    /// Names of the free functions marked `#[wasm_bindgen]` under `dir`.
+    /// Methods are indented, so only top-level items are collected.
+    fn exports(dir: &Path, names: &mut BTreeSet<String>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                exports(&path, names);
+                continue;
+            }
+            let source = std::fs::read_to_string(&path).unwrap();
+            let mut lines = source.lines();
+            while let Some(line) = lines.next() {
+                if !line.starts_with("#[wasm_bindgen") {
+                    continue;
+                }
+                let item = lines
+                    .by_ref()
+                    .find(|l| !l.starts_with("#[") && !l.starts_with("///"))
+                    .unwrap_or_default();
+                let name = item
+                    .strip_prefix("pub fn ")
+                    .or_else(|| item.strip_prefix("pub async fn "))
+                    .and_then(|rest| rest.split('(').next());
+                if let Some(name) = name {
+                    names.insert(name.to_string());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_export_is_described() {
+        let mut exported = BTreeSet::new();
+        exports(
+            &Path::new(env!("CARGO_MANIFEST_DIR")).join("src"),
+            &mut exported,
+        );
+        assert!(exported.contains("describe_tools"));
+
+        let described: BTreeSet<String> = tools().iter().map(|t| t.name.to_string()).collect();
+        let unlisted: BTreeSet<String> = UNLISTED.iter().map(|n| n.to_string()).collect();
+        let missing: Vec<_> = exported
+            .difference(&described)
+            .filter(|name| !unlisted.contains(*name))
+            .collect();
+        assert!(
+            missing.is_empty(),
+            "exports without a description: {missing:?}"
+        );
+        let unknown: Vec<_> = described.difference(&exported).collect();
+        assert!(unknown.is_empty(), "descriptions of no export: {unknown:?}");
+        assert!(unlisted.is_subset(&exported));
+    }
+
+    #[test]
+    fn test_names_are_unique() {
+        let tools = tools();
+        let names: BTreeSet<_> = tools.iter().map(|t| t.name).collect();
+        assert_eq!(names.len(), tools.len());
+    }
+
+    #[test]
+    fn test_schemas_follow_wire_names() {
+        let tools = tools();
+        let tool = |name| tools.iter().find(|t| t.name == name).unwrap();
+
+        let batch = &tool("run_batch").params["properties"]["options"]["properties"];
+        assert!(batch["onError"].is_object());
+        let batch_result = &tool("run_batch").result["properties"];
+        assert!(batch_result["rolledBack"].is_object());
+        let copy = &tool("copy_file").params["properties"]["onConflict"];
+        assert_eq!(copy["description"], ON_CONFLICT);
+        let hunks = &tool("search_files_with_options").result["anyOf"][0]["items"];
+        assert!(hunks["properties"]["matchColumns"].is_object());
+        let stat = &tool("stat_paths").result["items"]["properties"];
+        assert!(stat["editable"].is_object());
+    }
+}
@@ -0,0 +1,118 @@
+use crate::bindings::abort_ops::abort_flag_from_js;
+use crate::bindings::search_ops::{EditPreview, ReplaceResult, SearchOptions};
+use crate::globals::with_edit_plans;
+use crate::js_err;
+use crate::orchestrator::Orchestrator;
+use crate::utils::{core_err, to_js};
+use conduit_core::PathKey;
+use schemars::JsonSchema;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// Result of `get_edit_plan`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EditPlanView {
+    pub hunk_count: usize,
+    pub files: Vec<PlanFile>,
+}
+
+/// The hunks of one file in an [`EditPlanView`].
+#[derive(Serialize, JsonSchema)]
+pub struct PlanFile {
+    pub path: PathKey,
+    pub hunks: Vec<PlanHunk>,
+}
+
+/// A planned edit with its id for `apply_edit_hunks`.
+#[derive(Serialize, JsonSchema)]
+pub struct PlanHunk {
+    pub id: usize,
+    #[serde(flatten)]
+    pub edit: EditPreview,
+}
+
+/// Plan a find-and-replace like `replace_in_files` without staging any of
+/// it, and keep the plan for `apply_edit_hunks`.
+///
+/// Only the newest 16 plans are kept; older ids expire. Returns the plan id.
+#[wasm_bindgen]
+pub fn create_edit_plan(
+    pattern: String,
+    replacement: String,
+    options: JsValue,
+    handle: JsValue,
+) -> Result<String, JsValue> {
+    let abort_flag = abort_flag_from_js(&handle)?;
+    let request = SearchOptions::from_js(options)?.to_edit_request(pattern, replacement);
+    let plan = Orchestrator::new()
+        .create_edit_plan(request, &abort_flag)
+        .map_err(|e| core_err("Replace failed", &e))?;
+    Ok(with_edit_plans(|plans| plans.insert(plan)))
+}
+
+/// The hunks of plan `plan_id` grouped by file, as
+/// `{ hunkCount, files: [{ path, hunks }] }`. Each hunk is a
+/// `replace_in_files` item with an `id` for `apply_edit_hunks`.
+#[wasm_bindgen]
+pub fn get_edit_plan(plan_id: String) -> Result<JsValue, JsValue> {
+    with_edit_plans(|plans| {
+        let plan = plans
+            .get_mut(&plan_id)
+            .ok_or_else(|| js_err!("Unknown or expired edit plan '{}'", plan_id))?;
+        let files = plan
+            .files()
+            .iter()
+            .map(|file| PlanFile {
+                path: file.path.clone(),
+                hunks: file
+                    .hunk_ids()
+                    .zip(&file.items)
+                    .map(|(id, item)| PlanHunk {
+                        id,
+                        edit: EditPreview::from(item),
+                    })
+                    .collect(),
+            })
+            .collect();
+        to_js(&EditPlanView {
+            hunk_count: plan.hunk_count(),
+            files,
+        })
+    })
+}
+
+/// Stage the hunks of plan `plan_id` named by `hunk_ids`, as one undo
+/// step, and discard the plan. Returns the applied hunks in the shape of
+/// `replace_in_files`.
+///
+/// Fails with `code: "content_changed"`, staging nothing and keeping the
+/// plan, if a selected file changed since the plan was made; with
+/// `"unknown_hunk"` for an id not in the plan.
+#[wasm_bindgen]
+pub fn apply_edit_hunks(plan_id: String, hunk_ids: Vec<usize>) -> Result<JsValue, JsValue> {
+    let response = with_edit_plans(|plans| {
+        let plan = plans
+            .get_mut(&plan_id)
+            .ok_or_else(|| js_err!("Unknown or expired edit plan '{}'", plan_id))?;
+        let response = Orchestrator::new()
+            .apply_edit_plan(plan, &hunk_ids)
+            .map_err(|e| core_err("Failed to apply edit hunks", &e))?;
+        plans.take(&plan_id);
+        Ok::<_, JsValue>(response)
+    })?;
+
+    let mut files: Vec<&str> = response.items.iter().map(|i| i.path.as_str()).collect();
+    files.dedup();
+    to_js(&ReplaceResult {
+        items: response.items.iter().map(EditPreview::from).collect(),
+        files_changed: files.len(),
+        applied: true,
+    })
+}
+
+/// Drop plan `plan_id` without staging it. Returns whether it was kept.
+#[wasm_bindgen]
+pub fn discard_edit_plan(plan_id: String) -> bool {
+    with_edit_plans(|plans| plans.take(&plan_id)).is_some()
+}
@@ -0,0 +1,52 @@
+use crate::globals::with_elevation;
+use crate::js_err;
+use conduit_core::fs::{ElevatedScope, ElevationRecord};
+use js_sys::Function;
+use wasm_bindgen::prelude::*;
+
+/// Mint a token that lets `with_elevated` overwrite read-only files.
+///
+/// `reason` is stored with the token and copied into every audit record.
+#[wasm_bindgen]
+pub fn mint_elevated_token(reason: String) -> String {
+    let nonce = (js_sys::Math::random() * u64::MAX as f64) as u64;
+    with_elevation(|tokens| tokens.mint(reason, nonce))
+}
+
+/// Revoke a token. Returns whether it existed.
+#[wasm_bindgen]
+pub fn revoke_elevated_token(token: String) -> bool {
+    with_elevation(|tokens| tokens.revoke(&token))
+}
+
+/// Call `op` with the read-only guard lifted, returning its result.
+///
+/// Only writes made synchronously inside `op` are elevated. The run is
+/// recorded in the audit log whether or not `op` throws.
+#[wasm_bindgen]
+pub fn with_elevated(token: String, op: &Function) -> Result<JsValue, JsValue> {
+    let reason = with_elevation(|tokens| tokens.reason(&token).map(str::to_owned))
+        .map_err(|e| js_err!("{}", e))?;
+
+    let scope = ElevatedScope::enter();
+    let result = op.call0(&JsValue::NULL);
+    let paths = scope.finish();
+
+    let record = ElevationRecord {
+        reason,
+        at: js_sys::Date::now() as i64,
+        paths,
+        succeeded: result.is_ok(),
+    };
+    with_elevation(|tokens| tokens.record(record));
+
+    result
+}
+
+/// Audit log of elevated runs, oldest first.
+#[wasm_bindgen]
+pub fn get_elevation_audit() -> Result<JsValue, JsValue> {
+    let records: Vec<ElevationRecord> = with_elevation(|tokens| tokens.audit().cloned().collect());
+    serde_wasm_bindgen::to_value(&records)
+        .map_err(|e| js_err!("Failed to serialize elevation audit: {}", e))
+}
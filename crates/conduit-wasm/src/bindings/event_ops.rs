@@ -0,0 +1,35 @@
+//! Index change notifications pushed to a host callback.
+
+use crate::globals::{get_index_manager, with_index_event_callback};
+use conduit_core::fs::{EventListener, IndexEvent};
+use js_sys::Function;
+use serde::Serialize;
+use std::sync::Arc;
+use wasm_bindgen::prelude::*;
+
+/// Call `callback` with each index event as it happens, instead of polling
+/// `get_modified_files_summary`. Replaces any earlier callback; `null`
+/// unsubscribes.
+///
+/// Events of one operation arrive together once it has finished, so the
+/// callback may read the index. Exceptions it throws are ignored.
+#[wasm_bindgen]
+pub fn on_index_event(callback: Option<Function>) {
+    let listener: Option<EventListener> = callback
+        .is_some()
+        .then(|| Arc::new(dispatch) as EventListener);
+    with_index_event_callback(|c| *c = callback);
+    get_index_manager().set_event_listener(listener);
+}
+
+fn dispatch(event: &IndexEvent) {
+    // Cloned out so the callback can subscribe again while it runs.
+    let Some(callback) = with_index_event_callback(|c| c.clone()) else {
+        return;
+    };
+    let serializer = serde_wasm_bindgen::Serializer::json_compatible();
+    if let Ok(value) = event.serialize(&serializer) {
+        // A failing callback must not fail the change that was already made.
+        let _ = callback.call1(&JsValue::NULL, &value);
+    }
+}
@@ -1,14 +1,119 @@
-use crate::globals::create_path_key;
+use crate::globals::{create_path_key, get_index_manager};
 use crate::js_err;
 use crate::orchestrator::Orchestrator;
-use crate::utils::{parse_file_operations, JsObjectBuilder};
+use crate::utils::{core_err, parse_file_operations, path_keys_from_list, to_js};
 use conduit_core::{
-    BatchCopyRequest, BatchMoveRequest, CreateRequest, CreateResponse, CreateTool, DeleteRequest,
-    DeleteResponse, DeleteTool, FileOperation, MoveFilesTool,
+    BatchCopyRequest, BatchMoveRequest, BatchOperationResponse, ConflictPolicy, CreateRequest,
+    CreateResponse, CreateTool, DeleteDirRequest, DeleteRequest, DeleteTool, DestinationConflict,
+    DirOperationResponse, FileOperation, FileRewrite, MoveDirRequest, MoveFilesTool,
+    MoveTreeRequest, OperationFailure, PathKey,
 };
 use js_sys::{Array, Uint8Array};
+use schemars::JsonSchema;
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
 
+/// Result of `overwrite_file`.
+#[derive(Serialize, JsonSchema)]
+pub struct OverwriteResult {
+    #[serde(flatten)]
+    pub file: CreateResponse,
+    /// New content hash, XXH3-64 as 16 hex digits.
+    pub hash: Option<String>,
+}
+
+/// Result of `delete_files_in_list`.
+#[derive(Serialize, JsonSchema)]
+pub struct DeleteListResult {
+    pub deleted: usize,
+    /// Paths that did not exist.
+    pub missing: Vec<PathKey>,
+}
+
+/// Result of `copy_file` and `move_file`.
+#[derive(Serialize, JsonSchema)]
+pub struct PlacedFile {
+    /// Where the file went.
+    pub dst: PathKey,
+    /// Whether an existing file was replaced.
+    pub overwritten: bool,
+}
+
+/// Result of `copy_files` and `move_files`.
+#[derive(Serialize, JsonSchema)]
+pub struct BatchResult {
+    /// Operations applied.
+    pub count: usize,
+    /// Applied operations whose destination already existed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<ConflictItem>,
+    /// Per-operation outcomes; only with `continueOnError`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub results: Vec<BatchItemResult>,
+}
+
+/// A copy or move whose destination already existed.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictItem {
+    pub src: PathKey,
+    pub dst: PathKey,
+    /// Where the file went instead; absent if `dst` was overwritten.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub renamed_to: Option<PathKey>,
+}
+
+impl From<&DestinationConflict> for ConflictItem {
+    fn from(conflict: &DestinationConflict) -> Self {
+        ConflictItem {
+            src: conflict.src.clone(),
+            dst: conflict.dst.clone(),
+            renamed_to: conflict.renamed_to.clone(),
+        }
+    }
+}
+
+/// Result of `move_directory` and `copy_directory`.
+#[derive(Serialize, JsonSchema)]
+pub struct DirResult {
+    /// One operation per file, to where it went.
+    pub operations: Vec<FileOperation>,
+    /// Files whose destination already existed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<ConflictItem>,
+}
+
+impl From<DirOperationResponse> for DirResult {
+    fn from(response: DirOperationResponse) -> Self {
+        DirResult {
+            conflicts: response.conflicts.iter().map(ConflictItem::from).collect(),
+            operations: response.operations,
+        }
+    }
+}
+
+/// Result of `move_tree`.
+#[derive(Serialize, JsonSchema)]
+pub struct MoveTreeResult {
+    /// Files moved, to where they went.
+    pub moved: Vec<FileOperation>,
+    /// Rewritten files at their new paths.
+    pub rewrites: Vec<FileRewrite>,
+    /// Files whose destination already existed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conflicts: Vec<ConflictItem>,
+}
+
+/// Outcome of one operation in a `continueOnError` batch.
+#[derive(Serialize, JsonSchema)]
+pub struct BatchItemResult {
+    pub src: PathKey,
+    pub dst: PathKey,
+    pub ok: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<OperationFailure>,
+}
+
 #[wasm_bindgen]
 pub fn create_index_file(
     path: String,
@@ -27,21 +132,41 @@ pub fn create_index_file(
     let mut orchestrator = Orchestrator::new();
     let response = orchestrator
         .run_create(request)
-        .map_err(|e| js_err!("Failed to create '{}': {}", path, e))?;
+        .map_err(|e| core_err(format_args!("Failed to create '{path}'"), &e))?;
 
-    let CreateResponse {
-        path: response_path,
-        size,
-        created,
-    } = response;
+    to_js(&response)
+}
 
-    let obj = JsObjectBuilder::new()
-        .set("path", JsValue::from_str(response_path.as_str()))?
-        .set("size", JsValue::from_f64(size as f64))?
-        .set("created", JsValue::from_bool(created))?
-        .build();
+/// Replace a file's staged content, failing if its active content no longer
+/// hashes to `expected_active_hash` (hex XXH3-64, or null for "must not
+/// exist"). Returns `{ path, size, created, hash }` with the new hash.
+#[wasm_bindgen]
+pub fn overwrite_file(
+    path: String,
+    content: &Uint8Array,
+    expected_active_hash: Option<String>,
+) -> Result<JsValue, JsValue> {
+    let path_key = create_path_key(&path).map_err(|e| js_err!("Invalid path '{}': {}", path, e))?;
+    let expected = expected_active_hash
+        .map(|h| u64::from_str_radix(&h, 16).map_err(|_| js_err!("Invalid hash '{}'", h)))
+        .transpose()?;
+    let response = Orchestrator::new()
+        .handle_overwrite(path_key, content.to_vec(), expected)
+        .map_err(|e| core_err(format_args!("Failed to overwrite '{path}'"), &e))?;
+    let hash = get_index_manager()
+        .staged_index()
+        .ok()
+        .and_then(|index| {
+            index
+                .get_file(&response.path)
+                .and_then(|e| e.content_hash())
+        })
+        .map(|h| format!("{:016x}", h));
 
-    Ok(obj)
+    to_js(&OverwriteResult {
+        file: response,
+        hash,
+    })
 }
 
 #[wasm_bindgen]
@@ -54,21 +179,49 @@ pub fn delete_file(path: String) -> Result<JsValue, JsValue> {
         .run_delete(request)
         .map_err(|e| js_err!("Failed to delete '{}': {}", path, e))?;
 
-    let DeleteResponse {
-        path: response_path,
-        existed,
-    } = response;
+    to_js(&response)
+}
+
+/// Delete every file in a newline-delimited path list in one transaction.
+///
+/// Returns `{ deleted, missing }`, where `missing` lists paths that did not exist.
+#[wasm_bindgen]
+pub fn delete_files_in_list(paths: &Uint8Array) -> Result<JsValue, JsValue> {
+    let keys = path_keys_from_list(paths)?;
+    let total = keys.len();
 
-    let obj = JsObjectBuilder::new()
-        .set("path", JsValue::from_str(response_path.as_str()))?
-        .set("existed", JsValue::from_bool(existed))?
-        .build();
+    let orchestrator = Orchestrator::new();
+    let missing = orchestrator
+        .handle_delete_many(keys)
+        .map_err(|e| js_err!("Failed to delete files: {}", e))?;
 
-    Ok(obj)
+    to_js(&DeleteListResult {
+        deleted: total - missing.len(),
+        missing,
+    })
 }
 
+/// Flag staged files as hidden (or visible) to default listings and searches.
+///
+/// Dotfiles are always hidden by convention; this flags other files.
+/// Returns the number of paths that exist in the staged index.
 #[wasm_bindgen]
-pub fn copy_file(src: String, dst: String) -> Result<JsValue, JsValue> {
+pub fn set_files_hidden(paths: Vec<String>, hidden: bool) -> Result<u32, JsValue> {
+    let keys = paths
+        .iter()
+        .map(|p| create_path_key(p).map_err(|e| js_err!("Invalid path '{}': {}", p, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let found = get_index_manager()
+        .set_hidden(&keys, hidden)
+        .map_err(|e| js_err!("Failed to set hidden flag: {}", e))?;
+    Ok(found as u32)
+}
+
+/// `on_conflict` is `"error"` (the default), `"overwrite"` or `"rename"`;
+/// the returned `dst` is where the file went.
+#[wasm_bindgen]
+pub fn copy_file(src: String, dst: String, on_conflict: JsValue) -> Result<JsValue, JsValue> {
     let src_key =
         create_path_key(&src).map_err(|e| js_err!("Invalid source path '{}': {}", src, e))?;
     let dst_key =
@@ -79,25 +232,30 @@ pub fn copy_file(src: String, dst: String) -> Result<JsValue, JsValue> {
             src: src_key,
             dst: dst_key.clone(),
         }],
+        continue_on_error: false,
+        on_conflict: parse_conflict_policy(on_conflict)?,
     };
 
     let mut orchestrator = Orchestrator::new();
-    orchestrator
+    let response = orchestrator
         .run_copy_files(request)
-        .map_err(|e| js_err!("Failed to copy file: {}", e))?;
+        .map_err(|e| core_err("Failed to copy file", &e))?;
 
-    let obj = JsObjectBuilder::new()
-        .set("dst", JsValue::from(dst_key.as_str()))?
-        .build();
-    Ok(obj)
+    placed_file_to_js(&response, dst_key)
 }
 
 #[wasm_bindgen]
-pub fn copy_files(operations: Array) -> Result<JsValue, JsValue> {
+pub fn copy_files(
+    operations: Array,
+    continue_on_error: Option<bool>,
+    on_conflict: JsValue,
+) -> Result<JsValue, JsValue> {
     let file_operations = parse_file_operations(&operations)?;
 
     let request = BatchCopyRequest {
         operations: file_operations,
+        continue_on_error: continue_on_error.unwrap_or(false),
+        on_conflict: parse_conflict_policy(on_conflict)?,
     };
 
     let mut orchestrator = Orchestrator::new();
@@ -105,15 +263,13 @@ pub fn copy_files(operations: Array) -> Result<JsValue, JsValue> {
         .run_copy_files(request)
         .map_err(|e| js_err!("Failed to copy files: {}", e))?;
 
-    let obj = JsObjectBuilder::new()
-        .set("count", JsValue::from(response.count as u32))?
-        .build();
-
-    Ok(obj)
+    batch_response_to_js(&response)
 }
 
+/// `on_conflict` is `"error"` (the default), `"overwrite"` or `"rename"`;
+/// the returned `dst` is where the file went.
 #[wasm_bindgen]
-pub fn move_file(src: String, dst: String) -> Result<JsValue, JsValue> {
+pub fn move_file(src: String, dst: String, on_conflict: JsValue) -> Result<JsValue, JsValue> {
     let src_key =
         create_path_key(&src).map_err(|e| js_err!("Invalid source path '{}': {}", src, e))?;
     let dst_key =
@@ -124,25 +280,30 @@ pub fn move_file(src: String, dst: String) -> Result<JsValue, JsValue> {
             src: src_key,
             dst: dst_key.clone(),
         }],
+        continue_on_error: false,
+        on_conflict: parse_conflict_policy(on_conflict)?,
     };
 
     let mut orchestrator = Orchestrator::new();
-    orchestrator
+    let response = orchestrator
         .run_move_files(request)
         .map_err(|e| js_err!("Failed to move file: {}", e))?;
 
-    let obj = JsObjectBuilder::new()
-        .set("dst", JsValue::from(dst_key.as_str()))?
-        .build();
-    Ok(obj)
+    placed_file_to_js(&response, dst_key)
 }
 
 #[wasm_bindgen]
-pub fn move_files(operations: Array) -> Result<JsValue, JsValue> {
+pub fn move_files(
+    operations: Array,
+    continue_on_error: Option<bool>,
+    on_conflict: JsValue,
+) -> Result<JsValue, JsValue> {
     let file_operations = parse_file_operations(&operations)?;
 
     let request = BatchMoveRequest {
         operations: file_operations,
+        continue_on_error: continue_on_error.unwrap_or(false),
+        on_conflict: parse_conflict_policy(on_conflict)?,
     };
 
     let mut orchestrator = Orchestrator::new();
@@ -150,9 +311,115 @@ pub fn move_files(operations: Array) -> Result<JsValue, JsValue> {
         .run_move_files(request)
         .map_err(|e| js_err!("Failed to move files: {}", e))?;
 
-    let obj = JsObjectBuilder::new()
-        .set("count", JsValue::from(response.count as u32))?
-        .build();
+    batch_response_to_js(&response)
+}
+
+/// Move every file under the directory `from` to `to` in one transaction.
+///
+/// With `rewrite_references`, import paths pointing into or out of the moved
+/// tree are rewritten across the index. With `dry_run`, nothing is staged
+/// and the result previews what would change. `on_conflict` applies to each
+/// file whose destination exists, as in `move_file`.
+/// Returns `{ moved: [{ src, dst }], rewrites: [{ path, edits: [{ line, before, after }] }], conflicts? }`.
+#[wasm_bindgen]
+pub fn move_tree(
+    from: String,
+    to: String,
+    rewrite_references: Option<bool>,
+    dry_run: Option<bool>,
+    on_conflict: JsValue,
+) -> Result<JsValue, JsValue> {
+    let request = MoveTreeRequest {
+        from: from.clone(),
+        to: to.clone(),
+        rewrite_references: rewrite_references.unwrap_or(false),
+        dry_run: dry_run.unwrap_or(false),
+        on_conflict: parse_conflict_policy(on_conflict)?,
+    };
+
+    let mut orchestrator = Orchestrator::new();
+    let response = orchestrator
+        .run_move_tree(request)
+        .map_err(|e| js_err!("Failed to move '{}' to '{}': {}", from, to, e))?;
+
+    to_js(&MoveTreeResult {
+        conflicts: response.conflicts.iter().map(ConflictItem::from).collect(),
+        moved: response.moved,
+        rewrites: response.rewrites,
+    })
+}
+
+/// Delete every staged file under the directory `path` in one transaction.
+/// Returns `{ deleted: string[] }`.
+#[wasm_bindgen]
+pub fn delete_directory(path: String) -> Result<JsValue, JsValue> {
+    let response = Orchestrator::new()
+        .run_delete_dir(DeleteDirRequest { path: path.clone() })
+        .map_err(|e| js_err!("Failed to delete directory '{}': {}", path, e))?;
+
+    to_js(&response)
+}
+
+/// Move every staged file under `from` to `to` in one transaction.
+/// `on_conflict` applies to each file whose destination exists.
+/// Returns `{ operations: [{ src, dst }], conflicts? }`.
+#[wasm_bindgen]
+pub fn move_directory(from: String, to: String, on_conflict: JsValue) -> Result<JsValue, JsValue> {
+    let response = Orchestrator::new()
+        .run_move_dir(MoveDirRequest {
+            from: from.clone(),
+            to: to.clone(),
+            on_conflict: parse_conflict_policy(on_conflict)?,
+        })
+        .map_err(|e| js_err!("Failed to move '{}' to '{}': {}", from, to, e))?;
+    to_js(&DirResult::from(response))
+}
+
+/// Copy every staged file under `from` to `to` in one transaction.
+/// `on_conflict` applies to each file whose destination exists.
+/// Returns `{ operations: [{ src, dst }], conflicts? }`.
+#[wasm_bindgen]
+pub fn copy_directory(from: String, to: String, on_conflict: JsValue) -> Result<JsValue, JsValue> {
+    let response = Orchestrator::new()
+        .run_copy_dir(MoveDirRequest {
+            from: from.clone(),
+            to: to.clone(),
+            on_conflict: parse_conflict_policy(on_conflict)?,
+        })
+        .map_err(|e| core_err(format_args!("Failed to copy '{from}' to '{to}'"), &e))?;
+    to_js(&DirResult::from(response))
+}
+
+/// Where the single file of `response` went, given it was sent to `dst`.
+fn placed_file_to_js(response: &BatchOperationResponse, dst: PathKey) -> Result<JsValue, JsValue> {
+    let conflict = response.conflicts.first();
+    to_js(&PlacedFile {
+        dst: conflict.and_then(|c| c.renamed_to.clone()).unwrap_or(dst),
+        overwritten: conflict.is_some_and(|c| c.renamed_to.is_none()),
+    })
+}
+
+fn parse_conflict_policy(value: JsValue) -> Result<ConflictPolicy, JsValue> {
+    if value.is_undefined() || value.is_null() {
+        return Ok(ConflictPolicy::default());
+    }
+    serde_wasm_bindgen::from_value(value).map_err(|e| js_err!("Invalid conflict policy: {}", e))
+}
 
-    Ok(obj)
+/// Convert a batch response, including per-operation results when present.
+fn batch_response_to_js(response: &BatchOperationResponse) -> Result<JsValue, JsValue> {
+    to_js(&BatchResult {
+        count: response.count,
+        conflicts: response.conflicts.iter().map(ConflictItem::from).collect(),
+        results: response
+            .results
+            .iter()
+            .map(|result| BatchItemResult {
+                src: result.src.clone(),
+                dst: result.dst.clone(),
+                ok: result.error.is_none(),
+                error: result.error.clone(),
+            })
+            .collect(),
+    })
 }
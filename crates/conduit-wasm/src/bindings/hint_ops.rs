@@ -0,0 +1,76 @@
+use crate::globals::{create_path_key, get_index_manager, with_hints};
+use crate::js_err;
+use crate::utils::to_js;
+use conduit_core::fs::HintIntent;
+use js_sys::Date;
+use schemars::JsonSchema;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// Milliseconds `hint_paths` spends warming before returning.
+const DEFAULT_HINT_BUDGET_MS: f64 = 4.0;
+
+/// Progress of warming hinted paths.
+#[derive(Serialize, JsonSchema)]
+pub struct HintProgress {
+    /// Paths warmed by this call.
+    pub warmed: usize,
+    /// Paths still queued.
+    pub pending: usize,
+}
+
+/// Warm queued paths for up to `budget_ms`, returning `{ warmed, pending }`.
+fn drain(budget_ms: f64) -> Result<JsValue, JsValue> {
+    let deadline = Date::now() + budget_ms.max(0.0);
+    let manager = get_index_manager();
+    let (warmed, pending) = with_hints(|queue| {
+        queue
+            .drain(manager, || Date::now() < deadline)
+            .map(|warmed| (warmed, queue.len()))
+    })
+    .map_err(|e| js_err!("Failed to warm hinted paths: {}", e))?;
+
+    to_js(&HintProgress { warmed, pending })
+}
+
+/// Tell conduit which files are about to be used, so their caches are
+/// built before the first real operation.
+///
+/// `intent` is `"will-read"`, `"will-edit"` or `"will-search"`. Paths are
+/// queued and warmed for up to `budget_ms` (default 4) before returning;
+/// call `drain_hints` from idle time to finish the rest.
+#[wasm_bindgen]
+pub fn hint_paths(
+    paths: Vec<String>,
+    intent: String,
+    budget_ms: Option<f64>,
+) -> Result<JsValue, JsValue> {
+    let intent: HintIntent =
+        serde_wasm_bindgen::from_value(JsValue::from_str(&intent)).map_err(|_| {
+            js_err!(
+                "Invalid intent '{}': expected will-read, will-edit or will-search",
+                intent
+            )
+        })?;
+    let keys = paths
+        .iter()
+        .map(|p| create_path_key(p).map_err(|e| js_err!("Invalid path '{}': {}", p, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    with_hints(|queue| queue.push(keys, intent));
+    drain(budget_ms.unwrap_or(DEFAULT_HINT_BUDGET_MS))
+}
+
+/// Continue warming hinted paths for up to `budget_ms`.
+///
+/// Meant for `requestIdleCallback`, passing the time remaining.
+#[wasm_bindgen]
+pub fn drain_hints(budget_ms: f64) -> Result<JsValue, JsValue> {
+    drain(budget_ms)
+}
+
+/// Drop every queued hint.
+#[wasm_bindgen]
+pub fn clear_hints() {
+    with_hints(|queue| queue.clear());
+}
@@ -0,0 +1,71 @@
+use crate::bindings::abort_ops::abort_flag_from_js;
+use crate::bindings::search_ops::{run_search, SearchOptions};
+use crate::globals::{host_defaults, with_search_history};
+use crate::js_err;
+use crate::orchestrator::Orchestrator;
+use crate::utils::core_err;
+use conduit_core::tools::SearchHistory;
+use js_sys::Array;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// Recent searches (newest first) and saved searches, as
+/// `{ recent: FindRequest[], saved: Record<string, FindRequest> }`.
+///
+/// Store the value and pass it to `restore_search_history` after a reload.
+#[wasm_bindgen]
+pub fn get_search_history() -> Result<JsValue, JsValue> {
+    with_search_history(|h| h.serialize(&serde_wasm_bindgen::Serializer::json_compatible()))
+        .map_err(|e| js_err!("Failed to serialize search history: {}", e))
+}
+
+/// Replace the search history with one from `get_search_history`.
+#[wasm_bindgen]
+pub fn restore_search_history(history: JsValue) -> Result<(), JsValue> {
+    let history: SearchHistory = serde_wasm_bindgen::from_value(history)
+        .map_err(|e| js_err!("Invalid search history: {}", e))?;
+    with_search_history(|h| h.restore(history));
+    Ok(())
+}
+
+/// Forget recent searches; saved searches are kept.
+#[wasm_bindgen]
+pub fn clear_recent_searches() {
+    with_search_history(SearchHistory::clear_recent);
+}
+
+/// Save a search as `name`, with options as for `search_files_with_options`.
+///
+/// Unset options are resolved against the `configure` defaults now, so the
+/// saved search keeps behaving the same if the defaults change. Returns
+/// `true` if an existing saved search was replaced.
+#[wasm_bindgen]
+pub fn save_search(name: String, pattern: String, options: JsValue) -> Result<bool, JsValue> {
+    let request = SearchOptions::from_js(options)?.to_find_request(pattern);
+    Ok(with_search_history(|h| h.save(&name, request)))
+}
+
+/// Remove a saved search. Returns whether it existed.
+#[wasm_bindgen]
+pub fn remove_saved_search(name: String) -> bool {
+    with_search_history(|h| h.remove_saved(&name))
+}
+
+/// Run the saved search `name` and record it as a recent search.
+///
+/// Results have the shape of `search_files_with_options`. `limit` falls
+/// back to the configured `maxResults`.
+#[wasm_bindgen]
+pub fn run_saved_search(
+    name: String,
+    limit: Option<usize>,
+    handle: JsValue,
+) -> Result<Array, JsValue> {
+    let abort_flag = abort_flag_from_js(&handle)?;
+    let request = with_search_history(|h| h.saved(&name).cloned())
+        .map_err(|e| core_err("Failed to run saved search", &e))?;
+    let limit = host_defaults().result_limit(limit);
+    let results = run_search(request.clone(), limit, Orchestrator::new(), &abort_flag)?;
+    with_search_history(|h| h.record(&request));
+    Ok(results)
+}
@@ -0,0 +1,181 @@
+//! Metadata-only loading, with content fetched from the host on demand.
+
+use crate::bindings::staging_ops::{stage_files, IncomingFile, MetadataOnly};
+use crate::globals::{create_path_key, get_index_manager, with_content_provider};
+use crate::js_err;
+use crate::utils::core_err;
+use conduit_core::fs::HydrationUsage;
+use conduit_core::{Error, PathKey};
+use js_sys::{Array, Boolean, Function, Promise, Uint8Array};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::sync::Arc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
+
+/// Register the function conduit calls to fetch a metadata-only file.
+///
+/// It receives the normalized path and returns (or resolves to) a
+/// `Uint8Array` or string with the file's content, or `null` if the file
+/// is gone. A provider that returns content synchronously also serves
+/// reads of unhydrated files on demand.
+#[wasm_bindgen]
+pub fn register_content_provider(provider: Function) {
+    with_content_provider(|p| *p = Some(provider));
+}
+
+/// Stage a batch of files without content, as one batch of a load between
+/// `begin_file_load` and `commit_file_load`.
+///
+/// Searches skip these files, counting them in `skippedUnhydrated`, until
+/// `hydrate_files` or a read fetches them.
+/// Returns the same report as `add_files_to_staging`.
+#[wasm_bindgen]
+pub fn load_file_metadata_batch(
+    paths: Vec<String>,
+    sizes: Vec<f64>,
+    mtimes: Vec<f64>,
+    permissions: Vec<Boolean>,
+) -> Result<JsValue, JsValue> {
+    let len = paths.len();
+    if sizes.len() != len || mtimes.len() != len || permissions.len() != len {
+        return Err(js_err!(
+            "Array length mismatch: paths={}, sizes={}, mtimes={}, permissions={}",
+            paths.len(),
+            sizes.len(),
+            mtimes.len(),
+            permissions.len()
+        ));
+    }
+
+    let files = paths
+        .into_iter()
+        .zip(sizes)
+        .zip(mtimes)
+        .zip(permissions)
+        .map(|(((path, size), mtime), editable)| IncomingFile {
+            path,
+            content: MetadataOnly(size.max(0.0) as usize),
+            mtime,
+            editable: editable.value_of(),
+            text: None,
+        });
+    let report = stage_files(files, len)?;
+    serde_wasm_bindgen::to_value(&report)
+        .map_err(|e| js_err!("Failed to serialize load report: {}", e))
+}
+
+/// Result of `hydrate_files`.
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HydrateReport {
+    /// Paths given content by this call.
+    hydrated: Vec<String>,
+    /// Paths the provider returned `null` for.
+    unavailable: Vec<String>,
+    usage: HydrationUsage,
+}
+
+/// Fetch content for every path in `paths` that is indexed without it,
+/// calling the provider for all of them at once.
+///
+/// Paths that already have content count as just used, so they are the
+/// last to be evicted; unknown paths are ignored. Once hydrated content
+/// exceeds `hydrationBudget`, the least recently used files are evicted
+/// back to metadata only.
+#[wasm_bindgen]
+pub async fn hydrate_files(paths: Vec<String>) -> Result<JsValue, JsValue> {
+    let provider = with_content_provider(|p| p.clone()).ok_or_else(|| {
+        js_err!("No content provider registered; call register_content_provider first")
+    })?;
+    let manager = get_index_manager();
+
+    let mut keys = Vec::new();
+    let pending = Array::new();
+    for path in &paths {
+        let key = create_path_key(path).map_err(|e| core_err("Invalid path", &e))?;
+        if manager.needs_content(&key) {
+            let fetched = provider.call1(&JsValue::NULL, &JsValue::from_str(key.as_str()))?;
+            pending.push(&fetched);
+            keys.push(key);
+        }
+    }
+
+    let fetched: Array = JsFuture::from(Promise::all(&pending)).await?.into();
+    let mut hydrated = Vec::new();
+    let mut unavailable = Vec::new();
+    // Pinned so a batch over the budget does not evict its own files
+    // before the report lists them.
+    manager.with_hydration_pinned(|| -> Result<(), JsValue> {
+        for (key, content) in keys.into_iter().zip(fetched.iter()) {
+            let Some(bytes) = provided_bytes(&key, &content)? else {
+                unavailable.push(key.as_str().to_string());
+                continue;
+            };
+            // A file edited or reloaded while the fetch was in flight keeps
+            // its newer content.
+            if manager
+                .hydrate(&key, Arc::from(bytes))
+                .map_err(|e| core_err(format_args!("Failed to hydrate '{}'", key.as_str()), &e))?
+            {
+                hydrated.push(key.as_str().to_string());
+            }
+        }
+        Ok(())
+    })?;
+    let report = HydrateReport {
+        hydrated,
+        unavailable,
+        usage: manager.hydration_usage(),
+    };
+    serde_wasm_bindgen::to_value(&report)
+        .map_err(|e| js_err!("Failed to serialize hydration report: {}", e))
+}
+
+/// Hydrate `key` now if the registered provider returns its content
+/// synchronously. Returns whether it was hydrated; with no provider, or a
+/// provider returning a promise or `null`, nothing changes.
+pub(crate) fn fetch_content(key: &PathKey) -> conduit_core::Result<bool> {
+    let Some(provider) = with_content_provider(|p| p.clone()) else {
+        return Ok(false);
+    };
+    let provider_failed = |e: JsValue| {
+        let reason = e
+            .as_string()
+            .unwrap_or_else(|| "content provider failed".into());
+        Error::NotHydrated(format!("{}: {}", key.as_str(), reason))
+    };
+    let content = provider
+        .call1(&JsValue::NULL, &JsValue::from_str(key.as_str()))
+        .map_err(provider_failed)?;
+    if content.is_instance_of::<Promise>() {
+        return Ok(false);
+    }
+    match provided_bytes(key, &content).map_err(provider_failed)? {
+        Some(bytes) => get_index_manager().hydrate(key, Arc::from(bytes)),
+        None => Ok(false),
+    }
+}
+
+/// Content the provider returned for `key`, or `None` for `null`.
+fn provided_bytes(key: &PathKey, content: &JsValue) -> Result<Option<Vec<u8>>, JsValue> {
+    if content.is_null() || content.is_undefined() {
+        Ok(None)
+    } else if let Some(text) = content.as_string() {
+        Ok(Some(text.into_bytes()))
+    } else if let Some(array) = content.dyn_ref::<Uint8Array>() {
+        Ok(Some(array.to_vec()))
+    } else {
+        Err(js_err!(
+            "Content provider must return a Uint8Array, string or null for '{}'",
+            key.as_str()
+        ))
+    }
+}
+
+/// Bytes and files of hydrated content held, and the budget.
+#[wasm_bindgen]
+pub fn get_hydration_usage() -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(&get_index_manager().hydration_usage())
+        .map_err(|e| js_err!("Failed to serialize hydration usage: {}", e))
+}
@@ -1,82 +1,103 @@
 use crate::globals::create_path_key;
 use crate::js_err;
 use crate::orchestrator::Orchestrator;
-use crate::utils::{build_line_operation_response, get_string_field, get_usize_field};
+use crate::utils::{build_line_operation_response, core_err};
+use conduit_core::tools::{EditPolicy, EditPrecondition};
 use conduit_core::{
+    AnchoredEditRequest, AnchoredEditTool, AppendPosition, AppendRequest, AppendTool,
     DeleteLinesRequest, DeleteLinesTool, InsertLinesRequest, InsertLinesTool, InsertOperation,
     InsertPosition, ReplaceLinesRequest, ReplaceLinesTool,
 };
 use js_sys::Array;
+use schemars::JsonSchema;
+use serde::Deserialize;
 use wasm_bindgen::prelude::*;
 
+fn parse_edit_policy(policy: JsValue) -> Result<EditPolicy, JsValue> {
+    if policy.is_undefined() || policy.is_null() {
+        return Ok(EditPolicy::default());
+    }
+    serde_wasm_bindgen::from_value(policy).map_err(|e| js_err!("Invalid edit policy: {}", e))
+}
+
+/// [`EditPrecondition`] as passed from JS, with the mtime in milliseconds.
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+pub struct JsPrecondition {
+    expected_hash: Option<String>,
+    expected_mtime: Option<f64>,
+}
+
+impl From<JsPrecondition> for EditPrecondition {
+    fn from(js: JsPrecondition) -> Self {
+        EditPrecondition {
+            expected_hash: js.expected_hash,
+            expected_mtime: js.expected_mtime.map(|ms| (ms / 1000.0).floor() as i64),
+        }
+    }
+}
+
+fn parse_precondition(precondition: JsValue) -> Result<Option<EditPrecondition>, JsValue> {
+    if precondition.is_undefined() || precondition.is_null() {
+        return Ok(None);
+    }
+    let precondition: JsPrecondition = serde_wasm_bindgen::from_value(precondition)
+        .map_err(|e| js_err!("Invalid edit precondition: {}", e))?;
+    Ok(Some(precondition.into()))
+}
+
+/// One entry of `replace_lines`: `[lineNumber, content]` or
+/// `[startLine, endLine, content]`, 1-based and inclusive.
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum LineReplacement {
+    Line(usize, String),
+    Range(usize, usize, String),
+}
+
 #[wasm_bindgen]
 pub fn replace_lines(
     path: String,
     replacements: Array,
     _use_staged: bool,
+    policy: JsValue,
+    precondition: JsValue,
 ) -> Result<JsValue, JsValue> {
+    let policy = parse_edit_policy(policy)?;
+    let precondition = parse_precondition(precondition)?;
     let path_key = create_path_key(&path).map_err(|e| js_err!("Invalid path '{}': {}", path, e))?;
 
-    let mut line_replacements = Vec::new();
-    for i in 0..replacements.length() {
-        let pair = replacements.get(i);
-        if let Some(array) = pair.dyn_ref::<Array>() {
-            if array.length() == 2 {
-                let line_num = array
-                    .get(0)
-                    .as_f64()
-                    .ok_or_else(|| js_err!("Line number must be a number"))?;
-                let content = array
-                    .get(1)
-                    .as_string()
-                    .ok_or_else(|| js_err!("Line content must be a string"))?;
-
-                if line_num < 1.0 {
-                    return Err(js_err!("Line numbers must be 1-based (got {})", line_num));
-                }
-
-                line_replacements.push((line_num as usize, line_num as usize, content));
-            } else if array.length() == 3 {
-                let start_line = array
-                    .get(0)
-                    .as_f64()
-                    .ok_or_else(|| js_err!("Start line must be a number"))?;
-                let end_line = array
-                    .get(1)
-                    .as_f64()
-                    .ok_or_else(|| js_err!("End line must be a number"))?;
-                let content = array
-                    .get(2)
-                    .as_string()
-                    .ok_or_else(|| js_err!("Line content must be a string"))?;
-
-                if start_line < 1.0 || end_line < 1.0 {
-                    return Err(js_err!("Line numbers must be 1-based"));
-                }
-                if start_line > end_line {
-                    return Err(js_err!("Start line must be <= end line"));
-                }
-
-                line_replacements.push((start_line as usize, end_line as usize, content));
-            } else {
-                return Err(js_err!(
-                    "Each replacement must be [lineNumber, content] or [startLine, endLine, content]"
-                ));
-            }
-        } else {
-            return Err(js_err!("Each replacement must be an array"));
+    let replacements: Vec<LineReplacement> = serde_wasm_bindgen::from_value(replacements.into())
+        .map_err(|_| {
+            js_err!(
+                "Each replacement must be [lineNumber, content] or [startLine, endLine, content]"
+            )
+        })?;
+    let mut line_replacements = Vec::with_capacity(replacements.len());
+    for replacement in replacements {
+        let (start_line, end_line, content) = match replacement {
+            LineReplacement::Line(line, content) => (line, line, content),
+            LineReplacement::Range(start, end, content) => (start, end, content),
+        };
+        if start_line < 1 || end_line < 1 {
+            return Err(js_err!("Line numbers must be 1-based"));
         }
+        if start_line > end_line {
+            return Err(js_err!("Start line must be <= end line"));
+        }
+        line_replacements.push((start_line, end_line, content));
     }
 
     let request = ReplaceLinesRequest {
         path: path_key,
         replacements: line_replacements,
+        precondition,
     };
 
-    let mut orchestrator = Orchestrator::new();
+    let mut orchestrator = Orchestrator::new().with_edit_policy(policy);
     let response = orchestrator
         .run_replace_lines(request)
-        .map_err(|e| js_err!("Failed to replace lines in '{}': {}", path, e))?;
+        .map_err(|e| core_err(format_args!("Failed to replace lines in '{path}'"), &e))?;
 
     build_line_operation_response(&response)
 }
@@ -86,18 +107,23 @@ pub fn delete_lines(
     path: String,
     line_numbers: Vec<usize>,
     _use_staged: bool,
+    policy: JsValue,
+    precondition: JsValue,
 ) -> Result<JsValue, JsValue> {
+    let policy = parse_edit_policy(policy)?;
+    let precondition = parse_precondition(precondition)?;
     let path_key = create_path_key(&path).map_err(|e| js_err!("Invalid path '{}': {}", path, e))?;
 
     let request = DeleteLinesRequest {
         path: path_key,
         line_numbers,
+        precondition,
     };
 
-    let mut orchestrator = Orchestrator::new();
+    let mut orchestrator = Orchestrator::new().with_edit_policy(policy);
     let response = orchestrator
         .run_delete_lines(request)
-        .map_err(|e| js_err!("Failed to delete lines from '{}': {}", path, e))?;
+        .map_err(|e| core_err(format_args!("Failed to delete lines from '{path}'"), &e))?;
 
     build_line_operation_response(&response)
 }
@@ -108,7 +134,11 @@ pub fn insert_before_line(
     line_number: usize,
     content: String,
     _use_staged: bool,
+    policy: JsValue,
+    precondition: JsValue,
 ) -> Result<JsValue, JsValue> {
+    let policy = parse_edit_policy(policy)?;
+    let precondition = parse_precondition(precondition)?;
     if line_number < 1 {
         return Err(js_err!("Line number must be 1-based"));
     }
@@ -122,15 +152,14 @@ pub fn insert_before_line(
             content,
             position: InsertPosition::Before,
         }],
+        precondition,
     };
 
-    let mut orchestrator = Orchestrator::new();
+    let mut orchestrator = Orchestrator::new().with_edit_policy(policy);
     let response = orchestrator.run_insert_lines(request).map_err(|e| {
-        js_err!(
-            "Failed to insert before line {} in '{}': {}",
-            line_number,
-            path,
-            e
+        core_err(
+            format_args!("Failed to insert before line {line_number} in '{path}'"),
+            &e,
         )
     })?;
 
@@ -143,7 +172,11 @@ pub fn insert_after_line(
     line_number: usize,
     content: String,
     _use_staged: bool,
+    policy: JsValue,
+    precondition: JsValue,
 ) -> Result<JsValue, JsValue> {
+    let policy = parse_edit_policy(policy)?;
+    let precondition = parse_precondition(precondition)?;
     if line_number < 1 {
         return Err(js_err!("Line number must be 1-based"));
     }
@@ -157,67 +190,174 @@ pub fn insert_after_line(
             content,
             position: InsertPosition::After,
         }],
+        precondition,
     };
 
-    let mut orchestrator = Orchestrator::new();
+    let mut orchestrator = Orchestrator::new().with_edit_policy(policy);
     let response = orchestrator.run_insert_lines(request).map_err(|e| {
-        js_err!(
-            "Failed to insert after line {} in '{}': {}",
-            line_number,
-            path,
-            e
+        core_err(
+            format_args!("Failed to insert after line {line_number} in '{path}'"),
+            &e,
         )
     })?;
 
     build_line_operation_response(&response)
 }
 
+/// One entry of `insert_lines`, against the original line numbers.
+#[derive(Debug, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Insertion {
+    /// 1-based.
+    pub line_number: usize,
+    pub content: String,
+    pub position: Side,
+}
+
+/// Which side of its line an [`Insertion`] goes.
+#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Before,
+    After,
+}
+
 #[wasm_bindgen]
 pub fn insert_lines(
     path: String,
     insertions: Array,
     _use_staged: bool,
+    policy: JsValue,
+    precondition: JsValue,
 ) -> Result<JsValue, JsValue> {
+    let policy = parse_edit_policy(policy)?;
+    let precondition = parse_precondition(precondition)?;
     let path_key = create_path_key(&path).map_err(|e| js_err!("Invalid path '{}': {}", path, e))?;
 
-    let mut insert_operations = Vec::new();
-    for i in 0..insertions.length() {
-        let insertion = insertions.get(i);
-        if let Some(obj) = insertion.dyn_ref::<js_sys::Object>() {
-            let line_number = get_usize_field(obj, "lineNumber")?;
-            let content = get_string_field(obj, "content")?;
-            let position_str = get_string_field(obj, "position")?;
-
-            let position = match position_str.as_str() {
-                "before" => InsertPosition::Before,
-                "after" => InsertPosition::After,
-                _ => {
-                    return Err(js_err!(
-                        "Invalid position '{}', must be 'before' or 'after'",
-                        position_str
-                    ))
-                }
-            };
-
-            insert_operations.push(InsertOperation {
-                line_number,
-                content,
-                position,
-            });
-        } else {
-            return Err(js_err!("Each insertion must be an object"));
-        }
-    }
+    let insertions: Vec<Insertion> = serde_wasm_bindgen::from_value(insertions.into())
+        .map_err(|e| js_err!("Invalid insertions: {}", e))?;
+    let insert_operations = insertions
+        .into_iter()
+        .map(|insertion| InsertOperation {
+            line_number: insertion.line_number,
+            content: insertion.content,
+            position: match insertion.position {
+                Side::Before => InsertPosition::Before,
+                Side::After => InsertPosition::After,
+            },
+        })
+        .collect();
 
     let request = InsertLinesRequest {
         path: path_key,
         insertions: insert_operations,
+        precondition,
     };
 
-    let mut orchestrator = Orchestrator::new();
+    let mut orchestrator = Orchestrator::new().with_edit_policy(policy);
     let response = orchestrator
         .run_insert_lines(request)
-        .map_err(|e| js_err!("Failed to insert lines in '{}': {}", path, e))?;
+        .map_err(|e| core_err(format_args!("Failed to insert lines in '{path}'"), &e))?;
+
+    build_line_operation_response(&response)
+}
+
+/// Options for `append_to_file` and `prepend_to_file`.
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AppendOptions {
+    must_exist: bool,
+    policy: EditPolicy,
+    precondition: Option<JsPrecondition>,
+}
+
+fn append_at(
+    path: String,
+    content: String,
+    position: AppendPosition,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    let path_key = create_path_key(&path).map_err(|e| js_err!("Invalid path '{}': {}", path, e))?;
+    let options: AppendOptions = if options.is_undefined() || options.is_null() {
+        AppendOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)
+            .map_err(|e| js_err!("Invalid append options: {}", e))?
+    };
+
+    let request = AppendRequest {
+        path: path_key,
+        content,
+        position,
+        must_exist: options.must_exist,
+        precondition: options.precondition.map(Into::into),
+    };
+
+    let mut orchestrator = Orchestrator::new().with_edit_policy(options.policy);
+    let response = orchestrator
+        .run_append(request)
+        .map_err(|e| core_err(format_args!("Failed to add lines to '{path}'"), &e))?;
 
     build_line_operation_response(&response)
 }
+
+/// Add `content` as lines at the end of a file, creating the file unless
+/// `options.mustExist` is set. No need to read its line count first.
+#[wasm_bindgen]
+pub fn append_to_file(path: String, content: String, options: JsValue) -> Result<JsValue, JsValue> {
+    append_at(path, content, AppendPosition::End, options)
+}
+
+/// Add `content` as lines at the start of a file, after a shebang line if
+/// it has one. Missing files are created as in `append_to_file`.
+#[wasm_bindgen]
+pub fn prepend_to_file(
+    path: String,
+    content: String,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    append_at(path, content, AppendPosition::Start, options)
+}
+
+/// Options for `anchored_edit`.
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+pub struct AnchoredEditOptions {
+    occurrence: Option<usize>,
+    whitespace_insensitive: bool,
+    precondition: Option<JsPrecondition>,
+}
+
+/// Replace `expected_text` in a file with `replacement`. The text must
+/// appear exactly once unless `options.occurrence` picks one (1-based).
+#[wasm_bindgen]
+pub fn anchored_edit(
+    path: String,
+    expected_text: String,
+    replacement: String,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    let path_key = create_path_key(&path).map_err(|e| js_err!("Invalid path '{}': {}", path, e))?;
+    let options: AnchoredEditOptions = if options.is_undefined() || options.is_null() {
+        AnchoredEditOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)
+            .map_err(|e| js_err!("Invalid anchored edit options: {}", e))?
+    };
+
+    let request = AnchoredEditRequest {
+        path: path_key,
+        expected_text,
+        replacement,
+        occurrence: options.occurrence,
+        whitespace_insensitive: options.whitespace_insensitive,
+        precondition: options.precondition.map(Into::into),
+    };
+
+    let response = Orchestrator::new()
+        .run_anchored_edit(request)
+        .map_err(|e| core_err(format_args!("Failed to edit '{path}'"), &e))?;
+
+    serde_wasm_bindgen::to_value(&response)
+        .map_err(|e| js_err!("Failed to serialize edit result: {}", e))
+}
@@ -0,0 +1,41 @@
+use crate::globals::create_path_key;
+use crate::js_err;
+use crate::orchestrator::Orchestrator;
+use crate::utils::core_err;
+use conduit_core::MergeFileRequest;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+fn merge(path: String, base: String, theirs: String, stage: bool) -> Result<JsValue, JsValue> {
+    let path = create_path_key(&path).map_err(|e| js_err!("Invalid path '{}': {}", path, e))?;
+    let response = Orchestrator::new()
+        .handle_merge_file(MergeFileRequest {
+            path,
+            base,
+            theirs,
+            stage,
+        })
+        .map_err(|e| core_err("Failed to merge file", &e))?;
+    response
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| js_err!("Failed to serialize merge result: {}", e))
+}
+
+/// Three-way merge of the staged copy of `path` with `theirs`, both
+/// changed from `base`, without changing staging.
+///
+/// Changes to different lines are combined; changes to the same lines are
+/// written between `<<<<<<< staged` and `>>>>>>> theirs` markers and listed
+/// in `conflicts`.
+#[wasm_bindgen]
+pub fn merge_file(path: String, base: String, theirs: String) -> Result<JsValue, JsValue> {
+    merge(path, base, theirs, false)
+}
+
+/// Like `merge_file`, then stage the merged content, conflict markers
+/// included, as one undo step. The file must be read again before line
+/// edits.
+#[wasm_bindgen]
+pub fn stage_merge(path: String, base: String, theirs: String) -> Result<JsValue, JsValue> {
+    merge(path, base, theirs, true)
+}
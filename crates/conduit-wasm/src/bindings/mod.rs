@@ -1,15 +1,52 @@
+pub mod abort_ops;
+pub mod checkpoint_ops;
+pub mod command_ops;
+pub mod config_ops;
 pub mod debug_ops;
+pub mod describe_ops;
+pub mod edit_plan_ops;
+pub mod elevation_ops;
+pub mod event_ops;
 pub mod file_ops;
+pub mod hint_ops;
+pub mod history_ops;
+pub mod lazy_ops;
 pub mod line_ops;
+pub mod merge_ops;
+pub mod pattern_ops;
 pub mod read_ops;
+pub mod result_set_ops;
+pub mod scaffold_ops;
 pub mod search_ops;
+pub mod semantic_ops;
+pub mod session_ops;
 pub mod staging_ops;
+pub mod text_ops;
+pub mod transaction_ops;
 pub mod validation_ops;
 
+pub use abort_ops::*;
+pub use checkpoint_ops::*;
+pub use command_ops::*;
+pub use config_ops::*;
 pub use debug_ops::*;
+pub use describe_ops::*;
+pub use edit_plan_ops::*;
+pub use elevation_ops::*;
 pub use file_ops::*;
+pub use hint_ops::*;
+pub use history_ops::*;
+pub use lazy_ops::*;
 pub use line_ops::*;
+pub use merge_ops::*;
+pub use pattern_ops::*;
 pub use read_ops::*;
+pub use result_set_ops::*;
+pub use scaffold_ops::*;
 pub use search_ops::*;
+pub use semantic_ops::*;
+pub use session_ops::*;
 pub use staging_ops::*;
+pub use text_ops::*;
+pub use transaction_ops::*;
 pub use validation_ops::*;
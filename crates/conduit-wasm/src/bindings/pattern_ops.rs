@@ -0,0 +1,53 @@
+use crate::bindings::search_ops::SearchOptions;
+use crate::globals::with_patterns;
+use crate::js_err;
+use crate::utils::to_js;
+use schemars::JsonSchema;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// A saved pattern, as listed by `list_patterns`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PatternInfo {
+    pub name: String,
+    pub pattern: String,
+    pub case_sensitive: bool,
+    pub whole_word: bool,
+}
+
+/// Compile `pattern` and save it as `name` for use through `patternRef`.
+///
+/// Only the matching options of `options` (`caseSensitive`, `wholeWord`,
+/// `caseFolding`) apply; they fall back to the `configure` defaults like a
+/// search would. Returns `true` if an existing pattern was replaced.
+#[wasm_bindgen]
+pub fn register_pattern(name: String, pattern: String, options: JsValue) -> Result<bool, JsValue> {
+    let opts = SearchOptions::from_js(options)?
+        .to_find_request(String::new())
+        .engine_opts;
+    with_patterns(|p| p.register(&name, &pattern, opts))
+        .map_err(|e| js_err!("Invalid pattern '{}': {}", pattern, e))
+}
+
+/// Remove a saved pattern. Returns whether it existed.
+#[wasm_bindgen]
+pub fn remove_pattern(name: String) -> bool {
+    with_patterns(|p| p.remove(&name))
+}
+
+/// Saved patterns as `{ name, pattern, caseSensitive, wholeWord }`, by name.
+#[wasm_bindgen]
+pub fn list_patterns() -> Result<JsValue, JsValue> {
+    let patterns: Vec<PatternInfo> = with_patterns(|p| {
+        p.iter()
+            .map(|(name, saved)| PatternInfo {
+                name: name.to_string(),
+                pattern: saved.pattern.clone(),
+                case_sensitive: !saved.opts.case_insensitive,
+                whole_word: saved.opts.word,
+            })
+            .collect()
+    });
+    to_js(&patterns)
+}
@@ -1,10 +1,95 @@
-use crate::globals::create_path_key;
+use crate::globals::{create_path_key, get_index_manager, host_defaults};
 use crate::js_err;
 use crate::orchestrator::Orchestrator;
-use crate::utils::JsObjectBuilder;
-use conduit_core::{ReadTool, SearchSpace};
+use crate::utils::{core_err, path_keys_from_list, to_js};
+use conduit_core::fs::{build_tree, PathKey};
+use conduit_core::{Error, ReadTool, SearchSpace};
+use js_sys::Uint8Array;
+use schemars::JsonSchema;
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
 
+/// A line range read by `read_file_lines`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LinesRead {
+    pub path: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub content: String,
+    pub total_lines: usize,
+    pub lossy: bool,
+    pub epoch: u64,
+}
+
+/// A whole file read as text.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FileText {
+    pub path: String,
+    pub content: String,
+    pub lossy: bool,
+    pub epoch: u64,
+}
+
+/// Result of `read_files_in_list`.
+#[derive(Serialize, JsonSchema)]
+pub struct FilesRead {
+    pub files: Vec<FileText>,
+    /// Paths that are absent.
+    pub missing: Vec<PathKey>,
+}
+
+/// One result of `stat_paths`.
+#[derive(Serialize, JsonSchema)]
+pub struct PathStat {
+    pub path: String,
+    pub exists: bool,
+    #[serde(flatten)]
+    pub entry: Option<EntryStat>,
+    /// Why the path could not be looked up.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Metadata of an existing file in `stat_paths`.
+#[derive(Serialize, JsonSchema)]
+pub struct EntryStat {
+    pub size: u64,
+    /// JavaScript milliseconds.
+    pub mtime: f64,
+    pub editable: bool,
+    pub hidden: bool,
+    /// Changed in the current staging session.
+    pub modified: bool,
+    pub epoch: u64,
+}
+
+/// Result of `stat_file`.
+#[derive(Serialize, JsonSchema)]
+pub struct FileStat {
+    pub path: String,
+    pub exists: bool,
+    #[serde(flatten)]
+    pub entry: Option<FileDetails>,
+}
+
+/// Metadata of an existing file in `stat_file`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FileDetails {
+    pub size: u64,
+    /// JavaScript milliseconds.
+    pub mtime: f64,
+    pub extension: String,
+    pub editable: bool,
+    /// `null` for metadata-only entries.
+    pub line_count: Option<usize>,
+    /// XXH3-64 as 16 hex digits; `null` for metadata-only entries.
+    pub hash: Option<String>,
+    pub epoch: u64,
+}
+
 #[wasm_bindgen]
 pub fn read_file_lines(
     path: String,
@@ -25,13 +110,237 @@ pub fn read_file_lines(
         .run_read(&path_key, start_line, end_line, where_)
         .map_err(|e| js_err!("Failed to read '{}': {}", path, e))?;
 
-    let obj = JsObjectBuilder::new()
-        .set("path", JsValue::from_str(&path))?
-        .set("startLine", JsValue::from(response.start_line as u32))?
-        .set("endLine", JsValue::from(response.end_line as u32))?
-        .set("content", JsValue::from_str(&response.content))?
-        .set("totalLines", JsValue::from(response.total_lines as u32))?
-        .build();
+    to_js(&LinesRead {
+        epoch: get_index_manager().edit_epoch(&path_key),
+        path,
+        start_line: response.start_line,
+        end_line: response.end_line,
+        content: response.content,
+        total_lines: response.total_lines,
+        lossy: response.lossy,
+    })
+}
+
+/// Whole content of one file as bytes, exactly as loaded.
+///
+/// Unlike `read_file_lines`, nothing is decoded, so binary files and
+/// non-UTF-8 text come back unchanged.
+#[wasm_bindgen]
+pub fn read_file(path: String, use_staged: Option<bool>) -> Result<Uint8Array, JsValue> {
+    let path_key = create_path_key(&path).map_err(|e| js_err!("Invalid path '{}': {}", path, e))?;
+    let where_ = host_defaults().search_space(use_staged);
+
+    let orchestrator = Orchestrator::new();
+    let bytes = orchestrator
+        .read_bytes(&path_key, where_)
+        .and_then(|bytes| orchestrator.record_read(&path_key, where_).map(|_| bytes))
+        .map_err(|e| core_err(format_args!("Failed to read '{path}'"), &e))?;
+    Ok(Uint8Array::from(bytes.as_slice()))
+}
+
+/// Whole content of one file as text: `{ path, content, lossy, epoch }`.
+///
+/// Invalid UTF-8 is replaced unless strict UTF-8 mode is on, in which case
+/// the read fails.
+#[wasm_bindgen]
+pub fn read_file_text(path: String, use_staged: Option<bool>) -> Result<JsValue, JsValue> {
+    let path_key = create_path_key(&path).map_err(|e| js_err!("Invalid path '{}': {}", path, e))?;
+    let where_ = host_defaults().search_space(use_staged);
+
+    let orchestrator = Orchestrator::new();
+    let (content, lossy) = orchestrator
+        .read_text(&path_key, where_)
+        .and_then(|text| orchestrator.record_read(&path_key, where_).map(|_| text))
+        .map_err(|e| core_err(format_args!("Failed to read '{path}'"), &e))?;
+
+    to_js(&FileText {
+        epoch: get_index_manager().edit_epoch(&path_key),
+        path,
+        content,
+        lossy,
+    })
+}
+
+/// Read whole files from a newline-delimited path list.
+///
+/// Returns `{ files: [{ path, content, lossy }], missing }`; absent paths
+/// are listed in `missing` instead of failing. Fails with `not_hydrated`
+/// on a metadata-only file the content provider cannot serve at once.
+#[wasm_bindgen]
+pub fn read_files_in_list(
+    paths: &Uint8Array,
+    use_staged: Option<bool>,
+) -> Result<JsValue, JsValue> {
+    let keys = path_keys_from_list(paths)?;
+    let where_ = host_defaults().search_space(use_staged);
+
+    let orchestrator = Orchestrator::new();
+    let manager = get_index_manager();
+    let mut read = FilesRead {
+        files: Vec::new(),
+        missing: Vec::new(),
+    };
+
+    for key in keys {
+        match orchestrator.read_text(&key, where_) {
+            Ok((content, lossy)) => read.files.push(FileText {
+                path: key.as_str().to_string(),
+                content,
+                lossy,
+                epoch: manager.edit_epoch(&key),
+            }),
+            Err(Error::InvalidPath(_)) => read.missing.push(key),
+            Err(e) => {
+                return Err(core_err(
+                    format_args!("Failed to read '{}'", key.as_str()),
+                    &e,
+                ))
+            }
+        }
+    }
+
+    to_js(&read)
+}
+
+/// Stat many paths in one call.
+///
+/// Each result has `path` (as given) and `exists`; existing files add
+/// `size`, `mtime` (JavaScript milliseconds), `editable`, `hidden` and
+/// `modified` (changed in the current staging session). Invalid paths
+/// report `exists: false` with an `error` instead of failing the call.
+#[wasm_bindgen]
+pub fn stat_paths(paths: Vec<String>, use_staged: Option<bool>) -> Result<JsValue, JsValue> {
+    let manager = get_index_manager();
+    let index = if host_defaults().search_space(use_staged) == SearchSpace::Staged {
+        manager
+            .staged_index()
+            .map_err(|e| js_err!("Failed to access staged index: {}", e))?
+    } else {
+        manager.active_index()
+    };
+
+    let stats: Vec<PathStat> = paths
+        .into_iter()
+        .map(|path| match create_path_key(&path) {
+            Ok(key) => {
+                let entry = index.get_file(&key).map(|entry| EntryStat {
+                    size: entry.size(),
+                    mtime: entry.mtime() as f64 * 1000.0,
+                    editable: entry.is_editable(),
+                    hidden: entry.is_hidden(&key),
+                    modified: manager.is_staged_modified(&key),
+                    epoch: manager.edit_epoch(&key),
+                });
+                PathStat {
+                    path,
+                    exists: entry.is_some(),
+                    entry,
+                    error: None,
+                }
+            }
+            Err(e) => PathStat {
+                path,
+                exists: false,
+                entry: None,
+                error: Some(e.to_string()),
+            },
+        })
+        .collect();
+
+    to_js(&stats)
+}
+
+/// Metadata for one file, without its content.
+///
+/// Returns `{ path, exists }`; existing files add `size`, `mtime`
+/// (JavaScript milliseconds), `extension`, `editable`, `lineCount` and
+/// `hash` (XXH3-64 as 16 hex digits). The last two are `null` for
+/// metadata-only entries. `lineCount` agrees with `read_file_lines`'s
+/// `totalLines`.
+#[wasm_bindgen]
+pub fn stat_file(path: String, use_staged: Option<bool>) -> Result<JsValue, JsValue> {
+    let key = create_path_key(&path).map_err(|e| js_err!("Invalid path '{}': {}", path, e))?;
+    let manager = get_index_manager();
+    let index = if host_defaults().search_space(use_staged) == SearchSpace::Staged {
+        manager
+            .staged_index()
+            .map_err(|e| js_err!("Failed to access staged index: {}", e))?
+    } else {
+        manager.active_index()
+    };
+
+    let entry = index.get_file(&key).map(|entry| FileDetails {
+        size: entry.size(),
+        mtime: entry.mtime() as f64 * 1000.0,
+        extension: entry.ext().to_string(),
+        editable: entry.is_editable(),
+        line_count: manager
+            .get_line_index(&key, &index)
+            .map(|line_index| line_index.line_count()),
+        // Hex string: a u64 does not fit losslessly in a JS number.
+        hash: entry.content_hash().map(|hash| format!("{:016x}", hash)),
+        epoch: manager.edit_epoch(&key),
+    });
+    to_js(&FileStat {
+        path,
+        exists: entry.is_some(),
+        entry,
+    })
+}
+
+/// Whether `path` exists in the staged view, or the active index when no
+/// staging session is active. Invalid paths do not exist.
+#[wasm_bindgen]
+pub fn file_exists(path: String) -> bool {
+    let Ok(key) = create_path_key(&path) else {
+        return false;
+    };
+    let manager = get_index_manager();
+    let index = manager
+        .staged_index()
+        .unwrap_or_else(|_| manager.active_index());
+    index.get_file(&key).is_some()
+}
+
+/// Nested directory tree below `prefix` (the whole index by default), with
+/// file counts and total sizes per directory. `depth` limits how many
+/// levels are expanded. Hidden, junk and ignored files are left out unless
+/// asked for, as in `list_files_from_wasm`.
+#[wasm_bindgen]
+pub fn get_file_tree(
+    prefix: Option<String>,
+    depth: Option<usize>,
+    use_staged: Option<bool>,
+    include_hidden: Option<bool>,
+    include_ignored: Option<bool>,
+    include_junk: Option<bool>,
+) -> Result<JsValue, JsValue> {
+    let manager = get_index_manager();
+    let index = if host_defaults().search_space(use_staged) == SearchSpace::Staged {
+        manager
+            .staged_index()
+            .map_err(|e| js_err!("Failed to access staged index: {}", e))?
+    } else {
+        manager.active_index()
+    };
+    let visibility = manager.config().visibility(include_hidden, include_junk);
+    let ignore = if include_ignored.unwrap_or(false) {
+        None
+    } else {
+        host_defaults()
+            .ignore_set(&index, manager.generation())
+            .map_err(|e| core_err("Invalid ignorePatterns", &e))?
+    };
+
+    let visible = index
+        .iter_sorted()
+        .filter(|(path, entry)| entry.is_visible(path, visibility))
+        .filter(|(path, _)| {
+            ignore
+                .as_ref()
+                .is_none_or(|ignore| !ignore.is_ignored(path.as_str()))
+        });
+    let tree = build_tree(visible, prefix.as_deref().unwrap_or(""), depth);
 
-    Ok(obj)
+    serde_wasm_bindgen::to_value(&tree).map_err(|e| js_err!("Failed to serialize tree: {}", e))
 }
@@ -0,0 +1,70 @@
+use crate::bindings::abort_ops::abort_flag_from_js;
+use crate::bindings::search_ops::SearchOptions;
+use crate::globals::{with_result_sets, with_search_history};
+use crate::js_err;
+use crate::orchestrator::Orchestrator;
+use crate::utils::core_err;
+use conduit_core::tools::DEFAULT_RESULT_PAGE_SIZE;
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// Run a search (options as in `search_files_with_options`) and keep the
+/// lines it matched for `refine_result_set` and `get_result_set`.
+///
+/// The set searches the index as it was when created. Only the newest 16
+/// sets are kept; older ids expire. Returns the set id.
+#[wasm_bindgen]
+pub fn create_result_set(
+    pattern: String,
+    options: JsValue,
+    handle: JsValue,
+) -> Result<String, JsValue> {
+    let abort_flag = abort_flag_from_js(&handle)?;
+    let request = SearchOptions::from_js(options)?.to_find_request(pattern);
+    let set = Orchestrator::new()
+        .create_result_set(request.clone(), &abort_flag)
+        .map_err(|e| core_err("Search failed", &e))?;
+    with_search_history(|h| h.record(&request));
+    Ok(with_result_sets(|sets| sets.insert(set)))
+}
+
+/// Keep only the lines of set `id` that also match `pattern`, compiled
+/// with the original search's case and word options. Other lines and
+/// files are not searched. Returns the number of lines left.
+///
+/// An invalid pattern or an abort leaves the set unchanged.
+#[wasm_bindgen]
+pub fn refine_result_set(id: String, pattern: String, handle: JsValue) -> Result<usize, JsValue> {
+    let abort_flag = abort_flag_from_js(&handle)?;
+    with_result_sets(|sets| {
+        let set = sets
+            .get_mut(&id)
+            .ok_or_else(|| js_err!("Unknown or expired result set '{}'", id))?;
+        set.refine(&pattern, &abort_flag)
+            .map_err(|e| core_err("Failed to refine result set", &e))
+    })
+}
+
+/// Page `page` (0-based) of set `id`, `page_size` lines per page
+/// (default 100), as `{ lines, page, pageCount, totalLines, totalFiles,
+/// refinements }`.
+#[wasm_bindgen]
+pub fn get_result_set(
+    id: String,
+    page: usize,
+    page_size: Option<usize>,
+) -> Result<JsValue, JsValue> {
+    let page = with_result_sets(|sets| {
+        sets.get_mut(&id)
+            .map(|set| set.page(page, page_size.unwrap_or(DEFAULT_RESULT_PAGE_SIZE)))
+            .ok_or_else(|| js_err!("Unknown or expired result set '{}'", id))
+    })?;
+    page.serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| js_err!("Failed to serialize result set: {}", e))
+}
+
+/// Drop a result set. Returns whether it was kept.
+#[wasm_bindgen]
+pub fn close_result_set(id: String) -> bool {
+    with_result_sets(|sets| sets.take(&id)).is_some()
+}
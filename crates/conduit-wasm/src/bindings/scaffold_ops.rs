@@ -0,0 +1,85 @@
+use crate::globals::with_scaffolds;
+use crate::js_err;
+use crate::orchestrator::Orchestrator;
+use crate::utils::to_js;
+use conduit_core::tools::{ScaffoldTemplate, TemplateFile};
+use conduit_core::{ApplyScaffoldRequest, ScaffoldTool};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::collections::HashMap;
+use wasm_bindgen::prelude::*;
+
+/// A registered template, as listed by `list_scaffolds`.
+#[derive(Serialize, JsonSchema)]
+pub struct ScaffoldInfo {
+    pub name: String,
+    pub files: Vec<TemplateFile>,
+    /// Placeholder names used by the files.
+    pub placeholders: Vec<String>,
+}
+
+/// Register a named template, replacing any existing one with the same name.
+///
+/// `files` is an array of `{ path, content }`; both may contain `{{param}}`
+/// placeholders. Returns `true` if an existing template was replaced.
+#[wasm_bindgen]
+pub fn register_scaffold(name: String, files: JsValue) -> Result<bool, JsValue> {
+    let files = serde_wasm_bindgen::from_value(files)
+        .map_err(|e| js_err!("Invalid files for scaffold '{}': {}", name, e))?;
+    Ok(with_scaffolds(|r| {
+        r.register(ScaffoldTemplate { name, files })
+    }))
+}
+
+/// Remove a template. Returns whether it existed.
+#[wasm_bindgen]
+pub fn remove_scaffold(name: String) -> bool {
+    with_scaffolds(|r| r.remove(&name))
+}
+
+/// Registered templates as `{ name, files, placeholders }`.
+#[wasm_bindgen]
+pub fn list_scaffolds() -> Result<JsValue, JsValue> {
+    let templates: Vec<ScaffoldTemplate> = with_scaffolds(|r| r.iter().cloned().collect());
+    let scaffolds: Vec<ScaffoldInfo> = templates
+        .into_iter()
+        .map(|template| ScaffoldInfo {
+            placeholders: template.placeholders().into_iter().collect(),
+            name: template.name,
+            files: template.files,
+        })
+        .collect();
+    to_js(&scaffolds)
+}
+
+/// Render a registered template under `dst_prefix` and stage every file.
+///
+/// Either all files are created or none are. Returns `{ created: string[] }`.
+#[wasm_bindgen]
+pub fn apply_scaffold(
+    name: String,
+    params: JsValue,
+    dst_prefix: String,
+    allow_overwrite: Option<bool>,
+) -> Result<JsValue, JsValue> {
+    let params: HashMap<String, String> = if params.is_undefined() || params.is_null() {
+        HashMap::new()
+    } else {
+        serde_wasm_bindgen::from_value(params)
+            .map_err(|e| js_err!("Invalid params for scaffold '{}': {}", name, e))?
+    };
+
+    let request = ApplyScaffoldRequest {
+        template: name.clone(),
+        params,
+        dst_prefix,
+        allow_overwrite: allow_overwrite.unwrap_or(false),
+    };
+
+    let mut orchestrator = Orchestrator::new();
+    let response = orchestrator
+        .run_apply_scaffold(request)
+        .map_err(|e| js_err!("Failed to apply scaffold '{}': {}", name, e))?;
+
+    to_js(&response)
+}
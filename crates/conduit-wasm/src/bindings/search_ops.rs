@@ -1,11 +1,338 @@
+use crate::bindings::abort_ops::abort_flag_from_js;
+use crate::globals::{create_path_key, host_defaults, with_search_cursors, with_search_history};
 use crate::js_err;
 use crate::orchestrator::Orchestrator;
-use crate::utils::JsObjectBuilder;
-use conduit_core::{AbortFlag, FindRequest, FindTool, RegexEngineOpts, SearchSpace};
-use globset::Glob;
-use js_sys::Array;
+use crate::utils::{core_err, millis_to_unix_seconds, path_keys_from_list, to_js};
+use conduit_core::fs::{compile_globs, GlobOptions};
+use conduit_core::tools::{render_report, suggest_related, MatchColumns, ReportFormat, TokenKind};
+use conduit_core::{
+    AbortFlag, CaseFolding, EditItem, EditRequest, FindHit, FindRequest, FindResponse, PathKey,
+    PreviewHunk, RegexEngineOpts, SearchOrder, SearchSpace,
+};
+use js_sys::{Array, Function, Uint8Array};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use wasm_bindgen::prelude::*;
 
+/// Tokens returned by `get_token_frequencies` when `top_n` is not given.
+const DEFAULT_TOP_TOKENS: usize = 100;
+
+/// Search options shared by the search bindings.
+///
+/// Mirrors the positional arguments of `search_files`; all fields are optional.
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SearchOptions {
+    pub path_prefix: Option<String>,
+    pub include_pattern: Option<String>,
+    pub exclude_pattern: Option<String>,
+    pub case_sensitive: Option<bool>,
+    pub whole_word: Option<bool>,
+    pub use_staged: Option<bool>,
+    pub context_lines: Option<usize>,
+    /// Return at most this many results; `stats` still count the rest.
+    pub limit: Option<usize>,
+    /// JavaScript millisecond timestamp.
+    pub modified_after: Option<f64>,
+    /// JavaScript millisecond timestamp.
+    pub modified_before: Option<f64>,
+    /// Scan small files before large ones.
+    pub small_files_first: Option<bool>,
+    /// Return `{ path, line, length }` per hit instead of excerpts.
+    pub minimal: Option<bool>,
+    /// Search space; overrides `useStaged` when set.
+    pub space: Option<SearchSpace>,
+    /// Case folding for case-insensitive searches.
+    pub case_folding: Option<CaseFolding>,
+    /// Also search dotfiles and host-flagged hidden files. Defaults to the
+    /// index manager's `includeHidden`.
+    pub include_hidden: Option<bool>,
+    /// Also search OS junk files such as `.DS_Store`. Defaults to the index
+    /// manager's `includeJunk`.
+    pub include_junk: Option<bool>,
+    /// Also search paths matched by `.gitignore` files or `ignorePatterns`.
+    pub include_ignored: Option<bool>,
+    /// Search with a pattern from `register_pattern` instead of the search
+    /// term; the pattern's own matching options apply.
+    pub pattern_ref: Option<String>,
+    /// Report at most this many matches per file.
+    pub max_matches_per_file: Option<usize>,
+    /// Stop searching after this many matches. Unlike `limit`, which trims
+    /// the results, the scan itself ends early.
+    pub max_total_matches: Option<usize>,
+    /// Merge hunks of nearby matches whose excerpts overlap or touch.
+    pub merge_adjacent: Option<bool>,
+    /// How `includePattern` and `excludePattern` are matched.
+    pub glob_options: Option<GlobOptions>,
+    /// Replacements only: recase each replacement to follow the text it
+    /// replaces.
+    pub preserve_case: Option<bool>,
+    /// Give up after this many milliseconds and return what was found, with
+    /// `stats.aborted` set.
+    pub timeout_ms: Option<u64>,
+    /// Search exactly these files, skipping `pathPrefix` and the patterns.
+    pub paths: Option<Vec<String>>,
+}
+
+impl SearchOptions {
+    /// Parse options from a JS object; `undefined`/`null` yields defaults.
+    pub fn from_js(options: JsValue) -> Result<Self, JsValue> {
+        if options.is_undefined() || options.is_null() {
+            return Ok(Self::default());
+        }
+        serde_wasm_bindgen::from_value(options)
+            .map_err(|e| js_err!("Invalid search options: {}", e))
+    }
+
+    /// Result limit, falling back to the configured `maxResults`.
+    pub fn effective_limit(&self) -> Option<usize> {
+        host_defaults().result_limit(self.limit)
+    }
+
+    /// An edit request replacing `pattern` with `replacement` in the files
+    /// these options select.
+    pub fn to_edit_request(&self, pattern: String, replacement: String) -> EditRequest {
+        let find = self.to_find_request(pattern);
+        EditRequest {
+            include_globs: find.include_globs,
+            exclude_globs: find.exclude_globs,
+            prefix: find.prefix,
+            find: find.find,
+            pattern_ref: find.pattern_ref,
+            replace: replacement,
+            preserve_case: self.preserve_case.unwrap_or(false),
+            delta: find.delta,
+            engine_opts: find.engine_opts,
+            glob_options: find.glob_options,
+        }
+    }
+
+    /// Build the core request for `search_term`.
+    ///
+    /// Unset options fall back to the host defaults from `configure`.
+    pub fn to_find_request(&self, search_term: String) -> FindRequest {
+        let defaults = host_defaults();
+        let where_ = self
+            .space
+            .unwrap_or_else(|| defaults.search_space(self.use_staged));
+        let case_sensitive = self
+            .case_sensitive
+            .or(defaults.case_insensitive_default.map(|ci| !ci))
+            .unwrap_or(false);
+        let whole_word = self
+            .whole_word
+            .or(defaults.whole_word_default)
+            .unwrap_or(false);
+        let context_lines = self
+            .context_lines
+            .or(defaults.default_context_lines)
+            .unwrap_or(2);
+        let exclude_globs = match &self.exclude_pattern {
+            Some(pattern) => Some(vec![pattern.clone()]),
+            None => defaults.default_exclude_globs,
+        };
+
+        let case_folding = self
+            .case_folding
+            .or(defaults.case_folding)
+            .unwrap_or_default();
+        let visibility = get_index_manager()
+            .config()
+            .visibility(self.include_hidden, self.include_junk);
+
+        FindRequest {
+            find: search_term,
+            pattern_ref: self.pattern_ref.clone(),
+            patterns: Vec::new(),
+            where_,
+            prefix: self.path_prefix.clone(),
+            include_globs: self.include_pattern.as_ref().map(|p| vec![p.clone()]),
+            exclude_globs,
+            engine_opts: RegexEngineOpts {
+                case_insensitive: !case_sensitive,
+                case_folding,
+                multiline: true,
+                dot_all: false,
+                crlf: false, // Use LF line endings (Unix/Mac) instead of CRLF (Windows)
+                word: whole_word,
+                unicode: true,
+            },
+            delta: context_lines,
+            modified_after: self.modified_after.map(millis_to_unix_seconds),
+            modified_before: self.modified_before.map(millis_to_unix_seconds),
+            order: if self.small_files_first.unwrap_or(false) {
+                SearchOrder::SmallestFirst
+            } else {
+                SearchOrder::Path
+            },
+            minimal: self.minimal.unwrap_or(false),
+            include_hidden: visibility.include_hidden,
+            include_junk: visibility.include_junk,
+            include_ignored: self.include_ignored.unwrap_or(false),
+            max_matches_per_file: self.max_matches_per_file,
+            max_total_matches: self.max_total_matches,
+            merge_adjacent: self.merge_adjacent.unwrap_or(false),
+            glob_options: self.glob_options.unwrap_or_default(),
+            timeout_ms: self.timeout_ms,
+            // A path that does not normalize cannot name an indexed file,
+            // so dropping it is the same as skipping a missing one.
+            paths: self.paths.as_ref().map(|paths| {
+                paths
+                    .iter()
+                    .filter_map(|p| create_path_key(p).ok())
+                    .collect()
+            }),
+        }
+    }
+}
+
+/// Edit epochs for `paths`, in order; `0` for paths never written.
+///
+/// An epoch changes whenever the file's content may have changed, so
+/// derived data (highlighting, embeddings) cached per epoch stays valid
+/// until it moves.
+#[wasm_bindgen]
+pub fn get_edit_epochs(paths: Vec<String>) -> Result<Vec<f64>, JsValue> {
+    paths
+        .iter()
+        .map(|p| {
+            create_path_key(p)
+                .map(|key| get_index_manager().edit_epoch(&key) as f64)
+                .map_err(|e| js_err!("Invalid path '{}': {}", p, e))
+        })
+        .collect()
+}
+
+/// An excerpt around one or more matches, as returned by the search bindings.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHunk {
+    pub path: PathKey,
+    pub lines: Vec<HunkLine>,
+    pub lossy: bool,
+    /// Characters cut from the start of the first line.
+    pub omitted_prefix: usize,
+    /// Characters cut from the end of the last line.
+    pub omitted_suffix: usize,
+    pub epoch: u64,
+    /// Position of the pattern that matched, for multi-pattern searches.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern_index: Option<usize>,
+    /// Where the matches fall in `lines`, one entry per line they cover.
+    pub match_columns: Vec<MatchColumn>,
+}
+
+/// One line of a [`SearchHunk`].
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HunkLine {
+    pub line_number: usize,
+    pub content: String,
+    pub is_match: bool,
+}
+
+/// Part of a match on one line of a [`SearchHunk`], 0-based and half-open.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchColumn {
+    /// 1-based line in the file.
+    pub line: usize,
+    pub start_byte: usize,
+    pub end_byte: usize,
+    /// Columns in Unicode scalar values.
+    pub start_char: usize,
+    pub end_char: usize,
+}
+
+impl From<&MatchColumns> for MatchColumn {
+    fn from(col: &MatchColumns) -> Self {
+        Self {
+            line: col.line,
+            start_byte: col.start_byte,
+            end_byte: col.end_byte,
+            start_char: col.start_char,
+            end_char: col.end_char,
+        }
+    }
+}
+
+impl From<&PreviewHunk> for SearchHunk {
+    fn from(hunk: &PreviewHunk) -> Self {
+        let lines = hunk
+            .excerpt
+            .lines()
+            .enumerate()
+            .map(|(line_idx, content)| {
+                let line_number = hunk.preview_start_line + line_idx;
+                HunkLine {
+                    line_number,
+                    content: content.to_string(),
+                    is_match: hunk
+                        .matched_line_ranges
+                        .iter()
+                        .any(|(start, end)| line_number >= *start && line_number <= *end),
+                }
+            })
+            .collect();
+        Self {
+            path: hunk.path.clone(),
+            lines,
+            lossy: hunk.lossy,
+            omitted_prefix: hunk.omitted_prefix,
+            omitted_suffix: hunk.omitted_suffix,
+            epoch: get_index_manager().edit_epoch(&hunk.path),
+            pattern_index: hunk.pattern_index,
+            match_columns: hunk.match_columns.iter().map(MatchColumn::from).collect(),
+        }
+    }
+}
+
+/// A match without an excerpt, returned by `minimal` searches.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub path: PathKey,
+    /// 1-based line of the match start.
+    pub line: usize,
+    /// Length of the match in bytes.
+    pub length: usize,
+    pub epoch: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern_index: Option<usize>,
+}
+
+impl From<&FindHit> for SearchHit {
+    fn from(hit: &FindHit) -> Self {
+        Self {
+            path: hit.path.clone(),
+            line: hit.line,
+            length: hit.length,
+            epoch: get_index_manager().edit_epoch(&hit.path),
+            pattern_index: hit.pattern_index,
+        }
+    }
+}
+
+/// Counts of a search, set as the `stats` property of its result array.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchStats {
+    pub total_matches: usize,
+    pub files_with_matches: usize,
+    pub files_truncated: usize,
+    pub match_limit_reached: bool,
+    pub aborted: bool,
+    /// Candidates not searched because they are loaded as metadata only.
+    pub skipped_unhydrated: usize,
+    /// Files whose matches were cut off by `maxMatchesPerFile`.
+    pub truncated_paths: Vec<PathKey>,
+}
+
+/// Convert a preview hunk into the `{ path, lines }` shape used by the search bindings.
+pub(crate) fn hunk_to_js(hunk: &PreviewHunk) -> Result<JsValue, JsValue> {
+    to_js(&SearchHunk::from(hunk))
+}
+
 #[allow(clippy::too_many_arguments)]
 #[wasm_bindgen]
 pub fn search_files(
@@ -18,93 +345,540 @@ pub fn search_files(
     use_staged: Option<bool>,
     context_lines: Option<usize>,
     limit: Option<usize>,
+    modified_after: Option<f64>,
+    modified_before: Option<f64>,
 ) -> Result<JsValue, JsValue> {
-    let staged = use_staged.unwrap_or(true);
-    let case_sensitive = case_sensitive.unwrap_or(false);
-    let whole_word = whole_word.unwrap_or(false);
-    let context_lines = context_lines.unwrap_or(2);
-
-    let include_globs = include_pattern
-        .as_ref()
-        .map(|pattern| vec![pattern.clone()]);
-    let exclude_globs = exclude_pattern
-        .as_ref()
-        .map(|pattern| vec![pattern.clone()]);
-
-    let find_request = FindRequest {
-        find: search_term.clone(),
-        where_: if staged {
-            SearchSpace::Staged
-        } else {
-            SearchSpace::Active
-        },
-        prefix: path_prefix,
-        include_globs,
-        exclude_globs,
-        engine_opts: RegexEngineOpts {
-            case_insensitive: !case_sensitive,
-            multiline: true,
-            dot_all: false,
-            crlf: false, // Use LF line endings (Unix/Mac) instead of CRLF (Windows)
-            word: whole_word,
-            unicode: true,
-        },
-        delta: context_lines,
+    let options = SearchOptions {
+        path_prefix,
+        include_pattern,
+        exclude_pattern,
+        case_sensitive,
+        whole_word,
+        use_staged,
+        context_lines,
+        limit,
+        modified_after,
+        modified_before,
+        small_files_first: None,
+        minimal: None,
+        space: None,
+        case_folding: None,
+        include_hidden: None,
+        include_junk: None,
+        include_ignored: None,
+        pattern_ref: None,
+        max_matches_per_file: None,
+        max_total_matches: None,
+        merge_adjacent: None,
+        glob_options: None,
+        preserve_case: None,
+        timeout_ms: None,
+        paths: None,
     };
+    let find_request = options.to_find_request(search_term);
 
     let abort_flag = AbortFlag::new();
-    let mut orchestrator = Orchestrator::new();
-    let response = orchestrator
-        .run_find(find_request, &abort_flag)
+    let response = Orchestrator::new()
+        .find_up_to(find_request.clone(), options.effective_limit(), &abort_flag)
         .map_err(|e| js_err!("Search failed: {}", e))?;
+    with_search_history(|h| h.record(&find_request));
+
+    Ok(find_response_to_js(&response, false)?.into())
+}
+
+/// Search files with an options object.
+///
+/// Takes the same options as `search_files_streaming`. With `minimal: true`
+/// each result is `{ path, line, length }` and no excerpts are built, which
+/// keeps responses small when the caller follows up with targeted reads.
+#[wasm_bindgen]
+pub fn search_files_with_options(
+    search_term: String,
+    options: JsValue,
+    handle: JsValue,
+) -> Result<Array, JsValue> {
+    let abort_flag = abort_flag_from_js(&handle)?;
+    let options = SearchOptions::from_js(options)?;
+    let request = options.to_find_request(search_term);
+    let results = run_search(
+        request.clone(),
+        options.effective_limit(),
+        Orchestrator::new(),
+        &abort_flag,
+    )?;
+    with_search_history(|h| h.record(&request));
+    Ok(results)
+}
+
+/// Search for any of several patterns in one pass over the files.
+///
+/// Options apply as in `search_files_with_options` (`patternRef` is
+/// ignored). Each result carries `patternIndex`, the position in `patterns`
+/// of the pattern that matched; where several match at the same place the
+/// earliest wins.
+#[wasm_bindgen]
+pub fn search_files_multi(
+    patterns: Vec<String>,
+    options: JsValue,
+    handle: JsValue,
+) -> Result<Array, JsValue> {
+    if patterns.is_empty() {
+        return Err(js_err!("At least one pattern is required"));
+    }
+    let abort_flag = abort_flag_from_js(&handle)?;
+    let options = SearchOptions::from_js(options)?;
+    let request = FindRequest {
+        pattern_ref: None,
+        patterns,
+        ..options.to_find_request(String::new())
+    };
+    let results = run_search(
+        request.clone(),
+        options.effective_limit(),
+        Orchestrator::new(),
+        &abort_flag,
+    )?;
+    with_search_history(|h| h.record(&request));
+    Ok(results)
+}
 
-    let results_array = Array::new();
-    for (idx, hunk) in response.results.into_iter().enumerate() {
-        if let Some(limit) = limit {
-            if idx >= limit {
-                break;
+/// Search only the files in a newline-delimited path list.
+///
+/// Use this instead of long include-glob arrays for scopes of thousands of
+/// files. Other options apply as in `search_files_with_options`.
+#[wasm_bindgen]
+pub fn search_files_in_list(
+    search_term: String,
+    paths: &Uint8Array,
+    options: JsValue,
+    handle: JsValue,
+) -> Result<Array, JsValue> {
+    let abort_flag = abort_flag_from_js(&handle)?;
+    let options = SearchOptions::from_js(options)?;
+    let scope = path_keys_from_list(paths)?;
+    run_search(
+        options.to_find_request(search_term),
+        options.effective_limit(),
+        Orchestrator::new().with_scope(scope),
+        &abort_flag,
+    )
+}
+
+/// Run `request`, keep at most `limit` results, and convert them to hunks,
+/// or to bare hits when the request is `minimal`.
+pub(crate) fn run_search(
+    request: FindRequest,
+    limit: Option<usize>,
+    orchestrator: Orchestrator,
+    abort: &AbortFlag,
+) -> Result<Array, JsValue> {
+    let minimal = request.minimal;
+    let response = orchestrator
+        .find_up_to(request, limit, abort)
+        .map_err(|e| core_err("Search failed", &e))?;
+
+    find_response_to_js(&response, minimal)
+}
+
+/// Convert results to hunks, or to bare hits when `minimal`.
+///
+/// The array also carries the response's counts as a `stats` property.
+fn find_response_to_js(response: &FindResponse, minimal: bool) -> Result<Array, JsValue> {
+    let results: Array = if minimal {
+        let hits: Vec<SearchHit> = response.hits.iter().map(SearchHit::from).collect();
+        to_js(&hits)?.into()
+    } else {
+        let hunks: Vec<SearchHunk> = response.results.iter().map(SearchHunk::from).collect();
+        to_js(&hunks)?.into()
+    };
+
+    let stats = SearchStats {
+        total_matches: response.stats.total_matches,
+        files_with_matches: response.stats.files_with_matches,
+        files_truncated: response.stats.files_truncated,
+        match_limit_reached: response.stats.match_limit_reached,
+        aborted: response.stats.aborted,
+        skipped_unhydrated: response.stats.skipped_unhydrated,
+        truncated_paths: response.truncated_paths.clone(),
+    };
+    js_sys::Reflect::set(&results, &JsValue::from_str("stats"), &to_js(&stats)?)?;
+    Ok(results)
+}
+
+/// One page from `search_files_page`.
+#[derive(Serialize, JsonSchema)]
+pub struct SearchPage {
+    /// Hunks, or hits for `minimal` searches, with a `stats` property.
+    #[serde(with = "serde_wasm_bindgen::preserve")]
+    #[schemars(with = "Vec<SearchHunk>")]
+    pub results: JsValue,
+    /// Token for the next page; `null` once `done`.
+    pub cursor: Option<String>,
+    pub done: bool,
+}
+
+/// Fetch one page of search results.
+///
+/// Pass `cursor: null` to start a search; `pattern` and `options` (as in
+/// `search_files_with_options`) apply. Pass the returned `cursor` to get the
+/// next page; `pattern` must then match and `options` are ignored. A cursor
+/// searches the index as it was when the search started. Returns
+/// `{ results, cursor, done }`; `cursor` is `null` once `done`.
+#[wasm_bindgen]
+pub fn search_files_page(
+    pattern: String,
+    cursor: Option<String>,
+    page_size: usize,
+    options: JsValue,
+    handle: JsValue,
+) -> Result<JsValue, JsValue> {
+    let abort_flag = abort_flag_from_js(&handle)?;
+    let orchestrator = Orchestrator::new();
+    let (token, mut search) = match cursor {
+        Some(token) => {
+            let search = with_search_cursors(|c| c.take(&token))
+                .ok_or_else(|| js_err!("Unknown or expired search cursor '{}'", token))?;
+            if search.request().find != pattern {
+                let err = js_err!("Search cursor '{}' belongs to a different pattern", token);
+                with_search_cursors(|c| c.put(token, search));
+                return Err(err);
             }
+            (Some(token), search)
+        }
+        None => {
+            let request = SearchOptions::from_js(options)?.to_find_request(pattern);
+            let search = orchestrator
+                .open_search(request.clone())
+                .map_err(|e| core_err("Search failed", &e))?;
+            with_search_history(|h| h.record(&request));
+            (None, search)
         }
+    };
+
+    let page = orchestrator
+        .find_page(&mut search, page_size, &abort_flag)
+        .map_err(|e| core_err("Search failed", &e))?;
+    let results = find_response_to_js(&page, search.request().minimal)?;
 
-        let lines_array = Array::new();
-        for (line_idx, line_content) in hunk.excerpt.lines().enumerate() {
-            let line_num = hunk.preview_start_line + line_idx;
-            let is_match = hunk
-                .matched_line_ranges
-                .iter()
-                .any(|(start, end)| line_num >= *start && line_num <= *end);
-
-            let line_obj = JsObjectBuilder::new()
-                .set("lineNumber", JsValue::from(line_num as u32))?
-                .set("content", JsValue::from_str(line_content))?
-                .set("isMatch", JsValue::from_bool(is_match))?
-                .build();
-            lines_array.push(&line_obj);
+    let done = search.is_done();
+    let token = if done {
+        None
+    } else {
+        Some(with_search_cursors(|c| match token {
+            Some(token) => {
+                c.put(token.clone(), search);
+                token
+            }
+            None => c.open(search),
+        }))
+    };
+
+    to_js(&SearchPage {
+        results: results.into(),
+        cursor: token,
+        done,
+    })
+}
+
+/// Drop a search cursor before it is exhausted. Returns whether it was open.
+#[wasm_bindgen]
+pub fn close_search_cursor(cursor: String) -> bool {
+    with_search_cursors(|c| c.take(&cursor)).is_some()
+}
+
+/// Options for `replace_in_files`: the search options plus `dryRun`.
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ReplaceOptions {
+    #[serde(flatten)]
+    search: SearchOptions,
+    /// Return the planned edits without staging them.
+    dry_run: Option<bool>,
+}
+
+/// Regex find-and-replace across staged files.
+///
+/// `replacement` may use `$1`, `${name}` and `$$`. With `dryRun: true` the
+/// planned edits are returned and nothing is staged; otherwise every
+/// matching file is rewritten in staging, or none is if any fails.
+/// Path filters, matching options, `contextLines` and `patternRef` of
+/// `options` apply; edits always target staging.
+#[wasm_bindgen]
+pub fn replace_in_files(
+    pattern: String,
+    replacement: String,
+    options: JsValue,
+    handle: JsValue,
+) -> Result<JsValue, JsValue> {
+    let abort_flag = abort_flag_from_js(&handle)?;
+    let options: ReplaceOptions = if options.is_undefined() || options.is_null() {
+        ReplaceOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)
+            .map_err(|e| js_err!("Invalid replace options: {}", e))?
+    };
+    let dry_run = options.dry_run.unwrap_or(false);
+    let request = options.search.to_edit_request(pattern, replacement);
+
+    let orchestrator = Orchestrator::new();
+    let response = if dry_run {
+        orchestrator.preview_edit(&request, &abort_flag)
+    } else {
+        orchestrator.handle_edit(request, &abort_flag)
+    }
+    .map_err(|e| core_err("Replace failed", &e))?;
+
+    let mut files: Vec<&str> = response.items.iter().map(|i| i.path.as_str()).collect();
+    files.dedup();
+
+    to_js(&ReplaceResult {
+        items: response.items.iter().map(EditPreview::from).collect(),
+        files_changed: files.len(),
+        applied: !dry_run,
+    })
+}
+
+/// Result of `replace_in_files`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaceResult {
+    pub items: Vec<EditPreview>,
+    pub files_changed: usize,
+    /// Whether the edits were staged; `false` for dry runs.
+    pub applied: bool,
+}
+
+/// One planned or applied edit, with excerpts before and after.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EditPreview {
+    pub path: PathKey,
+    /// Inclusive 1-based lines replaced in the original.
+    pub original_range: (usize, usize),
+    /// Inclusive 1-based lines of the replacement in staging.
+    pub staged_range: (usize, usize),
+    pub original: SearchHunk,
+    pub staged: SearchHunk,
+}
+
+impl From<&EditItem> for EditPreview {
+    fn from(item: &EditItem) -> Self {
+        Self {
+            path: item.path.clone(),
+            original_range: item.original_range,
+            staged_range: item.staged_range,
+            original: SearchHunk::from(&item.original_preview),
+            staged: SearchHunk::from(&item.staged_preview),
         }
+    }
+}
+
+/// Search files, invoking `on_result` with each hunk as soon as it is found.
+///
+/// `options` takes the same fields as `search_files` in camelCase, plus
+/// `smallFilesFirst`. Returning `false` from the callback stops the search.
+#[wasm_bindgen]
+pub fn search_files_streaming(
+    search_term: String,
+    options: JsValue,
+    on_result: &Function,
+    handle: JsValue,
+) -> Result<JsValue, JsValue> {
+    let abort_flag = abort_flag_from_js(&handle)?;
+    let options = SearchOptions::from_js(options)?;
+    let find_request = options.to_find_request(search_term);
+
+    let orchestrator = Orchestrator::new();
+
+    let limit = options.effective_limit();
+    let mut emitted = 0usize;
+    let mut callback_error = None;
+    let limits = orchestrator
+        .find_each(find_request, &abort_flag, |hunk| {
+            if limit.is_some_and(|limit| emitted >= limit) {
+                return false;
+            }
+            let result = hunk_to_js(&hunk).and_then(|obj| on_result.call1(&JsValue::NULL, &obj));
+            match result {
+                Ok(ret) => {
+                    emitted += 1;
+                    ret.as_bool() != Some(false) && limit.is_none_or(|limit| emitted < limit)
+                }
+                Err(e) => {
+                    callback_error = Some(e);
+                    false
+                }
+            }
+        })
+        .map_err(|e| core_err("Search failed", &e))?;
+
+    if let Some(e) = callback_error {
+        return Err(e);
+    }
+
+    to_js(&StreamSummary {
+        emitted,
+        truncated_paths: limits.truncated_paths,
+        match_limit_reached: limits.limit_reached,
+        skipped_unhydrated: limits.unhydrated,
+    })
+}
+
+/// Result of `search_files_streaming`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamSummary {
+    /// Hunks passed to the callback.
+    pub emitted: usize,
+    pub truncated_paths: Vec<PathKey>,
+    pub match_limit_reached: bool,
+    /// Candidates not searched because they are loaded as metadata only.
+    pub skipped_unhydrated: usize,
+}
+
+/// Run a search and export every match as a CSV or JSON document.
+///
+/// `format` is `"csv"` or `"json"`. Each row carries path, line, byte column,
+/// matched text and capture group values. `options.limit` caps the row count.
+#[wasm_bindgen]
+pub fn export_search_report(
+    search_term: String,
+    options: JsValue,
+    format: String,
+    handle: JsValue,
+) -> Result<String, JsValue> {
+    let abort_flag = abort_flag_from_js(&handle)?;
+    let format: ReportFormat = format.parse().map_err(|e| js_err!("{}", e))?;
+    let options = SearchOptions::from_js(options)?;
+    let find_request = options.to_find_request(search_term);
+
+    let orchestrator = Orchestrator::new();
+    let records = orchestrator
+        .collect_match_records(find_request, options.effective_limit(), &abort_flag)
+        .map_err(|e| core_err("Search failed", &e))?;
+
+    render_report(&records, format).map_err(|e| js_err!("Failed to render report: {}", e))
+}
+
+/// Every match of `pattern` with its capture groups, as structured data.
+///
+/// Returns `{ path, line, column, text, start, end, groups, named }` per
+/// match: `groups` holds `$1..$N` as `{ name, text, start, end }` (byte
+/// offsets in the file) or `null` for groups that did not participate, and
+/// `named` maps each participating named group to its text.
+/// `options.limit` caps the number of matches.
+#[wasm_bindgen]
+pub fn extract_matches(
+    pattern: String,
+    options: JsValue,
+    handle: JsValue,
+) -> Result<JsValue, JsValue> {
+    let abort_flag = abort_flag_from_js(&handle)?;
+    let options = SearchOptions::from_js(options)?;
+    let find_request = options.to_find_request(pattern);
 
-        let hunk_obj = JsObjectBuilder::new()
-            .set("path", JsValue::from_str(hunk.path.as_str()))?
-            .set("lines", lines_array.into())?
-            .build();
-        results_array.push(&hunk_obj);
+    let matches = Orchestrator::new()
+        .extract_matches(find_request, options.effective_limit(), &abort_flag)
+        .map_err(|e| core_err("Search failed", &e))?;
+    to_js(&matches)
+}
+
+/// Most frequent tokens across the files matching `globs`, in one pass.
+///
+/// `token_kind` is `"identifier"` (default) or `"word"`. Returns up to
+/// `top_n` (default 100) `{ token, count, files }`, most frequent first,
+/// ties broken alphabetically.
+#[wasm_bindgen]
+pub fn get_token_frequencies(
+    globs: Option<Vec<String>>,
+    top_n: Option<usize>,
+    token_kind: Option<String>,
+    use_staged: Option<bool>,
+) -> Result<JsValue, JsValue> {
+    let kind: TokenKind = match token_kind {
+        Some(kind) => kind
+            .parse()
+            .map_err(|e| js_err!("Invalid token kind: {}", e))?,
+        None => TokenKind::default(),
+    };
+    let options = SearchOptions {
+        use_staged,
+        ..SearchOptions::default()
+    };
+    let mut req = options.to_find_request(String::new());
+    if globs.is_some() {
+        req.include_globs = globs;
     }
 
-    Ok(results_array.into())
+    let counts = Orchestrator::new()
+        .token_frequencies(&req, kind, top_n.unwrap_or(DEFAULT_TOP_TOKENS))
+        .map_err(|e| js_err!("Failed to count tokens: {}", e))?;
+
+    to_js(&counts)
 }
 
+/// For each changed path, suggest likely-related files: tests by naming
+/// convention (or the source, for a test), files whose import lines name the
+/// module, and siblings in the same directory.
+///
+/// `per_kind` caps each kind of suggestion per path (default 10).
 #[wasm_bindgen]
+pub fn suggest_related_files(
+    paths: Vec<String>,
+    use_staged: Option<bool>,
+    per_kind: Option<usize>,
+) -> Result<JsValue, JsValue> {
+    let keys = paths
+        .iter()
+        .map(|p| create_path_key(p).map_err(|e| js_err!("Invalid path '{}': {}", p, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let space = host_defaults().search_space(use_staged);
+    let index = get_index_manager()
+        .index_for(space)
+        .map_err(|e| js_err!("Failed to access index: {}", e))?;
+
+    let related = suggest_related(&index, &keys, per_kind.unwrap_or(10))
+        .map_err(|e| js_err!("Failed to suggest related files: {}", e))?;
+    serde_wasm_bindgen::to_value(&related)
+        .map_err(|e| js_err!("Failed to serialize related files: {}", e))
+}
+
+#[wasm_bindgen]
+#[allow(clippy::too_many_arguments)]
 pub fn list_files_from_wasm(
     path_prefix: Option<String>,
     glob_pattern: Option<String>,
     use_staged: Option<bool>,
     limit: Option<usize>,
     offset: Option<usize>,
+    modified_after: Option<f64>,
+    modified_before: Option<f64>,
+    with_checksums: Option<bool>,
+    include_hidden: Option<bool>,
+    include_ignored: Option<bool>,
+    glob_options: JsValue,
+    include_junk: Option<bool>,
 ) -> Result<JsValue, JsValue> {
-    let staged = use_staged.unwrap_or(true);
-    let limit = limit.unwrap_or(100).min(100);
+    let glob_options: GlobOptions = if glob_options.is_undefined() || glob_options.is_null() {
+        GlobOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(glob_options)
+            .map_err(|e| js_err!("Invalid glob options: {}", e))?
+    };
+    let defaults = host_defaults();
+    let space = defaults.search_space(use_staged);
+    let with_checksums = with_checksums.unwrap_or(false);
+    let visibility = get_index_manager()
+        .config()
+        .visibility(include_hidden, include_junk);
+    let include_ignored = include_ignored.unwrap_or(false);
+    let limit = defaults.result_limit(limit).unwrap_or(100).min(100);
     let offset = offset.unwrap_or(0);
+    let modified_after = modified_after.map(millis_to_unix_seconds);
+    let modified_before = modified_before.map(millis_to_unix_seconds);
 
-    let index = if staged {
+    let index = if space == SearchSpace::Staged {
         match get_index_manager().staged_index() {
             Ok(idx) => idx,
             Err(e) => return Err(js_err!("Failed to access staged index: {}", e)),
@@ -113,22 +887,28 @@ pub fn list_files_from_wasm(
         get_index_manager().active_index()
     };
 
-    let files: Vec<_> = if let Some(pattern) = glob_pattern {
-        match pattern.as_str() {
-            "" | "*" | "**/*" => index.iter_sorted().collect(),
-            _ => {
-                let glob =
-                    Glob::new(&pattern).map_err(|e| js_err!("Invalid glob pattern: {}", e))?;
-                let matcher = glob.compile_matcher();
-                index
-                    .iter_sorted()
-                    .filter(|(path, _)| matcher.is_match(path.as_str()))
-                    .collect()
-            }
-        }
+    let ignore = if include_ignored {
+        None
     } else {
-        index.iter_sorted().collect()
+        defaults
+            .ignore_set(&index, get_index_manager().generation())
+            .map_err(|e| core_err("Invalid ignorePatterns", &e))?
     };
+    let visible = index
+        .iter_sorted()
+        .filter(|(path, entry)| entry.is_visible(path, visibility))
+        .filter(|(path, _)| {
+            ignore
+                .as_ref()
+                .is_none_or(|ignore| !ignore.is_ignored(path.as_str()))
+        });
+
+    let patterns: Vec<String> = glob_pattern.into_iter().filter(|p| !p.is_empty()).collect();
+    let globs = compile_globs(Some(&patterns), &glob_options)
+        .map_err(|e| core_err("Invalid glob pattern", &e))?;
+    let files: Vec<_> = visible
+        .filter(|(path, _)| globs.as_ref().is_none_or(|g| g.is_match(path.as_str())))
+        .collect();
 
     let filtered_files: Vec<_> = if let Some(prefix) = path_prefix {
         files
@@ -139,27 +919,58 @@ pub fn list_files_from_wasm(
         files
     };
 
+    let filtered_files: Vec<_> = filtered_files
+        .into_iter()
+        .filter(|(_, entry)| entry.modified_within(modified_after, modified_before))
+        .collect();
+
     let total_count = filtered_files.len();
     let end = (offset + limit).min(total_count);
 
-    let results_array = Array::new();
-    for (path, entry) in filtered_files.into_iter().skip(offset).take(end - offset) {
-        let obj = JsObjectBuilder::new()
-            .set("path", JsValue::from_str(path.as_str()))?
-            .set("size", JsValue::from_f64(entry.size() as f64))?
-            .set("mtime", JsValue::from_f64(entry.mtime() as f64 * 1000.0))?
-            .set("editable", JsValue::from_bool(entry.is_editable()))?
-            .build();
-        results_array.push(&obj);
-    }
+    let files = filtered_files
+        .into_iter()
+        .skip(offset)
+        .take(end - offset)
+        .map(|(path, entry)| ListedFile {
+            path: path.clone(),
+            size: entry.size(),
+            mtime: entry.mtime() as f64 * 1000.0,
+            editable: entry.is_editable(),
+            // Hex string: a u64 does not fit losslessly in a JS number.
+            checksum: with_checksums
+                .then(|| entry.content_hash().map(|hash| format!("{:016x}", hash))),
+        })
+        .collect();
+
+    to_js(&FileListing {
+        files,
+        total: total_count,
+        has_more: end < total_count,
+    })
+}
 
-    let response_obj = JsObjectBuilder::new()
-        .set("files", results_array.into())?
-        .set("total", JsValue::from(total_count as u32))?
-        .set("hasMore", JsValue::from_bool(end < total_count))?
-        .build();
+/// One page of `list_files_from_wasm`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FileListing {
+    pub files: Vec<ListedFile>,
+    /// Files matching the filters, across all pages.
+    pub total: usize,
+    pub has_more: bool,
+}
 
-    Ok(response_obj)
+/// A file in a [`FileListing`].
+#[derive(Serialize, JsonSchema)]
+pub struct ListedFile {
+    pub path: PathKey,
+    pub size: u64,
+    /// JavaScript milliseconds.
+    pub mtime: f64,
+    pub editable: bool,
+    /// XXH3-64 as 16 hex digits, only with `withChecksums`; `null` for
+    /// metadata-only entries.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checksum: Option<Option<String>>,
 }
 
 use crate::globals::get_index_manager;
@@ -0,0 +1,330 @@
+use crate::bindings::search_ops::SearchOptions;
+use crate::globals::{get_index_manager, with_embeddings};
+use crate::js_err;
+use crate::orchestrator::Orchestrator;
+use crate::utils::to_js;
+use conduit_core::fs::{Index, PathKey};
+use conduit_core::tools::{rank_chunks, Chunk, ChunkOptions};
+use js_sys::{Array, Float32Array, Function, Promise};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+/// Texts passed to the provider per call.
+const EMBED_BATCH: usize = 64;
+
+/// Options for the semantic bindings: search filters plus chunking.
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+pub struct SemanticOptions {
+    #[serde(flatten)]
+    search: SearchOptions,
+    /// Lines per chunk (default 40).
+    max_chunk_lines: Option<usize>,
+    /// Lines shared by consecutive chunks (default 5).
+    overlap: Option<usize>,
+}
+
+/// A chunk ranked by `semantic_search`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoredChunk {
+    pub id: String,
+    pub path: PathKey,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub hash: String,
+    /// Cosine similarity to the query.
+    pub score: f32,
+}
+
+/// A chunk returned by `pending_embeddings`, with the text to embed.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingChunk {
+    pub hash: String,
+    pub path: PathKey,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub text: String,
+}
+
+/// A chunk returned by `chunk_workspace`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WorkspaceChunk {
+    /// Depends on path and chunk text only.
+    pub id: String,
+    pub path: PathKey,
+    pub start_line: usize,
+    pub end_line: usize,
+    pub hash: String,
+    pub epoch: u64,
+}
+
+impl SemanticOptions {
+    fn from_js(options: JsValue) -> Result<Self, JsValue> {
+        if options.is_undefined() || options.is_null() {
+            return Ok(Self::default());
+        }
+        serde_wasm_bindgen::from_value(options)
+            .map_err(|e| js_err!("Invalid semantic search options: {}", e))
+    }
+
+    fn chunk_options(&self) -> ChunkOptions {
+        let defaults = ChunkOptions::default();
+        ChunkOptions {
+            max_lines: self.max_chunk_lines.unwrap_or(defaults.max_lines),
+            overlap: self.overlap.unwrap_or(defaults.overlap),
+        }
+    }
+
+    /// Chunk the files selected by the search filters.
+    fn chunks(&self) -> Result<(std::sync::Arc<Index>, Vec<Chunk>), JsValue> {
+        let req = self.search.to_find_request(String::new());
+        Orchestrator::new()
+            .chunk_candidates(&req, self.chunk_options())
+            .map_err(|e| js_err!("Failed to chunk files: {}", e))
+    }
+}
+
+fn hash_to_hex(hash: u64) -> String {
+    format!("{:016x}", hash)
+}
+
+fn chunk_text(index: &Index, chunk: &Chunk) -> String {
+    let content = index
+        .get_file(&chunk.path)
+        .and_then(|entry| entry.search_content())
+        .unwrap_or_default();
+    String::from_utf8_lossy(chunk.text(content)).into_owned()
+}
+
+/// Call the provider with `texts`, expecting one vector per text back.
+fn call_provider(provider: &Function, texts: &[String]) -> Result<Vec<Vec<f32>>, JsValue> {
+    let input: Array = texts.iter().map(|t| JsValue::from_str(t)).collect();
+    let output = provider.call1(&JsValue::NULL, &input)?;
+    if output.is_instance_of::<Promise>() {
+        return Err(js_err!(
+            "Embedding provider returned a Promise; async providers should use \
+             pending_embeddings and put_embeddings instead"
+        ));
+    }
+    let vectors: Array = output
+        .dyn_into()
+        .map_err(|_| js_err!("Embedding provider must return an array of vectors"))?;
+    if vectors.length() as usize != texts.len() {
+        return Err(js_err!(
+            "Embedding provider returned {} vectors for {} texts",
+            vectors.length(),
+            texts.len()
+        ));
+    }
+    Ok(vectors
+        .iter()
+        .map(|v| Float32Array::new(&v).to_vec())
+        .collect())
+}
+
+fn registered_provider() -> Result<Function, JsValue> {
+    with_embeddings(|e| e.provider.clone()).ok_or_else(|| {
+        js_err!("No embedding provider registered; call register_embedding_provider first")
+    })
+}
+
+/// Embed and cache every chunk without a cached vector.
+fn fill_cache(index: &Index, chunks: &[Chunk]) -> Result<(), JsValue> {
+    let missing: Vec<(u64, String)> = with_embeddings(|e| {
+        e.cache
+            .missing(chunks)
+            .into_iter()
+            .map(|c| (c.hash, chunk_text(index, c)))
+            .collect()
+    });
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let provider = registered_provider()?;
+    for batch in missing.chunks(EMBED_BATCH) {
+        let texts: Vec<String> = batch.iter().map(|(_, text)| text.clone()).collect();
+        let vectors = call_provider(&provider, &texts)?;
+        with_embeddings(|e| {
+            batch
+                .iter()
+                .zip(vectors)
+                .try_for_each(|((hash, _), vector)| e.cache.insert(*hash, vector))
+        })
+        .map_err(|e| js_err!("Failed to cache embedding: {}", e))?;
+    }
+    Ok(())
+}
+
+fn rank_to_js(query: &[f32], chunks: &[Chunk], top_k: Option<usize>) -> Result<JsValue, JsValue> {
+    let ranked = with_embeddings(|e| rank_chunks(query, chunks, &e.cache, top_k.unwrap_or(10)));
+
+    let results: Vec<ScoredChunk> = ranked
+        .into_iter()
+        .map(|scored| ScoredChunk {
+            id: hash_to_hex(scored.chunk.id),
+            path: scored.chunk.path.clone(),
+            start_line: scored.chunk.start_line,
+            end_line: scored.chunk.end_line,
+            hash: hash_to_hex(scored.chunk.hash),
+            score: scored.score,
+        })
+        .collect();
+    to_js(&results)
+}
+
+/// Register the function conduit calls to embed text.
+///
+/// It receives an array of strings and must synchronously return an array
+/// of equal-length vectors (number arrays or `Float32Array`s), one per string.
+#[wasm_bindgen]
+pub fn register_embedding_provider(provider: Function) {
+    with_embeddings(|e| e.provider = Some(provider));
+}
+
+/// Remove the embedding provider. Cached vectors are kept.
+#[wasm_bindgen]
+pub fn clear_embedding_provider() {
+    with_embeddings(|e| e.provider = None);
+}
+
+/// Drop all cached vectors, e.g. after switching embedding models.
+#[wasm_bindgen]
+pub fn clear_embedding_cache() {
+    with_embeddings(|e| e.cache.clear());
+}
+
+/// Rank chunks of the selected files by similarity to `query`.
+///
+/// Chunks without a cached vector are embedded first via the registered
+/// provider. Returns the `top_k` best chunks (default 10), best first.
+#[wasm_bindgen]
+pub fn semantic_search(
+    query: String,
+    top_k: Option<usize>,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    let options = SemanticOptions::from_js(options)?;
+    let (index, chunks) = options.chunks()?;
+    fill_cache(&index, &chunks)?;
+
+    let provider = registered_provider()?;
+    let query_vector = call_provider(&provider, &[query])?
+        .pop()
+        .unwrap_or_default();
+    rank_to_js(&query_vector, &chunks, top_k)
+}
+
+/// Like `semantic_search` with a precomputed query vector and no provider
+/// calls; chunks without a cached vector are skipped.
+#[wasm_bindgen]
+pub fn semantic_search_by_vector(
+    query: Vec<f32>,
+    top_k: Option<usize>,
+    options: JsValue,
+) -> Result<JsValue, JsValue> {
+    let options = SemanticOptions::from_js(options)?;
+    let (_, chunks) = options.chunks()?;
+    rank_to_js(&query, &chunks, top_k)
+}
+
+/// Chunks of the selected files that have no cached vector yet, as
+/// `{ hash, path, startLine, endLine, text }`, one per distinct hash.
+///
+/// For async providers: embed these, then pass the results to `put_embeddings`.
+#[wasm_bindgen]
+pub fn pending_embeddings(options: JsValue) -> Result<JsValue, JsValue> {
+    let options = SemanticOptions::from_js(options)?;
+    let (index, chunks) = options.chunks()?;
+
+    let missing: Vec<Chunk> =
+        with_embeddings(|e| e.cache.missing(&chunks).into_iter().cloned().collect());
+    let pending: Vec<PendingChunk> = missing
+        .iter()
+        .map(|chunk| PendingChunk {
+            hash: hash_to_hex(chunk.hash),
+            path: chunk.path.clone(),
+            start_line: chunk.start_line,
+            end_line: chunk.end_line,
+            text: chunk_text(&index, chunk),
+        })
+        .collect();
+    to_js(&pending)
+}
+
+/// Cache vectors for chunk hashes from `pending_embeddings`.
+///
+/// Returns the number of vectors stored.
+#[wasm_bindgen]
+pub fn put_embeddings(hashes: Vec<String>, vectors: Array) -> Result<u32, JsValue> {
+    if hashes.len() != vectors.length() as usize {
+        return Err(js_err!(
+            "Got {} hashes but {} vectors",
+            hashes.len(),
+            vectors.length()
+        ));
+    }
+
+    let parsed = hashes
+        .iter()
+        .map(|h| u64::from_str_radix(h, 16).map_err(|_| js_err!("Invalid chunk hash '{}'", h)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    with_embeddings(|e| {
+        parsed
+            .iter()
+            .zip(vectors.iter())
+            .try_for_each(|(hash, v)| e.cache.insert(*hash, Float32Array::new(&v).to_vec()))
+    })
+    .map_err(|e| js_err!("Failed to cache embedding: {}", e))?;
+
+    Ok(parsed.len() as u32)
+}
+
+/// Split the workspace into chunks without embedding anything.
+///
+/// Returns `{ id, path, startLine, endLine, hash, epoch }` per chunk, in
+/// path then line order. Ids depend on path and chunk text only, so a
+/// pipeline can diff ids after staged edits land and re-index just the
+/// chunks that appeared or vanished.
+#[wasm_bindgen]
+pub fn chunk_workspace(
+    max_chunk_lines: Option<usize>,
+    overlap: Option<usize>,
+    globs: Option<Vec<String>>,
+    use_staged: Option<bool>,
+) -> Result<JsValue, JsValue> {
+    let options = SemanticOptions {
+        search: SearchOptions {
+            use_staged,
+            ..SearchOptions::default()
+        },
+        max_chunk_lines,
+        overlap,
+    };
+    let mut req = options.search.to_find_request(String::new());
+    if globs.is_some() {
+        req.include_globs = globs;
+    }
+    let (_, chunks) = Orchestrator::new()
+        .chunk_candidates(&req, options.chunk_options())
+        .map_err(|e| js_err!("Failed to chunk files: {}", e))?;
+
+    let manager = get_index_manager();
+    let results: Vec<WorkspaceChunk> = chunks
+        .iter()
+        .map(|chunk| WorkspaceChunk {
+            id: hash_to_hex(chunk.id),
+            path: chunk.path.clone(),
+            start_line: chunk.start_line,
+            end_line: chunk.end_line,
+            hash: hash_to_hex(chunk.hash),
+            epoch: manager.edit_epoch(&chunk.path),
+        })
+        .collect();
+    to_js(&results)
+}
@@ -0,0 +1,28 @@
+use crate::globals::get_index_manager;
+use crate::utils::core_err;
+use conduit_core::fs::Session;
+use wasm_bindgen::prelude::*;
+
+/// The session as bytes for `import_session`: references to the active
+/// files and every staged change, with undo history and checkpoints left
+/// out.
+#[wasm_bindgen]
+pub fn export_session() -> Result<Vec<u8>, JsValue> {
+    get_index_manager()
+        .export_session()
+        .to_bytes()
+        .map_err(|e| core_err("Failed to export session", &e))
+}
+
+/// Resume a session from `export_session`, replacing current staging.
+///
+/// Reload the same active files first; fails with `code: "content_changed"`
+/// or `"file_not_found"` if any differ or are missing, leaving staging as
+/// it was.
+#[wasm_bindgen]
+pub fn import_session(bytes: &[u8]) -> Result<(), JsValue> {
+    let session = Session::from_bytes(bytes).map_err(|e| core_err("Invalid session", &e))?;
+    get_index_manager()
+        .import_session(session)
+        .map_err(|e| core_err("Failed to import session", &e))
+}
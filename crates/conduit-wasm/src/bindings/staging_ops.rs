@@ -1,13 +1,37 @@
-use crate::globals::{create_path_key, get_index_manager};
+use crate::globals::{create_path_key, get_index_manager, host_defaults, with_load_progress};
 use crate::js_err;
 use crate::orchestrator::Orchestrator;
-use crate::utils::JsObjectBuilder;
-use conduit_core::fs::FileEntry;
-use conduit_core::DiffTool;
-use js_sys::{Array, Boolean, Uint8Array};
+use crate::utils::{core_err, millis_to_unix_seconds, pack_bytes, to_js};
+use conduit_core::fs::{
+    compile_globs, BatchReport, DeltaEntry, FileEntry, GlobOptions, LoadProgress, PathKey,
+    SkipReason, StagedView,
+};
+use conduit_core::tools::{
+    read_archive_with, refine_diff, render_change_summary, AbortFlag, ArchiveFormat,
+    DiffGranularity, InlineSpan,
+};
+use conduit_core::{DiffTool, Error, FileChangeStatus, FileDiff, ModifiedFileSummary, Versioned};
+use js_sys::{Boolean, Function, Uint8Array};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
 use wasm_bindgen::prelude::*;
 
+/// Run `f` on one consistent view of staging.
+///
+/// Bindings reporting several figures about staging derive all of them from
+/// this view instead of calling `staged_index()` again, so the figures agree
+/// and the view's generation says which state they describe.
+pub(crate) fn with_staged_view<T>(
+    f: impl FnOnce(&StagedView) -> Result<T, JsValue>,
+) -> Result<T, JsValue> {
+    let view = get_index_manager()
+        .staged_view()
+        .map_err(|e| js_err!("Failed to access staged index: {}", e))?;
+    f(&view)
+}
+
 #[wasm_bindgen]
 pub fn clear_wasm_index() -> Result<(), JsValue> {
     let manager = get_index_manager();
@@ -16,6 +40,12 @@ pub fn clear_wasm_index() -> Result<(), JsValue> {
         .map_err(|e| js_err!("Failed to begin staging: {}", e))
 }
 
+/// Stage a batch of files, returning a report of accepted and skipped files.
+///
+/// Files with invalid paths or timestamps and files over the configured
+/// `maxFileSize` are skipped rather than failing the batch. Paths already
+/// loaded (in this or an earlier batch) follow the configured
+/// `duplicatePolicy`. Mismatched array lengths are still an error.
 #[wasm_bindgen]
 pub fn add_files_to_staging(
     paths: Vec<String>,
@@ -23,7 +53,80 @@ pub fn add_files_to_staging(
     mtimes: Vec<f64>,
     permissions: Vec<Boolean>,
     text_contents: Option<Vec<String>>,
-) -> Result<usize, JsValue> {
+) -> Result<JsValue, JsValue> {
+    let report = stage_batch(paths, contents, mtimes, permissions, text_contents)?;
+    serde_wasm_bindgen::to_value(&report)
+        .map_err(|e| js_err!("Failed to serialize load report: {}", e))
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ArchiveLoadOptions {
+    /// Only load paths matching one of these globs.
+    include_globs: Option<Vec<String>>,
+    /// Skip paths matching one of these globs.
+    exclude_globs: Option<Vec<String>>,
+    /// How the globs are matched.
+    glob_options: GlobOptions,
+    /// Whether loaded files may be edited; defaults to true.
+    editable: Option<bool>,
+}
+
+/// Unpack a zip, tar or `.tar.gz` archive and stage its files, as one
+/// batch of a load between `begin_file_load` and `commit_file_load`.
+///
+/// Paths and mtimes come from the archive; directories and links are
+/// skipped. Files the globs filter out are never decompressed. Returns the
+/// same report as `add_files_to_staging`.
+#[wasm_bindgen]
+pub fn load_archive(bytes: &Uint8Array, options: JsValue) -> Result<JsValue, JsValue> {
+    let options: ArchiveLoadOptions = if options.is_undefined() || options.is_null() {
+        ArchiveLoadOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)
+            .map_err(|e| js_err!("Invalid archive options: {}", e))?
+    };
+    let include = compile_globs(options.include_globs.as_deref(), &options.glob_options)
+        .map_err(|e| core_err("Invalid include glob", &e))?;
+    let exclude = compile_globs(options.exclude_globs.as_deref(), &options.glob_options)
+        .map_err(|e| core_err("Invalid exclude glob", &e))?;
+    let editable = options.editable.unwrap_or(true);
+
+    // Filter and check the memory ceiling before each file is inflated.
+    let manager = get_index_manager();
+    let mut unpacked = 0u64;
+    let entries = read_archive_with(&bytes.to_vec(), |path, size| {
+        if !include.as_ref().is_none_or(|g| g.is_match(path))
+            || exclude.as_ref().is_some_and(|g| g.is_match(path))
+        {
+            return Ok(false);
+        }
+        unpacked += size;
+        manager.reserve_memory(unpacked)?;
+        Ok(true)
+    })
+    .map_err(|e| core_err("Failed to read archive", &e))?;
+    let len = entries.len();
+    let files = entries.into_iter().map(|entry| IncomingFile {
+        path: entry.path,
+        content: entry.content,
+        mtime: entry.mtime as f64 * 1000.0,
+        editable,
+        text: None,
+    });
+    let report = stage_files(files, len)?;
+    serde_wasm_bindgen::to_value(&report)
+        .map_err(|e| js_err!("Failed to serialize load report: {}", e))
+}
+
+/// Validate and stage a batch, recording it in the load progress.
+pub(crate) fn stage_batch(
+    paths: Vec<String>,
+    contents: Vec<Uint8Array>,
+    mtimes: Vec<f64>,
+    permissions: Vec<Boolean>,
+    text_contents: Option<Vec<String>>,
+) -> Result<BatchReport, JsValue> {
     let len = paths.len();
     if contents.len() != len || mtimes.len() != len || permissions.len() != len {
         return Err(js_err!(
@@ -45,134 +148,447 @@ pub fn add_files_to_staging(
         }
     }
 
+    let mut texts = text_contents.map(Vec::into_iter);
+    let files = paths
+        .into_iter()
+        .zip(contents)
+        .zip(mtimes)
+        .zip(permissions)
+        .map(|(((path, content), mtime), editable)| IncomingFile {
+            path,
+            content,
+            mtime,
+            editable: editable.value_of(),
+            // Empty text means none was supplied for this file.
+            text: texts
+                .as_mut()
+                .and_then(Iterator::next)
+                .filter(|text| !text.is_empty()),
+        });
+    stage_files(files, len)
+}
+
+/// Content of a file offered for staging, copied in only once accepted.
+pub(crate) trait FileSource {
+    /// Size of the file.
+    fn len(&self) -> usize;
+    /// Bytes held once staged.
+    fn resident_len(&self) -> usize {
+        self.len()
+    }
+    /// Content to hold, or `None` to stage metadata only.
+    fn into_bytes(self) -> Option<Vec<u8>>;
+}
+
+impl FileSource for Uint8Array {
+    fn len(&self) -> usize {
+        self.length() as usize
+    }
+
+    fn into_bytes(self) -> Option<Vec<u8>> {
+        Some(self.to_vec())
+    }
+}
+
+impl FileSource for Vec<u8> {
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+
+    fn into_bytes(self) -> Option<Vec<u8>> {
+        Some(self)
+    }
+}
+
+/// A file whose content the host supplies on demand.
+pub(crate) struct MetadataOnly(pub usize);
+
+impl FileSource for MetadataOnly {
+    fn len(&self) -> usize {
+        self.0
+    }
+
+    fn resident_len(&self) -> usize {
+        0
+    }
+
+    fn into_bytes(self) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+/// A file offered for staging by a load.
+pub(crate) struct IncomingFile<C> {
+    pub path: String,
+    pub content: C,
+    /// JavaScript millisecond timestamp.
+    pub mtime: f64,
+    pub editable: bool,
+    /// Decoded text to search instead of the raw bytes.
+    pub text: Option<String>,
+}
+
+/// Validate and stage `files`, recording them in the load progress.
+///
+/// Files with invalid paths or timestamps and files over the configured
+/// `maxFileSize` are skipped; paths already loaded follow the configured
+/// `duplicatePolicy`. `len` is a capacity hint.
+pub(crate) fn stage_files<C: FileSource>(
+    files: impl IntoIterator<Item = IncomingFile<C>>,
+    len: usize,
+) -> Result<BatchReport, JsValue> {
+    let manager = get_index_manager();
+    let defaults = host_defaults();
+    let max_file_size = defaults.max_file_size;
+    let policy = defaults.duplicate_policy.unwrap_or_default();
+    let mut report = BatchReport::default();
+    // Position of each path in `entries`, so in-batch duplicates replace in place.
+    let mut batch_slots: HashMap<PathKey, usize> = HashMap::with_capacity(len);
+    let mut staged: Vec<(PathKey, i64, u64)> = Vec::with_capacity(len);
     let mut entries = Vec::with_capacity(len);
 
-    for i in 0..len {
-        if paths[i].is_empty() {
-            return Err(js_err!("Empty path at index {}", i));
+    for (i, file) in files.into_iter().enumerate() {
+        let raw_path = &file.path;
+        if raw_path.is_empty() {
+            report.skip(
+                raw_path,
+                SkipReason::InvalidPath,
+                format!("Empty path at index {i}"),
+            );
+            continue;
         }
 
-        let path_key = create_path_key(&paths[i])
-            .map_err(|e| js_err!("Invalid path '{}': {}", paths[i], e))?;
+        let path_key = match create_path_key(raw_path) {
+            Ok(key) => key,
+            Err(e) => {
+                report.skip(raw_path, SkipReason::InvalidPath, e.to_string());
+                continue;
+            }
+        };
 
-        if !mtimes[i].is_finite() || mtimes[i] < 0.0 {
-            return Err(js_err!(
-                "Invalid timestamp for '{}': {}",
-                paths[i],
-                mtimes[i]
-            ));
+        if !file.mtime.is_finite() || file.mtime < 0.0 {
+            report.skip(
+                raw_path,
+                SkipReason::InvalidTimestamp,
+                format!("Invalid timestamp: {}", file.mtime),
+            );
+            continue;
         }
 
-        let original_bytes = contents[i].to_vec();
-        let is_editable = permissions[i].value_of();
+        let size = file.content.len();
+        if let Some(limit) = max_file_size {
+            if size > limit {
+                report.skip(
+                    raw_path,
+                    SkipReason::TooLarge,
+                    format!("{size} bytes exceeds limit of {limit}"),
+                );
+                continue;
+            }
+        }
 
-        let search_content = text_contents.as_ref().and_then(|texts| {
-            let text = &texts[i];
-            if text.is_empty() {
-                None // Skip empty text content
-            } else {
-                Some(text.as_bytes().to_vec())
+        let timestamp = millis_to_unix_seconds(file.mtime);
+        let batch_slot = batch_slots.get(&path_key).copied();
+        let existing_mtime = match batch_slot {
+            Some(slot) => Some(staged[slot].1),
+            None => with_load_progress(|progress| progress.loaded_mtime(&path_key)),
+        };
+        if let Some(existing_mtime) = existing_mtime {
+            report.duplicates.push(raw_path.clone());
+            let replace = policy
+                .should_replace(&path_key, existing_mtime, timestamp)
+                .map_err(|e| js_err!("Failed to load '{}': {}", raw_path, e))?;
+            if !replace {
+                report.skip(
+                    raw_path,
+                    SkipReason::Duplicate,
+                    format!("kept earlier entry for '{}'", path_key.as_str()),
+                );
+                continue;
             }
-        });
+        }
 
-        let timestamp = (mtimes[i] / 1000.0).floor() as i64;
-        let ext = FileEntry::get_extension(path_key.as_str());
+        // Refuse the batch before copying content that would not fit.
+        let resident = file.content.resident_len();
+        manager
+            .reserve_memory(report.bytes_staged + resident as u64)
+            .map_err(|e| core_err(format_args!("Failed to load '{raw_path}'"), &e))?;
 
-        let entry = if let Some(search_content) = search_content {
-            FileEntry::from_bytes_with_text(
+        let ext = FileEntry::get_extension(path_key.as_str());
+        let entry = match (file.content.into_bytes(), file.text) {
+            (None, _) => FileEntry::new(ext, size as u64, timestamp, file.editable),
+            (Some(original_bytes), Some(search_content)) => FileEntry::from_bytes_with_text(
                 ext,
                 timestamp,
                 Arc::from(original_bytes),
-                Arc::from(search_content),
-                is_editable,
-            )
-        } else {
-            FileEntry::from_bytes(ext, timestamp, Arc::from(original_bytes), is_editable)
+                Arc::from(search_content.into_bytes()),
+                file.editable,
+            ),
+            (Some(original_bytes), None) => {
+                FileEntry::from_bytes(ext, timestamp, Arc::from(original_bytes), file.editable)
+            }
         };
 
-        entries.push((path_key, entry));
+        match batch_slot {
+            Some(slot) => {
+                report.bytes_staged -= staged[slot].2;
+                staged[slot] = (path_key.clone(), timestamp, resident as u64);
+                entries[slot] = (path_key, entry);
+            }
+            None => {
+                report.accepted += 1;
+                batch_slots.insert(path_key.clone(), entries.len());
+                staged.push((path_key.clone(), timestamp, resident as u64));
+                entries.push((path_key, entry));
+            }
+        }
+        report.bytes_staged += resident as u64;
     }
 
-    let manager = get_index_manager();
     manager
         .add_files_to_staging(entries)
-        .map_err(|e| js_err!("Failed to add files to staging: {}", e))?;
+        .map_err(|e| core_err("Failed to add files to staging", &e))?;
 
-    Ok(len)
+    with_load_progress(|progress| progress.record(&mut report, &staged));
+    Ok(report)
 }
 
+/// Totals for the current or most recent load: batches, accepted files,
+/// bytes staged, and every skipped file with its reason.
 #[wasm_bindgen]
-pub fn promote_staged_index() -> Result<usize, JsValue> {
-    let manager = get_index_manager();
-    manager
-        .staged_index()
-        .map_err(|e| js_err!("Failed to access staged index: {}", e))?;
-    let count = manager
-        .staged_index()
-        .map_err(|e| js_err!("Failed to get staged index: {}", e))?
-        .len();
+pub fn get_load_progress() -> Result<JsValue, JsValue> {
+    let progress = with_load_progress(|progress| progress.clone());
+    serde_wasm_bindgen::to_value(&progress)
+        .map_err(|e| js_err!("Failed to serialize load progress: {}", e))
+}
 
-    manager
-        .promote_staged()
-        .map_err(|e| js_err!("Failed to commit staged files: {}", e))?;
+/// Content bytes held by the active and staged indexes, and the configured
+/// `memoryCeiling` (or `null`).
+#[wasm_bindgen]
+pub fn get_memory_usage() -> Result<JsValue, JsValue> {
+    let usage = get_index_manager().memory_usage();
+    to_js(&MemoryUsageInfo {
+        active_bytes: usage.active_bytes,
+        staged_bytes: usage.staged_bytes,
+        total_bytes: usage.total(),
+        ceiling: usage.ceiling,
+    })
+}
 
-    Ok(count)
+/// Result of `get_memory_usage`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryUsageInfo {
+    pub active_bytes: u64,
+    pub staged_bytes: u64,
+    pub total_bytes: u64,
+    pub ceiling: Option<u64>,
 }
 
+/// Estimated memory held by file content, duplicate search text, cached
+/// line indexes and paths.
 #[wasm_bindgen]
-pub fn begin_index_staging() -> Result<(), JsValue> {
-    let manager = get_index_manager();
-    manager
-        .begin_staging()
-        .map_err(|e| js_err!("Failed to begin staging: {}", e))
+pub fn get_memory_report() -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(&get_index_manager().memory_report())
+        .map_err(|e| js_err!("Failed to serialize memory report: {}", e))
 }
 
+/// Drop search text that duplicates file bytes and stale cached line
+/// indexes; returns what was freed.
 #[wasm_bindgen]
-pub fn get_staging_info() -> Result<JsValue, JsValue> {
-    let manager = get_index_manager();
-    let modifications = manager
-        .get_staged_modifications()
-        .map_err(|e| js_err!("Failed to get staged modifications: {}", e))?;
+pub fn compact_index() -> Result<JsValue, JsValue> {
+    serde_wasm_bindgen::to_value(&get_index_manager().compact())
+        .map_err(|e| js_err!("Failed to serialize compaction report: {}", e))
+}
 
-    let deletions = manager
-        .get_staged_deletions()
-        .map_err(|e| js_err!("Failed to get staged deletions: {}", e))?;
+/// Undo the last staged operation; returns its label, or `undefined` if
+/// there is nothing to undo.
+#[wasm_bindgen]
+pub fn undo_last_operation() -> Result<Option<String>, JsValue> {
+    get_index_manager()
+        .undo()
+        .map_err(|e| core_err("Failed to undo", &e))
+}
 
-    let staged = manager
-        .staged_index()
-        .map_err(|e| js_err!("Failed to access staged index: {}", e))?;
-    let count = staged.len() as u32;
+/// Reapply the last undone operation; returns its label, or `undefined`.
+#[wasm_bindgen]
+pub fn redo_operation() -> Result<Option<String>, JsValue> {
+    get_index_manager()
+        .redo()
+        .map_err(|e| core_err("Failed to redo", &e))
+}
 
-    let obj = JsObjectBuilder::new()
-        .set("fileCount", JsValue::from(count))?
-        .set("modifiedCount", JsValue::from(modifications.len() as u32))?
-        .set("deletedCount", JsValue::from(deletions.len() as u32))?
-        .build();
+/// Labels of the operations that can be undone and redone, most recent last.
+#[wasm_bindgen]
+pub fn get_undo_history() -> Result<JsValue, JsValue> {
+    to_js(&get_index_manager().undo_state())
+}
 
-    Ok(obj)
+/// Promote staging, returning the number of files in the promoted index.
+#[wasm_bindgen]
+pub fn promote_staged_index() -> Result<usize, JsValue> {
+    let promoted = get_index_manager()
+        .promote_staged()
+        .map_err(|e| js_err!("Failed to commit staged files: {}", e))?;
+    Ok(promoted.index.len())
 }
 
+/// Begin staging, optionally labeled with the `owner` flow.
+///
+/// Without an owner an active session is reused as before. With one, the
+/// call is re-entrant for that owner and fails if anyone else holds the session.
 #[wasm_bindgen]
-pub fn commit_index_staging() -> Result<JsValue, JsValue> {
+pub fn begin_index_staging(owner: Option<String>) -> Result<(), JsValue> {
     let manager = get_index_manager();
-    let staged = manager
-        .staged_index()
-        .map_err(|e| js_err!("Failed to access staged index: {}", e))?;
-    let file_count = staged.len();
+    match owner {
+        Some(owner) => manager.begin_staging_as(&owner),
+        None => manager.begin_staging(),
+    }
+    .map_err(|e| js_err!("Failed to begin staging: {}", e))
+}
 
-    manager
+/// Owner label of the active staging session, or `null` if unlabeled.
+#[wasm_bindgen]
+pub fn get_staging_owner() -> Result<Option<String>, JsValue> {
+    get_index_manager()
+        .staging_owner()
+        .map_err(|e| js_err!("Failed to get staging owner: {}", e))
+}
+
+/// Fail unless `owner` began the active session. Without an owner only an
+/// unlabeled session passes; with no session the action reports its own error.
+fn check_owner(owner: Option<&str>, action: &str) -> Result<(), JsValue> {
+    match get_index_manager().check_staging_owner(owner) {
+        Ok(()) | Err(Error::StagingNotActive) => Ok(()),
+        Err(e) => Err(js_err!("Failed to {} staging: {}", action, e)),
+    }
+}
+
+#[wasm_bindgen]
+pub fn get_staging_info() -> Result<JsValue, JsValue> {
+    with_staged_view(|view| {
+        to_js(&StagingInfo {
+            file_count: view.index.len(),
+            modified_count: view.modifications().count(),
+            deleted_count: view.deletions().count(),
+            generation: view.generation,
+        })
+    })
+}
+
+/// Result of `get_staging_info`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StagingInfo {
+    pub file_count: usize,
+    pub modified_count: usize,
+    pub deleted_count: usize,
+    /// Which staging state the counts describe.
+    pub generation: u64,
+}
+
+/// Result of `commit_index_staging`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitInfo {
+    /// Files in the promoted index.
+    pub file_count: usize,
+    pub generation: u64,
+}
+
+#[wasm_bindgen]
+pub fn commit_index_staging(owner: Option<String>) -> Result<JsValue, JsValue> {
+    check_owner(owner.as_deref(), "commit")?;
+    let promoted = get_index_manager()
         .promote_staged()
         .map_err(|e| js_err!("Failed to promote staged index: {}", e))?;
 
-    let obj = JsObjectBuilder::new()
-        .set("fileCount", JsValue::from(file_count as u32))?
-        .build();
+    to_js(&CommitInfo {
+        file_count: promoted.index.len(),
+        generation: promoted.generation,
+    })
+}
+
+/// Result of `export_commit_delta`.
+#[derive(Serialize, JsonSchema)]
+pub struct CommitDeltaView {
+    /// Increments on every commit; a gap means a delta was missed.
+    pub sequence: u64,
+    /// Changed paths in sorted order.
+    pub changes: Vec<DeltaChange>,
+}
+
+/// One changed path of a [`CommitDeltaView`].
+#[derive(Serialize, JsonSchema)]
+pub struct DeltaChange {
+    pub path: PathKey,
+    pub deleted: bool,
+    #[serde(flatten)]
+    pub file: Option<DeltaFile>,
+}
+
+/// The committed file of a [`DeltaChange`] that was not deleted.
+#[derive(Serialize, JsonSchema)]
+pub struct DeltaFile {
+    /// A `Uint8Array`, or `null` for metadata-only entries.
+    #[serde(with = "serde_wasm_bindgen::preserve")]
+    #[schemars(with = "Option<Vec<u8>>")]
+    pub content: JsValue,
+    /// `"gzip"`, `"deflate"`, or `null` when `content` is uncompressed.
+    #[serde(with = "serde_wasm_bindgen::preserve")]
+    #[schemars(with = "Option<String>")]
+    pub encoding: JsValue,
+    /// XXH3-64 as 16 hex digits.
+    pub hash: Option<String>,
+    /// JavaScript milliseconds.
+    pub mtime: f64,
+}
+
+/// Files changed by the most recent commit, or `null` before the first one.
+///
+/// Lets hosts persist each commit as a compact delta instead of
+/// re-serializing the whole index. Contents follow the configured
+/// compression; each change's `encoding` says how its `content` is stored.
+#[wasm_bindgen]
+pub fn export_commit_delta() -> Result<JsValue, JsValue> {
+    let Some(delta) = get_index_manager().last_commit_delta() else {
+        return Ok(JsValue::NULL);
+    };
+    let policy = host_defaults().compression_policy();
+
+    let mut changes = Vec::with_capacity(delta.entries.len());
+    for DeltaEntry { path, entry } in &delta.entries {
+        let file = match entry {
+            Some(entry) => {
+                let (content, encoding) = match entry.bytes() {
+                    Some(bytes) => pack_bytes(&policy, bytes)?,
+                    None => (JsValue::NULL, JsValue::NULL),
+                };
+                Some(DeltaFile {
+                    content,
+                    encoding,
+                    hash: entry.content_hash().map(|h| format!("{:016x}", h)),
+                    mtime: entry.mtime() as f64 * 1000.0,
+                })
+            }
+            None => None,
+        };
+        changes.push(DeltaChange {
+            path: path.clone(),
+            deleted: file.is_none(),
+            file,
+        });
+    }
 
-    Ok(obj)
+    to_js(&CommitDeltaView {
+        sequence: delta.sequence,
+        changes,
+    })
 }
 
 #[wasm_bindgen]
-pub fn revert_index_staging() -> Result<(), JsValue> {
+pub fn revert_index_staging(owner: Option<String>) -> Result<(), JsValue> {
+    check_owner(owner.as_deref(), "revert")?;
     let manager = get_index_manager();
     manager
         .revert_staged()
@@ -181,153 +597,469 @@ pub fn revert_index_staging() -> Result<(), JsValue> {
 
 #[wasm_bindgen]
 pub fn get_staged_modifications() -> Result<JsValue, JsValue> {
-    let manager = get_index_manager();
-    let modifications = manager
-        .get_staged_modifications()
-        .map_err(|e| js_err!("Failed to get staged modifications: {}", e))?;
+    with_staged_view(|view| {
+        let modified: Vec<&PathKey> = view.modifications().map(|(path, _)| path).collect();
+        to_js(&modified)
+    })
+}
 
-    let modified_array = Array::new();
-    for (path, _) in &modifications {
-        modified_array.push(&JsValue::from_str(path.as_str()));
-    }
+#[wasm_bindgen]
+pub fn get_staged_deletions() -> Result<JsValue, JsValue> {
+    with_staged_view(|view| {
+        let deleted: Vec<&PathKey> = view.deletions().collect();
+        to_js(&deleted)
+    })
+}
 
-    Ok(modified_array.into())
+#[wasm_bindgen]
+pub fn get_modified_files_summary() -> Result<JsValue, JsValue> {
+    let summaries = Orchestrator::new()
+        .get_modified_files_summary(&AbortFlag::new())
+        .map_err(|e| js_err!("Failed to get modified files summary: {}", e))?;
+    summaries_to_js(summaries.items)
 }
 
+/// Summarize staged changes, calling `on_progress(done, total)` between
+/// files. Returning `false` from the callback stops early; the result is
+/// then marked `complete: false` and holds the files summarized so far.
 #[wasm_bindgen]
-pub fn get_staged_deletions() -> Result<JsValue, JsValue> {
-    let manager = get_index_manager();
-    let deletions = manager
-        .get_staged_deletions()
-        .map_err(|e| js_err!("Failed to get staged deletions: {}", e))?;
+pub fn get_modified_files_summary_with_progress(
+    on_progress: &Function,
+) -> Result<JsValue, JsValue> {
+    let abort_flag = AbortFlag::new();
+    let mut callback_error = None;
+    let summaries = Orchestrator::new()
+        .summarize_modified_files(&abort_flag, |done, total| {
+            if abort_flag.is_aborted() {
+                return;
+            }
+            let result = on_progress.call2(
+                &JsValue::NULL,
+                &JsValue::from(done as u32),
+                &JsValue::from(total as u32),
+            );
+            match result {
+                Ok(ret) if ret.as_bool() == Some(false) => abort_flag.abort(),
+                Ok(_) => {}
+                Err(e) => {
+                    callback_error = Some(e);
+                    abort_flag.abort();
+                }
+            }
+        })
+        .map_err(|e| js_err!("Failed to get modified files summary: {}", e))?;
 
-    let deleted_array = Array::new();
-    for path in &deletions {
-        deleted_array.push(&JsValue::from_str(path.as_str()));
+    if let Some(e) = callback_error {
+        return Err(e);
     }
 
-    Ok(deleted_array.into())
+    to_js(&SummaryProgress {
+        files: summaries
+            .items
+            .into_iter()
+            .map(ChangeSummary::from)
+            .collect(),
+        complete: summaries.complete,
+    })
 }
 
-#[wasm_bindgen]
-pub fn get_modified_files_summary() -> Result<JsValue, JsValue> {
-    let orchestrator = Orchestrator::new();
-    let summaries = orchestrator
-        .get_modified_files_summary()
-        .map_err(|e| js_err!("Failed to get modified files summary: {}", e))?;
+/// Result of `get_modified_files_summary_with_progress`.
+#[derive(Serialize, JsonSchema)]
+pub struct SummaryProgress {
+    pub files: Vec<ChangeSummary>,
+    /// `false` if the callback stopped the summary early.
+    pub complete: bool,
+}
 
-    let result_array = Array::new();
-    for summary in summaries {
-        let obj = JsObjectBuilder::new()
-            .set("path", JsValue::from_str(summary.path.as_str()))?
-            .set("linesAdded", JsValue::from(summary.lines_added as u32))?
-            .set("linesRemoved", JsValue::from(summary.lines_removed as u32))?
-            .set(
-                "status",
-                JsValue::from_str(&format!("{:?}", summary.status).to_lowercase()),
-            )?;
-
-        let obj = if let Some(moved_to) = summary.moved_to {
-            obj.set("movedTo", JsValue::from_str(moved_to.as_str()))?
-        } else {
-            obj
-        };
+/// One staged change in a modified files summary.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeSummary {
+    pub path: PathKey,
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    pub status: FileChangeStatus,
+    /// Destination of moved and renamed files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub moved_to: Option<PathKey>,
+    /// Percentage of lines a renamed file kept.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<u8>,
+}
 
-        result_array.push(&obj.build());
+impl From<ModifiedFileSummary> for ChangeSummary {
+    fn from(summary: ModifiedFileSummary) -> Self {
+        Self {
+            path: summary.path,
+            lines_added: summary.lines_added,
+            lines_removed: summary.lines_removed,
+            status: summary.status,
+            moved_to: summary.moved_to,
+            similarity: summary.similarity,
+        }
     }
+}
+
+fn summaries_to_js(summaries: Vec<ModifiedFileSummary>) -> Result<JsValue, JsValue> {
+    let summaries: Vec<ChangeSummary> = summaries.into_iter().map(ChangeSummary::from).collect();
+    to_js(&summaries)
+}
 
-    Ok(result_array.into())
+/// Staged changes as a markdown report grouped by directory.
+#[wasm_bindgen]
+pub fn export_change_summary_markdown() -> Result<String, JsValue> {
+    let summaries = Orchestrator::new()
+        .get_modified_files_summary(&AbortFlag::new())
+        .map_err(|e| js_err!("Failed to get modified files summary: {}", e))?;
+    Ok(render_change_summary(&summaries.items))
 }
 
+/// Operations applied to `path` in the current staging session, oldest first.
 #[wasm_bindgen]
-pub fn get_file_diff(path: String) -> Result<JsValue, JsValue> {
+pub fn get_staged_history(path: String) -> Result<JsValue, JsValue> {
+    let key = create_path_key(&path).map_err(|e| js_err!("Invalid path '{}': {}", path, e))?;
+    let history = get_index_manager()
+        .staged_history(&key)
+        .map_err(|e| js_err!("Failed to get staged history: {}", e))?;
+    serde_wasm_bindgen::to_value(&history)
+        .map_err(|e| js_err!("Failed to serialize staged history: {}", e))
+}
+
+/// Options for `get_file_diff`; all fields are optional.
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DiffOptions {
+    granularity: DiffGranularity,
+    context_lines: usize,
+}
+
+/// Diff of `path` between active and staged.
+///
+/// With `{ granularity: "word" }` or `"char"`, regions also carry
+/// `removedSpans`/`addedSpans` marking the changed runs within their lines.
+/// `contextLines` fills each region's `contextBefore`/`contextAfter` with up
+/// to that many unchanged lines.
+#[wasm_bindgen]
+pub fn get_file_diff(path: String, options: JsValue) -> Result<JsValue, JsValue> {
+    let options: DiffOptions = if options.is_undefined() || options.is_null() {
+        DiffOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)
+            .map_err(|e| js_err!("Invalid diff options: {}", e))?
+    };
     let path_key = create_path_key(&path).map_err(|e| js_err!("Invalid path '{}': {}", path, e))?;
 
     let orchestrator = Orchestrator::new();
-    let diff = orchestrator
-        .get_file_diff(&path_key)
+    let mut diff = orchestrator
+        .get_file_diff(&path_key, options.context_lines)
         .map_err(|e| js_err!("Failed to get file diff for '{}': {}", path, e))?;
+    refine_diff(&mut diff, options.granularity);
 
-    let regions_array = Array::new();
-    for region in diff.regions {
-        let removed_lines_array = Array::new();
-        for line in &region.removed_lines {
-            removed_lines_array.push(&JsValue::from_str(line));
-        }
+    file_diff_to_js(&diff)
+}
 
-        let added_lines_array = Array::new();
-        for line in &region.added_lines {
-            added_lines_array.push(&JsValue::from_str(line));
-        }
+/// Staged change of `path` as a git-style unified patch; empty if unchanged.
+#[wasm_bindgen]
+pub fn get_unified_diff(path: String) -> Result<String, JsValue> {
+    let path_key = create_path_key(&path).map_err(|e| js_err!("Invalid path '{}': {}", path, e))?;
+    Orchestrator::new()
+        .get_unified_diff(&path_key)
+        .map_err(|e| js_err!("Failed to get unified diff for '{}': {}", path, e))
+}
 
-        let region_obj = JsObjectBuilder::new()
-            .set("originalStart", JsValue::from(region.original_start as u32))?
-            .set("linesRemoved", JsValue::from(region.lines_removed as u32))?
-            .set("modifiedStart", JsValue::from(region.modified_start as u32))?
-            .set("linesAdded", JsValue::from(region.lines_added as u32))?
-            .set("removedLines", removed_lines_array.into())?
-            .set("addedLines", added_lines_array.into())?
-            .build();
+/// Every staged change as one git-style patch, suitable for `git apply`.
+#[wasm_bindgen]
+pub fn export_patch() -> Result<String, JsValue> {
+    Orchestrator::new()
+        .get_all_unified_diffs()
+        .map_err(|e| js_err!("Failed to export patch: {}", e))
+}
 
-        regions_array.push(&region_obj);
-    }
+/// Every staged change as a `"zip"` or `"tar"` archive.
+///
+/// Created, modified and moved files are stored under their staged paths;
+/// `.conduit/changes.json` lists deleted files and moves.
+#[wasm_bindgen]
+pub fn export_changes_archive(format: String) -> Result<Uint8Array, JsValue> {
+    let format: ArchiveFormat = format.parse().map_err(|e| core_err("Invalid format", &e))?;
+    let bytes = Orchestrator::new()
+        .export_changes_archive(format)
+        .map_err(|e| core_err("Failed to export archive", &e))?;
+    Ok(Uint8Array::from(bytes.as_slice()))
+}
 
-    let stats_obj = JsObjectBuilder::new()
-        .set("linesAdded", JsValue::from(diff.stats.lines_added as u32))?
-        .set(
-            "linesRemoved",
-            JsValue::from(diff.stats.lines_removed as u32),
-        )?
-        .set(
-            "regionsChanged",
-            JsValue::from(diff.stats.regions_changed as u32),
-        )?
-        .build();
+/// Apply a unified diff (such as one from `export_patch`) to staging.
+///
+/// Hunks are matched by their context lines, so line numbers may be off.
+/// Staging changes only if every file and hunk applies; either way the
+/// result reports each file and hunk.
+#[wasm_bindgen]
+pub fn apply_patch(patch: String) -> Result<JsValue, JsValue> {
+    let report = Orchestrator::new()
+        .handle_apply_patch(&patch)
+        .map_err(|e| core_err("Failed to apply patch", &e))?;
 
-    let diff_obj = JsObjectBuilder::new()
-        .set("path", JsValue::from_str(diff.path.as_str()))?
-        .set("stats", stats_obj)?
-        .set("regions", regions_array.into())?
-        .build();
+    to_js(&PatchResult {
+        applied: report.applied,
+        files: report
+            .files
+            .iter()
+            .map(|file| PatchFileResult {
+                old_path: file.old_path.clone(),
+                new_path: file.new_path.clone(),
+                applied: file.is_applied(),
+                error: file.error.clone(),
+                hunks: file
+                    .hunks
+                    .iter()
+                    .map(|hunk| PatchHunkResult {
+                        old_start: hunk.old_start,
+                        new_start: hunk.new_start,
+                        applied: hunk.applied,
+                        offset: hunk.offset,
+                        error: hunk.error.clone(),
+                    })
+                    .collect(),
+            })
+            .collect(),
+    })
+}
+
+/// Result of `apply_patch`.
+#[derive(Serialize, JsonSchema)]
+pub struct PatchResult {
+    /// Whether every file and hunk applied, and staging changed.
+    pub applied: bool,
+    pub files: Vec<PatchFileResult>,
+}
 
-    Ok(diff_obj)
+/// Outcome for one file of a patch.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchFileResult {
+    pub old_path: Option<PathKey>,
+    pub new_path: Option<PathKey>,
+    pub applied: bool,
+    /// Why the file could not be patched, apart from failed hunks.
+    pub error: Option<String>,
+    pub hunks: Vec<PatchHunkResult>,
 }
 
+/// Outcome for one hunk of a patch.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PatchHunkResult {
+    pub old_start: usize,
+    pub new_start: usize,
+    pub applied: bool,
+    /// Lines between where the header placed the hunk and where its
+    /// context was found.
+    pub offset: isize,
+    pub error: Option<String>,
+}
+
+/// Diff of `path` as JSON bytes, compressed under the configured policy.
+///
+/// Returns `{ encoding, data }`. `data` decodes (after decompression when
+/// `encoding` is set) to the versioned wire form of the diff, which uses
+/// the snake_case field names of the schema rather than `get_file_diff`'s.
 #[wasm_bindgen]
-pub fn get_staged_modifications_with_active() -> Result<JsValue, JsValue> {
-    let manager = get_index_manager();
-    let modifications = manager
-        .get_staged_modifications()
-        .map_err(|e| js_err!("Failed to get staged modifications: {}", e))?;
-
-    let modified_array = Array::new();
-    let active_index = manager.active_index();
-
-    for (path, staged_content) in modifications {
-        let obj = JsObjectBuilder::new()
-            .set("path", JsValue::from_str(path.as_str()))?
-            .set(
-                "stagedContent",
-                Uint8Array::from(&staged_content[..]).into(),
-            )?;
-
-        let obj = if let Some(active_entry) = active_index.get_file(&path) {
-            if let Some(active_bytes) = active_entry.bytes() {
-                obj.set("activeContent", Uint8Array::from(active_bytes).into())?
-            } else {
-                obj
+pub fn get_file_diff_packed(path: String) -> Result<JsValue, JsValue> {
+    let path_key = create_path_key(&path).map_err(|e| js_err!("Invalid path '{}': {}", path, e))?;
+
+    let diff = Orchestrator::new()
+        .get_file_diff(&path_key, 0)
+        .map_err(|e| js_err!("Failed to get file diff for '{}': {}", path, e))?;
+    let json = serde_json::to_vec(&Versioned::new(diff))
+        .map_err(|e| js_err!("Failed to serialize diff: {}", e))?;
+
+    let (data, encoding) = pack_bytes(&host_defaults().compression_policy(), &json)?;
+    to_js(&PackedDiff { encoding, data })
+}
+
+/// Result of `get_file_diff_packed`.
+#[derive(Serialize, JsonSchema)]
+pub struct PackedDiff {
+    /// `"gzip"`, `"deflate"`, or `null` when `data` is uncompressed.
+    #[serde(with = "serde_wasm_bindgen::preserve")]
+    #[schemars(with = "Option<String>")]
+    pub encoding: JsValue,
+    /// The diff's JSON wire form as a `Uint8Array`.
+    #[serde(with = "serde_wasm_bindgen::preserve")]
+    #[schemars(with = "Vec<u8>")]
+    pub data: JsValue,
+}
+
+/// A file diff in the shape of `get_file_diff`.
+#[derive(Serialize, JsonSchema)]
+pub struct DiffView {
+    pub path: PathKey,
+    pub stats: DiffStatsView,
+    pub regions: Vec<RegionView>,
+}
+
+/// Totals of a [`DiffView`].
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiffStatsView {
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    pub regions_changed: usize,
+}
+
+/// One changed region of a [`DiffView`].
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct RegionView {
+    /// 1-based.
+    pub original_start: usize,
+    pub lines_removed: usize,
+    /// 1-based.
+    pub modified_start: usize,
+    pub lines_added: usize,
+    pub removed_lines: Vec<String>,
+    pub added_lines: Vec<String>,
+    pub context_before: Vec<String>,
+    pub context_after: Vec<String>,
+    /// Changed runs within `removedLines`, for word or char granularity.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub removed_spans: Option<Vec<SpanView>>,
+    /// Changed runs within `addedLines`, for word or char granularity.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub added_spans: Option<Vec<SpanView>>,
+}
+
+/// A changed run within one line of a region, in UTF-16 code units so it
+/// indexes the JS string directly.
+#[derive(Serialize, JsonSchema)]
+pub struct SpanView {
+    /// 0-based index into the region's lines.
+    pub line: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+fn spans_to_view(spans: &[InlineSpan], lines: &[String]) -> Vec<SpanView> {
+    spans
+        .iter()
+        .map(|span| {
+            let line = &lines[span.line];
+            let utf16 = |byte: usize| line[..byte].encode_utf16().count();
+            SpanView {
+                line: span.line,
+                start: utf16(span.start),
+                end: utf16(span.end),
             }
-        } else {
-            obj
-        };
+        })
+        .collect()
+}
 
-        modified_array.push(&obj.build());
+impl From<&FileDiff> for DiffView {
+    fn from(diff: &FileDiff) -> Self {
+        let regions = diff
+            .regions
+            .iter()
+            .map(|region| {
+                let spans = !(region.removed_spans.is_empty() && region.added_spans.is_empty());
+                RegionView {
+                    original_start: region.original_start,
+                    lines_removed: region.lines_removed,
+                    modified_start: region.modified_start,
+                    lines_added: region.lines_added,
+                    removed_lines: region.removed_lines.clone(),
+                    added_lines: region.added_lines.clone(),
+                    context_before: region.context_before.clone(),
+                    context_after: region.context_after.clone(),
+                    removed_spans: spans
+                        .then(|| spans_to_view(&region.removed_spans, &region.removed_lines)),
+                    added_spans: spans
+                        .then(|| spans_to_view(&region.added_spans, &region.added_lines)),
+                }
+            })
+            .collect();
+        Self {
+            path: diff.path.clone(),
+            stats: DiffStatsView {
+                lines_added: diff.stats.lines_added,
+                lines_removed: diff.stats.lines_removed,
+                regions_changed: diff.stats.regions_changed,
+            },
+            regions,
+        }
     }
+}
+
+/// Convert a diff into the `{ path, stats, regions }` shape used by the diff bindings.
+pub(crate) fn file_diff_to_js(diff: &FileDiff) -> Result<JsValue, JsValue> {
+    to_js(&DiffView::from(diff))
+}
+
+/// A modified file in `get_staged_modifications_with_active`.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StagedContents {
+    pub path: PathKey,
+    /// A `Uint8Array`.
+    #[serde(with = "serde_wasm_bindgen::preserve")]
+    #[schemars(with = "Vec<u8>")]
+    pub staged_content: JsValue,
+    /// `"gzip"`, `"deflate"`, or `null` when uncompressed.
+    #[serde(with = "serde_wasm_bindgen::preserve")]
+    #[schemars(with = "Option<String>")]
+    pub staged_encoding: JsValue,
+    #[serde(flatten)]
+    pub active: Option<ActiveContents>,
+}
 
-    Ok(modified_array.into())
+/// Active content of a [`StagedContents`] file that exists in the active index.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveContents {
+    /// A `Uint8Array`.
+    #[serde(with = "serde_wasm_bindgen::preserve")]
+    #[schemars(with = "Vec<u8>")]
+    pub active_content: JsValue,
+    #[serde(with = "serde_wasm_bindgen::preserve")]
+    #[schemars(with = "Option<String>")]
+    pub active_encoding: JsValue,
+}
+
+/// Staged and active contents of each modified file.
+///
+/// Contents follow the configured compression; `stagedEncoding` and
+/// `activeEncoding` say how each is stored.
+#[wasm_bindgen]
+pub fn get_staged_modifications_with_active() -> Result<JsValue, JsValue> {
+    let active_index = get_index_manager().active_index();
+    let policy = host_defaults().compression_policy();
+
+    with_staged_view(|view| {
+        let mut modified = Vec::new();
+        for (path, staged_content) in view.modifications() {
+            let (staged_content, staged_encoding) = pack_bytes(&policy, staged_content)?;
+            let active = match active_index.get_file(path).and_then(|e| e.bytes()) {
+                Some(active_bytes) => {
+                    let (active_content, active_encoding) = pack_bytes(&policy, active_bytes)?;
+                    Some(ActiveContents {
+                        active_content,
+                        active_encoding,
+                    })
+                }
+                None => None,
+            };
+            modified.push(StagedContents {
+                path: path.clone(),
+                staged_content,
+                staged_encoding,
+                active,
+            });
+        }
+        to_js(&modified)
+    })
 }
 
 #[wasm_bindgen]
 pub fn abort_file_load() -> Result<(), JsValue> {
+    with_load_progress(LoadProgress::finish);
     Ok(())
 }
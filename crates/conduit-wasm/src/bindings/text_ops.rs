@@ -0,0 +1,100 @@
+use crate::bindings::search_ops::{hunk_to_js, SearchOptions};
+use crate::bindings::staging_ops::file_diff_to_js;
+use crate::js_err;
+use crate::orchestrator::resolve_matcher;
+use crate::utils::to_js;
+use conduit_core::tools::{for_each_match, AbortFlag};
+use conduit_core::{compute_diff, LineIndex, PathKey, PreviewBuilder};
+use js_sys::Array;
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::sync::Arc;
+use wasm_bindgen::prelude::*;
+
+/// A match found by a `minimal` `find_in_text`.
+#[derive(Serialize, JsonSchema)]
+pub struct TextHit {
+    /// 1-based line of the match start.
+    pub line: usize,
+    /// Length of the match in bytes.
+    pub length: usize,
+}
+
+/// Path reported for caller-provided buffers, which have no index path.
+fn buffer_path() -> PathKey {
+    PathKey::from_arc(Arc::from(""))
+}
+
+/// Search a caller-provided string with the same engine as `search_files`.
+///
+/// Honors the matching options (`caseSensitive`, `wholeWord`, `contextLines`,
+/// `limit`, `minimal`); path filters are ignored. Hunks have an empty `path`.
+#[wasm_bindgen]
+pub fn find_in_text(content: String, pattern: String, options: JsValue) -> Result<Array, JsValue> {
+    let options = SearchOptions::from_js(options)?;
+    let request = options.to_find_request(pattern);
+    let limit = options.effective_limit().unwrap_or(usize::MAX);
+    let minimal = request.minimal;
+
+    let matcher = resolve_matcher(
+        &request.find,
+        request.pattern_ref.as_deref(),
+        &request.engine_opts,
+    )
+    .map_err(|e| js_err!("Invalid pattern '{}': {}", request.find, e))?;
+    let preview_builder = PreviewBuilder::new(request.delta);
+
+    let bytes = content.as_bytes();
+    let line_index = LineIndex::build(bytes);
+    let results = Array::new();
+    let mut js_error = None;
+
+    for_each_match(bytes, &matcher, &AbortFlag::new(), |span, region_line| {
+        if results.length() as usize >= limit {
+            return Ok(false);
+        }
+
+        let obj = if minimal {
+            to_js(&TextHit {
+                line: line_index.line_of_byte(span.start).unwrap_or(1),
+                length: span.len(),
+            })
+        } else {
+            let line_end = line_index.line_of_byte(span.end).unwrap_or(region_line);
+            let hunk = preview_builder.build_hunk(
+                buffer_path(),
+                &line_index,
+                bytes,
+                &span,
+                region_line,
+                line_end,
+            )?;
+            hunk_to_js(&hunk)
+        };
+
+        match obj {
+            Ok(obj) => {
+                results.push(&obj);
+                Ok(true)
+            }
+            Err(e) => {
+                js_error = Some(e);
+                Ok(false)
+            }
+        }
+    })
+    .map_err(|e| js_err!("Search failed: {}", e))?;
+
+    if let Some(e) = js_error {
+        return Err(e);
+    }
+    Ok(results)
+}
+
+/// Line diff of two caller-provided strings, in the `get_file_diff` shape
+/// with an empty `path`.
+#[wasm_bindgen]
+pub fn diff_texts(original: String, modified: String) -> Result<JsValue, JsValue> {
+    let diff = compute_diff(buffer_path(), &original, &modified, 0);
+    file_diff_to_js(&diff)
+}
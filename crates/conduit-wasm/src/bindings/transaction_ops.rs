@@ -0,0 +1,170 @@
+use crate::globals::{create_path_key, get_index_manager, with_transaction};
+use crate::js_err;
+use crate::orchestrator::Orchestrator;
+use crate::utils::{core_err, to_js, LineEditResult};
+use conduit_core::{
+    ConflictPolicy, CreateRequest, CreateResponse, DeleteRequest, DeleteResponse, PathKey,
+    ReplaceLinesRequest, Transaction, TransactionOp, TransactionOutcome,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+/// Operation as passed to `queue_operation`, before path normalization.
+#[derive(Deserialize, JsonSchema)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum QueuedOp {
+    #[serde(rename_all = "camelCase")]
+    Create {
+        path: String,
+        content: Option<String>,
+        #[serde(default)]
+        allow_overwrite: bool,
+    },
+    Delete {
+        path: String,
+    },
+    ReplaceLines {
+        path: String,
+        replacements: Vec<(usize, usize, String)>,
+    },
+    #[serde(rename_all = "camelCase")]
+    Move {
+        src: String,
+        dst: String,
+        /// What to do if `dst` exists.
+        #[serde(default)]
+        on_conflict: ConflictPolicy,
+    },
+}
+
+impl QueuedOp {
+    fn into_op(self) -> Result<TransactionOp, JsValue> {
+        let key = |path: &str| {
+            create_path_key(path).map_err(|e| js_err!("Invalid path '{}': {}", path, e))
+        };
+        Ok(match self {
+            QueuedOp::Create {
+                path,
+                content,
+                allow_overwrite,
+            } => TransactionOp::Create(CreateRequest {
+                path: key(&path)?,
+                content: content.map(String::into_bytes),
+                allow_overwrite,
+            }),
+            QueuedOp::Delete { path } => TransactionOp::Delete(DeleteRequest::new(key(&path)?)),
+            QueuedOp::ReplaceLines { path, replacements } => {
+                if replacements
+                    .iter()
+                    .any(|(start, end, _)| *start < 1 || start > end)
+                {
+                    return Err(js_err!(
+                        "Replacements must be [startLine, endLine, content] with 1 <= startLine <= endLine"
+                    ));
+                }
+                TransactionOp::ReplaceLines(ReplaceLinesRequest {
+                    path: key(&path)?,
+                    replacements,
+                    precondition: None,
+                })
+            }
+            QueuedOp::Move {
+                src,
+                dst,
+                on_conflict,
+            } => TransactionOp::Move {
+                src: key(&src)?,
+                dst: key(&dst)?,
+                on_conflict,
+            },
+        })
+    }
+}
+
+/// Open a transaction; operations queued afterwards are applied together by
+/// `commit_transaction`.
+#[wasm_bindgen]
+pub fn begin_transaction() -> Result<(), JsValue> {
+    with_transaction(|slot| {
+        if slot.is_some() {
+            return Err(js_err!("A transaction is already open"));
+        }
+        *slot = Some(Transaction::new());
+        Ok(())
+    })
+}
+
+/// Queue one operation on the open transaction. Nothing touches staging
+/// until the commit. Returns the number of queued operations.
+#[wasm_bindgen]
+pub fn queue_operation(operation: JsValue) -> Result<u32, JsValue> {
+    let op = serde_wasm_bindgen::from_value::<QueuedOp>(operation)
+        .map_err(|e| js_err!("Invalid transaction operation: {}", e))?
+        .into_op()?;
+    with_transaction(|slot| {
+        let tx = slot
+            .as_mut()
+            .ok_or_else(|| js_err!("No transaction is open"))?;
+        Ok(tx.queue(op) as u32)
+    })
+}
+
+/// Apply every queued operation to staging, all or nothing, and close the
+/// transaction. Returns one result per operation, in queue order.
+#[wasm_bindgen]
+pub fn commit_transaction() -> Result<JsValue, JsValue> {
+    let tx = with_transaction(Option::take).ok_or_else(|| js_err!("No transaction is open"))?;
+
+    let mut orchestrator = Orchestrator::new();
+    let outcomes = tx
+        .commit(&mut orchestrator, get_index_manager())
+        .map_err(|e| core_err("Transaction failed", &e))?;
+
+    let results: Vec<OpResult> = outcomes.iter().map(OpResult::from).collect();
+    to_js(&results)
+}
+
+/// Discard the open transaction without applying it. Returns whether one
+/// was open.
+#[wasm_bindgen]
+pub fn abort_transaction() -> bool {
+    with_transaction(Option::take).is_some()
+}
+
+/// Result of one committed operation, tagged like the queued operation.
+#[derive(Serialize, JsonSchema)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum OpResult {
+    Create(CreateResponse),
+    Delete(DeleteResponse),
+    ReplaceLines(LineEditResult),
+    Move {
+        src: PathKey,
+        /// Where the file went.
+        dst: PathKey,
+        /// Whether an existing file at `dst` was replaced.
+        overwritten: bool,
+    },
+}
+
+impl From<&TransactionOutcome> for OpResult {
+    fn from(outcome: &TransactionOutcome) -> Self {
+        match outcome {
+            TransactionOutcome::Create(response) => OpResult::Create(response.clone()),
+            TransactionOutcome::Delete(response) => OpResult::Delete(response.clone()),
+            TransactionOutcome::ReplaceLines(response) => {
+                OpResult::ReplaceLines(LineEditResult::from(response))
+            }
+            TransactionOutcome::Move {
+                src,
+                dst,
+                overwritten,
+            } => OpResult::Move {
+                src: src.clone(),
+                dst: dst.clone(),
+                overwritten: *overwritten,
+            },
+        }
+    }
+}
@@ -1,12 +1,16 @@
 /*!
- * WASM bindings for read-before-edit validation operations.
+ * WASM bindings for validating line edits before they are applied.
  *
  * This module provides functions to validate that files have been read before
- * line-based editing operations to prevent corruption from stale line numbers.
+ * line-based editing operations to prevent corruption from stale line numbers,
+ * and to dry-run line operations against the staged content.
  */
 
 use crate::globals::{create_path_key, get_index_manager};
 use crate::js_err;
+use crate::orchestrator::Orchestrator;
+use crate::utils::core_err;
+use conduit_core::{ValidateLinesRequest, ValidateLinesTool};
 use wasm_bindgen::prelude::*;
 
 /// Validates whether a file can be edited with line-based operations.
@@ -58,3 +62,23 @@ pub fn check_file_needs_read(path: String) -> Result<bool, JsValue> {
         .check_needs_read(&path_key)
         .map_err(|e| js_err!("Failed to check needs_read status: {}", e))
 }
+
+/// Checks line operations against the staged file without applying them.
+/// Returns the file's line count and every out-of-range, inverted or
+/// overlapping operation.
+#[wasm_bindgen]
+pub fn validate_line_operations(path: String, operations: JsValue) -> Result<JsValue, JsValue> {
+    let path_key = create_path_key(&path).map_err(|e| js_err!("Invalid path '{}': {}", path, e))?;
+    let operations = serde_wasm_bindgen::from_value(operations)
+        .map_err(|e| js_err!("Invalid line operations: {}", e))?;
+
+    let response = Orchestrator::new()
+        .run_validate_lines(ValidateLinesRequest {
+            path: path_key,
+            operations,
+        })
+        .map_err(|e| core_err(format_args!("Failed to validate edits to '{path}'"), &e))?;
+
+    serde_wasm_bindgen::to_value(&response)
+        .map_err(|e| js_err!("Failed to serialize validation result: {}", e))
+}
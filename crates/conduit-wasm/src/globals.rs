@@ -3,16 +3,511 @@
 //! These globals are initialized lazily on first access and persist
 //! for the lifetime of the WASM instance.
 
+use crate::orchestrator::SearchCursor;
 use conduit_core::error::Result;
-use conduit_core::fs::{normalize_path, IndexManager, PathKey};
+use conduit_core::fs::{
+    ignore_files, normalize_path, DuplicatePolicy, ElevationTokens, HintQueue, IgnoreSet, Index,
+    IndexManager, LoadProgress, PathKey, DEFAULT_HYDRATION_BUDGET,
+};
+use conduit_core::tools::{
+    AbortFlag, CaseFolding, Compression, CompressionPolicy, EditPlan, EmbeddingCache,
+    PatternRegistry, ResultSet, ScaffoldRegistry, SearchHistory, DEFAULT_COMPRESSION_THRESHOLD,
+};
+use conduit_core::transaction::Transaction;
+use conduit_core::SearchSpace;
 use once_cell::sync::Lazy;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::Arc;
 
 thread_local! {
     /// Path interning pool.
     static PATH_POOL: RefCell<HashMap<String, Arc<str>>> = RefCell::new(HashMap::new());
+
+    /// Host-configured request defaults.
+    static HOST_DEFAULTS: RefCell<HostDefaults> = RefCell::new(HostDefaults::default());
+
+    /// Registered scaffold templates.
+    static SCAFFOLDS: RefCell<ScaffoldRegistry> = RefCell::new(ScaffoldRegistry::default());
+
+    /// Totals for the current (or most recent) file load.
+    static LOAD_PROGRESS: RefCell<LoadProgress> = RefCell::new(LoadProgress::default());
+
+    /// Host-minted tokens for writing read-only files, with their audit log.
+    static ELEVATION: RefCell<ElevationTokens> = RefCell::new(ElevationTokens::default());
+
+    /// Host embedding provider and the vectors it has produced.
+    static EMBEDDINGS: RefCell<Embeddings> = RefCell::new(Embeddings::default());
+
+    /// Host callback fetching the content of metadata-only files.
+    static CONTENT_PROVIDER: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+
+    /// Host callback receiving index events; see `on_index_event`.
+    static INDEX_EVENT_CALLBACK: RefCell<Option<js_sys::Function>> = const { RefCell::new(None) };
+
+    /// Paths hinted by the host, waiting to be warmed.
+    static HINTS: RefCell<HintQueue> = RefCell::new(HintQueue::default());
+
+    /// Named patterns registered by the host, precompiled.
+    static PATTERNS: RefCell<PatternRegistry> = RefCell::new(PatternRegistry::default());
+
+    /// Transaction opened by `begin_transaction`, if any.
+    static TRANSACTION: RefCell<Option<Transaction>> = const { RefCell::new(None) };
+
+    /// Searches run recently and searches saved by name.
+    static SEARCH_HISTORY: RefCell<SearchHistory> = RefCell::new(SearchHistory::default());
+
+    /// Abort flags of live `SearchHandle`s, by handle id.
+    static SEARCH_HANDLES: RefCell<SearchHandles> = RefCell::new(SearchHandles::default());
+
+    /// Open paginated searches, by token.
+    static SEARCH_CURSORS: RefCell<SearchCursors> = RefCell::new(SearchCursors::default());
+
+    /// Search results kept for refining, by id.
+    static RESULT_SETS: RefCell<Kept<ResultSet>> =
+        RefCell::new(Kept::new("results", MAX_RESULT_SETS));
+
+    /// Bulk replacements awaiting review, by id.
+    static EDIT_PLANS: RefCell<Kept<EditPlan>> = RefCell::new(Kept::new("plan", MAX_EDIT_PLANS));
+
+    /// Ignore rules last built by [`HostDefaults::ignore_set`].
+    static IGNORE_CACHE: RefCell<Option<IgnoreCache>> = const { RefCell::new(None) };
+}
+
+/// Ignore rules built for one index, and what they were built from.
+struct IgnoreCache {
+    /// Address of the index last checked and the manager generation then;
+    /// while both hold, the index has not changed.
+    checked: (usize, u64),
+    /// Path and content hash of each ignore file the rules came from.
+    files: Vec<(PathKey, Option<u64>)>,
+    respect_ignore_files: bool,
+    ignore_patterns: Option<Vec<String>>,
+    set: Option<Arc<IgnoreSet>>,
+}
+
+/// Abort flags shared with JS-owned `SearchHandle`s.
+///
+/// Bindings receive a handle as a plain JS value and look its flag up by id,
+/// so passing a handle to a call does not consume the JS object.
+#[derive(Default)]
+pub struct SearchHandles {
+    next_id: u32,
+    flags: HashMap<u32, AbortFlag>,
+}
+
+impl SearchHandles {
+    /// Register `flag`, returning its id.
+    pub fn register(&mut self, flag: AbortFlag) -> u32 {
+        self.next_id += 1;
+        self.flags.insert(self.next_id, flag);
+        self.next_id
+    }
+
+    pub fn get(&self, id: u32) -> Option<AbortFlag> {
+        self.flags.get(&id).cloned()
+    }
+
+    pub fn remove(&mut self, id: u32) {
+        self.flags.remove(&id);
+    }
+}
+
+/// Open searches kept for `search_files_page`.
+pub const MAX_SEARCH_CURSORS: usize = 16;
+
+/// Paginated searches in progress, oldest first.
+///
+/// Each cursor pins an index snapshot, so only the newest
+/// [`MAX_SEARCH_CURSORS`] are kept; older tokens expire.
+#[derive(Default)]
+pub struct SearchCursors {
+    next_id: u64,
+    cursors: VecDeque<(String, SearchCursor)>,
+}
+
+impl SearchCursors {
+    /// Store a new cursor and return its token.
+    pub fn open(&mut self, cursor: SearchCursor) -> String {
+        self.next_id += 1;
+        let token = format!("search-{}", self.next_id);
+        self.put(token.clone(), cursor);
+        token
+    }
+
+    /// Store `cursor` under an existing token, as the newest cursor.
+    pub fn put(&mut self, token: String, cursor: SearchCursor) {
+        self.cursors.push_back((token, cursor));
+        while self.cursors.len() > MAX_SEARCH_CURSORS {
+            self.cursors.pop_front();
+        }
+    }
+
+    /// Remove and return the cursor for `token`.
+    pub fn take(&mut self, token: &str) -> Option<SearchCursor> {
+        let pos = self.cursors.iter().position(|(t, _)| t == token)?;
+        self.cursors.remove(pos).map(|(_, cursor)| cursor)
+    }
+}
+
+/// Result sets kept for `refine_result_set`.
+pub const MAX_RESULT_SETS: usize = 16;
+
+/// Edit plans kept for `apply_edit_hunks`.
+pub const MAX_EDIT_PLANS: usize = 16;
+
+/// Values kept between calls under generated ids, oldest first.
+///
+/// Result sets and edit plans pin index snapshots or planned content, so
+/// only the newest `limit` are kept; older ids expire.
+pub struct Kept<T> {
+    prefix: &'static str,
+    limit: usize,
+    next_id: u64,
+    items: VecDeque<(String, T)>,
+}
+
+impl<T> Kept<T> {
+    pub fn new(prefix: &'static str, limit: usize) -> Self {
+        Self {
+            prefix,
+            limit,
+            next_id: 0,
+            items: VecDeque::new(),
+        }
+    }
+
+    /// Store `value` and return its id.
+    pub fn insert(&mut self, value: T) -> String {
+        self.next_id += 1;
+        let id = format!("{}-{}", self.prefix, self.next_id);
+        self.items.push_back((id.clone(), value));
+        while self.items.len() > self.limit {
+            self.items.pop_front();
+        }
+        id
+    }
+
+    pub fn get_mut(&mut self, id: &str) -> Option<&mut T> {
+        self.items
+            .iter_mut()
+            .find(|(i, _)| i == id)
+            .map(|(_, value)| value)
+    }
+
+    /// Remove and return the value for `id`.
+    pub fn take(&mut self, id: &str) -> Option<T> {
+        let pos = self.items.iter().position(|(i, _)| i == id)?;
+        self.items.remove(pos).map(|(_, value)| value)
+    }
+}
+
+/// Host-registered embedding provider with its cache.
+#[derive(Default)]
+pub struct Embeddings {
+    /// Called with an array of strings; returns one vector per string.
+    pub provider: Option<js_sys::Function>,
+    pub cache: EmbeddingCache,
+}
+
+/// Defaults applied to requests when the caller does not specify a value.
+///
+/// Every field is optional; unset fields fall back to the built-in defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(default, rename_all = "camelCase")]
+pub struct HostDefaults {
+    pub default_context_lines: Option<usize>,
+    pub default_exclude_globs: Option<Vec<String>>,
+    pub case_insensitive_default: Option<bool>,
+    pub whole_word_default: Option<bool>,
+    /// `use_staged` for searches, reads, stats and listings.
+    pub use_staged_default: Option<bool>,
+    /// `limit` for searches, saved searches and listings.
+    pub max_results: Option<usize>,
+    /// Fail reads and edits on invalid UTF-8 instead of replacing it with U+FFFD.
+    pub strict_utf8: Option<bool>,
+    /// Skip files larger than this many bytes during load.
+    pub max_file_size: Option<usize>,
+    /// How paths offered more than once during a load are resolved.
+    pub duplicate_policy: Option<DuplicatePolicy>,
+    /// Case folding for case-insensitive searches.
+    pub case_folding: Option<CaseFolding>,
+    /// Compress large byte payloads returned to the host.
+    pub compression: Option<Compression>,
+    /// Minimum payload size in bytes to compress (default 64 KiB).
+    pub compression_threshold: Option<usize>,
+    /// Fail loads and writes that would hold more content bytes than this.
+    pub memory_ceiling: Option<u64>,
+    /// Bytes of content fetched on demand to keep before evicting the least
+    /// recently used.
+    pub hydration_budget: Option<u64>,
+    /// Match files on worker threads when the build and host provide a
+    /// thread pool (default true).
+    pub parallel_search: Option<bool>,
+    /// Skip paths matched by `.gitignore` files in the index (default true).
+    pub respect_ignore_files: Option<bool>,
+    /// Extra `.gitignore`-style rules applied from the root, after the
+    /// ignore files in the index.
+    pub ignore_patterns: Option<Vec<String>>,
+    /// Report a staged deletion and creation at least this percent similar
+    /// as one rename in change summaries (unset: no detection).
+    pub rename_threshold: Option<u8>,
+}
+
+impl HostDefaults {
+    /// Overwrite fields that are set in `other`, keeping the rest.
+    pub fn merge(&mut self, other: HostDefaults) {
+        let HostDefaults {
+            default_context_lines,
+            default_exclude_globs,
+            case_insensitive_default,
+            whole_word_default,
+            use_staged_default,
+            max_results,
+            strict_utf8,
+            max_file_size,
+            duplicate_policy,
+            case_folding,
+            compression,
+            compression_threshold,
+            memory_ceiling,
+            hydration_budget,
+            parallel_search,
+            respect_ignore_files,
+            ignore_patterns,
+            rename_threshold,
+        } = other;
+        if default_context_lines.is_some() {
+            self.default_context_lines = default_context_lines;
+        }
+        if default_exclude_globs.is_some() {
+            self.default_exclude_globs = default_exclude_globs;
+        }
+        if case_insensitive_default.is_some() {
+            self.case_insensitive_default = case_insensitive_default;
+        }
+        if whole_word_default.is_some() {
+            self.whole_word_default = whole_word_default;
+        }
+        if use_staged_default.is_some() {
+            self.use_staged_default = use_staged_default;
+        }
+        if max_results.is_some() {
+            self.max_results = max_results;
+        }
+        if strict_utf8.is_some() {
+            self.strict_utf8 = strict_utf8;
+        }
+        if max_file_size.is_some() {
+            self.max_file_size = max_file_size;
+        }
+        if duplicate_policy.is_some() {
+            self.duplicate_policy = duplicate_policy;
+        }
+        if case_folding.is_some() {
+            self.case_folding = case_folding;
+        }
+        if compression.is_some() {
+            self.compression = compression;
+        }
+        if compression_threshold.is_some() {
+            self.compression_threshold = compression_threshold;
+        }
+        if memory_ceiling.is_some() {
+            self.memory_ceiling = memory_ceiling;
+        }
+        if hydration_budget.is_some() {
+            self.hydration_budget = hydration_budget;
+        }
+        if parallel_search.is_some() {
+            self.parallel_search = parallel_search;
+        }
+        if respect_ignore_files.is_some() {
+            self.respect_ignore_files = respect_ignore_files;
+        }
+        if ignore_patterns.is_some() {
+            self.ignore_patterns = ignore_patterns;
+        }
+        if rename_threshold.is_some() {
+            self.rename_threshold = rename_threshold;
+        }
+    }
+
+    /// Ignore rules for `index`: its `.gitignore` files unless disabled, then
+    /// `ignore_patterns`. `None` when no rule applies.
+    ///
+    /// `generation` is the index manager's generation, read after `index`
+    /// was taken. The rules are cached: an index seen before at the same
+    /// generation reuses them without a walk, and otherwise they are rebuilt
+    /// only when the ignore files or the patterns differ.
+    pub fn ignore_set(&self, index: &Index, generation: u64) -> Result<Option<Arc<IgnoreSet>>> {
+        let respect = self.respect_ignore_files.unwrap_or(true);
+        let checked = (std::ptr::from_ref(index) as usize, generation);
+        IGNORE_CACHE.with(|cache| {
+            let mut cache = cache.borrow_mut();
+            let unchanged = |c: &IgnoreCache| {
+                c.respect_ignore_files == respect && c.ignore_patterns == self.ignore_patterns
+            };
+            if let Some(c) = cache
+                .as_ref()
+                .filter(|c| c.checked == checked && unchanged(c))
+            {
+                return Ok(c.set.clone());
+            }
+
+            let files = if respect {
+                ignore_files(index)
+            } else {
+                Vec::new()
+            };
+            let fingerprint: Vec<_> = files
+                .iter()
+                .map(|(path, entry)| ((*path).clone(), entry.content_hash()))
+                .collect();
+            if let Some(c) = cache
+                .as_mut()
+                .filter(|c| c.files == fingerprint && unchanged(c))
+            {
+                c.checked = checked;
+                return Ok(c.set.clone());
+            }
+
+            let mut set = IgnoreSet::from_files(files);
+            if let Some(patterns) = &self.ignore_patterns {
+                set.add_file("", &patterns.join("\n"))?;
+            }
+            let set = (!set.is_empty()).then(|| Arc::new(set));
+            *cache = Some(IgnoreCache {
+                checked,
+                files: fingerprint,
+                respect_ignore_files: respect,
+                ignore_patterns: self.ignore_patterns.clone(),
+                set: set.clone(),
+            });
+            Ok(set)
+        })
+    }
+
+    /// Space a request's `use_staged` selects, falling back to
+    /// `use_staged_default`, then the staged index.
+    pub fn search_space(&self, use_staged: Option<bool>) -> SearchSpace {
+        if use_staged.or(self.use_staged_default).unwrap_or(true) {
+            SearchSpace::Staged
+        } else {
+            SearchSpace::Active
+        }
+    }
+
+    /// A request's result limit, falling back to `max_results`.
+    pub fn result_limit(&self, limit: Option<usize>) -> Option<usize> {
+        limit.or(self.max_results)
+    }
+
+    pub fn compression_policy(&self) -> CompressionPolicy {
+        CompressionPolicy {
+            format: self.compression,
+            threshold: self
+                .compression_threshold
+                .unwrap_or(DEFAULT_COMPRESSION_THRESHOLD),
+        }
+    }
+}
+
+/// Snapshot of the current host defaults.
+pub fn host_defaults() -> HostDefaults {
+    HOST_DEFAULTS.with(|d| d.borrow().clone())
+}
+
+/// Merge `update` into the host defaults.
+pub fn update_host_defaults(update: HostDefaults) {
+    let (ceiling, budget) = HOST_DEFAULTS.with(|d| {
+        let mut d = d.borrow_mut();
+        d.merge(update);
+        (d.memory_ceiling, d.hydration_budget)
+    });
+    let manager = get_index_manager();
+    manager.set_memory_ceiling(ceiling);
+    manager.set_hydration_budget(budget.unwrap_or(DEFAULT_HYDRATION_BUDGET));
+}
+
+/// Restore the built-in defaults.
+pub fn reset_host_defaults() {
+    HOST_DEFAULTS.with(|d| *d.borrow_mut() = HostDefaults::default());
+    let manager = get_index_manager();
+    manager.set_memory_ceiling(None);
+    manager.set_hydration_budget(DEFAULT_HYDRATION_BUDGET);
+}
+
+/// Run `f` with mutable access to the registered content provider.
+pub fn with_content_provider<R>(f: impl FnOnce(&mut Option<js_sys::Function>) -> R) -> R {
+    CONTENT_PROVIDER.with(|p| f(&mut p.borrow_mut()))
+}
+
+/// Run `f` with mutable access to the registered index event callback.
+pub fn with_index_event_callback<R>(f: impl FnOnce(&mut Option<js_sys::Function>) -> R) -> R {
+    INDEX_EVENT_CALLBACK.with(|c| f(&mut c.borrow_mut()))
+}
+
+/// Run `f` with mutable access to the queue of hinted paths.
+pub fn with_hints<R>(f: impl FnOnce(&mut HintQueue) -> R) -> R {
+    HINTS.with(|h| f(&mut h.borrow_mut()))
+}
+
+/// Run `f` with mutable access to the pattern registry.
+pub fn with_patterns<R>(f: impl FnOnce(&mut PatternRegistry) -> R) -> R {
+    PATTERNS.with(|p| f(&mut p.borrow_mut()))
+}
+
+/// Run `f` with mutable access to the search history.
+pub fn with_search_history<R>(f: impl FnOnce(&mut SearchHistory) -> R) -> R {
+    SEARCH_HISTORY.with(|h| f(&mut h.borrow_mut()))
+}
+
+/// Run `f` with mutable access to the live search handles.
+pub fn with_search_handles<R>(f: impl FnOnce(&mut SearchHandles) -> R) -> R {
+    SEARCH_HANDLES.with(|h| f(&mut h.borrow_mut()))
+}
+
+/// Run `f` with mutable access to the open paginated searches.
+pub fn with_search_cursors<R>(f: impl FnOnce(&mut SearchCursors) -> R) -> R {
+    SEARCH_CURSORS.with(|c| f(&mut c.borrow_mut()))
+}
+
+/// Run `f` with mutable access to the kept result sets.
+pub fn with_result_sets<R>(f: impl FnOnce(&mut Kept<ResultSet>) -> R) -> R {
+    RESULT_SETS.with(|r| f(&mut r.borrow_mut()))
+}
+
+/// Run `f` with mutable access to the pending edit plans.
+pub fn with_edit_plans<R>(f: impl FnOnce(&mut Kept<EditPlan>) -> R) -> R {
+    EDIT_PLANS.with(|p| f(&mut p.borrow_mut()))
+}
+
+/// Run `f` with mutable access to the open transaction slot.
+pub fn with_transaction<R>(f: impl FnOnce(&mut Option<Transaction>) -> R) -> R {
+    TRANSACTION.with(|t| f(&mut t.borrow_mut()))
+}
+
+/// Run `f` with mutable access to the scaffold registry.
+pub fn with_scaffolds<R>(f: impl FnOnce(&mut ScaffoldRegistry) -> R) -> R {
+    SCAFFOLDS.with(|r| f(&mut r.borrow_mut()))
+}
+
+/// Run `f` with mutable access to the load progress.
+pub fn with_load_progress<R>(f: impl FnOnce(&mut LoadProgress) -> R) -> R {
+    LOAD_PROGRESS.with(|p| f(&mut p.borrow_mut()))
+}
+
+/// Run `f` with mutable access to the elevation tokens.
+pub fn with_elevation<R>(f: impl FnOnce(&mut ElevationTokens) -> R) -> R {
+    ELEVATION.with(|e| f(&mut e.borrow_mut()))
+}
+
+/// Run `f` with mutable access to the embedding provider and cache.
+pub fn with_embeddings<R>(f: impl FnOnce(&mut Embeddings) -> R) -> R {
+    EMBEDDINGS.with(|e| f(&mut e.borrow_mut()))
 }
 
 /// Global index manager for file management.
@@ -1,5 +1,7 @@
 //! WASM bindings for Conduit core functionality.
 
+use conduit_core::fs::LoadProgress;
+#[cfg(target_arch = "wasm32")]
 use js_sys::Date;
 use wasm_bindgen::prelude::*;
 
@@ -9,6 +11,11 @@ mod orchestrator;
 mod utils;
 
 pub use bindings::*;
+// The engine behind the bindings, for native frontends such as `conduit-cli`.
+pub use globals::{create_path_key, get_index_manager};
+pub use orchestrator::Orchestrator;
+
+#[cfg(target_arch = "wasm32")]
 pub(crate) fn current_unix_timestamp() -> i64 {
     let now_ms = Date::now();
     if !now_ms.is_finite() {
@@ -17,6 +24,26 @@ pub(crate) fn current_unix_timestamp() -> i64 {
     (now_ms / 1000.0).floor() as i64
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn current_unix_timestamp() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+/// Milliseconds since the Unix epoch, the clock for request timeouts.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn now_ms() -> f64 {
+    Date::now()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn now_ms() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0.0, |d| d.as_secs_f64() * 1000.0)
+}
+
 #[wasm_bindgen]
 pub fn init() {
     std::panic::set_hook(Box::new(console_error_panic_hook::hook));
@@ -27,17 +54,26 @@ pub fn ping() -> String {
     "pong".to_string()
 }
 
+/// Wire schema version of the structured responses produced by this module.
+#[wasm_bindgen]
+pub fn schema_version() -> u32 {
+    conduit_core::SCHEMA_VERSION
+}
+
 #[wasm_bindgen]
 pub fn file_count() -> u32 {
     use crate::globals::get_index_manager;
     get_index_manager().active_index().len() as u32
 }
 
+/// File counts plus a breakdown of the staged view: total size, editable
+/// vs read-only files, per-extension totals and the `top_n` largest files
+/// (default 10).
 #[wasm_bindgen]
-pub fn get_index_stats() -> Result<JsValue, JsValue> {
+pub fn get_index_stats(top_n: Option<usize>) -> Result<JsValue, JsValue> {
     use crate::globals::get_index_manager;
     use crate::js_err;
-    use crate::utils::JsObjectBuilder;
+    use crate::utils::to_js;
 
     let manager = get_index_manager();
     let active = manager.active_index();
@@ -45,16 +81,35 @@ pub fn get_index_stats() -> Result<JsValue, JsValue> {
         .staged_index()
         .map_err(|e| js_err!("Failed to access staged index: {}", e))?;
 
-    let active_count = active.len() as u32;
-    let staged_count = staged.len() as u32;
-
-    let obj = JsObjectBuilder::new()
-        .set("activeFiles", JsValue::from(active_count))?
-        .set("stagedFiles", JsValue::from(staged_count))?
-        .set("hasStagedChanges", JsValue::from_bool(staged_count > 0))?
-        .build();
+    let stats = staged.stats(top_n.unwrap_or(10));
+    to_js(&IndexStatsView {
+        active_files: active.len(),
+        staged_files: staged.len(),
+        has_staged_changes: !staged.is_empty(),
+        schema_version: conduit_core::SCHEMA_VERSION,
+        total_bytes: stats.total_bytes,
+        editable_files: stats.editable_files,
+        read_only_files: stats.read_only_files,
+        extensions: stats.extensions,
+        largest: stats.largest,
+    })
+}
 
-    Ok(obj)
+/// Result of `get_index_stats`.
+#[derive(serde::Serialize, schemars::JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexStatsView {
+    pub active_files: usize,
+    pub staged_files: usize,
+    pub has_staged_changes: bool,
+    pub schema_version: u32,
+    pub total_bytes: u64,
+    pub editable_files: usize,
+    pub read_only_files: usize,
+    /// Per-extension totals, most bytes first.
+    pub extensions: Vec<conduit_core::fs::ExtensionStats>,
+    /// The largest files, largest first.
+    pub largest: Vec<conduit_core::fs::SizedPath>,
 }
 
 #[wasm_bindgen]
@@ -94,7 +149,9 @@ pub fn begin_file_load() -> Result<(), JsValue> {
     manager.clear_line_index_cache();
     manager
         .begin_staging()
-        .map_err(|e| js_err!("Failed to begin staging: {}", e))
+        .map_err(|e| js_err!("Failed to begin staging: {}", e))?;
+    globals::with_load_progress(|progress| *progress = LoadProgress::begin());
+    Ok(())
 }
 
 #[wasm_bindgen]
@@ -115,10 +172,14 @@ pub fn load_file_batch_with_text(
     permissions: Vec<js_sys::Boolean>,
     text_contents: Option<Vec<String>>,
 ) -> Result<usize, JsValue> {
-    bindings::staging_ops::add_files_to_staging(paths, contents, mtimes, permissions, text_contents)
+    let report =
+        bindings::staging_ops::stage_batch(paths, contents, mtimes, permissions, text_contents)?;
+    Ok(report.accepted)
 }
 
 #[wasm_bindgen]
 pub fn commit_file_load() -> Result<usize, JsValue> {
-    bindings::staging_ops::promote_staged_index()
+    let count = bindings::staging_ops::promote_staged_index()?;
+    globals::with_load_progress(LoadProgress::finish);
+    Ok(count)
 }
@@ -1,100 +1,802 @@
 //! Orchestrator for search and edit operations.
 
-use crate::{current_unix_timestamp, globals::get_index_manager};
-use conduit_core::fs::FileEntry;
+use crate::{
+    current_unix_timestamp,
+    globals::{create_path_key, get_index_manager, host_defaults, with_patterns, with_scaffolds},
+};
+use conduit_core::fs::{compile_globs, encoding, FileEntry, TextEncoding, Visibility};
 use conduit_core::prelude::*;
 use conduit_core::tools::{
-    apply_line_operations, compute_diff, extract_lines_with_index, for_each_match, LineIndex,
-    LineOperation, PreviewBuilder,
+    apply_anchored_edit, apply_hunks, apply_line_edit, apply_plan, available_threads,
+    changed_lines, chunk_text, compute_diff, decode_checked, decode_lossy, detect_renames,
+    extract_lines_checked, extract_match, for_each_match, looks_binary, map_ordered,
+    merge_three_way, overlapping_operations, parse_patch, plan_in_bytes, preserve_case,
+    preview_plan, validate_line_operations, ArchiveFormat, ArchiveWriter, ByteSpan, ChangeManifest,
+    Chunk, ChunkOptions, EditPlan, EditPolicy, EditPrecondition, ExtractedMatch, FilePatch,
+    FilePatchOutcome, LineEdit, LineIndex, LineOperation, MatchRecord, MultiMatcher, PlannedFile,
+    PreviewBuilder, ReferenceRewriter, ResultSet, TokenCount, TokenCounter, TokenKind,
+    DEFAULT_CONTEXT_LINES,
 };
-use conduit_core::{MoveFilesTool, RegexMatcher};
-use globset::{Glob, GlobSet, GlobSetBuilder};
+use conduit_core::{FileRewrite, MoveFilesTool, MoveTreeRequest, MoveTreeResponse, RegexMatcher};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Candidate files matched per round when searching on worker threads.
+const PARALLEL_BATCH: usize = 256;
+
+/// Matches found in one file ahead of a scan, with where each match's
+/// region starts.
+struct FileMatches {
+    line_index: Arc<LineIndex>,
+    spans: Vec<(ByteSpan, usize)>,
+}
 
 pub struct Orchestrator {
     index_manager: &'static IndexManager,
+    /// Fail reads and edits on invalid UTF-8 instead of replacing it.
+    strict_utf8: bool,
+    /// Restrict searches to these paths (sorted, deduplicated).
+    scope: Option<Vec<PathKey>>,
+    /// Whitespace handling for line edits.
+    edit_policy: EditPolicy,
+    /// Match files on worker threads when a thread pool is available.
+    parallel: bool,
+    /// Pair staged deletions with similar creations in change summaries,
+    /// at this similarity percentage.
+    rename_threshold: Option<u8>,
+}
+
+impl Default for Orchestrator {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Orchestrator {
     pub fn new() -> Self {
+        let defaults = host_defaults();
         Self {
             index_manager: get_index_manager(),
+            strict_utf8: defaults.strict_utf8.unwrap_or(false),
+            scope: None,
+            edit_policy: EditPolicy::default(),
+            parallel: defaults.parallel_search.unwrap_or(true),
+            rename_threshold: defaults.rename_threshold,
         }
     }
 
+    /// Restrict searches to exactly `paths`; other request filters still apply.
+    pub fn with_scope(mut self, mut paths: Vec<PathKey>) -> Self {
+        paths.sort();
+        paths.dedup();
+        self.scope = Some(paths);
+        self
+    }
+
+    /// Apply `policy` to line replacements and insertions.
+    pub fn with_edit_policy(mut self, policy: EditPolicy) -> Self {
+        self.edit_policy = policy;
+        self
+    }
+
     pub fn handle_find(&self, req: FindRequest, abort: &AbortFlag) -> Result<FindResponse> {
-        abort.reset();
+        self.find_up_to(req, None, abort)
+    }
 
-        let index = match req.where_ {
-            SearchSpace::Active => self.index_manager.active_index(),
-            SearchSpace::Staged => self.index_manager.staged_index()?,
+    /// Run a search keeping at most `limit` results: hits for minimal
+    /// requests, hunks otherwise.
+    ///
+    /// The scan still covers every candidate so the totals describe the
+    /// whole search; results past `limit` are counted, including their
+    /// files in [`FindStats::files_truncated`], but not kept.
+    pub fn find_up_to(
+        &self,
+        req: FindRequest,
+        limit: Option<usize>,
+        abort: &AbortFlag,
+    ) -> Result<FindResponse> {
+        let full = |kept: usize| limit.is_some_and(|limit| kept >= limit);
+        let mut dropped = DroppedResults::default();
+        let response = if req.minimal {
+            let mut hits = Vec::new();
+            let limits = self.scan_matches(&req, abort, |site| {
+                if full(hits.len()) {
+                    dropped.add(site.path, 1);
+                } else {
+                    hits.push(FindHit {
+                        path: site.path.clone(),
+                        line: site.line_index.line_of_byte(site.span.start).unwrap_or(1),
+                        length: site.span.len(),
+                        pattern_index: site.pattern_index,
+                    });
+                }
+                Ok(true)
+            })?;
+            limits.apply(FindResponse::new(Vec::new(), hits))
+        } else {
+            let mut results = Vec::new();
+            let limits = self.find_each(req, abort, |hunk| {
+                if full(results.len()) {
+                    dropped.add(&hunk.path, hunk.matched_line_ranges.len());
+                } else {
+                    results.push(hunk);
+                }
+                true
+            })?;
+            limits.apply(FindResponse::new(results, Vec::new()))
         };
+        Ok(dropped.apply(response))
+    }
 
-        let matcher = RegexMatcher::compile(&req.find, &req.engine_opts)?;
-        let include_globs = compile_globs(req.include_globs.as_deref())?;
-        let exclude_globs = compile_globs(req.exclude_globs.as_deref())?;
-
-        let mut results = Vec::new();
+    /// Run a search, handing each hunk to `on_hunk` as soon as it is built.
+    ///
+    /// Returning `false` from the callback stops the search early. Returns
+    /// what the request's match limits cut off, or the error of a preview
+    /// that could not be built. With `merge_adjacent`, each
+    /// hunk is handed over once the next match shows it will not grow.
+    pub fn find_each(
+        &self,
+        req: FindRequest,
+        abort: &AbortFlag,
+        mut on_hunk: impl FnMut(PreviewHunk) -> bool,
+    ) -> Result<LimitReport> {
         let preview_builder = PreviewBuilder::new(req.delta);
-
-        for (path, entry) in index.iter_sorted() {
-            if abort.is_aborted() {
-                break;
+        let mut pending: Option<PreviewHunk> = None;
+        let mut stopped = false;
+
+        let limits = self.scan_matches(&req, abort, |site| {
+            let line_start = site.region_line;
+            let line_end = site
+                .line_index
+                .line_of_byte(site.span.end)
+                .unwrap_or(line_start);
+
+            let hunk = preview_builder.build_hunk(
+                site.path.clone(),
+                site.line_index,
+                site.content,
+                &site.span,
+                line_start,
+                line_end,
+            )?;
+            let hunk = PreviewHunk {
+                pattern_index: site.pattern_index,
+                ..hunk
+            };
+            if !req.merge_adjacent {
+                return Ok(on_hunk(hunk));
             }
-
-            if let Some(prefix) = &req.prefix {
-                if !path.as_str().starts_with(prefix) {
-                    continue;
+            // Hold each hunk back until the next match shows whether it
+            // grows.
+            let Some(prev) = pending.as_mut() else {
+                pending = Some(hunk);
+                return Ok(true);
+            };
+            match PreviewBuilder::merge(prev, hunk) {
+                None => Ok(true),
+                Some(next) => {
+                    stopped = !on_hunk(std::mem::replace(prev, next));
+                    Ok(!stopped)
                 }
             }
+        })?;
+        if let Some(hunk) = pending.filter(|_| !stopped) {
+            on_hunk(hunk);
+        }
+        Ok(limits)
+    }
 
-            if let Some(ref globs) = include_globs {
-                if !globs.is_match(path.as_str()) {
-                    continue;
+    /// Collect matches as flat records with line, column and captures,
+    /// stopping after `limit` records when set.
+    pub fn collect_match_records(
+        &self,
+        req: FindRequest,
+        limit: Option<usize>,
+        abort: &AbortFlag,
+    ) -> Result<Vec<MatchRecord>> {
+        if limit == Some(0) {
+            return Ok(Vec::new());
+        }
+
+        let mut records = Vec::new();
+        self.scan_matches(&req, abort, |site| {
+            let content = site.content;
+            let line = site.line_index.line_of_byte(site.span.start).unwrap_or(1);
+            let line_start = site.line_index.byte_of_line_start(line).unwrap_or(0);
+            let captures = site
+                .matcher
+                .captures_at(content, site.span.start)?
+                .into_iter()
+                .map(|cap| {
+                    cap.map(|c| String::from_utf8_lossy(&content[c.to_range()]).into_owned())
+                })
+                .collect();
+
+            records.push(MatchRecord {
+                path: site.path.as_str().to_string(),
+                line,
+                column: site.span.start - line_start + 1,
+                matched: String::from_utf8_lossy(&content[site.span.to_range()]).into_owned(),
+                captures,
+            });
+            Ok(limit.is_none_or(|limit| records.len() < limit))
+        })?;
+
+        if abort.is_aborted() {
+            return Err(Error::Aborted);
+        }
+        Ok(records)
+    }
+
+    /// Collect every match with its capture groups, stopping after `limit`
+    /// matches when set.
+    pub fn extract_matches(
+        &self,
+        req: FindRequest,
+        limit: Option<usize>,
+        abort: &AbortFlag,
+    ) -> Result<Vec<ExtractedMatch>> {
+        if limit == Some(0) {
+            return Ok(Vec::new());
+        }
+
+        let mut names = None;
+        let mut matches = Vec::new();
+        self.scan_matches(&req, abort, |site| {
+            let names = names.get_or_insert_with(|| site.matcher.capture_names());
+            matches.push(extract_match(
+                site.path,
+                site.content,
+                site.line_index,
+                site.matcher,
+                names,
+                site.span,
+            )?);
+            Ok(limit.is_none_or(|limit| matches.len() < limit))
+        })?;
+
+        if abort.is_aborted() {
+            return Err(Error::Aborted);
+        }
+        Ok(matches)
+    }
+
+    /// Chunk every text file the request's filters select.
+    ///
+    /// Only the path filters of `req` apply; `find` is ignored. Returns the
+    /// index the chunks were cut from so callers can fetch their text.
+    pub fn chunk_candidates(
+        &self,
+        req: &FindRequest,
+        opts: ChunkOptions,
+    ) -> Result<(Arc<Index>, Vec<Chunk>)> {
+        let index = self.index_manager.index_for(req.where_)?;
+
+        let mut chunks = Vec::new();
+        for (path, entry) in select_candidates(
+            &index,
+            self.index_manager.generation(),
+            req,
+            self.scope.as_deref(),
+        )? {
+            match entry.search_content() {
+                Some(content) if !looks_binary(content) => {
+                    chunks.extend(chunk_text(path, content, opts)?);
                 }
+                _ => {}
             }
-            if let Some(ref globs) = exclude_globs {
-                if globs.is_match(path.as_str()) {
-                    continue;
+        }
+
+        Ok((index, chunks))
+    }
+
+    /// Count tokens across every text file the request's filters select.
+    ///
+    /// Only the path filters of `req` apply; `find` is ignored.
+    pub fn token_frequencies(
+        &self,
+        req: &FindRequest,
+        kind: TokenKind,
+        top_n: usize,
+    ) -> Result<Vec<TokenCount>> {
+        let index = self.index_manager.index_for(req.where_)?;
+
+        let mut counter = TokenCounter::new(kind);
+        for (_, entry) in select_candidates(
+            &index,
+            self.index_manager.generation(),
+            req,
+            self.scope.as_deref(),
+        )? {
+            match entry.search_content() {
+                Some(content) if !looks_binary(content) => {
+                    counter.add_file(&decode_lossy(content).0);
                 }
+                _ => {}
+            }
+        }
+
+        Ok(counter.top(top_n))
+    }
+
+    /// Visit every match of `req` across the candidate files.
+    ///
+    /// Returning `Ok(false)` from the callback stops the scan; an abort stops
+    /// it silently between files.
+    fn scan_matches(
+        &self,
+        req: &FindRequest,
+        abort: &AbortFlag,
+        on_match: impl FnMut(MatchSite<'_>) -> Result<bool>,
+    ) -> Result<LimitReport> {
+        abort.reset();
+
+        let index = self.index_manager.index_for(req.where_)?;
+        let mut limits = LimitReport::default();
+        self.scan_index(
+            &index,
+            req,
+            SearchPosition::default(),
+            abort,
+            &mut limits,
+            on_match,
+        )?;
+        Ok(limits)
+    }
+
+    /// Visit the matches of `req` in `index`, starting at `from`.
+    ///
+    /// Returns where to resume if the callback stopped the scan or it was
+    /// aborted, or `None` once every candidate has been scanned or the
+    /// request's `max_total_matches` was reached. Files and totals cut off
+    /// by the request's match limits are recorded in `limits`, and so are an
+    /// abort, including one from the request's `timeout_ms`, and candidates
+    /// skipped for lack of content.
+    fn scan_index(
+        &self,
+        index: &Index,
+        req: &FindRequest,
+        from: SearchPosition,
+        abort: &AbortFlag,
+        limits: &mut LimitReport,
+        mut on_match: impl FnMut(MatchSite<'_>) -> Result<bool>,
+    ) -> Result<Option<SearchPosition>> {
+        let find_matcher = FindMatcher::resolve(req)?;
+        let matcher = find_matcher.matcher();
+        let timed = req
+            .timeout_ms
+            .map(|ms| abort.with_timeout(ms, crate::now_ms));
+        let abort = timed.as_ref().unwrap_or(abort);
+
+        let mut emitted = from.emitted;
+        let candidates = select_candidates(
+            index,
+            self.index_manager.generation(),
+            req,
+            self.scope.as_deref(),
+        )?;
+        // Matching runs ahead on worker threads a batch of files at a time;
+        // matches are still visited here in candidate order, so results and
+        // limits come out as in a serial scan.
+        let prefetch = self.parallel && available_threads() > 1;
+        let per_file_cap = req.max_matches_per_file.map(|max| max + 1);
+        let mut batch: Vec<Option<Result<FileMatches>>> = Vec::new();
+        let mut batch_start = 0;
+        for (file, &(path, entry)) in candidates.iter().enumerate().skip(from.file) {
+            if abort.is_aborted() {
+                limits.aborted = true;
+                return Ok(Some(SearchPosition {
+                    file,
+                    skip: 0,
+                    emitted,
+                }));
+            }
+            let skip = if file == from.file { from.skip } else { 0 };
+
+            if prefetch && file >= batch_start + batch.len() {
+                let end = (file + PARALLEL_BATCH).min(candidates.len());
+                batch = map_ordered(&candidates[file..end], |&(path, entry)| {
+                    self.collect_file_matches(path, entry, matcher, per_file_cap, abort)
+                });
+                batch_start = file;
             }
+            let prefetched = if prefetch {
+                batch[file - batch_start].take()
+            } else {
+                None
+            };
 
             let content = match entry.search_content() {
                 Some(bytes) => bytes,
-                None => continue,
+                None => {
+                    limits.unhydrated += 1;
+                    continue;
+                }
             };
 
-            let line_index = LineIndex::build(content);
-
-            for_each_match(content, &matcher, |span, line_start| {
-                let line_end = line_index.line_of_byte(span.end).unwrap_or(line_start);
+            let (line_index, spans) = match prefetched {
+                Some(found) => {
+                    let found = found?;
+                    (found.line_index, Some(found.spans))
+                }
+                None => (self.search_line_index(path, entry, content), None),
+            };
 
-                match preview_builder.build_hunk(
-                    path.clone(),
-                    &line_index,
+            let mut seen = 0;
+            let mut resume = None;
+            let mut exhausted = false;
+            let mut visit = |span: ByteSpan, region_line: usize| {
+                seen += 1;
+                if seen <= skip {
+                    return Ok(true);
+                }
+                // Limits trip on the first match past them, so a file or
+                // search that ends exactly at a limit is not marked.
+                if req.max_matches_per_file.is_some_and(|max| seen > max) {
+                    limits.truncated_paths.push(path.clone());
+                    return Ok(false);
+                }
+                if req.max_total_matches.is_some_and(|max| emitted >= max) {
+                    limits.limit_reached = true;
+                    exhausted = true;
+                    return Ok(false);
+                }
+                emitted += 1;
+                let pattern_index = find_matcher.pattern_index(content, &span)?;
+                let keep_going = on_match(MatchSite {
+                    path,
                     content,
-                    &span,
-                    line_start,
-                    line_end,
-                ) {
-                    Ok(hunk) => {
-                        results.push(hunk);
-                        Ok(true)
-                    }
-                    Err(e) => {
-                        eprintln!("Preview build error: {e}");
-                        Ok(true)
+                    line_index: &line_index,
+                    matcher,
+                    span,
+                    region_line,
+                    pattern_index,
+                })?;
+                if !keep_going {
+                    resume = Some(SearchPosition {
+                        file,
+                        skip: seen,
+                        emitted,
+                    });
+                }
+                Ok(keep_going)
+            };
+            match spans {
+                Some(spans) => {
+                    for (span, region_line) in spans {
+                        if !visit(span, region_line)? {
+                            break;
+                        }
                     }
                 }
-            })?;
+                None => for_each_match(content, matcher, abort, visit)?,
+            }
+            if exhausted {
+                return Ok(None);
+            }
+            if resume.is_some() {
+                return Ok(resume);
+            }
+            if abort.is_aborted() {
+                // Stopped partway through this file; resume after the
+                // matches already visited.
+                limits.aborted = true;
+                return Ok(Some(SearchPosition {
+                    file,
+                    skip: seen,
+                    emitted,
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Line index for searching `entry`; files hinted with `hint_paths`
+    /// have one cached already.
+    fn search_line_index(
+        &self,
+        path: &PathKey,
+        entry: &FileEntry,
+        content: &[u8],
+    ) -> Arc<LineIndex> {
+        self.index_manager
+            .cached_line_index(path, entry)
+            .unwrap_or_else(|| Arc::new(LineIndex::build(content)))
+    }
+
+    /// Every match in `entry`, up to `cap`, for a worker thread to find
+    /// ahead of the scan. `None` for files without content or once aborted.
+    fn collect_file_matches(
+        &self,
+        path: &PathKey,
+        entry: &FileEntry,
+        matcher: &RegexMatcher,
+        cap: Option<usize>,
+        abort: &AbortFlag,
+    ) -> Option<Result<FileMatches>> {
+        if abort.is_aborted() {
+            return None;
+        }
+        let content = entry.search_content()?;
+        let mut spans = Vec::new();
+        let found = for_each_match(content, matcher, abort, |span, region_line| {
+            spans.push((span, region_line));
+            Ok(cap.is_none_or(|cap| spans.len() < cap))
+        });
+        Some(found.map(|()| FileMatches {
+            line_index: self.search_line_index(path, entry, content),
+            spans,
+        }))
+    }
+
+    /// Start a paginated search over a snapshot of the index `req` targets.
+    pub fn open_search(&self, req: FindRequest) -> Result<SearchCursor> {
+        // Resolve now so a bad pattern fails before a cursor is handed out.
+        FindMatcher::resolve(&req)?;
+        Ok(SearchCursor {
+            index: self.index_manager.index_for(req.where_)?,
+            request: req,
+            position: Some(SearchPosition::default()),
+        })
+    }
+
+    /// Run `req` to completion and keep the lines it matched for refining.
+    pub fn create_result_set(&self, req: FindRequest, abort: &AbortFlag) -> Result<ResultSet> {
+        let index = self.index_manager.index_for(req.where_)?;
+        let mut lines = Vec::new();
+        let mut limits = LimitReport::default();
+        self.scan_index(
+            &index,
+            &req,
+            SearchPosition::default(),
+            abort,
+            &mut limits,
+            |site| {
+                let line = site.line_index.line_of_byte(site.span.start).unwrap_or(1);
+                lines.push((site.path.clone(), line));
+                Ok(true)
+            },
+        )?;
+        if abort.is_aborted() {
+            return Err(Error::Aborted);
+        }
+        Ok(ResultSet::new(req, index, lines))
+    }
+
+    /// Return the next `page_size` results of `cursor` and advance it.
+    ///
+    /// Results come as hunks, or as bare hits when the request is `minimal`.
+    /// Once the cursor is exhausted further pages are empty.
+    pub fn find_page(
+        &self,
+        cursor: &mut SearchCursor,
+        page_size: usize,
+        abort: &AbortFlag,
+    ) -> Result<FindResponse> {
+        let mut results = Vec::new();
+        let mut hits = Vec::new();
+        let Some(from) = cursor.position else {
+            return Ok(FindResponse::new(results, hits));
+        };
+        if page_size == 0 {
+            return Ok(FindResponse::new(results, hits));
+        }
+
+        abort.reset();
+        let req = &cursor.request;
+        let preview_builder = PreviewBuilder::new(req.delta);
+        let mut limits = LimitReport::default();
+        let next = self.scan_index(&cursor.index, req, from, abort, &mut limits, |site| {
+            if req.minimal {
+                hits.push(FindHit {
+                    path: site.path.clone(),
+                    line: site.line_index.line_of_byte(site.span.start).unwrap_or(1),
+                    length: site.span.len(),
+                    pattern_index: site.pattern_index,
+                });
+                return Ok(hits.len() < page_size);
+            }
+            let line_end = site
+                .line_index
+                .line_of_byte(site.span.end)
+                .unwrap_or(site.region_line);
+            let hunk = preview_builder.build_hunk(
+                site.path.clone(),
+                site.line_index,
+                site.content,
+                &site.span,
+                site.region_line,
+                line_end,
+            )?;
+            results.push(PreviewHunk {
+                pattern_index: site.pattern_index,
+                ..hunk
+            });
+            Ok(results.len() < page_size)
+        })?;
+
+        if abort.is_aborted() {
+            return Err(Error::Aborted);
+        }
+        cursor.position = next;
+        Ok(limits.apply(FindResponse::new(results, hits)))
+    }
+
+    /// Regex find-and-replace across the staged files the request selects.
+    ///
+    /// Every file is planned before any is staged, so a bad template, a
+    /// strict UTF-8 failure or an abort leaves staging untouched. Binary
+    /// files are skipped.
+    pub fn handle_edit(&self, req: EditRequest, abort: &AbortFlag) -> Result<EditResponse> {
+        self.index_manager.ensure_staging()?;
+        let (items, rewrites) = self.plan_edit(&req, abort)?;
+
+        let label = format!("replace '{}' in {} files", req.find, rewrites.len());
+        self.stage_rewrites(&label, rewrites)?;
+        Ok(EditResponse { items })
+    }
+
+    /// Stage `rewrites` as one undo step named `label`.
+    fn stage_rewrites(&self, label: &str, rewrites: Vec<PlannedRewrite>) -> Result<()> {
+        self.index_manager.with_undo_step(label, || {
+            for rewrite in rewrites {
+                let total_lines = rewrite.content.iter().filter(|&&b| b == b'\n').count() + 1;
+                self.stage_bytes(&rewrite.path, rewrite.content)?;
+                self.index_manager.update_line_stats(
+                    &rewrite.path,
+                    rewrite.lines_added as isize,
+                    rewrite.lines_removed as isize,
+                    total_lines,
+                )?;
+                self.index_manager.mark_needs_read(&rewrite.path)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Previews of the replacements `handle_edit` would make, without
+    /// staging anything.
+    pub fn preview_edit(&self, req: &EditRequest, abort: &AbortFlag) -> Result<EditResponse> {
+        let (items, _) = self.plan_edit(req, abort)?;
+        Ok(EditResponse { items })
+    }
+
+    /// Previews of every replacement `req` would make, and the rewritten
+    /// content of each changed file.
+    fn plan_edit(
+        &self,
+        req: &EditRequest,
+        abort: &AbortFlag,
+    ) -> Result<(Vec<EditItem>, Vec<PlannedRewrite>)> {
+        let mut items = Vec::new();
+        let mut rewrites = Vec::new();
+        for (file, content) in self.plan_files(req, abort)? {
+            let (lines_removed, lines_added) = changed_lines(&file.items);
+            items.extend(file.items);
+            rewrites.push(PlannedRewrite {
+                path: file.path,
+                content,
+                lines_removed,
+                lines_added,
+            });
+        }
+        Ok((items, rewrites))
+    }
+
+    /// Hold every replacement `req` would make in an [`EditPlan`] for
+    /// [`Orchestrator::apply_edit_plan`], without staging anything.
+    pub fn create_edit_plan(&self, req: EditRequest, abort: &AbortFlag) -> Result<EditPlan> {
+        let files = self
+            .plan_files(&req, abort)?
+            .into_iter()
+            .map(|(file, _)| file)
+            .collect();
+        Ok(EditPlan::new(req, files))
+    }
+
+    /// Stage the hunks of `plan` named by `hunk_ids`, as one undo step.
+    ///
+    /// Fails with [`Error::ContentChanged`], staging nothing, if a selected
+    /// file changed since the plan was made. Returns previews of the
+    /// applied hunks.
+    pub fn apply_edit_plan(&self, plan: &EditPlan, hunk_ids: &[usize]) -> Result<EditResponse> {
+        let selected = plan.select(hunk_ids)?;
+        let index = self.staged_for_change()?;
+        let preview_builder = PreviewBuilder::new(plan.request.delta);
+
+        let mut items = Vec::new();
+        let mut rewrites = Vec::new();
+        for (file, ops) in selected {
+            let content = index
+                .get_file(&file.path)
+                .and_then(FileEntry::search_content)
+                .ok_or_else(|| Error::FileNotFound(file.path.as_str().to_string()))?;
+            file.check_unchanged(content)?;
+            let updated = apply_plan(content, &ops);
+            let file_items = preview_plan(&file.path, content, &updated, &ops, &preview_builder)?;
+            let (lines_removed, lines_added) = changed_lines(&file_items);
+            items.extend(file_items);
+            rewrites.push(PlannedRewrite {
+                path: file.path.clone(),
+                content: updated,
+                lines_removed,
+                lines_added,
+            });
         }
 
-        Ok(FindResponse { results })
+        let label = format!(
+            "replace '{}' in {} files",
+            plan.request.find,
+            rewrites.len()
+        );
+        self.stage_rewrites(&label, rewrites)?;
+        Ok(EditResponse { items })
     }
 
-    pub fn handle_edit(&self, _req: EditRequest, abort: &AbortFlag) -> Result<EditResponse> {
+    /// Plans for every file `req` changes, with their rewritten content.
+    fn plan_files(
+        &self,
+        req: &EditRequest,
+        abort: &AbortFlag,
+    ) -> Result<Vec<(PlannedFile, Vec<u8>)>> {
         abort.reset();
-        // not implemented
-        Ok(EditResponse { items: Vec::new() })
+
+        let index = self.index_manager.staged_index()?;
+        let matcher = resolve_matcher(&req.find, req.pattern_ref.as_deref(), &req.engine_opts)?;
+        let filters = FindRequest {
+            include_globs: req.include_globs.clone(),
+            exclude_globs: req.exclude_globs.clone(),
+            glob_options: req.glob_options,
+            prefix: req.prefix.clone(),
+            where_: SearchSpace::Staged,
+            include_ignored: true,
+            ..FindRequest::default()
+        };
+        let preview_builder = PreviewBuilder::new(req.delta);
+
+        let mut files = Vec::new();
+        for (path, entry) in select_candidates(
+            &index,
+            self.index_manager.generation(),
+            &filters,
+            self.scope.as_deref(),
+        )? {
+            if abort.is_aborted() {
+                return Err(Error::Aborted);
+            }
+            let content = match entry.search_content() {
+                Some(bytes) if !looks_binary(bytes) => bytes,
+                _ => continue,
+            };
+
+            let mut plan = plan_in_bytes(
+                content,
+                &matcher,
+                &req.replace,
+                req.engine_opts.multiline,
+                abort,
+            )?;
+            if plan.is_empty() {
+                continue;
+            }
+            if req.preserve_case {
+                preserve_case(content, &mut plan);
+            }
+            decode_checked(path.as_str(), content, self.strict_utf8)?;
+
+            let updated = apply_plan(content, &plan);
+            let items = preview_plan(path, content, &updated, &plan, &preview_builder)?;
+            files.push((
+                PlannedFile::new(path.clone(), content, plan, items),
+                updated,
+            ));
+        }
+
+        if abort.is_aborted() {
+            return Err(Error::Aborted);
+        }
+        Ok(files)
     }
 
     pub fn handle_read(
@@ -104,26 +806,27 @@ impl Orchestrator {
         end_line: usize,
         where_: SearchSpace,
     ) -> Result<ReadResponse> {
-        let index = match where_ {
-            SearchSpace::Active => self.index_manager.active_index(),
-            SearchSpace::Staged => self.index_manager.staged_index()?,
-        };
+        let index = self.index_with_content(path, where_)?;
 
         let entry = index
             .get_file(path)
             .ok_or_else(|| Error::FileNotFound(path.as_str().to_string()))?;
 
-        let content = entry.search_content().ok_or_else(|| {
-            Error::MissingContent(format!("File has no content: {}", path.as_str()))
-        })?;
+        let content = loaded_content(path, entry)?;
 
         let line_index = self
             .index_manager
             .get_line_index(path, &index)
             .ok_or_else(|| Error::FileNotFound(path.as_str().to_string()))?;
 
-        let result =
-            extract_lines_with_index(path.clone(), content, start_line, end_line, &line_index)?;
+        let result = extract_lines_checked(
+            path.clone(),
+            content,
+            start_line,
+            end_line,
+            &line_index,
+            self.strict_utf8,
+        )?;
 
         if where_ == SearchSpace::Staged {
             self.index_manager.clear_needs_read(path)?;
@@ -133,6 +836,12 @@ impl Orchestrator {
     }
 
     pub fn handle_create(&self, req: CreateRequest) -> Result<CreateResponse> {
+        let label = format!("create {}", req.path.as_str());
+        self.index_manager
+            .with_undo_step(&label, || self.create_file(req))
+    }
+
+    fn create_file(&self, req: CreateRequest) -> Result<CreateResponse> {
         let staged = self.index_manager.staged_index()?;
         let exists = staged.get_file(&req.path).is_some();
 
@@ -150,45 +859,80 @@ impl Orchestrator {
         };
 
         let size = entry.size();
-
-        let line_count = if let Some(bytes) = entry.search_content() {
-            bytes.iter().filter(|&&b| b == b'\n').count() + 1
-        } else {
-            0
-        };
+        let line_count = Self::count_lines(&entry);
 
         self.index_manager.stage_file(req.path.clone(), entry)?;
+        self.record_write_stats(&req.path, exists, line_count)?;
+
+        Ok(CreateResponse {
+            path: req.path,
+            size,
+            created: !exists,
+        })
+    }
+
+    /// Replace the whole staged content of `path`, but only if its active
+    /// content still hashes to `expected` (`None`: the file must not exist).
+    pub fn handle_overwrite(
+        &self,
+        path: PathKey,
+        content: Vec<u8>,
+        expected: Option<u64>,
+    ) -> Result<CreateResponse> {
+        let exists = self.staged_for_change()?.get_file(&path).is_some();
+        let entry =
+            FileEntry::from_bytes_and_path(&path, current_unix_timestamp(), content.into(), true);
+        let size = entry.size();
+        let line_count = Self::count_lines(&entry);
+
+        let label = format!("overwrite {}", path.as_str());
+        self.index_manager.with_undo_step(&label, || {
+            self.index_manager
+                .stage_file_if_active(path.clone(), entry, expected)?;
+            self.record_write_stats(&path, exists, line_count)
+        })?;
+
+        Ok(CreateResponse {
+            path,
+            size,
+            created: !exists,
+        })
+    }
+
+    fn count_lines(entry: &FileEntry) -> usize {
+        entry
+            .search_content()
+            .map_or(0, |bytes| bytes.iter().filter(|&&b| b == b'\n').count() + 1)
+    }
 
-        if !exists {
+    fn record_write_stats(&self, path: &PathKey, existed: bool, line_count: usize) -> Result<()> {
+        if !existed {
             // New file - all lines are added
             self.index_manager
-                .update_line_stats(&req.path, line_count as isize, 0, line_count)?;
+                .update_line_stats(path, line_count as isize, 0, line_count)?;
         } else {
             // Overwriting existing file - need to calculate the delta
-            if let Ok(active_content) = self.get_file_content(&req.path, SearchSpace::Active) {
+            if let Ok(active_content) = self.get_file_content(path, SearchSpace::Active) {
                 let original_lines = active_content.lines().count();
                 self.index_manager.update_line_stats(
-                    &req.path,
+                    path,
                     line_count as isize,
                     original_lines as isize,
                     line_count,
                 )?;
             }
         }
-
-        Ok(CreateResponse {
-            path: req.path,
-            size,
-            created: !exists,
-        })
+        Ok(())
     }
 
     pub fn handle_delete(&self, req: DeleteRequest) -> Result<DeleteResponse> {
-        let staged = self.index_manager.staged_index()?;
+        let staged = self.staged_for_change()?;
         let existed = staged.get_file(&req.path).is_some();
 
         if existed {
-            self.index_manager.remove_staged_file(&req.path)?;
+            let label = format!("delete {}", req.path.as_str());
+            self.index_manager
+                .with_undo_step(&label, || self.index_manager.remove_staged_file(&req.path))?;
         }
 
         Ok(DeleteResponse {
@@ -197,101 +941,636 @@ impl Orchestrator {
         })
     }
 
-    fn copy_single_file(&self, src: &PathKey, dst: &PathKey) -> Result<()> {
-        let staged = self.index_manager.staged_index()?;
-        let src_entry = staged
-            .get_file(src)
-            .ok_or_else(|| Error::FileNotFound(src.as_str().to_string()))?;
-
-        let original_bytes = src_entry.bytes().ok_or_else(|| {
-            Error::MissingContent(format!("No original bytes for: {}", src.as_str()))
-        })?;
-
-        let line_count = original_bytes.iter().filter(|&&b| b == b'\n').count() + 1;
-        let src_content = String::from_utf8_lossy(original_bytes).to_string();
-
-        self.stage_file_with_content(dst, src_content)?;
-
-        if let Ok(active_content) = self.get_file_content(dst, SearchSpace::Active) {
-            let original_lines = active_content.lines().count();
-            self.index_manager.update_line_stats(
-                dst,
-                line_count as isize,
-                original_lines as isize,
-                line_count,
-            )?;
-        } else {
-            self.index_manager
-                .update_line_stats(dst, line_count as isize, 0, line_count)?;
-        }
-
-        if self.index_manager.check_needs_read(src)? {
-            self.index_manager.mark_needs_read(dst)?;
+    pub fn handle_apply_scaffold(
+        &self,
+        req: ApplyScaffoldRequest,
+    ) -> Result<ApplyScaffoldResponse> {
+        let template = with_scaffolds(|r| r.get(&req.template).cloned())
+            .ok_or_else(|| Error::UnknownTemplate(req.template.clone()))?;
+
+        let prefix = req.dst_prefix.trim_end_matches('/');
+        let mut files = Vec::with_capacity(template.files.len());
+        for (rel_path, content) in template.render(&req.params)? {
+            let full = if prefix.is_empty() {
+                rel_path
+            } else {
+                format!("{}/{}", prefix, rel_path.trim_start_matches('/'))
+            };
+            let path = create_path_key(&full)?;
+            if files.iter().any(|(p, _): &(PathKey, String)| p == &path) {
+                return Err(Error::InvalidPath(format!(
+                    "template '{}' renders '{}' more than once",
+                    template.name, full
+                )));
+            }
+            files.push((path, content));
         }
 
-        Ok(())
-    }
-
-    pub fn handle_copy_files(&self, req: BatchCopyRequest) -> Result<BatchOperationResponse> {
-        let count = req.operations.len();
-        self.index_manager.with_snapshot(|| {
-            for operation in &req.operations {
-                self.copy_single_file(&operation.src, &operation.dst)?;
+        // All-or-nothing: a conflict on any file leaves staging untouched.
+        let label = format!("apply scaffold {}", template.name);
+        self.index_manager.with_undo_step(&label, || {
+            let mut created = Vec::with_capacity(files.len());
+            for (path, content) in files {
+                let response = self.handle_create(CreateRequest {
+                    path,
+                    content: Some(content.into_bytes()),
+                    allow_overwrite: req.allow_overwrite,
+                })?;
+                created.push(response.path);
             }
-            Ok(BatchOperationResponse { count })
+            Ok(ApplyScaffoldResponse { created })
         })
     }
 
-    pub fn handle_move_files(&self, req: BatchMoveRequest) -> Result<BatchOperationResponse> {
-        let count = req.operations.len();
-        self.index_manager.with_snapshot(|| {
-            for operation in &req.operations {
-                self.index_manager.move_staged_file(
-                    &operation.src,
-                    &operation.dst,
-                    current_unix_timestamp(),
-                )?;
+    /// Delete every path in one transaction, returning the paths that did not exist.
+    pub fn handle_delete_many(&self, paths: Vec<PathKey>) -> Result<Vec<PathKey>> {
+        let label = format!("delete {} files", paths.len());
+        self.index_manager.with_undo_step(&label, || {
+            let mut missing = Vec::new();
+            for path in paths {
+                let response = self.handle_delete(DeleteRequest::new(path))?;
+                if !response.existed {
+                    missing.push(response.path);
+                }
             }
-            Ok(BatchOperationResponse { count })
+            Ok(missing)
         })
     }
 
-    fn get_file_content(&self, path: &PathKey, where_: SearchSpace) -> Result<String> {
-        let index = match where_ {
-            SearchSpace::Staged => self.index_manager.staged_index()?,
-            SearchSpace::Active => self.index_manager.active_index(),
-        };
-
-        let entry = index
-            .get_file(path)
-            .ok_or_else(|| Error::InvalidPath(format!("File not found: {}", path.as_str())))?;
-
-        let content = entry.search_content().ok_or_else(|| {
-            Error::MissingContent(format!("File has no content: {}", path.as_str()))
-        })?;
+    /// Bundle the staged changes into an archive.
+    ///
+    /// Created, modified and moved files are stored with their staged
+    /// content under their staged path; deletions and moves are listed in a
+    /// [`ChangeManifest`] at [`CHANGE_MANIFEST_PATH`](conduit_core::tools::CHANGE_MANIFEST_PATH).
+    pub fn export_changes_archive(&self, format: ArchiveFormat) -> Result<Vec<u8>> {
+        let staged = self.index_manager.staged_index()?;
+        let mut summaries = self
+            .summarize_modified_files(&AbortFlag::new(), |_, _| {})?
+            .items;
+        summaries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut writer = ArchiveWriter::new(format);
+        let (mut deleted, mut moved) = (Vec::new(), Vec::new());
+        for summary in summaries {
+            let path = match (summary.status, summary.moved_to) {
+                (FileChangeStatus::Deleted, _) => {
+                    deleted.push(summary.path);
+                    continue;
+                }
+                (_, Some(dst)) => {
+                    moved.push(FileOperation {
+                        src: summary.path,
+                        dst: dst.clone(),
+                    });
+                    dst
+                }
+                (_, None) => summary.path,
+            };
+            let entry = staged
+                .get_file(&path)
+                .ok_or_else(|| Error::FileNotFound(path.as_str().to_string()))?;
+            let content = entry
+                .bytes()
+                .or(entry.search_content())
+                .ok_or_else(|| Error::MissingContent(path.as_str().to_string()))?;
+            writer.add_file(path.as_str(), content, entry.mtime())?;
+        }
 
-        Ok(String::from_utf8_lossy(content).into_owned())
+        let manifest = ChangeManifest::new(deleted, moved);
+        writer.add_manifest(&manifest, current_unix_timestamp())?;
+        writer.finish()
     }
 
-    fn stage_file_with_content(&self, path: &PathKey, content: String) -> Result<()> {
-        // Get the existing file's editable status from staged index
-        let editable = self
+    /// Apply a unified diff to staging.
+    ///
+    /// Every section is checked against staged content, as left by earlier
+    /// sections of the same patch, before anything is staged; staging only
+    /// changes if every file and hunk applies, as one undo step.
+    pub fn handle_apply_patch(&self, patch: &str) -> Result<PatchReport> {
+        let sections = parse_patch(patch)?;
+        let staged = self.staged_for_change()?;
+
+        // Content of paths touched by earlier sections; `None` once removed.
+        let mut pending: HashMap<PathKey, Option<String>> = HashMap::new();
+        let current =
+            |pending: &HashMap<PathKey, Option<String>>, path: &PathKey| match pending.get(path) {
+                Some(content) => Ok(content.clone()),
+                None if staged.get_file(path).is_some() => self
+                    .read_text(path, SearchSpace::Staged)
+                    .map(|(text, _)| Some(text)),
+                None => Ok(None),
+            };
+
+        let mut planned = Vec::new();
+        let mut files = Vec::with_capacity(sections.len());
+        for section in &sections {
+            let key = |path: &Option<String>| path.as_deref().map(create_path_key).transpose();
+            let (old, new) = (key(&section.old_path)?, key(&section.new_path)?);
+            let mut outcome = FilePatchOutcome {
+                old_path: old.clone(),
+                new_path: new.clone(),
+                error: None,
+                hunks: Vec::new(),
+            };
+
+            let source = match &old {
+                Some(path) => current(&pending, path)?,
+                None => Some(String::new()),
+            };
+            let target_taken = match &new {
+                Some(path) if old.as_ref() != Some(path) => current(&pending, path)?.is_some(),
+                _ => false,
+            };
+            let Some(source) = source.filter(|_| !target_taken) else {
+                outcome.error = Some(match (&old, &new) {
+                    (Some(path), _) if !target_taken => format!("{} does not exist", path.as_str()),
+                    (_, Some(path)) => format!("{} already exists", path.as_str()),
+                    _ => unreachable!("a missing source implies an old path"),
+                });
+                files.push(outcome);
+                continue;
+            };
+
+            let (content, hunks) = apply_hunks(&source, &section.hunks);
+            outcome.hunks = hunks;
+            if new.is_none() && !content.is_empty() {
+                outcome.error = Some("file is deleted but hunks leave content".to_string());
+            }
+            if outcome.is_applied() {
+                if let Some(path) = old.as_ref().filter(|&path| new.as_ref() != Some(path)) {
+                    pending.insert(path.clone(), None);
+                }
+                if let Some(path) = &new {
+                    pending.insert(path.clone(), Some(content.clone()));
+                }
+                let (added, removed) = section.hunks.iter().fold((0, 0), |(a, r), hunk| {
+                    let (added, removed) = hunk.line_counts();
+                    (a + added, r + removed)
+                });
+                planned.push((old, new, source != content, content, added, removed));
+            }
+            files.push(outcome);
+        }
+
+        let applied = files.iter().all(FilePatchOutcome::is_applied);
+        if applied {
+            let label = format!("apply patch to {} files", planned.len());
+            self.index_manager.with_undo_step(&label, || {
+                for (old, new, changed, content, added, removed) in planned {
+                    let Some(new) = new else {
+                        if let Some(old) = old {
+                            self.index_manager.remove_staged_file(&old)?;
+                        }
+                        continue;
+                    };
+                    match old {
+                        Some(old) if old != new => {
+                            self.index_manager.move_staged_file(
+                                &old,
+                                &new,
+                                current_unix_timestamp(),
+                            )?;
+                        }
+                        Some(_) => {}
+                        None => {
+                            let lines = content.lines().count();
+                            self.stage_file_with_content(&new, content)?;
+                            self.record_write_stats(&new, false, lines)?;
+                            continue;
+                        }
+                    }
+                    if changed {
+                        let total_lines = content.lines().count();
+                        self.stage_file_with_content(&new, content)?;
+                        self.index_manager.update_line_stats(
+                            &new,
+                            added as isize,
+                            removed as isize,
+                            total_lines,
+                        )?;
+                        self.index_manager.mark_needs_read(&new)?;
+                    }
+                }
+                Ok(())
+            })?;
+        }
+        Ok(PatchReport { applied, files })
+    }
+
+    /// Three-way merge of the staged copy of `req.path` with `req.theirs`,
+    /// staging the result when `req.stage` is set.
+    pub fn handle_merge_file(&self, req: MergeFileRequest) -> Result<MergeFileResponse> {
+        let (ours, _) = self.read_text(&req.path, SearchSpace::Staged)?;
+        let outcome = merge_three_way(&req.base, &ours, &req.theirs);
+        let staged = req.stage && outcome.content != ours;
+        if staged {
+            let label = format!("merge {}", req.path.as_str());
+            self.index_manager.with_undo_step(&label, || {
+                let stats = compute_diff(req.path.clone(), &ours, &outcome.content, 0).stats;
+                self.stage_file_with_content(&req.path, outcome.content.clone())?;
+                self.index_manager.update_line_stats(
+                    &req.path,
+                    stats.lines_added as isize,
+                    stats.lines_removed as isize,
+                    outcome.content.lines().count(),
+                )?;
+                self.index_manager.mark_needs_read(&req.path)
+            })?;
+        }
+        Ok(MergeFileResponse {
+            path: req.path,
+            outcome,
+            staged,
+        })
+    }
+
+    fn copy_single_file(&self, src: &PathKey, dst: &PathKey) -> Result<()> {
+        self.index_manager
+            .copy_staged_file(src, dst, current_unix_timestamp())?;
+
+        // Binary files have no lines to count.
+        let line_count = self
             .index_manager
             .staged_index()?
+            .get_file(dst)
+            .and_then(|entry| entry.search_content())
+            .filter(|content| !looks_binary(content))
+            .map_or(0, |content| {
+                content.iter().filter(|&&b| b == b'\n').count() + 1
+            });
+
+        if let Ok(active_content) = self.get_file_content(dst, SearchSpace::Active) {
+            let original_lines = active_content.lines().count();
+            self.index_manager.update_line_stats(
+                dst,
+                line_count as isize,
+                original_lines as isize,
+                line_count,
+            )?;
+        } else {
+            self.index_manager
+                .update_line_stats(dst, line_count as isize, 0, line_count)?;
+        }
+
+        if self.index_manager.check_needs_read(src)? {
+            self.index_manager.mark_needs_read(dst)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn handle_copy_files(&self, req: BatchCopyRequest) -> Result<BatchOperationResponse> {
+        let label = format!("copy {} files", req.operations.len());
+        self.index_manager.with_undo_step(&label, || {
+            self.run_batch(&req.operations, req.continue_on_error, |op| {
+                let staged = self.index_manager.staged_index()?;
+                let (dst, conflict) = req.on_conflict.resolve(&staged, &op.src, &op.dst)?;
+                self.copy_single_file(&op.src, &dst)?;
+                Ok(conflict)
+            })
+        })
+    }
+
+    pub fn handle_move_files(&self, req: BatchMoveRequest) -> Result<BatchOperationResponse> {
+        let label = format!("move {} files", req.operations.len());
+        self.index_manager.with_undo_step(&label, || {
+            self.run_batch(&req.operations, req.continue_on_error, |op| {
+                let staged = self.index_manager.staged_index()?;
+                let (dst, conflict) = req.on_conflict.resolve(&staged, &op.src, &op.dst)?;
+                self.index_manager
+                    .move_staged_file(&op.src, &dst, current_unix_timestamp())?;
+                Ok(conflict)
+            })
+        })
+    }
+
+    /// Move every staged file under `req.from` to `req.to`, all or nothing,
+    /// optionally rewriting import paths that point into or out of the tree.
+    pub fn handle_move_tree(&self, req: MoveTreeRequest) -> Result<MoveTreeResponse> {
+        let rewriter = ReferenceRewriter::new(&req.from, &req.to)?;
+        let staged = self.staged_for_change()?;
+        let (moved, conflicts) = dir_operations(&staged, &req.from, &req.to, req.on_conflict)?;
+
+        let mut planned = Vec::new();
+        if req.rewrite_references {
+            let destinations: HashMap<&PathKey, &PathKey> =
+                moved.iter().map(|op| (&op.src, &op.dst)).collect();
+            let overwritten: HashSet<&PathKey> = conflicts
+                .iter()
+                .filter(|c| c.renamed_to.is_none())
+                .map(|c| &c.dst)
+                .collect();
+            for (path, entry) in staged.iter_sorted() {
+                // A file about to be overwritten needs no rewrite.
+                if overwritten.contains(path) {
+                    continue;
+                }
+                // Files that are not clean UTF-8 text are never rewritten.
+                let Some(text) = entry
+                    .bytes()
+                    .filter(|bytes| !looks_binary(bytes))
+                    .and_then(|bytes| std::str::from_utf8(bytes).ok())
+                else {
+                    continue;
+                };
+                let new_path = destinations.get(path).copied().unwrap_or(path);
+                if let Some((content, edits)) =
+                    rewriter.rewrite(text, path.as_str(), new_path.as_str())
+                {
+                    planned.push((new_path.clone(), content, edits));
+                }
+            }
+        }
+
+        if !req.dry_run {
+            let label = format!("move {} to {}", req.from, req.to);
+            self.index_manager.with_undo_step(&label, || {
+                let now = current_unix_timestamp();
+                for op in &moved {
+                    self.index_manager.move_staged_file(&op.src, &op.dst, now)?;
+                }
+                for (path, content, edits) in &planned {
+                    let total_lines = content.lines().count();
+                    self.stage_file_with_content(path, content.clone())?;
+                    self.index_manager.update_line_stats(
+                        path,
+                        edits.len() as isize,
+                        edits.len() as isize,
+                        total_lines,
+                    )?;
+                }
+                Ok(())
+            })?;
+        }
+
+        let mut rewrites: Vec<FileRewrite> = planned
+            .into_iter()
+            .map(|(path, _, edits)| FileRewrite { path, edits })
+            .collect();
+        rewrites.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(MoveTreeResponse {
+            moved,
+            rewrites,
+            conflicts,
+        })
+    }
+
+    /// Move every staged file under `req.from` to `req.to`, all or nothing.
+    pub fn handle_move_dir(&self, req: MoveDirRequest) -> Result<DirOperationResponse> {
+        let moved = self.handle_move_tree(MoveTreeRequest {
+            from: req.from,
+            to: req.to,
+            rewrite_references: false,
+            dry_run: false,
+            on_conflict: req.on_conflict,
+        })?;
+        Ok(DirOperationResponse {
+            operations: moved.moved,
+            conflicts: moved.conflicts,
+        })
+    }
+
+    /// Copy every staged file under `req.from` to `req.to`, all or nothing.
+    pub fn handle_copy_dir(&self, req: MoveDirRequest) -> Result<DirOperationResponse> {
+        let staged = self.staged_for_change()?;
+        let (operations, conflicts) = dir_operations(&staged, &req.from, &req.to, req.on_conflict)?;
+        let label = format!("copy {} to {}", req.from, req.to);
+        self.index_manager.with_undo_step(&label, || {
+            for op in &operations {
+                self.copy_single_file(&op.src, &op.dst)?;
+            }
+            Ok(())
+        })?;
+        Ok(DirOperationResponse {
+            operations,
+            conflicts,
+        })
+    }
+
+    /// Delete every staged file under `req.path`.
+    pub fn handle_delete_dir(&self, req: DeleteDirRequest) -> Result<DeleteDirResponse> {
+        // Refuse to wipe the whole index through an empty path.
+        if req.path.trim_end_matches('/').is_empty() {
+            return Err(Error::InvalidPath(req.path));
+        }
+        let staged = self.staged_for_change()?;
+        let deleted = staged.paths_under(&req.path);
+        if deleted.is_empty() {
+            return Err(Error::FileNotFound(req.path));
+        }
+        let label = format!("delete {}", req.path);
+        self.index_manager.with_undo_step(&label, || {
+            for path in &deleted {
+                self.index_manager.remove_staged_file(path)?;
+            }
+            Ok(())
+        })?;
+        Ok(DeleteDirResponse { deleted })
+    }
+
+    /// Apply `apply` to each operation, either all-or-nothing or, with
+    /// `continue_on_error`, each in its own transaction with failures recorded.
+    fn run_batch(
+        &self,
+        operations: &[FileOperation],
+        continue_on_error: bool,
+        apply: impl Fn(&FileOperation) -> Result<Option<DestinationConflict>>,
+    ) -> Result<BatchOperationResponse> {
+        if !continue_on_error {
+            return self.index_manager.with_snapshot(|| {
+                let mut conflicts = Vec::new();
+                for operation in operations {
+                    conflicts.extend(apply(operation)?);
+                }
+                Ok(BatchOperationResponse {
+                    count: operations.len(),
+                    results: Vec::new(),
+                    conflicts,
+                })
+            });
+        }
+
+        // Without staging every operation would fail the same way.
+        self.index_manager.staged_index()?;
+
+        let mut results = Vec::with_capacity(operations.len());
+        let mut conflicts = Vec::new();
+        for operation in operations {
+            let error = match self.index_manager.with_snapshot(|| apply(operation)) {
+                Ok(conflict) => {
+                    conflicts.extend(conflict);
+                    None
+                }
+                Err(e) => Some(OperationFailure {
+                    code: e.code().to_string(),
+                    message: e.to_string(),
+                }),
+            };
+            results.push(OperationResult {
+                src: operation.src.clone(),
+                dst: operation.dst.clone(),
+                error,
+            });
+        }
+
+        Ok(BatchOperationResponse {
+            count: results.iter().filter(|r| r.error.is_none()).count(),
+            results,
+            conflicts,
+        })
+    }
+
+    fn get_file_content(&self, path: &PathKey, where_: SearchSpace) -> Result<String> {
+        let index = self.index_with_content(path, where_)?;
+
+        let entry = index
             .get_file(path)
-            .map(|entry| entry.is_editable())
-            .unwrap_or(true); // Default to editable if file doesn't exist yet
+            .ok_or_else(|| Error::InvalidPath(format!("File not found: {}", path.as_str())))?;
+
+        let content = loaded_content(path, entry)?;
+
+        Ok(String::from_utf8_lossy(content).into_owned())
+    }
+
+    /// The staged index a change starts from, beginning staging first if
+    /// the manager is configured to.
+    fn staged_for_change(&self) -> Result<Arc<Index>> {
+        self.index_manager.ensure_staging()?;
+        self.index_manager.staged_index()
+    }
+
+    /// Staged content for a line-based edit, honoring strict UTF-8 mode.
+    ///
+    /// Returns the text and whether invalid UTF-8 was replaced.
+    fn get_editable_content(&self, path: &PathKey) -> Result<(String, bool)> {
+        self.read_text(path, SearchSpace::Staged)
+    }
+
+    /// Whole-file text and whether invalid UTF-8 was replaced.
+    pub fn read_text(&self, path: &PathKey, where_: SearchSpace) -> Result<(String, bool)> {
+        let index = self.index_with_content(path, where_)?;
+
+        let entry = index
+            .get_file(path)
+            .ok_or_else(|| Error::InvalidPath(format!("File not found: {}", path.as_str())))?;
+
+        let content = loaded_content(path, entry)?;
+
+        let (text, lossy) = decode_checked(path.as_str(), content, self.strict_utf8)?;
+        Ok((text.into_owned(), lossy))
+    }
+
+    /// Whole-file bytes as loaded, before any transcoding to UTF-8.
+    pub fn read_bytes(&self, path: &PathKey, where_: SearchSpace) -> Result<Vec<u8>> {
+        let index = self.index_with_content(path, where_)?;
+
+        let entry = index
+            .get_file(path)
+            .ok_or_else(|| Error::FileNotFound(path.as_str().to_string()))?;
+
+        match entry.bytes() {
+            Some(bytes) => Ok(bytes.to_vec()),
+            None => loaded_content(path, entry).map(<[u8]>::to_vec),
+        }
+    }
+
+    /// The index for `where_`, first fetching `path`'s content from the
+    /// host if it is loaded as metadata only.
+    fn index_with_content(&self, path: &PathKey, where_: SearchSpace) -> Result<Arc<Index>> {
+        let index = self.index_manager.index_for(where_)?;
+        let unhydrated = index
+            .get_file(path)
+            .is_some_and(|entry| entry.search_content().is_none());
+        if unhydrated && crate::bindings::lazy_ops::fetch_content(path)? {
+            return self.index_manager.index_for(where_);
+        }
+        Ok(index)
+    }
+
+    /// Record a whole-file read of `path`, as [`Self::handle_read`] does for
+    /// line ranges, so the file no longer needs reading before edits.
+    pub fn record_read(&self, path: &PathKey, where_: SearchSpace) -> Result<()> {
+        if where_ == SearchSpace::Staged {
+            self.index_manager.clear_needs_read(path)?;
+        }
+        Ok(())
+    }
+
+    /// Fail if the staged `path` no longer matches `precondition`.
+    fn check_precondition(
+        &self,
+        path: &PathKey,
+        precondition: Option<&EditPrecondition>,
+    ) -> Result<()> {
+        let Some(precondition) = precondition else {
+            return Ok(());
+        };
+        let staged = self.index_manager.staged_index()?;
+        let entry = staged
+            .get_file(path)
+            .ok_or_else(|| Error::FileNotFound(path.as_str().to_string()))?;
+        precondition.check(path, entry)
+    }
+
+    fn stage_file_with_content(&self, path: &PathKey, content: String) -> Result<()> {
+        self.stage_bytes(path, content.into_bytes())
+    }
+
+    fn stage_bytes(&self, path: &PathKey, content: Vec<u8>) -> Result<()> {
+        let entry = self.edited_entry(path, content)?;
+        self.index_manager.stage_file(path.clone(), entry)
+    }
+
+    /// Stage the result of a line edit. Its splice locates the change in the
+    /// staged text, which is only the stored content if nothing was replaced
+    /// while decoding it.
+    fn stage_line_edit(&self, path: &PathKey, edit: LineEdit, lossy: bool) -> Result<()> {
+        let entry = self.edited_entry(path, edit.content.into_bytes())?;
+        if lossy {
+            self.index_manager.stage_file(path.clone(), entry)
+        } else {
+            self.index_manager
+                .stage_file_spliced(path.clone(), entry, edit.splice)
+        }
+    }
+
+    /// Entry holding edited `content` for `path`.
+    fn edited_entry(&self, path: &PathKey, content: Vec<u8>) -> Result<FileEntry> {
+        // Carry over the existing file's flags and encoding from the staged index
+        let (editable, hidden, encoding) = self
+            .index_manager
+            .staged_index()?
+            .get_file(path)
+            .map(|entry| {
+                (
+                    entry.is_editable(),
+                    entry.is_host_hidden(),
+                    entry.encoding(),
+                )
+            })
+            .unwrap_or((true, false, TextEncoding::Utf8)); // Default to editable if file doesn't exist yet
+
+        // Edits produce UTF-8; write them back in the file's own encoding
+        // unless the new text has characters it cannot hold.
+        let content = match std::str::from_utf8(&content) {
+            Ok(text) if encoding != TextEncoding::Utf8 => {
+                encoding::encode(text, encoding).unwrap_or(content)
+            }
+            _ => content,
+        };
 
         let current_time = current_unix_timestamp();
-        let modified_bytes = content.into_bytes();
-        let modified_entry =
-            FileEntry::from_bytes_and_path(path, current_time, modified_bytes.into(), editable);
-        self.index_manager.stage_file(path.clone(), modified_entry)
+        let mut modified_entry =
+            FileEntry::from_bytes_and_path(path, current_time, content.into(), editable);
+        modified_entry.set_hidden(hidden);
+        Ok(modified_entry)
     }
 
     pub fn handle_replace_lines(&self, req: ReplaceLinesRequest) -> Result<ReplaceLinesResponse> {
-        self.index_manager.with_snapshot(|| {
-            let content = self.get_file_content(&req.path, SearchSpace::Staged)?;
+        let label = format!("replace lines in {}", req.path.as_str());
+        self.index_manager.with_undo_step(&label, || {
+            self.check_precondition(&req.path, req.precondition.as_ref())?;
+            let (content, lossy) = self.get_editable_content(&req.path)?;
             let original_lines = content.lines().count();
 
             let operations: Vec<LineOperation> = req
@@ -306,11 +1585,21 @@ impl Orchestrator {
                 )
                 .collect();
 
-            let (modified_content, lines_added, lines_removed) =
-                apply_line_operations(&content, operations);
-            let total_lines = modified_content.lines().count();
+            // Ranges are applied bottom-up, so overlapping ones would each
+            // rewrite lines the other already changed.
+            let conflicts = overlapping_operations(&operations);
+            if !conflicts.is_empty() {
+                return Err(Error::OverlappingEdits {
+                    path: req.path.as_str().to_string(),
+                    conflicts,
+                });
+            }
+
+            let edit = apply_line_edit(&content, operations, &self.edit_policy);
+            let (lines_added, lines_removed) = (edit.lines_added, edit.lines_removed);
+            let total_lines = edit.content.lines().count();
 
-            self.stage_file_with_content(&req.path, modified_content)?;
+            self.stage_line_edit(&req.path, edit, lossy)?;
             self.index_manager.update_line_stats(
                 &req.path,
                 lines_added as isize,
@@ -325,13 +1614,69 @@ impl Orchestrator {
                 lines_added: lines_added as isize - lines_removed as isize,
                 total_lines,
                 original_lines,
+                lossy,
+            })
+        })
+    }
+
+    /// Replace the text `req.expected_text` locates. Fails without staging
+    /// anything if the text is missing or matches more than once.
+    pub fn handle_anchored_edit(&self, req: AnchoredEditRequest) -> Result<AnchoredEditResponse> {
+        let label = format!("anchored edit in {}", req.path.as_str());
+        self.index_manager.with_undo_step(&label, || {
+            self.check_precondition(&req.path, req.precondition.as_ref())?;
+            let (content, lossy) = self.get_editable_content(&req.path)?;
+            let original_lines = content.lines().count();
+            let edit = apply_anchored_edit(&content, &req)?;
+            let total_lines = edit.content.lines().count();
+            let (start_line, end_line) = edit.original_lines;
+            let (new_start, new_end) = edit.new_lines;
+
+            self.stage_file_with_content(&req.path, edit.content)?;
+            self.index_manager.update_line_stats(
+                &req.path,
+                (new_end - new_start + 1) as isize,
+                (end_line - start_line + 1) as isize,
+                total_lines,
+            )?;
+            self.index_manager.mark_needs_read(&req.path)?;
+
+            Ok(AnchoredEditResponse {
+                path: req.path,
+                start_line,
+                end_line,
+                occurrences: edit.occurrences,
+                lines_added: total_lines as isize - original_lines as isize,
+                total_lines,
+                original_lines,
+                lossy,
             })
         })
     }
 
+    pub fn handle_validate_lines(
+        &self,
+        req: ValidateLinesRequest,
+    ) -> Result<ValidateLinesResponse> {
+        let (content, _) = self.read_text(&req.path, SearchSpace::Staged)?;
+        let issues = validate_line_operations(&content, &req.operations);
+        Ok(ValidateLinesResponse {
+            line_count: content
+                .strip_prefix('\u{FEFF}')
+                .unwrap_or(&content)
+                .lines()
+                .count(),
+            valid: issues.is_empty(),
+            issues,
+            path: req.path,
+        })
+    }
+
     pub fn handle_delete_lines(&self, req: DeleteLinesRequest) -> Result<ReplaceLinesResponse> {
-        self.index_manager.with_snapshot(|| {
-            let content = self.get_file_content(&req.path, SearchSpace::Staged)?;
+        let label = format!("delete lines in {}", req.path.as_str());
+        self.index_manager.with_undo_step(&label, || {
+            self.check_precondition(&req.path, req.precondition.as_ref())?;
+            let (content, lossy) = self.get_editable_content(&req.path)?;
             let original_lines = content.lines().count();
 
             let mut sorted_lines = req.line_numbers;
@@ -356,11 +1701,11 @@ impl Orchestrator {
                 operations.push(LineOperation::DeleteRange { start, end });
             }
 
-            let (modified_content, lines_added, lines_removed) =
-                apply_line_operations(&content, operations);
-            let total_lines = modified_content.lines().count();
+            let edit = apply_line_edit(&content, operations, &self.edit_policy);
+            let (lines_added, lines_removed) = (edit.lines_added, edit.lines_removed);
+            let total_lines = edit.content.lines().count();
 
-            self.stage_file_with_content(&req.path, modified_content)?;
+            self.stage_line_edit(&req.path, edit, lossy)?;
             self.index_manager.update_line_stats(
                 &req.path,
                 lines_added as isize,
@@ -375,13 +1720,16 @@ impl Orchestrator {
                 lines_added: -(lines_removed as isize),
                 total_lines,
                 original_lines,
+                lossy,
             })
         })
     }
 
     pub fn handle_insert_lines(&self, req: InsertLinesRequest) -> Result<ReplaceLinesResponse> {
-        self.index_manager.with_snapshot(|| {
-            let content = self.get_file_content(&req.path, SearchSpace::Staged)?;
+        let label = format!("insert lines in {}", req.path.as_str());
+        self.index_manager.with_undo_step(&label, || {
+            self.check_precondition(&req.path, req.precondition.as_ref())?;
+            let (content, lossy) = self.get_editable_content(&req.path)?;
             let original_lines = content.lines().count();
 
             let operations: Vec<LineOperation> = req
@@ -399,11 +1747,11 @@ impl Orchestrator {
                 })
                 .collect();
 
-            let (modified_content, lines_added, lines_removed) =
-                apply_line_operations(&content, operations);
-            let total_lines = modified_content.lines().count();
+            let edit = apply_line_edit(&content, operations, &self.edit_policy);
+            let (lines_added, lines_removed) = (edit.lines_added, edit.lines_removed);
+            let total_lines = edit.content.lines().count();
 
-            self.stage_file_with_content(&req.path, modified_content)?;
+            self.stage_line_edit(&req.path, edit, lossy)?;
             self.index_manager.update_line_stats(
                 &req.path,
                 lines_added as isize,
@@ -418,89 +1766,102 @@ impl Orchestrator {
                 lines_added: lines_added as isize,
                 total_lines,
                 original_lines,
+                lossy,
             })
         })
     }
-}
-
-impl FindTool for Orchestrator {
-    fn run_find(&mut self, req: FindRequest, abort: &AbortFlag) -> Result<FindResponse> {
-        self.handle_find(req, abort)
-    }
-}
-
-impl EditTool for Orchestrator {
-    fn run_edit(&mut self, req: EditRequest, abort: &AbortFlag) -> Result<EditResponse> {
-        self.handle_edit(req, abort)
-    }
-}
-
-impl ReadTool for Orchestrator {
-    fn run_read(
-        &mut self,
-        path: &PathKey,
-        start_line: usize,
-        end_line: usize,
-        where_: SearchSpace,
-    ) -> Result<ReadResponse> {
-        self.handle_read(path, start_line, end_line, where_)
-    }
-}
-
-impl CreateTool for Orchestrator {
-    fn run_create(&mut self, req: CreateRequest) -> Result<CreateResponse> {
-        self.handle_create(req)
-    }
-}
 
-impl DeleteTool for Orchestrator {
-    fn run_delete(&mut self, req: DeleteRequest) -> Result<DeleteResponse> {
-        self.handle_delete(req)
-    }
-}
-
-impl ReplaceLinesTool for Orchestrator {
-    fn run_replace_lines(&mut self, req: ReplaceLinesRequest) -> Result<ReplaceLinesResponse> {
-        self.handle_replace_lines(req)
-    }
-}
-
-impl DeleteLinesTool for Orchestrator {
-    fn run_delete_lines(&mut self, req: DeleteLinesRequest) -> Result<ReplaceLinesResponse> {
-        self.handle_delete_lines(req)
-    }
-}
+    /// Add `req.content` as lines at the start or end of a file, creating
+    /// it unless `req.must_exist` is set.
+    pub fn handle_append(&self, req: AppendRequest) -> Result<ReplaceLinesResponse> {
+        let label = match req.position {
+            AppendPosition::Start => format!("prepend to {}", req.path.as_str()),
+            AppendPosition::End => format!("append to {}", req.path.as_str()),
+        };
+        self.index_manager.with_undo_step(&label, || {
+            self.check_precondition(&req.path, req.precondition.as_ref())?;
+            let exists = self
+                .index_manager
+                .staged_index()?
+                .get_file(&req.path)
+                .is_some();
+            let (content, lossy) = if exists {
+                self.get_editable_content(&req.path)?
+            } else if req.must_exist {
+                return Err(Error::FileNotFound(req.path.as_str().to_string()));
+            } else {
+                (String::new(), false)
+            };
+            let original_lines = content.lines().count();
 
-impl InsertLinesTool for Orchestrator {
-    fn run_insert_lines(&mut self, req: InsertLinesRequest) -> Result<ReplaceLinesResponse> {
-        self.handle_insert_lines(req)
-    }
-}
+            let line = match req.position {
+                AppendPosition::Start => 1,
+                AppendPosition::End => original_lines + 1,
+            };
+            let operation = LineOperation::InsertBefore {
+                line,
+                content: req.content,
+            };
+            let edit = apply_line_edit(&content, vec![operation], &self.edit_policy);
+            let lines_added = edit.lines_added;
+            let total_lines = edit.content.lines().count();
 
-impl MoveFilesTool for Orchestrator {
-    fn run_copy_files(&mut self, req: BatchCopyRequest) -> Result<BatchOperationResponse> {
-        self.handle_copy_files(req)
-    }
+            self.stage_line_edit(&req.path, edit, lossy)?;
+            self.index_manager.update_line_stats(
+                &req.path,
+                lines_added as isize,
+                0,
+                total_lines,
+            )?;
+            if exists {
+                self.index_manager.mark_needs_read(&req.path)?;
+            }
 
-    fn run_move_files(&mut self, req: BatchMoveRequest) -> Result<BatchOperationResponse> {
-        self.handle_move_files(req)
+            Ok(ReplaceLinesResponse {
+                path: req.path,
+                lines_replaced: 0,
+                lines_added: lines_added as isize,
+                total_lines,
+                original_lines,
+                lossy,
+            })
+        })
     }
-}
 
-impl DiffTool for Orchestrator {
-    fn get_modified_files_summary(&self) -> Result<Vec<ModifiedFileSummary>> {
+    /// Summarize staged changes, calling `on_progress(done, total)` before
+    /// each file and once at the end. `abort` is checked right after each
+    /// call, so a callback can stop the work; the summaries made so far are
+    /// then returned marked incomplete.
+    pub fn summarize_modified_files(
+        &self,
+        abort: &AbortFlag,
+        mut on_progress: impl FnMut(usize, usize),
+    ) -> Result<Partial<ModifiedFileSummary>> {
         let active_index = self.index_manager.active_index();
         let staged_index = self.index_manager.staged_index()?;
         let change_stats = self.index_manager.get_change_stats()?;
         let deletions = self.index_manager.get_staged_deletions()?;
         let moves = self.index_manager.get_staged_moves()?;
 
+        let total = moves.len() + change_stats.len() + deletions.len();
+        let mut done = 0;
         let mut summaries = Vec::new();
-        let deletion_set: std::collections::HashSet<_> = deletions.iter().cloned().collect();
-        let mut processed_moves = std::collections::HashSet::new();
+        let incomplete = |items| {
+            Ok(Partial {
+                items,
+                complete: false,
+            })
+        };
+        let deletion_set: HashSet<_> = deletions.iter().cloned().collect();
+        let mut processed_moves = HashSet::new();
 
         // Process moves first
         for (src, dst) in &moves {
+            on_progress(done, total);
+            if abort.is_aborted() {
+                return incomplete(summaries);
+            }
+            done += 1;
             if deletion_set.contains(src) && staged_index.get_file(dst).is_some() {
                 processed_moves.insert(src.clone());
                 processed_moves.insert(dst.clone());
@@ -526,18 +1887,26 @@ impl DiffTool for Orchestrator {
                     lines_removed,
                     status: FileChangeStatus::Moved,
                     moved_to: Some(dst.clone()),
+                    similarity: None,
                 });
             }
         }
 
         // Process other changes
         for (path, stats) in change_stats {
+            on_progress(done, total);
+            if abort.is_aborted() {
+                return incomplete(summaries);
+            }
+            done += 1;
             if deletion_set.contains(&path) || processed_moves.contains(&path) {
                 continue;
             }
 
             let status = if active_index.get_file(&path).is_none() {
                 FileChangeStatus::Created
+            } else if self.index_manager.was_recreated(&path)? {
+                FileChangeStatus::Recreated
             } else {
                 FileChangeStatus::Modified
             };
@@ -548,11 +1917,17 @@ impl DiffTool for Orchestrator {
                 lines_removed: stats.lines_removed.unsigned_abs(),
                 status,
                 moved_to: None,
+                similarity: None,
             });
         }
 
         // Process deletions (excluding moves)
         for path in deletions {
+            on_progress(done, total);
+            if abort.is_aborted() {
+                return incomplete(summaries);
+            }
+            done += 1;
             if processed_moves.contains(&path) {
                 continue;
             }
@@ -569,13 +1944,224 @@ impl DiffTool for Orchestrator {
                 lines_removed,
                 status: FileChangeStatus::Deleted,
                 moved_to: None,
+                similarity: None,
             });
         }
 
-        Ok(summaries)
+        if let Some(threshold) = self.rename_threshold {
+            summaries = self.pair_renames(summaries, threshold, &active_index, &staged_index);
+        }
+
+        on_progress(done, total);
+        Ok(Partial {
+            items: summaries,
+            complete: true,
+        })
     }
 
-    fn get_file_diff(&self, path: &PathKey) -> Result<FileDiff> {
+    /// Replace each deleted file and created file detected as a rename
+    /// with one `Renamed` summary, counting lines against the old content.
+    fn pair_renames(
+        &self,
+        summaries: Vec<ModifiedFileSummary>,
+        threshold: u8,
+        active_index: &Index,
+        staged_index: &Index,
+    ) -> Vec<ModifiedFileSummary> {
+        fn collect<'a>(
+            summaries: &[ModifiedFileSummary],
+            status: FileChangeStatus,
+            index: &'a Index,
+        ) -> Vec<(PathKey, &'a [u8])> {
+            summaries
+                .iter()
+                .filter(|s| s.status == status)
+                .filter_map(|s| {
+                    let content = index.get_file(&s.path)?.search_content()?;
+                    Some((s.path.clone(), content))
+                })
+                .collect()
+        }
+        let deleted = collect(&summaries, FileChangeStatus::Deleted, active_index);
+        let created = collect(&summaries, FileChangeStatus::Created, staged_index);
+        if deleted.is_empty() || created.is_empty() {
+            return summaries;
+        }
+        let renames = detect_renames(&deleted, &created, threshold);
+        if renames.is_empty() {
+            return summaries;
+        }
+
+        let text = |files: &[(PathKey, &[u8])], path: &PathKey| {
+            files
+                .iter()
+                .find(|(p, _)| p == path)
+                .map(|(_, c)| String::from_utf8_lossy(c).into_owned())
+                .unwrap_or_default()
+        };
+        let renamed: Vec<ModifiedFileSummary> = renames
+            .into_iter()
+            .map(|rename| {
+                let stats = compute_diff(
+                    rename.dst.clone(),
+                    &text(&deleted, &rename.src),
+                    &text(&created, &rename.dst),
+                    0,
+                )
+                .stats;
+                ModifiedFileSummary {
+                    path: rename.src,
+                    lines_added: stats.lines_added,
+                    lines_removed: stats.lines_removed,
+                    status: FileChangeStatus::Renamed,
+                    moved_to: Some(rename.dst),
+                    similarity: Some(rename.similarity),
+                }
+            })
+            .collect();
+        let paired: HashSet<&PathKey> = renamed
+            .iter()
+            .flat_map(|s| [&s.path, s.moved_to.as_ref().unwrap_or(&s.path)])
+            .collect();
+        summaries
+            .iter()
+            .filter(|s| {
+                !matches!(
+                    s.status,
+                    FileChangeStatus::Deleted | FileChangeStatus::Created
+                ) || !paired.contains(&s.path)
+            })
+            .cloned()
+            .chain(renamed.iter().cloned())
+            .collect()
+    }
+}
+
+impl FindTool for Orchestrator {
+    fn run_find(&mut self, req: FindRequest, abort: &AbortFlag) -> Result<FindResponse> {
+        self.handle_find(req, abort)
+    }
+}
+
+impl EditTool for Orchestrator {
+    fn run_edit(&mut self, req: EditRequest, abort: &AbortFlag) -> Result<EditResponse> {
+        self.handle_edit(req, abort)
+    }
+}
+
+impl ReadTool for Orchestrator {
+    fn run_read(
+        &mut self,
+        path: &PathKey,
+        start_line: usize,
+        end_line: usize,
+        where_: SearchSpace,
+    ) -> Result<ReadResponse> {
+        self.handle_read(path, start_line, end_line, where_)
+    }
+}
+
+impl CreateTool for Orchestrator {
+    fn run_create(&mut self, req: CreateRequest) -> Result<CreateResponse> {
+        self.handle_create(req)
+    }
+}
+
+impl DeleteTool for Orchestrator {
+    fn run_delete(&mut self, req: DeleteRequest) -> Result<DeleteResponse> {
+        self.handle_delete(req)
+    }
+
+    fn run_delete_dir(&mut self, req: DeleteDirRequest) -> Result<DeleteDirResponse> {
+        self.handle_delete_dir(req)
+    }
+}
+
+impl ReplaceLinesTool for Orchestrator {
+    fn run_replace_lines(&mut self, req: ReplaceLinesRequest) -> Result<ReplaceLinesResponse> {
+        self.handle_replace_lines(req)
+    }
+}
+
+impl AnchoredEditTool for Orchestrator {
+    fn run_anchored_edit(&mut self, req: AnchoredEditRequest) -> Result<AnchoredEditResponse> {
+        self.handle_anchored_edit(req)
+    }
+}
+
+impl ValidateLinesTool for Orchestrator {
+    fn run_validate_lines(&mut self, req: ValidateLinesRequest) -> Result<ValidateLinesResponse> {
+        self.handle_validate_lines(req)
+    }
+}
+
+impl DeleteLinesTool for Orchestrator {
+    fn run_delete_lines(&mut self, req: DeleteLinesRequest) -> Result<ReplaceLinesResponse> {
+        self.handle_delete_lines(req)
+    }
+}
+
+impl InsertLinesTool for Orchestrator {
+    fn run_insert_lines(&mut self, req: InsertLinesRequest) -> Result<ReplaceLinesResponse> {
+        self.handle_insert_lines(req)
+    }
+}
+
+impl AppendTool for Orchestrator {
+    fn run_append(&mut self, req: AppendRequest) -> Result<ReplaceLinesResponse> {
+        self.handle_append(req)
+    }
+}
+
+impl MoveFilesTool for Orchestrator {
+    fn run_copy_files(&mut self, req: BatchCopyRequest) -> Result<BatchOperationResponse> {
+        self.handle_copy_files(req)
+    }
+
+    fn run_move_tree(&mut self, req: MoveTreeRequest) -> Result<MoveTreeResponse> {
+        self.handle_move_tree(req)
+    }
+
+    fn run_move_files(&mut self, req: BatchMoveRequest) -> Result<BatchOperationResponse> {
+        self.handle_move_files(req)
+    }
+
+    fn run_move_dir(&mut self, req: MoveDirRequest) -> Result<DirOperationResponse> {
+        self.handle_move_dir(req)
+    }
+
+    fn run_copy_dir(&mut self, req: MoveDirRequest) -> Result<DirOperationResponse> {
+        self.handle_copy_dir(req)
+    }
+}
+
+impl PatchTool for Orchestrator {
+    fn run_apply_patch(&mut self, patch: &str) -> Result<PatchReport> {
+        self.handle_apply_patch(patch)
+    }
+}
+
+impl MergeTool for Orchestrator {
+    fn run_merge_file(&mut self, req: MergeFileRequest) -> Result<MergeFileResponse> {
+        self.handle_merge_file(req)
+    }
+}
+
+impl ScaffoldTool for Orchestrator {
+    fn run_apply_scaffold(&mut self, req: ApplyScaffoldRequest) -> Result<ApplyScaffoldResponse> {
+        self.handle_apply_scaffold(req)
+    }
+}
+
+impl DiffTool for Orchestrator {
+    fn get_modified_files_summary(
+        &self,
+        abort: &AbortFlag,
+    ) -> Result<Partial<ModifiedFileSummary>> {
+        self.summarize_modified_files(abort, |_, _| {})
+    }
+
+    fn get_file_diff(&self, path: &PathKey, context_lines: usize) -> Result<FileDiff> {
         let active_index = self.index_manager.active_index();
         let staged_index = self.index_manager.staged_index()?;
 
@@ -596,19 +2182,467 @@ impl DiffTool for Orchestrator {
             None => String::new(),
         };
 
-        Ok(compute_diff(path.clone(), &active_content, &staged_content))
+        Ok(compute_diff(
+            path.clone(),
+            &active_content,
+            &staged_content,
+            context_lines,
+        ))
+    }
+
+    fn get_unified_diff(&self, path: &PathKey) -> Result<String> {
+        let active_index = self.index_manager.active_index();
+        let staged_index = self.index_manager.staged_index()?;
+        let moves = self.index_manager.get_staged_moves()?;
+
+        let rename = moves.iter().find(|(src, dst)| {
+            (*src == path || *dst == path)
+                && staged_index.get_file(src).is_none()
+                && staged_index.get_file(dst).is_some()
+        });
+        let (old, new) = match rename {
+            Some((src, dst)) => (src, dst),
+            None => (path, path),
+        };
+        if active_index.get_file(old).is_none() && staged_index.get_file(new).is_none() {
+            return Err(Error::FileNotFound(path.as_str().to_string()));
+        }
+        Ok(render_patch(&active_index, &staged_index, old, new))
+    }
+
+    fn get_all_unified_diffs(&self) -> Result<String> {
+        let active_index = self.index_manager.active_index();
+        let staged_index = self.index_manager.staged_index()?;
+        let mut summaries = self
+            .summarize_modified_files(&AbortFlag::new(), |_, _| {})?
+            .items;
+        summaries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut patch = String::new();
+        for summary in &summaries {
+            let new = summary.moved_to.as_ref().unwrap_or(&summary.path);
+            patch.push_str(&render_patch(
+                &active_index,
+                &staged_index,
+                &summary.path,
+                new,
+            ));
+        }
+        Ok(patch)
     }
 }
 
-fn compile_globs(patterns: Option<&[String]>) -> Result<Option<GlobSet>> {
-    patterns
-        .filter(|p| !p.is_empty())
-        .map(|patterns| {
-            let mut builder = GlobSetBuilder::new();
-            for pattern in patterns {
-                builder.add(Glob::new(pattern)?);
-            }
-            builder.build().map_err(Into::into)
+/// Patch from `old` in the active index to `new` in the staged index; a side
+/// missing from its index makes the patch a creation or deletion.
+/// Content of `entry`, failing with [`Error::NotHydrated`] while it is
+/// loaded as metadata only.
+fn loaded_content<'a>(path: &PathKey, entry: &'a FileEntry) -> Result<&'a [u8]> {
+    entry
+        .search_content()
+        .ok_or_else(|| Error::NotHydrated(path.as_str().to_string()))
+}
+
+fn render_patch(active: &Index, staged: &Index, old: &PathKey, new: &PathKey) -> String {
+    let content = |index: &Index, path: &PathKey| {
+        index
+            .get_file(path)
+            .map(|entry| entry.search_content().unwrap_or_default().to_vec())
+    };
+    let (old_content, new_content) = (content(active, old), content(staged, new));
+    FilePatch {
+        old_path: old_content.as_ref().map(|_| old.as_str()),
+        new_path: new_content.as_ref().map(|_| new.as_str()),
+        old: old_content.as_deref().unwrap_or_default(),
+        new: new_content.as_deref().unwrap_or_default(),
+    }
+    .render(DEFAULT_CONTEXT_LINES)
+}
+
+/// A single match handed to [`Orchestrator::scan_matches`] callbacks.
+/// The registered matcher named `pattern_ref`, or `find` compiled with `opts`.
+pub(crate) fn resolve_matcher(
+    find: &str,
+    pattern_ref: Option<&str>,
+    opts: &RegexEngineOpts,
+) -> Result<Arc<RegexMatcher>> {
+    match pattern_ref {
+        Some(name) => with_patterns(|patterns| patterns.matcher(name)),
+        None => Ok(Arc::new(RegexMatcher::compile(find, opts)?)),
+    }
+}
+
+/// One operation per staged file under `from`, mapping it to the same
+/// relative path under `to` or wherever `on_conflict` places it, with the
+/// destinations that were taken. Fails if either directory contains the
+/// other, `from` holds no files, or a destination exists under
+/// [`ConflictPolicy::Error`].
+fn dir_operations(
+    staged: &Index,
+    from: &str,
+    to: &str,
+    on_conflict: ConflictPolicy,
+) -> Result<(Vec<FileOperation>, Vec<DestinationConflict>)> {
+    let (from, to) = (from.trim_end_matches('/'), to.trim_end_matches('/'));
+    if from.is_empty() || to.is_empty() {
+        return Err(Error::InvalidPath(format!("{from} -> {to}")));
+    }
+    let nested = |outer: &str, inner: &str| {
+        inner
+            .strip_prefix(outer)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+    };
+    if nested(from, to) || nested(to, from) {
+        return Err(Error::InvalidPath(format!(
+            "cannot move '{}' to '{}': one contains the other",
+            from, to
+        )));
+    }
+
+    let sources = staged.paths_under(from);
+    if sources.is_empty() {
+        return Err(Error::FileNotFound(from.to_string()));
+    }
+    // Renamed files must also miss the names earlier files were given.
+    let mut placed = HashSet::new();
+    let mut operations = Vec::with_capacity(sources.len());
+    let mut conflicts = Vec::new();
+    for src in sources {
+        let dst = create_path_key(&format!("{to}{}", &src.as_str()[from.len()..]))?;
+        let (dst, conflict) = on_conflict.resolve_with(
+            |path| placed.contains(path) || staged.get_file(path).is_some(),
+            &src,
+            &dst,
+        )?;
+        placed.insert(dst.clone());
+        conflicts.extend(conflict);
+        operations.push(FileOperation { src, dst });
+    }
+    Ok((operations, conflicts))
+}
+
+/// New content for one file, planned by a find-and-replace.
+struct PlannedRewrite {
+    path: PathKey,
+    content: Vec<u8>,
+    lines_removed: usize,
+    lines_added: usize,
+}
+
+/// Where a paginated search resumes: the candidate file's ordinal, how
+/// many of its matches were already returned, and how many matches were
+/// returned in total.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct SearchPosition {
+    file: usize,
+    skip: usize,
+    emitted: usize,
+}
+
+/// What a request's `max_matches_per_file` and `max_total_matches` cut off.
+#[derive(Debug, Clone, Default)]
+pub struct LimitReport {
+    /// Files with matches past `max_matches_per_file`, in scan order.
+    pub truncated_paths: Vec<PathKey>,
+    /// The scan stopped at `max_total_matches` with matches left.
+    pub limit_reached: bool,
+    /// The scan was aborted or timed out with files left.
+    pub aborted: bool,
+    /// Candidates skipped because they are loaded as metadata only.
+    pub unhydrated: usize,
+}
+
+impl LimitReport {
+    fn apply(self, mut response: FindResponse) -> FindResponse {
+        response.truncated_paths = self.truncated_paths;
+        response.stats.match_limit_reached = self.limit_reached;
+        response.stats.aborted = self.aborted;
+        response.stats.skipped_unhydrated = self.unhydrated;
+        response
+    }
+}
+
+/// Results a result limit left out of a response, counted so its totals
+/// still describe the whole search.
+#[derive(Default)]
+struct DroppedResults {
+    matches: usize,
+    paths: HashSet<PathKey>,
+}
+
+impl DroppedResults {
+    fn add(&mut self, path: &PathKey, matches: usize) {
+        self.matches += matches;
+        if !self.paths.contains(path) {
+            self.paths.insert(path.clone());
+        }
+    }
+
+    fn apply(self, mut response: FindResponse) -> FindResponse {
+        let kept: HashSet<&PathKey> = response
+            .results
+            .iter()
+            .map(|h| &h.path)
+            .chain(response.hits.iter().map(|h| &h.path))
+            .collect();
+        let new_files = self.paths.iter().filter(|p| !kept.contains(p)).count();
+        response.stats.total_matches += self.matches;
+        response.stats.files_with_matches += new_files;
+        response.stats.files_truncated = self.paths.len();
+        response
+    }
+}
+
+/// State of a paginated search, see [`Orchestrator::open_search`].
+///
+/// The cursor pins the index snapshot it was opened on, so pages stay
+/// consistent while staging changes; they do not see those changes.
+pub struct SearchCursor {
+    request: FindRequest,
+    index: Arc<Index>,
+    /// `None` once every candidate has been scanned.
+    position: Option<SearchPosition>,
+}
+
+impl SearchCursor {
+    pub fn request(&self) -> &FindRequest {
+        &self.request
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.position.is_none()
+    }
+}
+
+struct MatchSite<'a> {
+    path: &'a PathKey,
+    content: &'a [u8],
+    line_index: &'a LineIndex,
+    matcher: &'a RegexMatcher,
+    span: ByteSpan,
+    /// 1-based first line of the grep region containing the match
+    region_line: usize,
+    /// Pattern that matched, for multi-pattern requests.
+    pattern_index: Option<usize>,
+}
+
+/// The matcher for a find request: a single pattern, or several searched
+/// as one alternation.
+enum FindMatcher {
+    Single(Arc<RegexMatcher>),
+    Multi(MultiMatcher),
+}
+
+impl FindMatcher {
+    /// A registered `pattern_ref` wins over `patterns`, which win over `find`.
+    fn resolve(req: &FindRequest) -> Result<Self> {
+        if req.pattern_ref.is_none() && !req.patterns.is_empty() {
+            return Ok(FindMatcher::Multi(MultiMatcher::compile(
+                &req.patterns,
+                &req.engine_opts,
+            )?));
+        }
+        resolve_matcher(&req.find, req.pattern_ref.as_deref(), &req.engine_opts)
+            .map(FindMatcher::Single)
+    }
+
+    fn matcher(&self) -> &RegexMatcher {
+        match self {
+            FindMatcher::Single(matcher) => matcher,
+            FindMatcher::Multi(multi) => multi.matcher(),
+        }
+    }
+
+    fn pattern_index(&self, content: &[u8], span: &ByteSpan) -> Result<Option<usize>> {
+        match self {
+            FindMatcher::Single(_) => Ok(None),
+            FindMatcher::Multi(multi) => multi.which(content, span),
+        }
+    }
+}
+
+/// Files in `index` (or in `scope`, when set) that pass the request's
+/// prefix, glob and mtime filters, in the requested search order.
+///
+/// `generation` is the index manager's, read after `index` was taken; it
+/// lets the ignore rules of an unchanged index be reused.
+fn select_candidates<'a>(
+    index: &'a Index,
+    generation: u64,
+    req: &'a FindRequest,
+    scope: Option<&'a [PathKey]>,
+) -> Result<Vec<(&'a PathKey, &'a FileEntry)>> {
+    // The request's own path list bypasses the path filters; a scope on the
+    // orchestrator still narrows it.
+    let listed = req.paths.as_deref().map(|paths| {
+        let mut paths: Vec<&PathKey> = paths
+            .iter()
+            .filter(|path| scope.is_none_or(|scope| scope.binary_search(path).is_ok()))
+            .collect();
+        paths.sort();
+        paths.dedup();
+        paths
+    });
+    let (include_globs, exclude_globs) = match listed {
+        Some(_) => (None, None),
+        None => (
+            compile_globs(req.include_globs.as_deref(), &req.glob_options)?,
+            compile_globs(req.exclude_globs.as_deref(), &req.glob_options)?,
+        ),
+    };
+    let prefix = req.prefix.as_ref().filter(|_| listed.is_none());
+
+    let named = scope.is_some() || listed.is_some();
+    let files: Box<dyn Iterator<Item = (&'a PathKey, &'a FileEntry)>> = match (listed, scope) {
+        (Some(paths), _) => Box::new(
+            paths
+                .into_iter()
+                .filter_map(|path| index.get_file(path).map(|entry| (path, entry))),
+        ),
+        (None, Some(paths)) => Box::new(
+            paths
+                .iter()
+                .filter_map(|path| index.get_file(path).map(|entry| (path, entry))),
+        ),
+        (None, None) => Box::new(index.iter_sorted()),
+    };
+
+    // An explicit scope names its files, so hidden, junk and ignored ones
+    // are kept.
+    let visibility = Visibility {
+        include_hidden: req.include_hidden || named,
+        include_junk: req.include_junk || named,
+    };
+    let ignore = if req.include_ignored || named {
+        None
+    } else {
+        host_defaults().ignore_set(index, generation)?
+    };
+
+    let mut candidates: Vec<_> = files
+        .filter(|(path, entry)| entry.is_visible(path, visibility))
+        .filter(|(path, _)| {
+            ignore
+                .as_ref()
+                .is_none_or(|ignore| !ignore.is_ignored(path.as_str()))
+        })
+        .filter(|(path, _)| prefix.is_none_or(|prefix| path.as_str().starts_with(prefix)))
+        .filter(|(path, _)| {
+            include_globs
+                .as_ref()
+                .is_none_or(|globs| globs.is_match(path.as_str()))
+        })
+        .filter(|(path, _)| {
+            exclude_globs
+                .as_ref()
+                .is_none_or(|globs| !globs.is_match(path.as_str()))
         })
-        .transpose()
+        .filter(|(_, entry)| entry.modified_within(req.modified_after, req.modified_before))
+        .collect();
+
+    if req.order == SearchOrder::SmallestFirst {
+        // Stable sort keeps path order among equally sized files.
+        candidates.sort_by_key(|(_, entry)| entry.size());
+    }
+
+    Ok(candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An orchestrator over its own index manager, holding `files` as the
+    /// active index.
+    fn orchestrator_with(files: &[(&str, &str)]) -> Orchestrator {
+        let manager: &'static IndexManager = Box::leak(Box::default());
+        let files = files
+            .iter()
+            .map(|(path, content)| {
+                let key = create_path_key(path).unwrap();
+                let entry =
+                    FileEntry::from_bytes_and_path(&key, 1, Arc::from(content.as_bytes()), true);
+                (key, entry)
+            })
+            .collect();
+        manager.load_files(files).unwrap();
+        Orchestrator {
+            index_manager: manager,
+            ..Orchestrator::new()
+        }
+    }
+
+    fn find(orchestrator: &Orchestrator, minimal: bool, limit: usize) -> FindResponse {
+        let req = FindRequest {
+            find: "x".into(),
+            where_: SearchSpace::Active,
+            delta: 0,
+            minimal,
+            ..FindRequest::default()
+        };
+        orchestrator
+            .find_up_to(req, Some(limit), &AbortFlag::new())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_minimal_limit_keeps_totals() {
+        let orchestrator =
+            orchestrator_with(&[("a.txt", "x x x\n"), ("b.txt", "x\n"), ("c.txt", "x\n")]);
+        let counts = |r: &FindResponse| {
+            let s = r.stats;
+            (
+                r.hits.len(),
+                s.total_matches,
+                s.files_with_matches,
+                s.files_truncated,
+            )
+        };
+
+        assert_eq!(counts(&find(&orchestrator, true, 5)), (5, 5, 3, 0));
+        assert_eq!(counts(&find(&orchestrator, true, 4)), (4, 5, 3, 1));
+        assert_eq!(counts(&find(&orchestrator, true, 3)), (3, 5, 3, 2));
+        assert!(!find(&orchestrator, true, 3).stats.match_limit_reached);
+    }
+
+    #[test]
+    fn test_hunk_limit_keeps_totals() {
+        let orchestrator =
+            orchestrator_with(&[("a.txt", "x\n"), ("b.txt", "x\n"), ("c.txt", "x\n")]);
+        let counts = |r: &FindResponse| {
+            let s = r.stats;
+            (
+                r.results.len(),
+                s.total_matches,
+                s.files_with_matches,
+                s.files_truncated,
+            )
+        };
+
+        assert_eq!(counts(&find(&orchestrator, false, 3)), (3, 3, 3, 0));
+        assert_eq!(counts(&find(&orchestrator, false, 2)), (2, 3, 3, 1));
+        let cut = find(&orchestrator, false, 1);
+        assert_eq!(counts(&cut), (1, 3, 3, 2));
+        assert_eq!(cut.results[0].path.as_str(), "a.txt");
+    }
+
+    #[test]
+    fn test_ignore_rules_follow_ignore_file_edits() {
+        let orchestrator =
+            orchestrator_with(&[(".gitignore", "*.log\n"), ("a.log", "x\n"), ("b.md", "x\n")]);
+        let paths = |r: FindResponse| -> Vec<String> {
+            r.hits.iter().map(|h| h.path.as_str().to_string()).collect()
+        };
+
+        assert_eq!(paths(find(&orchestrator, true, 10)), ["b.md"]);
+        // Unchanged index: the cached rules are reused.
+        assert_eq!(paths(find(&orchestrator, true, 10)), ["b.md"]);
+
+        let manager = orchestrator.index_manager;
+        let key = create_path_key(".gitignore").unwrap();
+        let entry = FileEntry::from_bytes_and_path(&key, 2, Arc::from(&b"*.md\n"[..]), true);
+        manager.begin_staging().unwrap();
+        manager.stage_file(key, entry).unwrap();
+        manager.promote_staged().unwrap();
+        assert_eq!(paths(find(&orchestrator, true, 10)), ["a.log"]);
+    }
 }
@@ -1,7 +1,12 @@
 //! Utility functions for WASM bindings to reduce boilerplate.
 
-use conduit_core::{FileOperation, ReplaceLinesResponse};
-use js_sys::{Array, Object};
+use conduit_core::error::Error;
+use conduit_core::fs::parse_path_list;
+use conduit_core::tools::CompressionPolicy;
+use conduit_core::{FileOperation, PathKey, ReplaceLinesResponse};
+use js_sys::{Array, Object, Uint8Array};
+use schemars::JsonSchema;
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
 
 #[macro_export]
@@ -14,6 +19,58 @@ macro_rules! js_err {
     };
 }
 
+/// `e` as a JS `Error` with message `"{context}: {e}"` and a `code` property.
+///
+/// Memory pressure errors also carry `usage`, `requested` and `ceiling` in
+/// bytes, so hosts can back off without parsing the message.
+pub fn core_err(context: impl std::fmt::Display, e: &Error) -> JsValue {
+    let err = js_sys::Error::new(&format!("{context}: {e}"));
+    let set = |key: &str, value: JsValue| {
+        let _ = js_sys::Reflect::set(&err, &JsValue::from_str(key), &value);
+    };
+    set("code", JsValue::from_str(e.code()));
+    if let Error::MemoryPressure {
+        usage,
+        requested,
+        ceiling,
+    } = e
+    {
+        set("usage", JsValue::from(*usage as f64));
+        set("requested", JsValue::from(*requested as f64));
+        set("ceiling", JsValue::from(*ceiling as f64));
+    }
+    if let Error::EditConflict { hash, mtime, .. } = e {
+        set("currentHash", JsValue::from_str(hash));
+        set("currentMtime", JsValue::from_f64(*mtime as f64 * 1000.0));
+    }
+    if let Error::AmbiguousAnchor { count, .. } = e {
+        set("count", JsValue::from(*count as u32));
+    }
+    if let Error::OverlappingEdits { conflicts, .. } = e {
+        let pairs = js_sys::Array::new();
+        for &(a, b) in conflicts {
+            pairs.push(&js_sys::Array::of2(
+                &JsValue::from(a as u32),
+                &JsValue::from(b as u32),
+            ));
+        }
+        set("conflicts", pairs.into());
+    }
+    if let Error::TransactionFailed { index, op, source } = e {
+        set("index", JsValue::from(*index as u32));
+        set("op", JsValue::from_str(op));
+        set("cause", JsValue::from_str(source.code()));
+    }
+    err.into()
+}
+
+/// `value` as a plain JS object, with `None` fields as `null`.
+pub fn to_js<T: Serialize + ?Sized>(value: &T) -> Result<JsValue, JsValue> {
+    value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| js_err!("Failed to serialize result: {}", e))
+}
+
 /// Extract a string field from a JavaScript object.
 pub fn get_string_field(obj: &Object, field: &str) -> Result<String, JsValue> {
     js_sys::Reflect::get(obj, &JsValue::from_str(field))?
@@ -21,15 +78,32 @@ pub fn get_string_field(obj: &Object, field: &str) -> Result<String, JsValue> {
         .ok_or_else(|| js_err!("Missing or invalid '{}' field", field))
 }
 
-/// Extract a usize field from a JavaScript object.
-pub fn get_usize_field(obj: &Object, field: &str) -> Result<usize, JsValue> {
-    let value = js_sys::Reflect::get(obj, &JsValue::from_str(field))?
-        .as_f64()
-        .ok_or_else(|| js_err!("Missing or invalid '{}' field", field))?;
-    if value < 0.0 {
-        return Err(js_err!("Field '{}' must be non-negative", field));
-    }
-    Ok(value as usize)
+/// Convert a JavaScript millisecond timestamp to unix epoch seconds.
+pub fn millis_to_unix_seconds(ms: f64) -> i64 {
+    (ms / 1000.0).floor() as i64
+}
+
+/// `bytes` as a `Uint8Array` under `policy`, plus its encoding
+/// (`"gzip"`, `"deflate"`, or `null` when left uncompressed).
+pub fn pack_bytes(policy: &CompressionPolicy, bytes: &[u8]) -> Result<(JsValue, JsValue), JsValue> {
+    let packed = policy
+        .pack(bytes)
+        .map_err(|e| js_err!("Failed to compress payload: {}", e))?;
+    let encoding = packed
+        .encoding
+        .map_or(JsValue::NULL, |e| JsValue::from_str(e.as_str()));
+    Ok((Uint8Array::from(&packed.bytes[..]).into(), encoding))
+}
+
+/// Parse a newline-delimited path list into interned path keys.
+pub fn path_keys_from_list(list: &Uint8Array) -> Result<Vec<PathKey>, JsValue> {
+    use crate::globals::intern_path;
+
+    let paths = parse_path_list(&list.to_vec()).map_err(|e| js_err!("Invalid path list: {}", e))?;
+    Ok(paths
+        .iter()
+        .map(|p| PathKey::from_arc(intern_path(p)))
+        .collect())
 }
 
 /// Parse an array of file operations from JavaScript.
@@ -63,41 +137,33 @@ pub fn parse_file_operations(array: &Array) -> Result<Vec<FileOperation>, JsValu
     Ok(operations)
 }
 
-/// Build a standard response for line operations.
-pub fn build_line_operation_response(response: &ReplaceLinesResponse) -> Result<JsValue, JsValue> {
-    let obj = JsObjectBuilder::new()
-        .set("path", JsValue::from_str(response.path.as_str()))?
-        .set(
-            "linesReplaced",
-            JsValue::from(response.lines_replaced as u32),
-        )?
-        .set("linesAdded", JsValue::from(response.lines_added as i32))?
-        .set("totalLines", JsValue::from(response.total_lines as u32))?
-        .set(
-            "originalLines",
-            JsValue::from(response.original_lines as u32),
-        )?
-        .build();
-    Ok(obj)
-}
-
-/// Helper for building JavaScript objects.
-pub struct JsObjectBuilder {
-    obj: Object,
+/// Result of a line edit, as returned by the line bindings.
+#[derive(Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LineEditResult {
+    pub path: PathKey,
+    pub lines_replaced: usize,
+    /// Net change in line count; negative when lines were removed.
+    pub lines_added: isize,
+    pub total_lines: usize,
+    pub original_lines: usize,
+    pub lossy: bool,
 }
 
-impl JsObjectBuilder {
-    pub fn new() -> Self {
-        Self { obj: Object::new() }
-    }
-
-    pub fn set(self, key: &str, value: JsValue) -> Result<Self, JsValue> {
-        js_sys::Reflect::set(&self.obj, &JsValue::from_str(key), &value)
-            .map_err(|e| js_err!("Failed to set property '{}': {:?}", key, e))?;
-        Ok(self)
+impl From<&ReplaceLinesResponse> for LineEditResult {
+    fn from(response: &ReplaceLinesResponse) -> Self {
+        Self {
+            path: response.path.clone(),
+            lines_replaced: response.lines_replaced,
+            lines_added: response.lines_added,
+            total_lines: response.total_lines,
+            original_lines: response.original_lines,
+            lossy: response.lossy,
+        }
     }
+}
 
-    pub fn build(self) -> JsValue {
-        self.obj.into()
-    }
+/// Build a standard response for line operations.
+pub fn build_line_operation_response(response: &ReplaceLinesResponse) -> Result<JsValue, JsValue> {
+    to_js(&LineEditResult::from(response))
 }
@@ -24,8 +24,9 @@ export function begin_file_load(): void;
  * @param contents - Array of Uint8Arrays with file contents
  * @param mtimes - Last modified timestamps (JavaScript milliseconds since epoch)
  * @param permissions - Array of booleans indicating if each file is editable
- * @returns Number of files loaded in this batch
- * @throws {Error} If array lengths don't match or paths are invalid
+ * @returns Number of files staged from this batch; skipped files are listed by `get_load_progress`
+ * @throws {Error} If array lengths don't match
+ * @throws {MemoryPressureError} If the batch would exceed `memoryCeiling`; nothing from it is staged
  */
 export function load_file_batch(
   paths: string[],
@@ -42,8 +43,9 @@ export function load_file_batch(
  * @param text_contents - Array of extracted text contents (Uint8Arrays or null for non-documents)
  * @param mtimes - Last modified timestamps (JavaScript milliseconds since epoch)
  * @param permissions - Array of booleans indicating if each file is editable
- * @returns Number of files loaded in this batch
- * @throws {Error} If array lengths don't match or paths are invalid
+ * @returns Number of files staged from this batch; skipped files are listed by `get_load_progress`
+ * @throws {Error} If array lengths don't match
+ * @throws {MemoryPressureError} If the batch would exceed `memoryCeiling`; nothing from it is staged
  */
 export function load_file_batch_with_text(
   paths: string[],
@@ -53,6 +55,200 @@ export function load_file_batch_with_text(
   text_contents?: string[],
 ): number;
 
+/**
+ * Stage files without content, as one batch of a load. Searches skip them,
+ * counting them in `stats.skippedUnhydrated`, until `hydrate_files` or a
+ * read fetches their content from the provider registered with
+ * `register_content_provider`.
+ * @param sizes - File sizes in bytes
+ * @param mtimes - Last modified timestamps (JavaScript milliseconds since epoch)
+ * @returns The same report as `add_files_to_staging`
+ * @throws {Error} If array lengths don't match
+ */
+export function load_file_metadata_batch(
+  paths: string[],
+  sizes: number[],
+  mtimes: number[],
+  permissions: boolean[],
+): ReturnType<typeof add_files_to_staging>;
+
+/**
+ * Register the function that fetches a metadata-only file's content. It
+ * receives the normalized path and returns or resolves to the content, or
+ * `null` if the file is gone. A provider that returns content synchronously
+ * also serves reads of unhydrated files on demand.
+ */
+export function register_content_provider(
+  provider: (path: string) => Uint8Array | string | null | Promise<Uint8Array | string | null>,
+): void;
+
+export interface HydrationUsage {
+  files: number;
+  bytes: number;
+  /** `hydrationBudget`, default 268435456 (256 MiB) */
+  budget: number;
+}
+
+/**
+ * Fetch content for the given paths that are indexed without it, all at
+ * once. Paths with content count as just used; unknown paths are ignored.
+ * Past `hydrationBudget`, the least recently used hydrated files go back
+ * to metadata only. Content loaded up front or written by edits is never
+ * evicted, and files hydrated by one call are all kept until it returns.
+ * A read that fails with `not_hydrated` can hydrate and retry.
+ * @throws {MemoryPressureError} If a file would exceed `memoryCeiling`
+ */
+export function hydrate_files(paths: string[]): Promise<{
+  hydrated: string[];
+  /** Paths the provider returned `null` for */
+  unavailable: string[];
+  usage: HydrationUsage;
+}>;
+
+export function get_hydration_usage(): HydrationUsage;
+
+export type SkipReason = "invalidPath" | "invalidTimestamp" | "duplicate" | "tooLarge";
+
+export interface SkippedFile {
+  /** Path as provided (not normalized) */
+  path: string;
+  reason: SkipReason;
+  message: string;
+}
+
+/**
+ * Stage a batch of files, skipping invalid or oversized entries instead of
+ * failing the whole batch. Repeated paths follow `duplicatePolicy`; with
+ * "error" the batch throws and nothing from it is staged.
+ * @returns Per-batch report; `totalBytesStaged` covers the whole load so far
+ * @throws {Error} If array lengths don't match
+ */
+export function add_files_to_staging(
+  paths: string[],
+  contents: Uint8Array[],
+  mtimes: number[],
+  permissions: boolean[],
+  text_contents?: string[] | null,
+): {
+  accepted: number;
+  skipped: SkippedFile[];
+  /** Paths already loaded earlier, whatever the policy decided */
+  duplicates: string[];
+  bytesStaged: number;
+  totalBytesStaged: number;
+};
+
+/**
+ * Unpack a zip, tar or `.tar.gz` archive and stage its files as one batch
+ * of a load (between `begin_file_load` and `commit_file_load`). Paths and
+ * mtimes come from the archive; directories and links are skipped.
+ * Files filtered out by the globs are never decompressed.
+ * @returns Report as from `add_files_to_staging`
+ * @throws {Error} With `code: "archive"` if the archive is corrupt, unsupported
+ *   or unpacks to more than 1 GiB
+ * @throws {Error} With `code: "memory_pressure"` if the kept files would
+ *   exceed the memory ceiling
+ */
+export function load_archive(
+  bytes: Uint8Array,
+  options?: {
+    /** Only load paths matching one of these globs */
+    includeGlobs?: string[] | null;
+    /** Skip paths matching one of these globs */
+    excludeGlobs?: string[] | null;
+    /** How the globs are matched */
+    globOptions?: GlobOptions | null;
+    /** Whether loaded files may be edited (default true) */
+    editable?: boolean | null;
+  } | null,
+): ReturnType<typeof add_files_to_staging>;
+
+/**
+ * Totals for the current or most recent load, for progress bars and for
+ * surfacing skipped files.
+ */
+export function get_load_progress(): {
+  inProgress: boolean;
+  batches: number;
+  filesAccepted: number;
+  bytesStaged: number;
+  skipped: SkippedFile[];
+  duplicates: string[];
+};
+
+/**
+ * Thrown by loads and writes that would exceed `memoryCeiling`. Sizes are
+ * in bytes; `requested` is how much the operation would add.
+ */
+export interface MemoryPressureError extends Error {
+  code: 'memory_pressure';
+  usage: number;
+  requested: number;
+  ceiling: number;
+}
+
+/**
+ * Content bytes held in memory. `stagedBytes` counts only staged content
+ * not shared with the active index; moves and untouched files are free.
+ */
+export function get_memory_usage(): {
+  activeBytes: number;
+  stagedBytes: number;
+  totalBytes: number;
+  ceiling: number | null;
+};
+
+/**
+ * Estimated memory by kind. Line index and path sizes count payload only.
+ */
+export function get_memory_report(): {
+  /** Same as `get_memory_usage().totalBytes`. */
+  contentBytes: number;
+  /** Part of `contentBytes` held as search text next to the file bytes. */
+  textBytes: number;
+  /** Part of `textBytes` identical to the file bytes; `compact_index` frees it. */
+  duplicateTextBytes: number;
+  lineIndexEntries: number;
+  lineIndexBytes: number;
+  /** Cached line indexes for content no longer indexed. */
+  staleLineIndexEntries: number;
+  /** Distinct paths across the active and staged indexes. */
+  paths: number;
+  pathBytes: number;
+};
+
+/**
+ * Drop data that can be derived again: search text identical to the file
+ * bytes and stale cached line indexes. Searches and reads are unaffected.
+ * Copies held by undo history are freed when the history is cleared.
+ */
+export function compact_index(): {
+  textBytesFreed: number;
+  lineIndexesDropped: number;
+};
+
+/**
+ * Undo the last staged operation (create, overwrite, delete, move, copy,
+ * line edit or replace). Batch operations undo as a whole.
+ * History is cleared when staging is committed, reverted or restarted.
+ * @returns The undone operation's label, or undefined if there was none
+ * @throws {Error} If no staging session is active
+ */
+export function undo_last_operation(): string | undefined;
+
+/**
+ * Reapply the last undone operation. Any new operation clears redo.
+ * @returns The redone operation's label, or undefined if there was none
+ * @throws {Error} If no staging session is active
+ */
+export function redo_operation(): string | undefined;
+
+/**
+ * Labels of the operations that can be undone and redone, most recent last.
+ * At most 100 undo steps are kept.
+ */
+export function get_undo_history(): { undo: string[]; redo: string[] };
+
 /**
  * Commit all staged files to the active index.
  * @returns The number of files committed
@@ -66,6 +262,198 @@ export function commit_file_load(): number;
  */
 export function abort_file_load(): void;
 
+/**
+ * Wire schema version of structured responses.
+ * Compare against the version your host was built for to detect breaking shape changes.
+ */
+export function schema_version(): number;
+
+/** Machine-readable description of one callable operation. */
+export interface ToolDescription {
+  name: string;
+  description: string;
+  /** Whether the operation can change files or staging state */
+  mutating: boolean;
+  /** Parameter names in call order */
+  args: string[];
+  /** JSON Schema object describing the parameters */
+  params: Record<string, unknown>;
+  /** JSON Schema of the return value; null when nothing is returned */
+  result: Record<string, unknown> | null;
+}
+
+/**
+ * Describe the operations agents can call, with parameter and result
+ * schemas derived from the types the bindings parse and return. Use it to
+ * generate tool manifests instead of maintaining them by hand. Every export
+ * is listed except those taking callbacks; trailing `SearchHandle`
+ * arguments are left out of `args`.
+ */
+export function describe_tools(): {
+  schemaVersion: number;
+  tools: ToolDescription[];
+};
+
+/**
+ * Request defaults applied when a call leaves the corresponding option unset.
+ */
+export interface HostDefaults {
+  defaultContextLines?: number | null;
+  defaultExcludeGlobs?: string[] | null;
+  caseInsensitiveDefault?: boolean | null;
+  wholeWordDefault?: boolean | null;
+  /** `useStaged` for searches, reads, stats and listings */
+  useStagedDefault?: boolean | null;
+  /** `limit` for searches, saved searches and listings */
+  maxResults?: number | null;
+  /** Fail reads and line edits on invalid UTF-8 instead of replacing it */
+  strictUtf8?: boolean | null;
+  /** Skip files larger than this many bytes during load */
+  maxFileSize?: number | null;
+  /** How paths offered more than once during a load are resolved (default "lastWriteWins") */
+  duplicatePolicy?: DuplicatePolicy | null;
+  /** Default case folding for case-insensitive searches (default "unicode") */
+  caseFolding?: CaseFolding | null;
+  /**
+   * Compress large byte payloads (staged contents, commit deltas, packed
+   * diffs). Off by default. Responses carry the encoding used per payload.
+   */
+  compression?: PayloadEncoding | null;
+  /** Minimum payload size in bytes to compress (default 65536) */
+  compressionThreshold?: number | null;
+  /**
+   * Fail loads and writes that would hold more content bytes than this,
+   * with a `MemoryPressureError`, instead of trapping when the heap runs out
+   */
+  memoryCeiling?: number | null;
+  /**
+   * Bytes of content fetched by `hydrate_files` to keep before evicting the
+   * least recently used (default 268435456, 256 MiB)
+   */
+  hydrationBudget?: number | null;
+  /**
+   * Match files on worker threads when `search_threads()` is above 1
+   * (default true). Results are the same either way.
+   */
+  parallelSearch?: boolean | null;
+  /**
+   * Skip paths matched by `.gitignore` files in the index when searching
+   * and listing (default true). Nested ignore files apply to their own
+   * directory; the last matching rule wins.
+   */
+  respectIgnoreFiles?: boolean | null;
+  /** Extra `.gitignore`-style lines applied from the root, e.g. `["dist/", "*.min.js"]` */
+  ignorePatterns?: string[] | null;
+  /**
+   * Report a staged deletion and a staged creation whose contents are at
+   * least this percent similar (0-100) as one `renamed` entry in change
+   * summaries, like `git diff -M`. Unset disables detection.
+   */
+  renameThreshold?: number | null;
+}
+
+/**
+ * `"gzip"` decodes with `DecompressionStream("gzip")`; `"deflate"` is raw
+ * deflate and decodes with `DecompressionStream("deflate-raw")`.
+ */
+export type PayloadEncoding = "gzip" | "deflate";
+
+/**
+ * `"ascii"` folds ASCII letters only, which scans large ASCII corpora faster
+ * but also makes `\w`, `\d` and `\b` ASCII-only for the pattern.
+ */
+export type CaseFolding = "unicode" | "ascii";
+
+export type DuplicatePolicy = "lastWriteWins" | "keepFirst" | "keepNewestMtime" | "error";
+
+/**
+ * Set request defaults once instead of passing them on every call.
+ * Only fields present in `options` are updated.
+ * @returns The full configuration after the update
+ */
+export function configure(options: HostDefaults): HostDefaults;
+
+/**
+ * Get the current request defaults.
+ */
+export function get_configuration(): HostDefaults;
+
+/**
+ * Restore the built-in request defaults.
+ */
+export function reset_configuration(): void;
+
+/**
+ * What changes do before `begin_index_staging`: `"strict"` fails with
+ * `StagingNotActive`, `"auto"` begins staging on the first change.
+ */
+export type StagingMode = "strict" | "auto";
+
+export interface IndexManagerConfig {
+  /** Default `"strict"` */
+  staging: StagingMode;
+  /**
+   * Whether listings, trees and searches include dotfiles and files flagged
+   * hidden when the call does not say (default false).
+   */
+  includeHidden: boolean;
+  /**
+   * Whether they include OS junk files (`.DS_Store`, `Thumbs.db`,
+   * `desktop.ini`, `._*`) when the call does not say (default false).
+   */
+  includeJunk: boolean;
+}
+
+/**
+ * Configure the index manager. Only fields present in `options` are
+ * updated; reads never begin staging, whatever the mode.
+ * @returns The full configuration after the update
+ */
+export function configure_index_manager(
+  options?: Partial<IndexManagerConfig> | null,
+): IndexManagerConfig;
+
+/**
+ * Threads searches match files on. Builds with the `parallel` feature use
+ * rayon's pool; on wasm that needs a threads-enabled build, a pool started
+ * by the host, and `SharedArrayBuffer` (cross-origin isolation). Returns 1
+ * otherwise, and searches run serially with the same results.
+ */
+export function search_threads(): number;
+
+/**
+ * Mint a token that lets `with_elevated` overwrite files marked read-only.
+ * @param reason - Why elevation is needed; recorded in the audit log
+ */
+export function mint_elevated_token(reason: string): string;
+
+/**
+ * Revoke an elevation token.
+ * @returns True if the token existed
+ */
+export function revoke_elevated_token(token: string): boolean;
+
+/**
+ * Run `op` with the read-only guard lifted and return its result.
+ * Only writes made synchronously inside `op` are elevated; every run is audited.
+ * @throws {Error} If the token is unknown or revoked, or rethrows from `op`
+ */
+export function with_elevated<T>(token: string, op: () => T): T;
+
+export interface ElevationRecord {
+  reason: string;
+  /** JavaScript milliseconds */
+  at: number;
+  /** Read-only files written during the run */
+  paths: string[];
+  succeeded: boolean;
+}
+
+/**
+ * Audit log of elevated runs, oldest first (most recent 256 kept).
+ */
+export function get_elevation_audit(): ElevationRecord[];
+
 /**
  * Get the number of files in the active index.
  */
@@ -78,10 +466,53 @@ export function file_count(): number;
 export function clear_index(): void;
 
 /**
- * Get basic statistics about the current index.
- * @returns Object with fileCount property
+ * Get statistics about the current index, computed in one pass.
+ * @param top_n - How many of the largest files to list (default 10)
+ * @returns Active/staged file counts, the response schema version, and a
+ *   breakdown of the staged view
+ * @throws {Error} If no staging session is active
+ */
+export function get_index_stats(top_n?: number | null): {
+  activeFiles: number;
+  stagedFiles: number;
+  hasStagedChanges: boolean;
+  schemaVersion: number;
+  /** Sum of staged file sizes in bytes */
+  totalBytes: number;
+  editableFiles: number;
+  readOnlyFiles: number;
+  /** Per-extension totals, most bytes first; `extension` is "" for none */
+  extensions: Array<{ extension: string; files: number; bytes: number }>;
+  /** Largest staged files, largest first */
+  largest: Array<{ path: string; size: number }>;
+};
+
+/** What the host expects to do with a hinted path next. */
+export type HintIntent = "will-read" | "will-edit" | "will-search";
+
+/**
+ * Tell conduit which files are about to be used (e.g. the user clicked
+ * one) so their line indexes are built before the first real operation.
+ * `will-edit` also warms the committed version that edits diff against.
+ * Paths are queued and warmed for up to `budgetMs` before returning.
+ * @param budgetMs - Time to spend warming now (default 4)
+ * @returns Paths warmed now and paths still queued
+ * @throws {Error} If a path or the intent is invalid
+ */
+export function hint_paths(
+  paths: string[],
+  intent: HintIntent,
+  budgetMs?: number | null,
+): { warmed: number; pending: number };
+
+/**
+ * Continue warming hinted paths for up to `budgetMs`, e.g. from
+ * `requestIdleCallback` with `deadline.timeRemaining()`.
  */
-export function get_index_stats(): { fileCount: number };
+export function drain_hints(budgetMs: number): { warmed: number; pending: number };
+
+/** Drop every queued hint. */
+export function clear_hints(): void;
 
 /**
  * Read specific lines from a file in the index.
@@ -103,29 +534,247 @@ export function read_file_lines(
   endLine: number;
   content: string;
   totalLines: number;
+  /** True if invalid UTF-8 was replaced with U+FFFD in `content` */
+  lossy: boolean;
+  /** Edit epoch of the file; see `get_edit_epochs` */
+  epoch: number;
 };
 
 /**
- * Begin a manual staging session.
- * @throws {Error} If staging is already active
+ * Read a whole file as bytes, exactly as loaded: nothing is decoded, so
+ * binary and non-UTF-8 files come back unchanged.
+ * @param path - File path
+ * @param useStaged - Read from the staged index (default true)
+ * @throws {Error} If the file is not found, or `not_hydrated` if its
+ *   content is not loaded and the content provider cannot return it at once
+ */
+export function read_file(path: string, useStaged?: boolean | null): Uint8Array;
+
+/**
+ * Read a whole file as text, without a line range.
+ * @param path - File path
+ * @param useStaged - Read from the staged index (default true)
+ * @throws {Error} If the file is not found, is not valid UTF-8 in strict
+ *   UTF-8 mode, or `not_hydrated` if its content is not loaded and the
+ *   content provider cannot return it at once
+ */
+export function read_file_text(
+  path: string,
+  useStaged?: boolean | null,
+): {
+  path: string;
+  content: string;
+  /** True if invalid UTF-8 was replaced with U+FFFD in `content` */
+  lossy: boolean;
+  /** Edit epoch of the file; see `get_edit_epochs` */
+  epoch: number;
+};
+
+/**
+ * Read whole files named in a newline-delimited UTF-8 path list.
+ * @param paths - Encoded path list, one path per line
+ * @param useStaged - Read from the staged index (default true)
+ * @returns Contents of found files; absent paths are listed in `missing`
+ * @throws {Error} `not_hydrated` for a file whose content is not loaded and
+ *   the content provider cannot return it at once
+ */
+export function read_files_in_list(
+  paths: Uint8Array,
+  useStaged?: boolean | null,
+): {
+  files: Array<{ path: string; content: string; lossy: boolean; epoch: number }>;
+  missing: string[];
+};
+
+/**
+ * Stat many paths in one call, e.g. to resolve references from an import
+ * graph. Invalid paths report `exists: false` with an `error`.
+ * @param paths - Paths to stat
+ * @param useStaged - Stat against the staged index (default true)
+ * @throws {Error} If useStaged is true but no staging session is active
+ */
+export function stat_paths(
+  paths: string[],
+  useStaged?: boolean | null,
+): Array<
+  | {
+      path: string;
+      exists: true;
+      size: number;
+      /** JavaScript milliseconds */
+      mtime: number;
+      editable: boolean;
+      hidden: boolean;
+      /** Written, deleted or moved in the current staging session */
+      modified: boolean;
+      /** Edit epoch; see `get_edit_epochs` */
+      epoch: number;
+    }
+  | { path: string; exists: false; error?: string }
+>;
+
+/**
+ * Metadata for one file without shipping its content. `lineCount` matches
+ * `read_file_lines`'s `totalLines`.
+ * @param path - File path
+ * @param useStaged - Stat against the staged index (default true)
+ * @throws {Error} If the path is invalid, or useStaged is true but no
+ *   staging session is active
+ */
+export function stat_file(
+  path: string,
+  useStaged?: boolean | null,
+):
+  | {
+      path: string;
+      exists: true;
+      size: number;
+      /** JavaScript milliseconds */
+      mtime: number;
+      /** Without the dot; empty if the name has none */
+      extension: string;
+      editable: boolean;
+      /** `null` for metadata-only entries */
+      lineCount: number | null;
+      /** XXH3-64 of the content as 16 hex digits; `null` for metadata-only entries */
+      hash: string | null;
+      /** Edit epoch; see `get_edit_epochs` */
+      epoch: number;
+    }
+  | { path: string; exists: false };
+
+/**
+ * Whether a file exists, in the staged view when a staging session is
+ * active and the active index otherwise. Invalid paths do not exist.
+ */
+export function file_exists(path: string): boolean;
+
+/** A directory returned by `get_file_tree`. */
+export interface DirNode {
+  /** Last path component; empty for the index root. */
+  name: string;
+  /** Full path without a trailing slash; empty for the index root. */
+  path: string;
+  /** Files anywhere below this directory. */
+  fileCount: number;
+  /** Sum of those files' sizes in bytes. */
+  totalSize: number;
+  /** Subdirectories, sorted by name. */
+  dirs: DirNode[];
+  /** Files directly in this directory, sorted by name. */
+  files: Array<{ name: string; size: number; editable: boolean }>;
+  /** Present when the depth limit cut off this directory's contents. */
+  truncated?: true;
+}
+
+/**
+ * Nested directory tree with per-directory file counts and sizes, for
+ * file explorers. Much cheaper than rebuilding it from `list_files_from_wasm`.
+ * @param prefix - Directory to root the tree at (default: whole index)
+ * @param depth - Directory levels to expand below the root; `0` returns only
+ *   the root's totals (default: unlimited)
+ * @param useStaged - Use the staged index (default true)
+ * @param includeHidden - Also include dotfiles and files flagged hidden
+ *   (default: the index manager's `includeHidden`)
+ * @param includeIgnored - Also include paths matched by `.gitignore` or `ignorePatterns`
+ * @param includeJunk - Also include OS junk files such as `.DS_Store`
+ *   (default: the index manager's `includeJunk`)
+ * @throws {Error} If useStaged is true but no staging session is active
+ */
+export function get_file_tree(
+  prefix?: string | null,
+  depth?: number | null,
+  useStaged?: boolean | null,
+  includeHidden?: boolean | null,
+  includeIgnored?: boolean | null,
+  includeJunk?: boolean | null,
+): DirNode;
+
+/**
+ * Edit epochs for a list of paths, in order. An epoch changes (and never
+ * repeats) whenever the file's staged or active content may have changed,
+ * including on revert, so derived data cached per epoch can be
+ * invalidated precisely. `0` means the path has not been written.
+ * Reads and searches also report `epoch` per file.
+ */
+export function get_edit_epochs(paths: string[]): number[];
+
+/**
+ * Begin a manual staging session. Not needed for changes once
+ * `configure_index_manager({ staging: "auto" })` is set.
+ * @param owner - Label for the flow starting the session. Re-entrant for the
+ *   same owner; without an owner an active session is reused silently.
+ * @throws {Error} If `owner` is given and another owner (or an unlabeled
+ *   caller) already holds the session
+ */
+export function begin_index_staging(owner?: string | null): void;
+
+/**
+ * Owner label of the active staging session.
+ * @returns The owner, or null if the session was begun without one
+ * @throws {Error} If no staging session is active
+ */
+export function get_staging_owner(): string | null;
+
+/**
+ * Commit the staged index to active.
+ * @param owner - Fail unless this owner began the session; without one,
+ *   fail if the session is owned
+ * @returns File count of the promoted index and the index generation it
+ *   became active at
+ * @throws {Error} If no staging session is active or it is owned by someone else
  */
-export function begin_index_staging(): void;
+export function commit_index_staging(owner?: string | null): {
+  fileCount: number;
+  generation: number;
+};
 
 /**
- * Commit the staged index to active, returning modified files and count.
- * @returns Object with fileCount and array of modified files
+ * Counts for the active staging session, all taken from one snapshot.
+ * `generation` changes whenever the staged or active index changes, so two
+ * results with the same generation describe the same state.
  * @throws {Error} If no staging session is active
  */
-export function commit_index_staging(): {
+export function get_staging_info(): {
   fileCount: number;
-  modified: Array<{ path: string; content: Uint8Array }>;
+  modifiedCount: number;
+  deletedCount: number;
+  generation: number;
 };
 
+export type CommitDeltaChange =
+  | {
+      path: string;
+      deleted: false;
+      /** Committed bytes, or null if content was not loaded */
+      content: Uint8Array | null;
+      /** How `content` is compressed; null if stored as-is */
+      encoding: PayloadEncoding | null;
+      /** XXH3-64 content hash as 16 hex digits */
+      hash: string | null;
+      /** JavaScript milliseconds */
+      mtime: number;
+    }
+  | { path: string; deleted: true };
+
+/**
+ * Files changed by the most recent commit, for incremental persistence.
+ * `sequence` increments on every commit; a gap means a delta was missed
+ * and the host should re-export the full index.
+ * @returns The delta, or null if nothing has been committed yet
+ */
+export function export_commit_delta(): {
+  sequence: number;
+  changes: CommitDeltaChange[];
+} | null;
+
 /**
  * Revert active staging session without committing.
- * @throws {Error} If no staging session is active
+ * @param owner - Fail unless this owner began the session; without one,
+ *   fail if the session is owned
+ * @throws {Error} If no staging session is active or it is owned by someone else
  */
-export function revert_index_staging(): void;
+export function revert_index_staging(owner?: string | null): void;
 
 /**
  * Get staged modifications without committing.
@@ -142,35 +791,132 @@ export function get_staged_modifications(): Array<{ path: string; content: Uint8
 export function get_staged_deletions(): string[];
 
 /**
- * Get staged modifications with both active and staged content for diff preview
+ * Get staged modifications with both active and staged content for diff preview.
+ * Contents over the configured threshold may be compressed; see `compression`.
  * @returns Array of objects with path, stagedContent, and optionally activeContent
  * @throws {Error} If staging is not active
  */
 export function get_staged_modifications_with_active(): Array<{
   path: string;
   stagedContent: Uint8Array;
+  stagedEncoding: PayloadEncoding | null;
   activeContent?: Uint8Array;
+  activeEncoding?: PayloadEncoding | null;
 }>;
 
+export interface ModifiedFileSummary {
+  path: string;
+  linesAdded: number;
+  linesRemoved: number;
+  /**
+   * `recreated`: existed before staging, was deleted or moved away, then written again.
+   * `renamed`: deleted and recreated elsewhere with similar content (see `renameThreshold`).
+   */
+  status: 'created' | 'modified' | 'deleted' | 'moved' | 'recreated' | 'renamed';
+  /** Destination, for moved and renamed files */
+  movedTo?: string;
+  /** Content similarity percentage, for renamed files */
+  similarity?: number;
+}
+
 /**
  * Get summary of all modified files with line change statistics
  * @returns Array of file summaries with change stats
  * @throws {Error} If staging is not active
  */
-export function get_modified_files_summary(): Array<{
-  path: string;
-  linesAdded: number;
-  linesRemoved: number;
-  status: 'created' | 'modified' | 'deleted';
-}>;
+export function get_modified_files_summary(): ModifiedFileSummary[];
+
+/**
+ * A change to the staged or active index. `filesLoaded` stands in for the
+ * per-file events of a bulk load.
+ */
+export type IndexEvent =
+  | { type: "stagingBegun" }
+  | { type: "fileStaged"; path: string }
+  | { type: "fileDeleted"; path: string }
+  | { type: "fileMoved"; from: string; to: string }
+  | { type: "stagingPromoted"; paths: string[] }
+  | { type: "stagingReverted" }
+  /** Undo, redo, checkpoint restore or session import replaced staging */
+  | { type: "stagingRestored" }
+  | { type: "filesLoaded"; count: number };
+
+/**
+ * Call `callback` with each index event as it happens, instead of polling
+ * `get_modified_files_summary`. Replaces any earlier callback; `null`
+ * unsubscribes. Events of one operation arrive together after it has
+ * finished, and none arrive for operations that failed and rolled back.
+ * Exceptions thrown by the callback are ignored.
+ */
+export function on_index_event(callback: ((event: IndexEvent) => void) | null): void;
+
+/**
+ * Same as `get_modified_files_summary`, calling `onProgress(done, total)`
+ * between files. Returning `false` from the callback stops early; the
+ * result then has `complete: false` and holds the files summarized so far.
+ * @throws {Error} If staging is not active, or rethrows a callback error
+ */
+export function get_modified_files_summary_with_progress(
+  onProgress: (done: number, total: number) => boolean | void
+): {
+  files: ModifiedFileSummary[];
+  complete: boolean;
+};
+
+/**
+ * Staged changes as a markdown report: per-file status and +/- line
+ * counts, grouped by directory, with moves shown as arrows. The output is
+ * sorted by path, so the same changes always render the same text.
+ * @throws {Error} If staging is not active
+ */
+export function export_change_summary_markdown(): string;
+
+export type StagedOp = 'write' | 'delete' | 'moveOut' | 'moveIn';
+
+/**
+ * Operations applied to a path in the current staging session, oldest
+ * first, with consecutive repeats collapsed.
+ * @throws {Error} If staging is not active
+ */
+export function get_staged_history(path: string): StagedOp[];
+
+/**
+ * Diff of a file as JSON bytes, compressed under the configured policy.
+ * After decompression `data` is the versioned wire form of the diff
+ * (`schemaVersion` plus snake_case fields), not the camelCase shape of
+ * `get_file_diff`.
+ * @throws {Error} If file not found or staging not active
+ */
+export function get_file_diff_packed(path: string): {
+  encoding: PayloadEncoding | null;
+  data: Uint8Array;
+};
+
+/** How finely `get_file_diff` marks changes within lines. */
+export type DiffGranularity = "line" | "word" | "char";
+
+/** A changed run within one of a region's lines, in UTF-16 code units. */
+export interface InlineSpan {
+  /** Index into `removedLines` or `addedLines`. */
+  line: number;
+  start: number;
+  end: number;
+}
 
 /**
  * Get detailed diff for a specific file
  * @param path - File path to diff
+ * @param options - `granularity` (default `"line"`); `"word"` or `"char"`
+ *   adds `removedSpans`/`addedSpans` to regions that both remove and add lines.
+ *   `contextLines` (default 0) fills `contextBefore`/`contextAfter` with up
+ *   to that many unchanged lines around each region
  * @returns Detailed diff with regions of changes
  * @throws {Error} If file not found or staging not active
  */
-export function get_file_diff(path: string): {
+export function get_file_diff(
+  path: string,
+  options?: { granularity?: DiffGranularity; contextLines?: number }
+): {
   path: string;
   stats: {
     linesAdded: number;
@@ -184,76 +930,436 @@ export function get_file_diff(path: string): {
     linesAdded: number;
     removedLines: string[];
     addedLines: string[];
+    contextBefore: string[];
+    contextAfter: string[];
+    removedSpans?: InlineSpan[];
+    addedSpans?: InlineSpan[];
   }>;
 };
 
 /**
- * Create or overwrite a file in the staged index.
- * @param path - File path to create
- * @param content - Optional file content
- * @param allowOverwrite - Whether to overwrite existing files
- * @returns Object with path, size, and created flag
- * @throws {Error} If file exists and allowOverwrite is false
+ * Save the session for `import_session`: references to the active files
+ * (path, size, mtime, hash) and every staged change with its content,
+ * change stats, moves and needs-read flags. Undo history and checkpoints
+ * are not included.
+ * @returns Gzip-compressed JSON
  */
-export function create_index_file(
-  path: string,
-  content?: Uint8Array | null,
-  allowOverwrite?: boolean,
-): {
-  path: string;
-  size: number;
-  created: boolean;
-};
+export function export_session(): Uint8Array;
 
 /**
- * Delete a file from the staged index.
- * @param path - File path to delete
- * @returns Object with path and existed flag
+ * Resume a session saved by `export_session`, replacing current staging.
+ * Load the same active files first.
+ * @throws {Error} With `code: "content_changed"` or `"file_not_found"` if an
+ * active file differs or is missing; staging is left as it was
+ * @throws {Error} With `code: "invalid_format"` if the bytes are not a session
+ */
+export function import_session(bytes: Uint8Array): void;
+
+export interface CheckpointInfo {
+  name: string;
+  /** Files staging had changed when the checkpoint was taken */
+  modifiedFiles: number;
+}
+
+/**
+ * Save the current staging state as a named checkpoint, replacing any of
+ * the same name. Checkpoints are cheap and last until staging is
+ * committed, reverted or restarted.
+ * @returns Whether an existing checkpoint was replaced
  * @throws {Error} If staging is not active
  */
-export function delete_file(path: string): {
-  path: string;
-  existed: boolean;
-};
+export function create_checkpoint(name: string): boolean;
 
 /**
- * Replace specific lines or line ranges in a file.
- * @param path - The file path to modify
- * @param replacements - Array of [lineNumber, newContent] for single line or [startLine, endLine, newContent] for range (1-based, inclusive)
- * @param use_staged - If true, modify staged index; otherwise modify active index
- * @returns Object containing path, linesReplaced, linesAdded, totalLines, and originalLines
- * @throws {Error} If file not found or line numbers invalid
+ * Return staging to a checkpoint. The restore is one undo step.
+ * @throws {Error} With `code: "unknown_checkpoint"` if there is no such checkpoint
  */
-export function replace_lines(
-  path: string,
-  replacements: Array<[number, string] | [number, number, string]>,
-  use_staged: boolean
-): {
-  path: string;
-  linesReplaced: number;
-  linesAdded: number;
-  totalLines: number;
-  originalLines: number;
-};
+export function restore_checkpoint(name: string): void;
+
+/** Remove a checkpoint; returns whether it existed. */
+export function remove_checkpoint(name: string): boolean;
+
+/** Checkpoints of the current staging session, oldest first. */
+export function list_checkpoints(): CheckpointInfo[];
+
+/**
+ * Diffs of every file that differs between two checkpoints, in path order.
+ * @param to - Checkpoint to compare against; current staging if omitted
+ * @param contextLines - Unchanged lines around each region (default 0)
+ * @throws {Error} With `code: "unknown_checkpoint"` if a checkpoint is missing
+ */
+export function diff_checkpoints(
+  from: string,
+  to?: string | null,
+  contextLines?: number | null
+): ReturnType<typeof get_file_diff>[];
+
+/**
+ * Staged change of a file as a git-style unified patch. Either end of a
+ * staged move renders as a rename.
+ * @returns The patch, or an empty string if the file is unchanged
+ * @throws {Error} If file not found or staging not active
+ */
+export function get_unified_diff(path: string): string;
+
+/**
+ * Every staged change as one git-style patch (headers, hunks, renames),
+ * ordered by path and suitable for `git apply`.
+ * @throws {Error} If staging not active
+ */
+export function export_patch(): string;
+
+/** Deletions and moves in an archive from `export_changes_archive`. */
+export interface ChangeManifest {
+  schemaVersion: number;
+  deleted: string[];
+  moved: Array<{ src: string; dst: string }>;
+}
+
+/**
+ * Every staged change as a zip (deflated) or tar archive, built in one
+ * call. Created, modified and moved files are stored under their staged
+ * paths; `.conduit/changes.json` holds a `ChangeManifest`.
+ * @throws {Error} If staging is not active or `format` is unknown
+ * (`code: "invalid_format"`)
+ */
+export function export_changes_archive(format: "zip" | "tar"): Uint8Array;
+
+/** Outcome of one hunk passed to `apply_patch`. */
+export interface PatchHunkResult {
+  oldStart: number;
+  newStart: number;
+  applied: boolean;
+  /** Lines between the header's position and where the context matched. */
+  offset: number;
+  error: string | null;
+}
+
+/** Outcome of one file section passed to `apply_patch`. */
+export interface PatchFileResult {
+  /** `null` for a created file. */
+  oldPath: string | null;
+  /** `null` for a deleted file. */
+  newPath: string | null;
+  applied: boolean;
+  /** File-level failure, such as a missing source or taken destination. */
+  error: string | null;
+  hunks: PatchHunkResult[];
+}
+
+/**
+ * Apply a unified diff (git-style headers optional) to staging. Hunks are
+ * located by their context lines, so line numbers may be off. Staging only
+ * changes if every file and hunk applies, as one undo step.
+ * @throws {Error} With `code: "invalid_patch"` if the patch cannot be parsed,
+ *   or if staging not active
+ */
+export function apply_patch(patch: string): {
+  applied: boolean;
+  files: PatchFileResult[];
+};
+
+/** Lines changed differently on both sides of a merge. */
+export interface MergeConflict {
+  /** 1-based line of the `<<<<<<< staged` marker in `content`. */
+  startLine: number;
+  /** 1-based line of the `>>>>>>> theirs` marker. */
+  endLine: number;
+  base: string;
+  ours: string;
+  theirs: string;
+}
+
+export interface MergeFileResult {
+  path: string;
+  /** Merged content, with markers around each conflict. */
+  content: string;
+  conflicts: MergeConflict[];
+  /** Whether staging changed (`stage_merge` only). */
+  staged: boolean;
+}
+
+/**
+ * Three-way merge of the staged copy of `path` ("ours") with `theirs`,
+ * both changed from `base`, e.g. after the file was edited outside the app.
+ * Changes to different lines are combined; changes to the same lines are
+ * written between `<<<<<<< staged`, `=======` and `>>>>>>> theirs` markers.
+ * Does not change staging.
+ * @throws {Error} If the file is not staged or staging is not active
+ */
+export function merge_file(path: string, base: string, theirs: string): MergeFileResult;
+
+/**
+ * Like `merge_file`, then stage the merged content, conflict markers
+ * included, as one undo step. The file must be read again before line edits.
+ */
+export function stage_merge(path: string, base: string, theirs: string): MergeFileResult;
+
+/**
+ * Line diff of two caller-provided strings, using the same engine as
+ * `get_file_diff`. The returned `path` is empty.
+ */
+export function diff_texts(original: string, modified: string): ReturnType<typeof get_file_diff>;
+
+/**
+ * Create or overwrite a file in the staged index.
+ * @param path - File path to create
+ * @param content - Optional file content
+ * @param allowOverwrite - Whether to overwrite existing files
+ * @returns Object with path, size, and created flag
+ * @throws {Error} If file exists and allowOverwrite is false
+ */
+/**
+ * Replace a file's staged content only if its active content is still the
+ * version the caller last saw (compare-and-swap). Use it to sync edits made
+ * in an external editor without clobbering a commit that landed since.
+ * Only active content is checked; unpromoted staged edits are overwritten.
+ * @param expectedActiveHash - Hex XXH3-64 of the active content, as
+ *   reported by `export_commit_delta`, or null if the file must not exist
+ * @returns The new content hash along with path, size, and created flag
+ * @throws {Error} If the active content hash differs or staging is not active
+ */
+export function overwrite_file(
+  path: string,
+  content: Uint8Array,
+  expectedActiveHash: string | null,
+): {
+  path: string;
+  size: number;
+  created: boolean;
+  hash: string;
+};
+
+export function create_index_file(
+  path: string,
+  content?: Uint8Array | null,
+  allowOverwrite?: boolean,
+): {
+  path: string;
+  size: number;
+  created: boolean;
+};
+
+/**
+ * Flag staged files as hidden (or visible again). Hidden files and dotfiles
+ * are left out of listings and searches unless `include_hidden` is set.
+ * The flag is metadata only and does not mark files modified.
+ * @returns Number of paths found in the staged index
+ * @throws {Error} If staging is not active
+ */
+export function set_files_hidden(paths: string[], hidden: boolean): number;
+
+/**
+ * Delete a file from the staged index.
+ * @param path - File path to delete
+ * @returns Object with path and existed flag
+ * @throws {Error} If staging is not active
+ */
+export function delete_file(path: string): {
+  path: string;
+  existed: boolean;
+};
+
+/**
+ * Delete every file in a newline-delimited UTF-8 path list in one step.
+ * @param paths - Encoded path list, one path per line
+ * @returns Number of files deleted and the paths that did not exist
+ * @throws {Error} If staging is not active or a path is invalid
+ */
+export function delete_files_in_list(paths: Uint8Array): {
+  deleted: number;
+  missing: string[];
+};
+
+/**
+ * Replace a piece of text located by its content instead of line numbers,
+ * so an edit cannot land on the wrong lines after the file has changed.
+ * @param path - The file path to modify
+ * @param expected_text - Text to replace, as last read
+ * @param replacement - New text
+ * @param options.occurrence - 1-based match to replace when the text appears
+ *   more than once; without it the text must be unique
+ * @param options.whitespaceInsensitive - Let any run of whitespace in
+ *   `expected_text` match any run of whitespace in the file
+ * @param options.precondition - Version of the file the edit was written
+ *   against; fails with `EditConflictError` if it changed since
+ * @returns Where the replaced text was and the resulting line counts
+ * @throws {Error} With code `anchor_not_found` if the text (or the requested
+ *   occurrence) is missing, or `ambiguous_anchor` with a `count` if it
+ *   matches several places and no occurrence was given
+ */
+export function anchored_edit(
+  path: string,
+  expected_text: string,
+  replacement: string,
+  options?: {
+    occurrence?: number;
+    whitespaceInsensitive?: boolean;
+    precondition?: EditPrecondition;
+  } | null
+): {
+  path: string;
+  /** First and last original line of the replaced text, 1-based. */
+  startLine: number;
+  endLine: number;
+  /** How many places `expected_text` matched. */
+  occurrences: number;
+  /** Net change in line count. */
+  linesAdded: number;
+  totalLines: number;
+  originalLines: number;
+  /** True if the file was not valid UTF-8 and replacement characters were written back */
+  lossy: boolean;
+};
+
+/** Options for `append_to_file` and `prepend_to_file`. */
+export interface AppendOptions {
+  /** Fail with `file_not_found` instead of creating a missing file */
+  mustExist?: boolean;
+  /** Whitespace handling for the added lines; all options default to off */
+  policy?: EditPolicy;
+  /** Version of the file the edit was written against */
+  precondition?: EditPrecondition;
+}
+
+/**
+ * Add lines at the end of a file without reading its line count first.
+ * A missing file is created with just these lines.
+ * @param path - The file path to modify
+ * @param content - Lines to add
+ * @returns Same as replace_lines - object with modification stats
+ */
+export function append_to_file(
+  path: string,
+  content: string,
+  options?: AppendOptions | null,
+): {
+  path: string;
+  linesReplaced: number;
+  linesAdded: number;
+  totalLines: number;
+  originalLines: number;
+  /** True if the file was not valid UTF-8 and replacement characters were written back */
+  lossy: boolean;
+};
+
+/**
+ * Add lines at the start of a file, after a leading shebang line if it has
+ * one. A missing file is created with just these lines.
+ * @param path - The file path to modify
+ * @param content - Lines to add
+ * @returns Same as replace_lines - object with modification stats
+ */
+export function prepend_to_file(
+  path: string,
+  content: string,
+  options?: AppendOptions | null,
+): {
+  path: string;
+  linesReplaced: number;
+  linesAdded: number;
+  totalLines: number;
+  originalLines: number;
+  /** True if the file was not valid UTF-8 and replacement characters were written back */
+  lossy: boolean;
+};
+
+/**
+ * The version of a file an edit was written against. Line edits and
+ * `anchored_edit` given one fail with `EditConflictError` instead of
+ * overwriting a change made since. All given fields must match.
+ */
+export interface EditPrecondition {
+  /**
+   * XXH3-64 of the staged content as 16 hex digits, as listed by
+   * `list_files_from_wasm` with checksums.
+   */
+  expectedHash?: string;
+  /**
+   * Staged mtime in JavaScript milliseconds, as reported by `stat_paths`.
+   * Has one-second resolution; prefer `expectedHash` for back-to-back edits.
+   */
+  expectedMtime?: number;
+}
+
+/** Thrown when an `EditPrecondition` no longer matches the staged file. */
+export interface EditConflictError extends Error {
+  code: 'edit_conflict';
+  /** Current content hash (16 hex digits). */
+  currentHash: string;
+  /** Current mtime in JavaScript milliseconds. */
+  currentMtime: number;
+}
+
+/** Thrown by `replace_lines` when ranges in one call share lines. */
+export interface OverlappingEditsError extends Error {
+  code: 'overlapping_edits';
+  /** Positions in `replacements` of each overlapping pair, lower first. */
+  conflicts: Array<[number, number]>;
+}
+
+/** Whitespace handling for line edits. */
+export interface EditPolicy {
+  /** Strip trailing whitespace from inserted and replacement lines. */
+  trimTrailingWhitespace?: boolean;
+  /** End the file with a newline, even if it did not have one. */
+  ensureFinalNewline?: boolean;
+  /** Shift replacement content to the indentation of the lines it replaces. */
+  preserveIndentation?: boolean;
+}
+
+/**
+ * Replace specific lines or line ranges in a file.
+ * @param path - The file path to modify
+ * @param replacements - Array of [lineNumber, newContent] for single line or [startLine, endLine, newContent] for range (1-based, inclusive)
+ * @param use_staged - If true, modify staged index; otherwise modify active index
+ * @param policy - Whitespace handling for the edit; all options default to off
+ * @param precondition - Version of the file the edit was written against
+ * @returns Object containing path, linesReplaced, linesAdded, totalLines, and originalLines
+ * @throws {Error} If file not found or line numbers invalid
+ * @throws {OverlappingEditsError} If two ranges share lines; nothing is changed
+ * @throws {EditConflictError} If `precondition` no longer matches the file
+ */
+export function replace_lines(
+  path: string,
+  replacements: Array<[number, string] | [number, number, string]>,
+  use_staged: boolean,
+  policy?: EditPolicy | null,
+  precondition?: EditPrecondition | null
+): {
+  path: string;
+  linesReplaced: number;
+  linesAdded: number;
+  totalLines: number;
+  originalLines: number;
+  /** True if the file was not valid UTF-8 and replacement characters were written back */
+  lossy: boolean;
+};
 
 /**
  * Delete specific lines from a file.
  * @param path - The file path to modify
  * @param line_numbers - Array of line numbers to delete (1-based)
  * @param use_staged - If true, modify staged index; otherwise modify active index
+ * @param policy - Whitespace handling for the edit; all options default to off
+ * @param precondition - Version of the file the edit was written against
  * @returns Same as replace_lines - object with modification stats
  * @throws {Error} If file not found or line numbers invalid
  */
 export function delete_lines(
   path: string,
   line_numbers: number[],
-  use_staged: boolean
+  use_staged: boolean,
+  policy?: EditPolicy | null,
+  precondition?: EditPrecondition | null
 ): {
   path: string;
   linesReplaced: number;
   linesAdded: number;
   totalLines: number;
   originalLines: number;
+  /** True if the file was not valid UTF-8 and replacement characters were written back */
+  lossy: boolean;
 };
 
 /**
@@ -262,6 +1368,8 @@ export function delete_lines(
  * @param line_number - Line number where to insert (1-based)
  * @param content - Content to insert (can be multi-line)
  * @param use_staged - If true, modify staged index; otherwise modify active index
+ * @param policy - Whitespace handling for the edit; all options default to off
+ * @param precondition - Version of the file the edit was written against
  * @returns Same as replace_lines - object with modification stats
  * @throws {Error} If file not found or line number invalid
  */
@@ -269,13 +1377,17 @@ export function insert_before_line(
   path: string,
   line_number: number,
   content: string,
-  use_staged: boolean
+  use_staged: boolean,
+  policy?: EditPolicy | null,
+  precondition?: EditPrecondition | null
 ): {
   path: string;
   linesReplaced: number;
   linesAdded: number;
   totalLines: number;
   originalLines: number;
+  /** True if the file was not valid UTF-8 and replacement characters were written back */
+  lossy: boolean;
 };
 
 /**
@@ -284,6 +1396,8 @@ export function insert_before_line(
  * @param line_number - Line number after which to insert (1-based)
  * @param content - Content to insert (can be multi-line)
  * @param use_staged - If true, modify staged index; otherwise modify active index
+ * @param policy - Whitespace handling for the edit; all options default to off
+ * @param precondition - Version of the file the edit was written against
  * @returns Same as replace_lines - object with modification stats
  * @throws {Error} If file not found or line number invalid
  */
@@ -291,13 +1405,17 @@ export function insert_after_line(
   path: string,
   line_number: number,
   content: string,
-  use_staged: boolean
+  use_staged: boolean,
+  policy?: EditPolicy | null,
+  precondition?: EditPrecondition | null
 ): {
   path: string;
   linesReplaced: number;
   linesAdded: number;
   totalLines: number;
   originalLines: number;
+  /** True if the file was not valid UTF-8 and replacement characters were written back */
+  lossy: boolean;
 };
 
 /**
@@ -305,6 +1423,8 @@ export function insert_after_line(
  * @param path - The file path to modify
  * @param insertions - Array of insertion operations
  * @param use_staged - If true, modify staged index; otherwise modify active index
+ * @param policy - Whitespace handling for the edit; all options default to off
+ * @param precondition - Version of the file the edit was written against
  * @returns Same as replace_lines - object with modification stats
  * @throws {Error} If file not found or line numbers invalid
  */
@@ -315,13 +1435,17 @@ export function insert_lines(
     content: string;
     position: 'before' | 'after';
   }>,
-  use_staged: boolean
+  use_staged: boolean,
+  policy?: EditPolicy | null,
+  precondition?: EditPrecondition | null
 ): {
   path: string;
   linesReplaced: number;
   linesAdded: number;
   totalLines: number;
   originalLines: number;
+  /** True if the file was not valid UTF-8 and replacement characters were written back */
+  lossy: boolean;
 };
 
 /**
@@ -329,25 +1453,77 @@ export function insert_lines(
  * @param start - Starting index (0-based, inclusive)
  * @param stop - Ending index (exclusive). If 0, returns all files from start.
  * @param use_staged - If true, list from staged index; otherwise list from active index
+ * @param modified_after - Only include files modified at or after this time (JavaScript milliseconds)
+ * @param modified_before - Only include files modified at or before this time (JavaScript milliseconds)
+ * @param with_checksums - Include a fast content hash per file for client-side caching
+ * @param include_hidden - Also list dotfiles and files flagged with `set_files_hidden`
+ *   (default: the index manager's `includeHidden`)
+ * @param include_ignored - Also list paths matched by `.gitignore` files or `ignorePatterns`
+ * @param glob_options - How `glob_pattern` is matched
+ * @param include_junk - Also list OS junk files such as `.DS_Store`
+ *   (default: the index manager's `includeJunk`)
  * @returns Object containing files array, total count, and actual pagination bounds
  * @throws {Error} If use_staged is true but no staging session is active
  */
-export function list_files_from_wasm(path_prefix?: string | null, glob_pattern?: string | null, use_staged?: boolean | null, limit?: number | null, offset?: number | null): {
+export function list_files_from_wasm(path_prefix?: string | null, glob_pattern?: string | null, use_staged?: boolean | null, limit?: number | null, offset?: number | null, modified_after?: number | null, modified_before?: number | null, with_checksums?: boolean | null, include_hidden?: boolean | null, include_ignored?: boolean | null, glob_options?: GlobOptions | null, include_junk?: boolean | null): {
   files: Array<{
     path: string;
     size: number;
     mtime: number;
     extension: string;
     editable: boolean;
+    /** XXH3-64 content hash as 16 hex digits; present only with `with_checksums`, null if content is not loaded */
+    checksum?: string | null;
   }>;
   total: number;
   start: number;
   end: number;
 };
 
+/**
+ * Part of a match on one line of a search excerpt. Columns are 0-based and
+ * half-open, counted in the line as shown: after a leading `…` and with
+ * invalid UTF-8 replaced.
+ */
+export interface MatchColumns {
+  /** 1-based line in the file */
+  line: number;
+  startByte: number;
+  endByte: number;
+  /** Columns in Unicode scalar values */
+  startChar: number;
+  endChar: number;
+}
+
+/**
+ * Counts for a search, attached to result arrays as `stats`. Totals cover
+ * every match found, including those cut by `limit`.
+ */
+export interface SearchStats {
+  /** Matches found; one per result unless `mergeAdjacent` is set */
+  totalMatches: number;
+  /** Distinct files with at least one match */
+  filesWithMatches: number;
+  /** Files with matches left out because of `limit` */
+  filesTruncated: number;
+  /** The search stopped at `maxTotalMatches` with matches left */
+  matchLimitReached: boolean;
+  /**
+   * The search was aborted or hit `timeoutMs` before scanning every file;
+   * the results are what it found until then
+   */
+  aborted: boolean;
+  /** Candidates not searched because they are loaded as metadata only */
+  skippedUnhydrated: number;
+  /** Files with more matches than `maxMatchesPerFile`, in scan order */
+  truncatedPaths: string[];
+}
+
 /**
  * Search for matches in files using regex patterns.
  * Returns an array of preview hunks showing matches with surrounding context.
+ * `modified_after`/`modified_before` restrict the search to files whose mtime
+ * (JavaScript milliseconds) falls within the inclusive range.
  */
 export function search_files(
   search_term: string,
@@ -358,57 +1534,1133 @@ export function search_files(
   whole_word?: boolean | null,
   use_staged?: boolean | null,
   context_lines?: number | null,
-  limit?: number | null
+  limit?: number | null,
+  modified_after?: number | null,
+  modified_before?: number | null
+): Array<{
+  path: string;
+  lines: Array<{ lineNumber: number; content: string; isMatch: boolean }>;
+  lossy: boolean;
+  /** Characters cut from the first line; it then starts with `…` */
+  omittedPrefix: number;
+  /** Characters cut from the last line; it then ends with `…` */
+  omittedSuffix: number;
+  /** Edit epoch of the file; see `get_edit_epochs` */
+  epoch: number;
+  /** Where the match falls in the excerpt, one entry per line it covers */
+  matchColumns: MatchColumns[];
+}> & { stats: SearchStats };
+
+/**
+ * How glob patterns are matched. Brace alternation (`{a,b}`) and `**` are
+ * always available; the same options mean the same thing in every binding.
+ */
+export interface GlobOptions {
+  /** `*` and `?` stop at `/`, so only `**` crosses directories (default false) */
+  literalSeparator?: boolean;
+  /** Match paths regardless of case (default false) */
+  caseInsensitive?: boolean;
+  /** A backslash escapes the next character, e.g. `\*` (default true) */
+  backslashEscape?: boolean;
+}
+
+/**
+ * Options accepted by the object-style search bindings.
+ * Mirrors the positional parameters of `search_files`.
+ */
+export interface SearchOptions {
+  pathPrefix?: string | null;
+  includePattern?: string | null;
+  excludePattern?: string | null;
+  caseSensitive?: boolean | null;
+  wholeWord?: boolean | null;
+  useStaged?: boolean | null;
+  contextLines?: number | null;
+  /** Return at most this many results; `stats` still count the rest */
+  limit?: number | null;
+  modifiedAfter?: number | null;
+  modifiedBefore?: number | null;
+  /** Scan small files before large ones so most hits arrive early. */
+  smallFilesFirst?: boolean | null;
+  /** Return `{ path, line, length }` per hit without excerpts. */
+  minimal?: boolean | null;
+  /**
+   * Search space; overrides `useStaged`. `StagedOnly` searches files touched
+   * in staging, `ActiveMinusStaged` the active versions staging will
+   * overwrite or delete, `Union` all staged files plus deleted ones.
+   */
+  space?: SearchSpace | null;
+  /** Case folding for case-insensitive searches */
+  caseFolding?: CaseFolding | null;
+  /**
+   * Also search dotfiles and files flagged with `set_files_hidden`
+   * (default: the index manager's `includeHidden`). Path-list searches
+   * always include them.
+   */
+  includeHidden?: boolean | null;
+  /**
+   * Also search OS junk files such as `.DS_Store` (default: the index
+   * manager's `includeJunk`). Path-list searches always include them.
+   */
+  includeJunk?: boolean | null;
+  /**
+   * Also search paths matched by `.gitignore` files in the index or by
+   * `ignorePatterns` (default false). Path-list searches always include them.
+   */
+  includeIgnored?: boolean | null;
+  /**
+   * Name of a pattern saved with `register_pattern`. Replaces the search
+   * term, and the saved matching options replace `caseSensitive`,
+   * `wholeWord` and `caseFolding`.
+   */
+  patternRef?: string | null;
+  /** Report at most this many matches per file */
+  maxMatchesPerFile?: number | null;
+  /**
+   * Stop searching after this many matches. Unlike `limit`, which trims
+   * results, the scan ends early, so `stats` only count the matches
+   * scanned.
+   */
+  maxTotalMatches?: number | null;
+  /**
+   * Merge hunks of nearby matches whose excerpts overlap or touch, so no
+   * line is shown twice. A merged hunk marks every match it covers.
+   */
+  mergeAdjacent?: boolean | null;
+  /** How `includePattern` and `excludePattern` are matched */
+  globOptions?: GlobOptions | null;
+  /**
+   * Replacements only: recase each replacement to follow the text it
+   * replaces, so replacing `foo` with `bar` writes `Bar` over `Foo` and
+   * `BAR` over `FOO`.
+   */
+  preserveCase?: boolean | null;
+  /**
+   * Stop searching after this many milliseconds and return the matches
+   * found so far, with `stats.aborted` set
+   */
+  timeoutMs?: number | null;
+  /**
+   * Search exactly these files. `pathPrefix`, `includePattern` and
+   * `excludePattern` are not evaluated and hidden or ignored files are
+   * searched; other filters still apply. Unknown paths are skipped.
+   */
+  paths?: string[] | null;
+}
+
+/**
+ * Compile a regex once and save it under `name`, so searches can refer to
+ * it with `patternRef` instead of recompiling it on every call.
+ * Only the matching options (`caseSensitive`, `wholeWord`, `caseFolding`)
+ * of `options` are used.
+ * @returns true if an existing pattern was replaced
+ * @throws {Error} If the pattern does not compile; the old one is kept
+ */
+export function register_pattern(
+  name: string,
+  pattern: string,
+  options?: SearchOptions | null,
+): boolean;
+
+/** Remove a saved pattern. Returns whether it existed. */
+export function remove_pattern(name: string): boolean;
+
+/** Saved patterns, sorted by name. */
+export function list_patterns(): Array<{
+  name: string;
+  pattern: string;
+  caseSensitive: boolean;
+  wholeWord: boolean;
+}>;
+
+/**
+ * A fully resolved search, as kept in the search history. Unlike
+ * `SearchOptions`, defaults from `configure` are already applied.
+ */
+export interface FindRequest {
+  find: string;
+  patternRef: string | null;
+  /** Set for multi-pattern searches, which then ignore `find` */
+  patterns?: string[];
+  includeGlobs: string[] | null;
+  excludeGlobs: string[] | null;
+  prefix: string | null;
+  /** Context lines around matches */
+  delta: number;
+  engineOpts: {
+    caseInsensitive: boolean;
+    caseFolding: CaseFolding;
+    unicode: boolean;
+    word: boolean;
+    crlf: boolean;
+    multiline: boolean;
+    dotAll: boolean;
+  };
+  where: SearchSpace;
+  modifiedAfter: number | null;
+  modifiedBefore: number | null;
+  order: "path" | "smallestFirst";
+  minimal: boolean;
+  includeHidden: boolean;
+  includeJunk: boolean;
+  includeIgnored: boolean;
+  maxMatchesPerFile: number | null;
+  maxTotalMatches: number | null;
+  mergeAdjacent: boolean;
+  globOptions: Required<GlobOptions>;
+}
+
+/** Recent searches, newest first (at most 50), and saved searches by name. */
+export interface SearchHistory {
+  recent: FindRequest[];
+  saved: Record<string, FindRequest>;
+}
+
+/**
+ * Recent and saved searches. Searches run through `search_files`,
+ * `search_files_with_options`, `search_files_page` and `run_saved_search`
+ * are recorded; path-list searches are not. Persist the value and pass it
+ * to `restore_search_history` after a reload.
+ */
+export function get_search_history(): SearchHistory;
+
+/**
+ * Replace the search history, e.g. with one persisted from
+ * `get_search_history`.
+ * @throws {Error} If `history` is not a valid search history
+ */
+export function restore_search_history(history: SearchHistory): void;
+
+/** Forget recent searches; saved searches are kept. */
+export function clear_recent_searches(): void;
+
+/**
+ * Save a search under `name`. Unset options are resolved against the
+ * `configure` defaults at save time.
+ * @returns true if an existing saved search was replaced
+ */
+export function save_search(
+  name: string,
+  pattern: string,
+  options?: SearchOptions | null,
+): boolean;
+
+/** Remove a saved search. Returns whether it existed. */
+export function remove_saved_search(name: string): boolean;
+
+/**
+ * Run a saved search and record it as recent. Results are shaped as from
+ * `search_files_with_options`, or bare hits if saved with `minimal: true`.
+ * @param limit - Maximum results; defaults to the configured `maxResults`
+ * @throws {Error} With `code: "unknown_saved_search"` if `name` is not saved
+ */
+export function run_saved_search(
+  name: string,
+  limit?: number | null,
+  handle?: SearchHandle | null,
+): ReturnType<typeof search_files_with_options>;
+
+/**
+ * `identifier`: identifiers as written (`parseHttpRequest`).
+ * `word`: lowercased words within identifiers, split at `_`, digits and
+ * case changes (`parse`, `http`, `request`).
+ */
+export type TokenKind = "identifier" | "word";
+
+/**
+ * Most frequent tokens across the files matching `globs`, counted in one
+ * pass without exporting file contents. Binary files are skipped.
+ * @param topN - Maximum tokens returned (default 100)
+ * @returns Most frequent first; ties sorted alphabetically
+ */
+export function get_token_frequencies(
+  globs?: string[] | null,
+  topN?: number | null,
+  tokenKind?: TokenKind | null,
+  useStaged?: boolean | null,
+): Array<{
+  token: string;
+  /** Occurrences across all files */
+  count: number;
+  /** Files containing the token */
+  files: number;
+}>;
+
+/**
+ * Cancels a search or replace. Pass it as the last argument of
+ * `search_files_with_options`, `search_files_in_list`, `search_files_page`,
+ * `search_files_streaming`, `replace_in_files`, `export_search_report`,
+ * `extract_matches` or `run_saved_search`, then call `abort()` (e.g. from a streaming callback)
+ * to stop the call; it throws with `code: "aborted"`.
+ */
+export class SearchHandle {
+  constructor();
+  /** Request cancellation of every call using this handle */
+  abort(): void;
+  /** Clear a previous `abort()` so the handle can be reused */
+  reset(): void;
+  readonly aborted: boolean;
+  readonly id: number;
+  /** Release the handle; calls given a freed handle throw */
+  free(): void;
+}
+
+export type SearchSpace = "Active" | "Staged" | "StagedOnly" | "ActiveMinusStaged" | "Union";
+
+/**
+ * Search files with an options object.
+ * With `minimal: true`, results are bare hits (1-based line, byte length)
+ * and no excerpts are built.
+ */
+export function search_files_with_options(
+  search_term: string,
+  options: (SearchOptions & { minimal: true }),
+  handle?: SearchHandle | null,
+): Array<{ path: string; line: number; length: number; epoch: number }> & {
+  stats: SearchStats;
+};
+export function search_files_with_options(
+  search_term: string,
+  options?: SearchOptions | null,
+  handle?: SearchHandle | null,
+): Array<{
+  path: string;
+  lines: Array<{ lineNumber: number; content: string; isMatch: boolean }>;
+  lossy: boolean;
+  /** Characters cut from the first line; it then starts with `…` */
+  omittedPrefix: number;
+  /** Characters cut from the last line; it then ends with `…` */
+  omittedSuffix: number;
+  /** Edit epoch of the file; see `get_edit_epochs` */
+  epoch: number;
+  /** Where the match falls in the excerpt, one entry per line it covers */
+  matchColumns: MatchColumns[];
+}> & { stats: SearchStats };
+
+/**
+ * Search for any of several patterns in one pass over the files. Options
+ * apply as in `search_files_with_options`; `patternRef` is ignored. Each
+ * result carries `patternIndex`, the position in `patterns` of the pattern
+ * that matched; if several match at the same place the earliest wins.
+ * @throws {Error} If `patterns` is empty or any pattern is invalid
+ */
+export function search_files_multi(
+  patterns: string[],
+  options: (SearchOptions & { minimal: true }),
+  handle?: SearchHandle | null,
+): Array<{ path: string; line: number; length: number; epoch: number; patternIndex: number }> & {
+  stats: SearchStats;
+};
+export function search_files_multi(
+  patterns: string[],
+  options?: SearchOptions | null,
+  handle?: SearchHandle | null,
+): Array<
+  ReturnType<typeof search_files_with_options>[number] & { patternIndex: number }
+> & { stats: SearchStats };
+
+/**
+ * Fetch one page of search results, so large result sets can be loaded
+ * incrementally. Pass `cursor: null` to start a search with `options`;
+ * pass the returned `cursor` with the same `pattern` for the next page
+ * (`options` are then ignored). A search sees the index as it was when it
+ * started. Only the 16 most recently used cursors are kept.
+ * Results are hunks as from `search_files_with_options`, or bare hits with
+ * `minimal: true`. The last page may be empty.
+ * @throws {Error} If the cursor is unknown or expired, or belongs to a different pattern
+ */
+export function search_files_page(
+  pattern: string,
+  cursor: string | null,
+  page_size: number,
+  options?: SearchOptions | null,
+  handle?: SearchHandle | null,
+): {
+  /** `stats` count this page only */
+  results:
+    | ReturnType<typeof search_files_with_options>
+    | (Array<{ path: string; line: number; length: number; epoch: number }> & {
+        stats: SearchStats;
+      });
+  /** Token for the next page; null once done */
+  cursor: string | null;
+  done: boolean;
+};
+
+/**
+ * Release a search cursor that will not be read to the end.
+ * @returns Whether the cursor was open
+ */
+export function close_search_cursor(cursor: string): boolean;
+
+/** A line kept in a result set. */
+export interface ResultLine {
+  path: string;
+  /** 1-based */
+  line: number;
+  /** The line without its line ending */
+  text: string;
+}
+
+export interface ResultSetPage {
+  lines: ResultLine[];
+  /** 0-based */
+  page: number;
+  pageCount: number;
+  totalLines: number;
+  totalFiles: number;
+  /** Patterns applied by `refine_result_set`, in order */
+  refinements: string[];
+}
+
+/**
+ * Run a search to completion and keep the lines it matched, for narrowing
+ * with `refine_result_set` without rescanning the index. The set sees the
+ * index as it was when created. Only the 16 newest sets are kept.
+ * @param options - As for `search_files_with_options`
+ * @param handle - Optional handle to abort the search
+ * @returns The set id
+ */
+export function create_result_set(
+  pattern: string,
+  options?: SearchOptions | null,
+  handle?: SearchHandle | null,
+): string;
+
+/**
+ * Keep only the lines of a result set that also match `pattern`, using the
+ * original search's case and word options. An invalid pattern or an abort
+ * leaves the set unchanged.
+ * @returns Lines left in the set
+ * @throws {Error} If the set is unknown or expired
+ */
+export function refine_result_set(
+  id: string,
+  pattern: string,
+  handle?: SearchHandle | null,
+): number;
+
+/**
+ * One page of a result set; pages past the end are empty.
+ * @param page - 0-based page number
+ * @param page_size - Lines per page (default 100)
+ * @throws {Error} If the set is unknown or expired
+ */
+export function get_result_set(id: string, page: number, page_size?: number | null): ResultSetPage;
+
+/** Drop a result set. @returns Whether it was kept */
+export function close_result_set(id: string): boolean;
+
+/**
+ * Search only the files named in a newline-delimited UTF-8 path list
+ * (e.g. `new TextEncoder().encode(paths.join("\n"))`). Prefer this over
+ * long include globs for scopes of thousands of files.
+ */
+export function search_files_in_list(
+  search_term: string,
+  paths: Uint8Array,
+  options: (SearchOptions & { minimal: true }),
+  handle?: SearchHandle | null,
+): Array<{ path: string; line: number; length: number; epoch: number }> & {
+  stats: SearchStats;
+};
+export function search_files_in_list(
+  search_term: string,
+  paths: Uint8Array,
+  options?: SearchOptions | null,
+  handle?: SearchHandle | null,
 ): Array<{
   path: string;
-  previewStartLine: number;
-  previewEndLine: number;
-  matchedLineRanges: Array<{ start: number; end: number }>;
-  excerpt: string;
+  lines: Array<{ lineNumber: number; content: string; isMatch: boolean }>;
+  lossy: boolean;
+  /** Characters cut from the first line; it then starts with `…` */
+  omittedPrefix: number;
+  /** Characters cut from the last line; it then ends with `…` */
+  omittedSuffix: number;
+  /** Edit epoch of the file; see `get_edit_epochs` */
+  epoch: number;
+  /** Where the match falls in the excerpt, one entry per line it covers */
+  matchColumns: MatchColumns[];
+}> & { stats: SearchStats };
+
+/** One replacement planned or made by `replace_in_files`. */
+export interface ReplaceItem {
+  path: string;
+  /** Inclusive 1-based lines of the match before the edit */
+  originalRange: [number, number];
+  /** Inclusive 1-based lines of the replacement after the edit */
+  stagedRange: [number, number];
+  original: {
+    path: string;
+    lines: Array<{ lineNumber: number; content: string; isMatch: boolean }>;
+    lossy: boolean;
+    omittedPrefix: number;
+    omittedSuffix: number;
+    epoch: number;
+    matchColumns: MatchColumns[];
+  };
+  staged: {
+    path: string;
+    lines: Array<{ lineNumber: number; content: string; isMatch: boolean }>;
+    lossy: boolean;
+    omittedPrefix: number;
+    omittedSuffix: number;
+    epoch: number;
+    matchColumns: MatchColumns[];
+  };
+}
+
+/**
+ * Regex find-and-replace across staged files. `replacement` may use `$1`,
+ * `${name}` and `$$`. With `dryRun: true` the planned edits are returned
+ * and nothing is staged, so a UI can show them before the user confirms;
+ * otherwise all matching files are rewritten, or none if any fails.
+ * Binary files are skipped. `useStaged`, `space`, `limit`, `minimal` and
+ * the mtime filters are ignored; edits always target staging.
+ * @throws {Error} If staging is not active, the pattern is invalid, or a
+ * file is read-only
+ */
+export function replace_in_files(
+  pattern: string,
+  replacement: string,
+  options?: (SearchOptions & { dryRun?: boolean | null }) | null,
+  handle?: SearchHandle | null,
+): {
+  items: ReplaceItem[];
+  /** Files with at least one replacement */
+  filesChanged: number;
+  /** false for dry runs */
+  applied: boolean;
+};
+
+/**
+ * Plan a find-and-replace as `replace_in_files` would make it, without
+ * staging anything, so a UI can let the user pick hunks to keep. Only the
+ * 16 newest plans are kept.
+ * @returns The plan id
+ * @throws {Error} If staging is not active or the pattern is invalid
+ */
+export function create_edit_plan(
+  pattern: string,
+  replacement: string,
+  options?: SearchOptions | null,
+  handle?: SearchHandle | null,
+): string;
+
+/** A planned replacement, with its id within the plan. */
+export interface EditPlanHunk extends ReplaceItem {
+  id: number;
+}
+
+/**
+ * The hunks of an edit plan, grouped by file.
+ * @throws {Error} If the plan is unknown or expired
+ */
+export function get_edit_plan(plan_id: string): {
+  hunkCount: number;
+  files: Array<{ path: string; hunks: EditPlanHunk[] }>;
+};
+
+/**
+ * Stage the chosen hunks of an edit plan as one undo step, then discard
+ * the plan. Staged files must be unchanged since the plan was made.
+ * @returns The applied hunks, as from `replace_in_files`
+ * @throws {Error} With `code: "content_changed"` if a chosen file changed
+ *   (nothing is staged and the plan is kept), or `code: "unknown_hunk"`
+ */
+export function apply_edit_hunks(
+  plan_id: string,
+  hunk_ids: number[],
+): ReturnType<typeof replace_in_files>;
+
+/** Drop an edit plan without staging it. @returns Whether it was kept */
+export function discard_edit_plan(plan_id: string): boolean;
+
+/**
+ * Search files and stream each hunk to `onResult` as soon as it is found.
+ * Return `false` from the callback to stop the search early.
+ * @returns Object with the number of hunks emitted
+ */
+export function search_files_streaming(
+  search_term: string,
+  options: SearchOptions | null | undefined,
+  onResult: (hunk: {
+    path: string;
+    lines: Array<{ lineNumber: number; content: string; isMatch: boolean }>;
+    lossy: boolean;
+    /** Characters cut from the first line; it then starts with `…` */
+    omittedPrefix: number;
+    /** Characters cut from the last line; it then ends with `…` */
+    omittedSuffix: number;
+    epoch: number;
+    /** Where the match falls in the excerpt, one entry per line it covers */
+    matchColumns: MatchColumns[];
+  }) => boolean | void,
+  handle?: SearchHandle | null,
+): {
+  emitted: number;
+  /** Files with more matches than `maxMatchesPerFile` */
+  truncatedPaths: string[];
+  /** The search stopped at `maxTotalMatches` with matches left */
+  matchLimitReached: boolean;
+  /** Candidates not searched because they are loaded as metadata only */
+  skippedUnhydrated: number;
+};
+
+/**
+ * Run a search and export every match as a CSV or JSON document.
+ *
+ * Rows contain `path`, `line`, `column` (1-based byte column), `match` and
+ * capture groups (`capture_1..N` columns in CSV, a `captures` array in JSON,
+ * `null` for groups that did not participate).
+ * @param search_term - Regex pattern
+ * @param options - Search options; `limit` caps the number of rows
+ * @param format - `"csv"` or `"json"`
+ * @returns The rendered document
+ * @throws {Error} If the pattern or format is invalid
+ */
+export function export_search_report(
+  search_term: string,
+  options: SearchOptions | null | undefined,
+  format: "csv" | "json",
+  handle?: SearchHandle | null,
+): string;
+
+/** A capture group that took part in a match */
+export interface CaptureGroup {
+  /** Name from `(?P<name>...)` or `(?<name>...)` */
+  name: string | null;
+  text: string;
+  /** Byte offset of the group's start in the file */
+  start: number;
+  /** Byte offset just past the group's end */
+  end: number;
+}
+
+/** One match from `extract_matches` */
+export interface ExtractedMatch {
+  path: string;
+  /** 1-based line of the match start */
+  line: number;
+  /** 1-based byte column of the match start */
+  column: number;
+  text: string;
+  /** Byte offsets of the match in the file */
+  start: number;
+  end: number;
+  /** Groups `$1..$N`; `null` for groups that did not participate */
+  groups: Array<CaptureGroup | null>;
+  /** Text of each named group that participated */
+  named: Record<string, string>;
+}
+
+/**
+ * Every match of a regex with its capture groups, as structured data
+ * rather than excerpts.
+ * @param pattern - Regex pattern
+ * @param options - Search options; `limit` caps the number of matches
+ * @throws {Error} If the pattern is invalid or the search is aborted
+ */
+export function extract_matches(
+  pattern: string,
+  options?: SearchOptions | null,
+  handle?: SearchHandle | null,
+): ExtractedMatch[];
+
+/**
+ * Search a caller-provided string with the same engine as `search_files`.
+ * Only matching options apply (`caseSensitive`, `wholeWord`, `contextLines`,
+ * `limit`, `minimal`); hunks have an empty `path`.
+ */
+export function find_in_text(
+  content: string,
+  pattern: string,
+  options: (SearchOptions & { minimal: true }),
+): Array<{ line: number; length: number }>;
+export function find_in_text(
+  content: string,
+  pattern: string,
+  options?: SearchOptions | null,
+  handle?: SearchHandle | null,
+): Array<{
+  path: string;
+  lines: Array<{ lineNumber: number; content: string; isMatch: boolean }>;
+  lossy: boolean;
+  /** Characters cut from the first line; it then starts with `…` */
+  omittedPrefix: number;
+  /** Characters cut from the last line; it then ends with `…` */
+  omittedSuffix: number;
+  /** Edit epoch of the file; see `get_edit_epochs` */
+  epoch: number;
+  /** Where the match falls in the excerpt, one entry per line it covers */
+  matchColumns: MatchColumns[];
 }>;
 
+/**
+ * Suggest files related to each changed path: tests by naming convention
+ * (or the source file, for a test), files whose import-like lines name the
+ * module, and siblings in the same directory.
+ * @param paths - Changed file paths
+ * @param useStaged - Search the staged index (default true)
+ * @param perKind - Maximum suggestions of each kind per path (default 10)
+ */
+export function suggest_related_files(
+  paths: string[],
+  useStaged?: boolean,
+  perKind?: number,
+): Array<{
+  path: string;
+  related: Array<{ path: string; kind: "test" | "source" | "importer" | "sibling" }>;
+}>;
+
+/**
+ * What a copy or move does when its destination already exists: fail with
+ * `file_already_exists`, replace it, or write to the first free
+ * `name-N.ext` next to it.
+ */
+export type ConflictPolicy = "error" | "overwrite" | "rename";
+
 /**
  * Copy a file to a new location in the staged index.
  * @param src - Source file path
  * @param dst - Destination file path
- * @returns Object containing the destination path
- * @throws {Error} If source file not found or staging not active
+ * @param on_conflict - When `dst` exists (default `"error"`)
+ * @returns Where the file went, and whether an existing file was replaced
+ * @throws {Error} If source file not found, staging not active, or `dst`
+ *   exists under `"error"`
  */
-export function copy_file(src: string, dst: string): {
+export function copy_file(src: string, dst: string, on_conflict?: ConflictPolicy | null): {
   dst: string;
+  overwritten: boolean;
 };
 
+export interface BatchOperationResult {
+  /** Operations applied */
+  count: number;
+  /** Per-operation outcomes in request order; only with `continue_on_error` */
+  results?: Array<{
+    src: string;
+    dst: string;
+    ok: boolean;
+    /** `code` is a stable error name such as "file_not_found" */
+    error?: { code: string; message: string };
+  }>;
+  /**
+   * Applied operations whose destination already existed, in request
+   * order; `renamedTo` is set when the file went elsewhere instead of
+   * overwriting `dst`
+   */
+  conflicts?: Array<{ src: string; dst: string; renamedTo?: string }>;
+}
+
 /**
  * Copy multiple files in a batch operation.
  * @param operations - Array of copy operations with src and dst paths
+ * @param continue_on_error - Apply each operation independently and report
+ *   failures in `results` instead of rolling back the batch on the first error
+ * @param on_conflict - When a destination exists (default `"error"`)
  * @returns Object containing the count of files copied
- * @throws {Error} If any source file not found or staging not active
+ * @throws {Error} If staging is not active, or any operation fails without `continue_on_error`
  */
-export function copy_files(operations: Array<{ src: string; dst: string }>): {
-  count: number;
-};
+export function copy_files(
+  operations: Array<{ src: string; dst: string }>,
+  continue_on_error?: boolean | null,
+  on_conflict?: ConflictPolicy | null,
+): BatchOperationResult;
 
 /**
  * Move (rename) a file in the staged index.
  * @param src - Source file path
  * @param dst - Destination file path
- * @returns Object containing the destination path
- * @throws {Error} If source file not found or staging not active
+ * @param on_conflict - When `dst` exists (default `"error"`)
+ * @returns Where the file went, and whether an existing file was replaced
+ * @throws {Error} If source file not found, staging not active, or `dst`
+ *   exists under `"error"`
  */
-export function move_file(src: string, dst: string): {
+export function move_file(src: string, dst: string, on_conflict?: ConflictPolicy | null): {
   dst: string;
+  overwritten: boolean;
 };
 
 /**
  * Move multiple files in a batch operation.
  * @param operations - Array of move operations with src and dst paths
+ * @param continue_on_error - Apply each operation independently and report
+ *   failures in `results` instead of rolling back the batch on the first error
+ * @param on_conflict - When a destination exists (default `"error"`)
  * @returns Object containing the count of files moved
- * @throws {Error} If any source file not found or staging not active
+ * @throws {Error} If staging is not active, or any operation fails without `continue_on_error`
  */
-export function move_files(operations: Array<{ src: string; dst: string }>): {
-  count: number;
+export function move_files(
+  operations: Array<{ src: string; dst: string }>,
+  continue_on_error?: boolean | null,
+  on_conflict?: ConflictPolicy | null,
+): BatchOperationResult;
+
+/**
+ * Move every file under the directory `from` to `to` in one transaction.
+ *
+ * With `rewriteReferences`, import-like lines (`import`, `export ... from`,
+ * `require()`, `import()`, `#include`, Python `from`/`import`) across the
+ * index are updated. Relative paths are recomputed, including those in
+ * moved files that point outside the tree. Other paths are rewritten only
+ * when they start with `from`. Aliases and computed paths are not touched,
+ * and files that are not valid UTF-8 are skipped.
+ * @param dryRun - Stage nothing; return what would change
+ * @param onConflict - When a file's destination exists (default `"error"`)
+ * @throws {Error} If no file is under `from`, a destination exists under
+ *   `"error"`, one directory contains the other, or staging is not active
+ */
+export function move_tree(
+  from: string,
+  to: string,
+  rewriteReferences?: boolean | null,
+  dryRun?: boolean | null,
+  onConflict?: ConflictPolicy | null,
+): {
+  /** Where each file went */
+  moved: Array<{ src: string; dst: string }>;
+  /** Rewritten files at their new paths, with before/after of each line */
+  rewrites: Array<{
+    path: string;
+    edits: Array<{ line: number; before: string; after: string }>;
+  }>;
+  /** Files whose destination existed; `renamedTo` is set under `"rename"` */
+  conflicts?: Array<{ src: string; dst: string; renamedTo?: string }>;
 };
 
+/**
+ * Delete every staged file under the directory `path` in one transaction.
+ * `src/a` covers `src/a/x` but not `src/ab/x`.
+ * @throws {Error} If no file is under `path`, `path` is empty, or staging
+ *   is not active
+ */
+export function delete_directory(path: string): { deleted: string[] };
+
+/**
+ * Move every staged file under `from` to `to` in one transaction, without
+ * copying content. Same as `move_tree` with no reference rewriting.
+ * @param onConflict - When a file's destination exists (default `"error"`)
+ * @throws {Error} If no file is under `from`, a destination exists under
+ *   `"error"`, one directory contains the other, or staging is not active
+ */
+export function move_directory(
+  from: string,
+  to: string,
+  onConflict?: ConflictPolicy | null,
+): DirectoryOperationResult;
+
+/**
+ * Copy every staged file under `from` to `to` in one transaction.
+ * @param onConflict - When a file's destination exists (default `"error"`)
+ * @throws {Error} If no file is under `from`, a destination exists under
+ *   `"error"`, one directory contains the other, or staging is not active
+ */
+export function copy_directory(
+  from: string,
+  to: string,
+  onConflict?: ConflictPolicy | null,
+): DirectoryOperationResult;
+
+export interface DirectoryOperationResult {
+  /** Where each file went */
+  operations: Array<{ src: string; dst: string }>;
+  /** Files whose destination existed; `renamedTo` is set under `"rename"` */
+  conflicts?: Array<{ src: string; dst: string; renamedTo?: string }>;
+}
+
+/** An operation queued with `queue_operation`. Lines are 1-based and inclusive. */
+export type TransactionOperation =
+  | { op: 'create'; path: string; content?: string; allowOverwrite?: boolean }
+  | { op: 'delete'; path: string }
+  | { op: 'replace_lines'; path: string; replacements: Array<[number, number, string]> }
+  | { op: 'move'; src: string; dst: string; onConflict?: ConflictPolicy };
+
+/** Result of one committed operation, tagged like the operation itself. */
+export type TransactionResult =
+  | { op: 'create'; path: string; size: number; created: boolean }
+  | { op: 'delete'; path: string; existed: boolean }
+  | {
+      op: 'replace_lines';
+      path: string;
+      linesReplaced: number;
+      linesAdded: number;
+      totalLines: number;
+      originalLines: number;
+      lossy: boolean;
+    }
+  | {
+      op: 'move';
+      src: string;
+      /** Where the file went, which differs from the queued `dst` under `"rename"` */
+      dst: string;
+      /** Whether an existing file at `dst` was replaced */
+      overwritten: boolean;
+    };
+
+/**
+ * Thrown by `commit_transaction` when an operation fails. Staging is left
+ * as it was before the commit.
+ */
+export interface TransactionError extends Error {
+  code: 'transaction_failed';
+  /** Position of the failed operation in the queue. */
+  index: number;
+  op: TransactionOperation['op'];
+  /** Error code of the underlying failure, e.g. `file_not_found`. */
+  cause: string;
+}
+
+/**
+ * Open a transaction. Operations queued afterwards are applied together by
+ * `commit_transaction`.
+ * @throws {Error} If a transaction is already open
+ */
+export function begin_transaction(): void;
+
+/**
+ * Queue an operation on the open transaction. Staging is not touched until
+ * the commit.
+ * @returns The number of queued operations
+ * @throws {Error} If no transaction is open or the operation is malformed
+ */
+export function queue_operation(operation: TransactionOperation): number;
+
+/**
+ * Apply every queued operation in order, all or nothing, and close the
+ * transaction. A successful commit is one undo step.
+ * @returns One result per operation, in queue order
+ * @throws {TransactionError} If an operation fails
+ * @throws {Error} If no transaction is open or staging is not active
+ */
+export function commit_transaction(): TransactionResult[];
+
+/**
+ * Discard the open transaction without applying it.
+ * @returns Whether a transaction was open
+ */
+export function abort_transaction(): boolean;
+
+/**
+ * Run one tool request given as JSON, naming its tool in `tool` with the
+ * tool's request fields beside it, e.g. `{"tool":"find","find":"todo"}`.
+ * Tools: find, edit, read, create, delete, delete_dir, replace_lines,
+ * anchored_edit, validate_lines, delete_lines, insert_lines, append,
+ * copy_files, move_files, move_tree, move_dir, copy_dir, apply_patch,
+ * apply_scaffold, merge_file, modified_files, file_diff, unified_diff,
+ * all_unified_diffs.
+ * Paths are normalized as the index stores them before the tool runs.
+ * @param handle - Optional handle to abort finds and edits
+ * @returns The tool's response as JSON
+ * @throws {Error} With `code: "invalid_command"` if the request is malformed,
+ * `code: "invalid_path"` if a path is invalid, otherwise the tool's own
+ * error code
+ */
+export function execute_command(json: string, handle?: SearchHandle | null): string;
+
+/** A tool request as taken by `execute_command`, e.g. `{ tool: 'find', find: 'todo' }`. */
+export type BatchOperation = { tool: string } & Record<string, unknown>;
+
+export interface BatchOptions {
+  /**
+   * What a failed operation does: 'stop' skips the rest (default),
+   * 'continue' runs them anyway, 'rollback' skips the rest and undoes the
+   * ones applied
+   */
+  onError?: 'stop' | 'continue' | 'rollback' | null;
+}
+
+export interface BatchResult {
+  /** One item per operation run, in request order */
+  results: {
+    tool: string;
+    /** The tool's response, if it succeeded */
+    response?: unknown;
+    error?: { code: string; message: string };
+  }[];
+  /** Operations not run because an earlier one failed */
+  skipped: number;
+  /** Whether the applied operations were undone by `onError: 'rollback'` */
+  rolledBack: boolean;
+}
+
+/**
+ * Run tool requests in order as one undo step, with a result per
+ * operation. An abort always stops the batch.
+ * @param handle - Optional handle to abort finds and edits
+ * @throws {Error} If staging is not active or an operation is malformed,
+ *   or with `code: "invalid_path"`, before anything runs, if any path is
+ *   invalid
+ */
+export function run_batch(
+  operations: BatchOperation[],
+  options?: BatchOptions | null,
+  handle?: SearchHandle | null
+): BatchResult;
+
+/**
+ * Options for the semantic search bindings: the usual search filters
+ * select files, which are split into line-aligned chunks.
+ */
+export interface SemanticSearchOptions extends SearchOptions {
+  /** Lines per chunk (default 40) */
+  maxChunkLines?: number | null;
+  /** Lines shared by consecutive chunks, below maxChunkLines (default 5) */
+  overlap?: number | null;
+}
+
+export interface SemanticMatch {
+  /** Stable chunk id (16 hex digits); see `chunk_workspace` */
+  id: string;
+  path: string;
+  startLine: number;
+  /** Inclusive */
+  endLine: number;
+  /** Chunk content hash (16 hex digits); the embedding cache key */
+  hash: string;
+  /** Cosine similarity */
+  score: number;
+}
+
+/**
+ * Register the function used to embed text. It receives an array of
+ * strings and must synchronously return one vector per string, all of the
+ * same length. Async providers should use `pending_embeddings` and
+ * `put_embeddings` with `semantic_search_by_vector` instead.
+ */
+export function register_embedding_provider(
+  provider: (texts: string[]) => Array<number[] | Float32Array>,
+): void;
+
+/** Remove the embedding provider; cached vectors are kept. */
+export function clear_embedding_provider(): void;
+
+/** Drop all cached vectors, e.g. after switching embedding models. */
+export function clear_embedding_cache(): void;
+
+/**
+ * Rank chunks of the selected files by similarity to `query`, embedding
+ * uncached chunks through the provider first. Vectors are cached by chunk
+ * content hash, so unchanged text is embedded once.
+ * @param topK - Number of results (default 10)
+ * @throws {Error} If no provider is registered or it returns bad vectors
+ */
+export function semantic_search(
+  query: string,
+  topK?: number | null,
+  options?: SemanticSearchOptions | null,
+): SemanticMatch[];
+
+/**
+ * Rank chunks against a precomputed query vector without calling the
+ * provider. Chunks without cached vectors are skipped.
+ */
+export function semantic_search_by_vector(
+  query: Float32Array | number[],
+  topK?: number | null,
+  options?: SemanticSearchOptions | null,
+): SemanticMatch[];
+
+/**
+ * Chunks of the selected files with no cached vector, one per distinct hash.
+ */
+export function pending_embeddings(
+  options?: SemanticSearchOptions | null,
+): Array<{ hash: string; path: string; startLine: number; endLine: number; text: string }>;
+
+/**
+ * Cache vectors for chunk hashes returned by `pending_embeddings`.
+ * @returns Number of vectors stored
+ * @throws {Error} If lengths differ or a vector's dimension does not match the cache
+ */
+export function put_embeddings(
+  hashes: string[],
+  vectors: Array<number[] | Float32Array>,
+): number;
+
+export interface WorkspaceChunk {
+  /**
+   * Chunk id (16 hex digits), derived from path and chunk text only.
+   * Edits elsewhere in the workspace, or elsewhere in the file, leave it
+   * unchanged; chunk boundaries prefer blank lines so they realign after
+   * an edit.
+   */
+  id: string;
+  path: string;
+  startLine: number;
+  /** Inclusive */
+  endLine: number;
+  /** Chunk content hash (16 hex digits) */
+  hash: string;
+  /** Edit epoch of the file when chunked */
+  epoch: number;
+}
+
+/**
+ * Split the workspace into deterministic line-aligned chunks, without
+ * embedding. Diff ids between calls to update a retrieval index
+ * incrementally as staged edits land.
+ * @param maxChunkLines - Lines per chunk (default 40)
+ * @param overlap - Lines shared by consecutive chunks (default 5)
+ * @param globs - Only chunk files matching these globs
+ * @param useStaged - Chunk the staged view (default true)
+ */
+export function chunk_workspace(
+  maxChunkLines?: number | null,
+  overlap?: number | null,
+  globs?: string[] | null,
+  useStaged?: boolean | null,
+): WorkspaceChunk[];
+
+/**
+ * A file within a scaffold template. `path` and `content` may contain
+ * `{{param}}` placeholders.
+ */
+export interface ScaffoldFile {
+  path: string;
+  content: string;
+}
+
+/**
+ * Register a named scaffold template, replacing any existing one.
+ * @param name - Template name
+ * @param files - Files the template creates
+ * @returns true if an existing template was replaced
+ */
+export function register_scaffold(name: string, files: ScaffoldFile[]): boolean;
+
+/**
+ * Remove a scaffold template.
+ * @returns true if the template existed
+ */
+export function remove_scaffold(name: string): boolean;
+
+/**
+ * List registered scaffold templates with the placeholders they use.
+ */
+export function list_scaffolds(): Array<{
+  name: string;
+  files: ScaffoldFile[];
+  placeholders: string[];
+}>;
+
+/**
+ * Render a scaffold under `dstPrefix` and stage every file in one transaction.
+ * If any file fails (missing param, existing file), nothing is staged.
+ * @param name - Registered template name
+ * @param params - Placeholder values
+ * @param dstPrefix - Directory the template paths are created under
+ * @param allowOverwrite - Overwrite existing files (default false)
+ * @returns Paths of the created files, in template order
+ * @throws {Error} If the template is unknown, a param is missing, or staging not active
+ */
+export function apply_scaffold(
+  name: string,
+  params: Record<string, string> | null | undefined,
+  dstPrefix: string,
+  allowOverwrite?: boolean,
+): { created: string[] };
+
 /**
  * Validates whether a file can be edited with line-based operations.
  * @param path - File path to validate
@@ -417,6 +2669,42 @@ export function move_files(operations: Array<{ src: string; dst: string }>): {
  */
 export function validate_can_edit_lines(path: string): boolean;
 
+/** A line edit for `validate_line_operations`. Lines are 1-based and inclusive. */
+export type LineOperation =
+  | { op: 'replaceRange'; start: number; end: number; content: string }
+  | { op: 'deleteRange'; start: number; end: number }
+  | { op: 'insertBefore'; line: number; content: string }
+  | { op: 'insertAfter'; line: number; content: string };
+
+/** A problem with one operation passed to `validate_line_operations`. */
+export interface LineOpIssue {
+  /** Position of the operation in the list. */
+  index: number;
+  kind: 'outOfRange' | 'invertedRange' | 'overlap';
+  /** The earlier operation this one overlaps. */
+  other?: number;
+  message: string;
+}
+
+/**
+ * Check line operations against the staged file without applying them.
+ * Line edits skip lines that do not exist and apply overlapping ranges one
+ * after another; this reports both so the edit can be fixed first.
+ * @param path - File the operations target
+ * @param operations - Operations to check
+ * @returns The file's line count and every problem, in operation order
+ * @throws {Error} If the file does not exist or the operations are malformed
+ */
+export function validate_line_operations(
+  path: string,
+  operations: LineOperation[]
+): {
+  path: string;
+  lineCount: number;
+  valid: boolean;
+  issues: LineOpIssue[];
+};
+
 /**
  * Records that a file has been read, clearing its needs_read flag.
  * Should be called after successfully reading a file's content.